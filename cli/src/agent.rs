@@ -0,0 +1,729 @@
+//! `didkit agent` subcommands: headless wallet daemon.
+//!
+//! The agent picks up DIDComm messages from its mediator (see `didkit didcomm mediate`), answers
+//! credential offers ([Issue Credential 3.0]) and presentation requests ([Present Proof 3.0])
+//! according to its auto-accept policy, and stores received credentials in an encrypted
+//! [`Wallet`]. Offers and requests that the policy does not accept are kept pending, to be accepted
+//! or rejected over the control API. With the `store` feature and `--store`, received credentials
+//! are also indexed in a [`CredentialStore`], to be queried with the `queryCredentials` method.
+//!
+//! Connections are started by out-of-band invitations, created with the `createInvitation` method
+//! and accepted, from a URL or JSON, with `acceptInvitation`, which stores the connection record
+//! in the wallet.
+//!
+//! The control API listens on a Unix socket. Each request is a line of JSON,
+//! `{"method": ..., "params": {...}}`, answered by a line `{"result": ...}` or `{"error": ...}`.
+//!
+//! [Issue Credential 3.0]: https://didcomm.org/issue-credential/3.0/
+//! [Present Proof 3.0]: https://didcomm.org/present-proof/3.0/
+//! [`CredentialStore`]: didkit::store::CredentialStore
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use structopt::StructOpt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tokio::task::LocalSet;
+
+use didkit::didcomm::{
+    self, Attachment, MediationEvent, Message, ISSUE_CREDENTIAL, OFFER_CREDENTIAL, PRESENTATION,
+    PROBLEM_REPORT, REQUEST_CREDENTIAL, REQUEST_PRESENTATION,
+};
+use didkit::pex::{self, PresentationDefinition};
+use didkit::redact::{redact, RedactOptions};
+#[cfg(feature = "store")]
+use didkit::store::{CredentialQuery, CredentialStore, Direction};
+use didkit::wallet::{StoredCredential, Wallet};
+use didkit::{
+    get_verification_method, issue_presentation, runtime, verify_credential, CredentialOrJWT,
+    JWTOrLDPOptions, ProofOptionsBuilder, ProofPurpose, VerifiablePresentation, JWK,
+};
+
+use crate::didcomm::{invitation_connection, post, send, MediationFile};
+use crate::opts::read_jwk;
+use crate::opts::ResolverOptions;
+
+/// Attachment format of presentations sent in reply to presentation requests
+const SUBMISSION_FORMAT: &str = "dif/presentation-exchange/submission@v1.0";
+
+#[derive(StructOpt, Debug)]
+pub enum AgentCmd {
+    /// Run the agent
+    Run {
+        /// Filename of the mediation state, created by `didkit didcomm mediate request`
+        #[structopt(short, long, parse(from_os_str))]
+        state: PathBuf,
+        /// Filename of the agent's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Directory of the encrypted credential store
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: PathBuf,
+        /// Path of the Unix socket of the control API
+        #[structopt(long, parse(from_os_str))]
+        socket: PathBuf,
+        /// Filename of the auto-accept policy, in JSON. Default is to accept nothing.
+        #[structopt(short, long, parse(from_os_str))]
+        policy: Option<PathBuf>,
+        /// Interval between message pickups, in seconds
+        #[structopt(long, default_value = "5")]
+        interval: u64,
+        /// URL of a database to also index received credentials in, e.g. sqlite://agent.db?mode=rwc
+        #[cfg(feature = "store")]
+        #[structopt(long)]
+        store: Option<String>,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Call a method of the control API of a running agent, and output the result
+    Call {
+        /// Path of the Unix socket of the control API
+        #[structopt(long, parse(from_os_str))]
+        socket: PathBuf,
+        /// Method name, e.g. status, listCredentials, listPending, accept
+        method: String,
+        /// Parameters, as a JSON object
+        params: Option<String>,
+    },
+}
+
+/// DIDs of the parties to accept offers or requests from. `*` accepts any party.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoAccept {
+    #[serde(default)]
+    pub from: Vec<String>,
+}
+
+impl AutoAccept {
+    fn accepts(&self, did: &str) -> bool {
+        self.from.iter().any(|from| from == "*" || from == did)
+    }
+}
+
+/// Auto-accept policy of the agent.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Policy {
+    /// Request the credentials offered by these issuers
+    #[serde(default)]
+    pub accept_offers: AutoAccept,
+    /// Present credentials requested by these verifiers, if the wallet has matching ones
+    #[serde(default)]
+    pub accept_requests: AutoAccept,
+}
+
+/// Offer or request waiting to be accepted or rejected.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Pending {
+    id: String,
+    from: String,
+    message: Message,
+    received: DateTime<Utc>,
+}
+
+struct Agent {
+    key: JWK,
+    did: String,
+    mediation: Mutex<MediationFile>,
+    wallet: Wallet,
+    #[cfg(feature = "store")]
+    store: Option<CredentialStore>,
+    policy: Policy,
+    resolver_options: ResolverOptions,
+    pending: RefCell<Vec<Pending>>,
+    /// Threads of credential requests sent, waiting for credentials
+    requested: RefCell<Vec<String>>,
+}
+
+/// Select stored credentials satisfying the presentation definition of a presentation request
+/// (see [`didkit::pex`]), with the claims of SD-JWT credentials limited to those that the
+/// definition asks to disclose. `holder` are the DIDs of the agent, for `is_holder` constraints.
+/// Returns `None` if the definition is not satisfied, or if it has an input descriptor without
+/// fields, which would select any credential.
+fn select_credentials(
+    request: &Value,
+    credentials: &[StoredCredential],
+    holder: &[String],
+) -> Option<Vec<StoredCredential>> {
+    let definition = request.get("presentation_definition")?;
+    let definition: PresentationDefinition = serde_json::from_value(definition.clone()).ok()?;
+    if definition.input_descriptors.is_empty()
+        || definition
+            .input_descriptors
+            .iter()
+            .any(|descriptor| descriptor.constraints.fields.is_empty())
+    {
+        return None;
+    }
+    let values: Vec<Value> = credentials
+        .iter()
+        .map(|stored| stored.credential.clone())
+        .collect();
+    let selection = pex::select(&definition, &values, holder).ok()?;
+    selection
+        .credentials
+        .into_iter()
+        .map(|selected| {
+            let mut stored = credentials[selected.credential].clone();
+            if let (Some(reveal), Value::String(sd_jwt)) = (selected.disclose, &stored.credential) {
+                let options = RedactOptions {
+                    reveal,
+                    ..Default::default()
+                };
+                let redacted = redact(sd_jwt, &options).ok()?;
+                stored.credential = Value::String(redacted);
+            }
+            Some(stored)
+        })
+        .collect()
+}
+
+fn request_attachment(message: &Message) -> Value {
+    message
+        .attachments
+        .first()
+        .and_then(|attachment| attachment.to_json().ok())
+        .unwrap_or_default()
+}
+
+impl Agent {
+    fn reply(&self, type_: &str, to: &Message, body: Value) -> Message {
+        let mut message = Message::new(type_, body);
+        message.from = Some(self.did.clone());
+        message.to = to.from.iter().cloned().collect();
+        message.thid = Some(to.thread_id().to_string());
+        message
+    }
+
+    /// Send a message to its recipient's DIDComm endpoint.
+    async fn deliver(&self, message: &Message) -> Result<(), String> {
+        let resolver = self.resolver_options.to_resolver();
+        let to = message.to.first().ok_or("Message has no recipient")?;
+        let (endpoint, envelope) = didcomm::pack_for_delivery(message, to, &resolver)
+            .await
+            .map_err(|e| e.to_string())?;
+        post(&endpoint, envelope, &self.key).await?;
+        Ok(())
+    }
+
+    async fn request_credential(&self, offer: &Message) -> Result<(), String> {
+        let request = self.reply(REQUEST_CREDENTIAL, offer, json!({}));
+        self.deliver(&request).await?;
+        self.requested
+            .borrow_mut()
+            .push(offer.thread_id().to_string());
+        Ok(())
+    }
+
+    async fn present(
+        &self,
+        request: &Message,
+        credentials: Vec<StoredCredential>,
+    ) -> Result<(), String> {
+        let resolver = self.resolver_options.to_resolver();
+        let attachment = request_attachment(request);
+        let presentation: VerifiablePresentation = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiablePresentation"],
+            "holder": self.did,
+            "verifiableCredential": credentials
+                .into_iter()
+                .map(|stored| stored.credential)
+                .collect::<Vec<Value>>(),
+        }))
+        .map_err(|e| e.to_string())?;
+        let verification_method = get_verification_method(&self.did, &resolver)
+            .await
+            .ok_or("Unable to find verification method")?;
+        let mut options = ProofOptionsBuilder::new()
+            .verification_method(verification_method)
+            .proof_purpose(ProofPurpose::Authentication);
+        if let Some(challenge) = attachment
+            .pointer("/options/challenge")
+            .and_then(Value::as_str)
+        {
+            options = options.challenge(challenge);
+        }
+        if let Some(domain) = attachment
+            .pointer("/options/domain")
+            .and_then(Value::as_str)
+        {
+            options = options.domain(domain);
+        }
+        let options = options.build().map_err(|e| e.to_string())?;
+        let presentation = issue_presentation(presentation, &options, &self.key, &resolver)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut message = self.reply(PRESENTATION, request, json!({}));
+        message.attachments = vec![Attachment::json(
+            None,
+            Some(SUBMISSION_FORMAT.to_string()),
+            serde_json::to_value(presentation).map_err(|e| e.to_string())?,
+        )];
+        self.deliver(&message).await
+    }
+
+    async fn store_credentials(&self, message: &Message) -> Result<(), String> {
+        let requested = self
+            .requested
+            .borrow()
+            .iter()
+            .any(|thid| thid == message.thread_id());
+        let from = message.from.clone().unwrap_or_default();
+        if !requested && !self.policy.accept_offers.accepts(&from) {
+            return Err("Unsolicited credential".to_string());
+        }
+        let resolver = self.resolver_options.to_resolver();
+        for attachment in &message.attachments {
+            let credential = attachment.to_json().map_err(|e| e.to_string())?;
+            let credential_or_jwt: CredentialOrJWT =
+                serde_json::from_value(credential.clone()).map_err(|e| e.to_string())?;
+            let report =
+                verify_credential(&credential_or_jwt, &JWTOrLDPOptions::default(), &resolver)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            if !report.is_success() {
+                return Err(format!("Invalid credential: {:?}", report.errors));
+            }
+            #[cfg(feature = "store")]
+            if let Some(ref store) = self.store {
+                store
+                    .insert(&credential, Direction::Received)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            let id = self
+                .wallet
+                .insert_credential(credential, Some(from.clone()))
+                .map_err(|e| e.to_string())?;
+            eprintln!("Stored credential {} from {}", id, from);
+        }
+        self.requested
+            .borrow_mut()
+            .retain(|thid| thid != message.thread_id());
+        Ok(())
+    }
+
+    fn add_pending(&self, message: &Message, from: &str) {
+        eprintln!("Pending {} from {}: {}", message.type_, from, message.id);
+        self.pending.borrow_mut().push(Pending {
+            id: message.id.clone(),
+            from: from.to_string(),
+            message: message.clone(),
+            received: Utc::now(),
+        });
+    }
+
+    async fn handle(&self, message: Message) -> Result<(), String> {
+        let from = match message.from {
+            Some(ref from) => from.clone(),
+            None => return Err(format!("Anonymous message: {}", message.type_)),
+        };
+        self.wallet
+            .touch_connection(&from)
+            .map_err(|e| e.to_string())?;
+        match &message.type_[..] {
+            OFFER_CREDENTIAL if self.policy.accept_offers.accepts(&from) => {
+                self.request_credential(&message).await
+            }
+            REQUEST_PRESENTATION if self.policy.accept_requests.accepts(&from) => {
+                let credentials = self.wallet.credentials().map_err(|e| e.to_string())?;
+                match select_credentials(
+                    &request_attachment(&message),
+                    &credentials,
+                    &[self.did.clone()],
+                ) {
+                    Some(selected) => self.present(&message, selected).await,
+                    None => {
+                        self.add_pending(&message, &from);
+                        Ok(())
+                    }
+                }
+            }
+            OFFER_CREDENTIAL | REQUEST_PRESENTATION => {
+                self.add_pending(&message, &from);
+                Ok(())
+            }
+            ISSUE_CREDENTIAL => self.store_credentials(&message).await,
+            PROBLEM_REPORT => {
+                eprintln!("Problem report from {}: {}", from, message.body);
+                Ok(())
+            }
+            type_ => Err(format!("Unsupported message type: {}", type_)),
+        }
+    }
+
+    /// Pick up and handle queued messages.
+    async fn pickup(&self) -> Result<(), String> {
+        let resolver = self.resolver_options.to_resolver();
+        let mut mediation = self.mediation.lock().await;
+        let request = mediation
+            .client
+            .delivery_request(10)
+            .map_err(|e| e.to_string())?;
+        let reply = match send(&request, &mediation, &self.key, &resolver).await? {
+            Some(reply) => reply,
+            None => return Ok(()),
+        };
+        let delivered = match mediation.client.handle(&reply).map_err(|e| e.to_string())? {
+            MediationEvent::Delivered(delivered) => delivered,
+            _ => return Ok(()),
+        };
+        if delivered.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<String> = delivered.iter().map(|message| message.id.clone()).collect();
+        let received = mediation
+            .client
+            .messages_received(&ids)
+            .map_err(|e| e.to_string())?;
+        send(&received, &mediation, &self.key, &resolver).await?;
+        drop(mediation);
+        for delivered in delivered {
+            let result = match didcomm::unpack(&delivered.envelope, &self.key) {
+                Ok(message) => self.handle(message).await,
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                eprintln!("Message {}: {}", delivered.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn take_pending(&self, id: &str) -> Result<Pending, String> {
+        let mut pending = self.pending.borrow_mut();
+        let index = pending
+            .iter()
+            .position(|pending| pending.id == id)
+            .ok_or_else(|| format!("No pending message: {}", id))?;
+        Ok(pending.remove(index))
+    }
+
+    async fn accept(&self, params: &Value) -> Result<Value, String> {
+        let id = params
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or("Missing id")?;
+        let pending = self.take_pending(id)?;
+        let result = match &pending.message.type_[..] {
+            OFFER_CREDENTIAL => self.request_credential(&pending.message).await,
+            _ => {
+                let credentials = match params.get("credentials").and_then(Value::as_array) {
+                    Some(ids) => ids
+                        .iter()
+                        .map(|id| {
+                            let id = id.as_str().ok_or("Invalid credential id")?;
+                            self.wallet
+                                .credential(id)
+                                .map_err(|e| e.to_string())?
+                                .ok_or_else(|| format!("No credential: {}", id))
+                        })
+                        .collect::<Result<Vec<_>, String>>(),
+                    None => {
+                        let credentials = self.wallet.credentials().map_err(|e| e.to_string())?;
+                        select_credentials(
+                            &request_attachment(&pending.message),
+                            &credentials,
+                            &[self.did.clone()],
+                        )
+                        .ok_or_else(|| "No matching credentials".to_string())
+                    }
+                };
+                match credentials {
+                    Ok(credentials) => self.present(&pending.message, credentials).await,
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        if result.is_err() {
+            // Keep the offer or request, to be retried.
+            self.pending.borrow_mut().push(pending);
+        }
+        result.map(|()| json!(true))
+    }
+
+    /// Accept an out-of-band invitation, given as a URL or a JSON object, and store the
+    /// connection with the inviter.
+    async fn accept_invitation(&self, params: &Value) -> Result<Value, String> {
+        let invitation = match params.get("invitation") {
+            Some(Value::String(url)) => url.clone(),
+            Some(invitation @ Value::Object(_)) => invitation.to_string(),
+            _ => return Err("Missing invitation".to_string()),
+        };
+        let resolver = self.resolver_options.to_resolver();
+        let inviter = didcomm::accept_invitation(&invitation, &resolver)
+            .await
+            .map_err(|e| e.to_string())?;
+        let connection = invitation_connection(inviter, self.did.clone());
+        self.wallet
+            .insert_connection(&connection)
+            .map_err(|e| e.to_string())?;
+        serde_json::to_value(connection).map_err(|e| e.to_string())
+    }
+
+    async fn call(&self, method: &str, params: &Value) -> Result<Value, String> {
+        let id = || {
+            params
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| "Missing id".to_string())
+        };
+        match method {
+            "status" => {
+                let mediation = self.mediation.lock().await;
+                Ok(json!({
+                    "did": self.did,
+                    "mediator": mediation.client.mediator,
+                    "routingDid": mediation.client.routing_did(),
+                    "pending": self.pending.borrow().len(),
+                }))
+            }
+            "listCredentials" => to_result(self.wallet.credentials()),
+            "getCredential" => to_result(self.wallet.credential(id()?)),
+            "removeCredential" => to_result(self.wallet.remove_credential(id()?)),
+            "listConnections" => to_result(self.wallet.connections()),
+            "createInvitation" => {
+                let param = |name: &str| params.get(name).and_then(Value::as_str);
+                let invitation = didcomm::invitation(
+                    &self.did,
+                    param("label"),
+                    param("goalCode"),
+                    param("goal"),
+                );
+                let url = match param("baseUrl") {
+                    Some(base_url) => Some(
+                        didcomm::invitation_url(&invitation, base_url)
+                            .map_err(|e| e.to_string())?,
+                    ),
+                    None => None,
+                };
+                Ok(json!({ "invitation": invitation, "url": url }))
+            }
+            "acceptInvitation" => self.accept_invitation(params).await,
+            #[cfg(feature = "store")]
+            "queryCredentials" => {
+                let store = self.store.as_ref().ok_or("No credential store")?;
+                let query: CredentialQuery =
+                    serde_json::from_value(params.clone()).map_err(|e| e.to_string())?;
+                to_result(store.query(&query).await)
+            }
+            "listPending" => {
+                serde_json::to_value(&*self.pending.borrow()).map_err(|e| e.to_string())
+            }
+            "accept" => self.accept(params).await,
+            "reject" => {
+                self.take_pending(id()?)?;
+                Ok(json!(true))
+            }
+            method => Err(format!("Unknown method: {}", method)),
+        }
+    }
+
+    async fn serve_connection(&self, stream: UnixStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = AsyncBufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            let result = match serde_json::from_str::<Value>(&line) {
+                Ok(request) => {
+                    let method = request.get("method").and_then(Value::as_str);
+                    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+                    match method {
+                        Some(method) => self.call(method, &params).await,
+                        None => Err("Missing method".to_string()),
+                    }
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            let response = match result {
+                Ok(result) => json!({ "result": result }),
+                Err(error) => json!({ "error": error }),
+            };
+            writer
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn to_result<T: Serialize, E: std::fmt::Display>(value: Result<T, E>) -> Result<Value, String> {
+    serde_json::to_value(value.map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+}
+
+fn read_policy(path: &Path) -> Policy {
+    let file = File::open(path).unwrap();
+    serde_json::from_reader(BufReader::new(file)).unwrap()
+}
+
+impl AgentCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            AgentCmd::Run {
+                state,
+                key_path,
+                wallet,
+                socket,
+                policy,
+                interval,
+                #[cfg(feature = "store")]
+                store,
+                resolver_options,
+            } => {
+                let key = read_jwk(&key_path);
+                let mediation = MediationFile::load(&state);
+                if mediation.client.routing_did().is_none() {
+                    eprintln!("Mediation has not been granted");
+                    std::process::exit(1);
+                }
+                let agent = Rc::new(Agent {
+                    did: mediation.client.did.clone(),
+                    mediation: Mutex::new(mediation),
+                    wallet: Wallet::open(&wallet, key.clone()).unwrap(),
+                    #[cfg(feature = "store")]
+                    store: store.map(|url| rt.block_on(CredentialStore::connect(&url)).unwrap()),
+                    key,
+                    policy: policy.as_deref().map(read_policy).unwrap_or_default(),
+                    resolver_options,
+                    pending: RefCell::new(Vec::new()),
+                    requested: RefCell::new(Vec::new()),
+                });
+                // Remove the socket of a previous run.
+                let _ = std::fs::remove_file(&socket);
+                let local = LocalSet::new();
+                local.block_on(&rt, async move {
+                    let listener = UnixListener::bind(&socket).unwrap();
+                    eprintln!("Agent {} listening on {}", agent.did, socket.display());
+                    let pickup_agent = agent.clone();
+                    tokio::task::spawn_local(async move {
+                        loop {
+                            if let Err(e) = pickup_agent.pickup().await {
+                                eprintln!("Pickup: {}", e);
+                            }
+                            tokio::time::sleep(Duration::from_secs(interval)).await;
+                        }
+                    });
+                    loop {
+                        let (stream, _) = listener.accept().await.unwrap();
+                        let agent = agent.clone();
+                        tokio::task::spawn_local(async move {
+                            if let Err(e) = agent.serve_connection(stream).await {
+                                eprintln!("Control API: {}", e);
+                            }
+                        });
+                    }
+                });
+            }
+            AgentCmd::Call {
+                socket,
+                method,
+                params,
+            } => {
+                let params: Value = match params {
+                    Some(params) => serde_json::from_str(&params).unwrap(),
+                    None => json!({}),
+                };
+                let response = rt.block_on(async {
+                    let stream = UnixStream::connect(&socket).await.unwrap();
+                    let (reader, mut writer) = stream.into_split();
+                    let request = json!({ "method": method, "params": params });
+                    writer
+                        .write_all(format!("{}\n", request).as_bytes())
+                        .await
+                        .unwrap();
+                    let mut lines = AsyncBufReader::new(reader).lines();
+                    lines.next_line().await.unwrap().unwrap_or_default()
+                });
+                let response: Value = serde_json::from_str(&response).unwrap();
+                if let Some(error) = response.get("error") {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+                println!("{}", response["result"]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_by_type() {
+        let stored = |types: Value| StoredCredential {
+            id: "1".to_string(),
+            credential: json!({ "type": types }),
+            received_from: None,
+            received: Utc::now(),
+        };
+        let credentials = vec![
+            stored(json!(["VerifiableCredential"])),
+            stored(json!([
+                "VerifiableCredential",
+                "UniversityDegreeCredential"
+            ])),
+        ];
+        let request = json!({
+            "presentation_definition": {
+                "input_descriptors": [{
+                    "id": "degree",
+                    "constraints": {
+                        "fields": [{
+                            "path": ["$.type"],
+                            "filter": { "type": "array", "contains": { "const": "UniversityDegreeCredential" } }
+                        }]
+                    }
+                }]
+            }
+        });
+        let selected = select_credentials(&request, &credentials, &[]).unwrap();
+        assert_eq!(selected, vec![credentials[1].clone()]);
+        assert!(select_credentials(&json!({}), &credentials, &[]).is_none());
+    }
+
+    #[test]
+    fn select_by_subject_field() {
+        let credentials = vec![StoredCredential {
+            id: "1".to_string(),
+            credential: json!({
+                "type": ["VerifiableCredential", "CohortCredential"],
+                "credentialSubject": [
+                    { "id": "did:example:alice", "cohort": 2020 },
+                    { "id": "did:example:bob", "cohort": 2021 }
+                ]
+            }),
+            received_from: None,
+            received: Utc::now(),
+        }];
+        let request = |cohort: i64| {
+            json!({
+                "presentation_definition": {
+                    "input_descriptors": [{
+                        "id": "cohort",
+                        "constraints": {
+                            "fields": [{
+                                "path": ["$.credentialSubject.cohort", "$.vc.credentialSubject.cohort"],
+                                "filter": { "type": "integer", "const": cohort }
+                            }]
+                        }
+                    }]
+                }
+            })
+        };
+        assert!(select_credentials(&request(2021), &credentials, &[]).is_some());
+        assert!(select_credentials(&request(2022), &credentials, &[]).is_none());
+    }
+}
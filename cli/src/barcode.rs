@@ -0,0 +1,56 @@
+//! Barcode images for `didkit vc-to-barcode` and `didkit barcode-to-vc`.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use rxing::{BarcodeFormat as RXingFormat, MultiFormatWriter, Writer};
+
+use didkit::barcode::BarcodeFormat;
+
+/// Module size, in SVG user units.
+const MODULE_SIZE: u32 = 2;
+/// Quiet zone, in modules.
+const QUIET_ZONE: u32 = 2;
+
+fn rxing_format(format: BarcodeFormat) -> RXingFormat {
+    match format {
+        BarcodeFormat::PDF417 => RXingFormat::PDF_417,
+        BarcodeFormat::Aztec => RXingFormat::AZTEC,
+    }
+}
+
+/// Render barcode text as an SVG image of a barcode.
+pub fn render_svg(text: &str, format: BarcodeFormat) -> Result<String, String> {
+    let matrix = MultiFormatWriter::default()
+        .encode(text, &rxing_format(format), 0, 0)
+        .map_err(|err| err.to_string())?;
+    let (width, height) = (matrix.getWidth(), matrix.getHeight());
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" shape-rendering="crispEdges"><rect width="100%" height="100%" fill="#fff"/><path fill="#000" d=""##,
+        (width + 2 * QUIET_ZONE) * MODULE_SIZE,
+        (height + 2 * QUIET_ZONE) * MODULE_SIZE,
+    );
+    for y in 0..height {
+        for x in (0..width).filter(|x| matrix.get(*x, y)) {
+            let _ = write!(
+                svg,
+                "M{},{}h{}v{}h-{}z",
+                (x + QUIET_ZONE) * MODULE_SIZE,
+                (y + QUIET_ZONE) * MODULE_SIZE,
+                MODULE_SIZE,
+                MODULE_SIZE,
+                MODULE_SIZE
+            );
+        }
+    }
+    svg.push_str(r#""/></svg>"#);
+    Ok(svg)
+}
+
+/// Read the text of a barcode in an image file.
+pub fn scan(path: &Path, format: BarcodeFormat) -> Result<String, String> {
+    let path = path.to_str().ok_or("Invalid image path")?;
+    let result = rxing::helpers::detect_in_file(path, Some(rxing_format(format)))
+        .map_err(|err| err.to_string())?;
+    Ok(result.getText().to_string())
+}
@@ -0,0 +1,480 @@
+//! `didkit did-publish` subcommands: publish a DID document where its DID method resolves it.
+//!
+//! For `did:web`, the `did.json` document is uploaded under a web root given as a target URL:
+//!
+//! - `sftp://[user@]host[:port]/path`, with the `sftp` command, in batch mode, using the SSH key
+//!   in `DID_PUBLISH_SSH_KEY` if set, or the SSH agent and configuration;
+//! - `s3://bucket[/prefix]`, with a PUT request signed with the AWS credentials of the
+//!   `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN` and `AWS_REGION`
+//!   environment variables, to `AWS_ENDPOINT_URL` (path-style) if set, e.g. for S3-compatible
+//!   storage;
+//! - `https://...`, with an HTTP PUT request, authenticated with the bearer token in
+//!   `DID_PUBLISH_TOKEN`, or the basic credentials in `DID_PUBLISH_USERNAME` and
+//!   `DID_PUBLISH_PASSWORD`.
+//!
+//! The document is then resolved, until it resolves to the published document or the attempts
+//! run out, e.g. while a CDN cache expires.
+
+use std::io::{stdin, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use structopt::StructOpt;
+
+use didkit::did_lint;
+use didkit::hex;
+use didkit::{runtime, DIDResolver, ResolutionInputMetadata, JWK};
+
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+/// Media type of uploaded DID documents, accepted by `did:web` resolvers
+const CONTENT_TYPE: &str = "application/json";
+
+#[derive(StructOpt, Debug)]
+pub enum DIDPublishCmd {
+    /// Upload the did.json document of a did:web DID, read from stdin or generated from a key,
+    /// and check that the DID resolves to it
+    Web {
+        /// The did:web DID
+        did: String,
+        /// Web root to upload to: sftp://[user@]host[:port]/path, s3://bucket[/prefix] or an
+        /// HTTP(S) URL for PUT requests
+        #[structopt(short, long)]
+        target: String,
+        /// Filename of a JWK whose public key to publish, instead of reading a DID document
+        /// from stdin
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: Option<PathBuf>,
+        /// Bearer token for HTTP PUT requests
+        #[structopt(env = "DID_PUBLISH_TOKEN", long, hide_env_values = true)]
+        token: Option<String>,
+        /// Number of times to try resolving the DID after uploading. 0 skips the check.
+        #[structopt(long, default_value = "5")]
+        verify_attempts: u32,
+        /// Seconds to wait between resolution attempts
+        #[structopt(long, default_value = "2")]
+        verify_interval: u64,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+/// Outcome of publishing a DID document.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Publication {
+    pub did: String,
+    /// URL that the DID method resolves the document from
+    pub url: String,
+    /// Where the document was uploaded
+    pub uploaded_to: String,
+    /// Whether the DID resolved to the published document, unless not checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+}
+
+/// Path of the DID document of a `did:web` DID, relative to the web root of its host, and the
+/// host.
+pub fn did_web_path(did: &str) -> Option<(String, String)> {
+    let id = did.strip_prefix("did:web:")?;
+    let mut parts = id.split(':');
+    let host = parts.next().filter(|host| !host.is_empty())?;
+    let host = host.replace("%3A", ":").replace("%3a", ":");
+    let path: Vec<&str> = parts.collect();
+    if path.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    let path = if path.is_empty() {
+        ".well-known/did.json".to_string()
+    } else {
+        format!("{}/did.json", path.join("/"))
+    };
+    Some((host, path))
+}
+
+/// Minimal DID document for a key: a `JsonWebKey2020` verification method, for authentication
+/// and assertions.
+pub fn key_document(did: &str, key: &JWK) -> Value {
+    let vm = format!("{}#key-1", did);
+    json!({
+        "@context": [
+            "https://www.w3.org/ns/did/v1",
+            "https://w3id.org/security/suites/jws-2020/v1"
+        ],
+        "id": did,
+        "verificationMethod": [{
+            "id": vm,
+            "type": "JsonWebKey2020",
+            "controller": did,
+            "publicKeyJwk": key.to_public(),
+        }],
+        "authentication": [vm],
+        "assertionMethod": [vm],
+    })
+}
+
+fn join(base: &str, path: &str) -> String {
+    let base = base.trim_end_matches('/');
+    if base.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// URI-encode an S3 object key, keeping its `/` separators.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn env(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("Missing environment variable {}", name))
+}
+
+/// Headers of an S3 `PutObject` request, signed with [AWS Signature Version 4][sigv4].
+///
+/// [sigv4]: https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+fn sign_s3_put(
+    host: &str,
+    path: &str,
+    body: &[u8],
+    region: &str,
+    access_key: &str,
+    secret_key: &str,
+    session_token: Option<&str>,
+) -> Vec<(String, String)> {
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(&Sha256::digest(body));
+    let mut headers = vec![
+        ("content-type".to_string(), CONTENT_TYPE.to_string()),
+        ("host".to_string(), host.to_string()),
+        ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = session_token {
+        headers.push(("x-amz-security-token".to_string(), token.to_string()));
+    }
+    let signed_headers: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+    let signed_headers = signed_headers.join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "PUT\n{}\n\n{}\n{}\n{}",
+        path, canonical_headers, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), &date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, "s3");
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature = hex::encode(&hmac_sha256(&key, &string_to_sign));
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key, scope, signed_headers, signature
+        ),
+    ));
+    // The host header is set by the HTTP client.
+    headers.retain(|(name, _)| name != "host");
+    headers
+}
+
+async fn upload_s3(target: &Url, path: &str, body: Vec<u8>) -> Result<String, String> {
+    let bucket = target.host_str().ok_or("Missing S3 bucket")?;
+    let key = join(target.path().trim_start_matches('/'), path);
+    let region = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+    let url = match std::env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => format!(
+            "{}/{}/{}",
+            endpoint.trim_end_matches('/'),
+            bucket,
+            encode_key(&key)
+        ),
+        Err(_) => format!(
+            "https://{}.s3.{}.amazonaws.com/{}",
+            bucket,
+            region,
+            encode_key(&key)
+        ),
+    };
+    let parsed = Url::parse(&url).map_err(|e| e.to_string())?;
+    let host = match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(format!("Invalid S3 endpoint: {}", url)),
+    };
+    let headers = sign_s3_put(
+        &host,
+        parsed.path(),
+        &body,
+        &region,
+        &env("AWS_ACCESS_KEY_ID")?,
+        &env("AWS_SECRET_ACCESS_KEY")?,
+        std::env::var("AWS_SESSION_TOKEN").ok().as_deref(),
+    );
+    let mut request = reqwest::Client::new().put(parsed).body(body);
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("S3 upload failed: {} {}", status, text));
+    }
+    Ok(format!("s3://{}/{}", bucket, key))
+}
+
+async fn upload_http(
+    target: &Url,
+    path: &str,
+    body: Vec<u8>,
+    token: Option<&str>,
+) -> Result<String, String> {
+    let url = join(target.as_str(), path);
+    let mut request = reqwest::Client::new()
+        .put(&url)
+        .header("content-type", CONTENT_TYPE)
+        .body(body);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    } else if let Ok(username) = std::env::var("DID_PUBLISH_USERNAME") {
+        request = request.basic_auth(username, std::env::var("DID_PUBLISH_PASSWORD").ok());
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP PUT {} failed: {}", url, response.status()));
+    }
+    Ok(url)
+}
+
+fn upload_sftp(target: &Url, path: &str, body: &[u8]) -> Result<String, String> {
+    let host = target.host_str().ok_or("Missing SFTP host")?;
+    let destination = match target.username() {
+        "" => host.to_string(),
+        user => format!("{}@{}", user, host),
+    };
+    let remote = join(target.path(), path);
+    let local = std::env::temp_dir().join(format!("didkit-did-{}.json", std::process::id()));
+    std::fs::write(&local, body).map_err(|e| e.to_string())?;
+
+    // Create the parent directories, ignoring errors for those that exist.
+    let mut batch = String::new();
+    let parents: Vec<&str> = remote.rsplitn(2, '/').skip(1).collect();
+    if let Some(parent) = parents.first() {
+        let absolute = remote.starts_with('/');
+        let mut dir = String::new();
+        for part in parent.split('/').filter(|part| !part.is_empty()) {
+            if absolute || !dir.is_empty() {
+                dir.push('/');
+            }
+            dir.push_str(part);
+            if !target.path().starts_with(&dir) {
+                batch.push_str(&format!("-mkdir \"{}\"\n", dir));
+            }
+        }
+    }
+    batch.push_str(&format!("put \"{}\" \"{}\"\n", local.display(), remote));
+
+    let mut command = Command::new("sftp");
+    command.arg("-b").arg("-");
+    if let Some(port) = target.port() {
+        command.arg("-P").arg(port.to_string());
+    }
+    if let Ok(key) = std::env::var("DID_PUBLISH_SSH_KEY") {
+        command.arg("-i").arg(key);
+    }
+    let result = command
+        .arg(&destination)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut child_stdin) = child.stdin.take() {
+                child_stdin.write_all(batch.as_bytes())?;
+            }
+            child.wait()
+        });
+    std::fs::remove_file(&local).ok();
+    match result {
+        Ok(status) if status.success() => Ok(format!("sftp://{}{}", destination, remote)),
+        Ok(status) => Err(format!("sftp failed: {}", status)),
+        Err(err) => Err(format!("Unable to run sftp: {}", err)),
+    }
+}
+
+/// Resolve a DID until it resolves to the expected document, or the attempts run out.
+async fn check_resolution(
+    did: &str,
+    expected: &Value,
+    resolver: &dyn DIDResolver,
+    attempts: u32,
+    interval: Duration,
+) -> bool {
+    let input_metadata = ResolutionInputMetadata::default();
+    for attempt in 0..attempts {
+        if attempt > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        let (res_meta, representation, _) =
+            resolver.resolve_representation(did, &input_metadata).await;
+        if res_meta.error.is_some() {
+            continue;
+        }
+        if serde_json::from_slice::<Value>(&representation)
+            .ok()
+            .as_ref()
+            == Some(expected)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+impl DIDPublishCmd {
+    pub fn run(self) {
+        match self {
+            DIDPublishCmd::Web {
+                did,
+                target,
+                key_path,
+                token,
+                verify_attempts,
+                verify_interval,
+                resolver_options,
+            } => {
+                let (host, path) = match did_web_path(&did) {
+                    Some(location) => location,
+                    None => {
+                        eprintln!("didkit: Expected did:web DID: {}", did);
+                        std::process::exit(1);
+                    }
+                };
+                let document: Value = match key_path {
+                    Some(key_path) => key_document(&did, &read_jwk(&key_path)),
+                    None => serde_json::from_reader(BufReader::new(stdin())).unwrap(),
+                };
+                if document.get("id").and_then(Value::as_str) != Some(did.as_str()) {
+                    eprintln!("didkit: DID document id does not match {}", did);
+                    std::process::exit(1);
+                }
+                let report = did_lint::lint(&document);
+                if !report.is_valid() {
+                    eprintln!("didkit: Invalid DID document:");
+                    serde_json::to_writer_pretty(std::io::stderr(), &report).unwrap();
+                    std::process::exit(1);
+                }
+                let target_url = match Url::parse(&target) {
+                    Ok(url) => url,
+                    Err(err) => {
+                        eprintln!("didkit: Invalid target {}: {}", target, err);
+                        std::process::exit(1);
+                    }
+                };
+                let body = serde_json::to_vec_pretty(&document).unwrap();
+                let rt = runtime::get().unwrap();
+                let uploaded = match target_url.scheme() {
+                    "sftp" => upload_sftp(&target_url, &path, &body),
+                    "s3" => rt.block_on(upload_s3(&target_url, &path, body)),
+                    "http" | "https" => {
+                        rt.block_on(upload_http(&target_url, &path, body, token.as_deref()))
+                    }
+                    scheme => Err(format!("Unsupported target scheme: {}", scheme)),
+                };
+                let uploaded_to = match uploaded {
+                    Ok(uploaded_to) => uploaded_to,
+                    Err(err) => {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    }
+                };
+                let verified = if verify_attempts == 0 {
+                    None
+                } else {
+                    let resolver = resolver_options.to_resolver();
+                    Some(rt.block_on(check_resolution(
+                        &did,
+                        &document,
+                        &resolver,
+                        verify_attempts,
+                        Duration::from_secs(verify_interval),
+                    )))
+                };
+                let publication = Publication {
+                    did,
+                    url: format!("https://{}/{}", host, path),
+                    uploaded_to,
+                    verified,
+                };
+                output::write_json_pretty(&publication);
+                if verified == Some(false) {
+                    eprintln!("didkit: DID does not resolve to the published document");
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_paths() {
+        assert_eq!(
+            did_web_path("did:web:example.com").unwrap(),
+            (
+                "example.com".to_string(),
+                ".well-known/did.json".to_string()
+            )
+        );
+        assert_eq!(
+            did_web_path("did:web:localhost%3A8443:user:alice").unwrap(),
+            (
+                "localhost:8443".to_string(),
+                "user/alice/did.json".to_string()
+            )
+        );
+        assert!(did_web_path("did:web:example.com::alice").is_none());
+        assert!(did_web_path("did:key:z6Mk").is_none());
+        assert_eq!(
+            join("/var/www/", ".well-known/did.json"),
+            "/var/www/.well-known/did.json"
+        );
+        assert_eq!(encode_key("users/a b/did.json"), "users/a%20b/did.json");
+    }
+}
@@ -0,0 +1,178 @@
+//! `didkit did-webvh` subcommands: create and update the DID log of a `did:webvh` DID.
+//!
+//! Each command outputs the new log entry, as one line to append to the `did.jsonl` file.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use structopt::StructOpt;
+
+use didkit::did_webvh::{self, Parameters, WebVHError};
+use didkit::multikey;
+use didkit::JWK;
+
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum DIDWebVHCmd {
+    /// Create a DID, and output the first entry of its log
+    Create {
+        /// Domain and optional path of the DID, as in a did:web DID, e.g. example.com:dids:issuer
+        address: String,
+        /// Filename of the update key JWK, an Ed25519 key
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Filename of the DID document, in which {DID} stands for the DID. Default is a document
+        /// with the update key as its verification method.
+        #[structopt(short, long, parse(from_os_str))]
+        document_path: Option<PathBuf>,
+        /// Filename of a JWK to commit to as a next update key, for pre-rotation. May be
+        /// repeated.
+        #[structopt(short, long, parse(from_os_str), number_of_values = 1)]
+        next_key_path: Vec<PathBuf>,
+        /// Allow the DID to move to another location
+        #[structopt(long)]
+        portable: bool,
+        /// Seconds for which resolvers may cache the DID document
+        #[structopt(long)]
+        ttl: Option<u64>,
+    },
+    /// Update a DID, and output the next entry of its log
+    Update {
+        /// Filename of the DID log
+        #[structopt(short, long, parse(from_os_str))]
+        log_path: PathBuf,
+        /// Filename of the update key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Filename of the new DID document. Default is the current one.
+        #[structopt(short, long, parse(from_os_str))]
+        document_path: Option<PathBuf>,
+        /// Filename of a JWK to rotate the update keys to. May be repeated. With pre-rotation,
+        /// the new update keys must be given, and include the update key.
+        #[structopt(short, long, parse(from_os_str), number_of_values = 1)]
+        update_key_path: Vec<PathBuf>,
+        /// Filename of a JWK to commit to as a next update key. May be repeated.
+        #[structopt(short, long, parse(from_os_str), number_of_values = 1)]
+        next_key_path: Vec<PathBuf>,
+        /// Stop pre-rotation: commit to no next update keys
+        #[structopt(long, conflicts_with = "next-key-path")]
+        no_prerotation: bool,
+        /// Deactivate the DID
+        #[structopt(long)]
+        deactivate: bool,
+    },
+    /// Output the hash of a key, as listed in nextKeyHashes
+    KeyHash {
+        /// Filename of the JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+    },
+}
+
+fn exit_error(err: impl std::fmt::Display) -> ! {
+    eprintln!("didkit: {}", err);
+    std::process::exit(1);
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let file =
+        File::open(path).unwrap_or_else(|err| exit_error(format!("{}: {}", path.display(), err)));
+    serde_json::from_reader(BufReader::new(file))
+        .unwrap_or_else(|err| exit_error(format!("{}: {}", path.display(), err)))
+}
+
+fn key_hashes(paths: &[PathBuf]) -> Result<Vec<String>, WebVHError> {
+    paths
+        .iter()
+        .map(|path| did_webvh::key_hash(&read_json::<JWK>(path)))
+        .collect()
+}
+
+fn multikeys(paths: &[PathBuf]) -> Result<Vec<String>, WebVHError> {
+    paths
+        .iter()
+        .map(|path| Ok(multikey::to_multibase(&read_json::<JWK>(path))?))
+        .collect()
+}
+
+fn output(entry: &Value) {
+    println!("{}", output::to_json_string(entry));
+}
+
+impl DIDWebVHCmd {
+    pub fn run(self) {
+        match self {
+            DIDWebVHCmd::Create {
+                address,
+                key_path,
+                document_path,
+                next_key_path,
+                portable,
+                ttl,
+            } => {
+                let key: JWK = read_json(&key_path);
+                let document: Option<Value> = document_path.as_deref().map(read_json);
+                let parameters = Parameters {
+                    next_key_hashes: Some(
+                        key_hashes(&next_key_path).unwrap_or_else(|err| exit_error(err)),
+                    )
+                    .filter(|hashes| !hashes.is_empty()),
+                    portable: if portable { Some(true) } else { None },
+                    ttl,
+                    ..Default::default()
+                };
+                let entry = did_webvh::create(&address, &key, document, parameters)
+                    .unwrap_or_else(|err| exit_error(err));
+                output(&entry);
+            }
+            DIDWebVHCmd::Update {
+                log_path,
+                key_path,
+                document_path,
+                update_key_path,
+                next_key_path,
+                no_prerotation,
+                deactivate,
+            } => {
+                let log = std::fs::read_to_string(&log_path)
+                    .unwrap_or_else(|err| exit_error(format!("{}: {}", log_path.display(), err)));
+                let key: JWK = read_json(&key_path);
+                let document = match document_path {
+                    Some(path) => read_json(&path),
+                    None => match did_webvh::read_log(&log) {
+                        Ok(versions) => versions.last().map(|v| v.document.clone()).unwrap(),
+                        Err(err) => exit_error(err),
+                    },
+                };
+                let next_key_hashes =
+                    key_hashes(&next_key_path).unwrap_or_else(|err| exit_error(err));
+                let parameters = Parameters {
+                    update_keys: Some(
+                        multikeys(&update_key_path).unwrap_or_else(|err| exit_error(err)),
+                    )
+                    .filter(|keys| !keys.is_empty()),
+                    next_key_hashes: if no_prerotation {
+                        Some(Vec::new())
+                    } else {
+                        Some(next_key_hashes).filter(|hashes| !hashes.is_empty())
+                    },
+                    deactivated: if deactivate { Some(true) } else { None },
+                    ..Default::default()
+                };
+                let entry = did_webvh::update(&log, document, parameters, &key)
+                    .unwrap_or_else(|err| exit_error(err));
+                output(&entry);
+            }
+            DIDWebVHCmd::KeyHash { key_path } => {
+                let key: JWK = read_json(&key_path);
+                println!(
+                    "{}",
+                    did_webvh::key_hash(&key).unwrap_or_else(|err| exit_error(err))
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! `didkit didcomm` subcommands: DIDComm v2 mediation and message pickup client, and out-of-band
+//! invitations, whose accepted connections are kept in the agent's wallet.
+
+use std::fs::File;
+use std::io::{stdin, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+use did_method_key::DIDKey;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use didkit::didcomm::{
+    self, Inviter, MediationClient, MediationEvent, MediationState, Message, ENCRYPTED_MEDIA_TYPE,
+};
+use didkit::pairwise;
+use didkit::wallet::{Connection, Wallet};
+use didkit::{runtime, DIDMethod, DIDResolver, Source, JWK};
+
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum DIDCommCmd {
+    /// Register with a mediator and pick up messages from it
+    Mediate(MediateCmd),
+    /// Create and accept out-of-band invitations
+    Oob(OobCmd),
+    /// Decrypt a DIDComm message read from stdin, and output it
+    Unpack {
+        /// Filename of the recipient's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum MediateCmd {
+    /// Request mediation, and output the routing DID granted by the mediator
+    Request {
+        /// DID of the mediator
+        #[structopt(short, long)]
+        mediator: String,
+        /// DIDComm endpoint of the mediator. Default is the endpoint in its DID document.
+        #[structopt(short, long)]
+        endpoint: Option<String>,
+        /// DID of the agent. Default is the did:key of the agent's key.
+        #[structopt(long)]
+        did: Option<String>,
+        #[structopt(flatten)]
+        state: StateArg,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Add or remove recipient DIDs for which the mediator accepts messages
+    Update {
+        /// DID to add. May be repeated.
+        #[structopt(short, long, number_of_values = 1)]
+        add: Vec<String>,
+        /// DID to remove. May be repeated.
+        #[structopt(long, number_of_values = 1)]
+        remove: Vec<String>,
+        #[structopt(flatten)]
+        state: StateArg,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// List the recipient DIDs registered with the mediator
+    Query {
+        #[structopt(flatten)]
+        state: StateArg,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Pick up queued messages, and output them decrypted, one per line
+    Pickup {
+        /// Maximum number of messages to pick up per request
+        #[structopt(short, long, default_value = "10")]
+        limit: usize,
+        /// Keep polling the mediator for new messages
+        #[structopt(short, long)]
+        follow: bool,
+        /// Interval between polls, in seconds
+        #[structopt(long, default_value = "5", requires = "follow")]
+        interval: u64,
+        #[structopt(flatten)]
+        state: StateArg,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum OobCmd {
+    /// Create an out-of-band invitation, and output it as JSON, or as a URL with --base-url
+    Create {
+        /// DID of the inviter. Default is the did:key of the key.
+        #[structopt(long, required_unless = "key-path")]
+        did: Option<String>,
+        /// Filename of the inviter's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: Option<PathBuf>,
+        /// Label of the inviter, for the invitee to show
+        #[structopt(short, long)]
+        label: Option<String>,
+        /// Goal code, e.g. issue-vc
+        #[structopt(long)]
+        goal_code: Option<String>,
+        /// Goal, for the invitee to show
+        #[structopt(long)]
+        goal: Option<String>,
+        /// URL to put the invitation in, in its _oob query parameter
+        #[structopt(short, long)]
+        base_url: Option<String>,
+        /// Also output the URL as a QR code, to standard error
+        #[structopt(long, requires = "base-url")]
+        qr: bool,
+    },
+    /// Accept an out-of-band invitation, given as a URL or JSON, store the connection in the
+    /// wallet, and output it
+    Accept {
+        /// Invitation URL, or JSON
+        invitation: String,
+        /// Filename of the wallet's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Directory of the encrypted credential store
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: PathBuf,
+        /// Our DID in the connection. Default is the pairwise DID of the inviter, with
+        /// --seed-path, or else the did:key of the key.
+        #[structopt(long)]
+        did: Option<String>,
+        /// Filename of the master seed of pairwise DIDs
+        #[structopt(short, long, parse(from_os_str), conflicts_with = "did")]
+        seed_path: Option<PathBuf>,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// List the connections of the wallet, one per line
+    List {
+        /// Filename of the wallet's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Directory of the encrypted credential store
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct StateArg {
+    /// Filename of the mediation state, created by `didkit didcomm mediate request`
+    #[structopt(short, long, parse(from_os_str))]
+    state: PathBuf,
+    /// Filename of the agent's private key JWK
+    #[structopt(short, long, parse(from_os_str))]
+    key_path: PathBuf,
+}
+
+/// Mediation state kept between runs.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MediationFile {
+    pub(crate) endpoint: String,
+    #[serde(flatten)]
+    pub(crate) client: MediationClient,
+}
+
+impl MediationFile {
+    pub(crate) fn load(path: &Path) -> Self {
+        let file = File::open(path).unwrap();
+        serde_json::from_reader(BufReader::new(file)).unwrap()
+    }
+
+    fn save(&self, path: &Path) {
+        let file = File::create(path).unwrap();
+        serde_json::to_writer_pretty(file, self).unwrap();
+    }
+}
+
+/// Post an encrypted message to a DIDComm endpoint, and decrypt the reply, if any.
+pub(crate) async fn post(
+    endpoint: &str,
+    envelope: String,
+    key: &JWK,
+) -> Result<Option<Message>, String> {
+    let response = reqwest::Client::new()
+        .post(endpoint)
+        .header("Content-Type", ENCRYPTED_MEDIA_TYPE)
+        .body(envelope)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+    didcomm::unpack(&body, key)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Connection record of an accepted invitation.
+pub(crate) fn invitation_connection(inviter: Inviter, my_did: String) -> Connection {
+    Connection {
+        label: inviter.label,
+        my_did: Some(my_did),
+        endpoint: Some(inviter.service.uri),
+        routing_keys: inviter.service.routing_keys,
+        invitation_id: Some(inviter.invitation_id),
+        ..Connection::new(inviter.did)
+    }
+}
+
+/// Send a message to the mediator, and decrypt its reply, if any.
+pub(crate) async fn send(
+    message: &Message,
+    mediation: &MediationFile,
+    key: &JWK,
+    resolver: &dyn DIDResolver,
+) -> Result<Option<Message>, String> {
+    let envelope = didcomm::pack(message, &mediation.client.mediator, resolver)
+        .await
+        .map_err(|e| e.to_string())?;
+    post(&mediation.endpoint, envelope, key).await
+}
+
+/// Send a message to the mediator, and process its reply.
+fn request(
+    message: Message,
+    mediation: &mut MediationFile,
+    key: &JWK,
+    resolver: &dyn DIDResolver,
+) -> MediationEvent {
+    let rt = runtime::get().unwrap();
+    let reply = rt
+        .block_on(send(&message, mediation, key, resolver))
+        .unwrap()
+        .expect("Mediator did not reply");
+    match mediation.client.handle(&reply).unwrap() {
+        MediationEvent::Problem { code, comment } => {
+            eprintln!("Problem report: {} {}", code, comment.unwrap_or_default());
+            std::process::exit(1);
+        }
+        event => event,
+    }
+}
+
+impl DIDCommCmd {
+    pub fn run(self) {
+        match self {
+            DIDCommCmd::Mediate(cmd) => cmd.run(),
+            DIDCommCmd::Oob(cmd) => cmd.run(),
+            DIDCommCmd::Unpack { key_path } => {
+                let key = read_jwk(&key_path);
+                let mut envelope = String::new();
+                stdin().read_to_string(&mut envelope).unwrap();
+                let message = didcomm::unpack(&envelope, &key).unwrap();
+                println!("{}", output::to_json_string(&message));
+            }
+        }
+    }
+}
+
+fn exit_error(err: impl std::fmt::Display) -> ! {
+    eprintln!("didkit: {}", err);
+    std::process::exit(1);
+}
+
+impl OobCmd {
+    pub fn run(self) {
+        match self {
+            OobCmd::Create {
+                did,
+                key_path,
+                label,
+                goal_code,
+                goal,
+                base_url,
+                qr,
+            } => {
+                let did = match (did, key_path) {
+                    (Some(did), _) => did,
+                    (None, Some(key_path)) => {
+                        let key = read_jwk(&key_path);
+                        DIDKey.generate(&Source::Key(&key)).unwrap()
+                    }
+                    (None, None) => exit_error("Missing DID or key"),
+                };
+                let invitation = didcomm::invitation(
+                    &did,
+                    label.as_deref(),
+                    goal_code.as_deref(),
+                    goal.as_deref(),
+                );
+                let base_url = match base_url {
+                    Some(base_url) => base_url,
+                    None => {
+                        println!("{}", output::to_json_string(&invitation));
+                        return;
+                    }
+                };
+                let url = didcomm::invitation_url(&invitation, &base_url)
+                    .unwrap_or_else(|err| exit_error(err));
+                if qr {
+                    let code = QrCode::new(url.as_bytes()).unwrap_or_else(|err| exit_error(err));
+                    eprintln!("{}", code.render::<Dense1x2>().quiet_zone(true).build());
+                }
+                println!("{}", url);
+            }
+            OobCmd::Accept {
+                invitation,
+                key_path,
+                wallet,
+                did,
+                seed_path,
+                resolver_options,
+            } => {
+                let rt = runtime::get().unwrap();
+                let resolver = resolver_options.to_resolver();
+                let key = read_jwk(&key_path);
+                let inviter = rt
+                    .block_on(didcomm::accept_invitation(&invitation, &resolver))
+                    .unwrap_or_else(|err| exit_error(err));
+                let my_did = match (did, seed_path) {
+                    (Some(did), _) => did,
+                    (None, Some(seed_path)) => {
+                        let seed = std::fs::read(&seed_path).unwrap();
+                        pairwise::derive_did(&seed, &inviter.did, "key")
+                            .unwrap_or_else(|err| exit_error(err))
+                            .did
+                    }
+                    (None, None) => DIDKey.generate(&Source::Key(&key)).unwrap(),
+                };
+                let wallet = Wallet::open(&wallet, key).unwrap();
+                let connection = invitation_connection(inviter, my_did);
+                wallet.insert_connection(&connection).unwrap();
+                println!("{}", output::to_json_string(&connection));
+            }
+            OobCmd::List { key_path, wallet } => {
+                let wallet = Wallet::open(&wallet, read_jwk(&key_path)).unwrap();
+                for connection in wallet.connections().unwrap() {
+                    println!("{}", output::to_json_string(&connection));
+                }
+            }
+        }
+    }
+}
+
+impl MediateCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            MediateCmd::Request {
+                mediator,
+                endpoint,
+                did,
+                state,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key = read_jwk(&state.key_path);
+                let did = did.unwrap_or_else(|| DIDKey.generate(&Source::Key(&key)).unwrap());
+                let endpoint = match endpoint {
+                    Some(endpoint) => endpoint,
+                    None => rt
+                        .block_on(didcomm::service_endpoint(&mediator, &resolver))
+                        .unwrap(),
+                };
+                let mut client = MediationClient::new(did, mediator);
+                let message = client.request_mediation();
+                let mut mediation = MediationFile { endpoint, client };
+                let event = request(message, &mut mediation, &key, &resolver);
+                mediation.save(&state.state);
+                match event {
+                    MediationEvent::Granted { routing_did } => println!("{}", routing_did),
+                    _ => {
+                        eprintln!("Mediation denied");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            MediateCmd::Update {
+                add,
+                remove,
+                state,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key = read_jwk(&state.key_path);
+                let mut mediation = MediationFile::load(&state.state);
+                let message = mediation.client.update_recipients(&add, &remove).unwrap();
+                if let MediationEvent::RecipientsUpdated(updates) =
+                    request(message, &mut mediation, &key, &resolver)
+                {
+                    println!("{}", output::to_json_string(&updates));
+                }
+            }
+            MediateCmd::Query {
+                state,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key = read_jwk(&state.key_path);
+                let mut mediation = MediationFile::load(&state.state);
+                let message = mediation.client.query_recipients().unwrap();
+                if let MediationEvent::Recipients(dids) =
+                    request(message, &mut mediation, &key, &resolver)
+                {
+                    for did in dids {
+                        println!("{}", did);
+                    }
+                }
+            }
+            MediateCmd::Pickup {
+                limit,
+                follow,
+                interval,
+                state,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key = read_jwk(&state.key_path);
+                let mut mediation = MediationFile::load(&state.state);
+                if !matches!(mediation.client.state, MediationState::Granted { .. }) {
+                    eprintln!("Mediation has not been granted");
+                    std::process::exit(1);
+                }
+                loop {
+                    let message = mediation.client.delivery_request(limit).unwrap();
+                    let delivered = match request(message, &mut mediation, &key, &resolver) {
+                        MediationEvent::Delivered(delivered) => delivered,
+                        // A status reply means that no messages are queued.
+                        _ => Vec::new(),
+                    };
+                    let mut ids = Vec::new();
+                    for delivered in &delivered {
+                        match didcomm::unpack(&delivered.envelope, &key) {
+                            Ok(message) => {
+                                println!("{}", output::to_json_string(&message))
+                            }
+                            Err(e) => eprintln!("Message {}: {}", delivered.id, e),
+                        }
+                        ids.push(delivered.id.clone());
+                    }
+                    if !ids.is_empty() {
+                        let message = mediation.client.messages_received(&ids).unwrap();
+                        request(message, &mut mediation, &key, &resolver);
+                    }
+                    if delivered.len() == limit {
+                        continue;
+                    }
+                    if !follow {
+                        break;
+                    }
+                    sleep(Duration::from_secs(interval));
+                }
+            }
+        }
+    }
+}
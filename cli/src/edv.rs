@@ -0,0 +1,284 @@
+//! `didkit edv` subcommands: Encrypted Data Vault (Confidential Storage) client.
+
+use std::io::{stdin, stdout, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde_json::Value;
+use structopt::StructOpt;
+
+use didkit::edv::{
+    Authorization, EDVClient, HmacKey, KeyReference, VaultConfiguration, KEY_AGREEMENT_KEY_TYPE,
+};
+use didkit::{jwe, runtime};
+
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum EdvCmd {
+    /// Generate a random HMAC key for blinding vault indexes, as a JWK
+    GenerateHmacKey {
+        /// Key ID, e.g. a DID URL under the vault controller
+        #[structopt(long)]
+        id: String,
+    },
+    /// Create a vault, and output its URL
+    CreateVault {
+        /// EDV server URL
+        #[structopt(short, long)]
+        server: String,
+        /// DID of the vault controller
+        #[structopt(short, long)]
+        controller: String,
+        /// Key agreement key ID. Default is the controller's key agreement key.
+        #[structopt(long)]
+        key_agreement_key: Option<String>,
+        #[structopt(long)]
+        reference_id: Option<String>,
+        #[structopt(flatten)]
+        hmac_key: HmacKeyArg,
+        #[structopt(flatten)]
+        auth: EdvAuthOptions,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Encrypt a document read from stdin to a DID, store it in a vault, and output its ID
+    Insert {
+        /// Vault URL
+        #[structopt(short, long)]
+        vault: String,
+        /// DID to encrypt the document to
+        #[structopt(short = "t", long)]
+        recipient: String,
+        /// Attribute to index the document by, as NAME=VALUE. May be repeated.
+        #[structopt(short, long, number_of_values = 1)]
+        index: Vec<IndexArg>,
+        #[structopt(flatten)]
+        hmac_key: HmacKeyArg,
+        #[structopt(flatten)]
+        auth: EdvAuthOptions,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Retrieve and decrypt a document
+    Get {
+        /// Vault URL
+        #[structopt(short, long)]
+        vault: String,
+        /// Document ID
+        id: String,
+        #[structopt(flatten)]
+        decryption_key: DecryptionKeyArg,
+        #[structopt(flatten)]
+        auth: EdvAuthOptions,
+    },
+    /// Find documents by an indexed attribute, and output them decrypted, one per line
+    Query {
+        /// Vault URL
+        #[structopt(short, long)]
+        vault: String,
+        /// Attribute to match, as NAME=VALUE
+        #[structopt(short, long)]
+        index: IndexArg,
+        #[structopt(flatten)]
+        hmac_key: HmacKeyArg,
+        #[structopt(flatten)]
+        decryption_key: DecryptionKeyArg,
+        #[structopt(flatten)]
+        auth: EdvAuthOptions,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct EdvAuthOptions {
+    /// OAuth bearer token for vault requests
+    #[structopt(env = "EDV_TOKEN", long, hide_env_values = true)]
+    edv_token: Option<String>,
+    /// Capability to invoke for vault requests. Default is the root capability of the request URL.
+    #[structopt(long, requires = "invocation-key-path")]
+    capability: Option<String>,
+    /// Verification method ID of the capability invoker
+    #[structopt(long, requires = "invocation-key-path")]
+    invoker: Option<String>,
+    /// Filename of the JWK to sign capability invocations with
+    #[structopt(
+        long,
+        parse(from_os_str),
+        requires = "invoker",
+        conflicts_with = "edv-token"
+    )]
+    invocation_key_path: Option<PathBuf>,
+}
+
+impl EdvAuthOptions {
+    fn to_authorization(&self) -> Authorization {
+        if let Some(ref token) = self.edv_token {
+            return Authorization::Bearer(token.clone());
+        }
+        match (&self.invoker, &self.invocation_key_path) {
+            (Some(invoker), Some(path)) => Authorization::Capability {
+                capability: self.capability.clone(),
+                invoker: invoker.clone(),
+                key: read_jwk(path),
+            },
+            _ => Authorization::None,
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct HmacKeyArg {
+    /// Filename of the HMAC key JWK used to blind indexes
+    #[structopt(long, parse(from_os_str))]
+    hmac_key: PathBuf,
+}
+
+impl HmacKeyArg {
+    fn get(&self) -> HmacKey {
+        HmacKey::from_jwk(&read_jwk(&self.hmac_key)).unwrap()
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct DecryptionKeyArg {
+    /// Filename of the recipient's private key JWK
+    #[structopt(short, long, parse(from_os_str))]
+    key_path: PathBuf,
+}
+
+/// Index attribute given on the command line. The value is parsed as JSON if possible, and
+/// otherwise used as a string.
+#[derive(Debug, Clone)]
+pub struct IndexArg {
+    name: String,
+    value: Value,
+}
+
+impl FromStr for IndexArg {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if !name.is_empty() => Ok(Self {
+                name: name.to_string(),
+                value: serde_json::from_str(value)
+                    .unwrap_or_else(|_| Value::String(value.to_string())),
+            }),
+            _ => Err(format!("Expected NAME=VALUE: {}", s)),
+        }
+    }
+}
+
+impl EdvCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            EdvCmd::GenerateHmacKey { id } => {
+                let jwk = HmacKey::generate_jwk(id);
+                println!("{}", output::to_json_string(&jwk));
+            }
+            EdvCmd::CreateVault {
+                server,
+                controller,
+                key_agreement_key,
+                reference_id,
+                hmac_key,
+                auth,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key_agreement_key = match key_agreement_key {
+                    Some(id) => id,
+                    None => {
+                        rt.block_on(jwe::key_agreement_key(&controller, &resolver))
+                            .unwrap()
+                            .0
+                    }
+                };
+                let config = VaultConfiguration {
+                    id: None,
+                    sequence: 0,
+                    controller,
+                    reference_id,
+                    key_agreement_key: KeyReference {
+                        id: key_agreement_key,
+                        type_: KEY_AGREEMENT_KEY_TYPE.to_string(),
+                    },
+                    hmac: hmac_key.get().key_reference(),
+                };
+                let client = rt
+                    .block_on(EDVClient::create_vault(
+                        &server,
+                        &config,
+                        auth.to_authorization(),
+                    ))
+                    .unwrap();
+                println!("{}", client.vault_url);
+            }
+            EdvCmd::Insert {
+                vault,
+                recipient,
+                index,
+                hmac_key,
+                auth,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let client = EDVClient::new(&vault, auth.to_authorization()).unwrap();
+                let mut document = String::new();
+                stdin().read_to_string(&mut document).unwrap();
+                let document = document.trim();
+                let cty = if document.starts_with('{') {
+                    jwe::CTY_LD_JSON
+                } else {
+                    jwe::CTY_JWT
+                };
+                let attributes: Vec<(String, Value)> =
+                    index.into_iter().map(|arg| (arg.name, arg.value)).collect();
+                let id = rt
+                    .block_on(client.insert(
+                        document.as_bytes(),
+                        Some(cty.to_string()),
+                        &recipient,
+                        &hmac_key.get(),
+                        &attributes,
+                        &resolver,
+                    ))
+                    .unwrap();
+                println!("{}", id);
+            }
+            EdvCmd::Get {
+                vault,
+                id,
+                decryption_key,
+                auth,
+            } => {
+                let client = EDVClient::new(&vault, auth.to_authorization()).unwrap();
+                let document = rt.block_on(client.get(&id)).unwrap();
+                let plaintext = document
+                    .decrypt(&read_jwk(&decryption_key.key_path))
+                    .unwrap();
+                stdout().write_all(&plaintext).unwrap();
+            }
+            EdvCmd::Query {
+                vault,
+                index,
+                hmac_key,
+                decryption_key,
+                auth,
+            } => {
+                let client = EDVClient::new(&vault, auth.to_authorization()).unwrap();
+                let documents = rt
+                    .block_on(client.query(&hmac_key.get(), &index.name, &index.value))
+                    .unwrap();
+                let jwk = read_jwk(&decryption_key.key_path);
+                let mut stdout = stdout();
+                for document in documents {
+                    stdout.write_all(&document.decrypt(&jwk).unwrap()).unwrap();
+                    stdout.write_all(b"\n").unwrap();
+                }
+            }
+        }
+    }
+}
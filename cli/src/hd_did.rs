@@ -0,0 +1,163 @@
+//! `didkit hd-did` subcommands: trees of hierarchical deterministic DIDs, derived from an
+//! organizational root seed and recorded in a tree file of derivation paths.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use didkit::hd_did::{self, DerivationPath, HDError, HDTree};
+
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum HDDIDCmd {
+    /// Derive the DID of a node, record it in the tree file, and output the DID, or its private
+    /// JWK
+    Add {
+        /// Filename of the root seed: at least 16 random bytes
+        #[structopt(short, long, parse(from_os_str))]
+        seed_path: PathBuf,
+        /// Tree file, created if it does not exist
+        #[structopt(short, long, parse(from_os_str))]
+        tree: PathBuf,
+        /// Name of the node, e.g. engineering/ci/runner-1. Its parent must be in the tree.
+        name: String,
+        /// Derivation path, e.g. m/0'/3'. Default is the next unused index under the parent.
+        #[structopt(short, long)]
+        path: Option<DerivationPath>,
+        /// DID method: key or peer (did:peer:0)
+        #[structopt(short, long, default_value = "key")]
+        method: String,
+        /// Output the derived private JWK instead of the DID
+        #[structopt(long)]
+        jwk: bool,
+    },
+    /// Output the nodes of a tree file, or the descendants of a node, as JSON
+    List {
+        #[structopt(short, long, parse(from_os_str))]
+        tree: PathBuf,
+        /// Name of a node whose descendants to list
+        #[structopt(long)]
+        under: Option<String>,
+    },
+    /// Regenerate and output the private JWK of a node of a tree file
+    Regenerate {
+        #[structopt(short, long, parse(from_os_str))]
+        seed_path: PathBuf,
+        #[structopt(short, long, parse(from_os_str))]
+        tree: PathBuf,
+        name: String,
+    },
+    /// Check that the nodes of a tree file were derived from a root seed, and output the names
+    /// of those that were not
+    Verify {
+        #[structopt(short, long, parse(from_os_str))]
+        seed_path: PathBuf,
+        #[structopt(short, long, parse(from_os_str))]
+        tree: PathBuf,
+    },
+    /// Derive the private JWK, or the DID, of a derivation path, without a tree file
+    Derive {
+        #[structopt(short, long, parse(from_os_str))]
+        seed_path: PathBuf,
+        path: DerivationPath,
+        /// DID method: key or peer (did:peer:0)
+        #[structopt(short, long, default_value = "key")]
+        method: String,
+        /// Output the DID instead of the private JWK
+        #[structopt(long)]
+        did: bool,
+    },
+}
+
+fn read_tree(path: &Path) -> HDTree {
+    let tree_file = File::open(path).unwrap();
+    serde_json::from_reader(BufReader::new(tree_file)).unwrap()
+}
+
+fn fail(err: HDError) -> ! {
+    eprintln!("didkit: {}", err);
+    std::process::exit(1);
+}
+
+impl HDDIDCmd {
+    pub fn run(self) {
+        match self {
+            HDDIDCmd::Add {
+                seed_path,
+                tree: tree_path,
+                name,
+                path,
+                method,
+                jwk,
+            } => {
+                let seed = std::fs::read(&seed_path).unwrap();
+                let mut tree = if tree_path.exists() {
+                    read_tree(&tree_path)
+                } else {
+                    HDTree::default()
+                };
+                let (node, key) = tree.add(&seed, &name, path, &method).unwrap_or_else(fail);
+                let tree_file = File::create(&tree_path).unwrap();
+                serde_json::to_writer_pretty(BufWriter::new(tree_file), &tree).unwrap();
+                if jwk {
+                    println!("{}", output::to_json_string(&key));
+                } else {
+                    println!("{}", node.did);
+                }
+            }
+            HDDIDCmd::List { tree, under } => {
+                let tree = read_tree(&tree);
+                let nodes: Vec<_> = match under {
+                    Some(name) => {
+                        let prefix = format!("{}/", name);
+                        tree.nodes
+                            .iter()
+                            .filter(|node| node.name.starts_with(&prefix))
+                            .collect()
+                    }
+                    None => tree.nodes.iter().collect(),
+                };
+                output::write_json_pretty(&nodes);
+            }
+            HDDIDCmd::Regenerate {
+                seed_path,
+                tree,
+                name,
+            } => {
+                let seed = std::fs::read(&seed_path).unwrap();
+                let key = read_tree(&tree)
+                    .regenerate(&seed, &name)
+                    .unwrap_or_else(fail);
+                println!("{}", output::to_json_string(&key));
+            }
+            HDDIDCmd::Verify { seed_path, tree } => {
+                let seed = std::fs::read(&seed_path).unwrap();
+                let mismatched = read_tree(&tree).verify(&seed).unwrap_or_else(fail);
+                for name in &mismatched {
+                    println!("{}", name);
+                }
+                if !mismatched.is_empty() {
+                    std::process::exit(2);
+                }
+            }
+            HDDIDCmd::Derive {
+                seed_path,
+                path,
+                method,
+                did,
+            } => {
+                let seed = std::fs::read(&seed_path).unwrap();
+                let (node, key) =
+                    hd_did::derive_node(&seed, "", path, &method).unwrap_or_else(fail);
+                if did {
+                    println!("{}", node.did);
+                } else {
+                    println!("{}", output::to_json_string(&key));
+                }
+            }
+        }
+    }
+}
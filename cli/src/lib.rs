@@ -1 +1,19 @@
+#[cfg(unix)]
+pub mod agent;
+pub mod barcode;
+pub mod did_publish;
+pub mod did_webvh;
+pub mod didcomm;
+pub mod edv;
+pub mod hd_did;
+pub mod oidc4vci;
+pub mod oidc4vp;
 pub mod opts;
+pub mod output;
+pub mod repl;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod test_vectors;
+pub mod vc_api;
+pub mod vc_audit;
+pub mod wallet;
@@ -1,28 +1,79 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{stdin, stdout, BufReader, BufWriter, Read, Write};
+use std::io::{stdin, stdout, BufReader, Read, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
 
 use chrono::prelude::*;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use sshkeys::PublicKey;
 use structopt::{clap::AppSettings, clap::ArgGroup, StructOpt};
 
 use did_method_key::DIDKey;
 use didkit::generate_proof;
 use didkit::{
-    dereference, get_verification_method, runtime, DIDMethod, DIDResolver,
-    DereferencingInputMetadata, Error, LinkedDataProofOptions, Metadata, ProofFormat, ProofPurpose,
-    ResolutionInputMetadata, ResolutionResult, Source, VerifiableCredential,
-    VerifiablePresentation, DID_METHODS, JWK, URI,
+    dereference, get_verification_method, runtime, CredentialOrJWT, DIDMethod, DIDResolver,
+    DereferencingInputMetadata, Error, JWTOrLDPOptions, LinkedDataProofOptions, Metadata,
+    PresentationOrJWT, ProofFormat, ProofOptionsBuilder, ProofPurpose, ResolutionInputMetadata,
+    ResolutionResult, Source, VerifiableCredential, VerifiablePresentation, VerificationOptions,
+    VerificationResult, DID_METHODS, JWK, URI,
 };
+use didkit::attestation;
+use didkit::barcode::{self, BarcodeFormat};
+use didkit::cacao::{self, Cacao, SiweMessage};
+use didkit::content_id;
+use didkit::convert;
+use didkit::did_lint;
+use didkit::integrity::{self, DigestFormat, RelatedResource};
+use didkit::issue::{check_verification_method, find_verification_method, IssueOptions};
+use didkit::jwe;
+use didkit::jwt_claims::{self, ClaimMapping, ClaimPrecedence, JWTClaimsOptions};
+use didkit::multikey;
+use didkit::pairwise;
+use didkit::profile::Profile;
+use didkit::query::{self, Query};
+use didkit::redact;
+use didkit::render::{self, CredentialManifest, RenderFormat};
+use didkit::resolution_error;
+use didkit::securing;
+use didkit::verification::{MissingExpiration, VerificationReport};
+#[cfg(unix)]
+use didkit_cli::agent::AgentCmd;
+use didkit_cli::barcode::{render_svg, scan};
+use didkit_cli::did_publish::DIDPublishCmd;
+use didkit_cli::did_webvh::DIDWebVHCmd;
+use didkit_cli::didcomm::DIDCommCmd;
+use didkit_cli::edv::EdvCmd;
+use didkit_cli::hd_did::HDDIDCmd;
+use didkit_cli::oidc4vci::Oidc4vciCmd;
+use didkit_cli::oidc4vp::Oidc4vpCmd;
 use didkit_cli::opts::ResolverOptions;
+use didkit_cli::output;
+use didkit_cli::repl::ReplCmd;
+#[cfg(feature = "store")]
+use didkit_cli::store::StoreCmd;
+use didkit_cli::test_vectors::TestVectorsCmd;
+use didkit_cli::vc_api::VcApiCmd;
+use didkit_cli::vc_audit::VcAuditCmd;
+use didkit_cli::wallet::WalletCmd;
+
+#[derive(StructOpt, Debug)]
+pub struct DIDKitOpts {
+    /// Output canonical JSON (RFC 8785), with the unordered arrays of verification results
+    /// sorted, for output that is reproducible between runs
+    #[structopt(long, global = true)]
+    canonical_json: bool,
+    #[structopt(subcommand)]
+    command: DIDKit,
+}
 
 #[derive(StructOpt, Debug)]
 pub enum DIDKit {
     /// Generate and output a Ed25519 keypair in JWK format
     GenerateEd25519Key,
+    /// Generate and output a BLS12-381 G2 keypair in JWK format, for BBS JSON Proof Tokens
+    GenerateBbsKey,
     /// Output a did:key DID for a JWK. Deprecated in favor of key-to-did.
     #[structopt(setting = AppSettings::Hidden)]
     KeyToDIDKey {
@@ -47,6 +98,8 @@ pub enum DIDKit {
         /// SSH Public Key
         ssh_pk: PublicKey,
     },
+    /// Convert public keys between JWK and other formats
+    Key(KeyCmd),
 
     /*
     // DID Functionality
@@ -77,6 +130,21 @@ pub enum DIDKit {
         #[structopt(flatten)]
         resolver_options: ResolverOptions,
     },
+    /// Validate a DID document, resolved from a DID or read from stdin, and report problems.
+    DIDLint {
+        /// DID to resolve. If not given, the DID document is read from stdin.
+        did: Option<String>,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Publish a DID document where its DID method resolves it, e.g. did:web
+    DIDPublish(DIDPublishCmd),
+    /// Create and update the DID log of a did:webvh DID
+    #[structopt(name = "did-webvh")]
+    DIDWebVH(DIDWebVHCmd),
+    /// Derive a tree of DIDs from an organizational root seed, and regenerate their keys
+    #[structopt(name = "hd-did")]
+    HDDID(HDDIDCmd),
     /// Authenticate with a DID.
     DIDAuth {
         #[structopt(flatten)]
@@ -107,12 +175,42 @@ pub enum DIDKit {
         proof_options: ProofOptions,
         #[structopt(flatten)]
         resolver_options: ResolverOptions,
+        #[structopt(flatten)]
+        related_resources: RelatedResourceArgs,
+        /// Set the id of the credential to its content-addressable ID, the urn:hash URN of the
+        /// hash of its canonical form
+        #[structopt(long)]
+        content_id: bool,
+        /// Validate and prepare the credential as for signing, and output it with a placeholder
+        /// signature, without using the key, which may be a public key
+        #[structopt(long)]
+        dry_run: bool,
     },
     /// Verify Credential
     VCVerifyCredential {
         #[structopt(flatten)]
         proof_options: ProofOptions,
         #[structopt(flatten)]
+        verify_options: VerifyOptions,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+        /// Output the decoded credential, with JWT claims mapped to credential properties, in
+        /// the verification result, if verification succeeds
+        #[structopt(long)]
+        decode: bool,
+    },
+    /// Verify a credential read from stdin, and output the values of its claims selected by JSON
+    /// pointers or JSONPath expressions, with their types, in the verification result
+    VCQuery {
+        /// JSON pointer (e.g. /credentialSubject/name) or JSONPath expression (e.g.
+        /// $.credentialSubject.name) into the decoded credential. May be repeated.
+        #[structopt(short, long = "query", required = true, number_of_values = 1)]
+        queries: Vec<Query>,
+        #[structopt(flatten)]
+        proof_options: ProofOptions,
+        #[structopt(flatten)]
+        verify_options: VerifyOptions,
+        #[structopt(flatten)]
         resolver_options: ResolverOptions,
     },
     /// Issue Presentation
@@ -123,6 +221,13 @@ pub enum DIDKit {
         proof_options: ProofOptions,
         #[structopt(flatten)]
         resolver_options: ResolverOptions,
+        #[structopt(flatten)]
+        related_resources: RelatedResourceArgs,
+
+        /// Validate and prepare the presentation as for signing, and output it with a placeholder
+        /// signature, without using the key, which may be a public key
+        #[structopt(long)]
+        dry_run: bool,
     },
     /// Verify Presentation
     VCVerifyPresentation {
@@ -130,7 +235,153 @@ pub enum DIDKit {
         resolver_options: ResolverOptions,
         #[structopt(flatten)]
         proof_options: ProofOptions,
+        #[structopt(flatten)]
+        verify_options: VerifyOptions,
+        /// Sign a verification report credential with the key in this JWK file, and output it
+        /// instead of the verification result
+        #[structopt(long, parse(from_os_str))]
+        attest: Option<PathBuf>,
+        /// DID of the verifier, as issuer of the verification report. Defaults to the did:key of
+        /// the --attest key.
+        #[structopt(long, requires = "attest")]
+        verifier: Option<String>,
+        /// Output the decoded presentation, with its credentials decoded, in the verification
+        /// result, if verification succeeds
+        #[structopt(long, conflicts_with = "attest")]
+        decode: bool,
+    },
+    /// Encrypt a credential or presentation to a DID, as a JWE
+    VCEncrypt {
+        /// DID of the recipient
+        #[structopt(short, long)]
+        recipient: String,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Decrypt a JWE-encrypted credential or presentation
+    VCDecrypt {
+        #[structopt(flatten)]
+        key: KeyArg,
+    },
+    /// Derive a credential disclosing only the given claims, from a selectively-disclosable
+    /// credential (SD-JWT or BBS JSON Proof Token) read from stdin
+    VCRedact {
+        /// JSON pointer of a claim to disclose, e.g. /address/locality. May be repeated.
+        #[structopt(long)]
+        reveal: Vec<String>,
+        /// JSON pointer of a claim that must be disclosed, in addition to the issuer and type.
+        /// May be repeated.
+        #[structopt(short, long)]
+        mandatory: Vec<String>,
+        /// Nonce of the verifier, for the presentation header of JSON Proof Tokens
+        #[structopt(long)]
+        nonce: Option<String>,
+        /// Audience of the presentation header of JSON Proof Tokens
+        #[structopt(long)]
+        audience: Option<String>,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
     },
+    /// Convert a credential read from stdin to another proof format, issuing it again with the
+    /// issuer's key
+    VCConvert {
+        /// Proof format to convert to: ldp or jwt
+        #[structopt(short, long)]
+        to: ProofFormat,
+        /// Verification method to sign with, instead of the issuer's verification method
+        /// matching the key
+        #[structopt(env, short, long)]
+        verification_method: Option<URI>,
+        #[structopt(flatten)]
+        key: KeyArg,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+        #[structopt(flatten)]
+        jwt_claims: JWTClaimsArgs,
+    },
+    /// Render a credential read from stdin, as JSON or a JWT, in a human-readable form, with the
+    /// display hints of a Credential Manifest or of the credential's render property
+    VCRender {
+        /// Output format: text, markdown or html
+        #[structopt(short, long, default_value = "text")]
+        format: RenderFormat,
+        /// Filename of a Credential Manifest whose output descriptor for the credential has
+        /// display hints
+        #[structopt(short, long, parse(from_os_str))]
+        manifest: Option<PathBuf>,
+        /// ID of the output descriptor of the manifest. Default is the descriptor whose schema is
+        /// one of the credential's types or schemas.
+        #[structopt(short, long, requires = "manifest")]
+        descriptor: Option<String>,
+    },
+    /// Output the content-addressable ID of a credential read from stdin, as JSON or a JWT: the
+    /// urn:hash URN of the hash of its canonical form, without its proofs or id
+    VCContentId {
+        /// Output the content hash, in hex, instead of the URN
+        #[structopt(long)]
+        hash: bool,
+    },
+    /// Encode a credential read from stdin in a barcode, for printing on a physical document, and
+    /// output it as an SVG image
+    VCToBarcode {
+        /// Barcode format: pdf417 or aztec
+        #[structopt(short, long, default_value = "pdf417")]
+        format: BarcodeFormat,
+        /// Compression profile: vc, or citizenship for permanent resident cards
+        #[structopt(short, long, default_value = "vc")]
+        profile: String,
+        /// Output the text of the barcode instead of an image
+        #[structopt(long)]
+        text: bool,
+    },
+    /// Decode a credential from the text of a barcode read from stdin, e.g. from a scanner, or
+    /// from a barcode image
+    BarcodeToVC {
+        /// Barcode format of the image: pdf417 or aztec
+        #[structopt(short, long, default_value = "pdf417")]
+        format: BarcodeFormat,
+        /// Filename of an image of the barcode
+        #[structopt(short, long, parse(from_os_str))]
+        image: Option<PathBuf>,
+    },
+    /// Sign a Sign-In with Ethereum message read from stdin, and output it as a CACAO
+    CacaoSign {
+        #[structopt(flatten)]
+        key: KeyArg,
+        /// Filename of a presentation without a proof, to bind the CACAO to by adding its hash to
+        /// the message's resources
+        #[structopt(short, long, parse(from_os_str))]
+        presentation: Option<PathBuf>,
+    },
+    /// Verify a CACAO read from stdin
+    CacaoVerify {},
+    /// Store credentials in an Encrypted Data Vault (Confidential Storage)
+    Edv(EdvCmd),
+    /// Client and conformance tests for remote VC-API implementations
+    VcApi(VcApiCmd),
+    /// OpenID for Verifiable Credential Issuance wallet client: accept credential offers
+    #[structopt(name = "oidc4vci")]
+    Oidc4vci(Oidc4vciCmd),
+    /// OpenID for Verifiable Presentations wallet responder: respond to authorization requests
+    #[structopt(name = "oidc4vp")]
+    Oidc4vp(Oidc4vpCmd),
+    /// Report the expired, near-expiry and revoked credentials of a directory or NDJSON stream
+    VcAudit(VcAuditCmd),
+    /// Generate and check VC test suite and DID test suite fixtures, reporting conformance
+    TestVectors(TestVectorsCmd),
+    /// DIDComm v2 messaging, through a mediator
+    #[structopt(name = "didcomm")]
+    DIDComm(DIDCommCmd),
+    /// Headless wallet: answer DIDComm credential offers and presentation requests
+    #[cfg(unix)]
+    Agent(AgentCmd),
+    /// Export and import Universal Wallet 2020 backups of a wallet
+    Wallet(WalletCmd),
+    /// Interactive shell, keeping a key, resolver options and variables between commands
+    Repl(ReplCmd),
+    /// Query issued and received credentials in a SQLite or PostgreSQL database
+    #[cfg(feature = "store")]
+    Store(StoreCmd),
     /// Convert JSON-LD to URDNA2015-canonicalized RDF N-Quads
     ToRdfURDNA2015 {
         /// Base IRI
@@ -147,16 +398,33 @@ pub enum DIDKit {
     /// Revoke Credential
     VCRevokeCredential {},
     */
+}
 
-    /*
-    // DIDComm Functionality (???)
-    /// Discover a messaging endpoint from a DID which supports DIDComm.
-    DIDCommDiscover {},
-    /// Send a DIDComm message.
-    DIDCommSend {},
-    /// Receive a DIDComm message.
-    DIDCommReceive {},
-    */
+#[derive(StructOpt, Debug)]
+pub enum KeyCmd {
+    /// Output the public key of a JWK as publicKeyMultibase, as in a Multikey verification method
+    ToMultibase {
+        #[structopt(flatten)]
+        key: KeyArg,
+    },
+    /// Convert a publicKeyMultibase value to a public JWK
+    FromMultibase { multibase: String },
+    /// Derive the pairwise DID of a relationship from a master seed, and output the DID, or its
+    /// private JWK
+    Pairwise {
+        /// Filename of the master seed: at least 16 random bytes
+        #[structopt(short, long, parse(from_os_str))]
+        seed_path: PathBuf,
+        /// Relationship identifier, e.g. the verifier's DID or origin
+        #[structopt(short, long)]
+        relationship: String,
+        /// DID method: key or peer (did:peer:0)
+        #[structopt(short, long, default_value = "key")]
+        method: String,
+        /// Output the derived private JWK instead of the DID
+        #[structopt(long)]
+        jwk: bool,
+    },
 }
 
 #[derive(StructOpt, Debug)]
@@ -177,6 +445,193 @@ pub struct ProofOptions {
     // Non-standard options
     #[structopt(env, default_value, short = "f", long)]
     pub proof_format: ProofFormat,
+    /// Issue reproducibly, with the created time given, and a key whose signatures are
+    /// deterministic
+    #[structopt(long, requires = "created")]
+    pub deterministic: bool,
+    /// Time after which the proof is no longer valid (ISO8601 datetime). Data Integrity proofs
+    /// only.
+    #[structopt(env, long)]
+    pub expires: Option<DateTime<Utc>>,
+    /// Proof nonce, e.g. to make proofs of the same document unique. Data Integrity proofs only.
+    #[structopt(env, long)]
+    pub nonce: Option<String>,
+    /// Additional proof property, as <name>=<value>, with a JSON value or a string. May be
+    /// repeated. Data Integrity proofs only.
+    #[structopt(long = "proof-property", number_of_values = 1)]
+    pub proof_properties: Vec<String>,
+    #[structopt(flatten)]
+    pub jwt_claims: JWTClaimsArgs,
+}
+
+/// Mapping between the JWT claims of a credential and its properties
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct JWTClaimsArgs {
+    /// Whether JWT claims replace (default) or duplicate the credential properties they map
+    #[structopt(env, long)]
+    pub jwt_claims_mapping: Option<ClaimMapping>,
+    /// JWT claims to map to credential properties (comma-separated, among iss, sub, nbf, exp and
+    /// jti). Defaults to all.
+    #[structopt(env, long = "jwt-claims", use_delimiter = true)]
+    pub claims: Option<Vec<String>>,
+    /// Which of a JWT claim and its credential property is kept when they differ: claims
+    /// (default), credential or error
+    #[structopt(env, long)]
+    pub jwt_claims_precedence: Option<ClaimPrecedence>,
+}
+
+impl JWTClaimsArgs {
+    /// Claim mapping options, if any is given.
+    fn options(&self) -> Option<JWTClaimsOptions> {
+        if self.jwt_claims_mapping.is_none()
+            && self.claims.is_none()
+            && self.jwt_claims_precedence.is_none()
+        {
+            return None;
+        }
+        let options = JWTClaimsOptions {
+            mapping: self.jwt_claims_mapping.unwrap_or_default(),
+            claims: self.claims.clone(),
+            precedence: self.jwt_claims_precedence.unwrap_or_default(),
+        };
+        if let Err(err) = options.validate() {
+            eprintln!("didkit: {}", err);
+            std::process::exit(1);
+        }
+        Some(options)
+    }
+}
+
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct VerifyOptions {
+    /// Only accept issuers and holders using these DID methods (comma-separated)
+    #[structopt(env, long, use_delimiter = true)]
+    pub allowed_did_methods: Option<Vec<String>>,
+    /// Tolerance, in seconds, for clock differences when checking validity periods
+    #[structopt(env, long)]
+    pub clock_skew: Option<i64>,
+    /// Check validity periods as of this time (ISO8601 datetime), instead of the current time
+    #[structopt(env, long)]
+    pub verify_as_of: Option<DateTime<Utc>>,
+    /// How to treat a credential without an expiration date: allow, warn or error
+    #[structopt(env, long)]
+    pub missing_expiration: Option<MissingExpiration>,
+    /// Require each credential in a presentation to be bound to the presentation's holder
+    #[structopt(long)]
+    pub require_holder_binding: bool,
+    /// Require the presentation's holder to be this DID
+    #[structopt(env, long)]
+    pub expected_holder: Option<String>,
+    /// Filename of a CACAO (signed SIWE message) proving control of the holder's did:pkh, for a
+    /// presentation without a proof
+    #[structopt(long, parse(from_os_str))]
+    pub cacao: Option<PathBuf>,
+    /// Also verify the credentials embedded in a presentation
+    #[structopt(long)]
+    pub verify_credentials: bool,
+    /// Maximum number of embedded credentials to verify concurrently
+    #[structopt(env, long)]
+    pub credential_parallelism: Option<usize>,
+    /// Filename of trusted X.509 certificates (PEM). JWT credentials must have an x5c or x5u
+    /// certificate chain to one of them.
+    #[structopt(env, long, parse(from_os_str))]
+    pub trusted_certificates: Option<PathBuf>,
+    /// Check the contexts listed with a digestSRI in relatedResource against their digests
+    #[structopt(long)]
+    pub check_context_integrity: bool,
+    /// Context document to check, as <url>=<file>, instead of fetching it. May be repeated.
+    #[structopt(long = "context-document")]
+    pub context_documents: Vec<String>,
+    /// Fetch every resource listed in relatedResource, and check it against its digestSRI or
+    /// digestMultibase
+    #[structopt(long)]
+    pub check_related_resources: bool,
+    /// Validate credentials against their credentialSchema, each subject against the schema of
+    /// credentialSubject
+    #[structopt(long)]
+    pub check_credential_schema: bool,
+    /// Schema document to validate with, as <id>=<file>, instead of fetching it. May be
+    /// repeated.
+    #[structopt(long = "schema-document", requires = "check-credential-schema")]
+    pub schema_documents: Vec<String>,
+    /// Validate credentials with the validators of their types in the credential type registry,
+    /// and warn of unknown types
+    #[structopt(long)]
+    pub check_credential_types: bool,
+    /// Require credentials to have a subject with this ID, among any others
+    #[structopt(env, long)]
+    pub expected_subject: Option<String>,
+    /// Presentations only: require the proof challenge (or JWT nonce) to be this value
+    #[structopt(env, long = "expect-challenge")]
+    pub expected_challenge: Option<String>,
+    /// Presentations only: require the proof domain (or JWT aud) to be this value
+    #[structopt(env, long = "expect-domain")]
+    pub expected_domain: Option<String>,
+    /// Verify proofs against the DID document of the signer as of each proof's creation, for
+    /// did:webvh and did:ion
+    #[structopt(long)]
+    pub historical_resolution: bool,
+    /// Verification profile: vc-api, ebsi or oid4vp-haip. May be repeated, to apply several.
+    #[structopt(long = "profile", number_of_values = 1)]
+    pub profiles: Vec<Profile>,
+}
+
+impl VerifyOptions {
+    /// The profiles to verify with, combined.
+    fn profile(&self) -> Profile {
+        self.profiles
+            .iter()
+            .cloned()
+            .fold(Profile::default(), Profile::and)
+    }
+}
+
+#[derive(StructOpt, Debug, Default)]
+pub struct RelatedResourceArgs {
+    /// Embed the digest of a resource, such as a context or an image, in relatedResource, as
+    /// <url>=<file>, or <url> to fetch it. May be repeated.
+    #[structopt(long = "related-resource")]
+    pub related_resources: Vec<String>,
+    /// Digests of related resources to embed: sri (digestSRI), multibase (digestMultibase), or
+    /// both, comma-separated
+    #[structopt(long, default_value = "sri", use_delimiter = true)]
+    pub related_resource_digest: Vec<DigestFormat>,
+}
+
+/// Split a <url>=<file> argument.
+fn url_file(arg: &str) -> (&str, Option<&str>) {
+    match arg.find('=') {
+        Some(i) => (&arg[..i], Some(&arg[i + 1..])),
+        None => (arg, None),
+    }
+}
+
+impl RelatedResourceArgs {
+    /// Add the related resources to a credential or presentation.
+    fn embed(&self, document: &mut Value, rt: &tokio::runtime::Runtime) {
+        if self.related_resources.is_empty() {
+            return;
+        }
+        let formats = match self.related_resource_digest.as_slice() {
+            [] => &[DigestFormat::SRI][..],
+            formats => formats,
+        };
+        let resources: Vec<RelatedResource> = self
+            .related_resources
+            .iter()
+            .map(|arg| {
+                let (url, file) = url_file(arg);
+                let data = match file {
+                    Some(file) => std::fs::read(file).unwrap(),
+                    None => rt.block_on(integrity::fetch(url)).unwrap(),
+                };
+                RelatedResource::with_digests(url, &data, formats)
+            })
+            .collect();
+        integrity::embed_related_resources(document, &resources).unwrap();
+    }
 }
 
 #[derive(StructOpt, Debug)]
@@ -231,6 +686,236 @@ impl From<ProofOptions> for LinkedDataProofOptions {
     }
 }
 
+impl ProofOptions {
+    /// Properties to add to a Data Integrity proof: expires, nonce and custom properties.
+    fn extra_proof_properties(&self) -> Option<Map<String, Value>> {
+        let mut builder = ProofOptionsBuilder::new().proof_format(self.proof_format.clone());
+        if let Some(created) = self.created {
+            builder = builder.created(created);
+        }
+        if let Some(expires) = self.expires {
+            builder = builder.expires(expires);
+        }
+        if let Some(ref nonce) = self.nonce {
+            builder = builder.nonce(nonce.clone());
+        }
+        for arg in &self.proof_properties {
+            let (name, value) = match url_file(arg) {
+                (name, Some(value)) if !name.is_empty() => (name, value),
+                _ => {
+                    eprintln!(
+                        "didkit: Invalid proof property (expected <name>=<value>): {}",
+                        arg
+                    );
+                    std::process::exit(1);
+                }
+            };
+            let value = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+            builder = builder.property(name, value);
+        }
+        match builder.build() {
+            Ok(options) => options.extra_proof_properties,
+            Err(err) => {
+                eprintln!("didkit: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Generate a linked data proof, with additional proof properties if any: with a JWK, through
+/// its [`Signer`](didkit::Signer), or with ssh-agent. Additional properties need a JWK, as the
+/// ssh-agent signer does not support them.
+async fn generate_proof_with_properties(
+    document: &(dyn ssi::ldp::LinkedDataDocument + Sync),
+    key: Option<&JWK>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    ssh_agent_sock_opt: Option<&str>,
+    extra_proof_properties: Option<Map<String, Value>>,
+) -> Result<ssi::vc::Proof, String> {
+    match (extra_proof_properties, key, ssh_agent_sock_opt) {
+        (properties, Some(jwk), None) => {
+            didkit::signer::generate_proof(document, &options, resolver, jwk, properties)
+                .await
+                .map_err(|err| err.to_string())
+        }
+        (None, _, Some(_)) => generate_proof(document, key, options, resolver, ssh_agent_sock_opt)
+            .await
+            .map_err(|err| err.to_string()),
+        (None, None, None) => Err("A JWK (-k or -j) or ssh-agent is required".to_string()),
+        (Some(_), _, _) => Err("Proof properties require a JWK (-k or -j)".to_string()),
+    }
+}
+
+impl From<VerifyOptions> for VerificationOptions {
+    fn from(options: VerifyOptions) -> VerificationOptions {
+        VerificationOptions {
+            allowed_did_methods: options.allowed_did_methods,
+            clock_skew: options.clock_skew,
+            verify_as_of: options.verify_as_of,
+            missing_expiration: options.missing_expiration,
+            require_holder_binding: options.require_holder_binding,
+            expected_holder: options.expected_holder,
+            cacao: options.cacao.map(|path| {
+                let cacao_file = File::open(path).unwrap();
+                serde_json::from_reader(BufReader::new(cacao_file)).unwrap()
+            }),
+            verify_credentials: options.verify_credentials,
+            credential_parallelism: options.credential_parallelism,
+            trusted_certificates: options
+                .trusted_certificates
+                .map(|path| vec![std::fs::read_to_string(path).unwrap()]),
+            check_context_integrity: options.check_context_integrity,
+            context_documents: read_documents(&options.context_documents),
+            check_related_resources: options.check_related_resources,
+            check_credential_schema: options.check_credential_schema,
+            schema_documents: read_documents(&options.schema_documents),
+            check_credential_types: options.check_credential_types,
+            expected_subject: options.expected_subject,
+            expected_challenge: options.expected_challenge,
+            expected_domain: options.expected_domain,
+            historical_resolution: options.historical_resolution,
+        }
+    }
+}
+
+/// Read documents given as <url>=<file> arguments, by URL.
+fn read_documents(args: &[String]) -> Option<HashMap<String, String>> {
+    if args.is_empty() {
+        return None;
+    }
+    let documents = args.iter().map(|arg| match url_file(arg) {
+        (url, Some(file)) => (url.to_string(), std::fs::read_to_string(file).unwrap()),
+        (url, None) => panic!("Expected <url>=<file>: {}", url),
+    });
+    Some(documents.collect())
+}
+
+/// Options of a dry run of issuance, signing with a placeholder signature.
+fn dry_run_options(
+    ldp_options: LinkedDataProofOptions,
+    proof_format: ProofFormat,
+    extra_proof_properties: Option<Map<String, Value>>,
+    jwt_claims: Option<JWTClaimsOptions>,
+) -> IssueOptions {
+    IssueOptions {
+        ldp_options,
+        proof_format,
+        extra_proof_properties,
+        jwt_claims: jwt_claims.unwrap_or_default(),
+        dry_run: true,
+        ..Default::default()
+    }
+}
+
+/// Key that a dry run stands in for. Its public key is enough.
+fn dry_run_key(jwk: Option<&JWK>) -> &JWK {
+    jwk.unwrap_or_else(|| {
+        eprintln!("didkit: --dry-run requires a key, with --key-path or --jwk");
+        std::process::exit(1);
+    })
+}
+
+/// The key to secure a credential or presentation with, in a proof format which, unlike linked
+/// data proofs, cannot be signed with an ssh-agent key. Exits if the key is not given.
+fn signing_key<'a>(jwk: Option<&'a JWK>, ssh_agent: bool, proof_format: &ProofFormat) -> &'a JWK {
+    if ssh_agent {
+        eprintln!(
+            "didkit: ssh-agent keys are not supported with the {} proof format",
+            proof_format
+        );
+        std::process::exit(1);
+    }
+    jwk.unwrap_or_else(|| {
+        eprintln!(
+            "didkit: The {} proof format requires a key, with --key-path or --jwk",
+            proof_format
+        );
+        std::process::exit(1);
+    })
+}
+
+/// Exit if deterministic issuance is requested with a key whose signatures are randomized.
+fn check_deterministic(deterministic: bool, jwk: Option<&JWK>) {
+    if !deterministic {
+        return;
+    }
+    match jwk.and_then(|jwk| jwk.get_algorithm()) {
+        Some(algorithm) if didkit::signer::is_deterministic(algorithm) => {}
+        _ => {
+            eprintln!(
+                "Deterministic issuance requires a key with a deterministic signature algorithm"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Set the verification method of the proof options, if not given, to the one of the DID's
+/// document that matches the key, or check that the given one matches the key. Exits on mismatch.
+fn discover_verification_method(
+    options: &mut LinkedDataProofOptions,
+    did: Option<String>,
+    default_purpose: ProofPurpose,
+    jwk: Option<&JWK>,
+    resolver: &dyn DIDResolver,
+    rt: &tokio::runtime::Runtime,
+) {
+    let jwk = match jwk {
+        Some(jwk) => jwk,
+        None => return,
+    };
+    let purpose = options.proof_purpose.clone().unwrap_or(default_purpose);
+    let result = match (&options.verification_method, did) {
+        (Some(vm), _) => rt.block_on(check_verification_method(
+            &vm.to_string(),
+            jwk,
+            &purpose,
+            resolver,
+        )),
+        (None, Some(did)) if did.starts_with("did:") => rt
+            .block_on(find_verification_method(&did, jwk, &purpose, resolver))
+            .map(|vm| options.verification_method = Some(URI::String(vm))),
+        (None, _) => Ok(()),
+    };
+    if let Err(err) = result {
+        eprintln!("didkit: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Verification result to output: the vc-http-api result, with the DID resolutions used.
+/// Read a credential from stdin, as a JWT or VC-JOSE/VC-COSE document, or as JSON.
+fn read_credential(proof_format: &ProofFormat) -> CredentialOrJWT {
+    let mut credential_reader = BufReader::new(stdin());
+    match proof_format {
+        ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
+            let mut jwt = String::new();
+            credential_reader.read_to_string(&mut jwt).unwrap();
+            CredentialOrJWT::JWT(jwt.trim().to_string())
+        }
+        ProofFormat::LDP => {
+            let credential: VerifiableCredential =
+                serde_json::from_reader(credential_reader).unwrap();
+            credential.validate_unsigned().unwrap();
+            CredentialOrJWT::Credential(credential)
+        }
+        _ => {
+            panic!("Unknown proof format: {:?}", proof_format);
+        }
+    }
+}
+
+fn verification_output(report: VerificationReport) -> Value {
+    let resolutions = report.resolutions.clone();
+    let mut output = serde_json::to_value(VerificationResult::from(report)).unwrap();
+    if !resolutions.is_empty() {
+        output["resolutions"] = serde_json::to_value(&resolutions).unwrap();
+    }
+    output
+}
+
 #[derive(Debug, Serialize)]
 /// Subset of [DID Metadata Structure][metadata] that is just a string property name and string value.
 /// [metadata]: https://w3c.github.io/did-core/#metadata-structure
@@ -333,14 +1018,19 @@ set. For more info, see the manual for ssh-agent(1) and ssh-add(1).
 
 fn main() {
     let rt = runtime::get().unwrap();
-    let opt = DIDKit::from_args();
+    let opts = DIDKitOpts::from_args();
+    output::set_canonical(opts.canonical_json);
     let ssh_agent_sock;
 
-    match opt {
+    match opts.command {
         DIDKit::GenerateEd25519Key => {
             let jwk = JWK::generate_ed25519().unwrap();
-            let jwk_str = serde_json::to_string(&jwk).unwrap();
-            println!("{}", jwk_str);
+            println!("{}", output::to_json_string(&jwk));
+        }
+
+        DIDKit::GenerateBbsKey => {
+            let jwk = didkit::jwp::generate_bbs_key().unwrap();
+            println!("{}", output::to_json_string(&jwk));
         }
 
         DIDKit::KeyToDIDKey { key } => {
@@ -368,8 +1058,7 @@ fn main() {
 
         DIDKit::SshPkToJwk { ssh_pk } => {
             let jwk = ssi::ssh::ssh_pkk_to_jwk(&ssh_pk.kind).unwrap();
-            let stdout_writer = BufWriter::new(stdout());
-            serde_json::to_writer_pretty(stdout_writer, &jwk).unwrap();
+            output::write_json_pretty(&jwk);
         }
 
         DIDKit::KeyToVerificationMethod {
@@ -402,43 +1091,155 @@ fn main() {
             key,
             resolver_options,
             proof_options,
+            related_resources,
+            content_id: use_content_id,
+            dry_run,
         } => {
             let resolver = resolver_options.to_resolver();
             let credential_reader = BufReader::new(stdin());
-            let mut credential: VerifiableCredential =
-                serde_json::from_reader(credential_reader).unwrap();
+            let mut credential: Value = serde_json::from_reader(credential_reader).unwrap();
+            related_resources.embed(&mut credential, &rt);
+            let mut credential: VerifiableCredential = serde_json::from_value(credential).unwrap();
+            if use_content_id {
+                if let Err(err) = rt.block_on(content_id::set_content_id(&mut credential)) {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            }
             let proof_format = proof_options.proof_format.clone();
             let jwk_opt: Option<JWK> = key.get_jwk_opt();
+            check_deterministic(proof_options.deterministic, jwk_opt.as_ref());
             let ssh_agent_sock_opt = if key.ssh_agent {
                 ssh_agent_sock = get_ssh_agent_sock();
                 Some(&ssh_agent_sock[..])
             } else {
                 None
             };
-            let options = LinkedDataProofOptions::from(proof_options);
+            let extra_proof_properties = proof_options.extra_proof_properties();
+            let claim_mapping = proof_options.jwt_claims.options();
+            let mut options = LinkedDataProofOptions::from(proof_options);
+            let issuer = serde_json::to_value(&credential.issuer).ok();
+            let issuer = issuer
+                .as_ref()
+                .and_then(|issuer| issuer.as_str().or_else(|| issuer["id"].as_str()));
+            discover_verification_method(
+                &mut options,
+                issuer.map(|issuer| issuer.to_string()),
+                ProofPurpose::AssertionMethod,
+                jwk_opt.as_ref(),
+                &resolver,
+                &rt,
+            );
+            if dry_run {
+                let jwk = dry_run_key(jwk_opt.as_ref());
+                let options =
+                    dry_run_options(options, proof_format, extra_proof_properties, claim_mapping);
+                let vc = rt.block_on(didkit::issue_credential(
+                    credential, &options, jwk, &resolver,
+                ));
+                match vc {
+                    Ok(CredentialOrJWT::Credential(vc)) => output::write_json(&vc),
+                    Ok(CredentialOrJWT::JWT(jwt)) => print!("{}", jwt),
+                    Err(err) => {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
             match proof_format {
                 ProofFormat::JWT => {
-                    if ssh_agent_sock_opt.is_some() {
-                        todo!("ssh-agent for JWT not implemented");
-                    }
-                    let jwt = rt
-                        .block_on(credential.generate_jwt(jwk_opt.as_ref(), &options, &resolver))
-                        .unwrap();
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
+                    let jwt = match claim_mapping {
+                        Some(claim_mapping) => {
+                            let credential = serde_json::to_value(&credential).unwrap();
+                            let claims = jwt_claims::encode_credential(&credential, &claim_mapping)
+                                .unwrap_or_else(|err| {
+                                    eprintln!("didkit: {}", err);
+                                    std::process::exit(1);
+                                });
+                            rt.block_on(didkit::signer::sign_jwt_with_header(
+                                &claims,
+                                &options,
+                                &Map::new(),
+                                jwk,
+                            ))
+                            .map_err(|err| err.to_string())
+                        }
+                        None => rt
+                            .block_on(credential.generate_jwt(Some(jwk), &options, &resolver))
+                            .map_err(|err| err.to_string()),
+                    };
+                    let jwt = jwt.unwrap_or_else(|err| {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    });
                     print!("{}", jwt);
                 }
+                ProofFormat::VcJose | ProofFormat::VcCose => {
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
+                    let secured = rt
+                        .block_on(securing::secure_credential(
+                            &credential,
+                            &proof_format,
+                            &options,
+                            jwk,
+                        ))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
+                    print!("{}", secured);
+                }
                 ProofFormat::LDP => {
                     let proof = rt
-                        .block_on(generate_proof(
+                        .block_on(generate_proof_with_properties(
                             &credential,
                             jwk_opt.as_ref(),
                             options,
                             &resolver,
                             ssh_agent_sock_opt,
+                            extra_proof_properties,
                         ))
-                        .unwrap();
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
                     credential.add_proof(proof);
-                    let stdout_writer = BufWriter::new(stdout());
-                    serde_json::to_writer(stdout_writer, &credential).unwrap();
+                    output::write_json(&credential);
+                }
+                ProofFormat::JPT => {
+                    let jwk = jwk_opt.as_ref().unwrap_or_else(|| {
+                        eprintln!(
+                            "didkit: JPT issuance requires a BLS12-381 key (--key-path or --jwk)"
+                        );
+                        std::process::exit(1);
+                    });
+                    let options = IssueOptions {
+                        ldp_options: options,
+                        proof_format,
+                        ..Default::default()
+                    };
+                    let jpt = rt
+                        .block_on(didkit::issue_credential(
+                            credential, &options, jwk, &resolver,
+                        ))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
+                    match jpt {
+                        CredentialOrJWT::JWT(jpt) => print!("{}", jpt),
+                        CredentialOrJWT::Credential(vc) => output::write_json(&vc),
+                    }
                 }
                 _ => {
                     panic!("Unknown proof format: {:?}", proof_format);
@@ -448,36 +1249,78 @@ fn main() {
 
         DIDKit::VCVerifyCredential {
             proof_options,
+            verify_options,
             resolver_options,
+            decode,
         } => {
             let resolver = resolver_options.to_resolver();
-            let mut credential_reader = BufReader::new(stdin());
             let proof_format = proof_options.proof_format.clone();
-            let options = LinkedDataProofOptions::from(proof_options);
-            let result = match proof_format {
-                ProofFormat::JWT => {
-                    let mut jwt = String::new();
-                    credential_reader.read_to_string(&mut jwt).unwrap();
-                    rt.block_on(VerifiableCredential::verify_jwt(
-                        &jwt,
-                        Some(options),
-                        &resolver,
-                    ))
-                }
-                ProofFormat::LDP => {
-                    let credential: VerifiableCredential =
-                        serde_json::from_reader(credential_reader).unwrap();
-                    credential.validate_unsigned().unwrap();
-                    rt.block_on(credential.verify(Some(options), &resolver))
-                }
-                _ => {
-                    panic!("Unknown proof format: {:?}", proof_format);
-                }
-            };
+            let credential = read_credential(&proof_format);
+            let mut options = JWTOrLDPOptions::default();
+            options.proof_format = Some(proof_format);
+            options.jwt_claims = proof_options.jwt_claims.options();
+            options.ldp_options = LinkedDataProofOptions::from(proof_options);
+            let profile = verify_options.profile();
+            options.verification_options = VerificationOptions::from(verify_options);
+            let report = rt
+                .block_on(profile.verify_credential(&credential, &options, &resolver))
+                .unwrap_or_else(|err| {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                });
+            let success = report.is_success();
+
+            let mut output = verification_output(report);
+            if decode && success {
+                let jwt_claims = options.jwt_claims.unwrap_or_default();
+                let decoded =
+                    convert::decode_credential(&credential, &jwt_claims).unwrap_or_else(|err| {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    });
+                output["decoded"] = serde_json::to_value(decoded).unwrap();
+            }
+            output::write_json(&output);
+            if !success {
+                std::process::exit(2);
+            }
+        }
+
+        DIDKit::VCQuery {
+            queries,
+            proof_options,
+            verify_options,
+            resolver_options,
+        } => {
+            let resolver = resolver_options.to_resolver();
+            let proof_format = proof_options.proof_format.clone();
+            let credential = read_credential(&proof_format);
+            let mut options = JWTOrLDPOptions::default();
+            options.proof_format = Some(proof_format);
+            options.jwt_claims = proof_options.jwt_claims.options();
+            options.ldp_options = LinkedDataProofOptions::from(proof_options);
+            let profile = verify_options.profile();
+            options.verification_options = VerificationOptions::from(verify_options);
+            let report = rt
+                .block_on(profile.verify_credential(&credential, &options, &resolver))
+                .unwrap_or_else(|err| {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                });
+            let success = report.is_success();
 
-            let stdout_writer = BufWriter::new(stdout());
-            serde_json::to_writer(stdout_writer, &result).unwrap();
-            if result.errors.len() > 0 {
+            let mut output = verification_output(report);
+            if success {
+                let jwt_claims = options.jwt_claims.unwrap_or_default();
+                let results = query::query_decoded_credential(&credential, &queries, &jwt_claims)
+                    .unwrap_or_else(|err| {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    });
+                output["claims"] = serde_json::to_value(results).unwrap();
+            }
+            output::write_json(&output);
+            if !success {
                 std::process::exit(2);
             }
         }
@@ -486,13 +1329,18 @@ fn main() {
             key,
             resolver_options,
             proof_options,
+            related_resources,
+            dry_run,
         } => {
             let resolver = resolver_options.to_resolver();
             let presentation_reader = BufReader::new(stdin());
+            let mut presentation: Value = serde_json::from_reader(presentation_reader).unwrap();
+            related_resources.embed(&mut presentation, &rt);
             let mut presentation: VerifiablePresentation =
-                serde_json::from_reader(presentation_reader).unwrap();
+                serde_json::from_value(presentation).unwrap();
 
             let jwk_opt: Option<JWK> = key.get_jwk_opt();
+            check_deterministic(proof_options.deterministic, jwk_opt.as_ref());
             let ssh_agent_sock_opt = if key.ssh_agent {
                 ssh_agent_sock = get_ssh_agent_sock();
                 Some(&ssh_agent_sock[..])
@@ -500,30 +1348,90 @@ fn main() {
                 None
             };
             let proof_format = proof_options.proof_format.clone();
-            let options = LinkedDataProofOptions::from(proof_options);
+            let extra_proof_properties = proof_options.extra_proof_properties();
+            let mut options = LinkedDataProofOptions::from(proof_options);
+            let holder = presentation.holder.as_ref().map(ToString::to_string);
+            discover_verification_method(
+                &mut options,
+                holder,
+                ProofPurpose::Authentication,
+                jwk_opt.as_ref(),
+                &resolver,
+                &rt,
+            );
+            if dry_run {
+                let jwk = dry_run_key(jwk_opt.as_ref());
+                let options = dry_run_options(options, proof_format, extra_proof_properties, None);
+                let vp = rt.block_on(didkit::issue_presentation(
+                    presentation,
+                    &options,
+                    jwk,
+                    &resolver,
+                ));
+                match vp {
+                    Ok(PresentationOrJWT::VP(vp)) => output::write_json(&vp),
+                    Ok(PresentationOrJWT::JWT(jwt)) => print!("{}", jwt),
+                    Err(err) => {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
             match proof_format {
                 ProofFormat::JWT => {
-                    if ssh_agent_sock_opt.is_some() {
-                        todo!("ssh-agent for JWT not implemented");
-                    }
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
                     let jwt = rt
-                        .block_on(presentation.generate_jwt(jwk_opt.as_ref(), &options, &resolver))
-                        .unwrap();
+                        .block_on(presentation.generate_jwt(Some(jwk), &options, &resolver))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
                     print!("{}", jwt);
                 }
+                ProofFormat::VcJose | ProofFormat::VcCose => {
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
+                    let secured = rt
+                        .block_on(securing::secure_presentation(
+                            &presentation,
+                            &proof_format,
+                            &options,
+                            jwk,
+                        ))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
+                    print!("{}", secured);
+                }
                 ProofFormat::LDP => {
                     let proof = rt
-                        .block_on(generate_proof(
+                        .block_on(generate_proof_with_properties(
                             &presentation,
                             jwk_opt.as_ref(),
                             options,
                             &resolver,
                             ssh_agent_sock_opt,
+                            extra_proof_properties,
                         ))
-                        .unwrap();
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
                     presentation.add_proof(proof);
-                    let stdout_writer = BufWriter::new(stdout());
-                    serde_json::to_writer(stdout_writer, &presentation).unwrap();
+                    output::write_json(&presentation);
+                }
+                ProofFormat::JPT => {
+                    eprintln!("didkit: JPT presentations are derived from a JPT credential");
+                    std::process::exit(1);
                 }
                 _ => {
                     panic!("Unexpected proof format: {:?}", proof_format);
@@ -533,39 +1441,378 @@ fn main() {
 
         DIDKit::VCVerifyPresentation {
             proof_options,
+            verify_options,
             resolver_options,
+            attest,
+            verifier,
+            decode,
         } => {
             let resolver = resolver_options.to_resolver();
             let mut presentation_reader = BufReader::new(stdin());
             let proof_format = proof_options.proof_format.clone();
-            let options = LinkedDataProofOptions::from(proof_options);
-            let result = match proof_format {
-                ProofFormat::JWT => {
+            let presentation = match proof_format {
+                ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
                     let mut jwt = String::new();
                     presentation_reader.read_to_string(&mut jwt).unwrap();
-                    rt.block_on(VerifiablePresentation::verify_jwt(
-                        &jwt,
-                        Some(options),
-                        &resolver,
-                    ))
+                    PresentationOrJWT::JWT(jwt.trim().to_string())
                 }
                 ProofFormat::LDP => {
                     let presentation: VerifiablePresentation =
                         serde_json::from_reader(presentation_reader).unwrap();
                     presentation.validate_unsigned().unwrap();
-                    rt.block_on(presentation.verify(Some(options), &resolver))
+                    PresentationOrJWT::VP(presentation)
                 }
                 _ => {
                     panic!("Unexpected proof format: {:?}", proof_format);
                 }
             };
-            let stdout_writer = BufWriter::new(stdout());
-            serde_json::to_writer(stdout_writer, &result).unwrap();
-            if result.errors.len() > 0 {
+            let mut options = JWTOrLDPOptions::default();
+            options.proof_format = Some(proof_format);
+            options.ldp_options = LinkedDataProofOptions::from(proof_options);
+            let profile = verify_options.profile();
+            options.verification_options = VerificationOptions::from(verify_options);
+            let report = rt
+                .block_on(profile.verify_presentation(&presentation, &options, &resolver))
+                .unwrap_or_else(|err| {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                });
+            if let Some(path) = attest {
+                let key_reader = BufReader::new(File::open(path).unwrap());
+                let jwk: JWK = serde_json::from_reader(key_reader).unwrap();
+                let verifier = verifier.unwrap_or_else(|| {
+                    DIDKey
+                        .generate(&Source::Key(&jwk))
+                        .ok_or(Error::UnableToGenerateDID)
+                        .unwrap()
+                });
+                let verification_method = rt
+                    .block_on(get_verification_method(&verifier, &resolver))
+                    .ok_or(Error::UnableToGetVerificationMethod)
+                    .unwrap();
+                let issue_options = ProofOptionsBuilder::new()
+                    .verification_method(verification_method)
+                    .build()
+                    .unwrap();
+                let attestation = rt
+                    .block_on(attestation::attest_presentation(
+                        &presentation,
+                        &report,
+                        &verifier,
+                        &issue_options,
+                        &jwk,
+                        &resolver,
+                    ))
+                    .unwrap();
+                output::write_json(&attestation);
+                if !report.is_success() {
+                    std::process::exit(2);
+                }
+                return;
+            }
+            let success = report.is_success();
+            let mut output = verification_output(report);
+            if decode && success {
+                let jwt_claims = JWTClaimsOptions::default();
+                output["decoded"] = convert::decode_presentation(&presentation, &jwt_claims)
+                    .unwrap_or_else(|err| {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    });
+            }
+            output::write_json(&output);
+            if !success {
                 std::process::exit(2);
             }
         }
 
+        DIDKit::VCEncrypt {
+            recipient,
+            resolver_options,
+        } => {
+            let resolver = resolver_options.to_resolver();
+            let mut document = String::new();
+            stdin().read_to_string(&mut document).unwrap();
+            let document = document.trim();
+            let cty = if document.starts_with('{') {
+                jwe::CTY_LD_JSON
+            } else {
+                jwe::CTY_JWT
+            };
+            let jwe = rt
+                .block_on(jwe::encrypt_to_did(
+                    document.as_bytes(),
+                    &recipient,
+                    Some(cty.to_string()),
+                    &resolver,
+                ))
+                .unwrap();
+            print!("{}", jwe);
+        }
+
+        DIDKit::VCDecrypt { key } => {
+            let jwk = key.get_jwk();
+            let mut jwe = String::new();
+            stdin().read_to_string(&mut jwe).unwrap();
+            let (_header, document) = jwe::decrypt(&jwe, &jwk).unwrap();
+            stdout().write_all(&document).unwrap();
+        }
+
+        DIDKit::VCRedact {
+            reveal,
+            mandatory,
+            nonce,
+            audience,
+            resolver_options,
+        } => {
+            let mut credential = String::new();
+            stdin().read_to_string(&mut credential).unwrap();
+            let options = redact::RedactOptions { reveal, mandatory };
+            let redacted = if didkit::jwp::is_jpt(credential.trim()) {
+                let resolver = resolver_options.to_resolver();
+                let presentation_header = didkit::jwp::PresentationHeader {
+                    nonce,
+                    aud: audience,
+                    ..Default::default()
+                };
+                rt.block_on(redact::redact_jpt_credential(
+                    &credential,
+                    &options,
+                    presentation_header,
+                    &resolver,
+                ))
+            } else {
+                redact::redact(&credential, &options)
+            };
+            let redacted = redacted.unwrap_or_else(|err| {
+                eprintln!("didkit: {}", err);
+                std::process::exit(1);
+            });
+            println!("{}", redacted);
+        }
+
+        DIDKit::VCConvert {
+            to,
+            verification_method,
+            key,
+            resolver_options,
+            jwt_claims,
+        } => {
+            let resolver = resolver_options.to_resolver();
+            let jwk = key.get_jwk();
+            let mut input = String::new();
+            stdin().read_to_string(&mut input).unwrap();
+            let credential = match serde_json::from_str(&input) {
+                Ok(credential) => CredentialOrJWT::Credential(credential),
+                Err(_) => CredentialOrJWT::JWT(input.trim().to_string()),
+            };
+            let mut options = IssueOptions::default();
+            options.ldp_options.verification_method = verification_method;
+            options.jwt_claims = jwt_claims.options().unwrap_or_default();
+            let conversion = rt.block_on(convert::convert_credential(
+                &credential,
+                to,
+                &options,
+                &jwk,
+                &resolver,
+            ));
+            let conversion = match conversion {
+                Ok(conversion) => conversion,
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            };
+            for warning in &conversion.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            match conversion.credential {
+                CredentialOrJWT::JWT(jwt) => print!("{}", jwt),
+                CredentialOrJWT::Credential(vc) => {
+                    output::write_json(&vc);
+                }
+            }
+        }
+
+        DIDKit::VCRender {
+            format,
+            manifest,
+            descriptor,
+        } => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input).unwrap();
+            let rendering = render::parse_credential(&input).and_then(|credential| {
+                let manifest: Option<CredentialManifest> = match manifest {
+                    Some(path) => {
+                        let file = File::open(path).unwrap();
+                        Some(serde_json::from_reader(BufReader::new(file))?)
+                    }
+                    None => None,
+                };
+                match manifest {
+                    Some(ref manifest) => {
+                        let descriptor = render::select_descriptor(
+                            manifest,
+                            &credential,
+                            descriptor.as_deref(),
+                        )?;
+                        render::render(
+                            &credential,
+                            descriptor.display.as_ref(),
+                            descriptor.styles.as_ref(),
+                        )
+                    }
+                    None => {
+                        let hints = render::render_property_hints(&credential)?;
+                        render::render(&credential, hints.as_ref(), None)
+                    }
+                }
+            });
+            match rendering {
+                Ok(rendering) => print!("{}", rendering.format(format)),
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        DIDKit::VCContentId { hash } => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input).unwrap();
+            let credential = serde_json::from_str(&input)
+                .unwrap_or_else(|_| Value::String(input.trim().to_string()));
+            match rt.block_on(content_id::content_hash_hex(&credential)) {
+                Ok(content_hash) if hash => println!("{}", content_hash),
+                Ok(content_hash) => println!("{}{}", content_id::URN_PREFIX, content_hash),
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        DIDKit::VCToBarcode {
+            format,
+            profile,
+            text,
+        } => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input).unwrap();
+            let credential = serde_json::from_str(&input)
+                .unwrap_or_else(|_| Value::String(input.trim().to_string()));
+            let barcode_text = barcode::profile(&profile)
+                .and_then(|profile| barcode::encode(&credential, profile, format))
+                .map_err(|err| err.to_string());
+            let output = match barcode_text {
+                Ok(barcode_text) if text => Ok(barcode_text),
+                Ok(barcode_text) => render_svg(&barcode_text, format),
+                Err(err) => Err(err),
+            };
+            match output {
+                Ok(output) => println!("{}", output),
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        DIDKit::BarcodeToVC { format, image } => {
+            let text = match image {
+                Some(path) => scan(&path, format).unwrap_or_else(|err| {
+                    eprintln!("didkit: {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }),
+                None => {
+                    let mut text = String::new();
+                    stdin().read_to_string(&mut text).unwrap();
+                    text
+                }
+            };
+            let credential = barcode::decode(&text).unwrap_or_else(|err| {
+                eprintln!("didkit: {}", err);
+                std::process::exit(1);
+            });
+            output::write_json(&credential);
+        }
+
+        DIDKit::CacaoSign { key, presentation } => {
+            let jwk = key.get_jwk();
+            let mut message = String::new();
+            stdin().read_to_string(&mut message).unwrap();
+            let mut message = SiweMessage::from_str(&message).unwrap();
+            if let Some(path) = presentation {
+                let presentation_file = File::open(path).unwrap();
+                let presentation: Value =
+                    serde_json::from_reader(BufReader::new(presentation_file)).unwrap();
+                message
+                    .resources
+                    .push(cacao::presentation_resource(&presentation));
+            }
+            let cacao = Cacao::sign(&message, &jwk).unwrap();
+            output::write_json(&cacao);
+        }
+
+        DIDKit::CacaoVerify {} => {
+            let cacao: Cacao = serde_json::from_reader(BufReader::new(stdin())).unwrap();
+            let mut result = VerificationResult::new();
+            match cacao.verify(Utc::now()) {
+                Ok(()) => result.checks.push(ssi::vc::Check::Proof),
+                Err(err) => result.errors.push(err.to_string()),
+            }
+            output::write_json(&result);
+            if !result.errors.is_empty() {
+                std::process::exit(2);
+            }
+        }
+
+        DIDKit::Key(KeyCmd::ToMultibase { key }) => {
+            let jwk = key.get_jwk();
+            println!("{}", multikey::to_multibase(&jwk).unwrap());
+        }
+        DIDKit::Key(KeyCmd::FromMultibase { multibase }) => {
+            let jwk = multikey::from_multibase(&multibase).unwrap();
+            println!("{}", output::to_json_string(&jwk));
+        }
+        DIDKit::Key(KeyCmd::Pairwise {
+            seed_path,
+            relationship,
+            method,
+            jwk,
+        }) => {
+            let seed = std::fs::read(&seed_path).unwrap();
+            let pairwise_did = match pairwise::derive_did(&seed, &relationship, &method) {
+                Ok(pairwise_did) => pairwise_did,
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                }
+            };
+            if jwk {
+                println!("{}", output::to_json_string(&pairwise_did.key));
+            } else {
+                println!("{}", pairwise_did.did);
+            }
+        }
+
+        DIDKit::DIDPublish(cmd) => cmd.run(),
+        DIDKit::DIDWebVH(cmd) => cmd.run(),
+        DIDKit::HDDID(cmd) => cmd.run(),
+        DIDKit::Edv(cmd) => cmd.run(),
+        DIDKit::VcApi(cmd) => cmd.run(),
+        DIDKit::Oidc4vci(cmd) => cmd.run(),
+        DIDKit::Oidc4vp(cmd) => cmd.run(),
+        DIDKit::VcAudit(cmd) => cmd.run(),
+        DIDKit::TestVectors(cmd) => cmd.run(),
+        DIDKit::DIDComm(cmd) => cmd.run(),
+        #[cfg(unix)]
+        DIDKit::Agent(cmd) => cmd.run(),
+        DIDKit::Wallet(cmd) => cmd.run(),
+        DIDKit::Repl(cmd) => cmd.run(DIDKitOpts::clap()),
+        #[cfg(feature = "store")]
+        DIDKit::Store(cmd) => cmd.run(),
+
         DIDKit::ToRdfURDNA2015 {
             base,
             expand_context,
@@ -606,8 +1853,9 @@ fn main() {
             let res_input_meta: ResolutionInputMetadata =
                 serde_json::from_value(res_input_meta_value).unwrap();
             if with_metadata {
-                let (res_meta, doc_opt, doc_meta_opt) =
+                let (mut res_meta, doc_opt, doc_meta_opt) =
                     rt.block_on(resolver.resolve(&did, &res_input_meta));
+                resolution_error::normalize_resolution_metadata(&mut res_meta);
                 let error = res_meta.error.is_some();
                 let result = ResolutionResult {
                     did_document: doc_opt,
@@ -615,16 +1863,19 @@ fn main() {
                     did_document_metadata: doc_meta_opt,
                     ..Default::default()
                 };
-                let stdout_writer = BufWriter::new(stdout());
-                serde_json::to_writer_pretty(stdout_writer, &result).unwrap();
+                output::write_json_pretty(&result);
                 if error {
                     std::process::exit(2);
                 }
             } else {
-                let (res_meta, doc_data, _doc_meta_opt) =
+                let (mut res_meta, doc_data, _doc_meta_opt) =
                     rt.block_on(resolver.resolve_representation(&did, &res_input_meta));
-                if let Some(err) = res_meta.error {
-                    eprintln!("{}", err);
+                resolution_error::normalize_resolution_metadata(&mut res_meta);
+                if let Some(ref err) = res_meta.error {
+                    eprintln!(
+                        "{}",
+                        resolution_error::describe(err, &res_meta.property_set)
+                    );
                     std::process::exit(2);
                 }
                 stdout().write_all(&doc_data).unwrap();
@@ -641,19 +1892,22 @@ fn main() {
             let deref_input_meta_value = metadata_properties_to_value(input_metadata).unwrap();
             let deref_input_meta: DereferencingInputMetadata =
                 serde_json::from_value(deref_input_meta_value).unwrap();
-            let stdout_writer = BufWriter::new(stdout());
-            let (deref_meta, content, content_meta) =
+            let (mut deref_meta, content, content_meta) =
                 rt.block_on(dereference(&resolver, &did_url, &deref_input_meta));
+            resolution_error::normalize_dereferencing_metadata(&mut deref_meta);
             if with_metadata {
                 use serde_json::json;
                 let result = json!([deref_meta, content, content_meta]);
-                serde_json::to_writer_pretty(stdout_writer, &result).unwrap();
+                output::write_json_pretty(&result);
                 if deref_meta.error.is_some() {
                     std::process::exit(2);
                 }
             } else {
-                if let Some(err) = deref_meta.error {
-                    eprintln!("{}", err);
+                if let Some(ref err) = deref_meta.error {
+                    eprintln!(
+                        "{}",
+                        resolution_error::describe(err, &deref_meta.property_set)
+                    );
                     std::process::exit(2);
                 }
                 let content_vec = content.into_vec().unwrap();
@@ -661,6 +1915,43 @@ fn main() {
             }
         }
 
+        DIDKit::DIDLint {
+            did,
+            resolver_options,
+        } => {
+            let document = match did {
+                Some(did) => {
+                    let resolver = resolver_options.to_resolver();
+                    let input_metadata = ResolutionInputMetadata::default();
+                    let (mut res_meta, representation, _) =
+                        rt.block_on(resolver.resolve_representation(&did, &input_metadata));
+                    resolution_error::normalize_resolution_metadata(&mut res_meta);
+                    if let Some(ref err) = res_meta.error {
+                        let err = resolution_error::describe(err, &res_meta.property_set);
+                        eprintln!("didkit: Unable to resolve {}: {}", did, err);
+                        std::process::exit(1);
+                    }
+                    representation
+                }
+                None => {
+                    let mut document = Vec::new();
+                    stdin().read_to_end(&mut document).unwrap();
+                    document
+                }
+            };
+            let report = match serde_json::from_slice(&document) {
+                Ok(document) => did_lint::lint(&document),
+                Err(err) => {
+                    eprintln!("didkit: DID document is not valid JSON: {}", err);
+                    std::process::exit(2);
+                }
+            };
+            output::write_json_pretty(&report);
+            if !report.is_valid() {
+                std::process::exit(2);
+            }
+        }
+
         DIDKit::DIDAuth {
             key,
             holder,
@@ -672,36 +1963,78 @@ fn main() {
             presentation.holder = Some(ssi::vc::URI::String(holder));
             let proof_format = proof_options.proof_format.clone();
             let jwk_opt: Option<JWK> = key.get_jwk_opt();
+            check_deterministic(proof_options.deterministic, jwk_opt.as_ref());
             let ssh_agent_sock_opt = if key.ssh_agent {
                 ssh_agent_sock = get_ssh_agent_sock();
                 Some(&ssh_agent_sock[..])
             } else {
                 None
             };
-            let options = LinkedDataProofOptions::from(proof_options);
+            let extra_proof_properties = proof_options.extra_proof_properties();
+            let mut options = LinkedDataProofOptions::from(proof_options);
+            let holder = presentation.holder.as_ref().map(ToString::to_string);
+            discover_verification_method(
+                &mut options,
+                holder,
+                ProofPurpose::Authentication,
+                jwk_opt.as_ref(),
+                &resolver,
+                &rt,
+            );
             match proof_format {
                 ProofFormat::JWT => {
-                    if ssh_agent_sock_opt.is_some() {
-                        todo!("ssh-agent for JWT not implemented");
-                    }
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
                     let jwt = rt
-                        .block_on(presentation.generate_jwt(jwk_opt.as_ref(), &options, &resolver))
-                        .unwrap();
+                        .block_on(presentation.generate_jwt(Some(jwk), &options, &resolver))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
                     print!("{}", jwt);
                 }
+                ProofFormat::VcJose | ProofFormat::VcCose => {
+                    let jwk = signing_key(
+                        jwk_opt.as_ref(),
+                        ssh_agent_sock_opt.is_some(),
+                        &proof_format,
+                    );
+                    let secured = rt
+                        .block_on(securing::secure_presentation(
+                            &presentation,
+                            &proof_format,
+                            &options,
+                            jwk,
+                        ))
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
+                    print!("{}", secured);
+                }
                 ProofFormat::LDP => {
                     let proof = rt
-                        .block_on(generate_proof(
+                        .block_on(generate_proof_with_properties(
                             &presentation,
                             jwk_opt.as_ref(),
                             options,
                             &resolver,
                             ssh_agent_sock_opt,
+                            extra_proof_properties,
                         ))
-                        .unwrap();
+                        .unwrap_or_else(|err| {
+                            eprintln!("didkit: {}", err);
+                            std::process::exit(1);
+                        });
                     presentation.add_proof(proof);
-                    let stdout_writer = BufWriter::new(stdout());
-                    serde_json::to_writer(stdout_writer, &presentation).unwrap();
+                    output::write_json(&presentation);
+                }
+                ProofFormat::JPT => {
+                    eprintln!("didkit: JPT presentations are derived from a JPT credential");
+                    std::process::exit(1);
                 }
                 _ => {
                     panic!("Unexpected proof format: {:?}", proof_format);
@@ -0,0 +1,731 @@
+//! `didkit oidc4vci` subcommands: wallet-side client of [OpenID for Verifiable Credential
+//! Issuance][oidc4vci] issuers, for testing issuers and for headless wallet automation.
+//!
+//! `accept-offer` takes a credential offer, by value (`credential_offer`) or by reference
+//! (`credential_offer_uri`), fetches the metadata of the credential issuer and of its
+//! authorization server, and gets an access token with the pre-authorized code of the offer, or
+//! with an authorization code: the authorization request URL is written to stderr, and the URL
+//! redirected to, or the code, is read from stdin. The credentials of the offer are then requested
+//! with a proof of possession of the holder key, a JWT with the `nonce` of the issuer, and
+//! written to stdout, and stored in an encrypted wallet with `--wallet`. Deferred issuance is not
+//! supported.
+//!
+//! [oidc4vci]: https://openid.net/specs/openid-4-verifiable-credential-issuance-1_0.html
+
+use std::io::{stdin, BufRead};
+use std::path::PathBuf;
+
+use chrono::Utc;
+use rand::RngCore;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use structopt::StructOpt;
+
+use didkit::wallet::Wallet;
+use didkit::{
+    get_verification_method, runtime, verify_credential, CredentialOrJWT, DIDResolver,
+    JWTOrLDPOptions, LinkedDataProofOptions, Source, DID_METHODS, JWK, URI,
+};
+
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+/// Grant type of pre-authorized codes.
+pub const GRANT_PRE_AUTHORIZED_CODE: &str = "urn:ietf:params:oauth:grant-type:pre-authorized_code";
+/// JWT type of proofs of possession of the holder key.
+pub const PROOF_JWT_TYPE: &str = "openid4vci-proof+jwt";
+
+#[derive(StructOpt, Debug)]
+pub enum Oidc4vciCmd {
+    /// Accept a credential offer, request its credentials, and output them
+    AcceptOffer {
+        /// Credential offer: an openid-credential-offer:// URI (or another URL with a
+        /// credential_offer or credential_offer_uri parameter), or the offer as JSON
+        offer: String,
+        /// Filename of the holder's private key JWK, to prove possession of
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Verification method of the holder key, as the kid of proofs. Default is the
+        /// verification method of the did:key DID of the key.
+        #[structopt(short, long)]
+        verification_method: Option<String>,
+        /// Credential configuration ID of the offer to request. May be repeated. Default is all
+        /// the credentials of the offer.
+        #[structopt(short, long = "credential", number_of_values = 1)]
+        credentials: Vec<String>,
+        /// Transaction code, for a pre-authorized code that requires one
+        #[structopt(env = "OIDC4VCI_TX_CODE", long, hide_env_values = true)]
+        tx_code: Option<String>,
+        /// Use the authorization code flow, even if the offer has a pre-authorized code
+        #[structopt(long)]
+        authorization_code: bool,
+        /// OAuth client ID of the wallet, for the authorization code flow
+        #[structopt(long)]
+        client_id: Option<String>,
+        /// Redirect URI of the wallet, for the authorization code flow
+        #[structopt(long, default_value = "http://localhost/callback")]
+        redirect_uri: String,
+        /// Directory of an encrypted credential store to also store the credentials in,
+        /// encrypted with the holder key
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: Option<PathBuf>,
+        /// Verify the received credentials, and fail if any does not verify
+        #[structopt(long)]
+        verify: bool,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+/// Credential offer of an issuer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CredentialOffer {
+    pub credential_issuer: String,
+    #[serde(default)]
+    pub credential_configuration_ids: Vec<String>,
+    #[serde(default)]
+    pub grants: Grants,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Grants {
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:pre-authorized_code")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_authorized_code: Option<PreAuthorizedCodeGrant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_code: Option<AuthorizationCodeGrant>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PreAuthorizedCodeGrant {
+    #[serde(rename = "pre-authorized_code")]
+    pub pre_authorized_code: String,
+    /// Description of the transaction code the wallet must send, if one is required
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_code: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_server: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuthorizationCodeGrant {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_server: Option<String>,
+}
+
+/// Credential offer given by value or by reference.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OfferInput {
+    Offer(CredentialOffer),
+    Uri(String),
+}
+
+/// Parse a credential offer, given as a URI with a `credential_offer` or `credential_offer_uri`
+/// query parameter, or as JSON.
+pub fn parse_offer(offer: &str) -> Result<OfferInput, String> {
+    let offer = offer.trim();
+    if offer.starts_with('{') {
+        let offer = serde_json::from_str(offer).map_err(|e| format!("Invalid offer: {}", e))?;
+        return Ok(OfferInput::Offer(offer));
+    }
+    let url = Url::parse(offer).map_err(|e| format!("Invalid offer URI: {}", e))?;
+    for (name, value) in url.query_pairs() {
+        match &*name {
+            "credential_offer" => {
+                let offer =
+                    serde_json::from_str(&value).map_err(|e| format!("Invalid offer: {}", e))?;
+                return Ok(OfferInput::Offer(offer));
+            }
+            "credential_offer_uri" => return Ok(OfferInput::Uri(value.into_owned())),
+            _ => {}
+        }
+    }
+    Err("Missing credential_offer or credential_offer_uri parameter".to_string())
+}
+
+/// URLs of a well-known metadata document of an issuer or authorization server: with the
+/// well-known path inserted between the host and the path of the identifier, and, for
+/// identifiers with a path, appended to it, as some servers publish it.
+pub fn well_known_urls(identifier: &str, suffix: &str) -> Result<Vec<String>, String> {
+    let url = Url::parse(identifier).map_err(|e| format!("Invalid URL {}: {}", identifier, e))?;
+    let path = url.path().trim_end_matches('/');
+    let mut inserted = url.clone();
+    inserted.set_path(&format!("/.well-known/{}{}", suffix, path));
+    let mut urls = vec![inserted.to_string()];
+    if !path.is_empty() {
+        urls.push(format!(
+            "{}/.well-known/{}",
+            identifier.trim_end_matches('/'),
+            suffix
+        ));
+    }
+    Ok(urls)
+}
+
+/// Metadata of a credential issuer.
+#[derive(Debug, Deserialize, Clone)]
+pub struct IssuerMetadata {
+    pub credential_issuer: String,
+    pub credential_endpoint: String,
+    #[serde(default)]
+    pub authorization_servers: Vec<String>,
+    pub nonce_endpoint: Option<String>,
+    #[serde(default)]
+    pub credential_configurations_supported: Map<String, Value>,
+}
+
+/// Metadata of an OAuth authorization server.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthorizationServerMetadata {
+    pub token_endpoint: String,
+    pub authorization_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct TokenResponse {
+    access_token: String,
+    c_nonce: Option<String>,
+}
+
+/// Random base64url string, for PKCE code verifiers and states.
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// PKCE `S256` code challenge of a code verifier.
+fn code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(
+        Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+/// Wallet-side client of one credential issuer.
+pub struct Oidc4vciClient {
+    http: reqwest::Client,
+}
+
+impl Default for Oidc4vciClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Oidc4vciClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Read a response as JSON, failing on an error status.
+    pub(crate) async fn json(response: reqwest::Response) -> Result<Value, String> {
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        if !status.is_success() {
+            return Err(format!("HTTP status {}: {}", status.as_u16(), value));
+        }
+        Ok(value)
+    }
+
+    async fn get(&self, url: &str) -> Result<Value, String> {
+        let response = self.http.get(url).send().await;
+        Self::json(response.map_err(|e| e.to_string())?).await
+    }
+
+    /// Fetch the first of a list of metadata URLs that is found.
+    async fn metadata<T: for<'de> Deserialize<'de>>(&self, urls: &[String]) -> Result<T, String> {
+        let mut error = String::new();
+        for url in urls {
+            match self.get(url).await {
+                Ok(metadata) => {
+                    return serde_json::from_value(metadata)
+                        .map_err(|e| format!("Invalid metadata at {}: {}", url, e))
+                }
+                Err(err) => error = format!("{}: {}", url, err),
+            }
+        }
+        Err(format!("Unable to fetch metadata: {}", error))
+    }
+
+    /// The credential offer, fetched if given by reference.
+    pub async fn offer(&self, input: OfferInput) -> Result<CredentialOffer, String> {
+        match input {
+            OfferInput::Offer(offer) => Ok(offer),
+            OfferInput::Uri(uri) => serde_json::from_value(self.get(&uri).await?)
+                .map_err(|e| format!("Invalid offer: {}", e)),
+        }
+    }
+
+    pub async fn issuer_metadata(&self, credential_issuer: &str) -> Result<IssuerMetadata, String> {
+        let urls = well_known_urls(credential_issuer, "openid-credential-issuer")?;
+        self.metadata(&urls).await
+    }
+
+    pub async fn authorization_server_metadata(
+        &self,
+        issuer: &str,
+    ) -> Result<AuthorizationServerMetadata, String> {
+        let mut urls = well_known_urls(issuer, "oauth-authorization-server")?;
+        urls.extend(well_known_urls(issuer, "openid-configuration")?);
+        self.metadata(&urls).await
+    }
+
+    async fn token(
+        &self,
+        token_endpoint: &str,
+        form: &[(&str, &str)],
+    ) -> Result<TokenResponse, String> {
+        let response = self.http.post(token_endpoint).form(form).send().await;
+        let token = Self::json(response.map_err(|e| e.to_string())?).await?;
+        serde_json::from_value(token).map_err(|e| format!("Invalid token response: {}", e))
+    }
+
+    /// A fresh `c_nonce` from the nonce endpoint of the issuer.
+    async fn nonce(&self, nonce_endpoint: &str) -> Result<String, String> {
+        let response = self.http.post(nonce_endpoint).send().await;
+        let nonce = Self::json(response.map_err(|e| e.to_string())?).await?;
+        match nonce.get("c_nonce").and_then(Value::as_str) {
+            Some(c_nonce) => Ok(c_nonce.to_string()),
+            None => Err(format!("Invalid nonce response: {}", nonce)),
+        }
+    }
+
+    /// Request a credential, returning the response, or the response of an `invalid_proof` or
+    /// `invalid_nonce` error as `Ok(Err)`, for a retry with a fresh nonce.
+    async fn credential(
+        &self,
+        credential_endpoint: &str,
+        access_token: &str,
+        request: &Value,
+    ) -> Result<Result<Value, Value>, String> {
+        let response = self
+            .http
+            .post(credential_endpoint)
+            .bearer_auth(access_token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().as_u16() == 400 {
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            let error: Value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+            let code = error.get("error").and_then(Value::as_str);
+            if code == Some("invalid_proof") || code == Some("invalid_nonce") {
+                return Ok(Err(error));
+            }
+            return Err(format!("HTTP status 400: {}", error));
+        }
+        Self::json(response).await.map(Ok)
+    }
+}
+
+/// Options of accepting an offer.
+pub struct AcceptOptions {
+    pub credentials: Vec<String>,
+    pub tx_code: Option<String>,
+    pub authorization_code: bool,
+    pub client_id: Option<String>,
+    pub redirect_uri: String,
+}
+
+/// Sign a proof of possession of the holder key, for a credential issuer and nonce.
+async fn proof_jwt(
+    key: &JWK,
+    verification_method: &str,
+    credential_issuer: &str,
+    client_id: Option<&str>,
+    c_nonce: Option<String>,
+) -> Result<String, String> {
+    let mut claims = json!({ "iat": Utc::now().timestamp() });
+    if let Some(client_id) = client_id {
+        claims["iss"] = json!(client_id);
+    }
+    let options = LinkedDataProofOptions {
+        verification_method: Some(URI::String(verification_method.to_string())),
+        domain: Some(credential_issuer.to_string()),
+        challenge: c_nonce,
+        ..Default::default()
+    };
+    let mut header = Map::new();
+    header.insert("typ".to_string(), json!(PROOF_JWT_TYPE));
+    didkit::signer::sign_jwt_with_header(&claims, &options, &header, key)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get an authorization code from the user: write the authorization request URL to stderr, and
+/// read the URL redirected to, or the code, from stdin.
+fn authorize(
+    authorization_endpoint: &str,
+    options: &AcceptOptions,
+    configuration_ids: &[String],
+    issuer_state: Option<&str>,
+    code_verifier: &str,
+) -> Result<String, String> {
+    let client_id = options
+        .client_id
+        .as_deref()
+        .ok_or("The authorization code flow requires a client ID (--client-id)")?;
+    let mut url = Url::parse(authorization_endpoint).map_err(|e| e.to_string())?;
+    let state = random_string();
+    let authorization_details: Vec<Value> = configuration_ids
+        .iter()
+        .map(|id| json!({ "type": "openid_credential", "credential_configuration_id": id }))
+        .collect();
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", &options.redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &code_challenge(code_verifier))
+            .append_pair("code_challenge_method", "S256")
+            .append_pair(
+                "authorization_details",
+                &Value::from(authorization_details).to_string(),
+            );
+        if let Some(issuer_state) = issuer_state {
+            query.append_pair("issuer_state", issuer_state);
+        }
+    }
+    eprintln!("Authorize at this URL, then enter the URL redirected to (or the code):");
+    eprintln!("{}", url);
+    let mut line = String::new();
+    stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    let line = line.trim();
+    let redirect = match Url::parse(line) {
+        Ok(redirect) => redirect,
+        Err(_) if !line.is_empty() => return Ok(line.to_string()),
+        Err(_) => return Err("Missing authorization code".to_string()),
+    };
+    let param = |name: &str| {
+        redirect
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if let Some(error) = param("error") {
+        return Err(format!("Authorization failed: {}", error));
+    }
+    if param("state").as_deref() != Some(state.as_str()) {
+        return Err("Authorization response state mismatch".to_string());
+    }
+    param("code").ok_or_else(|| "Missing code in the redirect URL".to_string())
+}
+
+/// Accept a credential offer: get an access token and request the credentials of the offer.
+pub async fn accept_offer(
+    client: &Oidc4vciClient,
+    offer: &CredentialOffer,
+    key: &JWK,
+    verification_method: &str,
+    options: &AcceptOptions,
+) -> Result<Vec<Value>, String> {
+    let metadata = client.issuer_metadata(&offer.credential_issuer).await?;
+    let configuration_ids: Vec<String> = if options.credentials.is_empty() {
+        offer.credential_configuration_ids.clone()
+    } else {
+        for id in &options.credentials {
+            if !offer.credential_configuration_ids.contains(id) {
+                return Err(format!("Credential not offered: {}", id));
+            }
+        }
+        options.credentials.clone()
+    };
+    let pre_authorized = offer
+        .grants
+        .pre_authorized_code
+        .as_ref()
+        .filter(|_| !options.authorization_code);
+    let grant_authorization_server = match pre_authorized {
+        Some(grant) => grant.authorization_server.as_ref(),
+        None => offer
+            .grants
+            .authorization_code
+            .as_ref()
+            .and_then(|grant| grant.authorization_server.as_ref()),
+    };
+    let authorization_server = grant_authorization_server
+        .or_else(|| metadata.authorization_servers.first())
+        .unwrap_or(&metadata.credential_issuer);
+    let as_metadata = client
+        .authorization_server_metadata(authorization_server)
+        .await?;
+
+    let token = match pre_authorized {
+        Some(grant) => {
+            let mut form = vec![
+                ("grant_type", GRANT_PRE_AUTHORIZED_CODE),
+                ("pre-authorized_code", grant.pre_authorized_code.as_str()),
+            ];
+            match (&grant.tx_code, &options.tx_code) {
+                (_, Some(tx_code)) => form.push(("tx_code", tx_code.as_str())),
+                (Some(_), None) => {
+                    return Err("The offer requires a transaction code (--tx-code)".to_string())
+                }
+                (None, None) => {}
+            }
+            if let Some(ref client_id) = options.client_id {
+                form.push(("client_id", client_id.as_str()));
+            }
+            client.token(&as_metadata.token_endpoint, &form).await?
+        }
+        None => {
+            let authorization_endpoint = as_metadata
+                .authorization_endpoint
+                .as_deref()
+                .ok_or("The authorization server has no authorization endpoint")?;
+            let issuer_state = offer
+                .grants
+                .authorization_code
+                .as_ref()
+                .and_then(|grant| grant.issuer_state.as_deref());
+            let code_verifier = random_string();
+            let code = authorize(
+                authorization_endpoint,
+                options,
+                &configuration_ids,
+                issuer_state,
+                &code_verifier,
+            )?;
+            let form = [
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", options.redirect_uri.as_str()),
+                (
+                    "client_id",
+                    options.client_id.as_deref().unwrap_or_default(),
+                ),
+                ("code_verifier", code_verifier.as_str()),
+            ];
+            client.token(&as_metadata.token_endpoint, &form).await?
+        }
+    };
+    // Proofs of a pre-authorized code flow are anonymous, without the client ID.
+    let client_id = match pre_authorized {
+        Some(_) => None,
+        None => options.client_id.as_deref(),
+    };
+
+    let mut c_nonce = token.c_nonce.clone();
+    let mut credentials = Vec::new();
+    for id in &configuration_ids {
+        if !metadata.credential_configurations_supported.is_empty()
+            && !metadata
+                .credential_configurations_supported
+                .contains_key(id)
+        {
+            return Err(format!("Unsupported credential configuration: {}", id));
+        }
+        let mut retried = false;
+        let response = loop {
+            if let Some(ref nonce_endpoint) = metadata.nonce_endpoint {
+                c_nonce = Some(client.nonce(nonce_endpoint).await?);
+            }
+            let jwt = proof_jwt(
+                key,
+                verification_method,
+                &metadata.credential_issuer,
+                client_id,
+                c_nonce.clone(),
+            )
+            .await?;
+            let request = json!({
+                "credential_configuration_id": id,
+                "proofs": { "jwt": [jwt] },
+            });
+            match client
+                .credential(&metadata.credential_endpoint, &token.access_token, &request)
+                .await?
+            {
+                Ok(response) => break response,
+                Err(error) if !retried => {
+                    // The error may carry a fresh nonce.
+                    if let Some(nonce) = error.get("c_nonce").and_then(Value::as_str) {
+                        c_nonce = Some(nonce.to_string());
+                    }
+                    retried = true;
+                }
+                Err(error) => return Err(format!("Credential request failed: {}", error)),
+            }
+        };
+        if let Some(nonce) = response.get("c_nonce").and_then(Value::as_str) {
+            c_nonce = Some(nonce.to_string());
+        }
+        match (response.get("credentials"), response.get("credential")) {
+            (Some(Value::Array(issued)), _) => credentials.extend(
+                issued
+                    .iter()
+                    .map(|issued| issued.get("credential").unwrap_or(issued).clone()),
+            ),
+            (_, Some(credential)) => credentials.push(credential.clone()),
+            _ if response.get("transaction_id").is_some() => {
+                return Err(format!("Deferred issuance is not supported: {}", response))
+            }
+            _ => return Err(format!("Invalid credential response: {}", response)),
+        }
+    }
+    Ok(credentials)
+}
+
+/// Verify a received credential, as JSON or a JWT.
+async fn check_credential(credential: &Value, resolver: &dyn DIDResolver) -> Result<(), String> {
+    let credential: CredentialOrJWT =
+        serde_json::from_value(credential.clone()).map_err(|e| e.to_string())?;
+    let report = verify_credential(&credential, &JWTOrLDPOptions::default(), resolver)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !report.is_success() {
+        let errors: Vec<String> = report.errors.into_iter().map(|e| e.message).collect();
+        return Err(format!("Invalid credential: {}", errors.join("; ")));
+    }
+    Ok(())
+}
+
+impl Oidc4vciCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            Oidc4vciCmd::AcceptOffer {
+                offer,
+                key_path,
+                verification_method,
+                credentials,
+                tx_code,
+                authorization_code,
+                client_id,
+                redirect_uri,
+                wallet,
+                verify,
+                resolver_options,
+            } => {
+                let key = read_jwk(&key_path);
+                let resolver = resolver_options.to_resolver();
+                let options = AcceptOptions {
+                    credentials,
+                    tx_code,
+                    authorization_code,
+                    client_id,
+                    redirect_uri,
+                };
+                let client = Oidc4vciClient::new();
+                let result = rt.block_on(async {
+                    let offer = client.offer(parse_offer(&offer)?).await?;
+                    let verification_method = match verification_method {
+                        Some(verification_method) => verification_method,
+                        None => {
+                            let did = DID_METHODS
+                                .generate(&Source::KeyAndPattern(&key, "key"))
+                                .ok_or("Unable to generate DID")?;
+                            get_verification_method(&did, &resolver)
+                                .await
+                                .ok_or("Unable to get verification method")?
+                        }
+                    };
+                    let credentials =
+                        accept_offer(&client, &offer, &key, &verification_method, &options).await?;
+                    if verify {
+                        for credential in &credentials {
+                            check_credential(credential, &resolver).await?;
+                        }
+                    }
+                    Ok::<_, String>((offer, credentials))
+                });
+                let (offer, credentials) = result.unwrap_or_else(|err| {
+                    eprintln!("didkit: {}", err);
+                    std::process::exit(1);
+                });
+                let wallet = wallet.map(|dir| Wallet::open(dir, key).unwrap());
+                for credential in credentials {
+                    if let Some(ref wallet) = wallet {
+                        let id = wallet
+                            .insert_credential(
+                                credential.clone(),
+                                Some(offer.credential_issuer.clone()),
+                            )
+                            .unwrap();
+                        eprintln!("Stored credential {}", id);
+                    }
+                    match credential {
+                        Value::String(credential) => println!("{}", credential),
+                        credential => output::write_json(&credential),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_credential_offer() {
+        let offer = json!({
+            "credential_issuer": "https://issuer.example.com",
+            "credential_configuration_ids": ["UniversityDegree_jwt"],
+            "grants": {
+                GRANT_PRE_AUTHORIZED_CODE: {
+                    "pre-authorized_code": "adhjhdjajkdkhjhdj",
+                    "tx_code": { "length": 4, "input_mode": "numeric" },
+                },
+            },
+        });
+        let mut uri = Url::parse("openid-credential-offer://").unwrap();
+        uri.query_pairs_mut()
+            .append_pair("credential_offer", &offer.to_string());
+        let parsed = match parse_offer(uri.as_str()).unwrap() {
+            OfferInput::Offer(parsed) => parsed,
+            other => panic!("Unexpected offer: {:?}", other),
+        };
+        assert_eq!(parsed.credential_issuer, "https://issuer.example.com");
+        let grant = parsed.grants.pre_authorized_code.as_ref().unwrap();
+        assert_eq!(grant.pre_authorized_code, "adhjhdjajkdkhjhdj");
+        assert!(grant.tx_code.is_some());
+        assert!(parsed.grants.authorization_code.is_none());
+        assert_eq!(
+            parse_offer(&offer.to_string()).unwrap(),
+            OfferInput::Offer(parsed)
+        );
+
+        let by_reference = "openid-credential-offer://?credential_offer_uri=https%3A%2F%2Fissuer.example.com%2Foffers%2F1";
+        assert_eq!(
+            parse_offer(by_reference).unwrap(),
+            OfferInput::Uri("https://issuer.example.com/offers/1".to_string())
+        );
+        assert!(parse_offer("openid-credential-offer://?other=1").is_err());
+    }
+
+    #[test]
+    fn metadata_urls() {
+        assert_eq!(
+            well_known_urls("https://issuer.example.com", "openid-credential-issuer").unwrap(),
+            ["https://issuer.example.com/.well-known/openid-credential-issuer"]
+        );
+        assert_eq!(
+            well_known_urls("https://example.com/tenant/", "oauth-authorization-server").unwrap(),
+            [
+                "https://example.com/.well-known/oauth-authorization-server/tenant",
+                "https://example.com/tenant/.well-known/oauth-authorization-server"
+            ]
+        );
+        // RFC 7636, Appendix B
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! `didkit oidc4vp` subcommands: wallet-side responder to [OpenID for Verifiable
+//! Presentations][oidc4vp] verifiers, for testing verifiers and for headless wallet automation.
+//!
+//! `respond` takes an authorization request, by value (its parameters, or a `request` object) or
+//! by reference (`request_uri`), fetches its presentation definition if given by reference
+//! (`presentation_definition_uri`), and chooses the credentials satisfying it (see
+//! [`didkit::pex`]) among the credentials of an encrypted wallet and of files. SD-JWTs disclose
+//! only the claims the definition asks for, and, if bound to the holder key, get a key binding
+//! JWT for the client ID and nonce of the request. The VP token is the SD-JWTs themselves, if only
+//! SD-JWTs are presented, or otherwise a presentation of the credentials signed with the holder
+//! key, in the presentation format of the definition, with the nonce as challenge and the client
+//! ID as domain. The response is posted to the response URI (`direct_post` response mode), or,
+//! for the `fragment` and `query` response modes, added to the redirect URI to open; the response
+//! of the verifier, or the URL, is written to stdout.
+//!
+//! Request objects are decoded without verifying their signature: the verifier is not
+//! authenticated beyond its client ID. The response is therefore only sent to the URI the client
+//! ID is, as in the `redirect_uri` client ID scheme, so that a request cannot relay the
+//! presentation of one verifier to another; requests of other client IDs are refused. DCQL
+//! queries and encrypted responses are not supported.
+//!
+//! [oidc4vp]: https://openid.net/specs/openid-4-verifiable-presentations-1_0.html
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use structopt::StructOpt;
+
+use didkit::pex::{self, PresentationDefinition, PresentationSubmission, LDP_VP};
+use didkit::redact::{bind_sd_jwt, redact, requires_key_binding, RedactOptions};
+use didkit::wallet::Wallet;
+use didkit::{
+    get_verification_method, issue_presentation, runtime, DIDResolver, PresentationOrJWT,
+    ProofFormat, ProofOptionsBuilder, ProofPurpose, Source, VerifiablePresentation, DID_METHODS,
+    JWK,
+};
+
+use crate::oidc4vci::Oidc4vciClient;
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+/// Response type of requests for a VP token.
+pub const RESPONSE_TYPE_VP_TOKEN: &str = "vp_token";
+/// Response mode posting the response to the response URI.
+pub const RESPONSE_MODE_DIRECT_POST: &str = "direct_post";
+/// Prefix of client IDs of the `redirect_uri` client ID scheme.
+pub const CLIENT_ID_PREFIX_REDIRECT_URI: &str = "redirect_uri:";
+
+#[derive(StructOpt, Debug)]
+pub enum Oidc4vpCmd {
+    /// Respond to an authorization request with a presentation of local credentials
+    Respond {
+        /// Authorization request: an openid4vp:// URI (or another URL with the request
+        /// parameters, a request object or a request_uri), or the request as JSON
+        request: String,
+        /// Filename of the holder's private key JWK, to sign presentations with
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Verification method of the holder key. Default is the verification method of the
+        /// did:key DID of the key.
+        #[structopt(short, long)]
+        verification_method: Option<String>,
+        /// Directory of an encrypted credential store of the holder, encrypted with the holder
+        /// key, to choose credentials from
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: Option<PathBuf>,
+        /// Filename of a credential to choose from, as JSON or a JWT. May be repeated.
+        #[structopt(short, long = "credential", parse(from_os_str), number_of_values = 1)]
+        credentials: Vec<PathBuf>,
+        /// Output the authorization response instead of sending it
+        #[structopt(long)]
+        dry_run: bool,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+fn default_response_type() -> String {
+    RESPONSE_TYPE_VP_TOKEN.to_string()
+}
+
+/// Authorization request of a verifier.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthorizationRequest {
+    pub client_id: String,
+    #[serde(default = "default_response_type")]
+    pub response_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+    pub nonce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation_definition: Option<PresentationDefinition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation_definition_uri: Option<String>,
+}
+
+/// Authorization request given by value or by reference.
+#[derive(Debug, Clone)]
+pub enum RequestInput {
+    Request(AuthorizationRequest),
+    /// `request_uri`, with the `client_id` given along with it
+    Uri(String, Option<String>),
+}
+
+/// Decode a request object, without verifying its signature.
+pub fn decode_request_object(jwt: &str) -> Result<AuthorizationRequest, String> {
+    let payload = jwt
+        .trim()
+        .split('.')
+        .nth(1)
+        .ok_or("Invalid request object: expected a JWT")?;
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| format!("Invalid request object: {}", e))?;
+    serde_json::from_slice(&payload).map_err(|e| format!("Invalid request object: {}", e))
+}
+
+/// Parse an authorization request, given as a URI with the request parameters, a `request`
+/// object or a `request_uri`, or as JSON.
+pub fn parse_request(request: &str) -> Result<RequestInput, String> {
+    let request = request.trim();
+    if request.starts_with('{') {
+        let request =
+            serde_json::from_str(request).map_err(|e| format!("Invalid request: {}", e))?;
+        return Ok(RequestInput::Request(request));
+    }
+    let url = Url::parse(request).map_err(|e| format!("Invalid request URI: {}", e))?;
+    let mut params = Map::new();
+    for (name, value) in url.query_pairs() {
+        // Object parameters are JSON-encoded.
+        let value = match &*name {
+            "presentation_definition" | "client_metadata" => serde_json::from_str(&value)
+                .map_err(|e| format!("Invalid {} parameter: {}", name, e))?,
+            _ => Value::String(value.into_owned()),
+        };
+        params.insert(name.into_owned(), value);
+    }
+    let client_id = params
+        .get("client_id")
+        .and_then(Value::as_str)
+        .map(String::from);
+    if let Some(request_uri) = params.get("request_uri").and_then(Value::as_str) {
+        return Ok(RequestInput::Uri(request_uri.to_string(), client_id));
+    }
+    let request_object = params
+        .get("request")
+        .and_then(Value::as_str)
+        .map(String::from);
+    let request = match request_object {
+        Some(jwt) => decode_request_object(&jwt)?,
+        None => serde_json::from_value(Value::Object(params))
+            .map_err(|e| format!("Invalid request: {}", e))?,
+    };
+    if client_id.is_some() && client_id.as_deref() != Some(request.client_id.as_str()) {
+        return Err("The client_id of the request object does not match".to_string());
+    }
+    Ok(RequestInput::Request(request))
+}
+
+/// Check that the response to a request of a client may be sent to a response or redirect URI.
+///
+/// Request objects are not verified, so the client ID is the only identity of the verifier, and
+/// key binding JWTs and presentations are bound to it: the URI must be the client ID itself, with
+/// or without the `redirect_uri:` prefix. Client IDs which are not URIs (e.g. DIDs, or the
+/// `x509_san_dns` and `verifier_attestation` schemes) would need a signed request object.
+pub fn check_response_uri(client_id: &str, uri: &str) -> Result<(), String> {
+    let client_uri = client_id
+        .strip_prefix(CLIENT_ID_PREFIX_REDIRECT_URI)
+        .unwrap_or(client_id);
+    let scheme = Url::parse(client_uri).map(|url| url.scheme().to_string());
+    if !matches!(scheme.as_deref(), Ok("https") | Ok("http")) {
+        return Err(format!(
+            "Unable to authenticate client {}: signed request objects are not supported",
+            client_id
+        ));
+    }
+    if client_uri != uri {
+        return Err(format!(
+            "The response URI {} does not match the client ID {}",
+            uri, client_id
+        ));
+    }
+    Ok(())
+}
+
+/// Authorization response of the wallet.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthorizationResponse {
+    /// A presentation, as JSON or a JWT, or SD-JWTs, one or an array of several
+    pub vp_token: Value,
+    pub presentation_submission: PresentationSubmission,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+impl AuthorizationResponse {
+    /// Parameters of the response, with JSON values encoded.
+    pub fn params(&self) -> Result<Vec<(&'static str, String)>, String> {
+        let vp_token = match self.vp_token {
+            Value::String(ref vp_token) => vp_token.clone(),
+            ref vp_token => vp_token.to_string(),
+        };
+        let submission =
+            serde_json::to_string(&self.presentation_submission).map_err(|e| e.to_string())?;
+        let mut params = vec![
+            ("vp_token", vp_token),
+            ("presentation_submission", submission),
+        ];
+        if let Some(ref state) = self.state {
+            params.push(("state", state.clone()));
+        }
+        Ok(params)
+    }
+}
+
+/// Wallet-side client of verifiers.
+pub struct Oidc4vpClient {
+    http: reqwest::Client,
+}
+
+impl Default for Oidc4vpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Oidc4vpClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn get(&self, url: &str) -> Result<Value, String> {
+        let response = self.http.get(url).send().await;
+        Oidc4vciClient::json(response.map_err(|e| e.to_string())?).await
+    }
+
+    /// The authorization request, fetched if given by reference.
+    pub async fn request(&self, input: RequestInput) -> Result<AuthorizationRequest, String> {
+        let (request_uri, client_id) = match input {
+            RequestInput::Request(request) => return Ok(request),
+            RequestInput::Uri(request_uri, client_id) => (request_uri, client_id),
+        };
+        let request = match self.get(&request_uri).await? {
+            Value::String(jwt) => decode_request_object(&jwt)?,
+            request => {
+                serde_json::from_value(request).map_err(|e| format!("Invalid request: {}", e))?
+            }
+        };
+        if client_id.is_some() && client_id.as_deref() != Some(request.client_id.as_str()) {
+            return Err("The client_id of the request object does not match".to_string());
+        }
+        Ok(request)
+    }
+
+    /// The presentation definition of a request, fetched if given by reference.
+    pub async fn presentation_definition(
+        &self,
+        request: &AuthorizationRequest,
+    ) -> Result<PresentationDefinition, String> {
+        match (
+            &request.presentation_definition,
+            &request.presentation_definition_uri,
+        ) {
+            (Some(definition), _) => Ok(definition.clone()),
+            (None, Some(uri)) => serde_json::from_value(self.get(uri).await?)
+                .map_err(|e| format!("Invalid presentation definition: {}", e)),
+            (None, None) => Err("The request has no presentation definition".to_string()),
+        }
+    }
+
+    /// Send an authorization response: post it to the response URI, or, for the `fragment` and
+    /// `query` response modes, return the redirect URI with the response, to open. The URI must
+    /// match the client ID (see [`check_response_uri`]).
+    pub async fn send_response(
+        &self,
+        request: &AuthorizationRequest,
+        response: &AuthorizationResponse,
+    ) -> Result<Value, String> {
+        let params = response.params()?;
+        let mode = request.response_mode.as_deref().unwrap_or("fragment");
+        if mode == RESPONSE_MODE_DIRECT_POST {
+            let response_uri = request
+                .response_uri
+                .as_deref()
+                .ok_or("The request has no response_uri")?;
+            check_response_uri(&request.client_id, response_uri)?;
+            let response = self.http.post(response_uri).form(&params).send().await;
+            let response = Oidc4vciClient::json(response.map_err(|e| e.to_string())?).await?;
+            return Ok(match response {
+                Value::String(text) if text.is_empty() => json!({}),
+                response => response,
+            });
+        }
+        let redirect_uri = request
+            .redirect_uri
+            .as_deref()
+            .ok_or("The request has no redirect_uri")?;
+        check_response_uri(&request.client_id, redirect_uri)?;
+        let mut url =
+            Url::parse(redirect_uri).map_err(|e| format!("Invalid redirect_uri: {}", e))?;
+        match mode {
+            "query" => {
+                url.query_pairs_mut().extend_pairs(&params);
+            }
+            "fragment" => {
+                // Encode the parameters as a query, to move them to the fragment.
+                let mut encoded = Url::parse("http://localhost").map_err(|e| e.to_string())?;
+                encoded.query_pairs_mut().extend_pairs(&params);
+                url.set_fragment(encoded.query());
+            }
+            mode => return Err(format!("Unsupported response mode: {}", mode)),
+        }
+        Ok(json!({ "redirect_uri": url.as_str() }))
+    }
+}
+
+fn is_sd_jwt(credential: &Value) -> bool {
+    matches!(credential, Value::String(credential) if credential.contains('~'))
+}
+
+/// Choose credentials satisfying the presentation definition of a request, and build the
+/// response presenting them.
+pub async fn build_response(
+    request: &AuthorizationRequest,
+    definition: &PresentationDefinition,
+    credentials: &[Value],
+    key: &JWK,
+    verification_method: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<AuthorizationResponse, String> {
+    if request.response_type != RESPONSE_TYPE_VP_TOKEN {
+        return Err(format!(
+            "Unsupported response type: {}",
+            request.response_type
+        ));
+    }
+    let holder = verification_method.split('#').next().unwrap_or_default();
+    let selection = pex::select(definition, credentials, &[holder.to_string()])
+        .map_err(|e| format!("Unable to satisfy the presentation definition: {}", e))?;
+    let mut presented = Vec::new();
+    for selected in &selection.credentials {
+        let credential = &credentials[selected.credential];
+        let sd_jwt = match credential {
+            Value::String(sd_jwt) if is_sd_jwt(credential) => sd_jwt,
+            _ => {
+                presented.push(credential.clone());
+                continue;
+            }
+        };
+        let mut sd_jwt = match selected.disclose {
+            Some(ref reveal) => {
+                let options = RedactOptions {
+                    reveal: reveal.clone(),
+                    ..Default::default()
+                };
+                redact(sd_jwt, &options).map_err(|e| e.to_string())?
+            }
+            None => sd_jwt.clone(),
+        };
+        if requires_key_binding(&sd_jwt).map_err(|e| e.to_string())? {
+            sd_jwt = bind_sd_jwt(&sd_jwt, &request.client_id, &request.nonce, key)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        presented.push(Value::String(sd_jwt));
+    }
+
+    let mut submission = selection.presentation_submission;
+    if presented.iter().all(is_sd_jwt) {
+        // SD-JWTs are presented by themselves, without a presentation.
+        for (entry, m) in submission.descriptor_map.iter_mut().zip(&selection.matches) {
+            let position = selection
+                .credentials
+                .iter()
+                .position(|selected| selected.credential == m.credential)
+                .unwrap_or_default();
+            entry.format = m.format.clone();
+            entry.path = match presented.len() {
+                1 => "$".to_string(),
+                _ => format!("$[{}]", position),
+            };
+            entry.path_nested = None;
+        }
+        let vp_token = match presented.len() {
+            1 => presented.remove(0),
+            _ => Value::Array(presented),
+        };
+        return Ok(AuthorizationResponse {
+            vp_token,
+            presentation_submission: submission,
+            state: request.state.clone(),
+        });
+    }
+
+    let presentation: VerifiablePresentation = serde_json::from_value(json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "type": ["VerifiablePresentation"],
+        "holder": holder,
+        "verifiableCredential": presented,
+    }))
+    .map_err(|e| e.to_string())?;
+    let proof_format = match selection.presentation_format.as_str() {
+        LDP_VP => ProofFormat::LDP,
+        _ => ProofFormat::JWT,
+    };
+    let options = ProofOptionsBuilder::new()
+        .verification_method(verification_method)
+        .proof_purpose(ProofPurpose::Authentication)
+        .challenge(request.nonce.as_str())
+        .domain(request.client_id.as_str())
+        .proof_format(proof_format)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let vp_token = match issue_presentation(presentation, &options, key, resolver)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        PresentationOrJWT::VP(vp) => serde_json::to_value(vp).map_err(|e| e.to_string())?,
+        PresentationOrJWT::JWT(jwt) => Value::String(jwt),
+    };
+    Ok(AuthorizationResponse {
+        vp_token,
+        presentation_submission: submission,
+        state: request.state.clone(),
+    })
+}
+
+/// Read a credential file, as JSON or a JWT.
+fn read_credential(path: &Path) -> Result<Value, String> {
+    let credential = read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let credential = credential.trim();
+    if credential.starts_with('{') {
+        serde_json::from_str(credential).map_err(|e| format!("{}: {}", path.display(), e))
+    } else {
+        Ok(Value::String(credential.to_string()))
+    }
+}
+
+impl Oidc4vpCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            Oidc4vpCmd::Respond {
+                request,
+                key_path,
+                verification_method,
+                wallet,
+                credentials,
+                dry_run,
+                resolver_options,
+            } => {
+                let key = read_jwk(&key_path);
+                let resolver = resolver_options.to_resolver();
+                let client = Oidc4vpClient::new();
+                let result = rt.block_on(async {
+                    let mut available = Vec::new();
+                    if let Some(dir) = wallet {
+                        let wallet = Wallet::open(dir, key.clone()).map_err(|e| e.to_string())?;
+                        let stored = wallet.credentials().map_err(|e| e.to_string())?;
+                        available.extend(stored.into_iter().map(|stored| stored.credential));
+                    }
+                    for path in &credentials {
+                        available.push(read_credential(path)?);
+                    }
+                    let request = client.request(parse_request(&request)?).await?;
+                    let definition = client.presentation_definition(&request).await?;
+                    let verification_method = match verification_method {
+                        Some(verification_method) => verification_method,
+                        None => {
+                            let did = DID_METHODS
+                                .generate(&Source::KeyAndPattern(&key, "key"))
+                                .ok_or("Unable to generate DID")?;
+                            get_verification_method(&did, &resolver)
+                                .await
+                                .ok_or("Unable to get verification method")?
+                        }
+                    };
+                    let response = build_response(
+                        &request,
+                        &definition,
+                        &available,
+                        &key,
+                        &verification_method,
+                        &resolver,
+                    )
+                    .await?;
+                    if dry_run {
+                        return serde_json::to_value(&response).map_err(|e| e.to_string());
+                    }
+                    client.send_response(&request, &response).await
+                });
+                match result {
+                    Ok(output) => output::write_json(&output),
+                    Err(err) => {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition() -> Value {
+        json!({
+            "id": "example",
+            "input_descriptors": [{
+                "id": "id_credential",
+                "constraints": { "fields": [{ "path": ["$.vct"] }] },
+            }],
+        })
+    }
+
+    #[test]
+    fn parse_authorization_request() {
+        let mut uri = Url::parse("openid4vp://").unwrap();
+        uri.query_pairs_mut()
+            .append_pair("client_id", "https://verifier.example")
+            .append_pair("response_type", "vp_token")
+            .append_pair("response_mode", "direct_post")
+            .append_pair("response_uri", "https://verifier.example/post")
+            .append_pair("nonce", "n-0S6_WzA2Mj")
+            .append_pair("presentation_definition", &definition().to_string());
+        let request = match parse_request(uri.as_str()).unwrap() {
+            RequestInput::Request(request) => request,
+            other => panic!("Unexpected request: {:?}", other),
+        };
+        assert_eq!(request.client_id, "https://verifier.example");
+        assert_eq!(request.response_mode.as_deref(), Some("direct_post"));
+        assert_eq!(request.nonce, "n-0S6_WzA2Mj");
+        let definition = request.presentation_definition.unwrap();
+        assert_eq!(definition.input_descriptors[0].id, "id_credential");
+
+        let by_reference = "openid4vp://?client_id=verifier&request_uri=https%3A%2F%2Fverifier.example%2Frequests%2F1";
+        match parse_request(by_reference).unwrap() {
+            RequestInput::Uri(uri, client_id) => {
+                assert_eq!(uri, "https://verifier.example/requests/1");
+                assert_eq!(client_id.as_deref(), Some("verifier"));
+            }
+            other => panic!("Unexpected request: {:?}", other),
+        }
+
+        let payload = json!({
+            "client_id": "verifier",
+            "nonce": "abc",
+            "redirect_uri": "https://verifier.example/cb",
+        });
+        let jwt = format!(
+            "eyJhbGciOiJub25lIn0.{}.",
+            base64::encode_config(payload.to_string(), base64::URL_SAFE_NO_PAD)
+        );
+        let request = decode_request_object(&jwt).unwrap();
+        assert_eq!(request.response_type, RESPONSE_TYPE_VP_TOKEN);
+        assert_eq!(
+            request.redirect_uri.as_deref(),
+            Some("https://verifier.example/cb")
+        );
+        let mismatch = format!("openid4vp://?client_id=other&request={}", jwt);
+        assert!(parse_request(&mismatch).is_err());
+        assert!(parse_request("openid4vp://?client_id=verifier").is_err());
+    }
+
+    #[test]
+    fn response_uri_matches_client_id() {
+        check_response_uri(
+            "https://verifier.example/post",
+            "https://verifier.example/post",
+        )
+        .unwrap();
+        check_response_uri(
+            "redirect_uri:https://verifier.example/cb",
+            "https://verifier.example/cb",
+        )
+        .unwrap();
+        // Relaying the response to another verifier
+        assert!(check_response_uri(
+            "https://verifier.example/post",
+            "https://attacker.example/post"
+        )
+        .is_err());
+        assert!(check_response_uri(
+            "redirect_uri:https://verifier.example/cb",
+            "https://verifier.example/other"
+        )
+        .is_err());
+        // Client IDs which are not URIs need a signed request object
+        assert!(check_response_uri("verifier", "https://verifier.example/post").is_err());
+        assert!(
+            check_response_uri("did:example:verifier", "https://verifier.example/post").is_err()
+        );
+        assert!(check_response_uri(
+            "x509_san_dns:verifier.example",
+            "https://verifier.example/post"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn response_params() {
+        let response = AuthorizationResponse {
+            vp_token: json!("eyJhbGciOiJFUzI1NiJ9.e30.c2ln~"),
+            presentation_submission: PresentationSubmission {
+                id: "1".to_string(),
+                definition_id: "example".to_string(),
+                descriptor_map: vec![],
+            },
+            state: Some("xyz".to_string()),
+        };
+        let params = response.params().unwrap();
+        assert_eq!(
+            params[0],
+            ("vp_token", "eyJhbGciOiJFUzI1NiJ9.e30.c2ln~".to_string())
+        );
+        let submission: Value = serde_json::from_str(&params[1].1).unwrap();
+        assert_eq!(submission["definition_id"], json!("example"));
+        assert_eq!(params[2], ("state", "xyz".to_string()));
+    }
+}
@@ -1,26 +1,132 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
 use structopt::StructOpt;
 
-use didkit::{HTTPDIDResolver, SeriesResolver, DID_METHODS};
+use didkit::ipfs::{self, IpfsGateway, DEFAULT_GATEWAY};
+use didkit::resolver::{
+    MethodFilter, MethodRouter, MultikeyNormalizer, ResolutionLogger, ResolutionTimeout,
+    ResolverCache, ResolverStack,
+};
+use didkit::resolver_config::ResolverFederation;
+use didkit::{HTTPDIDResolver, SeriesResolver, DID_METHODS, JWK};
 
 #[derive(StructOpt, Debug, Clone, Default)]
 pub struct ResolverOptions {
     #[structopt(env, short = "r", long, parse(from_str = HTTPDIDResolver::new))]
-    /// Fallback DID Resolver HTTP(S) endpoint, for non-built-in DID methods.
+    /// Fallback DID Resolver HTTP(S) endpoint, for non-built-in DID methods. Same as a `*`
+    /// endpoint in a resolver configuration.
     pub did_resolver: Option<HTTPDIDResolver>,
     #[structopt(env, short = "R", long, parse(from_str = HTTPDIDResolver::new))]
-    /// Override DID Resolver HTTP(S) endpoint, for all DID methods.
+    /// Override DID Resolver HTTP(S) endpoint, for all DID methods. Same as a `*` endpoint with
+    /// `overrideBuiltIn` in a resolver configuration.
     pub did_resolver_override: Option<HTTPDIDResolver>,
+    #[structopt(
+        env = "RESOLVER_CACHE_TTL",
+        long = "resolver-cache-ttl",
+        parse(try_from_str = ResolverCache::from_config_str)
+    )]
+    /// Cache DID resolution results for the given number of seconds. Per-method TTLs may follow,
+    /// e.g. "300,web=60".
+    pub resolver_cache: Option<Arc<ResolverCache>>,
+    #[structopt(env, long, use_delimiter = true)]
+    /// Only resolve DIDs of these methods (comma-separated).
+    pub resolver_allow_methods: Option<Vec<String>>,
+    #[structopt(env, long, use_delimiter = true)]
+    /// Do not resolve DIDs of these methods (comma-separated).
+    pub resolver_deny_methods: Vec<String>,
+    #[structopt(long = "resolver-route", number_of_values = 1)]
+    /// Resolve DIDs of a method using a DID Resolver HTTP(S) endpoint, as METHOD=URL. May be
+    /// repeated.
+    pub resolver_routes: Vec<ResolverRoute>,
+    #[structopt(env, long, parse(try_from_str = ResolverFederation::from_file))]
+    /// Resolver configuration file: DID Resolver HTTP(S) endpoints by DID method, in failover
+    /// order, with health check intervals.
+    pub resolver_config: Option<Arc<ResolverFederation>>,
+    #[structopt(long)]
+    /// Log DID resolutions to standard error.
+    pub resolver_log: bool,
+    #[structopt(env, long)]
+    /// Fail DID resolutions taking longer than the given number of seconds.
+    pub resolver_timeout: Option<u64>,
+    #[structopt(env, long, default_value = DEFAULT_GATEWAY)]
+    /// IPFS HTTP gateways (comma-separated), for did:ipid and ipfs:// contexts and schemas.
+    /// Content is checked against its CID.
+    pub ipfs_gateway: IpfsGateway,
+}
+
+/// DID method routed to a specific DID resolver endpoint.
+#[derive(Debug, Clone)]
+pub struct ResolverRoute {
+    pub method: String,
+    pub resolver: Arc<HTTPDIDResolver>,
+}
+
+impl FromStr for ResolverRoute {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(method), Some(url)) if !method.is_empty() && !url.is_empty() => Ok(Self {
+                method: method.to_string(),
+                resolver: Arc::new(HTTPDIDResolver::new(url)),
+            }),
+            _ => Err(format!("Expected METHOD=URL: {}", s)),
+        }
+    }
 }
 
 impl ResolverOptions {
-    pub fn to_resolver<'a>(&'a self) -> SeriesResolver<'a> {
+    pub fn to_resolver<'a>(&'a self) -> ResolverStack<SeriesResolver<'a>> {
+        // Contexts and schemas are fetched outside of DID resolution, with the same gateway.
+        ipfs::set_gateway(self.ipfs_gateway.clone());
         let mut resolvers = vec![DID_METHODS.to_resolver()];
+        resolvers.push(&self.ipfs_gateway);
         if let Some(http_did_resolver) = &self.did_resolver {
             resolvers.push(http_did_resolver);
         }
         if let Some(http_did_resolver) = &self.did_resolver_override {
             resolvers.insert(0, http_did_resolver);
         }
-        SeriesResolver { resolvers }
+        let mut stack =
+            ResolverStack::new(SeriesResolver { resolvers }).layer(Arc::new(MultikeyNormalizer));
+        if self.resolver_log {
+            stack = stack.layer(Arc::new(ResolutionLogger));
+        }
+        if let Some(timeout) = self.resolver_timeout {
+            stack = stack.layer(Arc::new(ResolutionTimeout(Duration::from_secs(timeout))));
+        }
+        if self.resolver_allow_methods.is_some() || !self.resolver_deny_methods.is_empty() {
+            stack = stack.layer(Arc::new(MethodFilter {
+                allow: self.resolver_allow_methods.clone(),
+                deny: self.resolver_deny_methods.clone(),
+            }));
+        }
+        if let Some(cache) = &self.resolver_cache {
+            stack = stack.layer(cache.clone());
+        }
+        if !self.resolver_routes.is_empty() {
+            let router = self
+                .resolver_routes
+                .iter()
+                .fold(MethodRouter::new(), |router, route| {
+                    router.route(&route.method, route.resolver.clone())
+                });
+            stack = stack.layer(Arc::new(router));
+        }
+        if let Some(federation) = &self.resolver_config {
+            stack = stack.layer(federation.clone());
+        }
+        stack
     }
 }
+
+/// Read a JWK from a file, such as the file of a `--key-path` option.
+pub fn read_jwk(path: &Path) -> JWK {
+    let key_file = File::open(path).unwrap();
+    serde_json::from_reader(BufReader::new(key_file)).unwrap()
+}
@@ -0,0 +1,70 @@
+//! JSON output of the commands.
+//!
+//! With the global `--canonical-json` option, JSON written to standard output is canonical
+//! ([`didkit::canonical_json`]): its object members are sorted, without whitespace, and the
+//! arrays of verification results that are sets are sorted, so that the output of two runs can be
+//! diffed, and hashed or signed reproducibly.
+
+use std::io::{stdout, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use didkit::canonical_json;
+use serde::Serialize;
+
+static CANONICAL: AtomicBool = AtomicBool::new(false);
+
+/// Output canonical JSON from now on.
+pub fn set_canonical(canonical: bool) {
+    CANONICAL.store(canonical, Ordering::SeqCst);
+}
+
+pub fn is_canonical() -> bool {
+    CANONICAL.load(Ordering::SeqCst)
+}
+
+/// Serialize a value as JSON: canonical, or compact.
+pub fn to_json_string<T: Serialize + ?Sized>(value: &T) -> String {
+    if is_canonical() {
+        canonical_json::to_string(value).unwrap()
+    } else {
+        serde_json::to_string(value).unwrap()
+    }
+}
+
+/// Write a value to standard output as JSON: canonical, or compact, without a newline.
+pub fn write_json<T: Serialize + ?Sized>(value: &T) {
+    let mut stdout_writer = BufWriter::new(stdout());
+    stdout_writer
+        .write_all(to_json_string(value).as_bytes())
+        .unwrap();
+}
+
+/// Write a value to standard output as JSON: canonical, or pretty-printed.
+pub fn write_json_pretty<T: Serialize + ?Sized>(value: &T) {
+    if is_canonical() {
+        write_json(value);
+    } else {
+        serde_json::to_writer_pretty(BufWriter::new(stdout()), value).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_output() {
+        let report = json!({ "checks": ["proof"], "warnings": ["b", "a"] });
+        assert_eq!(
+            to_json_string(&report),
+            r#"{"checks":["proof"],"warnings":["b","a"]}"#
+        );
+        set_canonical(true);
+        assert_eq!(
+            to_json_string(&report),
+            r#"{"checks":["proof"],"warnings":["a","b"]}"#
+        );
+        set_canonical(false);
+    }
+}
@@ -0,0 +1,595 @@
+//! `didkit repl`: an interactive shell that keeps a key, resolver options, variables and the last
+//! result between commands.
+//!
+//! Each line runs one or more `didkit` subcommands, as separate processes of the current
+//! executable, or a built-in command. Words are split at whitespace, unless quoted, and expanded:
+//! `$name` to the value of a variable, and `@name` to the path of a file containing it. `|`
+//! passes the output of a command, or the value of a variable, as input of the next, and
+//! `name = ...` sets a variable to the output of the line. The output of the last command is also
+//! kept in the `_` variable.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use structopt::clap::{App, AppSettings, ErrorKind};
+use structopt::StructOpt;
+
+use didkit::JWK;
+
+use crate::output;
+
+/// Variable set to the output of the last command.
+pub const LAST_RESULT: &str = "_";
+
+const BUILTINS: &[(&str, &str)] = &[
+    ("help", "Show this help"),
+    ("exit", "Leave the shell"),
+    ("vars", "List variables"),
+    ("show", "show <name>: print a variable"),
+    ("let", "let <name> <words...>: set a variable"),
+    (
+        "load",
+        "load <name> <path>: set a variable to the contents of a file",
+    ),
+    ("save", "save <name> <path>: write a variable to a file"),
+    ("unset", "unset <name>: remove a variable"),
+    (
+        "key",
+        "key <path>|$name: use a JWK as the key of the following commands",
+    ),
+    (
+        "resolver",
+        "resolver <url>: use a fallback DID resolver HTTP(S) endpoint",
+    ),
+    (
+        "resolver-config",
+        "resolver-config <path>: use a resolver configuration file",
+    ),
+    (
+        "env",
+        "env [<name> [<value>]]: list, set or unset environment variables of commands",
+    ),
+];
+
+#[derive(StructOpt, Debug)]
+pub struct ReplCmd {
+    /// File to keep the command history in, between sessions
+    #[structopt(long, parse(from_os_str))]
+    history: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Literal word, without its quotes
+    Word(String),
+    /// `$name`: the value of a variable
+    Var(String),
+    /// `@name`: the path of a file containing the value of a variable
+    File(String),
+    /// `|`: the output of a command as input of the next
+    Pipe,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    fn end_word(tokens: &mut Vec<Token>, word: &mut Option<(String, bool)>) {
+        let (word, quoted) = match word.take() {
+            Some(word) => word,
+            None => return,
+        };
+        let token = match (quoted, word.get(..1)) {
+            (false, Some("$")) if word.len() > 1 => Token::Var(word[1..].to_string()),
+            (false, Some("@")) if word.len() > 1 => Token::File(word[1..].to_string()),
+            _ => Token::Word(word),
+        };
+        tokens.push(token);
+    }
+    let mut tokens = Vec::new();
+    let mut word: Option<(String, bool)> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let (word, quoted) = word.get_or_insert_with(Default::default);
+                *quoted = true;
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unterminated quote: {}", c)),
+                    }
+                }
+            }
+            '|' => {
+                end_word(&mut tokens, &mut word);
+                tokens.push(Token::Pipe);
+            }
+            c if c.is_whitespace() => end_word(&mut tokens, &mut word),
+            c => word.get_or_insert_with(Default::default).0.push(c),
+        }
+    }
+    end_word(&mut tokens, &mut word);
+    Ok(tokens)
+}
+
+/// A parsed line: commands, each given the output of the previous one, and the variable to set
+/// to the output of the last one.
+#[derive(Debug, PartialEq)]
+struct Line {
+    assign: Option<String>,
+    pipeline: Vec<Vec<Token>>,
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse(line: &str) -> Result<Line, String> {
+    let mut tokens = tokenize(line)?;
+    let assign = match tokens.get(..2) {
+        Some([Token::Word(name), Token::Word(eq)]) if eq == "=" => {
+            if !is_name(name) {
+                return Err(format!("Invalid variable name: {}", name));
+            }
+            let name = name.clone();
+            tokens.drain(..2);
+            Some(name)
+        }
+        _ => None,
+    };
+    let pipeline: Vec<Vec<Token>> = tokens
+        .split(|token| *token == Token::Pipe)
+        .map(|command| command.to_vec())
+        .collect();
+    if pipeline.iter().any(|command| command.is_empty()) {
+        return Err("Empty command".to_string());
+    }
+    Ok(Line { assign, pipeline })
+}
+
+struct Repl {
+    exe: PathBuf,
+    vars: BTreeMap<String, Vec<u8>>,
+    env: BTreeMap<String, String>,
+    files: PathBuf,
+}
+
+fn text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).trim_end().to_string()
+}
+
+fn print_output(output: &[u8]) {
+    let mut stdout = stdout();
+    stdout.write_all(output).unwrap();
+    if !output.is_empty() && !output.ends_with(b"\n") {
+        stdout.write_all(b"\n").unwrap();
+    }
+    stdout.flush().unwrap();
+}
+
+impl Repl {
+    fn new() -> Result<Self, String> {
+        let exe = std::env::current_exe().map_err(|e| format!("Unable to find didkit: {}", e))?;
+        let files = std::env::temp_dir().join(format!("didkit-repl-{}", std::process::id()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&files)
+            .map_err(|e| format!("Unable to create {}: {}", files.display(), e))?;
+        Ok(Self {
+            exe,
+            vars: BTreeMap::new(),
+            env: BTreeMap::new(),
+            files,
+        })
+    }
+
+    fn var(&self, name: &str) -> Result<&[u8], String> {
+        self.vars
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| format!("Unknown variable: {}", name))
+    }
+
+    fn expand(&self, tokens: &[Token]) -> Result<Vec<String>, String> {
+        tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(word) => Ok(word.clone()),
+                Token::Var(name) => Ok(text(self.var(name)?)),
+                Token::File(name) => {
+                    let path = self.files.join(name);
+                    fs::write(&path, self.var(name)?)
+                        .map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+                    Ok(path.display().to_string())
+                }
+                Token::Pipe => unreachable!(),
+            })
+            .collect()
+    }
+
+    /// Run a didkit subcommand, and return its output, and whether it succeeded.
+    fn run_command(
+        &self,
+        args: &[String],
+        input: Option<Vec<u8>>,
+    ) -> Result<(Vec<u8>, bool), String> {
+        let mut command = Command::new(&self.exe);
+        if output::is_canonical() {
+            command.arg("--canonical-json");
+        }
+        let mut child = command
+            .args(args)
+            .envs(&self.env)
+            .stdin(match input {
+                Some(_) => Stdio::piped(),
+                None => Stdio::inherit(),
+            })
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Unable to run didkit: {}", e))?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            // Write from another thread, so that a command with a lot of output does not block.
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            });
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Unable to run didkit: {}", e))?;
+        if !output.status.success() {
+            match output.status.code() {
+                Some(code) => eprintln!("{}: exit status {}", args[0], code),
+                None => eprintln!("{}: terminated", args[0]),
+            }
+        }
+        Ok((output.stdout, output.status.success()))
+    }
+
+    fn run_line(&mut self, line: Line) -> Result<bool, String> {
+        if let (None, [command]) = (&line.assign, &line.pipeline[..]) {
+            if let Some(Token::Word(name)) = command.first() {
+                if BUILTINS.iter().any(|(builtin, _)| builtin == name) {
+                    let args = self.expand(&command[1..])?;
+                    return self.run_builtin(name, &args);
+                }
+            }
+        }
+        let mut output = None;
+        for command in &line.pipeline {
+            if let [Token::Var(name)] = &command[..] {
+                output = Some(self.var(name)?.to_vec());
+                continue;
+            }
+            let args = self.expand(command)?;
+            let (result, success) = self.run_command(&args, output.take())?;
+            self.vars.insert(LAST_RESULT.to_string(), result.clone());
+            if !success {
+                print_output(&result);
+                return Ok(true);
+            }
+            output = Some(result);
+        }
+        let output = output.unwrap_or_default();
+        match line.assign {
+            Some(name) => {
+                self.vars.insert(name, output);
+            }
+            None => print_output(&output),
+        }
+        Ok(true)
+    }
+
+    fn run_builtin(&mut self, name: &str, args: &[String]) -> Result<bool, String> {
+        let arg = |i: usize| -> Result<&str, String> {
+            args.get(i)
+                .map(String::as_str)
+                .ok_or_else(|| format!("Usage: {}", usage(name)))
+        };
+        match name {
+            "help" => {
+                println!("Commands are didkit subcommands, or:");
+                for (builtin, description) in BUILTINS {
+                    println!("  {:<16} {}", builtin, description);
+                }
+            }
+            "exit" => return Ok(false),
+            "vars" => {
+                for (name, value) in &self.vars {
+                    let value = text(value);
+                    let first_line = value.lines().next().unwrap_or_default();
+                    println!("{} = {:.60}", name, first_line);
+                }
+            }
+            "show" => print_output(self.var(arg(0)?)?),
+            "let" => {
+                let name = arg(0)?;
+                if !is_name(name) {
+                    return Err(format!("Invalid variable name: {}", name));
+                }
+                self.vars
+                    .insert(name.to_string(), args[1..].join(" ").into_bytes());
+            }
+            "load" => {
+                let (name, path) = (arg(0)?, arg(1)?);
+                if !is_name(name) {
+                    return Err(format!("Invalid variable name: {}", name));
+                }
+                let value =
+                    fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+                self.vars.insert(name.to_string(), value);
+            }
+            "save" => {
+                let (name, path) = (arg(0)?, arg(1)?);
+                fs::write(path, self.var(name)?)
+                    .map_err(|e| format!("Unable to write {}: {}", path, e))?;
+            }
+            "unset" => {
+                self.vars.remove(arg(0)?);
+            }
+            "key" => {
+                // A key given as `$name` is already expanded to a JWK.
+                let source = arg(0)?;
+                let key = match source.starts_with('{') {
+                    true => source.as_bytes().to_vec(),
+                    false => {
+                        fs::read(source).map_err(|e| format!("Unable to read {}: {}", source, e))?
+                    }
+                };
+                let jwk: JWK = serde_json::from_slice(&key)
+                    .map_err(|e| format!("Unable to parse key: {}", e))?;
+                let jwk = serde_json::to_string(&jwk).map_err(|e| e.to_string())?;
+                self.env.remove("KEY_PATH");
+                self.env.insert("JWK".to_string(), jwk.clone());
+                self.vars.insert("key".to_string(), jwk.into_bytes());
+            }
+            "resolver" => {
+                self.env
+                    .insert("DID_RESOLVER".to_string(), arg(0)?.to_string());
+            }
+            "resolver-config" => {
+                let path = arg(0)?;
+                let path = fs::canonicalize(path)
+                    .map_err(|e| format!("Unable to find {}: {}", path, e))?;
+                self.env
+                    .insert("RESOLVER_CONFIG".to_string(), path.display().to_string());
+            }
+            "env" => match (args.first(), args.get(1)) {
+                (None, _) => {
+                    for (name, value) in &self.env {
+                        match &name[..] {
+                            "JWK" => println!("{}=(hidden)", name),
+                            _ => println!("{}={}", name, value),
+                        }
+                    }
+                }
+                (Some(name), None) => {
+                    self.env.remove(name);
+                }
+                (Some(name), Some(_)) => {
+                    self.env.insert(name.clone(), args[1..].join(" "));
+                }
+            },
+            _ => unreachable!(),
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for Repl {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.files);
+    }
+}
+
+fn usage(builtin: &str) -> &'static str {
+    BUILTINS
+        .iter()
+        .find(|(name, _)| *name == builtin)
+        .map_or("", |(_, description)| description)
+}
+
+/// Names of the subcommands listed in the help of a command.
+fn help_subcommands(app: App, args: &[&str]) -> Vec<String> {
+    let help = match app
+        .setting(AppSettings::ColorNever)
+        .get_matches_from_safe(args.iter().copied())
+    {
+        Err(err) if err.kind == ErrorKind::HelpDisplayed => err.message,
+        _ => return Vec::new(),
+    };
+    help.lines()
+        .skip_while(|line| !line.starts_with("SUBCOMMANDS:"))
+        .skip(1)
+        // Descriptions that wrap are indented further.
+        .filter_map(|line| line.strip_prefix("    "))
+        .filter(|line| !line.starts_with(' '))
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "help")
+        .map(String::from)
+        .collect()
+}
+
+struct ReplHelper {
+    /// Subcommands, with their own subcommands
+    commands: BTreeMap<String, Vec<String>>,
+    vars: Vec<String>,
+    filenames: FilenameCompleter,
+}
+
+impl ReplHelper {
+    fn new(app: App) -> Self {
+        let commands = help_subcommands(app.clone(), &["didkit", "--help"])
+            .into_iter()
+            .map(|name| {
+                let subcommands = help_subcommands(app.clone(), &["didkit", &name, "--help"]);
+                (name, subcommands)
+            })
+            .collect();
+        Self {
+            commands,
+            vars: Vec::new(),
+            filenames: FilenameCompleter::new(),
+        }
+    }
+}
+
+fn pairs<'a>(names: impl Iterator<Item = &'a str>, prefix: &str, sigil: &str) -> Vec<Pair> {
+    names
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| Pair {
+            display: name.to_string(),
+            replacement: format!("{}{}", sigil, name),
+        })
+        .collect()
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(|c: char| c.is_whitespace() || c == '|')
+            .map_or(0, |i| i + 1);
+        let word = &before[start..];
+        if word.starts_with('$') || word.starts_with('@') {
+            let vars = self.vars.iter().map(String::as_str);
+            return Ok((start, pairs(vars, &word[1..], &word[..1])));
+        }
+        let command = before[..start].rsplit('|').next().unwrap_or_default();
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let words = match &words[..] {
+            [_, "=", words @ ..] => words,
+            words => words,
+        };
+        match words {
+            [] => {
+                let builtins = BUILTINS.iter().map(|(name, _)| *name);
+                let commands = self.commands.keys().map(String::as_str);
+                Ok((start, pairs(builtins.chain(commands), word, "")))
+            }
+            [command] if !self.commands.get(*command).map_or(true, Vec::is_empty) => {
+                let subcommands = self.commands[*command].iter().map(String::as_str);
+                Ok((start, pairs(subcommands, word, "")))
+            }
+            _ => self.filenames.complete(line, pos, ctx),
+        }
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+impl ReplCmd {
+    /// Run the shell, completing the subcommands of `app`.
+    pub fn run(self, app: App) {
+        let mut repl = match Repl::new() {
+            Ok(repl) => repl,
+            Err(err) => {
+                eprintln!("didkit: {}", err);
+                std::process::exit(1);
+            }
+        };
+        let mut editor = Editor::<ReplHelper>::new();
+        editor.set_helper(Some(ReplHelper::new(app)));
+        if let Some(ref history) = self.history {
+            // The history file does not exist before the first session.
+            let _ = editor.load_history(history);
+        }
+        eprintln!(
+            "didkit {}. Type help for built-in commands.",
+            env!("CARGO_PKG_VERSION")
+        );
+        loop {
+            let line = match editor.readline("didkit> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => {
+                    eprintln!("didkit: {}", err);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            editor.add_history_entry(line.as_str());
+            let result = parse(&line).and_then(|line| repl.run_line(line));
+            match result {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => eprintln!("{}", err),
+            }
+            if let Some(helper) = editor.helper_mut() {
+                helper.vars = repl.vars.keys().cloned().collect();
+            }
+        }
+        if let Some(ref history) = self.history {
+            if let Err(err) = editor.save_history(history) {
+                eprintln!("didkit: Unable to save history: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(
+            parse("vc = vc-issue-credential -k @key 'a b' | vc-verify-credential").unwrap(),
+            Line {
+                assign: Some("vc".to_string()),
+                pipeline: vec![
+                    vec![
+                        Token::Word("vc-issue-credential".to_string()),
+                        Token::Word("-k".to_string()),
+                        Token::File("key".to_string()),
+                        Token::Word("a b".to_string()),
+                    ],
+                    vec![Token::Word("vc-verify-credential".to_string())],
+                ],
+            }
+        );
+        assert_eq!(
+            parse("$vc|vc-content-id '$vc'").unwrap().pipeline,
+            vec![
+                vec![Token::Var("vc".to_string())],
+                vec![
+                    Token::Word("vc-content-id".to_string()),
+                    Token::Word("$vc".to_string()),
+                ],
+            ]
+        );
+        assert!(parse("key-to-did |").is_err());
+        assert!(parse("echo 'unterminated").is_err());
+        assert!(parse("a.b = key-to-did").is_err());
+    }
+}
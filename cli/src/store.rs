@@ -0,0 +1,160 @@
+//! `didkit store` subcommands: queryable store of issued and received credentials.
+
+use std::io::{stdin, BufReader};
+
+use serde_json::Value;
+use structopt::StructOpt;
+
+use didkit::runtime;
+use didkit::store::{CredentialQuery, CredentialStore, Direction};
+
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum StoreCmd {
+    /// Store a credential (or JWT) read from stdin, and output its ID in the store
+    Insert {
+        #[structopt(flatten)]
+        db: DatabaseArg,
+        /// Record the credential as issued, instead of received
+        #[structopt(long)]
+        issued: bool,
+    },
+    /// Output a stored credential, with its indexed properties
+    Get {
+        #[structopt(flatten)]
+        db: DatabaseArg,
+        /// ID in the store
+        id: String,
+    },
+    /// Find stored credentials, and output them one per line, most recently stored first
+    Query {
+        #[structopt(flatten)]
+        db: DatabaseArg,
+        /// Credential type
+        #[structopt(short, long = "type")]
+        type_: Option<String>,
+        /// Issuer DID
+        #[structopt(short, long)]
+        issuer: Option<String>,
+        /// Credential subject ID
+        #[structopt(short, long)]
+        subject: Option<String>,
+        /// Status, e.g. active or revoked
+        #[structopt(long)]
+        status: Option<String>,
+        /// Only issued credentials
+        #[structopt(long, conflicts_with = "received")]
+        issued: bool,
+        /// Only received credentials
+        #[structopt(long)]
+        received: bool,
+        #[structopt(long)]
+        limit: Option<u32>,
+        #[structopt(long)]
+        offset: Option<u32>,
+    },
+    /// Set the status of a stored credential
+    SetStatus {
+        #[structopt(flatten)]
+        db: DatabaseArg,
+        /// ID in the store
+        id: String,
+        /// New status, e.g. revoked or suspended
+        status: String,
+    },
+    /// Remove a credential from the store
+    Remove {
+        #[structopt(flatten)]
+        db: DatabaseArg,
+        /// ID in the store
+        id: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct DatabaseArg {
+    /// Database URL, e.g. sqlite://credentials.db?mode=rwc or postgres://localhost/didkit
+    #[structopt(short, long, env = "DIDKIT_STORE")]
+    database: String,
+}
+
+impl DatabaseArg {
+    async fn connect(&self) -> CredentialStore {
+        CredentialStore::connect(&self.database).await.unwrap()
+    }
+}
+
+fn not_found(id: &str) -> ! {
+    eprintln!("Credential not found: {}", id);
+    std::process::exit(1);
+}
+
+impl StoreCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            StoreCmd::Insert { db, issued } => {
+                let credential: Value = serde_json::from_reader(BufReader::new(stdin())).unwrap();
+                let direction = if issued {
+                    Direction::Issued
+                } else {
+                    Direction::Received
+                };
+                let id =
+                    rt.block_on(async { db.connect().await.insert(&credential, direction).await });
+                println!("{}", id.unwrap());
+            }
+            StoreCmd::Get { db, id } => {
+                let record = rt.block_on(async { db.connect().await.get(&id).await });
+                match record.unwrap() {
+                    Some(record) => output::write_json_pretty(&record),
+                    None => not_found(&id),
+                }
+            }
+            StoreCmd::Query {
+                db,
+                type_,
+                issuer,
+                subject,
+                status,
+                issued,
+                received,
+                limit,
+                offset,
+            } => {
+                let direction = match (issued, received) {
+                    (true, _) => Some(Direction::Issued),
+                    (_, true) => Some(Direction::Received),
+                    _ => None,
+                };
+                let query = CredentialQuery {
+                    type_,
+                    issuer,
+                    subject,
+                    status,
+                    direction,
+                    limit,
+                    offset,
+                };
+                let records = rt.block_on(async { db.connect().await.query(&query).await });
+                for record in records.unwrap() {
+                    println!("{}", output::to_json_string(&record));
+                }
+            }
+            StoreCmd::SetStatus { db, id, status } => {
+                let found =
+                    rt.block_on(async { db.connect().await.set_status(&id, &status).await });
+                if !found.unwrap() {
+                    not_found(&id);
+                }
+            }
+            StoreCmd::Remove { db, id } => {
+                let found = rt.block_on(async { db.connect().await.remove(&id).await });
+                if !found.unwrap() {
+                    not_found(&id);
+                }
+            }
+        }
+    }
+}
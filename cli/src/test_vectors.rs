@@ -0,0 +1,465 @@
+//! `didkit test-vectors` subcommands: generate fixtures for the [VC test suite][vc-test-suite] and
+//! the [DID test suite][did-test-suite] using the key types and proof formats compiled into this
+//! build, and check them, reporting conformance.
+//!
+//! Credential and presentation fixtures are files of the issued document (a JSON object, or a JWT
+//! as a JSON string), listed in `manifest.json` with whether they are expected to verify. DID
+//! resolution fixtures are in the DID test suite's resolver format: executions of `resolve`, with
+//! their input and output, and the expected outcome of each.
+//!
+//! [vc-test-suite]: https://github.com/w3c/vc-test-suite
+//! [did-test-suite]: https://github.com/w3c/did-test-suite
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use didkit::{
+    get_verification_method, issue_credential, issue_presentation, runtime, verify_credential,
+    verify_presentation, CredentialOrJWT, DIDResolver, IssueOptions, JWTOrLDPOptions,
+    PresentationOrJWT, ProofFormat, ResolutionInputMetadata, Source, VerifiableCredential,
+    VerifiablePresentation, VerificationResult, DID_METHODS, JWK, URI,
+};
+
+use crate::opts::ResolverOptions;
+use crate::output;
+use crate::vc_api::ConformanceReport;
+
+const MANIFEST: &str = "manifest.json";
+const INVALID_DID: &str = "did:key:z6Mk";
+
+#[derive(StructOpt, Debug)]
+pub enum TestVectorsCmd {
+    /// Generate test vectors into a directory, and report whether they verify
+    Generate {
+        /// Output directory
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+    /// Check the test vectors of a directory against their expected results, and report
+    /// conformance
+    Verify {
+        /// Directory containing manifest.json
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestVectorKind {
+    Credential,
+    Presentation,
+    DidResolution,
+}
+
+/// A test vector listed in the manifest.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TestVector {
+    pub id: String,
+    pub kind: TestVectorKind,
+    /// Path of the fixture, relative to the manifest
+    pub file: String,
+    /// Whether the credential or presentation is expected to verify. DID resolution fixtures
+    /// list their expected outcomes themselves.
+    pub valid: bool,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub generator: String,
+    pub generated: String,
+    /// Key types and proof formats of the generated vectors
+    pub features: Vec<String>,
+    pub vectors: Vec<TestVector>,
+}
+
+/// Key types to generate vectors for.
+fn key_types() -> Vec<(&'static str, Result<JWK, String>)> {
+    vec![
+        (
+            "ed25519",
+            JWK::generate_ed25519().map_err(|e| e.to_string()),
+        ),
+        (
+            "secp256k1",
+            JWK::generate_secp256k1().map_err(|e| e.to_string()),
+        ),
+        ("p256", JWK::generate_p256().map_err(|e| e.to_string())),
+    ]
+}
+
+fn format_name(proof_format: &ProofFormat) -> &'static str {
+    match proof_format {
+        ProofFormat::LDP => "ldp",
+        ProofFormat::JWT => "jwt",
+        ProofFormat::VcJose => "vc-jose",
+        ProofFormat::VcCose => "vc-cose",
+        ProofFormat::JPT => "jpt",
+    }
+}
+
+fn sample_credential(did: &str) -> Value {
+    json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "id": "urn:uuid:0b0f3a52-4a4e-4c3b-8d5c-1f7d7c9a2e01",
+        "type": ["VerifiableCredential"],
+        "issuer": did,
+        "issuanceDate": "2020-01-01T00:00:00Z",
+        "credentialSubject": {
+            "id": "did:example:ebfeb1f712ebc6f1c276e12ec21"
+        }
+    })
+}
+
+/// Change the subject of an issued credential, keeping its proof or signature.
+fn tamper(document: &Value) -> Value {
+    if let Value::String(jwt) = document {
+        let parts: Vec<&str> = jwt.split('.').collect();
+        let mut claims: Value = match parts.get(1).and_then(|payload| {
+            let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+            serde_json::from_slice(&bytes).ok()
+        }) {
+            Some(claims) => claims,
+            None => return document.clone(),
+        };
+        claims["sub"] = json!("did:example:tampered");
+        claims["vc"]["credentialSubject"]["id"] = json!("did:example:tampered");
+        let payload = base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD);
+        let mut parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+        parts[1] = payload;
+        return Value::String(parts.join("."));
+    }
+    let mut tampered = document.clone();
+    tampered["credentialSubject"]["id"] = json!("did:example:tampered");
+    tampered
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), value).map_err(|e| e.to_string())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Resolve a DID, as an execution of the DID test suite.
+async fn resolve_execution(did: &str, resolver: &dyn DIDResolver) -> Value {
+    let (res_meta, doc, doc_meta) = resolver
+        .resolve(did, &ResolutionInputMetadata::default())
+        .await;
+    json!({
+        "function": "resolve",
+        "input": { "did": did, "resolutionOptions": {} },
+        "output": {
+            "didResolutionMetadata": res_meta,
+            "didDocument": doc,
+            "didDocumentMetadata": doc_meta,
+        }
+    })
+}
+
+/// Issue the credential and presentation vectors for one key and proof format.
+async fn generate_documents(
+    key: &JWK,
+    proof_format: ProofFormat,
+    resolver: &dyn DIDResolver,
+) -> Result<(Value, Value), String> {
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(key, "key"))
+        .ok_or("Unable to generate DID")?;
+    let vm = get_verification_method(&did, resolver)
+        .await
+        .ok_or("Unable to get verification method")?;
+
+    let mut vc_options = JWTOrLDPOptions::default();
+    vc_options.proof_format = Some(proof_format.clone());
+    vc_options.ldp_options.verification_method = Some(URI::String(vm.clone()));
+    let vc_options = IssueOptions::try_from(vc_options).map_err(|e| e.to_string())?;
+    let credential: VerifiableCredential =
+        serde_json::from_value(sample_credential(&did)).map_err(|e| e.to_string())?;
+    let vc = issue_credential(credential, &vc_options, key, resolver)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut vp_options = JWTOrLDPOptions::default_for_vp();
+    vp_options.proof_format = Some(proof_format);
+    vp_options.ldp_options.verification_method = Some(URI::String(vm));
+    let vp_options = IssueOptions::try_from(vp_options).map_err(|e| e.to_string())?;
+    let presentation: VerifiablePresentation = serde_json::from_value(json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "type": ["VerifiablePresentation"],
+        "holder": did,
+        "verifiableCredential": vc,
+    }))
+    .map_err(|e| e.to_string())?;
+    let vp = issue_presentation(presentation, &vp_options, key, resolver)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((
+        serde_json::to_value(&vc).map_err(|e| e.to_string())?,
+        serde_json::to_value(&vp).map_err(|e| e.to_string())?,
+    ))
+}
+
+/// Generate test vectors into a directory. Failures to generate a vector are reported as failed
+/// checks.
+pub async fn generate(
+    dir: &Path,
+    resolver: &dyn DIDResolver,
+    report: &mut ConformanceReport,
+) -> Result<(), String> {
+    for subdir in &["vc", "did"] {
+        std::fs::create_dir_all(dir.join(subdir)).map_err(|e| e.to_string())?;
+    }
+    let mut manifest = Manifest {
+        generator: concat!("didkit ", env!("CARGO_PKG_VERSION")).to_string(),
+        generated: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        features: Vec::new(),
+        vectors: Vec::new(),
+    };
+    let mut executions = Vec::new();
+    for (key_type, key) in key_types() {
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                report.check(&format!("generate-{}", key_type), Err(e));
+                continue;
+            }
+        };
+        manifest.features.push(key_type.to_string());
+        if let Some(did) = DID_METHODS.generate(&Source::KeyAndPattern(&key, "key")) {
+            executions.push(resolve_execution(&did, resolver).await);
+        }
+        for proof_format in vec![ProofFormat::LDP, ProofFormat::JWT] {
+            let prefix = format!("{}-{}", key_type, format_name(&proof_format));
+            let (vc, vp) = match generate_documents(&key, proof_format, resolver).await {
+                Ok(documents) => documents,
+                Err(e) => {
+                    report.check(&format!("generate-{}", prefix), Err(e));
+                    continue;
+                }
+            };
+            let documents = vec![
+                (
+                    "credential",
+                    TestVectorKind::Credential,
+                    tamper(&vc),
+                    false,
+                    "credential with a subject changed after issuance",
+                ),
+                (
+                    "credential",
+                    TestVectorKind::Credential,
+                    vc,
+                    true,
+                    "credential",
+                ),
+                (
+                    "presentation",
+                    TestVectorKind::Presentation,
+                    vp,
+                    true,
+                    "presentation of a credential",
+                ),
+            ];
+            for (name, kind, document, valid, description) in documents {
+                let id = match valid {
+                    true => format!("{}-{}", prefix, name),
+                    false => format!("{}-{}-tampered", prefix, name),
+                };
+                let file = format!("vc/{}.json", id);
+                write_json(&dir.join(&file), &document)?;
+                manifest.vectors.push(TestVector {
+                    id,
+                    kind,
+                    file,
+                    valid,
+                    description: format!("{} {}", prefix, description),
+                });
+            }
+        }
+    }
+    for proof_format in &[ProofFormat::LDP, ProofFormat::JWT] {
+        manifest
+            .features
+            .push(format_name(proof_format).to_string());
+    }
+
+    let default_outcome: Vec<usize> = (0..executions.len()).collect();
+    executions.push(resolve_execution(INVALID_DID, resolver).await);
+    let resolution = json!({
+        "didMethod": "did:key",
+        "implementation": "DIDKit",
+        "implementer": "Spruce Systems, Inc.",
+        "expectedOutcomes": {
+            "defaultOutcome": default_outcome,
+            "invalidDidErrorOutcome": [executions.len() - 1],
+        },
+        "executions": executions,
+    });
+    let file = "did/did-key-resolution.json".to_string();
+    write_json(&dir.join(&file), &resolution)?;
+    manifest.vectors.push(TestVector {
+        id: "did-key-resolution".to_string(),
+        kind: TestVectorKind::DidResolution,
+        file,
+        valid: true,
+        description: "did:key resolution of the DIDs of the generated keys, and of an invalid DID"
+            .to_string(),
+    });
+    write_json(&dir.join(MANIFEST), &manifest)
+}
+
+/// Check that a verification matches the expected result.
+fn expect(verification: Result<VerificationResult, String>, valid: bool) -> Result<(), String> {
+    let result = verification?;
+    match (result.errors.is_empty(), valid) {
+        (true, true) | (false, false) => Ok(()),
+        (true, false) => Err("Expected verification to fail".to_string()),
+        (false, true) => Err(result.errors.join(", ")),
+    }
+}
+
+/// Check the executions of a DID resolution fixture against their expected outcomes: resolving
+/// each DID again must give the same DID document, and the error, if any, of its outcome.
+async fn check_resolution(
+    vector: &TestVector,
+    fixture: &Value,
+    resolver: &dyn DIDResolver,
+    report: &mut ConformanceReport,
+) {
+    let executions = fixture["executions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut outcomes = vec![None; executions.len()];
+    if let Some(expected) = fixture["expectedOutcomes"].as_object() {
+        for (outcome, indexes) in expected {
+            for index in indexes.as_array().into_iter().flatten() {
+                if let Some(slot) = index.as_u64().and_then(|i| outcomes.get_mut(i as usize)) {
+                    *slot = Some(outcome.as_str());
+                }
+            }
+        }
+    }
+    for (i, execution) in executions.iter().enumerate() {
+        let name = format!("{}-{}", vector.id, i);
+        let did = match execution["input"]["did"].as_str() {
+            Some(did) => did,
+            None => {
+                report.check(&name, Err("Missing input DID".to_string()));
+                continue;
+            }
+        };
+        let output = resolve_execution(did, resolver).await["output"].clone();
+        let error = output["didResolutionMetadata"]["error"].as_str();
+        let result = match outcomes[i] {
+            Some("defaultOutcome") if error.is_some() => {
+                Err(format!("Resolution failed: {}", error.unwrap_or_default()))
+            }
+            Some("defaultOutcome")
+                if output["didDocument"] != execution["output"]["didDocument"] =>
+            {
+                Err("DID document differs from the fixture".to_string())
+            }
+            Some("defaultOutcome") => Ok(()),
+            Some("invalidDidErrorOutcome") if error == Some("invalidDid") => Ok(()),
+            Some("notFoundErrorOutcome") if error == Some("notFound") => Ok(()),
+            Some(outcome) => Err(format!(
+                "Expected {}, got error {}",
+                outcome,
+                error.unwrap_or("none")
+            )),
+            None => Err("No expected outcome".to_string()),
+        };
+        report.check(&name, result);
+    }
+}
+
+/// Check the test vectors listed in the manifest of a directory.
+pub async fn check(dir: &Path, resolver: &dyn DIDResolver, report: &mut ConformanceReport) {
+    let manifest: Manifest = match read_json(&dir.join(MANIFEST)) {
+        Ok(manifest) => manifest,
+        Err(e) => return report.check(MANIFEST, Err(e)),
+    };
+    for vector in &manifest.vectors {
+        let fixture: Value = match read_json(&dir.join(&vector.file)) {
+            Ok(fixture) => fixture,
+            Err(e) => {
+                report.check(&vector.id, Err(e));
+                continue;
+            }
+        };
+        let verification = match vector.kind {
+            TestVectorKind::DidResolution => {
+                check_resolution(vector, &fixture, resolver, report).await;
+                continue;
+            }
+            TestVectorKind::Credential => {
+                match serde_json::from_value::<CredentialOrJWT>(fixture) {
+                    Ok(vc) => verify_credential(&vc, &JWTOrLDPOptions::default(), resolver)
+                        .await
+                        .map(VerificationResult::from)
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            TestVectorKind::Presentation => {
+                match serde_json::from_value::<PresentationOrJWT>(fixture) {
+                    Ok(vp) => {
+                        verify_presentation(&vp, &JWTOrLDPOptions::default_for_vp(), resolver)
+                            .await
+                            .map(VerificationResult::from)
+                            .map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+        report.check(&vector.id, expect(verification, vector.valid));
+    }
+}
+
+impl TestVectorsCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        let mut report = ConformanceReport::default();
+        match self {
+            TestVectorsCmd::Generate {
+                dir,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                rt.block_on(generate(&dir, &resolver, &mut report)).unwrap();
+                rt.block_on(check(&dir, &resolver, &mut report));
+            }
+            TestVectorsCmd::Verify {
+                dir,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                rt.block_on(check(&dir, &resolver, &mut report));
+            }
+        }
+        output::write_json_pretty(&report);
+        if report.failed > 0 {
+            std::process::exit(2);
+        }
+    }
+}
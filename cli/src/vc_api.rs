@@ -0,0 +1,443 @@
+//! `didkit vc-api` subcommands: client for a remote [VC-API][vc-api] implementation, with a
+//! conformance check of its issuer and verifier.
+//!
+//! [vc-api]: https://w3c-ccg.github.io/vc-api/
+
+use std::convert::TryFrom;
+use std::io::{stdin, Read};
+use std::path::PathBuf;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use didkit::{
+    get_verification_method, issue_credential, issue_presentation, runtime, verify_credential,
+    CredentialOrJWT, DIDResolver, IssueOptions, JWTOrLDPOptions, PresentationOrJWT, Source,
+    VerifiableCredential, VerifiablePresentation, VerificationResult, DID_METHODS, JWK,
+};
+
+use crate::opts::{read_jwk, ResolverOptions};
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum VcApiCmd {
+    /// Issue a credential read from stdin, using a remote issuer
+    Issue {
+        #[structopt(flatten)]
+        client: ClientOptions,
+    },
+    /// Create a verifiable presentation from a presentation read from stdin, using a remote holder
+    Prove {
+        #[structopt(flatten)]
+        client: ClientOptions,
+    },
+    /// Verify a credential, or with --presentation a presentation, read from stdin, using a remote
+    /// verifier
+    Verify {
+        #[structopt(flatten)]
+        client: ClientOptions,
+        /// Verify a presentation instead of a credential
+        #[structopt(long)]
+        presentation: bool,
+    },
+    /// Exercise the issue, verify and present flows of remote issuer and verifier implementations,
+    /// and report conformance results
+    Conformance {
+        /// Issuer base URL
+        #[structopt(long)]
+        issuer: String,
+        /// Verifier base URL. Default is the issuer base URL.
+        #[structopt(long)]
+        verifier: Option<String>,
+        /// Issuer ID to put in test credentials
+        #[structopt(long)]
+        issuer_id: String,
+        /// Filename of a JWK, to issue credentials and presentations locally for the verifier
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: Option<PathBuf>,
+        /// OAuth bearer token for requests
+        #[structopt(env = "VC_API_TOKEN", long, hide_env_values = true)]
+        token: Option<String>,
+        #[structopt(flatten)]
+        resolver_options: ResolverOptions,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ClientOptions {
+    /// Base URL of the VC-API implementation
+    #[structopt(short, long)]
+    endpoint: String,
+    /// Options to send with the request, as JSON
+    #[structopt(short, long, parse(try_from_str = serde_json::from_str))]
+    options: Option<Value>,
+    /// OAuth bearer token for requests
+    #[structopt(env = "VC_API_TOKEN", long, hide_env_values = true)]
+    token: Option<String>,
+}
+
+/// Client for one VC-API implementation.
+pub struct VcApiClient {
+    base_url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl VcApiClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// POST a JSON request, returning the response status and body. Non-JSON bodies are returned
+    /// as a string.
+    pub async fn post(&self, path: &str, body: &Value) -> Result<(u16, Value), reqwest::Error> {
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(body);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        let value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        Ok((status, value))
+    }
+}
+
+/// Result of one conformance check.
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct ConformanceReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    pub(crate) fn check(&mut self, name: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.passed += 1,
+            Err(_) => self.failed += 1,
+        }
+        self.results.push(CheckResult {
+            name: name.to_string(),
+            passed: result.is_ok(),
+            message: result.err(),
+        });
+    }
+}
+
+fn read_stdin_document() -> Value {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+    let input = input.trim();
+    serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.to_string()))
+}
+
+/// Check that a verify response indicates successful verification.
+fn verified((status, body): (u16, Value)) -> Result<(), String> {
+    if status != 200 {
+        return Err(format!("HTTP status {}: {}", status, body));
+    }
+    let result: VerificationResult =
+        serde_json::from_value(body).map_err(|e| format!("Invalid verification result: {}", e))?;
+    if !result.errors.is_empty() {
+        return Err(result.errors.join(", "));
+    }
+    Ok(())
+}
+
+fn sample_credential(issuer_id: &str) -> Value {
+    json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "id": "urn:uuid:5b3f4a0e-9d6c-4c8e-9f3a-d1d1c0a0f001",
+        "type": ["VerifiableCredential"],
+        "issuer": issuer_id,
+        "issuanceDate": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        "credentialSubject": {
+            "id": "did:example:d23dd687a7dc6787646f2eb98d0"
+        }
+    })
+}
+
+async fn conformance(
+    issuer: &VcApiClient,
+    verifier: &VcApiClient,
+    issuer_id: &str,
+    key: Option<&JWK>,
+    resolver: &dyn DIDResolver,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+
+    let issued = match issuer
+        .post(
+            "/credentials/issue",
+            &json!({ "credential": sample_credential(issuer_id) }),
+        )
+        .await
+    {
+        Ok((201, body)) | Ok((200, body)) => serde_json::from_value::<VerifiableCredential>(body)
+            .map_err(|e| format!("Invalid credential: {}", e))
+            .and_then(|vc| match vc.proof {
+                Some(_) => Ok(vc),
+                None => Err("Issued credential has no proof".to_string()),
+            }),
+        Ok((status, body)) => Err(format!("HTTP status {}: {}", status, body)),
+        Err(e) => Err(e.to_string()),
+    };
+    report.check("issue", issued.as_ref().map(|_| ()).map_err(Clone::clone));
+
+    if let Ok(vc) = issued {
+        let local = verify_credential(
+            &CredentialOrJWT::Credential(vc.clone()),
+            &JWTOrLDPOptions::default(),
+            resolver,
+        )
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|verification| {
+            let result = VerificationResult::from(verification);
+            if result.errors.is_empty() {
+                Ok(())
+            } else {
+                Err(result.errors.join(", "))
+            }
+        });
+        report.check("issued-credential-verifies-with-didkit", local);
+
+        let remote = verifier
+            .post(
+                "/credentials/verify",
+                &json!({ "verifiableCredential": vc }),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(verified);
+        report.check("verify-credential", remote);
+
+        let mut tampered = serde_json::to_value(&vc).unwrap_or_default();
+        tampered["credentialSubject"]["id"] = json!("did:example:tampered");
+        let rejected = match verifier
+            .post(
+                "/credentials/verify",
+                &json!({ "verifiableCredential": tampered }),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .map(verified)
+        {
+            Ok(Ok(())) => Err("Tampered credential verified".to_string()),
+            Ok(Err(_)) => Ok(()),
+            Err(e) => Err(e),
+        };
+        report.check("reject-tampered-credential", rejected);
+    }
+
+    if let Some(key) = key {
+        let local = present_locally(issuer_id, key, resolver).await;
+        let remote = match local {
+            Ok(vp) => verifier
+                .post(
+                    "/presentations/verify",
+                    &json!({ "verifiablePresentation": vp }),
+                )
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(verified),
+            Err(e) => Err(format!("Unable to create presentation locally: {}", e)),
+        };
+        report.check("verify-presentation", remote);
+    }
+    report
+}
+
+/// Issue a credential and a presentation of it, using a local key and its did:key DID.
+async fn present_locally(
+    issuer_id: &str,
+    key: &JWK,
+    resolver: &dyn DIDResolver,
+) -> Result<PresentationOrJWT, String> {
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(key, "key"))
+        .ok_or("Unable to generate DID")?;
+    let vm = get_verification_method(&did, resolver)
+        .await
+        .ok_or("Unable to get verification method")?;
+    let mut ldp_options = JWTOrLDPOptions::default();
+    ldp_options.ldp_options.verification_method = Some(didkit::URI::String(vm));
+    let options = IssueOptions::try_from(ldp_options).map_err(|e| e.to_string())?;
+
+    let mut credential = sample_credential(issuer_id);
+    credential["issuer"] = json!(did);
+    credential["credentialSubject"]["id"] = json!(did);
+    let credential: VerifiableCredential =
+        serde_json::from_value(credential).map_err(|e| e.to_string())?;
+    let vc = issue_credential(credential, &options, key, resolver)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let presentation: VerifiablePresentation = serde_json::from_value(json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "type": ["VerifiablePresentation"],
+        "holder": did,
+        "verifiableCredential": vc,
+    }))
+    .map_err(|e| e.to_string())?;
+    let mut vp_options = JWTOrLDPOptions::default_for_vp();
+    vp_options.ldp_options.verification_method = options.ldp_options.verification_method.clone();
+    let vp_options = IssueOptions::try_from(vp_options).map_err(|e| e.to_string())?;
+    issue_presentation(presentation, &vp_options, key, resolver)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+impl VcApiCmd {
+    pub fn run(self) {
+        let rt = runtime::get().unwrap();
+        match self {
+            VcApiCmd::Issue { client } => {
+                let api = VcApiClient::new(&client.endpoint, client.token);
+                let body = json!({
+                    "credential": read_stdin_document(),
+                    "options": client.options,
+                });
+                let (status, response) =
+                    rt.block_on(api.post("/credentials/issue", &body)).unwrap();
+                output::write_json(&response);
+                if status >= 300 {
+                    std::process::exit(2);
+                }
+            }
+            VcApiCmd::Prove { client } => {
+                let api = VcApiClient::new(&client.endpoint, client.token);
+                let body = json!({
+                    "presentation": read_stdin_document(),
+                    "options": client.options,
+                });
+                let (status, response) =
+                    rt.block_on(api.post("/credentials/prove", &body)).unwrap();
+                output::write_json(&response);
+                if status >= 300 {
+                    std::process::exit(2);
+                }
+            }
+            VcApiCmd::Verify {
+                client,
+                presentation,
+            } => {
+                let api = VcApiClient::new(&client.endpoint, client.token);
+                let (path, property) = if presentation {
+                    ("/presentations/verify", "verifiablePresentation")
+                } else {
+                    ("/credentials/verify", "verifiableCredential")
+                };
+                let body = json!({
+                    property: read_stdin_document(),
+                    "options": client.options,
+                });
+                let response = rt.block_on(api.post(path, &body)).unwrap();
+                output::write_json(&response.1);
+                if verified(response).is_err() {
+                    std::process::exit(2);
+                }
+            }
+            VcApiCmd::Conformance {
+                issuer,
+                verifier,
+                issuer_id,
+                key_path,
+                token,
+                resolver_options,
+            } => {
+                let resolver = resolver_options.to_resolver();
+                let key = key_path.as_deref().map(read_jwk);
+                let verifier =
+                    VcApiClient::new(verifier.as_deref().unwrap_or(&issuer), token.clone());
+                let issuer = VcApiClient::new(&issuer, token);
+                let report = rt.block_on(conformance(
+                    &issuer,
+                    &verifier,
+                    &issuer_id,
+                    key.as_ref(),
+                    &resolver,
+                ));
+                output::write_json_pretty(&report);
+                if report.failed > 0 {
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use didkit::verify_presentation;
+
+    #[test]
+    fn verify_response() {
+        let success = json!({ "checks": [], "warnings": [], "errors": [] });
+        assert!(verified((200, success.clone())).is_ok());
+        assert!(verified((400, success)).is_err());
+        let failure = json!({ "checks": [], "warnings": [], "errors": ["signature error"] });
+        assert_eq!(
+            verified((200, failure)).unwrap_err(),
+            "signature error".to_string()
+        );
+        assert!(verified((200, json!("Internal Server Error"))).is_err());
+    }
+
+    #[test]
+    fn conformance_report() {
+        let mut report = ConformanceReport::default();
+        report.check("issue", Ok(()));
+        report.check("verify-credential", Err("HTTP status 500".to_string()));
+        assert_eq!((report.passed, report.failed), (1, 1));
+        assert!(report.results[0].passed);
+        assert_eq!(
+            report.results[1].message.as_deref(),
+            Some("HTTP status 500")
+        );
+    }
+
+    #[test]
+    fn client_base_url() {
+        let client = VcApiClient::new("https://example.net/vc-api/", None);
+        assert_eq!(client.base_url, "https://example.net/vc-api");
+    }
+
+    #[test]
+    fn present_with_local_key() {
+        let key: JWK = serde_json::from_str(include_str!("../tests/ed25519-key.jwk")).unwrap();
+        let resolver = ResolverOptions::default().to_resolver();
+        let rt = runtime::get().unwrap();
+        let vp = rt
+            .block_on(present_locally("did:example:issuer", &key, &resolver))
+            .unwrap();
+        let report = rt
+            .block_on(verify_presentation(
+                &vp,
+                &JWTOrLDPOptions::default_for_vp(),
+                &resolver,
+            ))
+            .unwrap();
+        assert!(report.is_success(), "{:?}", report);
+    }
+}
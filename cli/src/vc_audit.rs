@@ -0,0 +1,618 @@
+//! `didkit vc-audit`: report which credentials of a batch are expired, near expiry or revoked.
+//!
+//! Credentials are read from the files of a directory, recursively, or from an NDJSON stream (a
+//! file, or standard input) of one credential per line. A credential is a JSON object, or a JWT,
+//! as is or as a JSON string. In a directory, `.json`, `.jsonld` and `.jwt` files hold one
+//! credential each, and `.ndjson` and `.jsonl` files are read as streams.
+//!
+//! A credential is expired or near expiry according to its `expirationDate`, `validUntil`, or the
+//! `exp` claim of its JWT. Unless skipped, its `StatusList2021Entry` and
+//! `RevocationList2020Status` entries are checked: each status list is fetched and verified once
+//! for the whole batch, and the credential is revoked or suspended if its bit is set. The proofs
+//! of the credentials themselves are not verified.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::Value;
+use structopt::StructOpt;
+
+use didkit::schema::subjects;
+use didkit::{runtime, verify_credential, CredentialOrJWT, DIDResolver, JWTOrLDPOptions};
+
+use crate::opts::ResolverOptions;
+use crate::output;
+
+/// Status entry types, with their list URL and index properties.
+const STATUS_ENTRY_TYPES: &[(&str, &str, &str)] = &[
+    (
+        "StatusList2021Entry",
+        "statusListCredential",
+        "statusListIndex",
+    ),
+    (
+        "RevocationList2020Status",
+        "revocationListCredential",
+        "revocationListIndex",
+    ),
+];
+
+/// Extensions of files holding one credential.
+const CREDENTIAL_EXTENSIONS: &[&str] = &["json", "jsonld", "jwt"];
+
+/// Extensions of files holding a stream of credentials.
+const STREAM_EXTENSIONS: &[&str] = &["ndjson", "jsonl"];
+
+#[derive(StructOpt, Debug)]
+pub struct VcAuditCmd {
+    /// Directory of credentials, or NDJSON file of credentials. Default is NDJSON read from
+    /// stdin.
+    #[structopt(parse(from_os_str))]
+    input: Option<PathBuf>,
+    /// Number of days before its expiration from which a credential is near expiry
+    #[structopt(short, long, default_value = "30")]
+    window: i64,
+    /// Output format: json or csv
+    #[structopt(short, long, default_value = "json")]
+    format: AuditFormat,
+    /// Report valid credentials too
+    #[structopt(long)]
+    all: bool,
+    /// Do not fetch status lists, and report expiration only
+    #[structopt(long)]
+    skip_status: bool,
+    #[structopt(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for AuditFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(AuditFormat::Json),
+            "csv" => Ok(AuditFormat::Csv),
+            _ => Err(format!("Unknown format: {} (expected json or csv)", s)),
+        }
+    }
+}
+
+/// Status of an audited credential. When several apply, the last one is reported.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditStatus {
+    Valid,
+    NearExpiry,
+    Expired,
+    Suspended,
+    Revoked,
+    /// The credential could not be read, or its expiration date or status entry is malformed
+    Invalid,
+}
+
+impl AuditStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            AuditStatus::Valid => "valid",
+            AuditStatus::NearExpiry => "near-expiry",
+            AuditStatus::Expired => "expired",
+            AuditStatus::Suspended => "suspended",
+            AuditStatus::Revoked => "revoked",
+            AuditStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// Audit of a credential.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// File, or line of a stream, that the credential was read from, e.g. `creds.ndjson:3`
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<String>,
+    pub status: AuditStatus,
+    /// Reasons for the status, and status lists that could not be checked
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+/// Number of audited credentials, by status.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditSummary {
+    pub total: usize,
+    pub valid: usize,
+    pub near_expiry: usize,
+    pub expired: usize,
+    pub suspended: usize,
+    pub revoked: usize,
+    pub invalid: usize,
+}
+
+/// JSON output of `didkit vc-audit`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    pub summary: AuditSummary,
+    pub credentials: Vec<AuditEntry>,
+}
+
+impl AuditSummary {
+    fn count(&mut self, status: AuditStatus) {
+        self.total += 1;
+        *match status {
+            AuditStatus::Valid => &mut self.valid,
+            AuditStatus::NearExpiry => &mut self.near_expiry,
+            AuditStatus::Expired => &mut self.expired,
+            AuditStatus::Suspended => &mut self.suspended,
+            AuditStatus::Revoked => &mut self.revoked,
+            AuditStatus::Invalid => &mut self.invalid,
+        } += 1;
+    }
+}
+
+/// Decode the claims of a JWT without verifying it.
+fn jwt_claims(jwt: &str) -> Option<Value> {
+    let payload = jwt.split('~').next()?.split('.').nth(1)?;
+    let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Read a credential, either JSON or a JWT. The credential of a JWT is its `vc` claim, or its
+/// claims if it has none (VC-JOSE), with its `exp`, `iss` and `jti` claims as the expiration
+/// date, issuer and ID, if they are not in the credential.
+pub fn parse_credential(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    let jwt = if text.starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| e.to_string());
+    } else if text.starts_with('"') {
+        serde_json::from_str::<String>(text).map_err(|e| e.to_string())?
+    } else {
+        text.to_string()
+    };
+    let claims = jwt_claims(&jwt).ok_or("Not a JSON credential or JWT")?;
+    let mut credential = match claims.get("vc") {
+        Some(vc) if vc.is_object() => vc.clone(),
+        _ => claims.clone(),
+    };
+    if !credential.is_object() {
+        return Err("JWT claims are not an object".to_string());
+    }
+    let exp = claims
+        .get("exp")
+        .and_then(Value::as_i64)
+        .and_then(|exp| Utc.timestamp_opt(exp, 0).single());
+    if let Some(exp) = exp {
+        if credential.get("expirationDate").is_none() && credential.get("validUntil").is_none() {
+            credential["expirationDate"] = exp.to_rfc3339_opts(SecondsFormat::Secs, true).into();
+        }
+    }
+    for (claim, property) in &[("iss", "issuer"), ("jti", "id")] {
+        if let (Some(value), None) = (claims.get(claim), credential.get(property)) {
+            credential[*property] = value.clone();
+        }
+    }
+    Ok(credential)
+}
+
+/// Status list URLs and indexes of the status entries of a credential, of known types.
+fn status_entries(credential: &Value) -> Result<Vec<(String, usize)>, String> {
+    let entries = match credential.get("credentialStatus") {
+        Some(Value::Array(entries)) => entries.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(entry) => vec![entry],
+    };
+    let mut list_indexes = Vec::new();
+    for entry in entries {
+        let type_ = entry
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let (_, url_property, index_property) = match STATUS_ENTRY_TYPES
+            .iter()
+            .find(|(entry_type, _, _)| *entry_type == type_)
+        {
+            Some(entry_type) => entry_type,
+            None => continue,
+        };
+        let url = entry.get(*url_property).and_then(Value::as_str);
+        let index = match entry.get(*index_property) {
+            Some(Value::String(index)) => index.parse().ok(),
+            Some(Value::Number(index)) => index.as_u64().map(|index| index as usize),
+            _ => None,
+        };
+        match (url, index) {
+            (Some(url), Some(index)) => list_indexes.push((url.to_string(), index)),
+            _ => return Err(format!("Invalid {} status entry", type_)),
+        }
+    }
+    Ok(list_indexes)
+}
+
+impl AuditEntry {
+    /// Audit a credential that could not be read.
+    pub fn invalid(source: String, message: String) -> Self {
+        Self {
+            source,
+            id: None,
+            issuer: None,
+            expiration: None,
+            status: AuditStatus::Invalid,
+            details: vec![message],
+        }
+    }
+
+    /// Audit a credential by its expiration date, at `now`, flagging it as near expiry if it
+    /// expires within `window`.
+    pub fn new(source: String, credential: &Value, now: DateTime<Utc>, window: Duration) -> Self {
+        let string = |property: &str| credential.get(property).and_then(Value::as_str);
+        let issuer = match credential.get("issuer") {
+            Some(Value::Object(issuer)) => issuer.get("id").and_then(Value::as_str),
+            _ => string("issuer"),
+        };
+        let expiration = string("expirationDate").or_else(|| string("validUntil"));
+        let mut entry = Self {
+            source,
+            id: string("id").map(str::to_string),
+            issuer: issuer.map(str::to_string),
+            expiration: expiration.map(str::to_string),
+            status: AuditStatus::Valid,
+            details: Vec::new(),
+        };
+        if let Some(expiration) = expiration {
+            match DateTime::parse_from_rfc3339(expiration) {
+                Ok(date) if date <= now => {
+                    entry.flag(AuditStatus::Expired, format!("Expired {}", expiration))
+                }
+                Ok(date) if date <= now + window => {
+                    entry.flag(AuditStatus::NearExpiry, format!("Expires {}", expiration))
+                }
+                Ok(_) => {}
+                Err(err) => entry.flag(
+                    AuditStatus::Invalid,
+                    format!("Invalid expiration date {}: {}", expiration, err),
+                ),
+            }
+        }
+        entry
+    }
+
+    fn flag(&mut self, status: AuditStatus, detail: String) {
+        self.status = self.status.max(status);
+        self.details.push(detail);
+    }
+}
+
+/// A decoded status list.
+#[derive(Debug, Clone)]
+struct StatusList {
+    purpose: String,
+    bits: Vec<u8>,
+}
+
+impl StatusList {
+    /// Decode an `encodedList`: a GZIP-compressed bitstring, in base64url.
+    fn decode(encoded: &str, purpose: &str) -> Result<Self, String> {
+        let compressed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .or_else(|_| base64::decode(encoded))
+            .map_err(|e| e.to_string())?;
+        let mut bits = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut bits)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            purpose: purpose.to_string(),
+            bits,
+        })
+    }
+
+    /// Get the bit at an index. The first index is the most significant bit of the first byte.
+    fn get(&self, index: usize) -> Option<bool> {
+        self.bits
+            .get(index / 8)
+            .map(|byte| byte & (0x80 >> (index % 8)) != 0)
+    }
+
+    /// Check the status of a credential at an index of the list.
+    fn check(&self, url: &str, index: usize, entry: &mut AuditEntry) {
+        let status = match self.purpose.as_str() {
+            "suspension" => AuditStatus::Suspended,
+            _ => AuditStatus::Revoked,
+        };
+        match self.get(index) {
+            Some(false) => {}
+            Some(true) => entry.flag(
+                status,
+                format!("Status set for {}: {} index {}", self.purpose, url, index),
+            ),
+            None => entry.flag(
+                AuditStatus::Invalid,
+                format!("Status list index out of range: {} index {}", url, index),
+            ),
+        }
+    }
+}
+
+/// Status lists of the audited credentials, fetched once each.
+struct StatusLists<'a> {
+    resolver: &'a dyn DIDResolver,
+    client: reqwest::Client,
+    lists: HashMap<String, Result<StatusList, String>>,
+}
+
+impl<'a> StatusLists<'a> {
+    fn new(resolver: &'a dyn DIDResolver) -> Self {
+        Self {
+            resolver,
+            client: reqwest::Client::new(),
+            lists: HashMap::new(),
+        }
+    }
+
+    /// Fetch a status list credential, verify it, and decode its list.
+    async fn fetch(&self, url: &str) -> Result<StatusList, String> {
+        let text = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+        let list_credential = if text.trim_start().starts_with('{') {
+            serde_json::from_str(&text).map_err(|e| e.to_string())?
+        } else {
+            CredentialOrJWT::JWT(text.trim().to_string())
+        };
+        let report =
+            verify_credential(&list_credential, &JWTOrLDPOptions::default(), self.resolver)
+                .await
+                .map_err(|e| e.to_string())?;
+        if let Some(error) = report.errors.first() {
+            return Err(format!("Unable to verify status list: {}", error.message));
+        }
+        let credential = parse_credential(&text)?;
+        let subject = subjects(&credential).into_iter().next();
+        let property = |name: &str| subject.and_then(|subject| subject.get(name)?.as_str());
+        let encoded = property("encodedList").ok_or("Missing encodedList")?;
+        StatusList::decode(encoded, property("statusPurpose").unwrap_or("revocation"))
+    }
+
+    /// Check the status of a credential in a status list.
+    async fn check(&mut self, url: &str, index: usize, entry: &mut AuditEntry) {
+        if !self.lists.contains_key(url) {
+            let list = self.fetch(url).await;
+            self.lists.insert(url.to_string(), list);
+        }
+        match &self.lists[url] {
+            Ok(list) => list.check(url, index, entry),
+            Err(err) => entry
+                .details
+                .push(format!("Status list not available: {}: {}", url, err)),
+        }
+    }
+}
+
+/// Read the lines of a stream of credentials.
+fn read_stream(
+    reader: impl BufRead,
+    name: &str,
+    read: &mut impl FnMut(String, Result<Value, String>),
+) -> std::io::Result<()> {
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            read(format!("{}:{}", name, i + 1), parse_credential(&line));
+        }
+    }
+    Ok(())
+}
+
+/// Read the credentials of the files of a directory and its subdirectories, in order of path.
+fn read_dir(
+    dir: &Path,
+    read: &mut impl FnMut(String, Result<Value, String>),
+) -> std::io::Result<()> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    paths.sort();
+    for path in paths {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let name = path.display().to_string();
+        if path.is_dir() {
+            read_dir(&path, read)?;
+        } else if STREAM_EXTENSIONS.contains(&extension) {
+            read_stream(BufReader::new(File::open(&path)?), &name, read)?;
+        } else if CREDENTIAL_EXTENSIONS.contains(&extension) {
+            let text = std::fs::read_to_string(&path)?;
+            read(name, parse_credential(&text));
+        }
+    }
+    Ok(())
+}
+
+/// Quote a CSV field, if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Write audit entries as CSV, with a header row.
+pub fn write_csv(entries: &[AuditEntry], mut writer: impl Write) -> std::io::Result<()> {
+    writeln!(writer, "source,id,issuer,expiration,status,details")?;
+    for entry in entries {
+        let details = entry.details.join("; ");
+        let fields = [
+            entry.source.as_str(),
+            entry.id.as_deref().unwrap_or_default(),
+            entry.issuer.as_deref().unwrap_or_default(),
+            entry.expiration.as_deref().unwrap_or_default(),
+            entry.status.as_str(),
+            details.as_str(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+impl VcAuditCmd {
+    pub fn run(self) {
+        let now = Utc::now();
+        let window = Duration::days(self.window);
+        let mut audited = Vec::new();
+        let mut read = |source: String, credential: Result<Value, String>| {
+            audited.push(match credential {
+                Ok(credential) => {
+                    let mut entry = AuditEntry::new(source, &credential, now, window);
+                    let list_indexes = match status_entries(&credential) {
+                        Ok(list_indexes) => list_indexes,
+                        Err(err) => {
+                            entry.flag(AuditStatus::Invalid, err);
+                            Vec::new()
+                        }
+                    };
+                    (entry, list_indexes)
+                }
+                Err(err) => (AuditEntry::invalid(source, err), Vec::new()),
+            });
+        };
+        let result = match self.input {
+            Some(ref dir) if dir.is_dir() => read_dir(dir, &mut read),
+            Some(ref path) => File::open(path).and_then(|file| {
+                read_stream(BufReader::new(file), &path.display().to_string(), &mut read)
+            }),
+            None => read_stream(stdin().lock(), "stdin", &mut read),
+        };
+        if let Err(err) = result {
+            eprintln!("didkit: Unable to read credentials: {}", err);
+            std::process::exit(1);
+        }
+
+        if !self.skip_status {
+            let resolver = self.resolver_options.to_resolver();
+            let mut lists = StatusLists::new(&resolver);
+            let rt = runtime::get().unwrap();
+            rt.block_on(async {
+                for (entry, list_indexes) in &mut audited {
+                    for (url, index) in list_indexes.iter() {
+                        lists.check(url, *index, entry).await;
+                    }
+                }
+            });
+        }
+
+        let mut summary = AuditSummary::default();
+        let mut credentials = Vec::new();
+        for (entry, _) in audited {
+            summary.count(entry.status);
+            if self.all || entry.status != AuditStatus::Valid {
+                credentials.push(entry);
+            }
+        }
+        match self.format {
+            AuditFormat::Json => {
+                let report = AuditReport {
+                    summary,
+                    credentials,
+                };
+                output::write_json_pretty(&report);
+            }
+            AuditFormat::Csv => write_csv(&credentials, BufWriter::new(stdout())).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn audit_expiration() {
+        // 2024-06-01
+        let now = Utc.timestamp_opt(1717200000, 0).unwrap();
+        let window = Duration::days(30);
+        let audit =
+            |credential: Value| AuditEntry::new("test".to_string(), &credential, now, window);
+
+        let entry = audit(json!({
+            "id": "urn:uuid:1",
+            "issuer": { "id": "did:example:issuer" },
+            "expirationDate": "2024-06-15T00:00:00Z"
+        }));
+        assert_eq!(entry.status, AuditStatus::NearExpiry);
+        assert_eq!(entry.issuer.as_deref(), Some("did:example:issuer"));
+        let entry = audit(json!({ "validUntil": "2024-01-01T00:00:00Z" }));
+        assert_eq!(entry.status, AuditStatus::Expired);
+        assert_eq!(audit(json!({})).status, AuditStatus::Valid);
+        let entry = audit(json!({ "expirationDate": "2024-06-15" }));
+        assert_eq!(entry.status, AuditStatus::Invalid);
+
+        // JWT with an exp claim of 2024-01-01
+        let claims = json!({ "iss": "did:example:issuer", "exp": 1704067200, "vc": {} });
+        let jwt = format!(
+            "eyJhbGciOiJub25lIn0.{}.",
+            base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD)
+        );
+        let credential = parse_credential(&format!("\"{}\"", jwt)).unwrap();
+        assert_eq!(credential["expirationDate"], "2024-01-01T00:00:00Z");
+        assert_eq!(audit(credential).status, AuditStatus::Expired);
+        assert!(parse_credential("not a credential").is_err());
+
+        let credential = json!({
+            "credentialStatus": {
+                "type": "StatusList2021Entry",
+                "statusListIndex": "9",
+                "statusListCredential": "https://example.org/status/1"
+            }
+        });
+        assert_eq!(
+            status_entries(&credential).unwrap(),
+            vec![("https://example.org/status/1".to_string(), 9)]
+        );
+        let list = StatusList {
+            purpose: "suspension".to_string(),
+            bits: vec![0, 0x40],
+        };
+        let mut entry = audit(credential);
+        list.check("https://example.org/status/1", 9, &mut entry);
+        assert_eq!(entry.status, AuditStatus::Suspended);
+
+        let mut csv = Vec::new();
+        write_csv(&[entry], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some(
+                "test,,,,suspended,Status set for suspension: https://example.org/status/1 index 9"
+            )
+        );
+        assert_eq!(csv_field("a \"b\", c"), "\"a \"\"b\"\", c\"");
+    }
+}
@@ -0,0 +1,88 @@
+//! `didkit wallet` subcommands: Universal Wallet 2020 backups of the agent's encrypted credential
+//! store, for migrating between didkit-based agents and other wallets.
+
+use std::io::{stdin, BufReader};
+use std::path::PathBuf;
+
+use serde_json::Value;
+use structopt::StructOpt;
+
+use didkit::wallet::Wallet;
+
+use crate::opts::read_jwk;
+use crate::output;
+
+#[derive(StructOpt, Debug)]
+pub enum WalletCmd {
+    /// Export the wallet's keys, credentials and connections as an `EncryptedWallet` credential
+    Export {
+        /// Filename of the wallet's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Directory of the encrypted credential store
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: PathBuf,
+        /// Filename of the JWK to encrypt the backup to. Default is the wallet key.
+        #[structopt(short, long, parse(from_os_str))]
+        recipient: Option<PathBuf>,
+        /// Leave the wallet's private key out of the backup
+        #[structopt(long)]
+        exclude_key: bool,
+    },
+    /// Import an `EncryptedWallet` credential read from stdin, and output what was imported
+    Import {
+        /// Filename of the wallet's private key JWK
+        #[structopt(short, long, parse(from_os_str))]
+        key_path: PathBuf,
+        /// Directory of the encrypted credential store
+        #[structopt(short, long, parse(from_os_str))]
+        wallet: PathBuf,
+        /// Filename of the JWK to decrypt the backup with. Default is the wallet key.
+        #[structopt(short, long, parse(from_os_str))]
+        decryption_key: Option<PathBuf>,
+    },
+}
+
+impl WalletCmd {
+    pub fn run(self) {
+        match self {
+            WalletCmd::Export {
+                key_path,
+                wallet,
+                recipient,
+                exclude_key,
+            } => {
+                let key = read_jwk(&key_path);
+                let recipient = recipient.as_deref().map_or_else(|| key.clone(), read_jwk);
+                let keys = if exclude_key {
+                    vec![]
+                } else {
+                    vec![key.clone()]
+                };
+                let wallet = Wallet::open(&wallet, key).unwrap();
+                let backup = wallet.export(&recipient, &keys).unwrap();
+                output::write_json_pretty(&backup);
+            }
+            WalletCmd::Import {
+                key_path,
+                wallet,
+                decryption_key,
+            } => {
+                let key = read_jwk(&key_path);
+                let decryption_key = decryption_key
+                    .as_deref()
+                    .map_or_else(|| key.clone(), read_jwk);
+                let backup: Value = serde_json::from_reader(BufReader::new(stdin())).unwrap();
+                let wallet = Wallet::open(&wallet, key).unwrap();
+                let import = match wallet.import(&backup, &decryption_key) {
+                    Ok(import) => import,
+                    Err(err) => {
+                        eprintln!("didkit: {}", err);
+                        std::process::exit(1);
+                    }
+                };
+                output::write_json_pretty(&import);
+            }
+        }
+    }
+}
@@ -254,6 +254,58 @@ _:c14n1 <example:cat> "AAA" .
     assert_eq!(rdf, rdf_expected);
 }
 
+#[test]
+fn did_lint() {
+    let lint = Command::new(BIN)
+        .args(&["did-lint", DID_KEY_K256])
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap();
+    assert!(lint.status.success());
+
+    let mut lint = Command::new(BIN)
+        .arg("did-lint")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .unwrap();
+    let document = r##"{"id": "did:example:123", "assertionMethod": ["#key-1"]}"##;
+    lint.stdin
+        .take()
+        .unwrap()
+        .write_all(document.as_bytes())
+        .unwrap();
+    let output = lint.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["errors"][0]["path"], "/assertionMethod/0");
+}
+
+#[test]
+fn test_vectors() {
+    let dir = std::env::temp_dir().join(format!("didkit-test-vectors-{}", std::process::id()));
+    let generate = Command::new(BIN)
+        .args(&["test-vectors", "generate"])
+        .arg(&dir)
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap();
+    assert!(generate.status.success());
+    let report: Value = serde_json::from_slice(&generate.stdout).unwrap();
+    assert_eq!(report["failed"], 0);
+    assert!(dir.join("manifest.json").exists());
+
+    let verify = Command::new(BIN)
+        .args(&["test-vectors", "verify"])
+        .arg(&dir)
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap();
+    assert!(verify.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 #[tokio::test]
 async fn resolver_option() {
     use serde_json::json;
@@ -0,0 +1,121 @@
+//! External base URL of the server, for running behind a reverse proxy.
+//!
+//! Behind a proxy, the server is reached at a URL other than the address it listens on, possibly
+//! under a path prefix, e.g. `https://example.com/didkit`. URLs that the server gives out for
+//! itself (status list credentials, `Location` headers, the OpenAPI document's server) are made
+//! from the [`BaseUrl`], and the path prefix is removed from request paths, if the proxy did not
+//! already remove it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hyper::http::uri::PathAndQuery;
+use hyper::Uri;
+
+/// Absolute HTTP(S) URL of the server, with an optional path prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseUrl {
+    /// Scheme and authority, e.g. `https://example.com`
+    origin: String,
+    /// Path prefix without a trailing slash, e.g. `/didkit`, or empty
+    prefix: String,
+}
+
+impl FromStr for BaseUrl {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let uri: Uri = s
+            .parse()
+            .map_err(|err| format!("Invalid base URL {}: {}", s, err))?;
+        match uri.scheme_str() {
+            Some("http") | Some("https") => {}
+            _ => return Err(format!("Base URL must be an HTTP(S) URL: {}", s)),
+        }
+        let authority = uri
+            .authority()
+            .ok_or_else(|| format!("Base URL has no host: {}", s))?;
+        if uri.query().is_some() {
+            return Err(format!("Base URL must not have a query: {}", s));
+        }
+        Ok(Self {
+            origin: format!("{}://{}", uri.scheme_str().unwrap_or_default(), authority),
+            prefix: uri.path().trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+impl fmt::Display for BaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.origin, self.prefix)
+    }
+}
+
+impl BaseUrl {
+    /// Path prefix, e.g. `/didkit`, or the empty string.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// External path of a route of the server, e.g. `/didkit/credentials/1` for
+    /// `/credentials/1`.
+    pub fn path(&self, path: &str) -> String {
+        format!("{}{}", self.prefix, path)
+    }
+
+    /// External URL of a route of the server.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.origin, self.prefix, path)
+    }
+
+    /// Route of an external path under the prefix, e.g. `/credentials/1` for
+    /// `/didkit/credentials/1`.
+    pub fn strip_prefix<'a>(&self, path: &'a str) -> Option<&'a str> {
+        match path.strip_prefix(self.prefix.as_str())? {
+            "" => Some("/"),
+            rest if rest.starts_with('/') => Some(rest),
+            _ => None,
+        }
+    }
+
+    /// Request URI with the prefix removed from its path, if it has the prefix.
+    pub fn strip_uri(&self, uri: &Uri) -> Option<Uri> {
+        if self.prefix.is_empty() {
+            return None;
+        }
+        let path = self.strip_prefix(uri.path())?;
+        let path_and_query = match uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        };
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(PathAndQuery::from_str(&path_and_query).ok()?);
+        Uri::from_parts(parts).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url() {
+        let base: BaseUrl = "https://example.com/didkit/".parse().unwrap();
+        assert_eq!(base.to_string(), "https://example.com/didkit");
+        assert_eq!(base.path("/docs"), "/didkit/docs");
+        assert_eq!(base.url("/status/1"), "https://example.com/didkit/status/1");
+        assert_eq!(base.strip_prefix("/didkit"), Some("/"));
+        assert_eq!(base.strip_prefix("/didkit/docs"), Some("/docs"));
+        assert_eq!(base.strip_prefix("/didkitx/docs"), None);
+        let uri: Uri = "/didkit/credentials/issue?template=a".parse().unwrap();
+        assert_eq!(
+            base.strip_uri(&uri).unwrap(),
+            "/credentials/issue?template=a"
+        );
+
+        let root: BaseUrl = "http://localhost:8080".parse().unwrap();
+        assert_eq!(root.url("/status/1"), "http://localhost:8080/status/1");
+        assert_eq!(root.strip_uri(&uri), None);
+        assert!("example.com/didkit".parse::<BaseUrl>().is_err());
+        assert!("ftp://example.com".parse::<BaseUrl>().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Concurrency limits and load shedding.
+//!
+//! Verifying and signing are CPU-heavy: canonicalizing a document for a linked data proof can
+//! take from milliseconds to seconds. [`ConcurrencyLimits`] bounds the number of verification
+//! and signing requests handled at a time, each with a queue of bounded depth. Requests over the
+//! queue depth are shed, with `503 Service Unavailable` and a `Retry-After` header, so that a
+//! burst of load fails the excess requests quickly instead of slowing down every request.
+//!
+//! The handlers of these requests stay on the runtime's worker threads, and only run their proof
+//! step, where documents are canonicalized and signed or verified, on Tokio's blocking thread
+//! pool with [`run_blocking`], so that canonicalization does not hold up the worker threads
+//! serving other requests. The DID resolution of the proof step happens on the blocking thread
+//! too, as `ssi` resolves verification methods while it prepares and checks proofs.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinError;
+
+/// Default `Retry-After` of shed requests.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Kind of CPU-heavy request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Verifying a credential or presentation
+    Verification,
+    /// Issuing a credential or presentation
+    Signing,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Workload::Verification => write!(f, "verification"),
+            Workload::Signing => write!(f, "signing"),
+        }
+    }
+}
+
+/// A request was shed, as too many requests of its kind are in progress and queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overloaded(pub Workload);
+
+impl std::error::Error for Overloaded {}
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Too many {} requests in progress", self.0)
+    }
+}
+
+struct WorkloadLimit {
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+    queue_depth: usize,
+}
+
+/// Decrements the queue length when a request leaves the queue, including when it is dropped
+/// while waiting, e.g. on a request timeout.
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl WorkloadLimit {
+    fn new(max_concurrent: usize, queue_depth: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            queued: AtomicUsize::new(0),
+            queue_depth,
+        }
+    }
+
+    async fn acquire(&self, workload: Workload) -> Result<OwnedSemaphorePermit, Overloaded> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        let queued = Queued(&self.queued);
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= self.queue_depth {
+            return Err(Overloaded(workload));
+        }
+        let permit = self.permits.clone().acquire_owned().await;
+        drop(queued);
+        permit.map_err(|_| Overloaded(workload))
+    }
+}
+
+/// Limits of verification and signing requests handled at a time.
+pub struct ConcurrencyLimits {
+    verification: Option<WorkloadLimit>,
+    signing: Option<WorkloadLimit>,
+    retry_after: Duration,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            verification: None,
+            signing: None,
+            retry_after: DEFAULT_RETRY_AFTER,
+        }
+    }
+}
+
+impl fmt::Debug for ConcurrencyLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = |limit: &Option<WorkloadLimit>| {
+            limit
+                .as_ref()
+                .map(|limit| (limit.permits.available_permits(), limit.queue_depth))
+        };
+        f.debug_struct("ConcurrencyLimits")
+            .field("verification", &limit(&self.verification))
+            .field("signing", &limit(&self.signing))
+            .field("retry_after", &self.retry_after)
+            .finish()
+    }
+}
+
+impl ConcurrencyLimits {
+    /// No limits, until set with the `with_` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify at most `max_concurrent` documents at a time, with at most `queue_depth` requests
+    /// waiting.
+    pub fn with_max_verifications(mut self, max_concurrent: usize, queue_depth: usize) -> Self {
+        self.verification = Some(WorkloadLimit::new(max_concurrent, queue_depth));
+        self
+    }
+
+    /// Issue at most `max_concurrent` documents at a time, with at most `queue_depth` requests
+    /// waiting.
+    pub fn with_max_signings(mut self, max_concurrent: usize, queue_depth: usize) -> Self {
+        self.signing = Some(WorkloadLimit::new(max_concurrent, queue_depth));
+        self
+    }
+
+    /// `Retry-After` of shed requests. Rounded up to whole seconds.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// `Retry-After` of shed requests, in seconds.
+    pub fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs + 1
+        } else {
+            secs
+        }
+    }
+
+    /// Wait for a slot for a request, unless the queue is full. The slot is released when the
+    /// returned permit is dropped; without a limit for the workload, there is no permit.
+    pub async fn admit(
+        &self,
+        workload: Workload,
+    ) -> Result<Option<OwnedSemaphorePermit>, Overloaded> {
+        let limit = match workload {
+            Workload::Verification => &self.verification,
+            Workload::Signing => &self.signing,
+        };
+        match limit {
+            Some(limit) => limit.acquire(workload).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Run the proof step of a request on Tokio's blocking thread pool, driving it with the current
+/// runtime. Dropping the returned future also drops the future on the blocking thread, at its
+/// next yield point.
+pub async fn run_blocking<F>(future: F) -> Result<F::Output, JoinError>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let handle = Handle::current();
+    let (_dropped, cancelled) = oneshot::channel::<()>();
+    let task = tokio::task::spawn_blocking(move || {
+        handle.block_on(async move {
+            tokio::select! {
+                output = future => Some(output),
+                _ = cancelled => None,
+            }
+        })
+    });
+    match task.await? {
+        Some(output) => Ok(output),
+        // The sender is only dropped with this future.
+        None => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shed_over_queue_depth() {
+        let limits = ConcurrencyLimits::new()
+            .with_max_verifications(1, 1)
+            .with_retry_after(Duration::from_millis(1500));
+        assert_eq!(limits.retry_after_secs(), 2);
+        let permit = limits.admit(Workload::Verification).await.unwrap();
+        assert!(permit.is_some());
+        assert!(limits.admit(Workload::Signing).await.unwrap().is_none());
+
+        // One request may wait; the next one is shed.
+        let mut queued = Box::pin(limits.admit(Workload::Verification));
+        let waiting = tokio::time::timeout(Duration::from_millis(10), &mut queued).await;
+        assert!(waiting.is_err());
+        let shed = limits.admit(Workload::Verification).await.unwrap_err();
+        assert_eq!(shed, Overloaded(Workload::Verification));
+        drop(permit);
+        assert!(queued.await.unwrap().is_some());
+
+        let output = run_blocking(async { 1 + 1 }).await.unwrap();
+        assert_eq!(output, 2);
+    }
+}
@@ -1,5 +1,8 @@
 use std::fmt;
 
+use crate::nonce::NonceError;
+use crate::storage::StorageError;
+use crate::worker::WorkerError;
 use didkit::Error as DIDKitError;
 use hyper::header::ToStrError as HeaderToStrError;
 use hyper::http::Error as HttpError;
@@ -7,6 +10,7 @@ use hyper::Error as HyperError;
 use serde_json::Error as JSONError;
 use std::error::Error as StdError;
 use std::num::ParseFloatError;
+use tokio::task::JoinError;
 
 #[derive(Debug)]
 pub enum Error {
@@ -17,6 +21,10 @@ pub enum Error {
     HeaderToStr(HeaderToStrError),
     ParseFloat(ParseFloatError),
     InvalidAccept,
+    Nonce(NonceError),
+    Worker(WorkerError),
+    Storage(StorageError),
+    Join(JoinError),
     #[doc(hidden)]
     __Nonexhaustive,
 }
@@ -28,6 +36,10 @@ impl StdError for Error {
             Error::Hyper(e) => Some(e),
             Error::Http(e) => Some(e),
             Error::ParseFloat(e) => Some(e),
+            Error::Nonce(e) => Some(e),
+            Error::Worker(e) => Some(e),
+            Error::Storage(e) => Some(e),
+            Error::Join(e) => Some(e),
             _ => None,
         }
     }
@@ -43,6 +55,10 @@ impl fmt::Display for Error {
             Error::HeaderToStr(e) => e.fmt(f),
             Error::ParseFloat(e) => e.fmt(f),
             Error::InvalidAccept => write!(f, "Invalid Accept header value"),
+            Error::Nonce(e) => e.fmt(f),
+            Error::Worker(e) => e.fmt(f),
+            Error::Storage(e) => e.fmt(f),
+            Error::Join(e) => e.fmt(f),
             _ => unreachable!(),
         }
     }
@@ -84,6 +100,30 @@ impl From<ParseFloatError> for Error {
     }
 }
 
+impl From<NonceError> for Error {
+    fn from(err: NonceError) -> Error {
+        Error::Nonce(err)
+    }
+}
+
+impl From<WorkerError> for Error {
+    fn from(err: WorkerError) -> Error {
+        Error::Worker(err)
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(err: StorageError) -> Error {
+        Error::Storage(err)
+    }
+}
+
+impl From<JoinError> for Error {
+    fn from(err: JoinError) -> Error {
+        Error::Join(err)
+    }
+}
+
 /*
 impl From<dyn StdError + Sized> for Error {
     fn from(err: StdError) -> Error {
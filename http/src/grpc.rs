@@ -0,0 +1,557 @@
+//! gRPC interface, with the `grpc` feature.
+//!
+//! The service, defined in `proto/didkit.proto`, offers the operations of the HTTP API, with
+//! documents and options passed as JSON strings, and streaming RPCs for verifying credentials and
+//! presentations in bulk. It is configured by a [`DIDKitHTTPSvc`], sharing the keys, resolver
+//! options, challenge store, status cache, key attestation policy, proofs of work, concurrency
+//! limits, credential store and lifecycle tracking of the HTTP server, and issues as the HTTP API
+//! does: with the verification method of the issuer or holder DID if the options give none, and
+//! recording issued credentials, with an entry of the hosted status list if the lifecycle is
+//! tracked. A verification request carries its proof of work in its `proof_of_work` field, each
+//! request of a stream its own; verifications and signings are run within the concurrency limits,
+//! with their proof step on the blocking thread pool.
+
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use didkit::didcomm;
+use didkit::key_attestation::KeyAttestation;
+#[cfg(feature = "store")]
+use didkit::store::{CredentialStore, Direction};
+use didkit::verification::ErrorCode;
+use didkit::{
+    issue_credential, issue_presentation, verify_credential, verify_presentation, CredentialOrJWT,
+    DIDResolver, IssueOptions, JWTOrLDPOptions, PresentationOrJWT, ProofPurpose,
+    ResolutionInputMetadata, Signer, VerifiableCredential, VerifiablePresentation,
+    VerificationReport, VerificationResult, JWK,
+};
+
+use crate::concurrency::{self, Workload};
+use crate::{pick_key, DIDKitHTTPSvc};
+
+pub mod proto {
+    tonic::include_proto!("didkit.v1");
+}
+
+use proto::didkit_server::{Didkit, DidkitServer};
+use proto::{
+    IssueRequest, IssueResponse, PackRequest, PackResponse, ResolveRequest, ResolveResponse,
+    StatusRequest, UnpackRequest, UnpackResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Number of requests of a stream verified concurrently
+const STREAM_CONCURRENCY: usize = 16;
+
+#[derive(Clone)]
+pub struct DIDKitGrpcSvc {
+    jwks: Arc<Vec<JWK>>,
+    http: DIDKitHTTPSvc,
+}
+
+fn parse_json<T: DeserializeOwned>(name: &str, json: &str) -> Result<T, Status> {
+    serde_json::from_str(json).map_err(|e| Status::invalid_argument(format!("{}: {}", name, e)))
+}
+
+/// Parse options, defaulting if empty.
+fn parse_options<T: DeserializeOwned + Default>(options: &str) -> Result<T, Status> {
+    if options.trim().is_empty() {
+        return Ok(T::default());
+    }
+    parse_json("options", options)
+}
+
+/// Parse a document that may be in JSON or a JWT.
+fn parse_document<T: DeserializeOwned>(document: &str, jwt: fn(String) -> T) -> Result<T, Status> {
+    let document = document.trim();
+    if document.starts_with('{') {
+        parse_json("document", document)
+    } else {
+        Ok(jwt(document.to_string()))
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Status> {
+    serde_json::to_string(value).map_err(|e| Status::internal(e.to_string()))
+}
+
+fn verify_response(id: String, report: VerificationReport) -> Result<VerifyResponse, Status> {
+    let json = to_json(&report)?;
+    let verified = report.is_success();
+    let result = VerificationResult::from(report);
+    Ok(VerifyResponse {
+        id,
+        verified,
+        checks: result.checks,
+        warnings: result.warnings,
+        errors: result.errors,
+        report: json,
+    })
+}
+
+impl DIDKitGrpcSvc {
+    /// Serve with the configuration of an HTTP service, given the private keys of its signers,
+    /// for DIDComm.
+    pub fn new(jwks: Vec<JWK>, http: DIDKitHTTPSvc) -> Self {
+        Self {
+            jwks: Arc::new(jwks),
+            http,
+        }
+    }
+
+    /// Run a verification or signing once admitted by the concurrency limits, if any.
+    async fn run_workload<F, T>(&self, workload: Workload, future: F) -> Result<T, Status>
+    where
+        F: std::future::Future<Output = Result<T, Status>>,
+    {
+        let _permit = match self.http.concurrency_limits {
+            Some(ref limits) => limits
+                .admit(workload)
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?,
+            None => None,
+        };
+        future.await
+    }
+
+    /// Run the proof step of a request on the blocking thread pool.
+    async fn run_blocking<F>(future: F) -> Result<F::Output, Status>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        concurrency::run_blocking(future)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// Issue a credential or presentation within the concurrency limits.
+    async fn issue(
+        &self,
+        request: IssueRequest,
+        presentation: bool,
+    ) -> Result<IssueResponse, Status> {
+        self.run_workload(Workload::Signing, self.sign(request, presentation))
+            .await
+    }
+
+    /// Verify a request, after checking its proof of work, within the concurrency limits.
+    async fn verify(
+        &self,
+        request: VerifyRequest,
+        presentation: bool,
+    ) -> Result<VerifyResponse, Status> {
+        if let Some(ref proof_of_work) = self.http.proof_of_work {
+            let proof = Some(request.proof_of_work.as_str()).filter(|proof| !proof.is_empty());
+            let result = proof_of_work
+                .check(proof)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+            if let Err(err) = result {
+                return Err(Status::permission_denied(err.to_string()));
+            }
+        }
+        self.run_workload(
+            Workload::Verification,
+            self.verify_one(request, presentation),
+        )
+        .await
+    }
+
+    /// Check the key attestation of an issuance request, if the service has a key attestation
+    /// policy.
+    async fn check_key_attestation(
+        &self,
+        request: &IssueRequest,
+        credential: &VerifiableCredential,
+        dry_run: bool,
+        resolver: &dyn DIDResolver,
+    ) -> Result<(), Status> {
+        let policy = match self.http.key_attestation {
+            Some(ref policy) => policy,
+            None => return Ok(()),
+        };
+        let nonce_store = self
+            .http
+            .nonce_store
+            .as_ref()
+            .ok_or_else(|| Status::internal("Key attestation requires a challenge store"))?;
+        let key_attestation = request.key_attestation.trim();
+        let (attestation, proof_of_possession) = if key_attestation.is_empty() {
+            (None, None)
+        } else if key_attestation.starts_with('{') {
+            let attestation: KeyAttestation = parse_json("key attestation", key_attestation)?;
+            (Some(attestation), None)
+        } else {
+            (None, Some(key_attestation))
+        };
+        let error = DIDKitHTTPSvc::check_key_attestation(
+            policy,
+            nonce_store.as_ref(),
+            !dry_run,
+            attestation.as_ref(),
+            proof_of_possession,
+            credential,
+            resolver,
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+        match error {
+            Some(message) => Err(Status::failed_precondition(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Serve the gRPC service on the given address.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(DidkitServer::new(self))
+            .serve(addr)
+            .await
+    }
+
+    /// The key to sign with, with the verification method of the issuer or holder DID if the
+    /// options give none.
+    async fn signer(
+        &self,
+        options: &mut IssueOptions,
+        did: Option<&str>,
+        default_purpose: ProofPurpose,
+        resolver: &dyn DIDResolver,
+    ) -> Result<Arc<dyn Signer>, Status> {
+        if let Some(message) = DIDKitHTTPSvc::discover_verification_method(
+            &self.http.keys,
+            options,
+            did,
+            default_purpose,
+            resolver,
+        )
+        .await
+        {
+            return Err(Status::invalid_argument(message));
+        }
+        pick_key(&self.http.keys, &options.ldp_options, resolver)
+            .await
+            .cloned()
+            .ok_or_else(|| Status::failed_precondition("Missing key"))
+    }
+
+    /// Record an issued credential in the credential store, if any, completing its tracked
+    /// issuance. Returns its ID in the store, or an empty string if it is not recorded.
+    #[cfg(feature = "store")]
+    async fn record_issuance(
+        credential_store: Option<&CredentialStore>,
+        tracked: Option<String>,
+        vc: &CredentialOrJWT,
+    ) -> Result<String, Status> {
+        let credential_store = match credential_store {
+            Some(credential_store) => credential_store,
+            None => return Ok(String::new()),
+        };
+        let value = serde_json::to_value(vc).map_err(|e| Status::internal(e.to_string()))?;
+        let result = match tracked {
+            Some(id) => credential_store
+                .complete_issuance(&id, &value)
+                .await
+                .map(|_| id),
+            None => credential_store.insert(&value, Direction::Issued).await,
+        };
+        result.map_err(|e| Status::internal(e.to_string()))
+    }
+
+    async fn sign(
+        &self,
+        request: IssueRequest,
+        presentation: bool,
+    ) -> Result<IssueResponse, Status> {
+        let options: JWTOrLDPOptions = if request.options.trim().is_empty() && presentation {
+            JWTOrLDPOptions::default_for_vp()
+        } else {
+            parse_options(&request.options)?
+        };
+        let mut options =
+            IssueOptions::try_from(options).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let resolver = self.http.resolver_options.to_resolver();
+        if presentation {
+            let vp: VerifiablePresentation = parse_json("document", &request.document)?;
+            let holder = vp.holder.as_ref().map(ToString::to_string);
+            let signer = self
+                .signer(
+                    &mut options,
+                    holder.as_deref(),
+                    ProofPurpose::Authentication,
+                    &resolver,
+                )
+                .await?;
+            let resolver_options = self.http.resolver_options.clone();
+            let document = match Self::run_blocking(async move {
+                let resolver = resolver_options.to_resolver();
+                issue_presentation(vp, &options, signer.as_ref(), &resolver).await
+            })
+            .await?
+            {
+                Ok(PresentationOrJWT::JWT(jwt)) => jwt,
+                Ok(PresentationOrJWT::VP(vp)) => to_json(&vp)?,
+                Err(e) => return Err(Status::invalid_argument(e.to_string())),
+            };
+            return Ok(IssueResponse {
+                document,
+                ..Default::default()
+            });
+        }
+        let vc: VerifiableCredential = parse_json("document", &request.document)?;
+        self.check_key_attestation(&request, &vc, options.dry_run, &resolver)
+            .await?;
+        // A dry run does not record the credential.
+        #[cfg(feature = "store")]
+        let (lifecycle, credential_store) = if options.dry_run {
+            (None, None)
+        } else {
+            (
+                self.http.lifecycle.as_deref(),
+                self.http.credential_store.as_deref(),
+            )
+        };
+        #[cfg(feature = "store")]
+        let (vc, tracked) = DIDKitHTTPSvc::track_issuance(vc, lifecycle, credential_store)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let issuer =
+            serde_json::to_value(&vc.issuer).map_err(|e| Status::internal(e.to_string()))?;
+        let issuer = issuer.as_str().or_else(|| issuer["id"].as_str());
+        let signer = self
+            .signer(
+                &mut options,
+                issuer,
+                ProofPurpose::AssertionMethod,
+                &resolver,
+            )
+            .await?;
+        let resolver_options = self.http.resolver_options.clone();
+        let vc = Self::run_blocking(async move {
+            let resolver = resolver_options.to_resolver();
+            issue_credential(vc, &options, signer.as_ref(), &resolver).await
+        })
+        .await?
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        #[cfg(feature = "store")]
+        let id = Self::record_issuance(credential_store, tracked, &vc).await?;
+        #[cfg(not(feature = "store"))]
+        let id = String::new();
+        let document = match vc {
+            CredentialOrJWT::JWT(jwt) => jwt,
+            CredentialOrJWT::Credential(vc) => to_json(&vc)?,
+        };
+        Ok(IssueResponse { document, id })
+    }
+
+    async fn verify_one(
+        &self,
+        request: VerifyRequest,
+        presentation: bool,
+    ) -> Result<VerifyResponse, Status> {
+        let options: JWTOrLDPOptions = if request.options.trim().is_empty() && presentation {
+            JWTOrLDPOptions::default_for_vp()
+        } else {
+            parse_options(&request.options)?
+        };
+        let resolver_options = self.http.resolver_options.clone();
+        let report = if presentation {
+            let vp = parse_document(&request.document, PresentationOrJWT::JWT)?;
+            let (proof_vp, proof_options) = (vp.clone(), options.clone());
+            let mut report = Self::run_blocking(async move {
+                let resolver = resolver_options.to_resolver();
+                verify_presentation(&proof_vp, &proof_options, &resolver).await
+            })
+            .await?
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            if let Some(ref status_cache) = self.http.status_cache {
+                status_cache.check_presentation(&vp, &mut report);
+            }
+            if report.is_success() {
+                let challenge_error =
+                    DIDKitHTTPSvc::consume_challenge(self.http.nonce_store.clone(), &options)
+                        .await
+                        .map_err(|e| Status::internal(e.to_string()))?;
+                if let Some(message) = challenge_error {
+                    return Err(Status::invalid_argument(message));
+                }
+            }
+            report
+        } else {
+            let vc = parse_document(&request.document, CredentialOrJWT::JWT)?;
+            let (proof_vc, proof_options) = (vc.clone(), options.clone());
+            let mut report = Self::run_blocking(async move {
+                let resolver = resolver_options.to_resolver();
+                verify_credential(&proof_vc, &proof_options, &resolver).await
+            })
+            .await?
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            if let Some(ref status_cache) = self.http.status_cache {
+                status_cache.check_credential(&vc, &mut report);
+            }
+            report
+        };
+        verify_response(request.id, report)
+    }
+
+    /// Verify the requests of a stream concurrently, sending the responses in order.
+    fn verify_stream(
+        &self,
+        requests: Streaming<VerifyRequest>,
+        presentation: bool,
+    ) -> ReceiverStream<Result<VerifyResponse, Status>> {
+        let (tx, rx) = mpsc::channel(STREAM_CONCURRENCY);
+        let svc = self.clone();
+        tokio::spawn(async move {
+            let mut responses = requests
+                .map(move |request| {
+                    let svc = svc.clone();
+                    async move {
+                        let request = request?;
+                        let id = request.id.clone();
+                        svc.verify(request, presentation).await.or_else(|status| {
+                            // Report errors of individual requests without ending the stream.
+                            let mut report = VerificationReport::default();
+                            report.push_error(ErrorCode::Other, status.message());
+                            verify_response(id, report)
+                        })
+                    }
+                })
+                .buffered(STREAM_CONCURRENCY);
+            while let Some(response) = responses.next().await {
+                if tx.send(response).await.is_err() {
+                    break;
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+#[tonic::async_trait]
+impl Didkit for DIDKitGrpcSvc {
+    async fn issue_credential(
+        &self,
+        request: Request<IssueRequest>,
+    ) -> Result<Response<IssueResponse>, Status> {
+        Ok(Response::new(
+            self.issue(request.into_inner(), false).await?,
+        ))
+    }
+
+    async fn prove_presentation(
+        &self,
+        request: Request<IssueRequest>,
+    ) -> Result<Response<IssueResponse>, Status> {
+        Ok(Response::new(self.issue(request.into_inner(), true).await?))
+    }
+
+    async fn verify_credential(
+        &self,
+        request: Request<VerifyRequest>,
+    ) -> Result<Response<VerifyResponse>, Status> {
+        Ok(Response::new(
+            self.verify(request.into_inner(), false).await?,
+        ))
+    }
+
+    async fn verify_presentation(
+        &self,
+        request: Request<VerifyRequest>,
+    ) -> Result<Response<VerifyResponse>, Status> {
+        Ok(Response::new(
+            self.verify(request.into_inner(), true).await?,
+        ))
+    }
+
+    type VerifyCredentialsStream = ReceiverStream<Result<VerifyResponse, Status>>;
+
+    async fn verify_credentials(
+        &self,
+        request: Request<Streaming<VerifyRequest>>,
+    ) -> Result<Response<Self::VerifyCredentialsStream>, Status> {
+        Ok(Response::new(
+            self.verify_stream(request.into_inner(), false),
+        ))
+    }
+
+    type VerifyPresentationsStream = ReceiverStream<Result<VerifyResponse, Status>>;
+
+    async fn verify_presentations(
+        &self,
+        request: Request<Streaming<VerifyRequest>>,
+    ) -> Result<Response<Self::VerifyPresentationsStream>, Status> {
+        Ok(Response::new(
+            self.verify_stream(request.into_inner(), true),
+        ))
+    }
+
+    async fn resolve(
+        &self,
+        request: Request<ResolveRequest>,
+    ) -> Result<Response<ResolveResponse>, Status> {
+        let request = request.into_inner();
+        let input_metadata: ResolutionInputMetadata = parse_options(&request.input_metadata)?;
+        let resolver = self.http.resolver_options.to_resolver();
+        let (res_meta, doc_opt, doc_meta_opt) =
+            resolver.resolve(&request.did, &input_metadata).await;
+        Ok(Response::new(ResolveResponse {
+            document: match doc_opt {
+                Some(doc) => to_json(&doc)?,
+                None => String::new(),
+            },
+            document_metadata: match doc_meta_opt {
+                Some(doc_meta) => to_json(&doc_meta)?,
+                None => String::new(),
+            },
+            resolution_metadata: to_json(&res_meta)?,
+        }))
+    }
+
+    async fn check_status(
+        &self,
+        request: Request<StatusRequest>,
+    ) -> Result<Response<VerifyResponse>, Status> {
+        let status_cache = self
+            .http
+            .status_cache
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("No status lists configured"))?;
+        let vc = parse_document(&request.into_inner().credential, CredentialOrJWT::JWT)?;
+        let mut report = VerificationReport::default();
+        status_cache.check_credential(&vc, &mut report);
+        Ok(Response::new(verify_response(String::new(), report)?))
+    }
+
+    async fn pack(&self, request: Request<PackRequest>) -> Result<Response<PackResponse>, Status> {
+        let request = request.into_inner();
+        let message: didcomm::Message = parse_json("message", &request.message)?;
+        let resolver = self.http.resolver_options.to_resolver();
+        let envelope = didcomm::pack(&message, &request.to, &resolver)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(Response::new(PackResponse { envelope }))
+    }
+
+    async fn unpack(
+        &self,
+        request: Request<UnpackRequest>,
+    ) -> Result<Response<UnpackResponse>, Status> {
+        let envelope = request.into_inner().envelope;
+        let message = self
+            .jwks
+            .iter()
+            .find_map(|jwk| didcomm::unpack(&envelope, jwk).ok())
+            .ok_or_else(|| Status::invalid_argument("Unable to decrypt message"))?;
+        Ok(Response::new(UnpackResponse {
+            message: to_json(&message)?,
+        }))
+    }
+}
@@ -0,0 +1,161 @@
+//! Embedding the server's routes in other services.
+//!
+//! [`DIDKitHTTPSvc`] is a [`Service`] of requests, configured as for the server, that other Rust
+//! web services can mount in their own app, with their own middleware, instead of running
+//! `didkit-http` as a separate server:
+//!
+//! - [`DIDKitLayer`] is a Tower [`Layer`] in front of the app's service: requests for the routes
+//!   of DIDKit (under the service's base URL prefix, if any) are handled by DIDKit, and others are
+//!   passed on to the app.
+//! - [`DIDKitService`] is the DIDKit service on its own, for routers that route by path prefix,
+//!   such as axum's `Router::nest_service`, which removes the prefix from request paths.
+//!
+//! Both answer errors of DIDKit with `500 Internal Server Error`, so that they have the error
+//! type of the app's service, or no error, as routers expect.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::FutureExt;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::Error;
+use crate::DIDKitHTTPSvc;
+
+/// Respond to an error of the DIDKit service.
+fn internal_error(err: Error) -> Response<Body> {
+    let mut response = Response::new(Body::from(json!(err.to_string()).to_string()));
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// Handle a request with the DIDKit service, answering errors with a response.
+fn call_didkit(
+    didkit: &mut DIDKitHTTPSvc,
+    req: Request<Body>,
+) -> impl Future<Output = Response<Body>> + Send {
+    let response = didkit.call(req);
+    async move { response.await.unwrap_or_else(internal_error) }
+}
+
+/// The routes of DIDKit, as an infallible service.
+#[derive(Clone)]
+pub struct DIDKitService(DIDKitHTTPSvc);
+
+impl DIDKitService {
+    pub fn new(didkit: DIDKitHTTPSvc) -> Self {
+        Self(didkit)
+    }
+}
+
+impl Service<Request<Body>> for DIDKitService {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        Box::pin(call_didkit(&mut self.0, req).map(Ok))
+    }
+}
+
+/// Layer handling the routes of DIDKit in front of a service.
+#[derive(Clone)]
+pub struct DIDKitLayer {
+    didkit: DIDKitHTTPSvc,
+}
+
+impl DIDKitLayer {
+    /// Handle the routes of a DIDKit service, under the path prefix of its base URL, if any.
+    pub fn new(didkit: DIDKitHTTPSvc) -> Self {
+        Self { didkit }
+    }
+}
+
+impl<S> Layer<S> for DIDKitLayer {
+    type Service = DIDKitRoutes<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DIDKitRoutes {
+            didkit: self.didkit.clone(),
+            inner,
+        }
+    }
+}
+
+/// Service handling the routes of DIDKit, and passing other requests on to an inner service.
+#[derive(Clone)]
+pub struct DIDKitRoutes<S> {
+    didkit: DIDKitHTTPSvc,
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for DIDKitRoutes<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    S::Error: 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let path = req.uri().path();
+        let route = match self.didkit.base_url() {
+            Some(base_url) => base_url.strip_prefix(path),
+            None => Some(path),
+        };
+        if route.map_or(false, |route| self.didkit.has_route(route)) {
+            Box::pin(call_didkit(&mut self.didkit, req).map(Ok))
+        } else {
+            Box::pin(self.inner.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DIDKitHTTPSvc;
+    use didkit_cli::opts::ResolverOptions;
+    use hyper::service::service_fn;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn didkit_layer() {
+        let base_url = "https://example.com/didkit".parse().unwrap();
+        let didkit = DIDKitHTTPSvc::new(vec![], ResolverOptions::default())
+            .with_base_url(Arc::new(base_url));
+        let app = service_fn(|_| async {
+            let mut response = Response::new(Body::from("app"));
+            *response.status_mut() = StatusCode::IM_A_TEAPOT;
+            Ok::<_, Infallible>(response)
+        });
+        let mut svc = DIDKitLayer::new(didkit).layer(app);
+
+        let request = |path: &str| Request::builder().uri(path).body(Body::empty()).unwrap();
+        let response = svc.call(request("/didkit/openapi.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = svc.call(request("/didkit/unknown")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        let response = svc.call(request("/openapi.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+    }
+}
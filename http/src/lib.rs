@@ -2,71 +2,205 @@ use percent_encoding::percent_decode;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::convert::TryFrom;
 use std::str::FromStr;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
+use didkit::cancellation::CancelError;
+use didkit::canonical_json;
+use didkit::convert;
+use didkit::did_auth;
+use didkit::error::ErrorInfo;
+use didkit::i18n::{Catalog, Catalogs, LocalizedReport};
+use didkit::issue::{find_verification_method, KeyDiscoveryError};
+use didkit::key_attestation::{self, KeyAttestation, KeyAttestationPolicy};
+use didkit::resolution_error;
 use didkit::resolve_key;
+use didkit::resolver::ResolutionRecord;
+#[cfg(feature = "store")]
+use didkit::store::{CredentialStore, Direction, StoreError};
+use didkit::verification::VerificationReport;
+use didkit::Error as DIDKitError;
+pub use didkit::PresentationOrJWT;
 use didkit::{
     dereference as dereference_did_url, Content, ContentMetadata, CredentialOrJWT, DIDResolver,
-    DereferencingInputMetadata, JWTOrLDPOptions, LinkedDataProofOptions, ProofFormat,
-    ResolutionResult, VerifiableCredential, VerifiablePresentation, VerificationResult, JWK,
+    DereferencingInputMetadata, IssueOptions, JWTOrLDPOptions, LinkedDataProofOptions,
+    ProofPurpose, ResolutionResult, Signer, VerifiableCredential, VerifiablePresentation,
+    VerificationResult, JWK, URI,
 };
+use didkit::{issue_credential, issue_presentation, verify_credential, verify_presentation};
 use didkit_cli::opts::ResolverOptions;
-use ssi::did_resolve::{
-    ERROR_INVALID_DID, ERROR_NOT_FOUND, ERROR_REPRESENTATION_NOT_SUPPORTED, TYPE_DID_LD_JSON,
-    TYPE_DID_RESOLUTION,
-};
+use ssi::did_resolve::{TYPE_DID_LD_JSON, TYPE_DID_RESOLUTION};
 
 pub mod accept;
+pub mod base_url;
+pub mod concurrency;
 pub mod error;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod layer;
+#[cfg(feature = "store")]
+pub mod lifecycle;
+pub mod nonce;
+pub mod openapi;
+pub mod pow;
+pub mod status;
+pub mod storage;
+pub mod templates;
+pub mod validation;
+pub mod worker;
 use accept::HttpAccept;
+use base_url::BaseUrl;
+use concurrency::{ConcurrencyLimits, Overloaded, Workload};
 pub use error::Error;
+#[cfg(feature = "store")]
+use lifecycle::{LifecycleConfig, TransitionRequest, STATUS_LIST_SIZE};
+use nonce::{NonceError, NonceStore};
+use pow::{ProofOfWork, POW_HEADER};
+use status::StatusCache;
+use templates::{IssuanceTemplate, Templates};
+use validation::{Problem, PROBLEM_JSON};
 
-use hyper::body::Buf;
-use hyper::header::{ACCEPT, CONTENT_TYPE, LOCATION};
+use hyper::header::{
+    ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE,
+    LOCATION, RETRY_AFTER,
+};
 use hyper::{Body, Response};
 use hyper::{Method, Request, StatusCode};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tower_service::Service;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
-pub enum PresentationOrJWT {
-    VP(VerifiablePresentation),
-    JWT(String),
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct IssueCredentialRequest {
+    #[schemars(with = "didkit::json_schema::Credential")]
     pub credential: VerifiableCredential,
     pub options: Option<JWTOrLDPOptions>,
+    /// Attestation that the key of the credential subject is hardware-bound, required if the
+    /// issuer has a key attestation policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_attestation: Option<KeyAttestation>,
+    /// OIDC4VCI proof of possession JWT of the key of the credential subject, with its key
+    /// attestation in the `key_attestation` header, instead of `keyAttestation`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_of_possession: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Query parameters of issuance requests.
+#[derive(Debug, Default, Deserialize)]
+struct IssueQuery {
+    /// Name of the issuance template to apply
+    template: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct VerifyCredentialRequest {
+    #[schemars(with = "didkit::json_schema::CredentialOrJWT")]
     pub verifiable_credential: CredentialOrJWT,
     pub options: Option<JWTOrLDPOptions>,
+    /// Include the decoded document, with JWT claims mapped to the data model, in the response,
+    /// if verification succeeds (not standard in vc-http-api)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub decode: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct ProvePresentationRequest {
+    #[schemars(with = "didkit::json_schema::Presentation")]
     pub presentation: VerifiablePresentation,
     pub options: Option<JWTOrLDPOptions>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct VerifyPresentationRequest {
+    #[schemars(with = "didkit::json_schema::PresentationOrJWT")]
     pub verifiable_presentation: PresentationOrJWT,
     pub options: Option<JWTOrLDPOptions>,
+    /// Include the decoded document, with JWT claims mapped to the data model, in the response,
+    /// if verification succeeds (not standard in vc-http-api)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub decode: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct DIDAuthRequest {
+    #[schemars(with = "didkit::json_schema::PresentationOrJWT")]
+    pub verifiable_presentation: PresentationOrJWT,
+    /// Challenge issued by `/auth/challenge`
+    pub challenge: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DIDAuthResponse {
+    /// DID of the authenticated holder
+    pub holder: String,
+    /// Session assertion (JWT)
+    pub token: String,
+    /// Validity period of the session assertion, in seconds
+    pub expires_in: u64,
+}
+
+/// Challenge issued by `/challenges`.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeResponse {
+    pub challenge: String,
+    /// Validity period of the challenge, in seconds
+    pub expires_in: u64,
+}
+
+/// Challenge issued by `/pow/challenges`, to solve for a verification request.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PowChallengeResponse {
+    pub challenge: String,
+    /// Number of leading zero bits of the SHA-256 hash of `<challenge>:<solution>`
+    pub difficulty: u8,
+    /// Validity period of the challenge, in seconds
+    pub expires_in: u64,
+}
+
+/// Result of verifying a credential or presentation.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationResponse {
+    #[serde(flatten)]
+    #[schemars(with = "didkit::json_schema::VerificationResult")]
+    pub result: VerificationResult,
+    /// Messages of the result in the language requested by `Accept-Language`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedReport>,
+    /// DID resolutions performed to verify the document
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolutions: Vec<ResolutionRecord>,
+    /// Verified document, decoded to the data model, if requested with `decode`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<serde_json::Value>,
+}
+
+/// Configuration of DIDAuth login.
+#[derive(Debug, Clone)]
+pub struct DIDAuthConfig {
+    /// Verification method (DID URL) of an issuer key, used to sign session assertions
+    pub verification_method: String,
+    /// Domain that DIDAuth presentations must be bound to, and audience of session assertions
+    pub domain: Option<String>,
+    /// Validity period of session assertions
+    pub session_ttl: Duration,
 }
 
 pub type IssueCredentialResponse = VerifiableCredential;
@@ -74,19 +208,43 @@ pub type VerifyCredentialResponse = VerificationResult;
 pub type ProvePresentationResponse = VerifiablePresentation;
 pub type VerifyPresentationResponse = VerificationResult;
 
-/// Mapping from public keys to private keys
-type KeyMap = HashMap<JWK, JWK>;
+/// Mapping from public keys to signers
+type KeyMap = HashMap<JWK, Arc<dyn Signer>>;
+
+/// Handler of a route
+type Handler = fn(
+    &DIDKitHTTPSvc,
+    Request<Body>,
+) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>;
 
+#[derive(Clone)]
 pub struct DIDKitHTTPSvc {
     keys: KeyMap,
     resolver_options: ResolverOptions,
+    nonce_store: Option<Arc<dyn NonceStore>>,
+    status_cache: Option<Arc<StatusCache>>,
+    #[cfg(feature = "store")]
+    credential_store: Option<Arc<CredentialStore>>,
+    #[cfg(feature = "store")]
+    lifecycle: Option<Arc<LifecycleConfig>>,
+    did_auth: Option<DIDAuthConfig>,
+    catalogs: Option<Arc<Catalogs>>,
+    context_documents: Option<Arc<HashMap<String, String>>>,
+    templates: Option<Arc<Templates>>,
+    key_attestation: Option<Arc<KeyAttestationPolicy>>,
+    swagger_ui: bool,
+    request_timeout: Option<Duration>,
+    base_url: Option<Arc<BaseUrl>>,
+    concurrency_limits: Option<Arc<ConcurrencyLimits>>,
+    canonical_json: bool,
+    proof_of_work: Option<Arc<ProofOfWork>>,
 }
 
 pub async fn pick_key<'a>(
     keys: &'a KeyMap,
     options: &LinkedDataProofOptions,
     did_resolver: &dyn DIDResolver,
-) -> Option<&'a JWK> {
+) -> Option<&'a Arc<dyn Signer>> {
     if keys.len() <= 1 {
         return keys.values().next();
     }
@@ -104,14 +262,240 @@ pub async fn pick_key<'a>(
     keys.get(&public_key)
 }
 
+/// Find the verification method of a DID, for a proof purpose, whose key is one of the keys.
+pub async fn discover_key(
+    keys: &KeyMap,
+    did: &str,
+    proof_purpose: &ProofPurpose,
+    did_resolver: &dyn DIDResolver,
+) -> Result<String, KeyDiscoveryError> {
+    let mut error = None;
+    for signer in keys.values() {
+        let public_key = signer.public_jwk();
+        match find_verification_method(did, &public_key, proof_purpose, did_resolver).await {
+            Ok(vm) => return Ok(vm),
+            Err(err) => error = Some(err),
+        }
+    }
+    Err(error.unwrap_or_else(|| KeyDiscoveryError::NoMatchingKey(did.to_string(), String::new())))
+}
+
 impl DIDKitHTTPSvc {
-    pub fn new(keys: KeyMap, resolver_options: ResolverOptions) -> Self {
+    pub fn new(keys: Vec<JWK>, resolver_options: ResolverOptions) -> Self {
+        let signers = keys
+            .into_iter()
+            .map(|key| Arc::new(key) as Arc<dyn Signer>)
+            .collect();
+        Self::with_signers(signers, resolver_options)
+    }
+
+    /// Use signers, such as remote signing services, instead of private keys.
+    pub fn with_signers(signers: Vec<Arc<dyn Signer>>, resolver_options: ResolverOptions) -> Self {
         Self {
-            keys,
+            keys: signers.into_iter().fold(KeyMap::new(), |mut map, signer| {
+                map.insert(signer.public_jwk(), signer);
+                map
+            }),
             resolver_options,
+            nonce_store: None,
+            status_cache: None,
+            #[cfg(feature = "store")]
+            credential_store: None,
+            #[cfg(feature = "store")]
+            lifecycle: None,
+            did_auth: None,
+            catalogs: None,
+            context_documents: None,
+            templates: None,
+            key_attestation: None,
+            swagger_ui: false,
+            request_timeout: None,
+            base_url: None,
+            concurrency_limits: None,
+            canonical_json: false,
+            proof_of_work: None,
         }
     }
 
+    /// Issue challenges from the given store, and require verified presentations to consume one.
+    pub fn with_nonce_store(mut self, nonce_store: Arc<dyn NonceStore>) -> Self {
+        self.nonce_store = Some(nonce_store);
+        self
+    }
+
+    /// Check the status of verified credentials against cached status lists.
+    pub fn with_status_cache(mut self, status_cache: Arc<StatusCache>) -> Self {
+        self.status_cache = Some(status_cache);
+        self
+    }
+
+    /// Record issued credentials in a credential store.
+    #[cfg(feature = "store")]
+    pub fn with_credential_store(mut self, credential_store: Arc<CredentialStore>) -> Self {
+        self.credential_store = Some(credential_store);
+        self
+    }
+
+    /// Track the lifecycle of issued credentials, with a hosted status list. Requires a
+    /// credential store.
+    #[cfg(feature = "store")]
+    pub fn with_lifecycle(mut self, lifecycle: Arc<LifecycleConfig>) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// Offer DIDAuth login, issuing session assertions. Requires a nonce store.
+    pub fn with_did_auth(mut self, did_auth: DIDAuthConfig) -> Self {
+        self.did_auth = Some(did_auth);
+        self
+    }
+
+    /// Localize verification results in the language requested by `Accept-Language`.
+    pub fn with_catalogs(mut self, catalogs: Arc<Catalogs>) -> Self {
+        self.catalogs = Some(catalogs);
+        self
+    }
+
+    /// Check context integrity against these context documents, by URL, unless a request gives
+    /// its own.
+    pub fn with_context_documents(mut self, documents: Arc<HashMap<String, String>>) -> Self {
+        self.context_documents = Some(documents);
+        self
+    }
+
+    /// Offer these issuance templates, selected with the `template` query parameter of
+    /// issuance requests.
+    pub fn with_templates(mut self, templates: Arc<Templates>) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// Require issuance requests to attest that the key of the credential subject is
+    /// hardware-bound, with a challenge from the nonce store, which is required.
+    pub fn with_key_attestation(mut self, policy: Arc<KeyAttestationPolicy>) -> Self {
+        self.key_attestation = Some(policy);
+        self
+    }
+
+    /// Serve a Swagger UI of the OpenAPI document at `/docs`.
+    pub fn with_swagger_ui(mut self) -> Self {
+        self.swagger_ui = true;
+        self
+    }
+
+    /// Respond with `504 Gateway Timeout` to requests not handled within a timeout, cancelling
+    /// their DID resolutions, context fetches and status checks.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Give out URLs of the server under an external base URL, e.g. behind a reverse proxy, and
+    /// route requests with its path prefix.
+    pub fn with_base_url(mut self, base_url: Arc<BaseUrl>) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    /// Limit the verification and signing requests handled at a time, shedding requests over
+    /// the queue depth with `503 Service Unavailable`.
+    pub fn with_concurrency_limits(mut self, limits: Arc<ConcurrencyLimits>) -> Self {
+        self.concurrency_limits = Some(limits);
+        self
+    }
+
+    /// Respond with canonical JSON (RFC 8785), with the unordered arrays of verification results
+    /// sorted, so that responses are reproducible.
+    pub fn with_canonical_json(mut self) -> Self {
+        self.canonical_json = true;
+        self
+    }
+
+    /// Require verification requests to carry a proof of work, of a challenge from
+    /// `/pow/challenges`. The proofs of work should have a nonce store of their own, so that
+    /// their challenges are not accepted as challenges of presentations.
+    pub fn with_proof_of_work(mut self, proof_of_work: Arc<ProofOfWork>) -> Self {
+        self.proof_of_work = Some(proof_of_work);
+        self
+    }
+
+    /// External base URL of the server, if any.
+    pub fn base_url(&self) -> Option<&BaseUrl> {
+        self.base_url.as_deref()
+    }
+
+    /// External path of a route of the server, under the path prefix of the base URL, if any.
+    fn external_path(&self, path: &str) -> String {
+        match self.base_url {
+            Some(ref base_url) => base_url.path(path),
+            None => path.to_string(),
+        }
+    }
+
+    /// Consume the challenge of the proof options of a verified presentation, if a challenge
+    /// store is configured. Only presentations that verify consume their challenge, so that an
+    /// invalid presentation does not use up the challenge of a legitimate one. Returns an error
+    /// message if the challenge is missing or not valid.
+    async fn consume_challenge(
+        nonce_store: Option<Arc<dyn NonceStore>>,
+        options: &JWTOrLDPOptions,
+    ) -> Result<Option<String>, Error> {
+        let nonce_store = match nonce_store {
+            Some(nonce_store) => nonce_store,
+            None => return Ok(None),
+        };
+        let challenge = match options.ldp_options.challenge {
+            Some(ref challenge) => challenge,
+            None => return Ok(Some("Missing challenge".to_string())),
+        };
+        if nonce_store.consume(challenge).await? {
+            Ok(None)
+        } else {
+            Ok(Some(
+                "Unknown, expired or already used challenge".to_string(),
+            ))
+        }
+    }
+
+    /// Check the key attestation of an issuance request, given directly or in an OIDC4VCI proof
+    /// of possession: that it meets the policy, that the attested key is a key of each credential
+    /// subject, and that its challenge was issued by the nonce store. The challenge is consumed
+    /// once the attestation is validated, unless `consume` is false. Returns an error message if
+    /// the attestation is missing or not valid.
+    pub(crate) async fn check_key_attestation(
+        policy: &KeyAttestationPolicy,
+        nonce_store: &dyn NonceStore,
+        consume: bool,
+        attestation: Option<&KeyAttestation>,
+        proof_of_possession: Option<&str>,
+        credential: &VerifiableCredential,
+        resolver: &dyn DIDResolver,
+    ) -> Result<Option<String>, Error> {
+        let now = chrono::Utc::now();
+        let validated = match (proof_of_possession, attestation) {
+            (Some(jwt), _) => policy.validate_proof(jwt, now).await,
+            (None, Some(attestation)) => policy
+                .validate(attestation, now)
+                .await
+                .map(|key| (attestation.clone(), key)),
+            (None, None) => return Ok(Some("Missing key attestation".to_string())),
+        };
+        let (attestation, key) = match validated {
+            Ok(validated) => validated,
+            Err(err) => return Ok(Some(err.to_string())),
+        };
+        let subject = serde_json::to_value(&credential.credential_subject)?;
+        if let Err(err) = key_attestation::check_subject_keys(&key, &subject, resolver).await {
+            return Ok(Some(err.to_string()));
+        }
+        if consume && !nonce_store.consume(&attestation.challenge).await? {
+            return Ok(Some(
+                "Unknown, expired or already used key attestation challenge".to_string(),
+            ));
+        }
+        Ok(None)
+    }
+
     pub fn response(
         status_code: StatusCode,
         text: String,
@@ -127,6 +511,44 @@ impl DIDKitHTTPSvc {
         })
     }
 
+    /// Respond to an invalid request body with its [problem details](validation).
+    pub fn problem_response(
+        problem: Problem,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Box::pin(async move {
+            let body = Body::from(serde_json::to_vec_pretty(&problem)?);
+            Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header(CONTENT_TYPE, PROBLEM_JSON)
+                .body(body)
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Respond with a DIDKit error, as a JSON object with its stable numeric `code`, `kind`,
+    /// `message` and `details`.
+    pub fn error_response(
+        status_code: StatusCode,
+        err: impl Into<DIDKitError>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Self::info_response(status_code, err.into().info())
+    }
+
+    /// Response with the JSON body of an error.
+    pub fn info_response(
+        status_code: StatusCode,
+        info: ErrorInfo,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Box::pin(async move {
+            let body = Body::from(serde_json::to_vec_pretty(&info)?);
+            Response::builder()
+                .status(status_code)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body)
+                .map_err(|err| err.into())
+        })
+    }
+
     pub fn ensure_json(
         &self,
         req: &Request<Body>,
@@ -201,67 +623,466 @@ impl DIDKitHTTPSvc {
         if let Some(resp) = self.ensure_accept_json(&req) {
             return resp;
         };
+        let template = match self.issuance_template(&req) {
+            Ok(template) => template,
+            Err((status_code, message)) => return Self::response(status_code, message),
+        };
         let keys = self.keys.clone();
         let resolver_options = self.resolver_options.clone();
+        #[cfg(feature = "store")]
+        let credential_store = self.credential_store.clone();
+        #[cfg(feature = "store")]
+        let lifecycle = self.lifecycle.clone();
+        #[cfg(feature = "store")]
+        let credentials_path = self.external_path("/credentials/");
+        let nonce_store = self.nonce_store.clone();
+        let key_attestation = self.key_attestation.clone();
         Box::pin(async move {
-            let body_reader = hyper::body::aggregate(req).await?.reader();
-            let issue_req: IssueCredentialRequest = match serde_json::from_reader(body_reader) {
+            let mut body = hyper::body::to_bytes(req).await?.to_vec();
+            if let Some((name, template)) = template {
+                body = match template.apply(&name, &body) {
+                    Ok(body) => body,
+                    Err(problem) => return Self::problem_response(problem).await,
+                };
+            }
+            let issue_req: IssueCredentialRequest = match validation::parse(&body) {
                 Ok(reader) => reader,
+                Err(problem) => {
+                    return Self::problem_response(problem).await;
+                }
+            };
+            let mut options = match IssueOptions::try_from(issue_req.options.unwrap_or_default()) {
+                Ok(options) => options,
                 Err(err) => {
-                    return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                    return Self::problem_response(Problem::invalid_options(err)).await;
                 }
             };
-            let options = issue_req.options.unwrap_or_default();
-            let proof_format = options.proof_format.unwrap_or_default();
             let resolver = resolver_options.to_resolver();
-            let key = match pick_key(&keys, &options.ldp_options, &resolver).await {
-                Some(key) => key,
+            // A dry run leaves no trace: it does not consume the key attestation challenge, or
+            // record the credential.
+            let dry_run = options.dry_run;
+            if let Some(policy) = key_attestation {
+                let nonce_store = match nonce_store {
+                    Some(nonce_store) => nonce_store,
+                    None => {
+                        let err_msg = "Key attestation requires a challenge store".to_string();
+                        return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err_msg).await;
+                    }
+                };
+                if let Some(err_msg) = Self::check_key_attestation(
+                    &policy,
+                    nonce_store.as_ref(),
+                    !dry_run,
+                    issue_req.key_attestation.as_ref(),
+                    issue_req.proof_of_possession.as_deref(),
+                    &issue_req.credential,
+                    &resolver,
+                )
+                .await?
+                {
+                    return Self::response(StatusCode::BAD_REQUEST, err_msg).await;
+                }
+            }
+            #[cfg(feature = "store")]
+            let (lifecycle, credential_store) = if dry_run {
+                (None, None)
+            } else {
+                (lifecycle, credential_store)
+            };
+            #[cfg(feature = "store")]
+            let (credential, tracked) = match Self::track_issuance(
+                issue_req.credential,
+                lifecycle.as_deref(),
+                credential_store.as_deref(),
+            )
+            .await
+            {
+                Ok(tracked) => tracked,
+                Err(err) => {
+                    return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                        .await;
+                }
+            };
+            #[cfg(not(feature = "store"))]
+            let credential = issue_req.credential;
+            let issuer = serde_json::to_value(&credential.issuer)?;
+            let issuer = issuer.as_str().or_else(|| issuer["id"].as_str());
+            if let Some(err_msg) = Self::discover_verification_method(
+                &keys,
+                &mut options,
+                issuer,
+                ProofPurpose::AssertionMethod,
+                &resolver,
+            )
+            .await
+            {
+                return Self::response(StatusCode::BAD_REQUEST, err_msg).await;
+            }
+            let signer = match pick_key(&keys, &options.ldp_options, &resolver).await {
+                Some(signer) => signer,
                 None => return Self::missing_key().await,
             };
-            let mut credential = issue_req.credential;
-            let body = match proof_format {
-                ProofFormat::JWT => {
-                    let jwt = match credential
-                        .generate_jwt(Some(&key), &options.ldp_options, &resolver)
+            let signer = signer.clone();
+            let resolver_options = resolver_options.clone();
+            let vc = match concurrency::run_blocking(async move {
+                let resolver = resolver_options.to_resolver();
+                issue_credential(credential, &options, signer.as_ref(), &resolver).await
+            })
+            .await?
+            {
+                Ok(vc) => vc,
+                Err(err) => {
+                    return Self::error_response(StatusCode::BAD_REQUEST, err).await;
+                }
+            };
+            #[cfg(feature = "store")]
+            if let Some(ref credential_store) = credential_store {
+                let value = serde_json::to_value(&vc)?;
+                let result = match tracked {
+                    Some(ref id) => credential_store.complete_issuance(id, &value).await,
+                    None => credential_store
+                        .insert(&value, Direction::Issued)
                         .await
-                    {
-                        Ok(reader) => reader,
-                        Err(err) => {
-                            return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                        .map(|_| ()),
+                };
+                if let Err(err) = result {
+                    return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                        .await;
+                }
+            }
+            let body = match vc {
+                CredentialOrJWT::JWT(jwt) => Body::from(jwt.into_bytes()),
+                CredentialOrJWT::Credential(credential) => {
+                    Body::from(serde_json::to_vec_pretty(&credential)?)
+                }
+            };
+
+            let status_code = if dry_run {
+                StatusCode::OK
+            } else {
+                StatusCode::CREATED
+            };
+            let builder = Response::builder()
+                .status(status_code)
+                .header(CONTENT_TYPE, "application/json");
+            #[cfg(feature = "store")]
+            let builder = match tracked {
+                Some(id) => builder.header(LOCATION, format!("{}{}", credentials_path, id)),
+                None => builder,
+            };
+            builder.body(body).map_err(|err| err.into())
+        })
+    }
+
+    /// The issuance template selected by the `template` query parameter of a request, if any.
+    /// Fails with the status and message of the response if the query is invalid or the template
+    /// is not one of the configured templates.
+    fn issuance_template(
+        &self,
+        req: &Request<Body>,
+    ) -> Result<Option<(String, IssuanceTemplate)>, (StatusCode, String)> {
+        let query: IssueQuery = serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+            .map_err(|err| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Unable to parse query: {}", err),
+                )
+            })?;
+        let name = match query.template {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        match self
+            .templates
+            .as_ref()
+            .and_then(|templates| templates.get(&name))
+        {
+            Some(template) => Ok(Some((name, template.clone()))),
+            None => Err((
+                StatusCode::NOT_FOUND,
+                format!("Unknown issuance template: {}", name),
+            )),
+        }
+    }
+
+    /// If credential lifecycle tracking is configured, store a credential to issue as pending,
+    /// and give it an entry of the hosted status list unless it has a `credentialStatus`.
+    /// Returns the credential to sign, and its ID in the store if it was stored.
+    #[cfg(feature = "store")]
+    pub(crate) async fn track_issuance(
+        credential: VerifiableCredential,
+        lifecycle: Option<&LifecycleConfig>,
+        credential_store: Option<&CredentialStore>,
+    ) -> Result<(VerifiableCredential, Option<String>), StoreError> {
+        let (lifecycle, credential_store) = match (lifecycle, credential_store) {
+            (Some(lifecycle), Some(credential_store)) => (lifecycle, credential_store),
+            _ => return Ok((credential, None)),
+        };
+        let mut value = serde_json::to_value(&credential)?;
+        let id = credential_store.insert_pending(&value).await?;
+        if value.get("credentialStatus").is_none() {
+            let entry = credential_store
+                .assign_status_index(&id, &lifecycle.status_list, STATUS_LIST_SIZE)
+                .await?;
+            lifecycle.add_status_entry(&mut value, &entry);
+        }
+        let credential = serde_json::from_value(value)?;
+        Ok((credential, Some(id)))
+    }
+
+    /// Respond with a credential store error: 404 for an unknown credential, 409 for a
+    /// transition that its state does not allow.
+    #[cfg(feature = "store")]
+    fn store_error_response(
+        err: StoreError,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        let status_code = match err {
+            StoreError::NotFound(_) => StatusCode::NOT_FOUND,
+            StoreError::InvalidTransition(..) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::response(status_code, err.to_string())
+    }
+
+    fn json_response(value: &impl Serialize) -> Result<Response<Body>, Error> {
+        Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec_pretty(value)?))
+            .map_err(|err| err.into())
+    }
+
+    /// Credential lifecycle endpoints: get a tracked credential and its status list entry (`GET
+    /// /credentials/{id}`), change its state (`POST /credentials/{id}/status`), or get its audit
+    /// log (`GET /credentials/{id}/audit`).
+    #[cfg(feature = "store")]
+    pub fn credential_lifecycle(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        let (lifecycle, credential_store) = match (&self.lifecycle, &self.credential_store) {
+            (Some(lifecycle), Some(credential_store)) => {
+                (lifecycle.clone(), credential_store.clone())
+            }
+            _ => return self.not_found(),
+        };
+        let (id, endpoint) = match lifecycle::parse_path(req.uri().path()) {
+            Some((id, endpoint)) => (id.to_string(), endpoint.map(|e| e.to_string())),
+            None => return self.not_found(),
+        };
+        let method = match endpoint.as_deref() {
+            None | Some("audit") => Method::GET,
+            Some("status") => Method::POST,
+            Some(_) => return self.not_found(),
+        };
+        if req.method() != method {
+            return self.method_not_allowed();
+        }
+        if method == Method::POST {
+            if let Some(resp) = self.ensure_json(&req) {
+                return resp;
+            }
+        }
+        if let Some(resp) = self.ensure_accept_json(&req) {
+            return resp;
+        }
+        Box::pin(async move {
+            match endpoint.as_deref() {
+                None => {
+                    let record = match credential_store.get(&id).await {
+                        Ok(Some(record)) => record,
+                        Ok(None) => {
+                            return Self::store_error_response(StoreError::NotFound(id)).await
                         }
+                        Err(err) => return Self::store_error_response(err).await,
                     };
-                    Body::from(jwt.into_bytes())
+                    let mut value = serde_json::to_value(&record)?;
+                    match credential_store.status_entry(&id).await {
+                        Ok(Some(entry)) => value["statusEntry"] = serde_json::to_value(&entry)?,
+                        Ok(None) => {}
+                        Err(err) => return Self::store_error_response(err).await,
+                    }
+                    Self::json_response(&value)
                 }
-                ProofFormat::LDP => {
-                    let proof = match credential
-                        .generate_proof(key, &options.ldp_options, &resolver)
-                        .await
-                    {
-                        Ok(reader) => reader,
-                        Err(err) => {
-                            return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                Some("audit") => match credential_store.audit_log(&id).await {
+                    Ok(log) if log.is_empty() => {
+                        Self::store_error_response(StoreError::NotFound(id)).await
+                    }
+                    Ok(log) => Self::json_response(&log),
+                    Err(err) => Self::store_error_response(err).await,
+                },
+                _ => {
+                    let body = hyper::body::to_bytes(req).await?;
+                    let transition_req: TransitionRequest = match validation::parse(&body) {
+                        Ok(transition_req) => transition_req,
+                        Err(problem) => {
+                            return Self::problem_response(problem).await;
                         }
                     };
-                    credential.add_proof(proof);
-                    Body::from(serde_json::to_vec_pretty(&credential)?)
+                    if !lifecycle.allows_state(transition_req.state) {
+                        let err_msg = format!(
+                            "Credentials cannot be {} with a {} status list",
+                            transition_req.state, lifecycle.purpose
+                        );
+                        return Self::response(StatusCode::CONFLICT, err_msg).await;
+                    }
+                    match credential_store
+                        .transition(&id, transition_req.state, transition_req.reason.as_deref())
+                        .await
+                    {
+                        Ok(entry) => Self::json_response(&entry),
+                        Err(err) => Self::store_error_response(err).await,
+                    }
                 }
-                _ => {
-                    return Self::response(
-                        StatusCode::BAD_REQUEST,
-                        format!("Unknown proof format: {}", proof_format),
-                    )
-                    .await;
+            }
+        })
+    }
+
+    /// Serve the hosted status list credential, signed by its issuer, from the states of the
+    /// tracked credentials.
+    #[cfg(feature = "store")]
+    pub fn hosted_status_list(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::GET {
+            return self.method_not_allowed();
+        }
+        let (lifecycle, credential_store) = match (&self.lifecycle, &self.credential_store) {
+            (Some(lifecycle), Some(credential_store)) => {
+                (lifecycle.clone(), credential_store.clone())
+            }
+            _ => return self.not_found(),
+        };
+        let keys = self.keys.clone();
+        let resolver_options = self.resolver_options.clone();
+        Box::pin(async move {
+            let indexes = match credential_store
+                .status_list_indexes(&lifecycle.status_list, lifecycle.purpose)
+                .await
+            {
+                Ok(indexes) => indexes,
+                Err(err) => return Self::store_error_response(err).await,
+            };
+            let credential = match lifecycle.status_list_credential(&indexes) {
+                Ok(credential) => serde_json::from_value(credential)?,
+                Err(err) => {
+                    return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                        .await;
                 }
             };
-
-            Response::builder()
-                .status(StatusCode::CREATED)
-                .header(CONTENT_TYPE, "application/json")
-                .body(body)
-                .map_err(|err| err.into())
+            let mut options = IssueOptions::default();
+            let resolver = resolver_options.to_resolver();
+            if let Some(err_msg) = Self::discover_verification_method(
+                &keys,
+                &mut options,
+                Some(&lifecycle.issuer),
+                ProofPurpose::AssertionMethod,
+                &resolver,
+            )
+            .await
+            {
+                return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err_msg).await;
+            }
+            let signer = match pick_key(&keys, &options.ldp_options, &resolver).await {
+                Some(signer) => signer,
+                None => return Self::missing_key().await,
+            };
+            match issue_credential(credential, &options, signer.as_ref(), &resolver).await {
+                Ok(CredentialOrJWT::Credential(credential)) => Self::json_response(&credential),
+                Ok(CredentialOrJWT::JWT(jwt)) => Self::json_response(&jwt),
+                Err(err) => Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, err).await,
+            }
         })
     }
 
+    /// If the proof options give no verification method, and the issuer or holder is a DID, use
+    /// its verification method for one of the keys. Returns an error message if none matches.
+    pub(crate) async fn discover_verification_method(
+        keys: &KeyMap,
+        options: &mut IssueOptions,
+        did: Option<&str>,
+        default_purpose: ProofPurpose,
+        resolver: &dyn DIDResolver,
+    ) -> Option<String> {
+        let did = match did {
+            Some(did) if did.starts_with("did:") => did,
+            _ => return None,
+        };
+        if options.ldp_options.verification_method.is_some() || keys.is_empty() {
+            return None;
+        }
+        let purpose = options
+            .ldp_options
+            .proof_purpose
+            .clone()
+            .unwrap_or(default_purpose);
+        match discover_key(keys, did, &purpose, resolver).await {
+            Ok(vm) => {
+                options.ldp_options.verification_method = Some(URI::String(vm));
+                None
+            }
+            Err(err) => Some(err.to_string()),
+        }
+    }
+
+    /// Get the message catalog for the `Accept-Language` header of a request, if catalogs are
+    /// configured.
+    fn negotiate_catalog(&self, req: &Request<Body>) -> Option<Catalog> {
+        let accept_language = req.headers().get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+        self.catalogs.as_ref()?.negotiate(accept_language).cloned()
+    }
+
+    /// Add the configured context documents to the verification options, keeping those given
+    /// in the request.
+    fn add_context_documents(
+        options: &mut JWTOrLDPOptions,
+        documents: Option<Arc<HashMap<String, String>>>,
+    ) {
+        let documents = match documents {
+            Some(documents) => documents,
+            None => return,
+        };
+        let request_documents = options
+            .verification_options
+            .context_documents
+            .get_or_insert_with(HashMap::new);
+        for (url, document) in documents.iter() {
+            request_documents
+                .entry(url.to_string())
+                .or_insert_with(|| document.to_string());
+        }
+    }
+
+    /// Respond with the verification result of a report, with its messages localized if a
+    /// catalog was negotiated.
+    fn verification_response(
+        report: VerificationReport,
+        catalog: Option<Catalog>,
+        decoded: Option<serde_json::Value>,
+    ) -> Result<Response<Body>, Error> {
+        let localized = catalog.map(|catalog| catalog.localize(&report));
+        let resolutions = report.resolutions.clone();
+        let result = VerificationResult::from(report);
+        let mut builder = Response::builder()
+            .status(match result.errors.is_empty() {
+                true => StatusCode::OK,
+                false => StatusCode::BAD_REQUEST,
+            })
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(ref localized) = localized {
+            builder = builder.header(CONTENT_LANGUAGE, localized.locale.as_str());
+        }
+        let body = VerificationResponse {
+            result,
+            localized,
+            resolutions,
+            decoded,
+        };
+        builder
+            .body(Body::from(serde_json::to_vec_pretty(&body)?))
+            .map_err(|err| err.into())
+    }
+
     pub fn verify_credentials(
         &self,
         req: Request<Body>,
@@ -276,44 +1097,57 @@ impl DIDKitHTTPSvc {
             return resp;
         };
         let resolver_options = self.resolver_options.clone();
+        let status_cache = self.status_cache.clone();
+        let catalog = self.negotiate_catalog(&req);
+        let context_documents = self.context_documents.clone();
         Box::pin(async move {
-            let body_reader = hyper::body::aggregate(req).await?.reader();
-            let verify_req: VerifyCredentialRequest = match serde_json::from_reader(body_reader) {
+            let body = hyper::body::to_bytes(req).await?;
+            let verify_req: VerifyCredentialRequest = match validation::parse(&body) {
                 Ok(reader) => reader,
-                Err(err) => {
-                    return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                Err(problem) => {
+                    return Self::problem_response(problem).await;
                 }
             };
-            let resolver = resolver_options.to_resolver();
-            let options = verify_req.options.unwrap_or_default();
-            let ldp_options = options.ldp_options;
-            let result = match (options.proof_format, verify_req.verifiable_credential) {
-                (Some(ProofFormat::LDP), CredentialOrJWT::Credential(vc))
-                | (None, CredentialOrJWT::Credential(vc)) => {
-                    vc.verify(Some(ldp_options), &resolver).await
-                }
-                (Some(ProofFormat::JWT), CredentialOrJWT::JWT(vc_jwt))
-                | (None, CredentialOrJWT::JWT(vc_jwt)) => {
-                    VerifiableCredential::verify_jwt(&vc_jwt, Some(ldp_options), &resolver).await
-                }
-                (Some(proof_format), vc) => {
-                    let err_msg = format!(
-                        "Credential/proof format mismatch. Proof format: {}, credential: {}",
-                        proof_format,
-                        serde_json::to_string(&vc)?
-                    );
-                    return Self::response(StatusCode::BAD_REQUEST, err_msg).await;
+            let mut options = verify_req.options.unwrap_or_default();
+            Self::add_context_documents(&mut options, context_documents);
+            let vc = verify_req.verifiable_credential;
+            let proof_step = {
+                let (vc, options) = (vc.clone(), options.clone());
+                concurrency::run_blocking(async move {
+                    let resolver = resolver_options.to_resolver();
+                    verify_credential(&vc, &options, &resolver).await
+                })
+            };
+            let report = match proof_step.await? {
+                Ok(mut report) => {
+                    if let Some(ref status_cache) = status_cache {
+                        status_cache.check_credential(&vc, &mut report);
+                    }
+                    report
+                }
+                Err(err) => {
+                    let mut info = err.info();
+                    if let DIDKitError::ProofFormatMismatch(proof_format) = err {
+                        info.message = format!(
+                            "Credential/proof format mismatch. Proof format: {}, credential: {}",
+                            proof_format,
+                            serde_json::to_string(&vc)?
+                        );
+                    }
+                    return Self::info_response(StatusCode::BAD_REQUEST, info).await;
                 }
             };
-            let body = Body::from(serde_json::to_vec_pretty(&result)?);
-            Response::builder()
-                .status(match result.errors.is_empty() {
-                    true => StatusCode::OK,
-                    false => StatusCode::BAD_REQUEST,
-                })
-                .header(CONTENT_TYPE, "application/json")
-                .body(body)
-                .map_err(|err| err.into())
+            let mut decoded = None;
+            if verify_req.decode && report.is_success() {
+                let jwt_claims = options.jwt_claims.unwrap_or_default();
+                match convert::decode_credential(&vc, &jwt_claims) {
+                    Ok(vc) => decoded = Some(serde_json::to_value(vc)?),
+                    Err(err) => {
+                        return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                    }
+                }
+            }
+            Self::verification_response(report, catalog, decoded)
         })
     }
 
@@ -333,60 +1167,67 @@ impl DIDKitHTTPSvc {
         let keys = self.keys.clone();
         let resolver_options = self.resolver_options.clone();
         Box::pin(async move {
-            let body_reader = hyper::body::aggregate(req).await?.reader();
-            let issue_req: ProvePresentationRequest = match serde_json::from_reader(body_reader) {
+            let body = hyper::body::to_bytes(req).await?;
+            let issue_req: ProvePresentationRequest = match validation::parse(&body) {
                 Ok(reader) => reader,
-                Err(err) => {
-                    return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                Err(problem) => {
+                    return Self::problem_response(problem).await;
                 }
             };
             let options = issue_req
                 .options
                 .unwrap_or_else(JWTOrLDPOptions::default_for_vp);
-            let mut presentation = issue_req.presentation;
-            let proof_format = options.proof_format.unwrap_or_default();
-            let ldp_options = options.ldp_options;
+            let mut options = match IssueOptions::try_from(options) {
+                Ok(options) => options,
+                Err(err) => {
+                    return Self::problem_response(Problem::invalid_options(err)).await;
+                }
+            };
             let resolver = resolver_options.to_resolver();
-            let key = match pick_key(&keys, &ldp_options, &resolver).await {
-                Some(key) => key,
+            let holder = issue_req
+                .presentation
+                .holder
+                .as_ref()
+                .map(ToString::to_string);
+            if let Some(err_msg) = Self::discover_verification_method(
+                &keys,
+                &mut options,
+                holder.as_deref(),
+                ProofPurpose::Authentication,
+                &resolver,
+            )
+            .await
+            {
+                return Self::response(StatusCode::BAD_REQUEST, err_msg).await;
+            }
+            let signer = match pick_key(&keys, &options.ldp_options, &resolver).await {
+                Some(signer) => signer,
                 None => return Self::missing_key().await,
             };
-            let body = match proof_format {
-                ProofFormat::JWT => {
-                    let jwt = match presentation
-                        .generate_jwt(Some(&key), &ldp_options, &resolver)
-                        .await
-                    {
-                        Ok(reader) => reader,
-                        Err(err) => {
-                            return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
-                        }
-                    };
-                    Body::from(jwt.into_bytes())
-                }
-                ProofFormat::LDP => {
-                    let proof = match presentation
-                        .generate_proof(key, &ldp_options, &resolver)
-                        .await
-                    {
-                        Ok(reader) => reader,
-                        Err(err) => {
-                            return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
-                        }
-                    };
-                    presentation.add_proof(proof);
+            let status_code = if options.dry_run {
+                StatusCode::OK
+            } else {
+                StatusCode::CREATED
+            };
+            let signer = signer.clone();
+            let resolver_options = resolver_options.clone();
+            let presentation = issue_req.presentation;
+            let body = match concurrency::run_blocking(async move {
+                let resolver = resolver_options.to_resolver();
+                issue_presentation(presentation, &options, signer.as_ref(), &resolver).await
+            })
+            .await?
+            {
+                Ok(PresentationOrJWT::JWT(jwt)) => Body::from(jwt.into_bytes()),
+                Ok(PresentationOrJWT::VP(presentation)) => {
                     Body::from(serde_json::to_vec_pretty(&presentation)?)
                 }
-                _ => {
-                    return Self::response(
-                        StatusCode::BAD_REQUEST,
-                        format!("Unknown proof format: {}", proof_format),
-                    )
-                    .await;
+                Err(err) => {
+                    return Self::error_response(StatusCode::BAD_REQUEST, err).await;
                 }
             };
             Response::builder()
-                .status(StatusCode::CREATED)
+                .status(status_code)
                 .header(CONTENT_TYPE, "application/json")
                 .body(body)
                 .map_err(|err| err.into())
@@ -407,45 +1248,326 @@ impl DIDKitHTTPSvc {
             return resp;
         };
         let resolver_options = self.resolver_options.clone();
+        let nonce_store = self.nonce_store.clone();
+        let status_cache = self.status_cache.clone();
+        let catalog = self.negotiate_catalog(&req);
+        let context_documents = self.context_documents.clone();
         Box::pin(async move {
-            let body_reader = hyper::body::aggregate(req).await?.reader();
-            let verify_req: VerifyPresentationRequest = match serde_json::from_reader(body_reader) {
+            let body = hyper::body::to_bytes(req).await?;
+            let verify_req: VerifyPresentationRequest = match validation::parse(&body) {
                 Ok(reader) => reader,
-                Err(err) => {
-                    return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                Err(problem) => {
+                    return Self::problem_response(problem).await;
                 }
             };
-            let resolver = resolver_options.to_resolver();
-            let options = verify_req
+            let mut options = verify_req
                 .options
                 .unwrap_or_else(JWTOrLDPOptions::default_for_vp);
-            let ldp_options = options.ldp_options;
-            let result = match (options.proof_format, verify_req.verifiable_presentation) {
-                (Some(ProofFormat::LDP), PresentationOrJWT::VP(vp))
-                | (None, PresentationOrJWT::VP(vp)) => {
-                    vp.verify(Some(ldp_options), &resolver).await
-                }
-                (Some(ProofFormat::JWT), PresentationOrJWT::JWT(vp_jwt))
-                | (None, PresentationOrJWT::JWT(vp_jwt)) => {
-                    VerifiablePresentation::verify_jwt(&vp_jwt, Some(ldp_options), &resolver).await
-                }
-                (Some(proof_format), vp) => {
-                    let err_msg = format!(
-                        "Presentation/proof format mismatch. Proof format: {}, presentation: {}",
-                        proof_format,
-                        serde_json::to_string(&vp)?
-                    );
+            Self::add_context_documents(&mut options, context_documents);
+            let vp = verify_req.verifiable_presentation;
+            let proof_step = {
+                let (vp, options) = (vp.clone(), options.clone());
+                concurrency::run_blocking(async move {
+                    let resolver = resolver_options.to_resolver();
+                    verify_presentation(&vp, &options, &resolver).await
+                })
+            };
+            let report = match proof_step.await? {
+                Ok(mut report) => {
+                    if let Some(ref status_cache) = status_cache {
+                        status_cache.check_presentation(&vp, &mut report);
+                    }
+                    report
+                }
+                Err(err) => {
+                    let mut info = err.info();
+                    if let DIDKitError::ProofFormatMismatch(proof_format) = err {
+                        info.message = format!(
+                            "Presentation/proof format mismatch. Proof format: {}, presentation: {}",
+                            proof_format,
+                            serde_json::to_string(&vp)?
+                        );
+                    }
+                    return Self::info_response(StatusCode::BAD_REQUEST, info).await;
+                }
+            };
+            if report.is_success() {
+                if let Some(err_msg) = Self::consume_challenge(nonce_store, &options).await? {
                     return Self::response(StatusCode::BAD_REQUEST, err_msg).await;
                 }
+            }
+            let mut decoded = None;
+            if verify_req.decode && report.is_success() {
+                let jwt_claims = options.jwt_claims.unwrap_or_default();
+                match convert::decode_presentation(&vp, &jwt_claims) {
+                    Ok(vp) => decoded = Some(vp),
+                    Err(err) => {
+                        return Self::response(StatusCode::BAD_REQUEST, err.to_string()).await;
+                    }
+                }
+            }
+            Self::verification_response(report, catalog, decoded)
+        })
+    }
+
+    /// Issue a challenge, for use in a presentation.
+    pub fn create_challenge(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::POST {
+            return self.method_not_allowed();
+        }
+        let nonce_store = match self.nonce_store {
+            Some(ref nonce_store) => nonce_store.clone(),
+            None => return self.not_found(),
+        };
+        Box::pin(async move {
+            let challenge = match nonce_store.create().await {
+                Ok(challenge) => challenge,
+                Err(NonceError::Full) => {
+                    let err_msg = NonceError::Full.to_string();
+                    return Self::response(StatusCode::SERVICE_UNAVAILABLE, err_msg).await;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let value = ChallengeResponse {
+                challenge,
+                expires_in: nonce_store.ttl().as_secs(),
             };
-            let body = Body::from(serde_json::to_vec_pretty(&result)?);
             Response::builder()
+                .status(StatusCode::CREATED)
                 .header(CONTENT_TYPE, "application/json")
-                .status(match result.errors.is_empty() {
-                    true => StatusCode::OK,
-                    false => StatusCode::BAD_REQUEST,
+                .body(Body::from(serde_json::to_vec_pretty(&value)?))
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Issue a proof of work challenge, for a verification request.
+    pub fn create_pow_challenge(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::POST {
+            return self.method_not_allowed();
+        }
+        let proof_of_work = match self.proof_of_work {
+            Some(ref proof_of_work) => proof_of_work.clone(),
+            None => return self.not_found(),
+        };
+        Box::pin(async move {
+            let nonce_store = proof_of_work.nonce_store();
+            let value = PowChallengeResponse {
+                challenge: nonce_store.create().await?,
+                difficulty: proof_of_work.difficulty(),
+                expires_in: nonce_store.ttl().as_secs(),
+            };
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec_pretty(&value)?))
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Issue a challenge, as a DIDAuth request.
+    pub fn did_auth_challenge(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::POST {
+            return self.method_not_allowed();
+        }
+        let (nonce_store, did_auth) = match (&self.nonce_store, &self.did_auth) {
+            (Some(nonce_store), Some(did_auth)) => (nonce_store.clone(), did_auth.clone()),
+            _ => return self.not_found(),
+        };
+        Box::pin(async move {
+            let challenge = nonce_store.create().await?;
+            let request = did_auth::request(&challenge, did_auth.domain.as_deref());
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec_pretty(&request)?))
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Verify a DIDAuth presentation answering a challenge, and issue a session assertion for
+    /// its holder.
+    pub fn did_auth_verify(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::POST {
+            return self.method_not_allowed();
+        }
+        let (nonce_store, did_auth) = match (&self.nonce_store, &self.did_auth) {
+            (Some(nonce_store), Some(did_auth)) => (nonce_store.clone(), did_auth.clone()),
+            _ => return self.not_found(),
+        };
+        if let Some(resp) = self.ensure_json(&req) {
+            return resp;
+        };
+        let keys = self.keys.clone();
+        let resolver_options = self.resolver_options.clone();
+        Box::pin(async move {
+            let body = hyper::body::to_bytes(req).await?;
+            let auth_req: DIDAuthRequest = match validation::parse(&body) {
+                Ok(auth_req) => auth_req,
+                Err(problem) => {
+                    return Self::problem_response(problem).await;
+                }
+            };
+            let resolver = resolver_options.to_resolver();
+            let proof_step = {
+                let resolver_options = resolver_options.clone();
+                let vp = auth_req.verifiable_presentation;
+                let challenge = auth_req.challenge.clone();
+                let domain = did_auth.domain.clone();
+                concurrency::run_blocking(async move {
+                    let resolver = resolver_options.to_resolver();
+                    did_auth::verify(&vp, &challenge, domain.as_deref(), &resolver).await
                 })
-                .body(body)
+            };
+            let domain = did_auth.domain.as_deref();
+            let holder = match proof_step.await? {
+                Ok(holder) => holder,
+                Err(err) => return Self::response(StatusCode::UNAUTHORIZED, err.to_string()).await,
+            };
+            if !nonce_store.consume(&auth_req.challenge).await? {
+                return Self::response(
+                    StatusCode::BAD_REQUEST,
+                    "Unknown, expired or already used challenge".to_string(),
+                )
+                .await;
+            }
+            let options = LinkedDataProofOptions {
+                verification_method: Some(URI::String(did_auth.verification_method.clone())),
+                ..Default::default()
+            };
+            let signer = match pick_key(&keys, &options, &resolver).await {
+                Some(signer) => signer,
+                None => return Self::missing_key().await,
+            };
+            let ttl = chrono::Duration::from_std(did_auth.session_ttl)
+                .unwrap_or_else(|_| chrono::Duration::max_value());
+            let token = match did_auth::issue_session(
+                &holder,
+                &did_auth.verification_method,
+                domain,
+                ttl,
+                signer.as_ref(),
+            )
+            .await
+            {
+                Ok(token) => token,
+                Err(err) => {
+                    return Self::response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).await
+                }
+            };
+            let response = DIDAuthResponse {
+                holder,
+                token,
+                expires_in: did_auth.session_ttl.as_secs(),
+            };
+            Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec_pretty(&response)?))
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Check a session assertion given as bearer token, and return its claims.
+    pub fn did_auth_session(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::GET {
+            return self.method_not_allowed();
+        }
+        let did_auth = match self.did_auth {
+            Some(ref did_auth) => did_auth.clone(),
+            None => return self.not_found(),
+        };
+        let token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+        let token = match token {
+            Some(token) => token,
+            None => {
+                return Self::response(StatusCode::UNAUTHORIZED, "Missing bearer token".to_string())
+            }
+        };
+        let resolver_options = self.resolver_options.clone();
+        Box::pin(async move {
+            let resolver = resolver_options.to_resolver();
+            let issuer = did_auth
+                .verification_method
+                .split('#')
+                .next()
+                .unwrap_or_default();
+            let audience = did_auth.domain.as_deref();
+            let claims = match did_auth::verify_session(&token, issuer, audience, &resolver).await {
+                Ok(claims) => claims,
+                Err(err) => return Self::response(StatusCode::UNAUTHORIZED, err.to_string()).await,
+            };
+            Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec_pretty(&claims)?))
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Serve the health of the DID resolver endpoints of the resolver configuration.
+    pub fn resolver_health(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::GET {
+            return self.method_not_allowed();
+        }
+        let federation = match self.resolver_options.resolver_config {
+            Some(ref federation) => federation.clone(),
+            None => return self.not_found(),
+        };
+        Box::pin(async move { Self::json_response(&federation.health()) })
+    }
+
+    /// Serve the OpenAPI document of the server's routes.
+    pub fn openapi_document(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::GET {
+            return self.method_not_allowed();
+        }
+        let base_url = self.base_url.clone();
+        Box::pin(async move {
+            let mut document = openapi::document();
+            if let Some(base_url) = base_url {
+                document["servers"] = json!([{ "url": base_url.to_string() }]);
+            }
+            Self::json_response(&document)
+        })
+    }
+
+    /// Serve a Swagger UI of the OpenAPI document.
+    pub fn swagger_ui(
+        &self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        if req.method() != Method::GET {
+            return self.method_not_allowed();
+        }
+        let openapi_path = self.external_path(openapi::OPENAPI_PATH);
+        Box::pin(async move {
+            Response::builder()
+                .header(CONTENT_TYPE, "text/html; charset=utf-8")
+                .body(Body::from(openapi::swagger_ui(&openapi_path)))
                 .map_err(|err| err.into())
         })
     }
@@ -502,18 +1624,28 @@ impl DIDKitHTTPSvc {
             };
             let resolver = resolver_options.to_resolver();
             // skip root "/identifiers/" to get DID
-            let (deref_meta, content, content_meta) =
+            let (mut deref_meta, content, content_meta) =
                 dereference_did_url(&resolver, &did_url, &deref_input_meta).await;
+            resolution_error::normalize_dereferencing_metadata(&mut deref_meta);
             let (mut parts, mut body) = Response::<Body>::default().into_parts();
             if let Some(ref error) = deref_meta.error {
                 // 1.6, 1.7, 1.8
-                parts.status = match &error[..] {
-                    ERROR_NOT_FOUND => StatusCode::NOT_FOUND,
-                    ERROR_INVALID_DID => StatusCode::BAD_REQUEST,
-                    ERROR_REPRESENTATION_NOT_SUPPORTED => StatusCode::NOT_ACCEPTABLE,
-                    _ => StatusCode::INTERNAL_SERVER_ERROR,
-                };
-                body = Body::from(error.as_bytes().to_vec());
+                parts.status = StatusCode::from_u16(resolution_error::status_code(error))
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                if deref_input_meta.accept.as_deref() == Some(TYPE_DID_RESOLUTION) {
+                    let result = ResolutionResult {
+                        did_resolution_metadata: Some(deref_meta.clone().into()),
+                        ..Default::default()
+                    };
+                    if let Ok(data) = serde_json::to_vec(&result) {
+                        if let Ok(content_type) = TYPE_DID_RESOLUTION.parse() {
+                            parts.headers.insert(CONTENT_TYPE, content_type);
+                        }
+                        body = Body::from(data);
+                    }
+                } else {
+                    body = Body::from(resolution_error::describe(error, &deref_meta.property_set));
+                }
             }
             if let ContentMetadata::DIDDocument(ref did_doc_meta) = content_meta {
                 if did_doc_meta.deactivated == Some(true) {
@@ -604,6 +1736,11 @@ impl DIDKitHTTPSvc {
                 Content::URL(url) => {
                     // 1.11
                     parts.status = StatusCode::SEE_OTHER;
+                    // Redirect ipfs:// service endpoints to the IPFS gateway.
+                    let url = match resolver_options.ipfs_gateway.gateway_url(&url) {
+                        Ok(gateway_url) => gateway_url,
+                        Err(_) => url,
+                    };
                     let location = match url.parse() {
                         Ok(location) => location,
                         Err(err) => {
@@ -649,43 +1786,228 @@ impl Service<Request<Body>> for DIDKitHTTPSvc {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let path = req.uri().path();
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        if let Some(ref base_url) = self.base_url {
+            if let Some(uri) = base_url.strip_uri(req.uri()) {
+                *req.uri_mut() = uri;
+            }
+        }
+        let response = match Self::workload(req.uri().path()) {
+            Some(workload) => {
+                let proof_of_work = match workload {
+                    Workload::Verification => self.proof_of_work.clone(),
+                    Workload::Signing => None,
+                };
+                let pow_header = req
+                    .headers()
+                    .get(POW_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                let response = self.route(req);
+                let response = self.run_workload(workload, response);
+                match proof_of_work {
+                    Some(proof_of_work) => {
+                        Self::require_proof_of_work(proof_of_work, pow_header, response)
+                    }
+                    None => response,
+                }
+            }
+            None => self.route(req),
+        };
+        let response = if self.canonical_json {
+            Self::canonical_response(response)
+        } else {
+            response
+        };
+        let timeout = match self.request_timeout {
+            Some(timeout) => timeout,
+            None => return response,
+        };
+        Box::pin(async move {
+            match tokio::time::timeout(timeout, response).await {
+                Ok(response) => response,
+                Err(_) => {
+                    Self::error_response(StatusCode::GATEWAY_TIMEOUT, CancelError::TimedOut).await
+                }
+            }
+        })
+    }
+}
+
+impl DIDKitHTTPSvc {
+    /// Kind of CPU-heavy request of a route, if any.
+    fn workload(path: &str) -> Option<Workload> {
         match path {
+            "/issue/credentials"
+            | "/credentials/issue"
+            | "/prove/presentations"
+            | "/credentials/prove" => Some(Workload::Signing),
+            "/verify/credentials"
+            | "/credentials/verify"
+            | "/verify/presentations"
+            | "/presentations/verify"
+            | "/auth/verify" => Some(Workload::Verification),
+            _ => None,
+        }
+    }
+
+    /// Handle a verification or signing request once it has a slot under the concurrency limits,
+    /// if any.
+    fn run_workload(
+        &self,
+        workload: Workload,
+        response: Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        let limits = self.concurrency_limits.clone();
+        Box::pin(async move {
+            let _permit = match limits {
+                Some(ref limits) => match limits.admit(workload).await {
+                    Ok(permit) => permit,
+                    Err(err) => {
+                        return Self::overloaded_response(err, limits.retry_after_secs()).await;
+                    }
+                },
+                None => None,
+            };
+            response.await
+        })
+    }
+
+    /// Reserialize a JSON response as canonical JSON. Bodies that are not JSON are left as is.
+    fn canonical_response(
+        response: Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Box::pin(async move {
+            let response = response.await?;
+            let is_json = match response.headers().get(CONTENT_TYPE) {
+                Some(content_type) => content_type
+                    .to_str()
+                    .map_or(false, |content_type| content_type.ends_with("json")),
+                None => false,
+            };
+            if !is_json {
+                return Ok(response);
+            }
+            let (mut parts, body) = response.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+            let body = match serde_json::from_slice(&body) {
+                Ok(mut value) => {
+                    canonical_json::sort_unordered(&mut value);
+                    parts.headers.remove(CONTENT_LENGTH);
+                    Body::from(canonical_json::jcs(&value))
+                }
+                Err(_) => Body::from(body),
+            };
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+
+    /// Respond to a verification request only if it has a proof of work, before it is admitted
+    /// for verification, so that requests without work are turned away at little cost. Requests
+    /// without an acceptable proof of work are `403 Forbidden`.
+    fn require_proof_of_work(
+        proof_of_work: Arc<ProofOfWork>,
+        pow_header: Option<String>,
+        response: Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Box::pin(async move {
+            match proof_of_work.check(pow_header.as_deref()).await? {
+                Ok(()) => response.await,
+                Err(err) => Self::response(StatusCode::FORBIDDEN, err.to_string()).await,
+            }
+        })
+    }
+
+    /// Respond to a shed request with `503 Service Unavailable`, and when to retry.
+    fn overloaded_response(
+        err: Overloaded,
+        retry_after: u64,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        Box::pin(async move {
+            let body = Body::from(serde_json::to_vec_pretty(&json!(err.to_string()))?);
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(CONTENT_TYPE, "application/json")
+                .header(RETRY_AFTER, retry_after.to_string())
+                .body(body)
+                .map_err(|err| err.into())
+        })
+    }
+
+    /// Handler of a request path, if it is one of the server's routes.
+    fn handler(&self, path: &str) -> Option<Handler> {
+        let handler: Handler = match path {
             // vc-http-api 0.0.1
-            "/issue/credentials" => return self.issue_credentials(req),
-            "/verify/credentials" => return self.verify_credentials(req),
-            "/prove/presentations" => return self.prove_presentations(req),
-            "/verify/presentations" => return self.verify_presentations(req),
+            "/issue/credentials" => Self::issue_credentials,
+            "/verify/credentials" => Self::verify_credentials,
+            "/prove/presentations" => Self::prove_presentations,
+            "/verify/presentations" => Self::verify_presentations,
             // vc-http-api 0.0.2-unstable
-            "/credentials/issue" => return self.issue_credentials(req),
-            "/credentials/verify" => return self.verify_credentials(req),
-            "/credentials/prove" => return self.prove_presentations(req),
-            "/presentations/verify" => return self.verify_presentations(req),
-            _ => {}
-        };
-        if path.starts_with("/identifiers/") {
+            "/credentials/issue" => Self::issue_credentials,
+            "/credentials/verify" => Self::verify_credentials,
+            "/credentials/prove" => Self::prove_presentations,
+            "/presentations/verify" => Self::verify_presentations,
+            "/challenges" => Self::create_challenge,
+            "/pow/challenges" => Self::create_pow_challenge,
+            "/auth/challenge" => Self::did_auth_challenge,
+            "/auth/verify" => Self::did_auth_verify,
+            "/auth/session" => Self::did_auth_session,
+            "/resolver/health" => Self::resolver_health,
+            openapi::OPENAPI_PATH => Self::openapi_document,
+            openapi::SWAGGER_UI_PATH if self.swagger_ui => Self::swagger_ui,
             // DID Resolution HTTP(S) binding
-            return self.resolve_dereference(req);
+            _ if path.starts_with("/identifiers/") => Self::resolve_dereference,
+            #[cfg(feature = "store")]
+            _ => return self.lifecycle_handler(path),
+            #[cfg(not(feature = "store"))]
+            _ => return None,
+        };
+        Some(handler)
+    }
+
+    /// Handler of a credential lifecycle route, if the lifecycle is tracked.
+    #[cfg(feature = "store")]
+    fn lifecycle_handler(&self, path: &str) -> Option<Handler> {
+        let lifecycle = self.lifecycle.as_ref()?;
+        if path == lifecycle.status_list_path() {
+            Some(Self::hosted_status_list)
+        } else if path.starts_with("/credentials/") {
+            Some(Self::credential_lifecycle)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a request path, without the base URL's path prefix, is one of the server's routes.
+    /// Other paths are `404 Not Found`.
+    pub fn has_route(&self, path: &str) -> bool {
+        self.handler(path).is_some()
+    }
+
+    fn route(
+        &mut self,
+        req: Request<Body>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>> {
+        match self.handler(req.uri().path()) {
+            Some(handler) => handler(self, req),
+            None => self.not_found(),
         }
-        self.not_found()
     }
 }
 
+/// Makes a configured [`DIDKitHTTPSvc`] for each connection, as a clone of it.
 pub struct DIDKitHTTPMakeSvc {
-    keys: KeyMap,
-    resolver_options: ResolverOptions,
+    svc: DIDKitHTTPSvc,
 }
 
 impl DIDKitHTTPMakeSvc {
-    pub fn new(keys: Vec<JWK>, resolver_options: ResolverOptions) -> Self {
-        Self {
-            keys: keys.into_iter().fold(KeyMap::new(), |mut map, key| {
-                map.insert(key.to_public(), key);
-                map
-            }),
-            resolver_options,
-        }
+    pub fn new(svc: DIDKitHTTPSvc) -> Self {
+        Self { svc }
+    }
+
+    /// The service of requests, e.g. to embed the routes in another service (see [`layer`]).
+    pub fn service(&self) -> DIDKitHTTPSvc {
+        self.svc.clone()
     }
 }
 
@@ -699,9 +2021,7 @@ impl<T> Service<T> for DIDKitHTTPMakeSvc {
     }
 
     fn call(&mut self, _: T) -> Self::Future {
-        let keys = self.keys.clone();
-        let resolver_options = self.resolver_options.clone();
-        let fut = async move { Ok(DIDKitHTTPSvc::new(keys, resolver_options)) };
-        Box::pin(fut)
+        let svc = self.service();
+        Box::pin(async move { Ok(svc) })
     }
 }
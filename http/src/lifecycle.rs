@@ -0,0 +1,234 @@
+//! Lifecycle of the credentials issued by the server, with a hosted status list.
+//!
+//! With a [`LifecycleConfig`] and a credential store, the server tracks the credentials it
+//! issues through their [`CredentialState`]s. A credential to issue is stored as pending, assigned
+//! a random index in the hosted status list, and given a `StatusList2021Entry` of it as
+//! `credentialStatus`; once signed, it is issued. Transitions between states are recorded in the
+//! store's audit log.
+//!
+//! The hosted status list credential is signed when requested, from the states in the store,
+//! following the [`StatusPurpose`] of the list. The bits of a `revocation` list are set when
+//! credentials are revoked, and never cleared: credentials cannot be suspended. The bits of a
+//! `suspension` list are set while credentials are suspended, cleared if they are reinstated,
+//! and stay set once they are revoked. A credential has a single `credentialStatus` entry, so
+//! the list has one purpose.
+
+use std::io::Write;
+
+use didkit::store::{CredentialState, StatusEntry, StatusPurpose};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::base_url::BaseUrl;
+
+/// Number of entries of the hosted status list: 16KB of bits, the minimum size for herd privacy.
+pub const STATUS_LIST_SIZE: u64 = 131_072;
+
+const CREDENTIALS_V1_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+const STATUS_LIST_CONTEXT: &str = "https://w3id.org/vc/status-list/2021/v1";
+
+/// Configuration of credential lifecycle tracking.
+#[derive(Debug, Clone)]
+pub struct LifecycleConfig {
+    /// URL of the hosted status list credential, which the server serves at its path
+    pub status_list: String,
+    /// DID of the issuer of the status list credential
+    pub issuer: String,
+    /// Purpose of the status list, `revocation` by default
+    pub purpose: StatusPurpose,
+    /// External base URL of the server, under which the status list is hosted
+    pub base_url: Option<BaseUrl>,
+}
+
+/// Request to change the state of a credential.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct TransitionRequest {
+    pub state: CredentialState,
+    pub reason: Option<String>,
+}
+
+/// Path of a credential lifecycle endpoint: the credential's ID in the store, and the endpoint
+/// under it, if any.
+pub fn parse_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let rest = path.strip_prefix("/credentials/")?;
+    let mut parts = rest.splitn(2, '/');
+    let id = parts.next().filter(|id| !id.is_empty())?;
+    Some((id, parts.next()))
+}
+
+impl LifecycleConfig {
+    pub fn new(status_list: String, issuer: String) -> Self {
+        Self {
+            status_list,
+            issuer,
+            purpose: StatusPurpose::default(),
+            base_url: None,
+        }
+    }
+
+    /// Set the purpose of the status list.
+    pub fn with_purpose(mut self, purpose: StatusPurpose) -> Self {
+        self.purpose = purpose;
+        self
+    }
+
+    /// Whether a credential may be put in a state, given the purpose of the status list:
+    /// revocation lists cannot express suspensions.
+    pub fn allows_state(&self, state: CredentialState) -> bool {
+        state != CredentialState::Suspended || self.purpose == StatusPurpose::Suspension
+    }
+
+    /// Host the status list under a base URL. A status list given as a path, e.g. `/status/1`,
+    /// is relative to the base URL.
+    pub fn with_base_url(mut self, base_url: BaseUrl) -> Self {
+        if self.status_list.starts_with('/') {
+            self.status_list = base_url.url(&self.status_list);
+        }
+        self.base_url = Some(base_url);
+        self
+    }
+
+    /// Path at which the server hosts the status list credential, without the path prefix of
+    /// the base URL.
+    pub fn status_list_path(&self) -> String {
+        let path = match self.status_list.parse::<hyper::Uri>() {
+            Ok(uri) => uri.path().to_string(),
+            Err(_) => self.status_list.clone(),
+        };
+        match self.base_url {
+            Some(ref base_url) => base_url.strip_prefix(&path).unwrap_or(&path).to_string(),
+            None => path,
+        }
+    }
+
+    /// Add a `credentialStatus` entry of the hosted status list to a credential, and the status
+    /// list context.
+    pub fn add_status_entry(&self, credential: &mut Value, entry: &StatusEntry) {
+        match credential.get("@context") {
+            Some(Value::Array(_)) => {}
+            Some(context) => {
+                let context = context.clone();
+                credential["@context"] = json!([context]);
+            }
+            None => credential["@context"] = json!([CREDENTIALS_V1_CONTEXT]),
+        }
+        if let Some(contexts) = credential["@context"].as_array_mut() {
+            if !contexts
+                .iter()
+                .any(|context| context == STATUS_LIST_CONTEXT)
+            {
+                contexts.push(Value::from(STATUS_LIST_CONTEXT));
+            }
+        }
+        credential["credentialStatus"] = json!({
+            "id": format!("{}#{}", entry.list, entry.index),
+            "type": "StatusList2021Entry",
+            "statusPurpose": self.purpose.to_string(),
+            "statusListIndex": entry.index.to_string(),
+            "statusListCredential": entry.list,
+        });
+    }
+
+    /// Unsigned status list credential, with the given indexes set.
+    pub fn status_list_credential(&self, indexes: &[u64]) -> Result<Value, std::io::Error> {
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        Ok(json!({
+            "@context": [CREDENTIALS_V1_CONTEXT, STATUS_LIST_CONTEXT],
+            "id": self.status_list,
+            "type": ["VerifiableCredential", "StatusList2021Credential"],
+            "issuer": self.issuer,
+            "issuanceDate": now,
+            "credentialSubject": {
+                "id": format!("{}#list", self.status_list),
+                "type": "StatusList2021",
+                "statusPurpose": self.purpose.to_string(),
+                "encodedList": encode_list(indexes, STATUS_LIST_SIZE)?,
+            },
+        }))
+    }
+}
+
+/// Encode a list of `size` bits, with the given indexes set, as an `encodedList`: a
+/// GZIP-compressed bitstring, in base64url. The first index is the most significant bit of the
+/// first byte.
+pub fn encode_list(indexes: &[u64], size: u64) -> Result<String, std::io::Error> {
+    let mut bits = vec![0u8; ((size + 7) / 8) as usize];
+    for &index in indexes {
+        if let Some(byte) = bits.get_mut((index / 8) as usize) {
+            *byte |= 0x80 >> (index % 8);
+        }
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&bits)?;
+    Ok(base64::encode_config(
+        encoder.finish()?,
+        base64::URL_SAFE_NO_PAD,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn status_list() {
+        let config = LifecycleConfig::new(
+            "https://issuer.example/status/1".to_string(),
+            "did:example:issuer".to_string(),
+        );
+        assert_eq!(config.status_list_path(), "/status/1");
+        let proxied = LifecycleConfig::new("/status/1".to_string(), config.issuer.clone())
+            .with_base_url("https://example.com/didkit".parse().unwrap());
+        assert_eq!(proxied.status_list, "https://example.com/didkit/status/1");
+        assert_eq!(proxied.status_list_path(), "/status/1");
+        assert_eq!(
+            parse_path("/credentials/abc/audit"),
+            Some(("abc", Some("audit")))
+        );
+        assert_eq!(parse_path("/credentials/abc"), Some(("abc", None)));
+        assert_eq!(parse_path("/credentials/"), None);
+
+        let mut credential = json!({ "@context": CREDENTIALS_V1_CONTEXT });
+        let entry = StatusEntry {
+            list: config.status_list.clone(),
+            index: 9,
+        };
+        config.add_status_entry(&mut credential, &entry);
+        assert_eq!(
+            credential["@context"],
+            json!([CREDENTIALS_V1_CONTEXT, STATUS_LIST_CONTEXT])
+        );
+        assert_eq!(credential["credentialStatus"]["statusListIndex"], "9");
+
+        let list = config.status_list_credential(&[1, 9]).unwrap();
+        let encoded = list["credentialSubject"]["encodedList"].as_str().unwrap();
+        let compressed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).unwrap();
+        let mut bits = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut bits)
+            .unwrap();
+        assert_eq!(bits.len(), (STATUS_LIST_SIZE / 8) as usize);
+        assert_eq!(&bits[..2], &[0x40, 0x40]);
+        assert_eq!(list["credentialSubject"]["statusPurpose"], "revocation");
+        assert!(!config.allows_state(CredentialState::Suspended));
+        assert!(config.allows_state(CredentialState::Revoked));
+
+        let suspension = config.with_purpose(StatusPurpose::Suspension);
+        assert!(suspension.allows_state(CredentialState::Suspended));
+        let mut credential = json!({ "@context": CREDENTIALS_V1_CONTEXT });
+        suspension.add_status_entry(&mut credential, &entry);
+        assert_eq!(
+            credential["credentialStatus"]["statusPurpose"],
+            "suspension"
+        );
+        let list = suspension.status_list_credential(&[9]).unwrap();
+        assert_eq!(list["credentialSubject"]["statusPurpose"], "suspension");
+    }
+}
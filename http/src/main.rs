@@ -1,14 +1,37 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use hyper::Server;
 use structopt::StructOpt;
 
-use didkit::JWK;
+use didkit::i18n::Catalogs;
+use didkit::integrity::{self, ContextLoaderEntry};
+use didkit::ipfs;
+use didkit::key_attestation::{KeyAttestationPolicy, SecurityLevel};
+use didkit::key_cache::{self, KeyCache};
+use didkit::resolver_config::ResolverFederation;
+use didkit::{Signer, JWK};
 use didkit_cli::opts::ResolverOptions;
-use didkit_http::DIDKitHTTPMakeSvc;
+use didkit_http::base_url::BaseUrl;
+use didkit_http::concurrency::ConcurrencyLimits;
+#[cfg(feature = "grpc")]
+use didkit_http::grpc::DIDKitGrpcSvc;
+use didkit_http::nonce::{MemoryNonceStore, NonceStore, StorageNonceStore};
+use didkit_http::pow::ProofOfWork;
+use didkit_http::status::StatusCache;
+use didkit_http::storage::{self, Storage};
+use didkit_http::templates::Templates;
+use didkit_http::worker::{self, Worker};
+use didkit_http::DIDAuthConfig;
 use didkit_http::Error;
+use didkit_http::{DIDKitHTTPMakeSvc, DIDKitHTTPSvc};
+
+/// Maximum number of verification method keys kept by the key cache.
+const KEY_CACHE_CAPACITY: usize = 10000;
 
 #[derive(StructOpt, Debug)]
 pub struct DIDKitHttpOpts {
@@ -23,6 +46,305 @@ pub struct DIDKitHttpOpts {
     key: KeyArg,
     #[structopt(flatten)]
     resolver_options: ResolverOptions,
+    /// Cache the public keys of verification methods resolved during verification, shared
+    /// across requests, for this many seconds
+    #[structopt(env, long)]
+    key_cache_ttl: Option<u64>,
+    /// Storage backend for state shared between server processes: "memory", or a Redis,
+    /// PostgreSQL or S3 (s3://bucket/prefix) URL
+    #[structopt(env, long, default_value = "memory")]
+    storage: String,
+    /// Issue challenges and require presentations to use them: "memory", "storage" (the
+    /// --storage backend, if it is not S3), or a Redis URL
+    #[structopt(env, long)]
+    nonce_store: Option<String>,
+    /// Validity period of issued challenges, in seconds
+    #[structopt(env, long, default_value = "300")]
+    challenge_ttl: u64,
+    /// Maximum number of unexpired challenges of the "memory" nonce store. Further challenge
+    /// requests are refused with 503 Service Unavailable.
+    #[structopt(env, long, default_value = "100000")]
+    max_challenges: usize,
+    /// Require verification requests to carry a proof of work of this difficulty: the number of
+    /// leading zero bits of the hash of a solution, e.g. 20 for about a million hashes. Requires
+    /// --nonce-store.
+    #[structopt(env, long, requires = "nonce-store")]
+    pow_difficulty: Option<u8>,
+    /// Offer DIDAuth login, signing session assertions with the issuer key of this verification
+    /// method (DID URL). Requires --nonce-store.
+    #[structopt(env, long, requires = "nonce-store")]
+    did_auth_verification_method: Option<String>,
+    /// Domain that DIDAuth presentations must be bound to, and audience of session assertions
+    #[structopt(env, long)]
+    did_auth_domain: Option<String>,
+    /// Validity period of DIDAuth session assertions, in seconds
+    #[structopt(env, long, default_value = "3600")]
+    session_ttl: u64,
+    /// URL of a status list credential to cache, and check the status of credentials with
+    #[structopt(env, long, use_delimiter = true)]
+    status_list: Vec<String>,
+    /// Interval between refreshes of the cached status lists, in seconds
+    #[structopt(env, long, default_value = "300")]
+    status_refresh: u64,
+    /// Maximum age of a cached status list that could not be refreshed, in seconds
+    #[structopt(env, long, default_value = "86400")]
+    status_max_age: u64,
+    /// URL to post notifications to when the status of a credential checked against a cached
+    /// status list changes
+    #[structopt(env, long)]
+    status_webhook: Option<String>,
+    /// Directory of message catalogs (<locale>.json), to localize verification results in the
+    /// language requested by Accept-Language
+    #[structopt(env, long, parse(from_os_str))]
+    message_catalogs: Option<PathBuf>,
+    /// JSON file of context documents to check context integrity against, as an array of
+    /// entries with a url, and a docBodyFilePath, docBody, or docUrl with a digestSRI. Documents
+    /// given by URL are fetched once, at startup.
+    #[structopt(env, long, parse(from_os_str))]
+    additional_contexts: Option<PathBuf>,
+    /// JSON file of issuance templates by name, each with a base credential, default options and
+    /// the allowedFields that requests may set, selected with the template query parameter of
+    /// /credentials/issue
+    #[structopt(env, long, parse(from_os_str))]
+    issuance_templates: Option<PathBuf>,
+    /// Require issuance requests to attest that the subject's key is hardware-bound, trusting
+    /// the PEM root certificates of a file for an attestation format:
+    /// FORMAT=FILE, e.g. android-key=google-roots.pem. Requires --nonce-store.
+    #[structopt(env, long, use_delimiter = true, requires = "nonce-store")]
+    key_attestation_root: Vec<String>,
+    /// Minimum security level of attested keys: software, trustedEnvironment or secureElement
+    #[structopt(env, long, default_value = "trustedEnvironment")]
+    key_attestation_min_level: SecurityLevel,
+    /// Serve a Swagger UI of the OpenAPI document (/openapi.json) at /docs
+    #[structopt(long)]
+    swagger_ui: bool,
+    /// Respond with 504 Gateway Timeout to requests not handled within this number of seconds
+    #[structopt(env, long)]
+    request_timeout: Option<u64>,
+    /// External URL of the server, e.g. behind a reverse proxy, used in the URLs it gives out
+    /// for itself. Requests may have its path prefix.
+    #[structopt(env, long)]
+    base_url: Option<BaseUrl>,
+    /// Maximum number of credentials and presentations verified at a time
+    #[structopt(env, long)]
+    max_concurrent_verifications: Option<usize>,
+    /// Maximum number of credentials and presentations issued at a time
+    #[structopt(env, long)]
+    max_concurrent_signings: Option<usize>,
+    /// Maximum number of verification (or signing) requests waiting for one of the
+    /// --max-concurrent-verifications (or --max-concurrent-signings) slots. Further requests are
+    /// shed with 503 Service Unavailable.
+    #[structopt(env, long, default_value = "64")]
+    queue_depth: usize,
+    /// Retry-After of shed requests, in seconds
+    #[structopt(env, long, default_value = "1")]
+    retry_after: u64,
+    /// Number of worker threads of the runtime serving requests. Defaults to the number of CPU
+    /// cores.
+    #[structopt(env, long)]
+    runtime_threads: Option<usize>,
+    /// Maximum number of threads of the blocking pool running verifications and signings
+    #[structopt(env, long)]
+    max_blocking_threads: Option<usize>,
+    /// Respond with canonical JSON (RFC 8785), with the unordered arrays of verification results
+    /// sorted
+    #[structopt(long)]
+    canonical_json: bool,
+    /// Run as an issuance worker, consuming requests from the message broker at this URL
+    #[structopt(env, long)]
+    worker: Option<String>,
+    /// Topic, queue or subject to consume issuance requests from
+    #[structopt(env, long, default_value = "didkit-issue-requests")]
+    worker_requests: String,
+    /// Topic, queue or subject to publish issuance results to
+    #[structopt(env, long, default_value = "didkit-issue-results")]
+    worker_results: String,
+    /// Consumer group, tag or durable name of the worker
+    #[structopt(env, long, default_value = "didkit-http")]
+    worker_group: String,
+    /// Maximum number of requests processed at a time by the worker
+    #[structopt(env, long, default_value = "16")]
+    worker_concurrency: usize,
+    /// How long to keep the results of processed requests, to answer redelivered requests
+    /// with the same idempotency key, in seconds
+    #[structopt(env, long, default_value = "86400")]
+    idempotency_ttl: u64,
+    /// Port to serve the gRPC interface on, in addition to HTTP
+    #[cfg(feature = "grpc")]
+    #[structopt(env, long)]
+    grpc_port: Option<u16>,
+    /// URL of a database to record issued credentials in, e.g. sqlite://issued.db?mode=rwc
+    #[cfg(feature = "store")]
+    #[structopt(env, long)]
+    credential_store: Option<String>,
+    /// URL of a status list credential to host, and to track the lifecycle of issued credentials
+    /// with, or its path under --base-url. Requires --credential-store and
+    /// --hosted-status-list-issuer.
+    #[cfg(feature = "store")]
+    #[structopt(
+        env,
+        long,
+        requires_all = &["credential-store", "hosted-status-list-issuer"]
+    )]
+    hosted_status_list: Option<String>,
+    /// DID that signs the hosted status list credential, with one of the keys
+    #[cfg(feature = "store")]
+    #[structopt(env, long)]
+    hosted_status_list_issuer: Option<String>,
+    /// Status purpose of the hosted status list: revocation, or suspension to also allow
+    /// suspending credentials
+    #[cfg(feature = "store")]
+    #[structopt(env, long, default_value = "revocation")]
+    hosted_status_purpose: didkit::store::StatusPurpose,
+}
+
+impl DIDKitHttpOpts {
+    fn get_nonce_store(&self, storage: &Arc<dyn Storage>) -> Option<Arc<dyn NonceStore>> {
+        self.open_nonce_store(storage, StorageNonceStore::DEFAULT_NAMESPACE)
+    }
+
+    /// Open a store of challenges of the configured kind, with challenges apart from those of
+    /// other namespaces in a shared backend.
+    fn open_nonce_store(
+        &self,
+        storage: &Arc<dyn Storage>,
+        namespace: &str,
+    ) -> Option<Arc<dyn NonceStore>> {
+        let ttl = Duration::from_secs(self.challenge_ttl);
+        match self.nonce_store.as_deref()? {
+            "memory" => Some(Arc::new(
+                MemoryNonceStore::new(ttl).with_capacity(self.max_challenges),
+            )),
+            "storage" if !storage.is_atomic() => invalid_value(format!(
+                "--nonce-store storage requires a --storage backend with atomic operations, such \
+                 as Redis or PostgreSQL: {}",
+                self.storage
+            )),
+            "storage" => Some(Arc::new(
+                StorageNonceStore::new(storage.clone(), ttl).with_namespace(namespace),
+            )),
+            #[cfg(feature = "redis")]
+            url if url.starts_with("redis://") || url.starts_with("rediss://") => {
+                match didkit_http::nonce::RedisNonceStore::new(url, ttl) {
+                    Ok(nonce_store) => Some(Arc::new(
+                        nonce_store.with_prefix(&format!("didkit:{}:", namespace)),
+                    )),
+                    Err(err) => invalid_value(format!("Invalid nonce store: {}", err)),
+                }
+            }
+            other => invalid_value(format!("Unsupported nonce store: {}", other)),
+        }
+    }
+
+    fn get_did_auth(&self) -> Option<DIDAuthConfig> {
+        Some(DIDAuthConfig {
+            verification_method: self.did_auth_verification_method.clone()?,
+            domain: self.did_auth_domain.clone(),
+            session_ttl: Duration::from_secs(self.session_ttl),
+        })
+    }
+
+    fn get_catalogs(&self) -> Option<Arc<Catalogs>> {
+        let mut catalogs = Catalogs::new();
+        catalogs.load_dir(self.message_catalogs.as_ref()?).unwrap();
+        Some(Arc::new(catalogs))
+    }
+
+    async fn get_context_documents(&self) -> Option<Arc<HashMap<String, String>>> {
+        let file = File::open(self.additional_contexts.as_ref()?).unwrap();
+        let entries: Vec<ContextLoaderEntry> =
+            serde_json::from_reader(BufReader::new(file)).unwrap();
+        // ipfs:// context documents are fetched from the configured gateway.
+        ipfs::set_gateway(self.resolver_options.ipfs_gateway.clone());
+        let documents = integrity::load_contexts(&entries).await.unwrap();
+        Some(Arc::new(documents))
+    }
+
+    fn get_templates(&self) -> Option<Arc<Templates>> {
+        let file = File::open(self.issuance_templates.as_ref()?).unwrap();
+        let templates: Templates = serde_json::from_reader(BufReader::new(file)).unwrap();
+        Some(Arc::new(templates))
+    }
+
+    fn get_key_attestation(&self) -> Option<Arc<KeyAttestationPolicy>> {
+        if self.key_attestation_root.is_empty() {
+            return None;
+        }
+        let mut policy =
+            KeyAttestationPolicy::new().with_minimum_security_level(self.key_attestation_min_level);
+        for root in &self.key_attestation_root {
+            let (format, path) = root
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Expected FORMAT=FILE: {}", root));
+            let certificates = std::fs::read_to_string(path).unwrap();
+            policy = policy
+                .with_trusted_certificates(format, &certificates)
+                .unwrap();
+        }
+        Some(Arc::new(policy))
+    }
+
+    /// Proofs of work, with challenges in a store of their own, so that proof of work challenges
+    /// are not accepted as challenges of presentations, nor the reverse.
+    fn get_proof_of_work(&self, storage: &Arc<dyn Storage>) -> Option<Arc<ProofOfWork>> {
+        let difficulty = self.pow_difficulty?;
+        let nonce_store = self.open_nonce_store(storage, "pow").unwrap_or_else(|| {
+            invalid_value("--pow-difficulty requires --nonce-store".to_string())
+        });
+        Some(Arc::new(ProofOfWork::new(nonce_store, difficulty)))
+    }
+
+    #[cfg(feature = "store")]
+    fn get_lifecycle(&self) -> Option<Arc<didkit_http::lifecycle::LifecycleConfig>> {
+        let status_list = self.hosted_status_list.clone()?;
+        let issuer = self.hosted_status_list_issuer.clone()?;
+        let mut lifecycle = didkit_http::lifecycle::LifecycleConfig::new(status_list, issuer)
+            .with_purpose(self.hosted_status_purpose);
+        if let Some(ref base_url) = self.base_url {
+            lifecycle = lifecycle.with_base_url(base_url.clone());
+        }
+        Some(Arc::new(lifecycle))
+    }
+
+    fn get_concurrency_limits(&self) -> Option<Arc<ConcurrencyLimits>> {
+        if self.max_concurrent_verifications.is_none() && self.max_concurrent_signings.is_none() {
+            return None;
+        }
+        let mut limits =
+            ConcurrencyLimits::new().with_retry_after(Duration::from_secs(self.retry_after));
+        if let Some(max) = self.max_concurrent_verifications {
+            limits = limits.with_max_verifications(max, self.queue_depth);
+        }
+        if let Some(max) = self.max_concurrent_signings {
+            limits = limits.with_max_signings(max, self.queue_depth);
+        }
+        Some(Arc::new(limits))
+    }
+
+    fn get_status_cache(&self, storage: &Arc<dyn Storage>) -> Option<Arc<StatusCache>> {
+        if self.status_list.is_empty() {
+            return None;
+        }
+        let mut status_cache = StatusCache::new(
+            self.status_list.clone(),
+            Duration::from_secs(self.status_refresh),
+            Duration::from_secs(self.status_max_age),
+            self.resolver_options.clone(),
+        );
+        if let Some(ref webhook) = self.status_webhook {
+            status_cache = status_cache.with_webhook(webhook.clone());
+        }
+        if self.storage != "memory" {
+            status_cache = status_cache.with_storage(storage.clone());
+        }
+        Some(Arc::new(status_cache))
+    }
+}
+
+/// Exit with a usage error about the value of an option.
+fn invalid_value(message: String) -> ! {
+    structopt::clap::Error::with_description(&message, structopt::clap::ErrorKind::InvalidValue)
+        .exit()
 }
 
 #[derive(StructOpt, Debug)]
@@ -57,13 +379,146 @@ impl KeyArg {
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
+/// Check the health of the resolver configuration's endpoints every health check interval, in a
+/// background task.
+fn spawn_health_checks(federation: Arc<ResolverFederation>) {
+    tokio::spawn(async move {
+        loop {
+            federation.check_health().await;
+            let interval = federation.health_check_interval();
+            tokio::time::sleep(interval.max(Duration::from_secs(1))).await;
+        }
+    });
+}
+
+fn main() -> Result<(), Error> {
     let opt = DIDKitHttpOpts::from_args();
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+    if let Some(threads) = opt.runtime_threads {
+        runtime.worker_threads(threads);
+    }
+    if let Some(threads) = opt.max_blocking_threads {
+        runtime.max_blocking_threads(threads);
+    }
+    runtime.build().unwrap().block_on(serve(opt))
+}
 
+async fn serve(opt: DIDKitHttpOpts) -> Result<(), Error> {
     let keys = opt.key.get_jwks();
-    let makesvc = DIDKitHTTPMakeSvc::new(keys, opt.resolver_options);
+    if let Some(ref federation) = opt.resolver_options.resolver_config {
+        spawn_health_checks(federation.clone());
+    }
+    let storage = storage::open(&opt.storage).await?;
+    if let Some(ref url) = opt.worker {
+        let broker = worker::connect(
+            url,
+            &opt.worker_requests,
+            &opt.worker_results,
+            &opt.worker_group,
+            opt.worker_concurrency,
+        )
+        .await?;
+        let signers = keys
+            .into_iter()
+            .map(|key| Arc::new(key) as Arc<dyn Signer>)
+            .collect();
+        let worker = Worker::new(
+            broker,
+            signers,
+            opt.resolver_options,
+            Duration::from_secs(opt.idempotency_ttl),
+            opt.worker_concurrency,
+        )
+        .with_storage(storage);
+        println!("Consuming issuance requests from {}", opt.worker_requests);
+        Arc::new(worker).run().await?;
+        return Ok(());
+    }
+    if let Some(ttl) = opt.key_cache_ttl {
+        let cache = KeyCache::new(KEY_CACHE_CAPACITY, Duration::from_secs(ttl));
+        key_cache::set_key_cache(Some(Arc::new(cache)));
+    }
+    let nonce_store = opt.get_nonce_store(&storage);
+    let proof_of_work = opt.get_proof_of_work(&storage);
+    let status_cache = opt.get_status_cache(&storage);
+    let did_auth = opt.get_did_auth();
+    let catalogs = opt.get_catalogs();
+    let context_documents = opt.get_context_documents().await;
+    let templates = opt.get_templates();
+    let key_attestation = opt.get_key_attestation();
+    let concurrency_limits = opt.get_concurrency_limits();
     let host = opt.host.unwrap_or([127, 0, 0, 1].into());
+    #[cfg(feature = "store")]
+    let credential_store = match opt.credential_store {
+        Some(ref url) => Some(Arc::new(
+            didkit::store::CredentialStore::connect(url).await.unwrap(),
+        )),
+        None => None,
+    };
+    #[cfg(feature = "store")]
+    let lifecycle = opt.get_lifecycle();
+    let mut svc = DIDKitHTTPSvc::new(keys.clone(), opt.resolver_options);
+    if let Some(nonce_store) = nonce_store {
+        svc = svc.with_nonce_store(nonce_store);
+    }
+    if let Some(did_auth) = did_auth {
+        svc = svc.with_did_auth(did_auth);
+    }
+    if let Some(catalogs) = catalogs {
+        svc = svc.with_catalogs(catalogs);
+    }
+    if let Some(context_documents) = context_documents {
+        svc = svc.with_context_documents(context_documents);
+    }
+    if let Some(templates) = templates {
+        svc = svc.with_templates(templates);
+    }
+    if let Some(key_attestation) = key_attestation {
+        svc = svc.with_key_attestation(key_attestation);
+    }
+    if opt.swagger_ui {
+        svc = svc.with_swagger_ui();
+    }
+    if let Some(timeout) = opt.request_timeout {
+        svc = svc.with_request_timeout(Duration::from_secs(timeout));
+    }
+    if let Some(ref base_url) = opt.base_url {
+        svc = svc.with_base_url(Arc::new(base_url.clone()));
+    }
+    if let Some(concurrency_limits) = concurrency_limits {
+        svc = svc.with_concurrency_limits(concurrency_limits);
+    }
+    if opt.canonical_json {
+        svc = svc.with_canonical_json();
+    }
+    if let Some(proof_of_work) = proof_of_work {
+        svc = svc.with_proof_of_work(proof_of_work);
+    }
+    if let Some(status_cache) = status_cache {
+        status_cache.clone().spawn_refresh();
+        svc = svc.with_status_cache(status_cache);
+    }
+    #[cfg(feature = "store")]
+    if let Some(credential_store) = credential_store {
+        svc = svc.with_credential_store(credential_store);
+    }
+    #[cfg(feature = "store")]
+    if let Some(lifecycle) = lifecycle {
+        svc = svc.with_lifecycle(lifecycle);
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = opt.grpc_port {
+        let grpc_svc = DIDKitGrpcSvc::new(keys, svc.clone());
+        let addr = (host, grpc_port).into();
+        println!("gRPC listening on {}", addr);
+        tokio::spawn(async move {
+            if let Err(err) = grpc_svc.serve(addr).await {
+                eprintln!("gRPC server: {}", err);
+            }
+        });
+    }
+    let makesvc = DIDKitHTTPMakeSvc::new(svc);
     let addr = (host, opt.port.unwrap_or(0)).into();
 
     let server = Server::bind(&addr).serve(makesvc);
@@ -0,0 +1,261 @@
+//! Challenge (nonce) stores, for replay protection of presentations.
+//!
+//! The server issues challenges from a [`NonceStore`]; a presentation verified with a challenge
+//! consumes it, so that each challenge is accepted at most once within its validity window.
+//! Challenges are consumed only by presentations that verify, so that an invalid presentation
+//! cannot use up the challenge of a legitimate one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use didkit::hex;
+
+use crate::storage::{Storage, StorageError};
+
+#[derive(Debug)]
+pub enum NonceError {
+    #[cfg(feature = "redis")]
+    Redis(redis::RedisError),
+    Storage(StorageError),
+    Poisoned,
+    /// The store has as many unexpired challenges as it can hold
+    Full,
+}
+
+impl std::error::Error for NonceError {}
+
+impl fmt::Display for NonceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "redis")]
+            NonceError::Redis(e) => e.fmt(f),
+            NonceError::Storage(e) => e.fmt(f),
+            NonceError::Poisoned => write!(f, "Nonce store lock poisoned"),
+            NonceError::Full => write!(f, "Too many outstanding challenges"),
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for NonceError {
+    fn from(err: redis::RedisError) -> NonceError {
+        NonceError::Redis(err)
+    }
+}
+
+impl From<StorageError> for NonceError {
+    fn from(err: StorageError) -> NonceError {
+        NonceError::Storage(err)
+    }
+}
+
+/// Storage for issued challenges.
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Issue a new challenge, valid for [`NonceStore::ttl`].
+    async fn create(&self) -> Result<String, NonceError>;
+    /// Consume a challenge. Returns `false` if the challenge was not issued, has expired, or was
+    /// already consumed.
+    async fn consume(&self, nonce: &str) -> Result<bool, NonceError>;
+    /// Validity period of issued challenges.
+    fn ttl(&self) -> Duration;
+}
+
+/// In-memory challenge store, for a single server process. The store holds a bounded number of
+/// unexpired challenges, so that issuing challenges cannot exhaust the memory of the server.
+#[derive(Debug)]
+pub struct MemoryNonceStore {
+    ttl: Duration,
+    capacity: usize,
+    nonces: Mutex<HashMap<String, Instant>>,
+}
+
+impl MemoryNonceStore {
+    /// Default maximum number of unexpired challenges.
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            capacity: Self::DEFAULT_CAPACITY,
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the maximum number of unexpired challenges. Further challenges are refused with
+    /// [`NonceError::Full`] until challenges are consumed or expire.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+#[async_trait]
+impl NonceStore for MemoryNonceStore {
+    async fn create(&self) -> Result<String, NonceError> {
+        let nonce = hex::random_id();
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().map_err(|_| NonceError::Poisoned)?;
+        nonces.retain(|_, expires| *expires > now);
+        if nonces.len() >= self.capacity {
+            return Err(NonceError::Full);
+        }
+        nonces.insert(nonce.clone(), now + self.ttl);
+        Ok(nonce)
+    }
+
+    async fn consume(&self, nonce: &str) -> Result<bool, NonceError> {
+        let mut nonces = self.nonces.lock().map_err(|_| NonceError::Poisoned)?;
+        Ok(match nonces.remove(nonce) {
+            Some(expires) => expires > Instant::now(),
+            None => false,
+        })
+    }
+
+    fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+/// Challenge store in Redis, shared between server processes. Challenges are stored as keys
+/// that expire with the challenge; consuming a challenge deletes its key.
+#[cfg(feature = "redis")]
+pub struct RedisNonceStore {
+    client: redis::Client,
+    prefix: String,
+    ttl: Duration,
+}
+
+#[cfg(feature = "redis")]
+impl RedisNonceStore {
+    pub fn new(url: &str, ttl: Duration) -> Result<Self, NonceError> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            prefix: "didkit:nonce:".to_string(),
+            ttl,
+        })
+    }
+
+    /// Set the prefix of the keys of challenges, by default `didkit:nonce:`, to keep challenges
+    /// of different purposes apart.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl NonceStore for RedisNonceStore {
+    async fn create(&self) -> Result<String, NonceError> {
+        let nonce = hex::random_id();
+        let mut con = self.client.get_async_connection().await?;
+        redis::cmd("SET")
+            .arg(format!("{}{}", self.prefix, nonce))
+            .arg(1)
+            .arg("EX")
+            .arg(self.ttl.as_secs().max(1))
+            .arg("NX")
+            .query_async::<_, ()>(&mut con)
+            .await?;
+        Ok(nonce)
+    }
+
+    async fn consume(&self, nonce: &str) -> Result<bool, NonceError> {
+        let mut con = self.client.get_async_connection().await?;
+        let deleted: i64 = redis::cmd("DEL")
+            .arg(format!("{}{}", self.prefix, nonce))
+            .query_async(&mut con)
+            .await?;
+        Ok(deleted == 1)
+    }
+
+    fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+/// Challenge store in a [`Storage`] backend, shared between server processes if the backend is.
+pub struct StorageNonceStore {
+    storage: Arc<dyn Storage>,
+    namespace: String,
+    ttl: Duration,
+}
+
+impl StorageNonceStore {
+    /// Default namespace of challenges in the storage.
+    pub const DEFAULT_NAMESPACE: &'static str = "nonce";
+
+    pub fn new(storage: Arc<dyn Storage>, ttl: Duration) -> Self {
+        Self {
+            storage,
+            namespace: Self::DEFAULT_NAMESPACE.to_string(),
+            ttl,
+        }
+    }
+
+    /// Set the namespace of challenges in the storage, to keep challenges of different purposes
+    /// apart.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl NonceStore for StorageNonceStore {
+    async fn create(&self) -> Result<String, NonceError> {
+        let nonce = hex::random_id();
+        self.storage
+            .put(&self.namespace, &nonce, b"", Some(self.ttl))
+            .await?;
+        Ok(nonce)
+    }
+
+    async fn consume(&self, nonce: &str) -> Result<bool, NonceError> {
+        Ok(self.storage.take(&self.namespace, nonce).await?.is_some())
+    }
+
+    fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn consume_once() {
+        let store = MemoryNonceStore::new(Duration::from_secs(60));
+        let nonce = store.create().await.unwrap();
+        assert!(store.consume(&nonce).await.unwrap());
+        assert!(!store.consume(&nonce).await.unwrap());
+        assert!(!store.consume("unknown").await.unwrap());
+
+        let store = MemoryNonceStore::new(Duration::from_secs(0));
+        let nonce = store.create().await.unwrap();
+        assert!(!store.consume(&nonce).await.unwrap());
+
+        let store = MemoryNonceStore::new(Duration::from_secs(60)).with_capacity(1);
+        let nonce = store.create().await.unwrap();
+        assert!(matches!(store.create().await, Err(NonceError::Full)));
+        assert!(store.consume(&nonce).await.unwrap());
+        assert!(store.create().await.is_ok());
+
+        let storage = Arc::new(crate::storage::MemoryStorage::new());
+        let store = StorageNonceStore::new(storage.clone(), Duration::from_secs(60));
+        let nonce = store.create().await.unwrap();
+        assert!(store.consume(&nonce).await.unwrap());
+        assert!(!store.consume(&nonce).await.unwrap());
+
+        // Challenges of another namespace are not accepted.
+        let other = StorageNonceStore::new(storage, Duration::from_secs(60)).with_namespace("pow");
+        let nonce = other.create().await.unwrap();
+        assert!(!store.consume(&nonce).await.unwrap());
+        assert!(other.consume(&nonce).await.unwrap());
+    }
+}
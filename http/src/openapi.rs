@@ -0,0 +1,383 @@
+//! OpenAPI 3.1 description of the server's routes, served at `/openapi.json`.
+//!
+//! Request and response schemas are generated from the types that the handlers deserialize and
+//! serialize, so that the document follows them. The description lists every route; routes that
+//! depend on the server's configuration, such as `/challenges`, respond with 404 Not Found when it
+//! is not enabled.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use didkit::error::ErrorInfo;
+use didkit::resolver_config::EndpointHealth;
+#[cfg(feature = "store")]
+use didkit::store::{AuditEntry, CredentialRecord, StatusEntry};
+
+#[cfg(feature = "store")]
+use crate::lifecycle::TransitionRequest;
+use crate::validation::{Problem, PROBLEM_JSON};
+use crate::{
+    ChallengeResponse, DIDAuthRequest, DIDAuthResponse, IssueCredentialRequest,
+    PowChallengeResponse, ProvePresentationRequest, VerificationResponse, VerifyCredentialRequest,
+    VerifyPresentationRequest,
+};
+
+/// Path of the OpenAPI document.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// Path of the Swagger UI of the OpenAPI document, if enabled.
+pub const SWAGGER_UI_PATH: &str = "/docs";
+
+struct Operation {
+    summary: &'static str,
+    request: Option<Schema>,
+    responses: Vec<(u16, &'static str, Option<Schema>)>,
+    /// Schema of the problem details of a 400 response to an invalid request body
+    problem: Option<Schema>,
+    /// Optional query parameters, with their descriptions
+    query: Vec<(&'static str, &'static str)>,
+}
+
+impl Operation {
+    fn new(summary: &'static str) -> Self {
+        Self {
+            summary,
+            request: None,
+            responses: Vec::new(),
+            problem: None,
+            query: Vec::new(),
+        }
+    }
+
+    fn request<T: JsonSchema>(mut self, gen: &mut SchemaGenerator) -> Self {
+        self.request = Some(gen.subschema_for::<T>());
+        self
+    }
+
+    fn response<T: JsonSchema>(
+        mut self,
+        gen: &mut SchemaGenerator,
+        status: u16,
+        description: &'static str,
+    ) -> Self {
+        self.responses
+            .push((status, description, Some(gen.subschema_for::<T>())));
+        self
+    }
+
+    fn no_content(mut self, status: u16, description: &'static str) -> Self {
+        self.responses.push((status, description, None));
+        self
+    }
+
+    fn query(mut self, name: &'static str, description: &'static str) -> Self {
+        self.query.push((name, description));
+        self
+    }
+
+    /// Describe the problem details of a 400 response to an invalid request body, with the other
+    /// content of the response, if any.
+    fn invalid_request(mut self, gen: &mut SchemaGenerator) -> Self {
+        self.problem = Some(gen.subschema_for::<Problem>());
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let content = |schema: &Schema| json!({ "application/json": { "schema": schema } });
+        let mut responses = Map::new();
+        for (status, description, schema) in &self.responses {
+            let mut response = json!({ "description": description });
+            if let Some(schema) = schema {
+                response["content"] = content(schema);
+            }
+            responses.insert(status.to_string(), response);
+        }
+        if let Some(ref problem) = self.problem {
+            let response = responses
+                .entry("400")
+                .or_insert_with(|| json!({ "description": "Invalid request" }));
+            response["content"][PROBLEM_JSON] = json!({ "schema": problem });
+        }
+        let mut operation = json!({ "summary": self.summary, "responses": responses });
+        if !self.query.is_empty() {
+            let parameters = self
+                .query
+                .iter()
+                .map(|(name, description)| {
+                    json!({
+                        "name": name,
+                        "in": "query",
+                        "description": description,
+                        "schema": { "type": "string" },
+                    })
+                })
+                .collect();
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if let Some(ref schema) = self.request {
+            operation["requestBody"] = json!({ "required": true, "content": content(schema) });
+        }
+        operation
+    }
+
+    /// Error responses of issuance
+    fn issuance_errors(self, gen: &mut SchemaGenerator) -> Self {
+        self.no_content(400, "Invalid request or options")
+            .invalid_request(gen)
+            .response::<String>(gen, 500, "Unable to sign")
+    }
+
+    /// Responses of verification
+    fn verification(self, gen: &mut SchemaGenerator) -> Self {
+        self.response::<VerificationResponse>(gen, 200, "Verified")
+            .response::<VerificationResponse>(gen, 400, "Not verified, or invalid request")
+            .invalid_request(gen)
+            .response::<String>(gen, 403, "Missing proof of work, if required")
+    }
+}
+
+/// Operations of the server, by path and method.
+fn operations(gen: &mut SchemaGenerator) -> Vec<(&'static str, &'static str, Operation)> {
+    #[allow(unused_mut)]
+    let mut operations = vec![
+        (
+            "/credentials/issue",
+            "post",
+            Operation::new("Issue a credential")
+                .query("template", "Name of an issuance template to apply")
+                .request::<IssueCredentialRequest>(gen)
+                .response::<didkit::json_schema::CredentialOrJWT>(
+                    gen,
+                    201,
+                    "Verifiable credential, or JWT string",
+                )
+                .issuance_errors(gen)
+                .no_content(404, "Unknown issuance template"),
+        ),
+        (
+            "/credentials/verify",
+            "post",
+            Operation::new("Verify a credential")
+                .request::<VerifyCredentialRequest>(gen)
+                .verification(gen),
+        ),
+        (
+            "/credentials/prove",
+            "post",
+            Operation::new("Prove a presentation")
+                .request::<ProvePresentationRequest>(gen)
+                .response::<didkit::json_schema::PresentationOrJWT>(
+                    gen,
+                    201,
+                    "Verifiable presentation, or JWT string",
+                )
+                .issuance_errors(gen),
+        ),
+        (
+            "/presentations/verify",
+            "post",
+            Operation::new("Verify a presentation")
+                .request::<VerifyPresentationRequest>(gen)
+                .verification(gen),
+        ),
+        (
+            "/challenges",
+            "post",
+            Operation::new("Issue a challenge, with a nonce store").response::<ChallengeResponse>(
+                gen,
+                201,
+                "Challenge",
+            ),
+        ),
+        (
+            "/pow/challenges",
+            "post",
+            Operation::new("Issue a proof of work challenge, with proofs of work required")
+                .response::<PowChallengeResponse>(gen, 201, "Proof of work challenge"),
+        ),
+        (
+            "/auth/challenge",
+            "post",
+            Operation::new("Issue a DIDAuth request, with DIDAuth login").response::<Value>(
+                gen,
+                201,
+                "DIDAuth request",
+            ),
+        ),
+        (
+            "/auth/verify",
+            "post",
+            Operation::new("Verify a DIDAuth presentation, and issue a session assertion")
+                .request::<DIDAuthRequest>(gen)
+                .response::<DIDAuthResponse>(gen, 200, "Session assertion")
+                .no_content(400, "Invalid request")
+                .invalid_request(gen)
+                .response::<String>(gen, 401, "Not authenticated"),
+        ),
+        (
+            "/auth/session",
+            "get",
+            Operation::new("Check a session assertion, given as bearer token")
+                .response::<didkit::did_auth::SessionClaims>(gen, 200, "Session claims")
+                .response::<String>(gen, 401, "Missing or invalid session assertion"),
+        ),
+        (
+            "/resolver/health",
+            "get",
+            Operation::new("Get the health of DID resolver endpoints, with a resolver config")
+                .response::<Vec<EndpointHealth>>(gen, 200, "Endpoint health")
+                .no_content(404, "No resolver configuration"),
+        ),
+        (
+            "/identifiers/{didUrl}",
+            "get",
+            Operation::new("Resolve a DID, or dereference a DID URL")
+                .response::<didkit::json_schema::ResolutionResult>(gen, 200, "Resolution result")
+                .no_content(303, "Redirect to a service endpoint")
+                .response::<String>(gen, 400, "Invalid DID or DID URL")
+                .response::<String>(gen, 404, "Not found")
+                .response::<String>(gen, 406, "Representation not supported")
+                .no_content(410, "Deactivated")
+                .response::<String>(gen, 501, "DID method not supported"),
+        ),
+    ];
+    #[cfg(feature = "store")]
+    operations.extend(vec![
+        (
+            "/credentials/{id}",
+            "get",
+            Operation::new("Get an issued credential, with credential lifecycle tracking")
+                .response::<CredentialRecord>(gen, 200, "Credential record, with its statusEntry")
+                .response::<ErrorInfo>(gen, 404, "Not found"),
+        ),
+        (
+            "/credentials/{id}/audit",
+            "get",
+            Operation::new("Get the lifecycle transitions of an issued credential")
+                .response::<Vec<AuditEntry>>(gen, 200, "Audit log")
+                .response::<ErrorInfo>(gen, 404, "Not found"),
+        ),
+        (
+            "/credentials/{id}/status",
+            "post",
+            Operation::new("Change the lifecycle state of an issued credential")
+                .request::<TransitionRequest>(gen)
+                .response::<StatusEntry>(gen, 200, "Status list entry")
+                .response::<ErrorInfo>(gen, 404, "Not found")
+                .response::<ErrorInfo>(gen, 409, "Invalid transition")
+                .invalid_request(gen),
+        ),
+    ]);
+    operations
+}
+
+/// Path parameters of a templated path.
+fn parameters(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            })
+        })
+        .collect()
+}
+
+/// Generate the OpenAPI document.
+pub fn document() -> Value {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        settings.definitions_path = "#/components/schemas/".to_string();
+        settings.meta_schema = None;
+    });
+    let mut gen = settings.into_generator();
+    let mut paths = Map::new();
+    for (path, method, operation) in operations(&mut gen) {
+        let item = paths.entry(path.to_string()).or_insert_with(|| json!({}));
+        let parameters = parameters(path);
+        if !parameters.is_empty() {
+            item["parameters"] = Value::Array(parameters);
+        }
+        item[method] = operation.to_json();
+    }
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "DIDKit HTTP",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Verifiable Credentials and Decentralized Identifiers, following vc-http-api",
+        },
+        "paths": paths,
+        "components": { "schemas": gen.take_definitions() },
+    })
+}
+
+/// HTML page of the Swagger UI of the OpenAPI document, at a path such as [`OPENAPI_PATH`].
+pub fn swagger_ui(openapi_path: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>DIDKit HTTP</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>SwaggerUIBundle({{ url: "{}", dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"#,
+        openapi_path
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openapi_document() {
+        let document = document();
+        assert_eq!(document["openapi"], "3.1.0");
+        let issue = &document["paths"]["/credentials/issue"]["post"];
+        assert_eq!(
+            issue["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/IssueCredentialRequest"
+        );
+        let schemas = &document["components"]["schemas"];
+        let request = &schemas["IssueCredentialRequest"];
+        assert_eq!(request["required"], json!(["credential"]));
+        let options = &schemas["JWTOrLDPOptions"]["properties"];
+        for name in &[
+            "verificationMethod",
+            "proofFormat",
+            "allowedDidMethods",
+            "expires",
+            "jwtClaims",
+        ] {
+            assert!(options.get(*name).is_some(), "missing option {}", name);
+        }
+        assert_eq!(
+            issue["responses"]["400"]["content"]["application/problem+json"]["schema"]["$ref"],
+            "#/components/schemas/Problem"
+        );
+        assert_eq!(issue["parameters"][0]["name"], "template");
+        assert_eq!(issue["parameters"][0]["in"], "query");
+        assert_eq!(
+            document["paths"]["/identifiers/{didUrl}"]["parameters"][0]["name"],
+            "didUrl"
+        );
+        // Every referenced schema is defined.
+        let text = document.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(schemas.get(name).is_some(), "missing schema {}", name);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Proof-of-work rate limiting of the verification endpoints.
+//!
+//! A public verifier may serve anyone, without API keys. To resist floods of requests, the server
+//! may require each verification request to carry a proof of work: the client gets a challenge
+//! from `/pow/challenges`, finds a solution, any string, such that the SHA-256 hash of
+//! `<challenge>:<solution>` starts with [`ProofOfWork::difficulty`] zero bits, and sends both in
+//! the [`POW_HEADER`] header, as `<challenge>:<solution>`. Each challenge is accepted once, from
+//! a [`NonceStore`] apart from that of presentation challenges. The work is negligible for a
+//! client making a request now and then, and costly for one making many; it is anonymous, as it
+//! does not identify the client.
+
+use std::fmt;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::nonce::{NonceError, NonceStore};
+
+/// Request header of a proof of work.
+pub const POW_HEADER: &str = "didkit-pow";
+
+/// A request's proof of work is not acceptable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PowError {
+    Missing,
+    Malformed,
+    InsufficientWork,
+    UnknownChallenge,
+}
+
+impl std::error::Error for PowError {}
+
+impl fmt::Display for PowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowError::Missing => write!(f, "Missing proof of work"),
+            PowError::Malformed => write!(f, "Expected proof of work as <challenge>:<solution>"),
+            PowError::InsufficientWork => write!(f, "Proof of work does not meet the difficulty"),
+            PowError::UnknownChallenge => write!(
+                f,
+                "Unknown, expired or already used proof of work challenge"
+            ),
+        }
+    }
+}
+
+/// Number of leading zero bits of a hash.
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte != 0 {
+            return bits + byte.leading_zeros();
+        }
+        bits += 8;
+    }
+    bits
+}
+
+/// Whether a solution of a challenge meets a difficulty.
+pub fn is_solution(challenge: &str, solution: &str, difficulty: u8) -> bool {
+    let hash = Sha256::digest(format!("{}:{}", challenge, solution).as_bytes());
+    leading_zero_bits(&hash) >= difficulty as u32
+}
+
+/// Find a solution of a challenge, as a client does.
+pub fn solve(challenge: &str, difficulty: u8) -> String {
+    (0u64..)
+        .map(|counter| counter.to_string())
+        .find(|solution| is_solution(challenge, solution, difficulty))
+        .unwrap()
+}
+
+/// Proofs of work required of verification requests.
+pub struct ProofOfWork {
+    nonce_store: Arc<dyn NonceStore>,
+    difficulty: u8,
+}
+
+impl ProofOfWork {
+    /// Issue challenges from a nonce store, to be solved with the given number of leading zero
+    /// bits. Each additional bit doubles the work of a solution.
+    pub fn new(nonce_store: Arc<dyn NonceStore>, difficulty: u8) -> Self {
+        Self {
+            nonce_store,
+            difficulty,
+        }
+    }
+
+    pub fn difficulty(&self) -> u8 {
+        self.difficulty
+    }
+
+    pub fn nonce_store(&self) -> &Arc<dyn NonceStore> {
+        &self.nonce_store
+    }
+
+    /// Check the proof of work of a request, from its [`POW_HEADER`] header, and consume its
+    /// challenge. The hash is checked first, so that requests without work do not reach the
+    /// nonce store.
+    pub async fn check(&self, header: Option<&str>) -> Result<Result<(), PowError>, NonceError> {
+        let header = match header {
+            Some(header) => header,
+            None => return Ok(Err(PowError::Missing)),
+        };
+        let (challenge, solution) = match header.find(':') {
+            Some(i) => (&header[..i], &header[i + 1..]),
+            None => return Ok(Err(PowError::Malformed)),
+        };
+        if !is_solution(challenge, solution, self.difficulty) {
+            return Ok(Err(PowError::InsufficientWork));
+        }
+        if !self.nonce_store.consume(challenge).await? {
+            return Ok(Err(PowError::UnknownChallenge));
+        }
+        Ok(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nonce::MemoryNonceStore;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn check_proof_of_work() {
+        assert_eq!(leading_zero_bits(&[0, 0x10, 0xff]), 11);
+        let nonce_store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+        let pow = ProofOfWork::new(nonce_store.clone(), 8);
+        let challenge = nonce_store.create().await.unwrap();
+        let solution = solve(&challenge, 8);
+        let header = format!("{}:{}", challenge, solution);
+
+        assert_eq!(pow.check(None).await.unwrap(), Err(PowError::Missing));
+        assert_eq!(
+            pow.check(Some(&challenge)).await.unwrap(),
+            Err(PowError::Malformed)
+        );
+        let unsolved = (0u64..)
+            .map(|counter| counter.to_string())
+            .find(|solution| !is_solution(&challenge, solution, 8))
+            .unwrap();
+        let result = pow.check(Some(&format!("{}:{}", challenge, unsolved)));
+        assert_eq!(result.await.unwrap(), Err(PowError::InsufficientWork));
+        assert_eq!(pow.check(Some(&header)).await.unwrap(), Ok(()));
+        // A challenge is accepted once.
+        assert_eq!(
+            pow.check(Some(&header)).await.unwrap(),
+            Err(PowError::UnknownChallenge)
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//! Status list cache, for checking the status of verified credentials.
+//!
+//! A [`StatusCache`] holds the status lists (`StatusList2021Credential` or
+//! `RevocationList2020Credential`) at configured URLs, fetched and verified in the background
+//! every refresh interval. Credentials whose `credentialStatus` refers to one of these lists are
+//! checked against the cached copy, so verification does not wait on the status list's server.
+//! If a refresh fails, the last list fetched is used until it is older than the maximum age, so
+//! that brief outages do not fail verification. Status entries of other lists are not checked.
+//! A status entry is only checked against a list issued by the credential's issuer, for the
+//! entry's status purpose; otherwise the credential fails with `statusListMismatch`.
+//!
+//! The indexes of credentials checked against a cached list, and found not set, are tracked. When
+//! a refresh changes the status of a tracked index, e.g. an accepted credential is revoked, the
+//! change is logged and, if a webhook is configured, posted to it as a [`StatusNotification`].
+//! With a shared [`Storage`] backend, servers share their tracked indexes, and each change is
+//! notified by one of them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use didkit::verification::{ErrorCode, VerificationReport};
+use didkit::{verify_credential, CredentialOrJWT, JWTOrLDPOptions, PresentationOrJWT};
+use didkit_cli::opts::ResolverOptions;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ssi::one_or_many::OneOrMany;
+
+use crate::storage::{Storage, StorageError};
+
+/// Status entry types, with their list URL and index properties.
+const STATUS_ENTRY_TYPES: &[(&str, &str, &str)] = &[
+    (
+        "StatusList2021Entry",
+        "statusListCredential",
+        "statusListIndex",
+    ),
+    (
+        "RevocationList2020Status",
+        "revocationListCredential",
+        "revocationListIndex",
+    ),
+];
+
+/// Namespaces of tracked indexes, and of claimed notifications, in the storage.
+const TRACKED_NAMESPACE: &str = "status-tracked";
+const NOTIFIED_NAMESPACE: &str = "status-notified";
+
+#[derive(Debug)]
+pub enum StatusError {
+    Fetch(reqwest::Error),
+    InvalidList(String),
+    Verification(String),
+}
+
+impl std::error::Error for StatusError {}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusError::Fetch(e) => e.fmt(f),
+            StatusError::InvalidList(msg) => write!(f, "Invalid status list: {}", msg),
+            StatusError::Verification(msg) => {
+                write!(f, "Unable to verify status list credential: {}", msg)
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for StatusError {
+    fn from(err: reqwest::Error) -> StatusError {
+        StatusError::Fetch(err)
+    }
+}
+
+/// A decoded status list.
+#[derive(Debug, Clone)]
+struct StatusList {
+    bits: Vec<u8>,
+    purpose: String,
+    issuer: String,
+    fetched: Instant,
+}
+
+impl StatusList {
+    /// Decode an `encodedList`: a GZIP-compressed bitstring, in base64url, of a list with the
+    /// given purpose and issuer.
+    fn decode(encoded: &str, purpose: &str, issuer: &str) -> Result<Self, StatusError> {
+        let compressed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .or_else(|_| base64::decode(encoded))
+            .map_err(|e| StatusError::InvalidList(e.to_string()))?;
+        let mut bits = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut bits)
+            .map_err(|e| StatusError::InvalidList(e.to_string()))?;
+        Ok(Self {
+            bits,
+            purpose: purpose.to_string(),
+            issuer: issuer.to_string(),
+            fetched: Instant::now(),
+        })
+    }
+
+    /// Get the bit at an index. The first index is the most significant bit of the first byte.
+    fn get(&self, index: usize) -> Option<bool> {
+        self.bits
+            .get(index / 8)
+            .map(|byte| byte & (0x80 >> (index % 8)) != 0)
+    }
+}
+
+/// Decode the claims of a JWT without verifying it.
+fn jwt_claims(jwt: &str) -> Option<Value> {
+    let payload = jwt.split('.').nth(1)?;
+    let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Get the issuer ID of a credential.
+fn credential_issuer(credential: &CredentialOrJWT) -> Option<String> {
+    let issuer = match credential {
+        CredentialOrJWT::Credential(vc) => serde_json::to_value(&vc.issuer).ok()?,
+        CredentialOrJWT::JWT(jwt) => {
+            let claims = jwt_claims(jwt)?;
+            match claims.get("iss") {
+                Some(iss) => iss.clone(),
+                None => claims.pointer("/vc/issuer")?.clone(),
+            }
+        }
+    };
+    match issuer {
+        Value::String(id) => Some(id),
+        issuer => issuer.get("id")?.as_str().map(ToString::to_string),
+    }
+}
+
+/// Get the `credentialStatus` entries of a credential.
+fn status_entries(credential: &CredentialOrJWT) -> Vec<Value> {
+    let status = match credential {
+        CredentialOrJWT::Credential(vc) => serde_json::to_value(&vc.credential_status).ok(),
+        CredentialOrJWT::JWT(jwt) => {
+            jwt_claims(jwt).and_then(|claims| claims.pointer("/vc/credentialStatus").cloned())
+        }
+    };
+    match status {
+        Some(Value::Array(entries)) => entries,
+        Some(Value::Object(entry)) => vec![Value::Object(entry)],
+        _ => Vec::new(),
+    }
+}
+
+/// Get the status list URL and index of a status entry, if it is of a known type.
+fn list_index(entry: &Value) -> Option<(&str, usize)> {
+    let type_ = entry.get("type")?.as_str()?;
+    let (_, url_property, index_property) = STATUS_ENTRY_TYPES
+        .iter()
+        .find(|(entry_type, _, _)| *entry_type == type_)?;
+    let url = entry.get(*url_property)?.as_str()?;
+    let index = match entry.get(*index_property)? {
+        Value::String(index) => index.parse().ok()?,
+        Value::Number(index) => index.as_u64()? as usize,
+        _ => return None,
+    };
+    Some((url, index))
+}
+
+/// Get the credentials embedded in a presentation.
+fn presentation_credentials(presentation: &PresentationOrJWT) -> Vec<CredentialOrJWT> {
+    let credentials = match presentation {
+        PresentationOrJWT::VP(vp) => vp.verifiable_credential.clone(),
+        PresentationOrJWT::JWT(jwt) => jwt_claims(jwt)
+            .and_then(|claims| claims.pointer("/vp/verifiableCredential").cloned())
+            .and_then(|credentials| serde_json::from_value(credentials).ok()),
+    };
+    match credentials {
+        Some(OneOrMany::One(credential)) => vec![credential],
+        Some(OneOrMany::Many(credentials)) => credentials,
+        None => Vec::new(),
+    }
+}
+
+/// Change of the status of a tracked index of a status list.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    pub index: usize,
+    /// Whether the status is now set, e.g. the credential is revoked.
+    pub status: bool,
+}
+
+/// Notification of status changes posted to the status webhook.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusNotification {
+    pub status_list_credential: String,
+    pub status_purpose: String,
+    pub changes: Vec<StatusChange>,
+}
+
+/// Cache of status lists, refreshed in the background.
+pub struct StatusCache {
+    urls: Vec<String>,
+    refresh_interval: Duration,
+    max_age: Duration,
+    resolver_options: ResolverOptions,
+    client: reqwest::Client,
+    lists: RwLock<HashMap<String, StatusList>>,
+    tracked: RwLock<HashMap<String, BTreeSet<usize>>>,
+    webhook: Option<String>,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl fmt::Debug for StatusCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatusCache")
+            .field("urls", &self.urls)
+            .field("refresh_interval", &self.refresh_interval)
+            .field("max_age", &self.max_age)
+            .field("webhook", &self.webhook)
+            .field("shared", &self.storage.is_some())
+            .finish()
+    }
+}
+
+impl StatusCache {
+    /// Create a cache of the status lists at the given URLs, to refresh every
+    /// `refresh_interval`, and to use for up to `max_age` after they were last fetched.
+    pub fn new(
+        urls: Vec<String>,
+        refresh_interval: Duration,
+        max_age: Duration,
+        resolver_options: ResolverOptions,
+    ) -> Self {
+        Self {
+            urls,
+            refresh_interval,
+            max_age,
+            resolver_options,
+            client: reqwest::Client::new(),
+            lists: RwLock::new(HashMap::new()),
+            tracked: RwLock::new(HashMap::new()),
+            webhook: None,
+            storage: None,
+        }
+    }
+
+    /// Post notifications of status changes of tracked indexes to this URL.
+    pub fn with_webhook(mut self, webhook: String) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Share tracked indexes with the other servers using this storage backend, and notify each
+    /// change once between them.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Track an index of a status list, to notify when its status changes.
+    pub fn track(&self, url: &str, index: usize) {
+        if let Ok(mut tracked) = self.tracked.write() {
+            tracked.entry(url.to_string()).or_default().insert(index);
+        }
+    }
+
+    /// Fetch a status list credential, verify it, and decode its list.
+    async fn fetch(&self, url: &str) -> Result<StatusList, StatusError> {
+        let text = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let credential = if text.trim_start().starts_with('{') {
+            serde_json::from_str(&text).map_err(|e| StatusError::InvalidList(e.to_string()))?
+        } else {
+            CredentialOrJWT::JWT(text.trim().to_string())
+        };
+        let resolver = self.resolver_options.to_resolver();
+        let report = verify_credential(&credential, &JWTOrLDPOptions::default(), &resolver)
+            .await
+            .map_err(|e| StatusError::Verification(e.to_string()))?;
+        if let Some(error) = report.errors.first() {
+            return Err(StatusError::Verification(error.message.clone()));
+        }
+        let issuer = credential_issuer(&credential)
+            .ok_or_else(|| StatusError::InvalidList("Missing issuer".to_string()))?;
+        let subject = match credential {
+            CredentialOrJWT::Credential(ref vc) => {
+                serde_json::to_value(&vc.credential_subject).ok()
+            }
+            CredentialOrJWT::JWT(ref jwt) => {
+                jwt_claims(jwt).and_then(|claims| claims.pointer("/vc/credentialSubject").cloned())
+            }
+        };
+        let subject = match subject {
+            Some(Value::Array(mut subjects)) if !subjects.is_empty() => subjects.swap_remove(0),
+            Some(subject) => subject,
+            None => Value::Null,
+        };
+        let encoded = subject
+            .get("encodedList")
+            .and_then(Value::as_str)
+            .ok_or_else(|| StatusError::InvalidList("Missing encodedList".to_string()))?;
+        let purpose = subject
+            .get("statusPurpose")
+            .and_then(Value::as_str)
+            .unwrap_or("revocation");
+        StatusList::decode(encoded, purpose, &issuer)
+    }
+
+    /// Replace the cached copy of a list, and return the status changes of its tracked indexes,
+    /// if there was a previous copy.
+    fn insert(&self, url: &str, list: StatusList) -> Option<StatusNotification> {
+        let previous = match self.lists.write() {
+            Ok(mut lists) => lists.insert(url.to_string(), list.clone())?,
+            Err(_) => return None,
+        };
+        let tracked = self.tracked.read().ok()?;
+        let changes: Vec<StatusChange> = tracked
+            .get(url)?
+            .iter()
+            .filter_map(|&index| match (previous.get(index), list.get(index)) {
+                (Some(before), Some(status)) if before != status => {
+                    Some(StatusChange { index, status })
+                }
+                _ => None,
+            })
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        Some(StatusNotification {
+            status_list_credential: url.to_string(),
+            status_purpose: list.purpose,
+            changes,
+        })
+    }
+
+    /// Log a notification, and post it to the webhook, if any.
+    async fn notify(&self, notification: &StatusNotification) {
+        for change in &notification.changes {
+            eprintln!(
+                "Credential status {} for {}: {} index {}",
+                match change.status {
+                    true => "set",
+                    false => "unset",
+                },
+                notification.status_purpose,
+                notification.status_list_credential,
+                change.index
+            );
+        }
+        let webhook = match self.webhook {
+            Some(ref webhook) => webhook,
+            None => return,
+        };
+        let body = match serde_json::to_vec(notification) {
+            Ok(body) => body,
+            Err(err) => return eprintln!("Unable to serialize status notification: {}", err),
+        };
+        let result = self
+            .client
+            .post(webhook)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            eprintln!("Unable to post status notification to {}: {}", webhook, err);
+        }
+    }
+
+    /// Record the tracked indexes of a list in the storage, and track those recorded by other
+    /// servers.
+    async fn share_tracked(&self, storage: &dyn Storage, url: &str) -> Result<(), StorageError> {
+        let prefix = format!("{}#", url);
+        let recorded: BTreeSet<usize> = storage
+            .names(TRACKED_NAMESPACE)
+            .await?
+            .iter()
+            .filter_map(|name| name.strip_prefix(&prefix)?.parse().ok())
+            .collect();
+        let unrecorded: Vec<usize> = match self.tracked.read() {
+            Ok(tracked) => tracked
+                .get(url)
+                .map(|indexes| indexes.difference(&recorded).copied().collect())
+                .unwrap_or_default(),
+            Err(_) => return Err(StorageError::Poisoned),
+        };
+        for index in unrecorded {
+            let name = format!("{}{}", prefix, index);
+            storage.put(TRACKED_NAMESPACE, &name, b"", None).await?;
+        }
+        for index in recorded {
+            self.track(url, index);
+        }
+        Ok(())
+    }
+
+    /// Keep the changes of a notification that no other server sharing the storage has claimed
+    /// to notify.
+    async fn claim_changes(&self, storage: &dyn Storage, notification: &mut StatusNotification) {
+        let ttl = self.refresh_interval * 2;
+        let mut changes = Vec::new();
+        for change in notification.changes.drain(..) {
+            let name = format!(
+                "{}#{}={}",
+                notification.status_list_credential, change.index, change.status
+            );
+            match storage
+                .insert(NOTIFIED_NAMESPACE, &name, b"", Some(ttl))
+                .await
+            {
+                Ok(false) => {}
+                Ok(true) => changes.push(change),
+                Err(err) => {
+                    eprintln!("Unable to claim status notification {}: {}", name, err);
+                    changes.push(change);
+                }
+            }
+        }
+        notification.changes = changes;
+    }
+
+    /// Fetch all the status lists. Lists that cannot be fetched keep their previous copy.
+    pub async fn refresh(&self) {
+        for url in &self.urls {
+            if let Some(ref storage) = self.storage {
+                if let Err(err) = self.share_tracked(storage.as_ref(), url).await {
+                    eprintln!("Unable to share tracked indexes of {}: {}", url, err);
+                }
+            }
+            match self.fetch(url).await {
+                Ok(list) => {
+                    if let Some(mut notification) = self.insert(url, list) {
+                        if let Some(ref storage) = self.storage {
+                            self.claim_changes(storage.as_ref(), &mut notification)
+                                .await;
+                        }
+                        if !notification.changes.is_empty() {
+                            self.notify(&notification).await;
+                        }
+                    }
+                }
+                Err(err) => eprintln!("Unable to refresh status list {}: {}", url, err),
+            }
+        }
+    }
+
+    /// Refresh the status lists now and then every refresh interval, in a background task.
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                self.refresh().await;
+                tokio::time::sleep(self.refresh_interval).await;
+            }
+        })
+    }
+
+    /// Check the status entry of a credential by the given issuer, if it refers to one of the
+    /// cached lists.
+    fn check_entry(&self, entry: &Value, issuer: Option<&str>) -> Result<(), (ErrorCode, String)> {
+        let (url, index) = match list_index(entry) {
+            Some((url, index)) if self.urls.iter().any(|u| u == url) => (url, index),
+            _ => return Ok(()),
+        };
+        let lists = self.lists.read().map_err(|_| {
+            (
+                ErrorCode::StatusUnavailable,
+                "Status cache lock poisoned".to_string(),
+            )
+        })?;
+        let list = match lists.get(url) {
+            Some(list) if list.fetched.elapsed() <= self.max_age => list,
+            _ => {
+                return Err((
+                    ErrorCode::StatusUnavailable,
+                    format!("Status list not available: {}", url),
+                ))
+            }
+        };
+        if issuer != Some(list.issuer.as_str()) {
+            return Err((
+                ErrorCode::StatusListMismatch,
+                format!(
+                    "Status list {} is issued by {}, not by the credential's issuer",
+                    url, list.issuer
+                ),
+            ));
+        }
+        // Entries of RevocationList2020 have no purpose: their lists are for revocation.
+        let purpose = entry
+            .get("statusPurpose")
+            .and_then(Value::as_str)
+            .unwrap_or("revocation");
+        if purpose != list.purpose {
+            return Err((
+                ErrorCode::StatusListMismatch,
+                format!(
+                    "Status list {} is for {}, not for {}",
+                    url, list.purpose, purpose
+                ),
+            ));
+        }
+        match list.get(index) {
+            Some(false) => {
+                drop(lists);
+                self.track(url, index);
+                Ok(())
+            }
+            Some(true) => Err((
+                ErrorCode::Revoked,
+                format!(
+                    "Credential status set for {}: {} index {}",
+                    list.purpose, url, index
+                ),
+            )),
+            None => Err((
+                ErrorCode::Revoked,
+                format!("Status list index out of range: {} index {}", url, index),
+            )),
+        }
+    }
+
+    /// Check the status of a credential, adding errors to its verification report.
+    pub fn check_credential(&self, credential: &CredentialOrJWT, report: &mut VerificationReport) {
+        let issuer = credential_issuer(credential);
+        for entry in status_entries(credential) {
+            if let Err((code, message)) = self.check_entry(&entry, issuer.as_deref()) {
+                report.push_error(code, &message);
+            }
+        }
+    }
+
+    /// Check the status of the credentials of a presentation, adding errors to its verification
+    /// report.
+    pub fn check_presentation(
+        &self,
+        presentation: &PresentationOrJWT,
+        report: &mut VerificationReport,
+    ) {
+        for (i, credential) in presentation_credentials(presentation).iter().enumerate() {
+            let issuer = credential_issuer(credential);
+            for entry in status_entries(credential) {
+                if let Err((code, message)) = self.check_entry(&entry, issuer.as_deref()) {
+                    report.push_error(code, &format!("Credential {}: {}", i, message));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const ISSUER: &str = "did:example:issuer";
+
+    fn encode(bits: &[u8]) -> String {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bits).unwrap();
+        base64::encode_config(encoder.finish().unwrap(), base64::URL_SAFE_NO_PAD)
+    }
+
+    #[test]
+    fn check_cached_status() {
+        let url = "https://example.org/status/1";
+        let cache = StatusCache::new(
+            vec![url.to_string()],
+            Duration::from_secs(300),
+            Duration::from_secs(3600),
+            ResolverOptions::default(),
+        );
+        let entry = |index: &str| {
+            serde_json::json!({
+                "id": format!("{}#{}", url, index),
+                "type": "StatusList2021Entry",
+                "statusPurpose": "revocation",
+                "statusListIndex": index,
+                "statusListCredential": url
+            })
+        };
+        let issuer = Some(ISSUER);
+        let (code, _) = cache.check_entry(&entry("0"), issuer).unwrap_err();
+        assert_eq!(code, ErrorCode::StatusUnavailable);
+
+        cache.insert(
+            url,
+            StatusList::decode(&encode(&[0x40, 0]), "revocation", ISSUER).unwrap(),
+        );
+        assert!(cache.check_entry(&entry("0"), issuer).is_ok());
+        let (code, _) = cache.check_entry(&entry("1"), issuer).unwrap_err();
+        assert_eq!(code, ErrorCode::Revoked);
+        assert!(cache.check_entry(&entry("15"), issuer).is_ok());
+        assert!(cache.check_entry(&entry("16"), issuer).is_err());
+
+        let mut other = entry("1");
+        other["statusListCredential"] = Value::from("https://example.org/status/2");
+        assert!(cache.check_entry(&other, issuer).is_ok());
+    }
+
+    #[test]
+    fn check_list_issuer_and_purpose() {
+        let url = "https://example.org/status/1";
+        let cache = StatusCache::new(
+            vec![url.to_string()],
+            Duration::from_secs(300),
+            Duration::from_secs(3600),
+            ResolverOptions::default(),
+        );
+        cache.insert(
+            url,
+            StatusList::decode(&encode(&[0]), "revocation", ISSUER).unwrap(),
+        );
+        let entry = serde_json::json!({
+            "id": format!("{}#0", url),
+            "type": "StatusList2021Entry",
+            "statusPurpose": "revocation",
+            "statusListIndex": "0",
+            "statusListCredential": url
+        });
+        assert!(cache.check_entry(&entry, Some(ISSUER)).is_ok());
+
+        // A list of another issuer does not give the status of the credential.
+        let (code, _) = cache
+            .check_entry(&entry, Some("did:example:other"))
+            .unwrap_err();
+        assert_eq!(code, ErrorCode::StatusListMismatch);
+        let (code, _) = cache.check_entry(&entry, None).unwrap_err();
+        assert_eq!(code, ErrorCode::StatusListMismatch);
+
+        // Nor does a list of another purpose.
+        let mut suspension = entry.clone();
+        suspension["statusPurpose"] = Value::from("suspension");
+        let (code, _) = cache.check_entry(&suspension, Some(ISSUER)).unwrap_err();
+        assert_eq!(code, ErrorCode::StatusListMismatch);
+
+        let credential: CredentialOrJWT = serde_json::from_value(serde_json::json!({
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "type": "VerifiableCredential",
+            "issuer": {"id": "did:example:other"},
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject"},
+            "credentialStatus": suspension
+        }))
+        .unwrap();
+        let mut report = VerificationReport::new();
+        cache.check_credential(&credential, &mut report);
+        assert!(report.has_error(ErrorCode::StatusListMismatch));
+    }
+
+    #[test]
+    fn notify_tracked_changes() {
+        let url = "https://example.org/status/1";
+        let cache = StatusCache::new(
+            vec![url.to_string()],
+            Duration::from_secs(300),
+            Duration::from_secs(3600),
+            ResolverOptions::default(),
+        );
+        let list = |bits: &[u8]| StatusList::decode(&encode(bits), "revocation", ISSUER).unwrap();
+        assert_eq!(cache.insert(url, list(&[0])), None);
+        cache.track(url, 0);
+        cache.track(url, 2);
+        assert_eq!(cache.insert(url, list(&[0])), None);
+        // Index 1 is not tracked.
+        let notification = cache.insert(url, list(&[0xe0])).unwrap();
+        assert_eq!(notification.status_list_credential, url);
+        assert_eq!(
+            notification.changes,
+            vec![
+                StatusChange {
+                    index: 0,
+                    status: true
+                },
+                StatusChange {
+                    index: 2,
+                    status: true
+                },
+            ]
+        );
+        let notification = cache.insert(url, list(&[0x40])).unwrap();
+        assert_eq!(notification.changes.len(), 2);
+        assert!(notification.changes.iter().all(|change| !change.status));
+    }
+
+    #[tokio::test]
+    async fn share_tracked_indexes() {
+        let url = "https://example.org/status/1";
+        let storage = Arc::new(crate::storage::MemoryStorage::new());
+        let cache = |storage: Arc<dyn Storage>| {
+            StatusCache::new(
+                vec![url.to_string()],
+                Duration::from_secs(300),
+                Duration::from_secs(3600),
+                ResolverOptions::default(),
+            )
+            .with_storage(storage)
+        };
+        let (first, second) = (cache(storage.clone()), cache(storage.clone()));
+        first.track(url, 3);
+        first.share_tracked(storage.as_ref(), url).await.unwrap();
+        second.share_tracked(storage.as_ref(), url).await.unwrap();
+        assert!(second.tracked.read().unwrap()[url].contains(&3));
+
+        let notification = || StatusNotification {
+            status_list_credential: url.to_string(),
+            status_purpose: "revocation".to_string(),
+            changes: vec![StatusChange {
+                index: 3,
+                status: true,
+            }],
+        };
+        let (mut claimed, mut unclaimed) = (notification(), notification());
+        first.claim_changes(storage.as_ref(), &mut claimed).await;
+        second.claim_changes(storage.as_ref(), &mut unclaimed).await;
+        assert_eq!(claimed.changes.len(), 1);
+        assert!(unclaimed.changes.is_empty());
+    }
+}
@@ -0,0 +1,640 @@
+//! Storage backends for the server's shared state, so that several server processes can serve
+//! behind a load balancer.
+//!
+//! A [`Storage`] holds values by namespace and name, optionally expiring. The server keeps in it
+//! the challenges of a [`crate::nonce::StorageNonceStore`], the results of an issuance
+//! [`crate::worker::Worker`] by idempotency key, and the status list indexes tracked by a
+//! [`crate::status::StatusCache`].
+//!
+//! The hosted status list and the audit log are not kept in a [`Storage`]: they are tables of the
+//! credential store (`store` feature), next to the credentials whose lifecycle they record, as a
+//! state transition, its audit entry and the status list index of a credential are written in
+//! one database transaction, which values by name cannot give. Several servers share them by
+//! sharing a PostgreSQL credential store. The server has no exchange (VC-API workflow) routes, so
+//! there is no exchange state to share.
+//!
+//! [`open`] selects a backend by URL:
+//!
+//! - `memory`: in the server process ([`MemoryStorage`]);
+//! - `redis://` or `rediss://` (`redis` feature): [`RedisStorage`];
+//! - `postgres://` or `postgresql://` (`postgres` feature): [`PostgresStorage`];
+//! - `s3://<bucket>/<prefix>` (`s3` feature): [`S3Storage`], with credentials and region from
+//!   the `AWS_*` environment variables.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+#[derive(Debug)]
+pub enum StorageError {
+    #[cfg(feature = "redis")]
+    Redis(redis::RedisError),
+    #[cfg(feature = "postgres")]
+    Postgres(sqlx::Error),
+    #[cfg(feature = "s3")]
+    S3(object_store::Error),
+    Poisoned,
+    UnsupportedStorage(String),
+}
+
+impl std::error::Error for StorageError {}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "redis")]
+            StorageError::Redis(e) => e.fmt(f),
+            #[cfg(feature = "postgres")]
+            StorageError::Postgres(e) => e.fmt(f),
+            #[cfg(feature = "s3")]
+            StorageError::S3(e) => e.fmt(f),
+            StorageError::Poisoned => write!(f, "Storage lock poisoned"),
+            StorageError::UnsupportedStorage(url) => write!(f, "Unsupported storage: {}", url),
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for StorageError {
+    fn from(err: redis::RedisError) -> StorageError {
+        StorageError::Redis(err)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<sqlx::Error> for StorageError {
+    fn from(err: sqlx::Error) -> StorageError {
+        StorageError::Postgres(err)
+    }
+}
+
+#[cfg(feature = "s3")]
+impl From<object_store::Error> for StorageError {
+    fn from(err: object_store::Error) -> StorageError {
+        StorageError::S3(err)
+    }
+}
+
+/// Values by namespace and name, shared between server processes.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Get a value, if it is stored and has not expired.
+    async fn get(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError>;
+    /// Store a value, replacing any previous one, expiring after `ttl` if given.
+    async fn put(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), StorageError>;
+    /// Store a value if there is none. Returns `false` if there is one.
+    async fn insert(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StorageError>;
+    /// Remove a value, returning it if it was stored and had not expired.
+    async fn take(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError>;
+    /// Names of the values of a namespace that have not expired.
+    async fn names(&self, namespace: &str) -> Result<Vec<String>, StorageError>;
+    /// Whether [`Storage::insert`] and [`Storage::take`] are atomic, even between server
+    /// processes, as a store of challenges requires.
+    fn is_atomic(&self) -> bool {
+        true
+    }
+}
+
+/// Open the storage backend at a URL.
+pub async fn open(url: &str) -> Result<Arc<dyn Storage>, StorageError> {
+    if url == "memory" {
+        return Ok(Arc::new(MemoryStorage::new()));
+    }
+    #[cfg(feature = "redis")]
+    if url.starts_with("redis://") || url.starts_with("rediss://") {
+        return Ok(Arc::new(RedisStorage::new(url)?));
+    }
+    #[cfg(feature = "postgres")]
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        return Ok(Arc::new(PostgresStorage::connect(url).await?));
+    }
+    #[cfg(feature = "s3")]
+    if let Some(location) = url.strip_prefix("s3://") {
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        return Ok(Arc::new(S3Storage::new(bucket, prefix)?));
+    }
+    Err(StorageError::UnsupportedStorage(url.to_string()))
+}
+
+/// Storage in the server process.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    values: Mutex<HashMap<(String, String), (Option<Instant>, Vec<u8>)>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn live(expires: &Option<Instant>, now: Instant) -> bool {
+    expires.map_or(true, |expires| expires > now)
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn get(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let values = self.values.lock().map_err(|_| StorageError::Poisoned)?;
+        let now = Instant::now();
+        Ok(values
+            .get(&(namespace.to_string(), name.to_string()))
+            .filter(|(expires, _)| live(expires, now))
+            .map(|(_, value)| value.clone()))
+    }
+
+    async fn put(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), StorageError> {
+        let mut values = self.values.lock().map_err(|_| StorageError::Poisoned)?;
+        let now = Instant::now();
+        values.retain(|_, (expires, _)| live(expires, now));
+        values.insert(
+            (namespace.to_string(), name.to_string()),
+            (ttl.map(|ttl| now + ttl), value.to_vec()),
+        );
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StorageError> {
+        let mut values = self.values.lock().map_err(|_| StorageError::Poisoned)?;
+        let now = Instant::now();
+        values.retain(|_, (expires, _)| live(expires, now));
+        let key = (namespace.to_string(), name.to_string());
+        if values.contains_key(&key) {
+            return Ok(false);
+        }
+        values.insert(key, (ttl.map(|ttl| now + ttl), value.to_vec()));
+        Ok(true)
+    }
+
+    async fn take(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let mut values = self.values.lock().map_err(|_| StorageError::Poisoned)?;
+        let now = Instant::now();
+        Ok(values
+            .remove(&(namespace.to_string(), name.to_string()))
+            .filter(|(expires, _)| live(expires, now))
+            .map(|(_, value)| value))
+    }
+
+    async fn names(&self, namespace: &str) -> Result<Vec<String>, StorageError> {
+        let values = self.values.lock().map_err(|_| StorageError::Poisoned)?;
+        let now = Instant::now();
+        Ok(values
+            .iter()
+            .filter(|((ns, _), (expires, _))| ns == namespace && live(expires, now))
+            .map(|((_, name), _)| name.clone())
+            .collect())
+    }
+}
+
+/// Storage in Redis. Values are stored as keys `didkit:<namespace>:<name>`, expiring with the
+/// value.
+#[cfg(feature = "redis")]
+pub struct RedisStorage {
+    client: redis::Client,
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStorage {
+    pub fn new(url: &str) -> Result<Self, StorageError> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            prefix: "didkit:".to_string(),
+        })
+    }
+
+    fn key(&self, namespace: &str, name: &str) -> String {
+        format!("{}{}:{}", self.prefix, namespace, name)
+    }
+
+    fn set(key: String, value: &[u8], ttl: Option<Duration>) -> redis::Cmd {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value);
+        if let Some(ttl) = ttl {
+            cmd.arg("PX").arg((ttl.as_millis() as u64).max(1));
+        }
+        cmd
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl Storage for RedisStorage {
+    async fn get(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let mut con = self.client.get_async_connection().await?;
+        Ok(redis::cmd("GET")
+            .arg(self.key(namespace, name))
+            .query_async(&mut con)
+            .await?)
+    }
+
+    async fn put(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), StorageError> {
+        let mut con = self.client.get_async_connection().await?;
+        Self::set(self.key(namespace, name), value, ttl)
+            .query_async::<_, ()>(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StorageError> {
+        let mut con = self.client.get_async_connection().await?;
+        let set: Option<String> = Self::set(self.key(namespace, name), value, ttl)
+            .arg("NX")
+            .query_async(&mut con)
+            .await?;
+        Ok(set.is_some())
+    }
+
+    async fn take(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let mut con = self.client.get_async_connection().await?;
+        let key = self.key(namespace, name);
+        let (value,): (Option<Vec<u8>>,) = redis::pipe()
+            .atomic()
+            .cmd("GET")
+            .arg(&key)
+            .cmd("DEL")
+            .arg(&key)
+            .ignore()
+            .query_async(&mut con)
+            .await?;
+        Ok(value)
+    }
+
+    async fn names(&self, namespace: &str) -> Result<Vec<String>, StorageError> {
+        let mut con = self.client.get_async_connection().await?;
+        let prefix = self.key(namespace, "");
+        let pattern: String = prefix
+            .chars()
+            .flat_map(|c| match c {
+                '*' | '?' | '[' | ']' | '\\' => vec!['\\', c],
+                c => vec![c],
+            })
+            .chain(std::iter::once('*'))
+            .collect();
+        let mut names = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(1000)
+                .query_async(&mut con)
+                .await?;
+            names.extend(
+                keys.iter()
+                    .filter_map(|key| key.strip_prefix(&prefix))
+                    .map(|name| name.to_string()),
+            );
+            if next == 0 {
+                return Ok(names);
+            }
+            cursor = next;
+        }
+    }
+}
+
+/// Storage in a PostgreSQL table, `didkit_storage`, created if needed. Expired rows are deleted
+/// when their names are listed.
+#[cfg(feature = "postgres")]
+pub struct PostgresStorage {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+const POSTGRES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS didkit_storage (
+    namespace TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value BYTEA NOT NULL,
+    expires TIMESTAMPTZ,
+    PRIMARY KEY (namespace, name)
+)";
+
+#[cfg(feature = "postgres")]
+const POSTGRES_LIVE: &str = "(expires IS NULL OR expires > now())";
+
+#[cfg(feature = "postgres")]
+impl PostgresStorage {
+    pub async fn connect(url: &str) -> Result<Self, StorageError> {
+        let pool = sqlx::postgres::PgPoolOptions::new().connect(url).await?;
+        sqlx::query(POSTGRES_SCHEMA).execute(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn get(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        use sqlx::Row;
+        let row = sqlx::query(&format!(
+            "SELECT value FROM didkit_storage WHERE namespace = $1 AND name = $2 AND {}",
+            POSTGRES_LIVE
+        ))
+        .bind(namespace)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(match row {
+            Some(row) => Some(row.try_get("value")?),
+            None => None,
+        })
+    }
+
+    async fn put(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO didkit_storage (namespace, name, value, expires)
+            VALUES ($1, $2, $3, now() + $4 * interval '1 second')
+            ON CONFLICT (namespace, name)
+            DO UPDATE SET value = EXCLUDED.value, expires = EXCLUDED.expires",
+        )
+        .bind(namespace)
+        .bind(name)
+        .bind(value)
+        .bind(ttl.map(|ttl| ttl.as_secs_f64()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StorageError> {
+        // Replace an expired row, but not a live one.
+        let result = sqlx::query(
+            "INSERT INTO didkit_storage (namespace, name, value, expires)
+            VALUES ($1, $2, $3, now() + $4 * interval '1 second')
+            ON CONFLICT (namespace, name)
+            DO UPDATE SET value = EXCLUDED.value, expires = EXCLUDED.expires
+            WHERE didkit_storage.expires <= now()",
+        )
+        .bind(namespace)
+        .bind(name)
+        .bind(value)
+        .bind(ttl.map(|ttl| ttl.as_secs_f64()))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn take(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        use sqlx::Row;
+        let row = sqlx::query(&format!(
+            "DELETE FROM didkit_storage WHERE namespace = $1 AND name = $2
+            RETURNING value, {} AS live",
+            POSTGRES_LIVE
+        ))
+        .bind(namespace)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(row) if row.try_get("live")? => Ok(Some(row.try_get("value")?)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn names(&self, namespace: &str) -> Result<Vec<String>, StorageError> {
+        use sqlx::Row;
+        sqlx::query("DELETE FROM didkit_storage WHERE namespace = $1 AND expires <= now()")
+            .bind(namespace)
+            .execute(&self.pool)
+            .await?;
+        let rows = sqlx::query("SELECT name FROM didkit_storage WHERE namespace = $1")
+            .bind(namespace)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get("name"))
+            .collect::<Result<Vec<String>, sqlx::Error>>()?)
+    }
+}
+
+/// Storage in an S3 bucket, as objects `<prefix>/<namespace>/<name>`, with the name in
+/// base64url.
+///
+/// Objects expire when they are next read, or listed: configure a lifecycle rule on the bucket to
+/// delete objects that are not. S3 has no conditional writes, so [`Storage::insert`] and
+/// [`Storage::take`] are not atomic: S3 suits the state of a single writer, such as an issuance
+/// worker's results, rather than challenges consumed by several servers, and it is not
+/// [`Storage::is_atomic`].
+#[cfg(feature = "s3")]
+pub struct S3Storage {
+    store: object_store::aws::AmazonS3,
+    prefix: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3Storage {
+    pub fn new(bucket: &str, prefix: &str) -> Result<Self, StorageError> {
+        let store = object_store::aws::AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?;
+        Ok(Self {
+            store,
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    fn directory(&self, namespace: &str) -> object_store::path::Path {
+        match self.prefix.as_str() {
+            "" => object_store::path::Path::from(namespace),
+            prefix => object_store::path::Path::from(prefix).child(namespace),
+        }
+    }
+
+    fn path(&self, namespace: &str, name: &str) -> object_store::path::Path {
+        self.directory(namespace)
+            .child(base64::encode_config(name, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Object contents: the expiration time, in seconds since the Unix epoch or 0 for none, as
+    /// 8 bytes big-endian, followed by the value.
+    fn encode(value: &[u8], ttl: Option<Duration>) -> bytes::Bytes {
+        let expires = ttl.map_or(0, |ttl| Self::now() + ttl.as_secs().max(1));
+        let mut data = expires.to_be_bytes().to_vec();
+        data.extend_from_slice(value);
+        data.into()
+    }
+
+    /// Read an object, deleting it if it has expired.
+    async fn read(&self, path: &object_store::path::Path) -> Result<Option<Vec<u8>>, StorageError> {
+        use object_store::ObjectStore;
+        let data = match self.store.get(path).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if data.len() < 8 {
+            return Ok(None);
+        }
+        let mut expires = [0u8; 8];
+        expires.copy_from_slice(&data[..8]);
+        let expires = u64::from_be_bytes(expires);
+        if expires != 0 && expires <= Self::now() {
+            self.store.delete(path).await?;
+            return Ok(None);
+        }
+        Ok(Some(data[8..].to_vec()))
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait]
+impl Storage for S3Storage {
+    async fn get(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        self.read(&self.path(namespace, name)).await
+    }
+
+    async fn put(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), StorageError> {
+        use object_store::ObjectStore;
+        let path = self.path(namespace, name);
+        self.store.put(&path, Self::encode(value, ttl)).await?;
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        namespace: &str,
+        name: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StorageError> {
+        if self.get(namespace, name).await?.is_some() {
+            return Ok(false);
+        }
+        self.put(namespace, name, value, ttl).await?;
+        Ok(true)
+    }
+
+    async fn take(&self, namespace: &str, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        use object_store::ObjectStore;
+        let path = self.path(namespace, name);
+        let value = self.read(&path).await?;
+        if value.is_some() {
+            self.store.delete(&path).await?;
+        }
+        Ok(value)
+    }
+
+    async fn names(&self, namespace: &str) -> Result<Vec<String>, StorageError> {
+        use futures_util::TryStreamExt;
+        use object_store::ObjectStore;
+        let objects: Vec<object_store::ObjectMeta> = self
+            .store
+            .list(Some(&self.directory(namespace)))
+            .await?
+            .try_collect()
+            .await?;
+        let mut names = Vec::new();
+        for object in objects {
+            let name = match object
+                .location
+                .filename()
+                .and_then(|name| base64::decode_config(name, base64::URL_SAFE_NO_PAD).ok())
+                .and_then(|name| String::from_utf8(name).ok())
+            {
+                Some(name) => name,
+                None => continue,
+            };
+            if self.read(&object.location).await?.is_some() {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    fn is_atomic(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_storage() {
+        let storage = MemoryStorage::new();
+        assert_eq!(storage.get("ns", "a").await.unwrap(), None);
+        storage.put("ns", "a", b"1", None).await.unwrap();
+        assert_eq!(storage.get("ns", "a").await.unwrap(), Some(b"1".to_vec()));
+        assert!(!storage.insert("ns", "a", b"2", None).await.unwrap());
+        assert!(storage.insert("other", "a", b"2", None).await.unwrap());
+        assert_eq!(storage.names("ns").await.unwrap(), vec!["a".to_string()]);
+        assert_eq!(storage.take("ns", "a").await.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.take("ns", "a").await.unwrap(), None);
+
+        let expired = Some(Duration::from_secs(0));
+        storage.put("ns", "b", b"1", expired).await.unwrap();
+        assert_eq!(storage.get("ns", "b").await.unwrap(), None);
+        assert!(storage.names("ns").await.unwrap().is_empty());
+        assert!(storage.insert("ns", "b", b"2", None).await.unwrap());
+        assert!(open("memory").await.is_ok());
+        assert!(open("unknown://").await.is_err());
+    }
+}
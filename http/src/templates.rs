@@ -0,0 +1,188 @@
+//! Named issuance templates.
+//!
+//! A template, selected with `POST /credentials/issue?template=<name>`, gives the base
+//! credential and the default options of an issuance, and the fields that clients may set. The
+//! request's `credential` and `options` are merged into the template's, objects member by member,
+//! and other values replacing the template's. A request that sets a field the template does not
+//! allow is rejected with [problem details](crate::validation) pointing to each such field.
+//!
+//! Templates are configured as a JSON object of templates by name, e.g.:
+//!
+//! ```json
+//! {
+//!   "membership": {
+//!     "credential": {
+//!       "@context": ["https://www.w3.org/2018/credentials/v1"],
+//!       "type": ["VerifiableCredential", "MembershipCredential"],
+//!       "issuer": "did:example:club"
+//!     },
+//!     "options": { "proofFormat": "ldp" },
+//!     "allowedFields": ["/credential/credentialSubject", "/credential/issuanceDate"]
+//!   }
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::validation::{self, InvalidValue, Problem};
+
+/// Issuance templates, by name.
+pub type Templates = HashMap<String, IssuanceTemplate>;
+
+/// An issuance template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IssuanceTemplate {
+    /// Base credential
+    #[serde(default)]
+    pub credential: Map<String, Value>,
+    /// Default issuance options
+    #[serde(default)]
+    pub options: Map<String, Value>,
+    /// JSON Pointers, in request bodies, of the fields that clients may set, with their members,
+    /// e.g. `/credential/credentialSubject` or `/options/expires`
+    #[serde(default)]
+    pub allowed_fields: Vec<String>,
+}
+
+/// Members of request bodies merged into a template.
+const MEMBERS: &[&str] = &["credential", "options"];
+/// Members of request bodies passed through as they are, not being part of the credential.
+const PASS_THROUGH: &[&str] = &["keyAttestation", "proofOfPossession"];
+
+impl IssuanceTemplate {
+    /// Apply the template to an issuance request body: check that the request only sets allowed
+    /// fields, and return the body of the request merged into the template.
+    pub fn apply(&self, name: &str, body: &[u8]) -> Result<Vec<u8>, Problem> {
+        let request: Map<String, Value> = validation::parse(body)?;
+        let mut invalid = Vec::new();
+        for (member, value) in &request {
+            if MEMBERS.contains(&member.as_str()) {
+                self.check(name, &format!("/{}", member), value, &mut invalid);
+            } else if !PASS_THROUGH.contains(&member.as_str()) {
+                invalid.push(InvalidValue {
+                    detail: format!("unknown field `{}`", member),
+                    pointer: format!("#/{}", escape(member)),
+                    expected: None,
+                    allowed: Some(
+                        MEMBERS
+                            .iter()
+                            .chain(PASS_THROUGH)
+                            .map(|m| m.to_string())
+                            .collect(),
+                    ),
+                });
+            }
+        }
+        if let Some(first) = invalid.first() {
+            let err = <serde_json::Error as serde::de::Error>::custom(&first.detail);
+            return Err(Problem::bad_request(err, invalid));
+        }
+        let mut merged = Value::Object(Map::new());
+        merged["credential"] = Value::Object(self.credential.clone());
+        if !self.options.is_empty() {
+            merged["options"] = Value::Object(self.options.clone());
+        }
+        merge(&mut merged, Value::Object(request));
+        Ok(serde_json::to_vec(&merged).unwrap_or_default())
+    }
+
+    fn allows(&self, pointer: &str) -> bool {
+        self.allowed_fields.iter().any(|allowed| {
+            pointer == allowed
+                || (pointer.starts_with(allowed.as_str())
+                    && pointer[allowed.len()..].starts_with('/'))
+        })
+    }
+
+    /// Report the fields of a value that the template does not allow.
+    fn check(&self, name: &str, pointer: &str, value: &Value, invalid: &mut Vec<InvalidValue>) {
+        if self.allows(pointer) {
+            return;
+        }
+        match value {
+            Value::Object(object) => {
+                for (member, value) in object {
+                    let pointer = format!("{}/{}", pointer, escape(member));
+                    self.check(name, &pointer, value, invalid);
+                }
+            }
+            _ => invalid.push(InvalidValue {
+                detail: format!("field not allowed by issuance template `{}`", name),
+                pointer: format!("#{}", pointer),
+                expected: None,
+                allowed: Some(self.allowed_fields.clone()),
+            }),
+        }
+    }
+}
+
+/// JSON Pointer escaping of a member name.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// Merge a value into another: objects member by member, other values replacing the base.
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(object)) => {
+            for (member, value) in object {
+                merge(base.entry(member).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn apply_template() {
+        let template: IssuanceTemplate = serde_json::from_value(json!({
+            "credential": {
+                "type": ["VerifiableCredential", "MembershipCredential"],
+                "issuer": "did:example:club",
+                "credentialSubject": { "memberOf": "Club" },
+            },
+            "options": { "proofFormat": "ldp" },
+            "allowedFields": ["/credential/credentialSubject/id", "/options/created"],
+        }))
+        .unwrap();
+
+        let body = json!({
+            "credential": { "credentialSubject": { "id": "did:example:member" } },
+            "options": { "created": "2024-01-01T00:00:00Z" },
+        });
+        let merged = template
+            .apply("membership", body.to_string().as_bytes())
+            .unwrap();
+        let merged: Value = serde_json::from_slice(&merged).unwrap();
+        assert_eq!(merged["credential"]["issuer"], "did:example:club");
+        assert_eq!(
+            merged["credential"]["credentialSubject"],
+            json!({ "id": "did:example:member", "memberOf": "Club" })
+        );
+        assert_eq!(merged["options"]["proofFormat"], "ldp");
+        assert_eq!(merged["options"]["created"], "2024-01-01T00:00:00Z");
+
+        let body = json!({
+            "credential": { "issuer": "did:example:other", "credentialSubject": {} },
+            "options": { "proofFormat": "jwt" },
+        });
+        let problem = template
+            .apply("membership", body.to_string().as_bytes())
+            .unwrap_err();
+        let pointers: Vec<&str> = problem.errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(
+            pointers,
+            vec!["#/credential/issuer", "#/options/proofFormat"]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Validation of request bodies, with [problem details][rfc9457] for invalid requests.
+//!
+//! Instead of the bare deserialization error, a 400 Bad Request response for an invalid body is
+//! an `application/problem+json` object that points to the offending value with a JSON Pointer,
+//! and gives the expected type or the allowed values, where known. It also has the `code`, `kind`
+//! and `message` members of other [error responses](crate::DIDKitHTTPSvc::error_response).
+//!
+//! [rfc9457]: https://www.rfc-editor.org/rfc/rfc9457
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_path_to_error::Segment;
+
+use didkit::error::ErrorInfo;
+use didkit::Error as DIDKitError;
+
+/// Media type of problem details.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An invalid value in a request body.
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidValue {
+    /// What is wrong with the value
+    pub detail: String,
+    /// JSON Pointer to the value in the request body, as a URI fragment, e.g.
+    /// `#/options/proofFormat`
+    pub pointer: String,
+    /// Expected type of the value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// Allowed values, for an unknown enum variant, or allowed properties, for an unknown field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<String>>,
+}
+
+/// Problem details of an invalid request.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(flatten)]
+    pub info: ErrorInfo,
+    pub errors: Vec<InvalidValue>,
+}
+
+impl Problem {
+    /// Problem details of a request with the given invalid values.
+    pub fn bad_request(error: impl Into<DIDKitError>, errors: Vec<InvalidValue>) -> Self {
+        let info = error.into().info();
+        let detail = match errors.first() {
+            Some(error) if error.pointer != "#" => format!("{} at {}", error.detail, error.pointer),
+            Some(error) => error.detail.clone(),
+            None => info.message.clone(),
+        };
+        Self {
+            type_: "about:blank".to_string(),
+            title: "Bad Request".to_string(),
+            status: 400,
+            detail,
+            info,
+            errors,
+        }
+    }
+
+    /// Problem details of a request with invalid `options`.
+    pub fn invalid_options(error: impl Into<DIDKitError> + ToString) -> Self {
+        let invalid = InvalidValue {
+            detail: error.to_string(),
+            pointer: "#/options".to_string(),
+            expected: None,
+            allowed: None,
+        };
+        Self::bad_request(error, vec![invalid])
+    }
+}
+
+/// Deserialize a request body, or describe why it is invalid.
+pub fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Problem> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value: T = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+        let segments = err
+            .path()
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Seq { index } => Some(index.to_string()),
+                Segment::Map { key } => Some(key.clone()),
+                Segment::Enum { variant } => Some(variant.clone()),
+                Segment::Unknown => None,
+            })
+            .collect();
+        let invalid = invalid_value(segments, err.inner());
+        Problem::bad_request(err.into_inner(), vec![invalid])
+    })?;
+    deserializer.end().map_err(|err| {
+        let invalid = invalid_value(Vec::new(), &err);
+        Problem::bad_request(err, vec![invalid])
+    })?;
+    Ok(value)
+}
+
+fn invalid_value(mut segments: Vec<String>, err: &serde_json::Error) -> InvalidValue {
+    if err.is_syntax() || err.is_eof() || err.is_io() {
+        return InvalidValue {
+            detail: err.to_string(),
+            pointer: pointer(&segments),
+            expected: None,
+            allowed: None,
+        };
+    }
+    let message = err.to_string();
+    // Messages of serde_json data errors end with the position of the error.
+    let message = match message.rfind(" at line ") {
+        Some(i) => &message[..i],
+        None => &message[..],
+    };
+    let mut invalid = InvalidValue {
+        detail: message.to_string(),
+        pointer: String::new(),
+        expected: None,
+        allowed: None,
+    };
+    if let Some(field) = between(message, "missing field `", "`") {
+        segments.push(field.to_string());
+    } else if let Some((unknown, allowed)) = unknown(message, "unknown field `") {
+        if segments.last().map(String::as_str) != Some(unknown) {
+            segments.push(unknown.to_string());
+        }
+        invalid.allowed = Some(allowed);
+    } else if let Some((_, allowed)) = unknown(message, "unknown variant `") {
+        invalid.allowed = Some(allowed);
+    } else if let Some(i) = message.find(", expected ") {
+        invalid.expected = Some(message[i + ", expected ".len()..].to_string());
+    }
+    invalid.pointer = pointer(&segments);
+    invalid
+}
+
+/// JSON Pointer, as a URI fragment.
+fn pointer(segments: &[String]) -> String {
+    let mut pointer = "#".to_string();
+    for segment in segments {
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+    pointer
+}
+
+fn between<'a>(message: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    let rest = message.strip_prefix(prefix)?;
+    Some(&rest[..rest.find(suffix)?])
+}
+
+/// The unknown name and the expected names, of an unknown field or variant error, e.g.
+/// ``unknown variant `foo`, expected one of `bar`, `baz` ``.
+fn unknown<'a>(message: &'a str, prefix: &str) -> Option<(&'a str, Vec<String>)> {
+    let name = between(message, prefix, "`")?;
+    let rest = &message[prefix.len() + name.len() + 1..];
+    let allowed = rest
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect();
+    Some((name, allowed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Request {
+        credential: Vec<u32>,
+        options: Option<Options>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Options {
+        proof_format: Option<Format>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Format {
+        Jwt,
+        Ldp,
+    }
+
+    fn error(body: &str) -> InvalidValue {
+        let problem = parse::<Request>(body.as_bytes()).unwrap_err();
+        assert_eq!(problem.status, 400);
+        problem.errors[0].clone()
+    }
+
+    #[test]
+    fn invalid_values() {
+        let invalid = error(r#"{"credential": [1, "two"]}"#);
+        assert_eq!(invalid.pointer, "#/credential/1");
+        assert_eq!(invalid.expected.as_deref(), Some("u32"));
+
+        let invalid = error(r#"{"credential": [], "options": {"proofFormat": "jws"}}"#);
+        assert_eq!(invalid.pointer, "#/options/proofFormat");
+        assert_eq!(
+            invalid.allowed,
+            Some(vec!["jwt".to_string(), "ldp".to_string()])
+        );
+
+        let invalid = error(r#"{"credential": [], "options": {"format": "jwt"}}"#);
+        assert_eq!(invalid.pointer, "#/options/format");
+        assert_eq!(invalid.allowed, Some(vec!["proofFormat".to_string()]));
+
+        let invalid = error(r#"{"options": {}}"#);
+        assert_eq!(invalid.pointer, "#/credential");
+        assert_eq!(invalid.detail, "missing field `credential`");
+
+        let invalid = error(r#"{"credential": ["#);
+        assert!(invalid.pointer.starts_with("#/credential"));
+        assert!(invalid.expected.is_none());
+    }
+}
@@ -0,0 +1,753 @@
+//! Issuance worker, consuming requests from a message queue.
+//!
+//! A [`Worker`] receives issuance requests from a [`Broker`], signs the credentials, and
+//! publishes the signed credentials, or errors, as results. A request is acknowledged only after
+//! its result is published, so that requests are processed at least once: a request redelivered
+//! after a failure or a restart is answered with the result recorded for its idempotency key, if
+//! any, rather than signed again.
+//!
+//! Requests are JSON objects, as for `/credentials/issue`, with an `idempotencyKey`:
+//!
+//! ```json
+//! {"idempotencyKey": "...", "credential": {...}, "options": {...}}
+//! ```
+//!
+//! Results are `{"idempotencyKey": "...", "credential": ...}` or
+//! `{"idempotencyKey": "...", "error": "..."}`.
+//!
+//! Brokers are available with features: `kafka` (Kafka topics), `amqp` (AMQP 0.9.1 queues, e.g.
+//! RabbitMQ) and `nats` (NATS JetStream subjects).
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use didkit::{issue_credential, CredentialOrJWT, IssueOptions, JWTOrLDPOptions};
+use didkit::{Signer, VerifiableCredential};
+use didkit_cli::opts::ResolverOptions;
+
+use crate::storage::{MemoryStorage, Storage};
+use crate::{pick_key, KeyMap};
+
+#[derive(Debug)]
+pub enum WorkerError {
+    #[cfg(feature = "kafka")]
+    Kafka(rdkafka::error::KafkaError),
+    #[cfg(feature = "amqp")]
+    AMQP(lapin::Error),
+    #[cfg(feature = "nats")]
+    NATS(String),
+    /// The broker's subscription ended
+    Closed,
+    UnsupportedBroker(String),
+}
+
+impl std::error::Error for WorkerError {}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "kafka")]
+            WorkerError::Kafka(e) => e.fmt(f),
+            #[cfg(feature = "amqp")]
+            WorkerError::AMQP(e) => e.fmt(f),
+            #[cfg(feature = "nats")]
+            WorkerError::NATS(e) => e.fmt(f),
+            WorkerError::Closed => write!(f, "Subscription closed"),
+            WorkerError::UnsupportedBroker(url) => write!(f, "Unsupported broker: {}", url),
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<rdkafka::error::KafkaError> for WorkerError {
+    fn from(err: rdkafka::error::KafkaError) -> WorkerError {
+        WorkerError::Kafka(err)
+    }
+}
+
+#[cfg(feature = "amqp")]
+impl From<lapin::Error> for WorkerError {
+    fn from(err: lapin::Error) -> WorkerError {
+        WorkerError::AMQP(err)
+    }
+}
+
+/// Issuance request.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueJob {
+    pub idempotency_key: String,
+    pub credential: VerifiableCredential,
+    pub options: Option<JWTOrLDPOptions>,
+}
+
+/// Result of an issuance request.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobResult {
+    pub idempotency_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<CredentialOrJWT>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Acknowledgement handle of a received message.
+#[async_trait]
+pub trait Acker: Send {
+    /// The message was processed: do not deliver it again.
+    async fn ack(self: Box<Self>) -> Result<(), WorkerError>;
+    /// The message could not be processed: deliver it again.
+    async fn nack(self: Box<Self>) -> Result<(), WorkerError>;
+}
+
+/// Message received from a broker.
+pub struct Delivery {
+    pub payload: Vec<u8>,
+    pub acker: Box<dyn Acker>,
+}
+
+/// Message queue, with a subscription to requests and a destination for results.
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Wait for the next request.
+    async fn receive(&self) -> Result<Delivery, WorkerError>;
+    /// Publish a result, returning once the broker has accepted it.
+    async fn publish(&self, key: &str, payload: Vec<u8>) -> Result<(), WorkerError>;
+}
+
+/// Results of processed requests, by idempotency key.
+///
+/// Results are kept for the given time, in memory unless a [`Storage`] backend is given: workers
+/// sharing a subscription, without shared storage, should receive the requests of a given key
+/// from the same partition or queue.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    storage: Arc<dyn Storage>,
+}
+
+impl IdempotencyCache {
+    /// Namespace of results in the storage.
+    const NAMESPACE: &'static str = "idempotency";
+
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_storage(Arc::new(MemoryStorage::new()), ttl)
+    }
+
+    pub fn with_storage(storage: Arc<dyn Storage>, ttl: Duration) -> Self {
+        Self { ttl, storage }
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        match self.storage.get(Self::NAMESPACE, key).await {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("Unable to get result for {}: {}", key, err);
+                None
+            }
+        }
+    }
+
+    async fn insert(&self, key: &str, result: Vec<u8>) {
+        if let Err(err) = self
+            .storage
+            .put(Self::NAMESPACE, key, &result, Some(self.ttl))
+            .await
+        {
+            eprintln!("Unable to record result for {}: {}", key, err);
+        }
+    }
+}
+
+pub struct Worker {
+    broker: Arc<dyn Broker>,
+    keys: KeyMap,
+    resolver_options: ResolverOptions,
+    idempotency: IdempotencyCache,
+    concurrency: usize,
+}
+
+impl Worker {
+    /// Process up to `concurrency` requests at a time.
+    pub fn new(
+        broker: Arc<dyn Broker>,
+        keys: Vec<Arc<dyn Signer>>,
+        resolver_options: ResolverOptions,
+        idempotency_ttl: Duration,
+        concurrency: usize,
+    ) -> Self {
+        Self {
+            broker,
+            keys: keys.into_iter().fold(KeyMap::new(), |mut map, signer| {
+                map.insert(signer.public_jwk(), signer);
+                map
+            }),
+            resolver_options,
+            idempotency: IdempotencyCache::new(idempotency_ttl),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Record results in a storage backend, shared with the other workers of the subscription.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.idempotency = IdempotencyCache::with_storage(storage, self.idempotency.ttl);
+        self
+    }
+
+    async fn issue(&self, job: IssueJob) -> Result<CredentialOrJWT, String> {
+        let options =
+            IssueOptions::try_from(job.options.unwrap_or_default()).map_err(|e| e.to_string())?;
+        let resolver = self.resolver_options.to_resolver();
+        let signer = pick_key(&self.keys, &options.ldp_options, &resolver)
+            .await
+            .ok_or_else(|| "Missing key".to_string())?;
+        issue_credential(job.credential, &options, signer.as_ref(), &resolver)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Get the result of a request: the recorded one for its idempotency key, or a new one.
+    async fn process(&self, payload: &[u8]) -> (String, Vec<u8>) {
+        let job: IssueJob = match serde_json::from_slice(payload) {
+            Ok(job) => job,
+            Err(err) => {
+                // Invalid request: it would fail again if redelivered.
+                let key = serde_json::from_slice::<serde_json::Value>(payload)
+                    .ok()
+                    .and_then(|request| {
+                        request["idempotencyKey"]
+                            .as_str()
+                            .map(|key| key.to_string())
+                    });
+                let result = JobResult {
+                    idempotency_key: key.clone(),
+                    credential: None,
+                    error: Some(format!("Invalid request: {}", err)),
+                };
+                let result = serde_json::to_vec(&result).unwrap_or_default();
+                return (key.unwrap_or_default(), result);
+            }
+        };
+        let key = job.idempotency_key.clone();
+        if let Some(result) = self.idempotency.get(&key).await {
+            return (key, result);
+        }
+        let result = match self.issue(job).await {
+            Ok(credential) => JobResult {
+                idempotency_key: Some(key.clone()),
+                credential: Some(credential),
+                error: None,
+            },
+            Err(error) => JobResult {
+                idempotency_key: Some(key.clone()),
+                credential: None,
+                error: Some(error),
+            },
+        };
+        let result = serde_json::to_vec(&result).unwrap_or_default();
+        self.idempotency.insert(&key, result.clone()).await;
+        (key, result)
+    }
+
+    async fn handle(&self, delivery: Delivery) -> Result<(), WorkerError> {
+        let (key, result) = self.process(&delivery.payload).await;
+        match self.broker.publish(&key, result).await {
+            Ok(()) => delivery.acker.ack().await,
+            Err(err) => {
+                eprintln!("Unable to publish result for {}: {}", key, err);
+                delivery.acker.nack().await
+            }
+        }
+    }
+
+    /// Receive and process requests until the subscription ends.
+    pub async fn run(self: Arc<Self>) -> Result<(), WorkerError> {
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        loop {
+            let permit = match permits.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return Err(WorkerError::Closed),
+            };
+            let delivery = self.broker.receive().await?;
+            let worker = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = worker.handle(delivery).await {
+                    eprintln!("Unable to acknowledge request: {}", err);
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Connect to the broker at a URL: `kafka://<servers>`, `amqp[s]://...` or `nats://...`.
+///
+/// `requests` and `results` are the topics, queues or subjects of requests and results; `group`
+/// is the consumer group (Kafka), consumer tag (AMQP) or durable consumer name (NATS).
+#[allow(unused_variables)]
+pub async fn connect(
+    url: &str,
+    requests: &str,
+    results: &str,
+    group: &str,
+    prefetch: usize,
+) -> Result<Arc<dyn Broker>, WorkerError> {
+    #[cfg(feature = "kafka")]
+    if let Some(servers) = url.strip_prefix("kafka://") {
+        return Ok(Arc::new(kafka::KafkaBroker::new(
+            servers, requests, results, group,
+        )?));
+    }
+    #[cfg(feature = "amqp")]
+    if url.starts_with("amqp://") || url.starts_with("amqps://") {
+        return Ok(Arc::new(
+            amqp::AMQPBroker::connect(url, requests, results, group, prefetch).await?,
+        ));
+    }
+    #[cfg(feature = "nats")]
+    if url.starts_with("nats://") || url.starts_with("tls://") {
+        return Ok(Arc::new(
+            nats::NATSBroker::connect(url, requests, results, group, prefetch).await?,
+        ));
+    }
+    Err(WorkerError::UnsupportedBroker(url.to_string()))
+}
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use rdkafka::config::ClientConfig;
+    use rdkafka::consumer::{Consumer, StreamConsumer};
+    use rdkafka::producer::{FutureProducer, FutureRecord};
+    use rdkafka::Message;
+
+    use super::{Acker, Broker, Delivery, WorkerError};
+
+    /// Offsets of the messages being processed, and the last processed, by partition.
+    ///
+    /// Kafka acknowledges messages by committing an offset, which covers all the earlier messages
+    /// of the partition: an offset is stored only once all the earlier messages are processed.
+    #[derive(Default)]
+    struct Offsets {
+        pending: HashMap<i32, BTreeSet<i64>>,
+        processed: HashMap<i32, i64>,
+    }
+
+    pub struct KafkaBroker {
+        consumer: Arc<StreamConsumer>,
+        producer: FutureProducer,
+        requests: String,
+        results: String,
+        offsets: Arc<Mutex<Offsets>>,
+    }
+
+    impl KafkaBroker {
+        pub fn new(
+            servers: &str,
+            requests: &str,
+            results: &str,
+            group: &str,
+        ) -> Result<Self, WorkerError> {
+            let consumer: StreamConsumer = ClientConfig::new()
+                .set("bootstrap.servers", servers)
+                .set("group.id", group)
+                .set("enable.auto.commit", "true")
+                .set("enable.auto.offset.store", "false")
+                .set("auto.offset.reset", "earliest")
+                .create()?;
+            consumer.subscribe(&[requests])?;
+            let producer: FutureProducer = ClientConfig::new()
+                .set("bootstrap.servers", servers)
+                .set("enable.idempotence", "true")
+                .create()?;
+            Ok(Self {
+                consumer: Arc::new(consumer),
+                producer,
+                requests: requests.to_string(),
+                results: results.to_string(),
+                offsets: Arc::new(Mutex::new(Offsets::default())),
+            })
+        }
+    }
+
+    struct KafkaAcker {
+        consumer: Arc<StreamConsumer>,
+        topic: String,
+        partition: i32,
+        offset: i64,
+        offsets: Arc<Mutex<Offsets>>,
+    }
+
+    #[async_trait]
+    impl Acker for KafkaAcker {
+        async fn ack(self: Box<Self>) -> Result<(), WorkerError> {
+            let commit = {
+                let mut offsets = self.offsets.lock().map_err(|_| WorkerError::Closed)?;
+                let Offsets { pending, processed } = &mut *offsets;
+                let pending = pending.entry(self.partition).or_default();
+                pending.remove(&self.offset);
+                let last = processed.entry(self.partition).or_insert(self.offset);
+                *last = (*last).max(self.offset);
+                // Messages before the first pending one are all processed.
+                match pending.iter().next() {
+                    Some(&first_pending) => first_pending - 1,
+                    None => *last,
+                }
+            };
+            if commit >= 0 {
+                self.consumer
+                    .store_offset(&self.topic, self.partition, commit)?;
+            }
+            Ok(())
+        }
+
+        async fn nack(self: Box<Self>) -> Result<(), WorkerError> {
+            // Leave the offset pending, so that it is not committed, and the message is
+            // delivered again after a restart or rebalance.
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Broker for KafkaBroker {
+        async fn receive(&self) -> Result<Delivery, WorkerError> {
+            let message = self.consumer.recv().await?;
+            let (partition, offset) = (message.partition(), message.offset());
+            let payload = message.payload().unwrap_or_default().to_vec();
+            drop(message);
+            self.offsets
+                .lock()
+                .map_err(|_| WorkerError::Closed)?
+                .pending
+                .entry(partition)
+                .or_default()
+                .insert(offset);
+            Ok(Delivery {
+                payload,
+                acker: Box::new(KafkaAcker {
+                    consumer: self.consumer.clone(),
+                    topic: self.requests.clone(),
+                    partition,
+                    offset,
+                    offsets: self.offsets.clone(),
+                }),
+            })
+        }
+
+        async fn publish(&self, key: &str, payload: Vec<u8>) -> Result<(), WorkerError> {
+            let record = FutureRecord::to(&self.results).key(key).payload(&payload);
+            self.producer
+                .send(record, Duration::from_secs(0))
+                .await
+                .map_err(|(err, _)| err)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "amqp")]
+mod amqp {
+    use async_trait::async_trait;
+    use futures_util::StreamExt;
+    use lapin::options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, BasicPublishOptions,
+        BasicQosOptions, ConfirmSelectOptions,
+    };
+    use lapin::types::FieldTable;
+    use lapin::{BasicProperties, Channel, Connection, ConnectionProperties, Consumer};
+    use tokio::sync::Mutex;
+
+    use super::{Acker, Broker, Delivery, WorkerError};
+
+    pub struct AMQPBroker {
+        channel: Channel,
+        consumer: Mutex<Consumer>,
+        results: String,
+    }
+
+    impl AMQPBroker {
+        pub async fn connect(
+            url: &str,
+            requests: &str,
+            results: &str,
+            tag: &str,
+            prefetch: usize,
+        ) -> Result<Self, WorkerError> {
+            let connection = Connection::connect(url, ConnectionProperties::default()).await?;
+            let channel = connection.create_channel().await?;
+            channel
+                .basic_qos(prefetch as u16, BasicQosOptions::default())
+                .await?;
+            // Publisher confirms, so that publishing returns once the broker has the result.
+            channel
+                .confirm_select(ConfirmSelectOptions::default())
+                .await?;
+            let consumer = channel
+                .basic_consume(
+                    requests,
+                    tag,
+                    BasicConsumeOptions::default(),
+                    FieldTable::default(),
+                )
+                .await?;
+            Ok(Self {
+                channel,
+                consumer: Mutex::new(consumer),
+                results: results.to_string(),
+            })
+        }
+    }
+
+    struct AMQPAcker(lapin::acker::Acker);
+
+    #[async_trait]
+    impl Acker for AMQPAcker {
+        async fn ack(self: Box<Self>) -> Result<(), WorkerError> {
+            Ok(self.0.ack(BasicAckOptions::default()).await?)
+        }
+
+        async fn nack(self: Box<Self>) -> Result<(), WorkerError> {
+            let options = BasicNackOptions {
+                requeue: true,
+                ..Default::default()
+            };
+            Ok(self.0.nack(options).await?)
+        }
+    }
+
+    #[async_trait]
+    impl Broker for AMQPBroker {
+        async fn receive(&self) -> Result<Delivery, WorkerError> {
+            let delivery = self
+                .consumer
+                .lock()
+                .await
+                .next()
+                .await
+                .ok_or(WorkerError::Closed)??;
+            Ok(Delivery {
+                payload: delivery.data,
+                acker: Box::new(AMQPAcker(delivery.acker)),
+            })
+        }
+
+        async fn publish(&self, _key: &str, payload: Vec<u8>) -> Result<(), WorkerError> {
+            self.channel
+                .basic_publish(
+                    "",
+                    &self.results,
+                    BasicPublishOptions::default(),
+                    &payload,
+                    BasicProperties::default().with_content_type("application/json".into()),
+                )
+                .await?
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use async_nats::jetstream::{self, consumer::pull, AckKind, Context};
+    use async_trait::async_trait;
+    use futures_util::StreamExt;
+    use tokio::sync::Mutex;
+
+    use super::{Acker, Broker, Delivery, WorkerError};
+
+    fn nats_error(err: impl std::fmt::Display) -> WorkerError {
+        WorkerError::NATS(err.to_string())
+    }
+
+    pub struct NATSBroker {
+        jetstream: Context,
+        messages: Mutex<pull::Stream>,
+        results: String,
+    }
+
+    impl NATSBroker {
+        /// Consume the JetStream stream that captures the `requests` subject, with a durable
+        /// pull consumer named `group`.
+        pub async fn connect(
+            url: &str,
+            requests: &str,
+            results: &str,
+            group: &str,
+            prefetch: usize,
+        ) -> Result<Self, WorkerError> {
+            let client = async_nats::connect(url).await.map_err(nats_error)?;
+            let jetstream = jetstream::new(client);
+            let stream_name = jetstream
+                .stream_by_subject(requests)
+                .await
+                .map_err(nats_error)?;
+            let stream = jetstream
+                .get_stream(stream_name)
+                .await
+                .map_err(nats_error)?;
+            let consumer = stream
+                .get_or_create_consumer(
+                    group,
+                    pull::Config {
+                        durable_name: Some(group.to_string()),
+                        filter_subject: requests.to_string(),
+                        max_ack_pending: prefetch as i64,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .map_err(nats_error)?;
+            let messages = consumer.messages().await.map_err(nats_error)?;
+            Ok(Self {
+                jetstream,
+                messages: Mutex::new(messages),
+                results: results.to_string(),
+            })
+        }
+    }
+
+    struct NATSAcker(jetstream::Message);
+
+    #[async_trait]
+    impl Acker for NATSAcker {
+        async fn ack(self: Box<Self>) -> Result<(), WorkerError> {
+            self.0.ack().await.map_err(nats_error)
+        }
+
+        async fn nack(self: Box<Self>) -> Result<(), WorkerError> {
+            self.0
+                .ack_with(AckKind::Nak(None))
+                .await
+                .map_err(nats_error)
+        }
+    }
+
+    #[async_trait]
+    impl Broker for NATSBroker {
+        async fn receive(&self) -> Result<Delivery, WorkerError> {
+            let message = self
+                .messages
+                .lock()
+                .await
+                .next()
+                .await
+                .ok_or(WorkerError::Closed)?
+                .map_err(nats_error)?;
+            Ok(Delivery {
+                payload: message.payload.to_vec(),
+                acker: Box::new(NATSAcker(message)),
+            })
+        }
+
+        async fn publish(&self, _key: &str, payload: Vec<u8>) -> Result<(), WorkerError> {
+            self.jetstream
+                .publish(self.results.clone(), payload.into())
+                .await
+                .map_err(nats_error)?
+                .await
+                .map_err(nats_error)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use didkit::JWK;
+    use tokio::sync::mpsc;
+
+    struct ChannelBroker {
+        requests: tokio::sync::Mutex<mpsc::Receiver<Vec<u8>>>,
+        results: mpsc::Sender<Vec<u8>>,
+    }
+
+    struct NoAcker;
+
+    #[async_trait]
+    impl Acker for NoAcker {
+        async fn ack(self: Box<Self>) -> Result<(), WorkerError> {
+            Ok(())
+        }
+        async fn nack(self: Box<Self>) -> Result<(), WorkerError> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Broker for ChannelBroker {
+        async fn receive(&self) -> Result<Delivery, WorkerError> {
+            let payload = self
+                .requests
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or(WorkerError::Closed)?;
+            Ok(Delivery {
+                payload,
+                acker: Box::new(NoAcker),
+            })
+        }
+
+        async fn publish(&self, _key: &str, payload: Vec<u8>) -> Result<(), WorkerError> {
+            self.results
+                .send(payload)
+                .await
+                .map_err(|_| WorkerError::Closed)
+        }
+    }
+
+    #[tokio::test]
+    async fn idempotent_results() {
+        let (request_tx, request_rx) = mpsc::channel(4);
+        let (result_tx, mut result_rx) = mpsc::channel(4);
+        let broker = Arc::new(ChannelBroker {
+            requests: tokio::sync::Mutex::new(request_rx),
+            results: result_tx,
+        });
+        let key = JWK::generate_ed25519().unwrap();
+        let worker = Arc::new(Worker::new(
+            broker,
+            vec![Arc::new(key) as Arc<dyn Signer>],
+            ResolverOptions::default(),
+            Duration::from_secs(60),
+            2,
+        ));
+        tokio::spawn(worker.clone().run());
+
+        let request = serde_json::to_vec(&serde_json::json!({
+            "idempotencyKey": "job-1",
+            "credential": {
+                "@context": "https://www.w3.org/2018/credentials/v1",
+                "type": "VerifiableCredential",
+                "issuer": "did:example:issuer",
+                "issuanceDate": "2021-01-01T00:00:00Z",
+                "credentialSubject": {}
+            },
+            "options": { "proofFormat": "jwt" }
+        }))
+        .unwrap();
+        request_tx.send(request.clone()).await.unwrap();
+        let first = result_rx.recv().await.unwrap();
+        assert_eq!(worker.idempotency.get("job-1").await, Some(first.clone()));
+        request_tx.send(request).await.unwrap();
+        let second = result_rx.recv().await.unwrap();
+        assert_eq!(first, second);
+
+        request_tx.send(b"{}".to_vec()).await.unwrap();
+        let invalid: JobResult = serde_json::from_slice(&result_rx.recv().await.unwrap()).unwrap();
+        assert!(invalid.error.unwrap().starts_with("Invalid request"));
+    }
+}
@@ -1,10 +1,13 @@
 use std::str::FromStr;
+use std::sync::Arc;
 
 use didkit::{Document, JWK};
 use didkit_cli::opts::ResolverOptions;
-use didkit_http::DIDKitHTTPMakeSvc;
+use didkit_http::concurrency::ConcurrencyLimits;
+use didkit_http::templates::{IssuanceTemplate, Templates};
 use didkit_http::VerifyCredentialResponse;
 use didkit_http::VerifyPresentationResponse;
+use didkit_http::{DIDKitHTTPMakeSvc, DIDKitHTTPSvc};
 use ssi::did_resolve::{ResolutionResult, TYPE_DID_LD_JSON, TYPE_DID_RESOLUTION};
 
 use hyper::body::Buf;
@@ -54,9 +57,12 @@ fn serve(other_keys: Option<Vec<JWK>>) -> (String, impl FnOnce() -> ()) {
         keys.append(&mut other_keys);
     }
     let resolver_options = ResolverOptions::default();
-    let makesvc = DIDKitHTTPMakeSvc::new(keys, resolver_options);
+    serve_svc(DIDKitHTTPSvc::new(keys, resolver_options))
+}
+
+fn serve_svc(svc: DIDKitHTTPSvc) -> (String, impl FnOnce() -> ()) {
     let addr = ([127, 0, 0, 1], 0).into();
-    let server = Server::bind(&addr).serve(makesvc);
+    let server = Server::bind(&addr).serve(DIDKitHTTPMakeSvc::new(svc));
     let url = "http://".to_string() + &server.local_addr().to_string();
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
     let shutdown = || shutdown_tx.send(()).unwrap();
@@ -263,6 +269,55 @@ async fn credential_issue_verify_other_key() {
     shutdown()
 }
 
+#[tokio::test]
+async fn credential_issue_verify_multibase_proof() {
+    // Ed25519Signature2020 proofs have a multibase proofValue instead of a JWS.
+    let (base, shutdown) = serve(None);
+    let client = Client::builder().build_http::<Body>();
+    let uri = Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap();
+    let mut cred_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    cred_req["credential"]["@context"] = json!([
+        "https://www.w3.org/2018/credentials/v1",
+        "https://w3id.org/security/suites/ed25519-2020/v1"
+    ]);
+    cred_req["options"]["type"] = json!("Ed25519Signature2020");
+    let body = Body::from(serde_json::to_string(&cred_req).unwrap());
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(body)
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(vc["proof"]["type"], "Ed25519Signature2020");
+    assert!(vc["proof"]["jws"].is_null());
+    assert!(vc["proof"]["proofValue"].as_str().unwrap().starts_with('z'));
+
+    let uri = Uri::from_str(&(base.to_string() + "/credentials/verify")).unwrap();
+    let verify_cred_req = json!({
+      "verifiableCredential": vc,
+      "options": {
+          "verificationMethod": VERIFICATION_METHOD,
+          "proofPurpose": "assertionMethod",
+          "domain": "example.net",
+          "challenge": "c16239ed-9775-4cf5-8f7a-65fc07e0d379"
+      }
+    });
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(Body::from(serde_json::to_string(&verify_cred_req).unwrap()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let response: VerifyCredentialResponse = serde_json::from_reader(body_reader).unwrap();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    shutdown()
+}
+
 #[tokio::test]
 async fn invalid_input() {
     let (base, shutdown) = serve(None);
@@ -276,6 +331,45 @@ async fn invalid_input() {
         .unwrap();
     let resp = client.request(req).await.unwrap();
     assert_eq!(resp.status(), 400);
+    assert_eq!(
+        resp.headers().get(CONTENT_TYPE).unwrap(),
+        "application/problem+json"
+    );
+    let body = hyper::body::aggregate(resp).await.unwrap().reader();
+    let error: Value = serde_json::from_reader(body).unwrap();
+    assert_eq!(error["code"], 1);
+    assert_eq!(error["kind"], "ssi");
+    assert!(error["message"].is_string());
+    assert_eq!(error["status"], 400);
+    assert_eq!(error["errors"][0]["pointer"], "#/credential");
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn proof_format_mismatch() {
+    let (base, shutdown) = serve(None);
+    let client = Client::builder().build_http::<Body>();
+
+    let uri = Uri::from_str(&(base + "/credentials/verify")).unwrap();
+    let verify_cred_req = json!({
+        "verifiableCredential": "eyJhbGciOiJFZERTQSJ9.e30.c2ln",
+        "options": {"proofFormat": "ldp"}
+    });
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(Body::from(verify_cred_req.to_string()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 400);
+    let body = hyper::body::aggregate(resp).await.unwrap().reader();
+    let error: Value = serde_json::from_reader(body).unwrap();
+    assert_eq!(error["kind"], "proofFormatMismatch");
+    assert_eq!(
+        error["message"],
+        "Credential/proof format mismatch. Proof format: ldp, credential: \"eyJhbGciOiJFZERTQSJ9.e30.c2ln\""
+    );
 
     shutdown();
 }
@@ -379,5 +473,793 @@ async fn resolve_dereference() {
     let vm: Value = serde_json::from_reader(body_reader).unwrap();
     eprintln!("vm {:?}", vm);
 
+    // Resolution errors have standard codes
+    let uri = Uri::from_str(&format!("{}/identifiers/did:unsupported:x", base)).unwrap();
+    let req = Request::builder()
+        .method("GET")
+        .header(ACCEPT, TYPE_DID_RESOLUTION)
+        .uri(uri)
+        .body(Body::default())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 501);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let result: ResolutionResult = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(
+        result.did_resolution_metadata.unwrap().error.unwrap(),
+        "methodNotSupported"
+    );
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn did_auth_login() {
+    use didkit_http::nonce::MemoryNonceStore;
+    use didkit_http::{DIDAuthConfig, DIDAuthResponse};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let svc = DIDKitHTTPSvc::new(vec![key], ResolverOptions::default())
+        .with_nonce_store(Arc::new(MemoryNonceStore::new(Duration::from_secs(60))))
+        .with_did_auth(DIDAuthConfig {
+            verification_method: VERIFICATION_METHOD.to_string(),
+            domain: Some("example.org".to_string()),
+            session_ttl: Duration::from_secs(600),
+        });
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+    let post = |path: &str, body: String| {
+        Request::builder()
+            .method("POST")
+            .uri(Uri::from_str(&(base.to_string() + path)).unwrap())
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let resp = client
+        .request(post("/auth/challenge", String::new()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let request: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(request["query"][0]["type"], "DIDAuthentication");
+    let challenge = request["challenge"].as_str().unwrap().to_string();
+
+    // DIDAuth presentation, without credentials
+    let prove_req = json!({
+      "presentation": {
+          "@context": ["https://www.w3.org/2018/credentials/v1"],
+          "type": ["VerifiablePresentation"],
+          "holder": DID_KEY
+      },
+      "options": {
+          "verificationMethod": VERIFICATION_METHOD,
+          "proofPurpose": "authentication",
+          "domain": "example.org",
+          "challenge": challenge
+      }
+    });
+    let resp = client
+        .request(post("/credentials/prove", prove_req.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vp: Value = serde_json::from_reader(body_reader).unwrap();
+
+    let auth_req = json!({ "verifiablePresentation": vp, "challenge": challenge }).to_string();
+    let resp = client
+        .request(post("/auth/verify", auth_req.clone()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let auth: DIDAuthResponse = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(auth.holder, DID_KEY);
+
+    // The challenge can be used only once.
+    let resp = client
+        .request(post("/auth/verify", auth_req))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let req = Request::builder()
+        .uri(Uri::from_str(&(base.to_string() + "/auth/session")).unwrap())
+        .header("Authorization", format!("Bearer {}", auth.token))
+        .body(Body::empty())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let claims: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(claims["sub"], DID_KEY);
+    assert_eq!(claims["aud"], "example.org");
+
     shutdown();
 }
+
+#[tokio::test]
+async fn verify_localized() {
+    use didkit::i18n::{Catalog, Catalogs};
+    use std::sync::Arc;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let mut catalogs = Catalogs::new();
+    catalogs.insert(Catalog::from_json("fr", r#"{"check.proof": "Preuve"}"#).unwrap());
+    let svc =
+        DIDKitHTTPSvc::new(vec![key], ResolverOptions::default()).with_catalogs(Arc::new(catalogs));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+
+    let req = Request::builder()
+        .method("POST")
+        .uri(Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap())
+        .body(Body::from(ISSUE_CRED_REQ))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+
+    let verify_cred_req = json!({
+      "verifiableCredential": vc,
+      "options": {
+          "verificationMethod": VERIFICATION_METHOD,
+          "proofPurpose": "assertionMethod",
+          "domain": "example.net",
+          "challenge": "c16239ed-9775-4cf5-8f7a-65fc07e0d379"
+      }
+    });
+    let req = Request::builder()
+        .method("POST")
+        .uri(Uri::from_str(&(base.to_string() + "/credentials/verify")).unwrap())
+        .header("Accept-Language", "fr-CH, fr;q=0.9, en;q=0.8")
+        .body(Body::from(verify_cred_req.to_string()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["Content-Language"], "fr");
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let response: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(response["checks"], json!(["proof"]));
+    assert_eq!(response["localized"]["checks"], json!(["Preuve"]));
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn issue_discover_verification_method() {
+    let (base, shutdown) = serve(None);
+    let client = Client::builder().build_http::<Body>();
+    let uri = Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap();
+    let mut issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    issue_req["options"] = json!({ "proofPurpose": "assertionMethod" });
+    let req = Request::builder()
+        .method("POST")
+        .uri(&uri)
+        .body(Body::from(issue_req.to_string()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(vc["proof"]["verificationMethod"], VERIFICATION_METHOD);
+
+    // No key of the server matches the issuer's DID document.
+    issue_req["credential"]["issuer"] = json!(DID_KEY_P256);
+    let req = Request::builder()
+        .method("POST")
+        .uri(&uri)
+        .body(Body::from(issue_req.to_string()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 400);
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn issue_dry_run() {
+    let (base, shutdown) = serve(None);
+    let client = Client::builder().build_http::<Body>();
+    let uri = Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap();
+    let mut issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    issue_req["options"]["dryRun"] = json!(true);
+    let req = Request::builder()
+        .method("POST")
+        .uri(&uri)
+        .body(Body::from(issue_req.to_string()))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(vc["proof"]["verificationMethod"], VERIFICATION_METHOD);
+    // The proof has a placeholder signature, of zeros.
+    let placeholder = base64::encode_config([0u8; 64], base64::URL_SAFE_NO_PAD);
+    let jws = vc["proof"]["jws"].as_str().unwrap();
+    assert!(jws.ends_with(&format!("..{}", placeholder)));
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn issue_template() {
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    let mut credential = issue_req["credential"].clone();
+    credential
+        .as_object_mut()
+        .unwrap()
+        .remove("credentialSubject");
+    let template: IssuanceTemplate = serde_json::from_value(json!({
+        "credential": credential,
+        "options": { "verificationMethod": VERIFICATION_METHOD },
+        "allowedFields": ["/credential/credentialSubject"],
+    }))
+    .unwrap();
+    let mut templates = Templates::new();
+    templates.insert("membership".to_string(), template);
+    let svc = DIDKitHTTPSvc::new(vec![key], ResolverOptions::default())
+        .with_templates(Arc::new(templates));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+    let issue = |query: &str, body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri(Uri::from_str(&format!("{}/credentials/issue?{}", base, query)).unwrap())
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let subject = json!({ "credential": { "credentialSubject": { "id": DID_KEY } } });
+    let resp = client
+        .request(issue("template=membership", subject.clone()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(vc["issuer"], DID_KEY);
+    assert_eq!(vc["credentialSubject"]["id"], DID_KEY);
+    assert_eq!(vc["proof"]["verificationMethod"], VERIFICATION_METHOD);
+
+    // The template does not allow overriding the issuer.
+    let override_issuer = json!({ "credential": { "issuer": DID_KEY_P256 } });
+    let resp = client
+        .request(issue("template=membership", override_issuer))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let problem: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(problem["errors"][0]["pointer"], "#/credential/issuer");
+
+    let resp = client
+        .request(issue("template=unknown", subject))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn openapi_document() {
+    let (base, shutdown) =
+        serve_svc(DIDKitHTTPSvc::new(vec![], ResolverOptions::default()).with_swagger_ui());
+    let client = Client::builder().build_http::<Body>();
+
+    let uri = Uri::from_str(&(base.to_string() + "/openapi.json")).unwrap();
+    let resp = client.get(uri).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let document: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(document["openapi"], "3.1.0");
+    assert!(document["paths"]["/credentials/verify"]["post"].is_object());
+    assert!(document["components"]["schemas"]["VerifyCredentialRequest"].is_object());
+
+    let uri = Uri::from_str(&(base.to_string() + "/docs")).unwrap();
+    let resp = client.get(uri).await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn request_timeout() {
+    // DID resolver that accepts connections but never responds
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let resolver_url = format!("http://{}/1.0/identifiers/", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let _connections: Vec<_> = listener.incoming().collect();
+    });
+    let resolver_options = ResolverOptions {
+        did_resolver: Some(didkit::HTTPDIDResolver::new(&resolver_url)),
+        ..Default::default()
+    };
+    let svc = DIDKitHTTPSvc::new(vec![], resolver_options)
+        .with_request_timeout(std::time::Duration::from_millis(500));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+
+    let uri = Uri::from_str(&(base + "/identifiers/did:example:slow")).unwrap();
+    let resp = client.get(uri).await.unwrap();
+    assert_eq!(resp.status(), 504);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let error: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(error["kind"], "cancelled");
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn base_url() {
+    let base_url = "https://example.com/didkit".parse().unwrap();
+    let svc = DIDKitHTTPSvc::new(vec![], ResolverOptions::default())
+        .with_swagger_ui()
+        .with_base_url(Arc::new(base_url));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+
+    let uri = Uri::from_str(&(base.to_string() + "/didkit/openapi.json")).unwrap();
+    let resp = client.get(uri).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let document: Value = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(document["servers"][0]["url"], "https://example.com/didkit");
+
+    // The proxy may also strip the prefix.
+    let uri = Uri::from_str(&(base.to_string() + "/docs")).unwrap();
+    let resp = client.get(uri).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = hyper::body::to_bytes(resp).await.unwrap();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("\"/didkit/openapi.json\""));
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn load_shedding() {
+    // DID resolver that accepts connections but never responds
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let resolver_url = format!("http://{}/1.0/identifiers/", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let _connections: Vec<_> = listener.incoming().collect();
+    });
+    let resolver_options = ResolverOptions {
+        did_resolver: Some(didkit::HTTPDIDResolver::new(&resolver_url)),
+        ..Default::default()
+    };
+    let limits = ConcurrencyLimits::new()
+        .with_max_verifications(1, 0)
+        .with_retry_after(std::time::Duration::from_secs(5));
+    let svc = DIDKitHTTPSvc::new(vec![], resolver_options)
+        .with_concurrency_limits(Arc::new(limits))
+        .with_request_timeout(std::time::Duration::from_secs(2));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+
+    // Verifying a credential of the hanging resolver's DID holds the only slot.
+    let body = json!({
+        "verifiableCredential": {
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:slow",
+            "issuanceDate": "2020-11-18T20:47:16Z",
+            "credentialSubject": { "id": "did:example:subject" },
+            "proof": {
+                "type": "Ed25519Signature2018",
+                "created": "2020-11-18T20:50:10Z",
+                "verificationMethod": "did:example:slow#key",
+                "proofPurpose": "assertionMethod",
+                "jws": "eyJhbGciOiJFZERTQSIsImNyaXQiOlsiYjY0Il0sImI2NCI6ZmFsc2V9..c2ln"
+            }
+        }
+    });
+    let verify = || {
+        Request::builder()
+            .method("POST")
+            .uri(base.to_string() + "/credentials/verify")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let first = tokio::spawn(client.request(verify()));
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+    let resp = client.request(verify()).await.unwrap();
+    assert_eq!(resp.status(), 503);
+    assert_eq!(resp.headers()["Retry-After"], "5");
+
+    let resp = first.await.unwrap().unwrap();
+    assert_eq!(resp.status(), 504);
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn canonical_json() {
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let svc = DIDKitHTTPSvc::new(vec![key], ResolverOptions::default()).with_canonical_json();
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+
+    let uri = Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap();
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(Body::from(ISSUE_CRED_REQ))
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), 201);
+    let body = hyper::body::to_bytes(resp).await.unwrap();
+    let vc: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body, didkit::canonical_json::jcs(&vc));
+
+    // Verification results are the same, byte for byte, from one request to the next.
+    let verify_cred_req = json!({
+        "verifiableCredential": vc,
+        "options": { "proofPurpose": "assertionMethod" }
+    });
+    let mut results = Vec::new();
+    for _ in 0..2 {
+        let uri = Uri::from_str(&(base.to_string() + "/credentials/verify")).unwrap();
+        let req = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(verify_cred_req.to_string()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        results.push(hyper::body::to_bytes(resp).await.unwrap());
+    }
+    assert_eq!(results[0], results[1]);
+    let result: Value = serde_json::from_slice(&results[0]).unwrap();
+    assert_eq!(
+        results[0],
+        didkit::canonical_json::to_string(&result).unwrap()
+    );
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn proof_of_work() {
+    use didkit_http::nonce::{MemoryNonceStore, NonceStore};
+    use didkit_http::pow::{self, ProofOfWork, POW_HEADER};
+    use didkit_http::PowChallengeResponse;
+    use std::time::Duration;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let nonce_store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+    let pow_store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+    let svc = DIDKitHTTPSvc::new(vec![key], ResolverOptions::default())
+        .with_nonce_store(nonce_store.clone())
+        .with_proof_of_work(Arc::new(ProofOfWork::new(pow_store, 8)));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+    let post = |path: &str, body: String| {
+        Request::builder()
+            .method("POST")
+            .uri(Uri::from_str(&(base.to_string() + path)).unwrap())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // Issuance does not require a proof of work.
+    let resp = client
+        .request(post("/credentials/issue", ISSUE_CRED_REQ.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vc: Value = serde_json::from_reader(body_reader).unwrap();
+    let verify_cred_req = json!({
+        "verifiableCredential": vc,
+        "options": { "proofPurpose": "assertionMethod" }
+    })
+    .to_string();
+
+    let resp = client
+        .request(post("/credentials/verify", verify_cred_req.clone()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+
+    let resp = client
+        .request(post("/pow/challenges", String::new()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let challenge: PowChallengeResponse = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(challenge.difficulty, 8);
+    let solution = pow::solve(&challenge.challenge, challenge.difficulty);
+    let header = format!("{}:{}", challenge.challenge, solution);
+    // The challenge is accepted once.
+    for expected_status in [200, 403] {
+        let mut req = post("/credentials/verify", verify_cred_req.clone());
+        req.headers_mut()
+            .insert(POW_HEADER, header.parse().unwrap());
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), expected_status);
+    }
+    // Proof of work challenges are not challenges of presentations.
+    assert!(!nonce_store.consume(&challenge.challenge).await.unwrap());
+
+    shutdown();
+}
+
+#[tokio::test]
+async fn issue_key_attestation() {
+    use didkit::key_attestation::KeyAttestationPolicy;
+    use didkit_http::nonce::{MemoryNonceStore, NonceStore};
+    use std::time::Duration;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let policy = Arc::new(KeyAttestationPolicy::new());
+    let nonce_store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+    let svc = DIDKitHTTPSvc::new(vec![key.clone()], ResolverOptions::default())
+        .with_nonce_store(nonce_store.clone())
+        .with_key_attestation(policy.clone());
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+    let post = |base: &str, body: String| {
+        Request::builder()
+            .method("POST")
+            .uri(Uri::from_str(&(base.to_string() + "/credentials/issue")).unwrap())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let resp = client
+        .request(post(&base, ISSUE_CRED_REQ.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let message: String = serde_json::from_reader(body_reader).unwrap();
+    assert_eq!(message, "Missing key attestation");
+
+    // An invalid attestation does not use up its challenge.
+    let challenge = nonce_store.create().await.unwrap();
+    let mut issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    issue_req["keyAttestation"] = json!({
+        "format": "packed",
+        "challenge": challenge,
+        "attestationObject": "oA",
+        "clientDataJSON": "e30"
+    });
+    let resp = client
+        .request(post(&base, issue_req.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    assert!(nonce_store.consume(&challenge).await.unwrap());
+    shutdown();
+
+    // Challenges of key attestations cannot be checked without a nonce store.
+    let svc =
+        DIDKitHTTPSvc::new(vec![key], ResolverOptions::default()).with_key_attestation(policy);
+    let (base, shutdown) = serve_svc(svc);
+    let resp = client
+        .request(post(&base, issue_req.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 500);
+    shutdown();
+}
+
+#[tokio::test]
+async fn presentation_challenge() {
+    use didkit_http::nonce::MemoryNonceStore;
+    use didkit_http::ChallengeResponse;
+    use std::time::Duration;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let nonce_store = MemoryNonceStore::new(Duration::from_secs(60)).with_capacity(1);
+    let svc = DIDKitHTTPSvc::new(vec![key], ResolverOptions::default())
+        .with_nonce_store(Arc::new(nonce_store));
+    let (base, shutdown) = serve_svc(svc);
+    let client = Client::builder().build_http::<Body>();
+    let post = |path: &str, body: String| {
+        Request::builder()
+            .method("POST")
+            .uri(Uri::from_str(&(base.to_string() + path)).unwrap())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let resp = client
+        .request(post("/challenges", String::new()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let challenge: ChallengeResponse = serde_json::from_reader(body_reader).unwrap();
+    // The store is full until the challenge is used.
+    let resp = client
+        .request(post("/challenges", String::new()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 503);
+
+    let prove_req = json!({
+      "presentation": {
+          "@context": ["https://www.w3.org/2018/credentials/v1"],
+          "type": ["VerifiablePresentation"],
+          "holder": DID_KEY
+      },
+      "options": {
+          "verificationMethod": VERIFICATION_METHOD,
+          "proofPurpose": "authentication",
+          "challenge": challenge.challenge
+      }
+    });
+    let resp = client
+        .request(post("/credentials/prove", prove_req.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let vp: Value = serde_json::from_reader(body_reader).unwrap();
+    let verify_req = |vp: &Value| {
+        json!({
+          "verifiablePresentation": vp,
+          "options": {
+              "proofPurpose": "authentication",
+              "challenge": challenge.challenge
+          }
+        })
+        .to_string()
+    };
+
+    // A presentation that does not verify leaves the challenge for the holder.
+    let mut forged = vp.clone();
+    forged["holder"] = json!(DID_KEY_P256);
+    let resp = client
+        .request(post("/presentations/verify", verify_req(&forged)))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body_reader = hyper::body::aggregate(resp).await.unwrap().reader();
+    let response: VerifyPresentationResponse = serde_json::from_reader(body_reader).unwrap();
+    assert!(!response.errors.is_empty());
+
+    for expected_status in [200, 400] {
+        let resp = client
+            .request(post("/presentations/verify", verify_req(&vp)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), expected_status);
+    }
+    let resp = client
+        .request(post("/challenges", String::new()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+
+    shutdown();
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_issue_verify() {
+    use didkit_http::grpc::proto::didkit_server::Didkit;
+    use didkit_http::grpc::proto::{IssueRequest, VerifyRequest};
+    use didkit_http::grpc::DIDKitGrpcSvc;
+
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let http = DIDKitHTTPSvc::new(vec![key.clone()], ResolverOptions::default());
+    let svc = DIDKitGrpcSvc::new(vec![key], http);
+    let issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    let request = IssueRequest {
+        document: issue_req["credential"].to_string(),
+        options: json!({ "proofPurpose": "assertionMethod" }).to_string(),
+        ..Default::default()
+    };
+    let response = svc
+        .issue_credential(tonic::Request::new(request))
+        .await
+        .unwrap()
+        .into_inner();
+    // Without a credential store, the credential is not recorded.
+    assert!(response.id.is_empty());
+    let vc: Value = serde_json::from_str(&response.document).unwrap();
+    assert_eq!(vc["proof"]["verificationMethod"], VERIFICATION_METHOD);
+
+    let verify_req = |vc: &Value| VerifyRequest {
+        document: vc.to_string(),
+        options: json!({ "proofPurpose": "assertionMethod" }).to_string(),
+        id: "1".to_string(),
+        ..Default::default()
+    };
+    let response = svc
+        .verify_credential(tonic::Request::new(verify_req(&vc)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.id, "1");
+    assert!(response.verified, "{:?}", response.errors);
+
+    let mut tampered = vc.clone();
+    tampered["credentialSubject"]["id"] = json!(DID_KEY);
+    let response = svc
+        .verify_credential(tonic::Request::new(verify_req(&tampered)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!response.verified);
+    assert!(!response.errors.is_empty());
+
+    // No key of the server matches the issuer's DID document.
+    let mut credential = issue_req["credential"].clone();
+    credential["issuer"] = json!(DID_KEY_P256);
+    let request = IssueRequest {
+        document: credential.to_string(),
+        ..Default::default()
+    };
+    let status = svc
+        .issue_credential(tonic::Request::new(request))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[cfg(all(feature = "grpc", feature = "store"))]
+#[tokio::test]
+async fn grpc_issue_lifecycle() {
+    use didkit::store::CredentialStore;
+    use didkit_http::grpc::proto::didkit_server::Didkit;
+    use didkit_http::grpc::proto::IssueRequest;
+    use didkit_http::grpc::DIDKitGrpcSvc;
+    use didkit_http::lifecycle::LifecycleConfig;
+
+    const STATUS_LIST: &str = "https://example.net/status/1";
+    let key: JWK = serde_json::from_str(DID_KEY_JSON).unwrap();
+    let credential_store = Arc::new(CredentialStore::connect("sqlite::memory:").await.unwrap());
+    let lifecycle = LifecycleConfig::new(STATUS_LIST.to_string(), DID_KEY.to_string());
+    let http = DIDKitHTTPSvc::new(vec![key.clone()], ResolverOptions::default())
+        .with_credential_store(credential_store.clone())
+        .with_lifecycle(Arc::new(lifecycle));
+    let svc = DIDKitGrpcSvc::new(vec![key], http);
+    let issue_req: Value = serde_json::from_str(ISSUE_CRED_REQ).unwrap();
+    let request = |options: Value| IssueRequest {
+        document: issue_req["credential"].to_string(),
+        options: options.to_string(),
+        ..Default::default()
+    };
+
+    let response = svc
+        .issue_credential(tonic::Request::new(request(json!({}))))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!response.id.is_empty());
+    let vc: Value = serde_json::from_str(&response.document).unwrap();
+    let status_id = vc["credentialStatus"]["id"].as_str().unwrap();
+    assert!(status_id.starts_with(&format!("{}#", STATUS_LIST)));
+    let record = credential_store.get(&response.id).await.unwrap().unwrap();
+    assert_eq!(record.status, "issued");
+    assert_eq!(record.credential, vc);
+
+    // A dry run is not recorded.
+    let response = svc
+        .issue_credential(tonic::Request::new(request(json!({ "dryRun": true }))))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.id.is_empty());
+    let vc: Value = serde_json::from_str(&response.document).unwrap();
+    assert!(vc.get("credentialStatus").is_none());
+}
@@ -1,5 +1,6 @@
 #include <assert.h>
 #include <err.h>
+#include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
@@ -7,6 +8,18 @@
 
 #include "didkit.h"
 
+static int sign_calls = 0;
+
+// Signing callback that refuses to sign
+int refuse_sign(void *context, const uint8_t *signing_input, size_t signing_input_len,
+        const char *algorithm, uint8_t *signature, size_t *signature_len) {
+    assert(context == &sign_calls);
+    assert(signing_input_len > 0);
+    assert(strcmp(algorithm, "EdDSA") == 0);
+    sign_calls++;
+    return 1;
+}
+
 int main() {
     const char *version = didkit_get_version();
     assert(version != NULL);
@@ -20,6 +33,9 @@ int main() {
     assert(strlen(error_msg) > 0);
     int error_code = didkit_error_code();
     assert(error_code != 0);
+    const char *error_json = didkit_error_json();
+    assert(error_json != NULL);
+    assert(strstr(error_json, "\"code\":") != NULL);
 
     // Generate key
     const char *key = didkit_vc_generate_ed25519_key();
@@ -62,6 +78,14 @@ int main() {
     const char *vc_jwt = didkit_vc_issue_credential(credential, vc_options, key);
     if (vc_jwt == NULL) errx(1, "issue credential (JWT): %s", didkit_error_message());
 
+    // Issue Credential with a signing callback
+    const char *vc_cb = didkit_vc_issue_credential_with_signer(credential, vc_options, key,
+            refuse_sign, &sign_calls);
+    assert(vc_cb == NULL);
+    assert(sign_calls == 1);
+    if (strstr(didkit_error_message(), "callback") == NULL)
+        errx(1, "issue credential with signer: %s", didkit_error_message());
+
     // Verify Credential (LDP)
     const char *vc_verify_options = "{\"proofPurpose\": \"assertionMethod\"}";
     const char *res = didkit_vc_verify_credential(vc_ldp, vc_verify_options);
@@ -0,0 +1,51 @@
+using System.Collections.Generic;
+using System.Text.Json;
+using System.Threading.Tasks;
+using Xunit;
+
+namespace SpruceID.DIDKit.Tests
+{
+    public class DIDKitTests
+    {
+        [Fact]
+        public void Version()
+        {
+            Assert.NotEmpty(DIDKit.Version);
+        }
+
+        [Fact]
+        public void Errors()
+        {
+            var e = Assert.Throws<DIDKitException>(() => DIDKit.IssuePresentation("{}", "{}", "{}"));
+            Assert.NotEqual(0, e.Code);
+            Assert.NotEmpty(e.Message);
+        }
+
+        [Fact]
+        public async Task IssueVerifyCredential()
+        {
+            var key = DIDKit.GenerateEd25519Key();
+            var did = DIDKit.KeyToDID("key", key);
+            var verificationMethod = await DIDKit.KeyToVerificationMethodAsync("key", key);
+            // An anonymous type cannot have an "@context" property.
+            var credential = JsonSerializer.Serialize(new Dictionary<string, object>
+            {
+                ["@context"] = "https://www.w3.org/2018/credentials/v1",
+                ["id"] = "http://example.org/credentials/3731",
+                ["type"] = new[] { "VerifiableCredential" },
+                ["issuer"] = did,
+                ["issuanceDate"] = "2020-08-19T21:41:50Z",
+                ["credentialSubject"] = new { id = "did:example:d23dd687a7dc6787646f2eb98d0" },
+            });
+            var options = JsonSerializer.Serialize(new
+            {
+                proofPurpose = "assertionMethod",
+                verificationMethod,
+            });
+            var vc = await DIDKit.IssueCredentialAsync(credential, options, key);
+            var result = await DIDKit.VerifyCredentialAsync(vc, "{\"proofPurpose\":\"assertionMethod\"}");
+            using var report = JsonDocument.Parse(result);
+            Assert.Equal(0, report.RootElement.GetProperty("errors").GetArrayLength());
+        }
+    }
+}
@@ -0,0 +1,105 @@
+using System;
+using System.Threading;
+using System.Threading.Tasks;
+
+namespace SpruceID.DIDKit
+{
+    /// <summary>
+    /// DIDKit functions. Credentials, presentations, keys and options are JSON strings, as in the
+    /// C API.
+    /// </summary>
+    /// <remarks>
+    /// The synchronous methods block the calling thread while resolving DIDs. The <c>Async</c>
+    /// methods run them on the thread pool; the native call itself cannot be interrupted, so
+    /// cancellation only takes effect before it starts.
+    /// </remarks>
+    public static class DIDKit
+    {
+        /// <summary>Take ownership of a string returned by a DIDKit function.</summary>
+        private static string Take(IntPtr result)
+        {
+            if (result == IntPtr.Zero)
+            {
+                throw DIDKitException.Last();
+            }
+            try
+            {
+                return NativeMethods.FromUtf8(result)!;
+            }
+            finally
+            {
+                NativeMethods.didkit_free_string(result);
+            }
+        }
+
+        private static byte[] Utf8(string str) => NativeMethods.ToUtf8(str);
+
+        private static Task<string> Run(Func<string> function, CancellationToken cancellationToken) =>
+            Task.Run(function, cancellationToken);
+
+        public static string Version =>
+            NativeMethods.FromUtf8(NativeMethods.didkit_get_version()) ?? "";
+
+        public static string GenerateEd25519Key() =>
+            Take(NativeMethods.didkit_vc_generate_ed25519_key());
+
+        public static string KeyToDID(string methodPattern, string jwk) =>
+            Take(NativeMethods.didkit_key_to_did(Utf8(methodPattern), Utf8(jwk)));
+
+        public static string KeyToVerificationMethod(string methodPattern, string jwk) =>
+            Take(NativeMethods.didkit_key_to_verification_method(Utf8(methodPattern), Utf8(jwk)));
+
+        public static string IssueCredential(string credential, string options, string key) =>
+            Take(NativeMethods.didkit_vc_issue_credential(Utf8(credential), Utf8(options), Utf8(key)));
+
+        public static string VerifyCredential(string credential, string options) =>
+            Take(NativeMethods.didkit_vc_verify_credential(Utf8(credential), Utf8(options)));
+
+        public static string IssuePresentation(string presentation, string options, string key) =>
+            Take(NativeMethods.didkit_vc_issue_presentation(Utf8(presentation), Utf8(options), Utf8(key)));
+
+        public static string DIDAuth(string holder, string options, string key) =>
+            Take(NativeMethods.didkit_did_auth(Utf8(holder), Utf8(options), Utf8(key)));
+
+        public static string VerifyPresentation(string presentation, string options) =>
+            Take(NativeMethods.didkit_vc_verify_presentation(Utf8(presentation), Utf8(options)));
+
+        public static string ResolveDID(string did, string inputMetadata = "{}") =>
+            Take(NativeMethods.didkit_did_resolve(Utf8(did), Utf8(inputMetadata)));
+
+        public static string DereferenceDIDURL(string didUrl, string inputMetadata = "{}") =>
+            Take(NativeMethods.didkit_did_url_dereference(Utf8(didUrl), Utf8(inputMetadata)));
+
+        public static Task<string> KeyToVerificationMethodAsync(string methodPattern, string jwk,
+            CancellationToken cancellationToken = default) =>
+            Run(() => KeyToVerificationMethod(methodPattern, jwk), cancellationToken);
+
+        public static Task<string> IssueCredentialAsync(string credential, string options, string key,
+            CancellationToken cancellationToken = default) =>
+            Run(() => IssueCredential(credential, options, key), cancellationToken);
+
+        public static Task<string> VerifyCredentialAsync(string credential, string options,
+            CancellationToken cancellationToken = default) =>
+            Run(() => VerifyCredential(credential, options), cancellationToken);
+
+        public static Task<string> IssuePresentationAsync(string presentation, string options, string key,
+            CancellationToken cancellationToken = default) =>
+            Run(() => IssuePresentation(presentation, options, key), cancellationToken);
+
+        public static Task<string> DIDAuthAsync(string holder, string options, string key,
+            CancellationToken cancellationToken = default) =>
+            Run(() => DIDAuth(holder, options, key), cancellationToken);
+
+        public static Task<string> VerifyPresentationAsync(string presentation, string options,
+            CancellationToken cancellationToken = default) =>
+            Run(() => VerifyPresentation(presentation, options), cancellationToken);
+
+        public static Task<string> ResolveDIDAsync(string did, string inputMetadata = "{}",
+            CancellationToken cancellationToken = default) =>
+            Run(() => ResolveDID(did, inputMetadata), cancellationToken);
+
+        public static Task<string> DereferenceDIDURLAsync(string didUrl, string inputMetadata = "{}",
+            CancellationToken cancellationToken = default) =>
+            Run(() => DereferenceDIDURL(didUrl, inputMetadata), cancellationToken);
+    }
+}
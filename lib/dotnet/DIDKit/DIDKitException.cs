@@ -0,0 +1,44 @@
+using System;
+using System.Text.Json;
+
+namespace SpruceID.DIDKit
+{
+    /// <summary>Error from a DIDKit function.</summary>
+    public class DIDKitException : Exception
+    {
+        /// <summary>Stable numeric error code, as returned by <c>didkit_error_code</c>.</summary>
+        public int Code { get; }
+
+        /// <summary>Name of the error kind, e.g. <c>unknownProofFormat</c>.</summary>
+        public string Kind { get; }
+
+        /// <summary>Details specific to the error kind, as a JSON object, or null.</summary>
+        public string? Details { get; }
+
+        public DIDKitException(int code, string kind, string message, string? details = null)
+            : base(message)
+        {
+            Code = code;
+            Kind = kind;
+            Details = details;
+        }
+
+        /// <summary>The most recent error of a DIDKit function in the current thread.</summary>
+        internal static DIDKitException Last()
+        {
+            var json = NativeMethods.FromUtf8(NativeMethods.didkit_error_json());
+            if (json == null)
+            {
+                return new DIDKitException(NativeMethods.didkit_error_code(), "unknown",
+                    NativeMethods.FromUtf8(NativeMethods.didkit_error_message()) ?? "Unknown error");
+            }
+            using var error = JsonDocument.Parse(json);
+            var root = error.RootElement;
+            return new DIDKitException(
+                root.GetProperty("code").GetInt32(),
+                root.GetProperty("kind").GetString() ?? "unknown",
+                root.GetProperty("message").GetString() ?? "",
+                root.TryGetProperty("details", out var details) ? details.GetRawText() : null);
+        }
+    }
+}
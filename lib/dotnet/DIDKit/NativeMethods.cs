@@ -0,0 +1,84 @@
+using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
+using System.Text;
+
+namespace SpruceID.DIDKit
+{
+    /// <summary>P/Invoke declarations of the DIDKit C functions, declared in <c>didkit.h</c>.</summary>
+    /// <remarks>
+    /// Strings are passed as null-terminated UTF-8 byte arrays, which are pinned during the call,
+    /// and returned as pointers which must be freed with <see cref="didkit_free_string"/>.
+    /// </remarks>
+    internal static class NativeMethods
+    {
+        private const string Library = "didkit";
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_get_version();
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_error_message();
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern int didkit_error_code();
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_error_json();
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_vc_generate_ed25519_key();
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_key_to_did(byte[] methodPattern, byte[] jwk);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_key_to_verification_method(byte[] methodPattern, byte[] jwk);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_vc_issue_credential(byte[] credential, byte[] options, byte[] key);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_vc_verify_credential(byte[] credential, byte[] options);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_vc_issue_presentation(byte[] presentation, byte[] options, byte[] key);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_did_auth(byte[] holder, byte[] options, byte[] key);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_vc_verify_presentation(byte[] presentation, byte[] options);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_did_resolve(byte[] did, byte[] inputMetadata);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern IntPtr didkit_did_url_dereference(byte[] didUrl, byte[] inputMetadata);
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        internal static extern void didkit_free_string(IntPtr str);
+
+        internal static byte[] ToUtf8(string str) => Encoding.UTF8.GetBytes(str + "\0");
+
+        /// <summary>Read a null-terminated UTF-8 string, without freeing it.</summary>
+        internal static string? FromUtf8(IntPtr ptr)
+        {
+            if (ptr == IntPtr.Zero)
+            {
+                return null;
+            }
+            var bytes = new List<byte>();
+            for (var i = 0; ; i++)
+            {
+                var b = Marshal.ReadByte(ptr, i);
+                if (b == 0)
+                {
+                    break;
+                }
+                bytes.Add(b);
+            }
+            return Encoding.UTF8.GetString(bytes.ToArray());
+        }
+    }
+}
@@ -1,18 +1,79 @@
 package com.spruceid.DIDKit;
 
+import android.content.Context;
+import android.content.SharedPreferences;
+
 import androidx.annotation.NonNull;
+import androidx.security.crypto.EncryptedSharedPreferences;
+import androidx.security.crypto.MasterKeys;
 
 import io.flutter.embedding.engine.plugins.FlutterPlugin;
+import io.flutter.plugin.common.MethodCall;
+import io.flutter.plugin.common.MethodChannel;
 
 /** DIDKitFlutterPlugin */
-public class DIDKitFlutterPlugin implements FlutterPlugin {
-	/// This class is required by Flutter, but does not do anything.
-	/// All functionality is in the FFI between Dart and the C shared library.
+public class DIDKitFlutterPlugin implements FlutterPlugin, MethodChannel.MethodCallHandler {
+	/// DIDKit functions are in the FFI between Dart and the C shared library. This class only
+	/// implements the key storage channel, storing keys in shared preferences encrypted with a
+	/// key held by the Android Keystore.
+	private static final String CHANNEL = "com.spruceid.didkit/key_storage";
+	private static final String PREFERENCES = "com.spruceid.didkit.keys";
+
+	private MethodChannel channel;
+	private Context context;
+	private SharedPreferences preferences;
+
 	@Override
 	public void onAttachedToEngine(@NonNull FlutterPluginBinding binding) {
+		context = binding.getApplicationContext();
+		channel = new MethodChannel(binding.getBinaryMessenger(), CHANNEL);
+		channel.setMethodCallHandler(this);
 	}
 
 	@Override
 	public void onDetachedFromEngine(@NonNull FlutterPluginBinding binding) {
+		channel.setMethodCallHandler(null);
+		channel = null;
+		preferences = null;
+	}
+
+	private SharedPreferences getPreferences() throws Exception {
+		if (preferences == null) {
+			String masterKeyAlias = MasterKeys.getOrCreate(MasterKeys.AES256_GCM_SPEC);
+			preferences = EncryptedSharedPreferences.create(
+				PREFERENCES,
+				masterKeyAlias,
+				context,
+				EncryptedSharedPreferences.PrefKeyEncryptionScheme.AES256_SIV,
+				EncryptedSharedPreferences.PrefValueEncryptionScheme.AES256_GCM);
+		}
+		return preferences;
+	}
+
+	@Override
+	public void onMethodCall(@NonNull MethodCall call, @NonNull MethodChannel.Result result) {
+		String alias = call.argument("alias");
+		try {
+			switch (call.method) {
+				case "store":
+					getPreferences().edit().putString(alias, call.argument("key")).apply();
+					result.success(null);
+					break;
+				case "load":
+					result.success(getPreferences().getString(alias, null));
+					break;
+				case "contains":
+					result.success(getPreferences().contains(alias));
+					break;
+				case "delete":
+					getPreferences().edit().remove(alias).apply();
+					result.success(null);
+					break;
+				default:
+					result.notImplemented();
+			}
+		} catch (Exception e) {
+			result.error("KeyStorageError", e.getMessage(), null);
+		}
 	}
 }
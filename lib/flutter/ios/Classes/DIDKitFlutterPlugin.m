@@ -1,9 +1,79 @@
 #import "DIDKitFlutterPlugin.h"
 
+#import <Security/Security.h>
+
 #import "didkit.h"
 
+static NSString *const kKeyStorageChannel = @"com.spruceid.didkit/key_storage";
+static NSString *const kKeychainService = @"com.spruceid.didkit.keys";
+
 @implementation DIDKitFlutterPlugin
-+ (void)registerWithRegistrar:(NSObject<FlutterPluginRegistrar>*)registrar {}
++ (void)registerWithRegistrar:(NSObject<FlutterPluginRegistrar>*)registrar {
+  // DIDKit functions are in the FFI between Dart and the C library. The plugin
+  // only implements the key storage channel, storing keys in the Keychain.
+  FlutterMethodChannel *channel =
+      [FlutterMethodChannel methodChannelWithName:kKeyStorageChannel
+                                  binaryMessenger:[registrar messenger]];
+  DIDKitFlutterPlugin *instance = [[DIDKitFlutterPlugin alloc] init];
+  [registrar addMethodCallDelegate:instance channel:channel];
+}
+
++ (NSMutableDictionary *)queryForAlias:(NSString *)alias {
+  return [@{
+    (__bridge id)kSecClass : (__bridge id)kSecClassGenericPassword,
+    (__bridge id)kSecAttrService : kKeychainService,
+    (__bridge id)kSecAttrAccount : alias,
+  } mutableCopy];
+}
+
+- (void)handleMethodCall:(FlutterMethodCall *)call result:(FlutterResult)result {
+  NSString *alias = call.arguments[@"alias"];
+  NSMutableDictionary *query = [DIDKitFlutterPlugin queryForAlias:alias];
+  OSStatus status;
+  if ([@"store" isEqualToString:call.method]) {
+    SecItemDelete((__bridge CFDictionaryRef)query);
+    NSString *key = call.arguments[@"key"];
+    query[(__bridge id)kSecValueData] = [key dataUsingEncoding:NSUTF8StringEncoding];
+    query[(__bridge id)kSecAttrAccessible] =
+        (__bridge id)kSecAttrAccessibleWhenUnlockedThisDeviceOnly;
+    status = SecItemAdd((__bridge CFDictionaryRef)query, NULL);
+    if (status == errSecSuccess) {
+      result(nil);
+      return;
+    }
+  } else if ([@"load" isEqualToString:call.method]) {
+    query[(__bridge id)kSecReturnData] = @YES;
+    query[(__bridge id)kSecMatchLimit] = (__bridge id)kSecMatchLimitOne;
+    CFTypeRef data = NULL;
+    status = SecItemCopyMatching((__bridge CFDictionaryRef)query, &data);
+    if (status == errSecSuccess) {
+      result([[NSString alloc] initWithData:(__bridge_transfer NSData *)data
+                                   encoding:NSUTF8StringEncoding]);
+      return;
+    } else if (status == errSecItemNotFound) {
+      result(nil);
+      return;
+    }
+  } else if ([@"contains" isEqualToString:call.method]) {
+    status = SecItemCopyMatching((__bridge CFDictionaryRef)query, NULL);
+    if (status == errSecSuccess || status == errSecItemNotFound) {
+      result(@(status == errSecSuccess));
+      return;
+    }
+  } else if ([@"delete" isEqualToString:call.method]) {
+    status = SecItemDelete((__bridge CFDictionaryRef)query);
+    if (status == errSecSuccess || status == errSecItemNotFound) {
+      result(nil);
+      return;
+    }
+  } else {
+    result(FlutterMethodNotImplemented);
+    return;
+  }
+  result([FlutterError errorWithCode:@"KeyStorageError"
+                             message:[NSString stringWithFormat:@"Keychain error %d", (int)status]
+                             details:nil]);
+}
 
 + (void)dummyMethodToEnforceFunctionsDontGetOptmized {
   // Here we MUST call all functions from didkit.h so that they don't get 
@@ -13,6 +83,7 @@
   didkit_did_resolve(NULL, NULL);
   didkit_did_url_dereference(NULL, NULL);
   didkit_error_code();
+  didkit_error_json();
   didkit_error_message();
   didkit_free_string(NULL);
   didkit_key_to_did(NULL, NULL);
@@ -1,9 +1,15 @@
 library didkit;
 
+import 'dart:convert';
 import 'dart:ffi';
 import 'dart:io';
+import 'dart:isolate';
 import 'package:ffi/ffi.dart';
 
+import 'src/bindings.dart';
+
+export 'src/key_storage.dart';
+
 // TODO: support Windows
 final DynamicLibrary lib = Platform.isAndroid || Platform.isLinux
     ? DynamicLibrary.open('libdidkit.so')
@@ -11,71 +17,21 @@ final DynamicLibrary lib = Platform.isAndroid || Platform.isLinux
         ? DynamicLibrary.open('libdidkit.dylib')
         : DynamicLibrary.process();
 
-final get_version =
-    lib.lookupFunction<Pointer<Utf8> Function(), Pointer<Utf8> Function()>(
-        'didkit_get_version');
-
-final get_error_message =
-    lib.lookupFunction<Pointer<Utf8> Function(), Pointer<Utf8> Function()>(
-        'didkit_error_message');
-
-final get_error_code =
-    lib.lookupFunction<Int32 Function(), int Function()>('didkit_error_code');
-
-final generate_ed25519_key =
-    lib.lookupFunction<Pointer<Utf8> Function(), Pointer<Utf8> Function()>(
-        'didkit_vc_generate_ed25519_key');
-
-final key_to_did = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>)>('didkit_key_to_did');
-
-final key_to_verification_method = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(
-        Pointer<Utf8>, Pointer<Utf8>)>('didkit_key_to_verification_method');
-
-final issue_credential = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>,
-        Pointer<Utf8>)>('didkit_vc_issue_credential');
-
-final verify_credential = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(
-        Pointer<Utf8>, Pointer<Utf8>)>('didkit_vc_verify_credential');
-
-final issue_presentation = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>,
-        Pointer<Utf8>)>('didkit_vc_issue_presentation');
-
-final verify_presentation = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(
-        Pointer<Utf8>, Pointer<Utf8>)>('didkit_vc_verify_presentation');
-
-final resolve_did = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>)>('didkit_did_resolve');
-
-final dereference_did_url = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(
-        Pointer<Utf8>, Pointer<Utf8>)>('didkit_did_url_dereference');
-
-final did_auth = lib.lookupFunction<
-    Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>),
-    Pointer<Utf8> Function(
-        Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>)>('didkit_did_auth');
-
-final free_string = lib.lookupFunction<Void Function(Pointer<Utf8>),
-    void Function(Pointer<Utf8>)>('didkit_free_string');
+final DIDKitBindings bindings = DIDKitBindings(lib);
 
 class DIDKitException implements Exception {
+  /// Stable numeric error code, as returned by `didkit_error_code`.
   int code;
   String message;
-  DIDKitException(this.code, this.message);
+
+  /// Name of the error kind, e.g. `unknownProofFormat`.
+  String kind;
+
+  /// Details specific to the error kind.
+  Map<String, dynamic>? details;
+
+  DIDKitException(this.code, this.message,
+      {this.kind = 'unknown', this.details});
 
   @override
   String toString() {
@@ -84,115 +40,160 @@ class DIDKitException implements Exception {
 }
 
 DIDKitException lastError() {
-  final code = get_error_code();
-  final message_utf8 = get_error_message();
-  final message_string = message_utf8.address == nullptr.address
-      ? 'Unable to get error message'
-      : message_utf8.toDartString();
+  final json = bindings.didkit_error_json();
+  if (json.address == nullptr.address) {
+    return DIDKitException(bindings.didkit_error_code(),
+        'Unable to get error message');
+  }
+  final error = jsonDecode(json.cast<Utf8>().toDartString());
+  return DIDKitException(error['code'], error['message'],
+      kind: error['kind'], details: error['details']);
+}
+
+String _takeString(Pointer<Int8> result) {
+  if (result.address == nullptr.address) throw lastError();
+  final string = result.cast<Utf8>().toDartString();
+  bindings.didkit_free_string(result);
+  return string;
+}
+
+/// Call a DIDKit function with native copies of [args], freed afterwards.
+String _call(
+    Pointer<Int8> Function(List<Pointer<Int8>>) function, List<String> args) {
+  final nativeArgs =
+      args.map((arg) => arg.toNativeUtf8().cast<Int8>()).toList();
+  try {
+    return _takeString(function(nativeArgs));
+  } finally {
+    nativeArgs.forEach(malloc.free);
+  }
+}
+
+void _isolateMain(List<Object> message) {
+  final port = message[0] as SendPort;
+  final function = message[1] as String Function(List<String>);
+  final args = message[2] as List<String>;
+  try {
+    port.send([true, function(args)]);
+  } on DIDKitException catch (e) {
+    port.send([false, e.code, e.message, e.kind, jsonEncode(e.details)]);
+  } catch (e) {
+    port.send([false, -1, e.toString(), 'unknown', 'null']);
+  }
+}
 
-  return DIDKitException(code, message_string);
+/// Run [function] in a new isolate, so that resolving DIDs and verifying
+/// proofs does not block the UI isolate.
+Future<String> _runInIsolate(
+    String Function(List<String>) function, List<String> args) async {
+  final port = ReceivePort();
+  await Isolate.spawn(_isolateMain, [port.sendPort, function, args]);
+  final result = await port.first as List;
+  if (result[0] == true) return result[1] as String;
+  throw DIDKitException(result[1] as int, result[2] as String,
+      kind: result[3] as String, details: jsonDecode(result[4] as String));
 }
 
+String _issueCredential(List<String> a) =>
+    DIDKit.issueCredential(a[0], a[1], a[2]);
+String _verifyCredential(List<String> a) => DIDKit.verifyCredential(a[0], a[1]);
+String _issuePresentation(List<String> a) =>
+    DIDKit.issuePresentation(a[0], a[1], a[2]);
+String _verifyPresentation(List<String> a) =>
+    DIDKit.verifyPresentation(a[0], a[1]);
+String _resolveDID(List<String> a) => DIDKit.resolveDID(a[0], a[1]);
+String _dereferenceDIDURL(List<String> a) =>
+    DIDKit.dereferenceDIDURL(a[0], a[1]);
+String _didAuth(List<String> a) => DIDKit.DIDAuth(a[0], a[1], a[2]);
+
 class DIDKit {
   static String getVersion() {
-    return get_version().toDartString();
+    return bindings.didkit_get_version().cast<Utf8>().toDartString();
   }
 
   static String generateEd25519Key() {
-    final key = generate_ed25519_key();
-    if (key.address == nullptr.address) throw lastError();
-    final key_string = key.toDartString();
-    free_string(key);
-    return key_string;
+    return _takeString(bindings.didkit_vc_generate_ed25519_key());
   }
 
   @Deprecated('Use [keyToDID]')
   static String keyToDIDKey(String key) {
-    final did_key = key_to_did('key'.toNativeUtf8(), key.toNativeUtf8());
-    if (did_key.address == nullptr.address) throw lastError();
-    final did_key_string = did_key.toDartString();
-    free_string(did_key);
-    return did_key_string;
+    return keyToDID('key', key);
   }
 
   static String keyToDID(String methodPattern, String key) {
-    final did = key_to_did(methodPattern.toNativeUtf8(), key.toNativeUtf8());
-    if (did.address == nullptr.address) throw lastError();
-    final did_string = did.toDartString();
-    free_string(did);
-    return did_string;
+    return _call((a) => bindings.didkit_key_to_did(a[0], a[1]),
+        [methodPattern, key]);
   }
 
   static String keyToVerificationMethod(String methodPattern, String key) {
-    final vm = key_to_verification_method(
-        methodPattern.toNativeUtf8(), key.toNativeUtf8());
-    if (vm.address == nullptr.address) throw lastError();
-    final vm_string = vm.toDartString();
-    free_string(vm);
-    return vm_string;
+    return _call(
+        (a) => bindings.didkit_key_to_verification_method(a[0], a[1]),
+        [methodPattern, key]);
   }
 
   static String issueCredential(String credential, String options, String key) {
-    final vc = issue_credential(
-        credential.toNativeUtf8(), options.toNativeUtf8(), key.toNativeUtf8());
-    if (vc.address == nullptr.address) throw lastError();
-    final vc_string = vc.toDartString();
-    free_string(vc);
-    return vc_string;
+    return _call(
+        (a) => bindings.didkit_vc_issue_credential(a[0], a[1], a[2]),
+        [credential, options, key]);
   }
 
   static String verifyCredential(String credential, String options) {
-    final result =
-        verify_credential(credential.toNativeUtf8(), options.toNativeUtf8());
-    if (result.address == nullptr.address) throw lastError();
-    final result_string = result.toDartString();
-    free_string(result);
-    return result_string;
+    return _call((a) => bindings.didkit_vc_verify_credential(a[0], a[1]),
+        [credential, options]);
   }
 
   static String issuePresentation(
       String presentation, String options, String key) {
-    final vp = issue_presentation(presentation.toNativeUtf8(),
-        options.toNativeUtf8(), key.toNativeUtf8());
-    if (vp.address == nullptr.address) throw lastError();
-    final vp_string = vp.toDartString();
-    free_string(vp);
-    return vp_string;
+    return _call(
+        (a) => bindings.didkit_vc_issue_presentation(a[0], a[1], a[2]),
+        [presentation, options, key]);
   }
 
   static String verifyPresentation(String presentation, String options) {
-    final result = verify_presentation(
-        presentation.toNativeUtf8(), options.toNativeUtf8());
-    if (result.address == nullptr.address) throw lastError();
-    final result_string = result.toDartString();
-    free_string(result);
-    return result_string;
+    return _call((a) => bindings.didkit_vc_verify_presentation(a[0], a[1]),
+        [presentation, options]);
   }
 
   static String resolveDID(String did, String inputMetadata) {
-    final result =
-        resolve_did(did.toNativeUtf8(), inputMetadata.toNativeUtf8());
-    if (result.address == nullptr.address) throw lastError();
-    final result_string = result.toDartString();
-    free_string(result);
-    return result_string;
+    return _call((a) => bindings.didkit_did_resolve(a[0], a[1]),
+        [did, inputMetadata]);
   }
 
   static String dereferenceDIDURL(String didUrl, String inputMetadata) {
-    final result = dereference_did_url(
-        didUrl.toNativeUtf8(), inputMetadata.toNativeUtf8());
-    if (result.address == nullptr.address) throw lastError();
-    final result_string = result.toDartString();
-    free_string(result);
-    return result_string;
+    return _call((a) => bindings.didkit_did_url_dereference(a[0], a[1]),
+        [didUrl, inputMetadata]);
   }
 
   static String DIDAuth(String did, String options, String key) {
-    final vp = did_auth(
-        did.toNativeUtf8(), options.toNativeUtf8(), key.toNativeUtf8());
-    if (vp.address == nullptr.address) throw lastError();
-    final vp_string = vp.toDartString();
-    free_string(vp);
-    return vp_string;
+    return _call((a) => bindings.didkit_did_auth(a[0], a[1], a[2]),
+        [did, options, key]);
   }
+
+  // Asynchronous variants, running in a separate isolate.
+
+  static Future<String> issueCredentialAsync(
+          String credential, String options, String key) =>
+      _runInIsolate(_issueCredential, [credential, options, key]);
+
+  static Future<String> verifyCredentialAsync(
+          String credential, String options) =>
+      _runInIsolate(_verifyCredential, [credential, options]);
+
+  static Future<String> issuePresentationAsync(
+          String presentation, String options, String key) =>
+      _runInIsolate(_issuePresentation, [presentation, options, key]);
+
+  static Future<String> verifyPresentationAsync(
+          String presentation, String options) =>
+      _runInIsolate(_verifyPresentation, [presentation, options]);
+
+  static Future<String> resolveDIDAsync(String did, String inputMetadata) =>
+      _runInIsolate(_resolveDID, [did, inputMetadata]);
+
+  static Future<String> dereferenceDIDURLAsync(
+          String didUrl, String inputMetadata) =>
+      _runInIsolate(_dereferenceDIDURL, [didUrl, inputMetadata]);
+
+  static Future<String> DIDAuthAsync(String did, String options, String key) =>
+      _runInIsolate(_didAuth, [did, options, key]);
 }
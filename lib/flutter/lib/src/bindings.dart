@@ -0,0 +1,264 @@
+// AUTO GENERATED FILE, DO NOT EDIT.
+//
+// Generated by `package:ffigen` from `didkit.h`; run `flutter pub run ffigen` to regenerate.
+// ignore_for_file: type=lint
+import 'dart:ffi' as ffi;
+
+/// Bindings to the DIDKit C library.
+class DIDKitBindings {
+  /// Holds the symbol lookup function.
+  final ffi.Pointer<T> Function<T extends ffi.NativeType>(String symbolName)
+      _lookup;
+
+  /// The symbols are looked up in [dynamicLibrary].
+  DIDKitBindings(ffi.DynamicLibrary dynamicLibrary)
+      : _lookup = dynamicLibrary.lookup;
+
+  /// The symbols are looked up with [lookup].
+  DIDKitBindings.fromLookup(
+      ffi.Pointer<T> Function<T extends ffi.NativeType>(String symbolName)
+          lookup)
+      : _lookup = lookup;
+
+  ffi.Pointer<ffi.Int8> didkit_get_version() {
+    return _didkit_get_version();
+  }
+
+  late final _didkit_get_versionPtr =
+      _lookup<ffi.NativeFunction<ffi.Pointer<ffi.Int8> Function()>>(
+          'didkit_get_version');
+  late final _didkit_get_version =
+      _didkit_get_versionPtr.asFunction<ffi.Pointer<ffi.Int8> Function()>();
+
+  ffi.Pointer<ffi.Int8> didkit_error_message() {
+    return _didkit_error_message();
+  }
+
+  late final _didkit_error_messagePtr =
+      _lookup<ffi.NativeFunction<ffi.Pointer<ffi.Int8> Function()>>(
+          'didkit_error_message');
+  late final _didkit_error_message =
+      _didkit_error_messagePtr.asFunction<ffi.Pointer<ffi.Int8> Function()>();
+
+  int didkit_error_code() {
+    return _didkit_error_code();
+  }
+
+  late final _didkit_error_codePtr =
+      _lookup<ffi.NativeFunction<ffi.Int32 Function()>>('didkit_error_code');
+  late final _didkit_error_code =
+      _didkit_error_codePtr.asFunction<int Function()>();
+
+  ffi.Pointer<ffi.Int8> didkit_error_json() {
+    return _didkit_error_json();
+  }
+
+  late final _didkit_error_jsonPtr =
+      _lookup<ffi.NativeFunction<ffi.Pointer<ffi.Int8> Function()>>(
+          'didkit_error_json');
+  late final _didkit_error_json =
+      _didkit_error_jsonPtr.asFunction<ffi.Pointer<ffi.Int8> Function()>();
+
+  ffi.Pointer<ffi.Int8> didkit_vc_generate_ed25519_key() {
+    return _didkit_vc_generate_ed25519_key();
+  }
+
+  late final _didkit_vc_generate_ed25519_keyPtr =
+      _lookup<ffi.NativeFunction<ffi.Pointer<ffi.Int8> Function()>>(
+          'didkit_vc_generate_ed25519_key');
+  late final _didkit_vc_generate_ed25519_key =
+      _didkit_vc_generate_ed25519_keyPtr
+          .asFunction<ffi.Pointer<ffi.Int8> Function()>();
+
+  ffi.Pointer<ffi.Int8> didkit_key_to_did(
+    ffi.Pointer<ffi.Int8> method_pattern,
+    ffi.Pointer<ffi.Int8> jwk,
+  ) {
+    return _didkit_key_to_did(
+      method_pattern,
+      jwk,
+    );
+  }
+
+  late final _didkit_key_to_didPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_key_to_did');
+  late final _didkit_key_to_did = _didkit_key_to_didPtr.asFunction<
+      ffi.Pointer<ffi.Int8> Function(
+          ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_key_to_verification_method(
+    ffi.Pointer<ffi.Int8> method_pattern,
+    ffi.Pointer<ffi.Int8> jwk,
+  ) {
+    return _didkit_key_to_verification_method(
+      method_pattern,
+      jwk,
+    );
+  }
+
+  late final _didkit_key_to_verification_methodPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_key_to_verification_method');
+  late final _didkit_key_to_verification_method =
+      _didkit_key_to_verification_methodPtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_vc_issue_credential(
+    ffi.Pointer<ffi.Int8> credential_json,
+    ffi.Pointer<ffi.Int8> proof_options_json,
+    ffi.Pointer<ffi.Int8> key_json,
+  ) {
+    return _didkit_vc_issue_credential(
+      credential_json,
+      proof_options_json,
+      key_json,
+    );
+  }
+
+  late final _didkit_vc_issue_credentialPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_vc_issue_credential');
+  late final _didkit_vc_issue_credential =
+      _didkit_vc_issue_credentialPtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_vc_verify_credential(
+    ffi.Pointer<ffi.Int8> credential_json,
+    ffi.Pointer<ffi.Int8> proof_options_json,
+  ) {
+    return _didkit_vc_verify_credential(
+      credential_json,
+      proof_options_json,
+    );
+  }
+
+  late final _didkit_vc_verify_credentialPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_vc_verify_credential');
+  late final _didkit_vc_verify_credential =
+      _didkit_vc_verify_credentialPtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_vc_issue_presentation(
+    ffi.Pointer<ffi.Int8> presentation_json,
+    ffi.Pointer<ffi.Int8> proof_options_json,
+    ffi.Pointer<ffi.Int8> key_json,
+  ) {
+    return _didkit_vc_issue_presentation(
+      presentation_json,
+      proof_options_json,
+      key_json,
+    );
+  }
+
+  late final _didkit_vc_issue_presentationPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_vc_issue_presentation');
+  late final _didkit_vc_issue_presentation =
+      _didkit_vc_issue_presentationPtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_did_auth(
+    ffi.Pointer<ffi.Int8> holder,
+    ffi.Pointer<ffi.Int8> proof_options_json,
+    ffi.Pointer<ffi.Int8> key_json,
+  ) {
+    return _didkit_did_auth(
+      holder,
+      proof_options_json,
+      key_json,
+    );
+  }
+
+  late final _didkit_did_authPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>>('didkit_did_auth');
+  late final _didkit_did_auth = _didkit_did_authPtr.asFunction<
+      ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+          ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_vc_verify_presentation(
+    ffi.Pointer<ffi.Int8> presentation_json,
+    ffi.Pointer<ffi.Int8> proof_options_json,
+  ) {
+    return _didkit_vc_verify_presentation(
+      presentation_json,
+      proof_options_json,
+    );
+  }
+
+  late final _didkit_vc_verify_presentationPtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_vc_verify_presentation');
+  late final _didkit_vc_verify_presentation =
+      _didkit_vc_verify_presentationPtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_did_resolve(
+    ffi.Pointer<ffi.Int8> did,
+    ffi.Pointer<ffi.Int8> input_metadata_json,
+  ) {
+    return _didkit_did_resolve(
+      did,
+      input_metadata_json,
+    );
+  }
+
+  late final _didkit_did_resolvePtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_did_resolve');
+  late final _didkit_did_resolve = _didkit_did_resolvePtr.asFunction<
+      ffi.Pointer<ffi.Int8> Function(
+          ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  ffi.Pointer<ffi.Int8> didkit_did_url_dereference(
+    ffi.Pointer<ffi.Int8> did_url,
+    ffi.Pointer<ffi.Int8> input_metadata_json,
+  ) {
+    return _didkit_did_url_dereference(
+      did_url,
+      input_metadata_json,
+    );
+  }
+
+  late final _didkit_did_url_dereferencePtr = _lookup<
+      ffi.NativeFunction<
+          ffi.Pointer<ffi.Int8> Function(ffi.Pointer<ffi.Int8>,
+              ffi.Pointer<ffi.Int8>)>>('didkit_did_url_dereference');
+  late final _didkit_did_url_dereference =
+      _didkit_did_url_dereferencePtr.asFunction<
+          ffi.Pointer<ffi.Int8> Function(
+              ffi.Pointer<ffi.Int8>, ffi.Pointer<ffi.Int8>)>();
+
+  void didkit_free_string(
+    ffi.Pointer<ffi.Int8> string,
+  ) {
+    return _didkit_free_string(
+      string,
+    );
+  }
+
+  late final _didkit_free_stringPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Pointer<ffi.Int8>)>>(
+          'didkit_free_string');
+  late final _didkit_free_string = _didkit_free_stringPtr
+      .asFunction<void Function(ffi.Pointer<ffi.Int8>)>();
+}
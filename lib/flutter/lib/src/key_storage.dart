@@ -0,0 +1,61 @@
+import 'package:flutter/services.dart';
+
+import '../didkit.dart';
+
+/// Storage of private keys in the platform's secure storage: the Keychain on
+/// iOS, and shared preferences encrypted with an Android Keystore key on
+/// Android.
+///
+/// Keys are loaded only for the duration of a signing operation.
+class DIDKitKeyStorage {
+  static const MethodChannel _channel =
+      MethodChannel('com.spruceid.didkit/key_storage');
+
+  /// Store [key], a JWK, under [alias], replacing any key with that alias.
+  static Future<void> store(String alias, String key) async {
+    await _channel.invokeMethod('store', {'alias': alias, 'key': key});
+  }
+
+  static Future<bool> contains(String alias) async {
+    return await _channel.invokeMethod<bool>('contains', {'alias': alias}) ??
+        false;
+  }
+
+  static Future<void> delete(String alias) async {
+    await _channel.invokeMethod('delete', {'alias': alias});
+  }
+
+  static Future<String> _load(String alias) async {
+    final key = await _channel.invokeMethod<String>('load', {'alias': alias});
+    if (key == null) {
+      throw DIDKitException(-1, 'No key stored for alias: $alias');
+    }
+    return key;
+  }
+
+  /// Generate an Ed25519 key and store it under [alias].
+  static Future<void> generateEd25519Key(String alias) async {
+    await store(alias, DIDKit.generateEd25519Key());
+  }
+
+  static Future<String> keyToVerificationMethod(
+      String alias, String methodPattern) async {
+    return DIDKit.keyToVerificationMethod(methodPattern, await _load(alias));
+  }
+
+  static Future<String> issueCredential(
+      String alias, String credential, String options) async {
+    return DIDKit.issueCredentialAsync(credential, options, await _load(alias));
+  }
+
+  static Future<String> issuePresentation(
+      String alias, String presentation, String options) async {
+    return DIDKit.issuePresentationAsync(
+        presentation, options, await _load(alias));
+  }
+
+  static Future<String> DIDAuth(
+      String alias, String did, String options) async {
+    return DIDKit.DIDAuthAsync(did, options, await _load(alias));
+  }
+}
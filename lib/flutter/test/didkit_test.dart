@@ -126,4 +126,40 @@ void main() {
     final verifyResult = jsonDecode(verification);
     expect(verifyResult['errors'], isEmpty);
   });
+
+  test('exception codes', () async {
+    try {
+      DIDKit.issuePresentation('{}', '{}', '{}');
+      fail('expected DIDKitException');
+    } on DIDKitException catch (e) {
+      expect(e.code, isNot(0));
+      expect(e.kind, isNot('unknown'));
+    }
+  });
+
+  test('issueCredentialAsync, verifyCredentialAsync', () async {
+    final key = DIDKit.generateEd25519Key();
+    final did = DIDKit.keyToDID('key', key);
+    final verificationMethod = DIDKit.keyToVerificationMethod('key', key);
+    final options = {
+      'proofPurpose': 'assertionMethod',
+      'verificationMethod': verificationMethod
+    };
+    final credential = {
+      '@context': 'https://www.w3.org/2018/credentials/v1',
+      'id': 'http://example.org/credentials/3731',
+      'type': ['VerifiableCredential'],
+      'issuer': did,
+      'issuanceDate': '2020-08-19T21:41:50Z',
+      'credentialSubject': {'id': 'did:example:d23dd687a7dc6787646f2eb98d0'}
+    };
+    final vc = await DIDKit.issueCredentialAsync(
+        jsonEncode(credential), jsonEncode(options), key);
+    final verifyResult = jsonDecode(await DIDKit.verifyCredentialAsync(
+        vc, jsonEncode({'proofPurpose': 'assertionMethod'})));
+    expect(verifyResult['errors'], isEmpty);
+
+    expect(() => DIDKit.verifyCredentialAsync('', ''),
+        throwsA(isInstanceOf<DIDKitException>()));
+  });
 }
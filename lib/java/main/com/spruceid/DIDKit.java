@@ -12,6 +12,8 @@ public class DIDKit {
     public static native String verifyPresentation(String verifiablePresentation, String linkedDataProofOptions);
     public static native String resolveDID(String did, String inputMetadata);
     public static native String dereferenceDIDURL(String didUrl, String inputMetadata);
+    public static native String credentialCard(String credential, String credentialManifest, String descriptorId) throws DIDKitException;
+    public static native String validateCredentialDisplay(String credential, String credentialManifest, String descriptorId) throws DIDKitException;
 
     static {
         System.loadLibrary("didkit");
@@ -1,7 +1,33 @@
 package com.spruceid;
 
 public class DIDKitException extends Exception {
+	private final int code;
+	private final String kind;
+	private final String details;
+
 	DIDKitException(String message) {
+		this(-1, "unknown", message, null);
+	}
+
+	DIDKitException(int code, String kind, String message, String details) {
 		super(message);
+		this.code = code;
+		this.kind = kind;
+		this.details = details;
+	}
+
+	/** Stable numeric error code, as returned by didkit_error_code in the C API. */
+	public int getCode() {
+		return code;
+	}
+
+	/** Name of the error kind, e.g. "unknownProofFormat". */
+	public String getKind() {
+		return kind;
+	}
+
+	/** Details specific to the error kind, as a JSON object, or null. */
+	public String getDetails() {
+		return details;
 	}
 }
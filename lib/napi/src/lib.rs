@@ -0,0 +1,156 @@
+//! Native Node.js bindings using N-API, via `napi-rs`.
+//!
+//! Unlike the `neon` bindings in `lib/node`, which block the calling thread, functions that
+//! resolve DIDs or sign return promises and run on a Tokio runtime owned by the addon, so they
+//! do not block the Node.js event loop. Arguments and results are plain JavaScript objects,
+//! converted with serde.
+
+use std::convert::TryFrom;
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::Value;
+
+use didkit::error::Error as DIDKitError;
+use didkit::{
+    dereference, get_verification_method, CredentialOrJWT, DIDResolver, DereferencingInputMetadata,
+    IssueOptions, JWTOrLDPOptions, PresentationOrJWT, ResolutionInputMetadata, Source,
+    VerifiableCredential, VerifiablePresentation, DID_METHODS, JWK, URI,
+};
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn error(err: impl ToString) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(error)
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value).map_err(error)
+}
+
+#[napi(js_name = "getVersion")]
+pub fn get_version() -> String {
+    VERSION.to_string()
+}
+
+#[napi(js_name = "generateEd25519Key")]
+pub fn generate_ed25519_key() -> Result<Value> {
+    let jwk = JWK::generate_ed25519().map_err(error)?;
+    to_value(&jwk)
+}
+
+#[napi(js_name = "keyToDID")]
+pub fn key_to_did(method_pattern: String, key: Value) -> Result<String> {
+    let key: JWK = from_value(key)?;
+    DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, &method_pattern))
+        .ok_or_else(|| error(DIDKitError::UnableToGenerateDID))
+}
+
+#[napi(js_name = "keyToVerificationMethod")]
+pub async fn key_to_verification_method(method_pattern: String, key: Value) -> Result<String> {
+    let did = key_to_did(method_pattern, key)?;
+    get_verification_method(&did, DID_METHODS.to_resolver())
+        .await
+        .ok_or_else(|| error(DIDKitError::UnableToGetVerificationMethod))
+}
+
+#[napi(js_name = "resolveDID")]
+pub async fn resolve_did(did: String, input_metadata: Option<Value>) -> Result<Value> {
+    let input_metadata: ResolutionInputMetadata =
+        from_value(input_metadata.unwrap_or_else(|| Value::Object(Default::default())))?;
+    let (res_meta, doc, _) = DID_METHODS
+        .to_resolver()
+        .resolve(&did, &input_metadata)
+        .await;
+    if let Some(err) = res_meta.error {
+        return Err(error(err));
+    }
+    match doc {
+        Some(doc) => to_value(&doc),
+        None => Err(error("No document resolved.")),
+    }
+}
+
+#[napi(js_name = "dereferenceDIDURL")]
+pub async fn dereference_did_url(did_url: String, input_metadata: Option<Value>) -> Result<Value> {
+    let input_metadata: DereferencingInputMetadata =
+        from_value(input_metadata.unwrap_or_else(|| Value::Object(Default::default())))?;
+    let deref_result = dereference(DID_METHODS.to_resolver(), &did_url, &input_metadata).await;
+    to_value(&deref_result)
+}
+
+/// Issue a credential. Resolves to the credential with a proof, or to a JWT string if the
+/// `proofFormat` option is `jwt`.
+#[napi(js_name = "issueCredential")]
+pub async fn issue_credential(credential: Value, options: Value, key: Value) -> Result<Value> {
+    let credential: VerifiableCredential = from_value(credential)?;
+    let options: JWTOrLDPOptions = from_value(options)?;
+    let options = IssueOptions::try_from(options).map_err(error)?;
+    let key: JWK = from_value(key)?;
+    let vc = didkit::issue_credential(credential, &options, &key, DID_METHODS.to_resolver())
+        .await
+        .map_err(error)?;
+    to_value(&vc)
+}
+
+/// Verify a credential, given as an object or a JWT string. Resolves to a `VerificationReport`.
+#[napi(js_name = "verifyCredential")]
+pub async fn verify_credential(credential: Value, options: Option<Value>) -> Result<Value> {
+    let credential: CredentialOrJWT = from_value(credential)?;
+    let options: JWTOrLDPOptions = match options {
+        Some(options) => from_value(options)?,
+        None => JWTOrLDPOptions::default(),
+    };
+    let report = didkit::verify_credential(&credential, &options, DID_METHODS.to_resolver())
+        .await
+        .map_err(error)?;
+    to_value(&report)
+}
+
+#[napi(js_name = "issuePresentation")]
+pub async fn issue_presentation(presentation: Value, options: Value, key: Value) -> Result<Value> {
+    let presentation: VerifiablePresentation = from_value(presentation)?;
+    issue_presentation_with(presentation, options, key).await
+}
+
+#[napi(js_name = "DIDAuth")]
+pub async fn did_auth(holder: String, options: Value, key: Value) -> Result<Value> {
+    let presentation = VerifiablePresentation {
+        holder: Some(URI::String(holder)),
+        ..Default::default()
+    };
+    issue_presentation_with(presentation, options, key).await
+}
+
+async fn issue_presentation_with(
+    presentation: VerifiablePresentation,
+    options: Value,
+    key: Value,
+) -> Result<Value> {
+    let options: JWTOrLDPOptions = from_value(options)?;
+    let options = IssueOptions::try_from(options).map_err(error)?;
+    let key: JWK = from_value(key)?;
+    let vp = didkit::issue_presentation(presentation, &options, &key, DID_METHODS.to_resolver())
+        .await
+        .map_err(error)?;
+    to_value(&vp)
+}
+
+/// Verify a presentation, given as an object or a JWT string. Resolves to a `VerificationReport`.
+#[napi(js_name = "verifyPresentation")]
+pub async fn verify_presentation(presentation: Value, options: Option<Value>) -> Result<Value> {
+    let presentation: PresentationOrJWT = from_value(presentation)?;
+    let options: JWTOrLDPOptions = match options {
+        Some(options) => from_value(options)?,
+        None => JWTOrLDPOptions::default_for_vp(),
+    };
+    let report = didkit::verify_presentation(&presentation, &options, DID_METHODS.to_resolver())
+        .await
+        .map_err(error)?;
+    to_value(&report)
+}
@@ -0,0 +1,59 @@
+const DIDKit = require("..");
+
+const key = {
+  kty: "OKP",
+  crv: "Ed25519",
+  x: "PBcY2yJ4h_cLUnQNcYhplu9KQQBNpGxP4sYcMPdlu6I",
+  d: "n5WUFIghmRYZi0rEYo2lz-Zg2B9B1KW4MYfJXwOXfyI",
+};
+const did = "did:key:z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK";
+
+describe("key", () => {
+  it("should produce did", () => {
+    expect(DIDKit.keyToDID("key", key)).toEqual(did);
+  });
+
+  it("should produce verificationMethod", async () => {
+    await expect(DIDKit.keyToVerificationMethod("key", key)).resolves.toEqual(
+      did + "#z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK"
+    );
+  });
+
+  it("should resolve did", async () => {
+    const doc = await DIDKit.resolveDID(did);
+    expect(doc).toHaveProperty("id", did);
+  });
+});
+
+describe("credential", () => {
+  const credential = {
+    "@context": "https://www.w3.org/2018/credentials/v1",
+    id: "http://example.org/credentials/3731",
+    type: ["VerifiableCredential"],
+    issuer: did,
+    issuanceDate: "2020-08-19T21:41:50Z",
+    credentialSubject: {
+      id: "did:example:d23dd687a7dc6787646f2eb98d0",
+    },
+  };
+
+  it("should issue and verify", async () => {
+    const verificationMethod = await DIDKit.keyToVerificationMethod("key", key);
+    const options = { proofPurpose: "assertionMethod", verificationMethod };
+    const vc = await DIDKit.issueCredential(credential, options, key);
+    expect(vc).toHaveProperty("proof");
+    const report = await DIDKit.verifyCredential(vc, {
+      proofPurpose: "assertionMethod",
+    });
+    expect(report.errors).toEqual([]);
+  });
+
+  it("should issue and verify a JWT", async () => {
+    const verificationMethod = await DIDKit.keyToVerificationMethod("key", key);
+    const options = { proofFormat: "jwt", verificationMethod };
+    const jwt = await DIDKit.issueCredential(credential, options, key);
+    expect(typeof jwt).toEqual("string");
+    const report = await DIDKit.verifyCredential(jwt, { proofFormat: "jwt" });
+    expect(report.errors).toEqual([]);
+  });
+});
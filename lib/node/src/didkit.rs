@@ -1,3 +1,5 @@
+use std::convert::TryFrom;
+
 use neon::prelude::*;
 
 use didkit::error::Error as DIDKitError;
@@ -6,6 +8,7 @@ use didkit::error::{didkit_error_code, didkit_error_message};
 use didkit::get_verification_method;
 use didkit::jwk_from_tezos_key as tz_to_jwk;
 use didkit::runtime;
+use didkit::signer;
 use didkit::ProofPreparation;
 use didkit::Source;
 use didkit::VerifiableCredential;
@@ -13,8 +16,8 @@ use didkit::VerifiablePresentation;
 use didkit::DID_METHODS;
 use didkit::JWK;
 use didkit::URI;
+use didkit::{CredentialOrJWT, IssueOptions, JWTOrLDPOptions, LinkedDataProofOptions};
 use didkit::{Delegation, Invocation};
-use didkit::{JWTOrLDPOptions, LinkedDataProofOptions, ProofFormat};
 
 use crate::error::Error;
 use crate::{arg, throws};
@@ -68,33 +71,26 @@ pub fn key_to_verification_method(mut cx: FunctionContext) -> JsResult<JsString>
 }
 
 pub fn issue_credential(mut cx: FunctionContext) -> JsResult<JsValue> {
-    let mut credential = arg!(cx, 0, VerifiableCredential);
+    let credential = arg!(cx, 0, VerifiableCredential);
     let options = arg!(cx, 1, JWTOrLDPOptions);
     let key = arg!(cx, 2, JWK);
-    let proof_format = options.proof_format.unwrap_or_default();
     let resolver = DID_METHODS.to_resolver();
+    let options = throws!(
+        cx,
+        IssueOptions::try_from(options).map_err(DIDKitError::from)
+    )?;
 
     let rt = throws!(cx, runtime::get())?;
-    let vc = match proof_format {
-        ProofFormat::JWT => {
-            let jwt = throws!(
-                cx,
-                rt.block_on(credential.generate_jwt(Some(&key), &options.ldp_options, resolver))
-            )?;
-            cx.string(jwt).as_value(&mut cx)
-        }
-        ProofFormat::LDP => {
-            let proof = throws!(
-                cx,
-                rt.block_on(credential.generate_proof(&key, &options.ldp_options, resolver))
-            )?;
-            credential.add_proof(proof);
-            throws!(cx, neon_serde::to_value(&mut cx, &credential))?
-        }
-        _ => throws!(
-            cx,
-            Err(DIDKitError::UnknownProofFormat(proof_format.to_string()))
-        )?,
+    let vc = throws!(
+        cx,
+        rt.block_on(didkit::issue_credential(
+            credential, &options, &key, resolver
+        ))
+        .map_err(DIDKitError::from)
+    )?;
+    let vc = match vc {
+        CredentialOrJWT::JWT(jwt) => cx.string(jwt).as_value(&mut cx),
+        vc => throws!(cx, neon_serde::to_value(&mut cx, &vc))?,
     };
     Ok(vc)
 }
@@ -118,7 +114,14 @@ pub fn issue_presentation(mut cx: FunctionContext) -> JsResult<JsValue> {
     let rt = throws!(cx, runtime::get())?;
     let proof = throws!(
         cx,
-        rt.block_on(presentation.generate_proof(&key, &options, resolver))
+        rt.block_on(signer::generate_proof(
+            &presentation,
+            &options,
+            resolver,
+            &key,
+            None
+        ))
+        .map_err(DIDKitError::from)
     )?;
     presentation.add_proof(proof);
 
@@ -138,7 +141,14 @@ pub fn did_auth(mut cx: FunctionContext) -> JsResult<JsValue> {
     let rt = throws!(cx, runtime::get())?;
     let proof = throws!(
         cx,
-        rt.block_on(presentation.generate_proof(&key, &options, resolver))
+        rt.block_on(signer::generate_proof(
+            &presentation,
+            &options,
+            resolver,
+            &key,
+            None
+        ))
+        .map_err(DIDKitError::from)
     )?;
     presentation.add_proof(proof);
 
@@ -0,0 +1,248 @@
+"""DIDKit: Verifiable Credentials and Decentralized Identifiers.
+
+Functions that resolve DIDs or sign are coroutines, running in native threads
+so that they do not block the event loop. Arguments may be given as JSON
+strings or as dicts.
+"""
+
+import json
+from dataclasses import dataclass, field
+from typing import Any, Dict, List, Optional, Union
+
+from . import _didkit
+from ._didkit import DIDKitException
+
+__all__ = [
+    "DIDKitException",
+    "KeyStore",
+    "ProofReport",
+    "VerificationError",
+    "VerificationReport",
+    "VerificationWarning",
+    "dereference_did_url",
+    "did_auth",
+    "generate_ed25519_key",
+    "get_version",
+    "issue_credential",
+    "issue_presentation",
+    "key_to_did",
+    "key_to_verification_method",
+    "resolve_did",
+    "verify_credential",
+    "verify_presentation",
+]
+
+Json = Union[str, Dict[str, Any]]
+
+
+def _to_json(value: Optional[Json]) -> str:
+    if value is None:
+        return "{}"
+    if isinstance(value, str):
+        return value
+    return json.dumps(value)
+
+
+@dataclass(frozen=True)
+class VerificationError:
+    code: str
+    message: str
+
+
+@dataclass(frozen=True)
+class VerificationWarning:
+    code: str
+    message: str
+
+
+@dataclass(frozen=True)
+class ProofReport:
+    checks: List[str]
+    warnings: List[VerificationWarning]
+    errors: List[VerificationError]
+    type: Optional[str] = None
+    verification_method: Optional[str] = None
+    proof_purpose: Optional[str] = None
+    created: Optional[str] = None
+
+    @staticmethod
+    def from_dict(proof: Dict[str, Any]) -> "ProofReport":
+        return ProofReport(
+            checks=proof.get("checks", []),
+            warnings=[VerificationWarning(**w) for w in proof.get("warnings", [])],
+            errors=[VerificationError(**e) for e in proof.get("errors", [])],
+            type=proof.get("type"),
+            verification_method=proof.get("verificationMethod"),
+            proof_purpose=proof.get("proofPurpose"),
+            created=proof.get("created"),
+        )
+
+
+@dataclass(frozen=True)
+class VerificationReport:
+    """Result of verifying a credential or presentation."""
+
+    checks: List[str]
+    warnings: List[VerificationWarning]
+    errors: List[VerificationError]
+    proofs: List[ProofReport] = field(default_factory=list)
+    issuer: Optional[str] = None
+    holder: Optional[str] = None
+
+    @property
+    def ok(self) -> bool:
+        return not self.errors
+
+    @staticmethod
+    def from_json(report_json: str) -> "VerificationReport":
+        report = json.loads(report_json)
+        return VerificationReport(
+            checks=report.get("checks", []),
+            warnings=[VerificationWarning(**w) for w in report.get("warnings", [])],
+            errors=[VerificationError(**e) for e in report.get("errors", [])],
+            proofs=[ProofReport.from_dict(p) for p in report.get("proofs", [])],
+            issuer=report.get("issuer"),
+            holder=report.get("holder"),
+        )
+
+
+def get_version() -> str:
+    return _didkit.get_version()
+
+
+def generate_ed25519_key() -> str:
+    return _didkit.generate_ed25519_key()
+
+
+def key_to_did(method_pattern: str, key: Json) -> str:
+    return _didkit.key_to_did(method_pattern, _to_json(key))
+
+
+async def key_to_verification_method(method_pattern: str, key: Json) -> str:
+    return await _didkit.key_to_verification_method(method_pattern, _to_json(key))
+
+
+async def resolve_did(did: str, input_metadata: Optional[Json] = None) -> str:
+    return await _didkit.resolve_did(did, _to_json(input_metadata))
+
+
+async def dereference_did_url(did_url: str, input_metadata: Optional[Json] = None) -> str:
+    return await _didkit.dereference_did_url(did_url, _to_json(input_metadata))
+
+
+async def issue_credential(credential: Json, options: Json, key: Json) -> str:
+    """Issue a credential, returning it as JSON, or as a JWT if the
+    `proofFormat` option is `jwt`."""
+    return await _didkit.issue_credential(
+        _to_json(credential), _to_json(options), _to_json(key)
+    )
+
+
+async def verify_credential(
+    credential: Json, options: Optional[Json] = None
+) -> VerificationReport:
+    report = await _didkit.verify_credential(_to_json(credential), _to_json(options))
+    return VerificationReport.from_json(report)
+
+
+async def issue_presentation(presentation: Json, options: Json, key: Json) -> str:
+    return await _didkit.issue_presentation(
+        _to_json(presentation), _to_json(options), _to_json(key)
+    )
+
+
+async def verify_presentation(
+    presentation: Json, options: Optional[Json] = None
+) -> VerificationReport:
+    if options is None:
+        options = {"proofPurpose": "authentication"}
+    report = await _didkit.verify_presentation(
+        _to_json(presentation), _to_json(options)
+    )
+    return VerificationReport.from_json(report)
+
+
+async def did_auth(did: str, options: Json, key: Json) -> str:
+    return await _didkit.did_auth(did, _to_json(options), _to_json(key))
+
+
+class KeyStore:
+    """In-memory store of private keys, by verification method ID.
+
+    Use it as a context manager, so that keys are dropped when leaving the
+    block:
+
+        async with KeyStore() as keys:
+            vm = await keys.generate_ed25519()
+            vc = await keys.issue_credential(credential, vm)
+    """
+
+    def __init__(self) -> None:
+        self._keys: Dict[str, str] = {}
+
+    @staticmethod
+    def from_file(path: str) -> "KeyStore":
+        """Load keys from a JSON file mapping verification method IDs to JWKs."""
+        store = KeyStore()
+        with open(path) as f:
+            for vm, key in json.load(f).items():
+                store._keys[vm] = _to_json(key)
+        return store
+
+    async def add(self, key: Json, method_pattern: str = "key") -> str:
+        """Add a key, returning its verification method ID."""
+        key = _to_json(key)
+        vm = await key_to_verification_method(method_pattern, key)
+        self._keys[vm] = key
+        return vm
+
+    async def generate_ed25519(self, method_pattern: str = "key") -> str:
+        return await self.add(generate_ed25519_key(), method_pattern)
+
+    def verification_methods(self) -> List[str]:
+        return list(self._keys)
+
+    def _key(self, verification_method: str) -> str:
+        try:
+            return self._keys[verification_method]
+        except KeyError:
+            raise DIDKitException("No key for " + verification_method) from None
+
+    async def issue_credential(
+        self,
+        credential: Json,
+        verification_method: str,
+        options: Optional[Dict[str, Any]] = None,
+    ) -> str:
+        options = dict(options or {}, verificationMethod=verification_method)
+        options.setdefault("proofPurpose", "assertionMethod")
+        return await issue_credential(
+            credential, options, self._key(verification_method)
+        )
+
+    async def issue_presentation(
+        self,
+        presentation: Json,
+        verification_method: str,
+        options: Optional[Dict[str, Any]] = None,
+    ) -> str:
+        options = dict(options or {}, verificationMethod=verification_method)
+        options.setdefault("proofPurpose", "authentication")
+        return await issue_presentation(
+            presentation, options, self._key(verification_method)
+        )
+
+    def close(self) -> None:
+        self._keys.clear()
+
+    def __enter__(self) -> "KeyStore":
+        return self
+
+    def __exit__(self, *exc) -> None:
+        self.close()
+
+    async def __aenter__(self) -> "KeyStore":
+        return self
+
+    async def __aexit__(self, *exc) -> None:
+        self.close()
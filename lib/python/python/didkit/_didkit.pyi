@@ -0,0 +1,15 @@
+from typing import Awaitable
+
+class DIDKitException(Exception): ...
+
+def get_version() -> str: ...
+def generate_ed25519_key() -> str: ...
+def key_to_did(method_pattern: str, jwk: str) -> str: ...
+def key_to_verification_method(method_pattern: str, jwk: str) -> Awaitable[str]: ...
+def resolve_did(did: str, input_metadata: str = "{}") -> Awaitable[str]: ...
+def dereference_did_url(did_url: str, input_metadata: str = "{}") -> Awaitable[str]: ...
+def issue_credential(credential: str, options: str, jwk: str) -> Awaitable[str]: ...
+def verify_credential(credential: str, options: str) -> Awaitable[str]: ...
+def issue_presentation(presentation: str, options: str, jwk: str) -> Awaitable[str]: ...
+def did_auth(holder: str, options: str, jwk: str) -> Awaitable[str]: ...
+def verify_presentation(presentation: str, options: str) -> Awaitable[str]: ...
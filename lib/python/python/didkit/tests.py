@@ -0,0 +1,144 @@
+import json
+import unittest
+import uuid
+
+import didkit
+
+KEY = "{\"kty\":\"OKP\",\"crv\":\"Ed25519\",\"x\":\"PBcY2yJ4h_cLUnQNcYhplu9KQQBNpGxP4sYcMPdlu6I\",\"d\":\"n5WUFIghmRYZi0rEYo2lz-Zg2B9B1KW4MYfJXwOXfyI\"}"
+DID = "did:key:z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK"
+VM = DID + "#z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK"
+
+
+class TestKeyMethods(unittest.IsolatedAsyncioTestCase):
+    def testGetLibraryVersion(self):
+        self.assertTrue(type(didkit.get_version()) is str)
+
+    def testGeneratesEd25519Key(self):
+        key = json.loads(didkit.generate_ed25519_key())
+        self.assertIn("kty", key.keys())
+        self.assertIn("crv", key.keys())
+        self.assertIn("x", key.keys())
+        self.assertIn("d", key.keys())
+
+    def testKeyToDID(self):
+        self.assertEqual(didkit.key_to_did("key", KEY), DID)
+
+    async def testKeyToVerificationMethod(self):
+        self.assertEqual(await didkit.key_to_verification_method("key", KEY), VM)
+
+    async def testResolveDID(self):
+        doc = json.loads(await didkit.resolve_did(DID))
+        self.assertEqual(doc["id"], DID)
+
+
+class TestCredentialMethods(unittest.IsolatedAsyncioTestCase):
+    def setUp(self):
+        self.credential = {
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "id": "http://example.org/credentials/3731",
+            "type": ["VerifiableCredential"],
+            "issuer": DID,
+            "issuanceDate": "2020-08-19T21:41:50Z",
+            "credentialSubject": {
+                "id": "did:example:d23dd687a7dc6787646f2eb98d0",
+            },
+        }
+        self.options = {
+            "proofPurpose": "assertionMethod",
+            "verificationMethod": VM,
+        }
+
+    async def testRaisesOnIssueWithEmptyObjects(self):
+        with self.assertRaises(didkit.DIDKitException):
+            await didkit.issue_credential("{}", "{}", "{}")
+
+    async def testIssuesCredentials(self):
+        credential = await didkit.issue_credential(self.credential, self.options, KEY)
+        report = await didkit.verify_credential(
+            credential, {"proofPurpose": "assertionMethod"}
+        )
+        self.assertTrue(report.ok, report.errors)
+        self.assertEqual(report.issuer, DID)
+
+    async def testIssuesJWTCredentials(self):
+        options = dict(self.options, proofFormat="jwt")
+        jwt = await didkit.issue_credential(self.credential, options, KEY)
+        report = await didkit.verify_credential(jwt, {"proofFormat": "jwt"})
+        self.assertTrue(report.ok, report.errors)
+
+
+class TestPresentationMethods(unittest.IsolatedAsyncioTestCase):
+    def setUp(self):
+        self.presentation = {
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "id": "http://example.org/presentations/3731",
+            "type": ["VerifiablePresentation"],
+            "holder": DID,
+            "verifiableCredential": {
+                "@context": "https://www.w3.org/2018/credentials/v1",
+                "id": "http://example.org/credentials/3731",
+                "type": ["VerifiableCredential"],
+                "issuer": "did:example:30e07a529f32d234f6181736bd3",
+                "issuanceDate": "2020-08-19T21:41:50Z",
+                "credentialSubject": {
+                    "id": "did:example:d23dd687a7dc6787646f2eb98d0",
+                },
+            },
+        }
+        self.options = {
+            "proofPurpose": "authentication",
+            "verificationMethod": VM,
+        }
+
+    async def testRaisesOnPresentWithEmptyObjects(self):
+        with self.assertRaises(didkit.DIDKitException):
+            await didkit.issue_presentation("{}", "{}", "{}")
+
+    async def testVerifyIssuedPresentation(self):
+        presentation = await didkit.issue_presentation(
+            self.presentation, self.options, KEY
+        )
+        report = await didkit.verify_presentation(presentation, self.options)
+        self.assertTrue(report.ok, report.errors)
+
+
+class TestAuthMethods(unittest.IsolatedAsyncioTestCase):
+    def setUp(self):
+        self.options = {
+            "proofPurpose": "authentication",
+            "verificationMethod": VM,
+            "challenge": str(uuid.uuid4()),
+        }
+
+    async def testRaisesOnPresentWithEmptyObjects(self):
+        with self.assertRaises(didkit.DIDKitException):
+            await didkit.did_auth("", "{}", "{}")
+
+    async def testIssueAndVerifyDIDAuthVerifiablePresentation(self):
+        presentation = await didkit.did_auth(DID, self.options, KEY)
+        report = await didkit.verify_presentation(presentation, self.options)
+        self.assertTrue(report.ok, report.errors)
+
+
+class TestKeyStore(unittest.IsolatedAsyncioTestCase):
+    async def testIssueWithKeyStore(self):
+        async with didkit.KeyStore() as keys:
+            vm = await keys.add(KEY)
+            self.assertEqual(vm, VM)
+            presentation = await keys.issue_presentation({
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "type": ["VerifiablePresentation"],
+                "holder": DID,
+            }, vm)
+        self.assertEqual(keys.verification_methods(), [])
+        report = await didkit.verify_presentation(presentation)
+        self.assertTrue(report.ok, report.errors)
+
+    async def testRaisesOnMissingKey(self):
+        with didkit.KeyStore() as keys:
+            with self.assertRaises(didkit.DIDKitException):
+                await keys.issue_credential({}, VM)
+
+
+if __name__ == '__main__':
+    unittest.main()
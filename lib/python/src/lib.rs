@@ -0,0 +1,229 @@
+//! Python bindings using PyO3.
+//!
+//! This is the native module `didkit._didkit`. Functions take and return JSON strings;
+//! functions that resolve DIDs or sign return awaitables, run on a Tokio runtime so that they
+//! do not block the asyncio event loop. The `didkit` Python package wraps them with typed
+//! results.
+
+use std::convert::TryFrom;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use didkit::error::Error as DIDKitError;
+use didkit::{
+    dereference, get_verification_method, CredentialOrJWT, DIDResolver, DereferencingInputMetadata,
+    IssueOptions, JWTOrLDPOptions, PresentationOrJWT, ResolutionInputMetadata, Source,
+    VerifiableCredential, VerifiablePresentation, DID_METHODS, JWK, URI,
+};
+
+create_exception!(_didkit, DIDKitException, PyException);
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn error(err: impl ToString) -> PyErr {
+    DIDKitException::new_err(err.to_string())
+}
+
+fn to_py(result: PyResult<String>) -> PyResult<PyObject> {
+    result.map(|string| Python::with_gil(|py| string.into_py(py)))
+}
+
+#[pyfunction]
+fn get_version() -> &'static str {
+    VERSION
+}
+
+#[pyfunction]
+fn generate_ed25519_key() -> PyResult<String> {
+    let jwk = JWK::generate_ed25519().map_err(error)?;
+    serde_json::to_string(&jwk).map_err(error)
+}
+
+#[pyfunction]
+fn key_to_did(method_pattern: &str, jwk: &str) -> PyResult<String> {
+    let key: JWK = serde_json::from_str(jwk).map_err(error)?;
+    DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, method_pattern))
+        .ok_or_else(|| error(DIDKitError::UnableToGenerateDID))
+}
+
+#[pyfunction]
+fn key_to_verification_method<'p>(
+    py: Python<'p>,
+    method_pattern: &str,
+    jwk: &str,
+) -> PyResult<&'p PyAny> {
+    let did = key_to_did(method_pattern, jwk)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        to_py(
+            get_verification_method(&did, DID_METHODS.to_resolver())
+                .await
+                .ok_or_else(|| error(DIDKitError::UnableToGetVerificationMethod)),
+        )
+    })
+}
+
+#[pyfunction(input_metadata = "\"{}\"")]
+fn resolve_did<'p>(py: Python<'p>, did: String, input_metadata: &str) -> PyResult<&'p PyAny> {
+    let input_metadata: ResolutionInputMetadata =
+        serde_json::from_str(input_metadata).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let (res_meta, doc, _) = DID_METHODS
+            .to_resolver()
+            .resolve(&did, &input_metadata)
+            .await;
+        to_py(match (res_meta.error, doc) {
+            (Some(err), _) => Err(error(err)),
+            (None, Some(doc)) => serde_json::to_string(&doc).map_err(error),
+            (None, None) => Err(error("No document resolved.")),
+        })
+    })
+}
+
+#[pyfunction(input_metadata = "\"{}\"")]
+fn dereference_did_url<'p>(
+    py: Python<'p>,
+    did_url: String,
+    input_metadata: &str,
+) -> PyResult<&'p PyAny> {
+    let input_metadata: DereferencingInputMetadata =
+        serde_json::from_str(input_metadata).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let deref_result = dereference(DID_METHODS.to_resolver(), &did_url, &input_metadata).await;
+        to_py(serde_json::to_string(&deref_result).map_err(error))
+    })
+}
+
+fn issue_options(options: &str) -> PyResult<IssueOptions> {
+    let options: JWTOrLDPOptions = serde_json::from_str(options).map_err(error)?;
+    IssueOptions::try_from(options).map_err(error)
+}
+
+/// Issue a credential. The result is the credential with a proof, or a JWT if the
+/// `proofFormat` option is `jwt`.
+#[pyfunction]
+fn issue_credential<'p>(
+    py: Python<'p>,
+    credential: &str,
+    options: &str,
+    jwk: &str,
+) -> PyResult<&'p PyAny> {
+    let credential: VerifiableCredential = serde_json::from_str(credential).map_err(error)?;
+    let options = issue_options(options)?;
+    let key: JWK = serde_json::from_str(jwk).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        to_py(
+            match didkit::issue_credential(credential, &options, &key, DID_METHODS.to_resolver())
+                .await
+                .map_err(error)?
+            {
+                CredentialOrJWT::JWT(jwt) => Ok(jwt),
+                vc => serde_json::to_string(&vc).map_err(error),
+            },
+        )
+    })
+}
+
+/// Verify a credential, given as JSON or as a JWT. The result is a JSON `VerificationReport`.
+#[pyfunction]
+fn verify_credential<'p>(py: Python<'p>, credential: &str, options: &str) -> PyResult<&'p PyAny> {
+    let credential = if credential.trim_start().starts_with('{') {
+        CredentialOrJWT::Credential(serde_json::from_str(credential).map_err(error)?)
+    } else {
+        CredentialOrJWT::JWT(credential.to_string())
+    };
+    let options: JWTOrLDPOptions = serde_json::from_str(options).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let report = didkit::verify_credential(&credential, &options, DID_METHODS.to_resolver())
+            .await
+            .map_err(error)?;
+        to_py(serde_json::to_string(&report).map_err(error))
+    })
+}
+
+fn issue_presentation_with(
+    py: Python,
+    presentation: VerifiablePresentation,
+    options: &str,
+    jwk: &str,
+) -> PyResult<&PyAny> {
+    let options = issue_options(options)?;
+    let key: JWK = serde_json::from_str(jwk).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        to_py(
+            match didkit::issue_presentation(
+                presentation,
+                &options,
+                &key,
+                DID_METHODS.to_resolver(),
+            )
+            .await
+            .map_err(error)?
+            {
+                PresentationOrJWT::JWT(jwt) => Ok(jwt),
+                vp => serde_json::to_string(&vp).map_err(error),
+            },
+        )
+    })
+}
+
+#[pyfunction]
+fn issue_presentation<'p>(
+    py: Python<'p>,
+    presentation: &str,
+    options: &str,
+    jwk: &str,
+) -> PyResult<&'p PyAny> {
+    let presentation: VerifiablePresentation = serde_json::from_str(presentation).map_err(error)?;
+    issue_presentation_with(py, presentation, options, jwk)
+}
+
+#[pyfunction]
+fn did_auth<'p>(py: Python<'p>, holder: String, options: &str, jwk: &str) -> PyResult<&'p PyAny> {
+    let presentation = VerifiablePresentation {
+        holder: Some(URI::String(holder)),
+        ..Default::default()
+    };
+    issue_presentation_with(py, presentation, options, jwk)
+}
+
+/// Verify a presentation, given as JSON or as a JWT. The result is a JSON `VerificationReport`.
+#[pyfunction]
+fn verify_presentation<'p>(
+    py: Python<'p>,
+    presentation: &str,
+    options: &str,
+) -> PyResult<&'p PyAny> {
+    let presentation = if presentation.trim_start().starts_with('{') {
+        PresentationOrJWT::VP(serde_json::from_str(presentation).map_err(error)?)
+    } else {
+        PresentationOrJWT::JWT(presentation.to_string())
+    };
+    let options: JWTOrLDPOptions = serde_json::from_str(options).map_err(error)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let report =
+            didkit::verify_presentation(&presentation, &options, DID_METHODS.to_resolver())
+                .await
+                .map_err(error)?;
+        to_py(serde_json::to_string(&report).map_err(error))
+    })
+}
+
+#[pymodule]
+fn _didkit(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("DIDKitException", py.get_type::<DIDKitException>())?;
+    m.add_function(wrap_pyfunction!(get_version, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_ed25519_key, m)?)?;
+    m.add_function(wrap_pyfunction!(key_to_did, m)?)?;
+    m.add_function(wrap_pyfunction!(key_to_verification_method, m)?)?;
+    m.add_function(wrap_pyfunction!(resolve_did, m)?)?;
+    m.add_function(wrap_pyfunction!(dereference_did_url, m)?)?;
+    m.add_function(wrap_pyfunction!(issue_credential, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_credential, m)?)?;
+    m.add_function(wrap_pyfunction!(issue_presentation, m)?)?;
+    m.add_function(wrap_pyfunction!(did_auth, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_presentation, m)?)?;
+    Ok(())
+}
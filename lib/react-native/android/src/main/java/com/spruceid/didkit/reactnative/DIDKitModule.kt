@@ -0,0 +1,94 @@
+package com.spruceid.didkit.reactnative
+
+import com.facebook.react.bridge.Promise
+import com.facebook.react.bridge.ReactApplicationContext
+import com.facebook.react.bridge.ReactContextBaseJavaModule
+import com.facebook.react.bridge.ReactMethod
+import com.spruceid.didkit.DidKitException
+import kotlinx.coroutines.CoroutineScope
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.SupervisorJob
+import kotlinx.coroutines.cancel
+import kotlinx.coroutines.launch
+import com.spruceid.didkit.didAuth as didkitDIDAuth
+import com.spruceid.didkit.dereferenceDidUrl as didkitDereferenceDIDURL
+import com.spruceid.didkit.generateEd25519Key as didkitGenerateEd25519Key
+import com.spruceid.didkit.getVersion as didkitGetVersion
+import com.spruceid.didkit.issueCredential as didkitIssueCredential
+import com.spruceid.didkit.issuePresentation as didkitIssuePresentation
+import com.spruceid.didkit.keyToDid as didkitKeyToDID
+import com.spruceid.didkit.keyToVerificationMethod as didkitKeyToVerificationMethod
+import com.spruceid.didkit.resolveDid as didkitResolveDID
+import com.spruceid.didkit.verifyCredential as didkitVerifyCredential
+import com.spruceid.didkit.verifyPresentation as didkitVerifyPresentation
+
+/**
+ * Bridge to the UniFFI bindings. Every call runs on a background dispatcher, and settles the
+ * promise with the result or with the DIDKit error.
+ */
+class DIDKitModule(reactContext: ReactApplicationContext) :
+    ReactContextBaseJavaModule(reactContext) {
+
+    private val scope = CoroutineScope(SupervisorJob() + Dispatchers.Default)
+
+    override fun getName() = "DIDKit"
+
+    override fun invalidate() {
+        scope.cancel()
+        super.invalidate()
+    }
+
+    private fun run(promise: Promise, block: suspend () -> String) {
+        scope.launch {
+            try {
+                promise.resolve(block())
+            } catch (e: DidKitException) {
+                promise.reject(e.javaClass.simpleName, e.message, e)
+            } catch (e: Exception) {
+                promise.reject("DIDKitError", e.message, e)
+            }
+        }
+    }
+
+    @ReactMethod
+    fun getVersion(promise: Promise) = run(promise) { didkitGetVersion() }
+
+    @ReactMethod
+    fun generateEd25519Key(promise: Promise) = run(promise) { didkitGenerateEd25519Key() }
+
+    @ReactMethod
+    fun keyToDID(methodPattern: String, key: String, promise: Promise) =
+        run(promise) { didkitKeyToDID(methodPattern, key) }
+
+    @ReactMethod
+    fun keyToVerificationMethod(methodPattern: String, key: String, promise: Promise) =
+        run(promise) { didkitKeyToVerificationMethod(methodPattern, key) }
+
+    @ReactMethod
+    fun resolveDID(did: String, inputMetadata: String, promise: Promise) =
+        run(promise) { didkitResolveDID(did, inputMetadata) }
+
+    @ReactMethod
+    fun dereferenceDIDURL(didUrl: String, inputMetadata: String, promise: Promise) =
+        run(promise) { didkitDereferenceDIDURL(didUrl, inputMetadata) }
+
+    @ReactMethod
+    fun issueCredential(credential: String, options: String, key: String, promise: Promise) =
+        run(promise) { didkitIssueCredential(credential, options, key) }
+
+    @ReactMethod
+    fun verifyCredential(credential: String, options: String, promise: Promise) =
+        run(promise) { didkitVerifyCredential(credential, options).json }
+
+    @ReactMethod
+    fun issuePresentation(presentation: String, options: String, key: String, promise: Promise) =
+        run(promise) { didkitIssuePresentation(presentation, options, key) }
+
+    @ReactMethod
+    fun DIDAuth(holder: String, options: String, key: String, promise: Promise) =
+        run(promise) { didkitDIDAuth(holder, options, key) }
+
+    @ReactMethod
+    fun verifyPresentation(presentation: String, options: String, promise: Promise) =
+        run(promise) { didkitVerifyPresentation(presentation, options).json }
+}
@@ -0,0 +1,31 @@
+#import <React/RCTBridgeModule.h>
+
+@interface RCT_EXTERN_MODULE(DIDKit, NSObject)
+
+RCT_EXTERN_METHOD(getVersion:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(generateEd25519Key:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(keyToDID:(NSString *)methodPattern key:(NSString *)key
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(keyToVerificationMethod:(NSString *)methodPattern key:(NSString *)key
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(resolveDID:(NSString *)did inputMetadata:(NSString *)inputMetadata
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(dereferenceDIDURL:(NSString *)didUrl inputMetadata:(NSString *)inputMetadata
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(issueCredential:(NSString *)credential options:(NSString *)options key:(NSString *)key
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(verifyCredential:(NSString *)credential options:(NSString *)options
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(issuePresentation:(NSString *)presentation options:(NSString *)options key:(NSString *)key
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(DIDAuth:(NSString *)holder options:(NSString *)options key:(NSString *)key
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+RCT_EXTERN_METHOD(verifyPresentation:(NSString *)presentation options:(NSString *)options
+                  resolver:(RCTPromiseResolveBlock)resolve rejecter:(RCTPromiseRejectBlock)reject)
+
++ (BOOL)requiresMainQueueSetup
+{
+  return NO;
+}
+
+@end
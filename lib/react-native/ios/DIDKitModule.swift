@@ -0,0 +1,119 @@
+import Foundation
+
+/// Bridge to the UniFFI bindings. Every call runs in a detached task, off the JavaScript thread,
+/// and settles the promise with the result or with the DIDKit error.
+@objc(DIDKit)
+class DIDKitModule: NSObject {
+
+  private func run(
+    _ resolve: @escaping RCTPromiseResolveBlock,
+    _ reject: @escaping RCTPromiseRejectBlock,
+    _ body: @escaping () async throws -> String
+  ) {
+    Task.detached {
+      do {
+        resolve(try await body())
+      } catch let error as DidKitError {
+        reject(String(describing: error).components(separatedBy: "(").first, "\(error)", error)
+      } catch {
+        reject("DIDKitError", "\(error)", error)
+      }
+    }
+  }
+
+  @objc func getVersion(
+    _ resolve: @escaping RCTPromiseResolveBlock, rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) { RNDIDKit.getVersion() }
+  }
+
+  @objc func generateEd25519Key(
+    _ resolve: @escaping RCTPromiseResolveBlock, rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) { try DIDKit.generateEd25519Key() }
+  }
+
+  @objc func keyToDID(
+    _ methodPattern: String, key: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) { try RNDIDKit.keyToDid(methodPattern: methodPattern, jwk: key) }
+  }
+
+  @objc func keyToVerificationMethod(
+    _ methodPattern: String, key: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.keyToVerificationMethod(methodPattern: methodPattern, jwk: key)
+    }
+  }
+
+  @objc func resolveDID(
+    _ did: String, inputMetadata: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) { try await RNDIDKit.resolveDid(did: did, inputMetadata: inputMetadata) }
+  }
+
+  @objc func dereferenceDIDURL(
+    _ didUrl: String, inputMetadata: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.dereferenceDidUrl(didUrl: didUrl, inputMetadata: inputMetadata)
+    }
+  }
+
+  @objc func issueCredential(
+    _ credential: String, options: String, key: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.issueCredential(credential: credential, options: options, jwk: key)
+    }
+  }
+
+  @objc func verifyCredential(
+    _ credential: String, options: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.verifyCredential(credential: credential, options: options).json
+    }
+  }
+
+  @objc func issuePresentation(
+    _ presentation: String, options: String, key: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.issuePresentation(presentation: presentation, options: options, jwk: key)
+    }
+  }
+
+  @objc func DIDAuth(
+    _ holder: String, options: String, key: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) { try await RNDIDKit.didAuth(holder: holder, options: options, jwk: key) }
+  }
+
+  @objc func verifyPresentation(
+    _ presentation: String, options: String,
+    resolver resolve: @escaping RCTPromiseResolveBlock,
+    rejecter reject: @escaping RCTPromiseRejectBlock
+  ) {
+    run(resolve, reject) {
+      try await RNDIDKit.verifyPresentation(presentation: presentation, options: options).json
+    }
+  }
+}
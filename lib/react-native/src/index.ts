@@ -0,0 +1,170 @@
+// React Native interface to DIDKit.
+//
+// The native modules call the UniFFI bindings on background threads, so none of these functions
+// block the JavaScript thread. Arguments and results are plain objects; JWT credentials and
+// presentations are strings.
+
+import { NativeModules, Platform } from "react-native";
+
+const LINKING_ERROR =
+  "The package '@spruceid/react-native-didkit' doesn't seem to be linked. Make sure to run " +
+  (Platform.OS === "ios" ? "'pod install' and " : "") +
+  "rebuild the app.";
+
+const Native = NativeModules.DIDKit
+  ? NativeModules.DIDKit
+  : new Proxy(
+      {},
+      {
+        get() {
+          throw new Error(LINKING_ERROR);
+        },
+      }
+    );
+
+export type JSONObject = { [key: string]: any };
+export type JWK = JSONObject;
+
+export interface ProofOptions {
+  type?: string;
+  verificationMethod?: string;
+  proofPurpose?: string;
+  created?: string;
+  challenge?: string;
+  domain?: string;
+  proofFormat?: "ldp" | "jwt";
+  [option: string]: any;
+}
+
+export interface VerificationMessage {
+  code: string;
+  message: string;
+}
+
+export interface VerificationReport {
+  checks: string[];
+  warnings: VerificationMessage[];
+  errors: VerificationMessage[];
+  proofs: JSONObject[];
+  issuer?: string;
+  holder?: string;
+}
+
+/** Operation for which a private key is about to be used. */
+export interface KeyAccessRequest {
+  operation: "issueCredential" | "issuePresentation" | "DIDAuth";
+  verificationMethod?: string;
+}
+
+/**
+ * Called before a private key is passed to the native module. Resolve to `false` to deny access,
+ * e.g. when biometric authentication with `expo-local-authentication` or
+ * `react-native-biometrics` fails. The request is then rejected with `KeyAccessDenied`.
+ */
+export type KeyAccessHook = (request: KeyAccessRequest) => boolean | Promise<boolean>;
+
+let keyAccessHook: KeyAccessHook | undefined;
+
+/** Set or clear the hook gating access to private keys. */
+export function setKeyAccessHook(hook: KeyAccessHook | undefined): void {
+  keyAccessHook = hook;
+}
+
+export class KeyAccessDenied extends Error {
+  constructor(public readonly request: KeyAccessRequest) {
+    super(`Key access denied for ${request.operation}`);
+    this.name = "KeyAccessDenied";
+  }
+}
+
+async function checkKeyAccess(request: KeyAccessRequest): Promise<void> {
+  if (keyAccessHook && !(await keyAccessHook(request))) {
+    throw new KeyAccessDenied(request);
+  }
+}
+
+function toJSON(value: JSONObject | string | undefined): string {
+  if (value === undefined) return "{}";
+  return typeof value === "string" ? value : JSON.stringify(value);
+}
+
+function parseResult(result: string): JSONObject | string {
+  return result.startsWith("{") ? JSON.parse(result) : result;
+}
+
+export function getVersion(): Promise<string> {
+  return Native.getVersion();
+}
+
+export async function generateEd25519Key(): Promise<JWK> {
+  return JSON.parse(await Native.generateEd25519Key());
+}
+
+export function keyToDID(methodPattern: string, key: JWK): Promise<string> {
+  return Native.keyToDID(methodPattern, toJSON(key));
+}
+
+export function keyToVerificationMethod(methodPattern: string, key: JWK): Promise<string> {
+  return Native.keyToVerificationMethod(methodPattern, toJSON(key));
+}
+
+export async function resolveDID(did: string, inputMetadata?: JSONObject): Promise<JSONObject> {
+  return JSON.parse(await Native.resolveDID(did, toJSON(inputMetadata)));
+}
+
+export async function dereferenceDIDURL(
+  didUrl: string,
+  inputMetadata?: JSONObject
+): Promise<JSONObject> {
+  return JSON.parse(await Native.dereferenceDIDURL(didUrl, toJSON(inputMetadata)));
+}
+
+/** Issue a credential. Resolves to the credential with a proof, or to a JWT string. */
+export async function issueCredential(
+  credential: JSONObject,
+  options: ProofOptions,
+  key: JWK
+): Promise<JSONObject | string> {
+  await checkKeyAccess({
+    operation: "issueCredential",
+    verificationMethod: options.verificationMethod,
+  });
+  return parseResult(await Native.issueCredential(toJSON(credential), toJSON(options), toJSON(key)));
+}
+
+export async function verifyCredential(
+  credential: JSONObject | string,
+  options?: ProofOptions
+): Promise<VerificationReport> {
+  return JSON.parse(await Native.verifyCredential(toJSON(credential), toJSON(options)));
+}
+
+export async function issuePresentation(
+  presentation: JSONObject,
+  options: ProofOptions,
+  key: JWK
+): Promise<JSONObject | string> {
+  await checkKeyAccess({
+    operation: "issuePresentation",
+    verificationMethod: options.verificationMethod,
+  });
+  return parseResult(
+    await Native.issuePresentation(toJSON(presentation), toJSON(options), toJSON(key))
+  );
+}
+
+export async function DIDAuth(
+  holder: string,
+  options: ProofOptions,
+  key: JWK
+): Promise<JSONObject | string> {
+  await checkKeyAccess({ operation: "DIDAuth", verificationMethod: options.verificationMethod });
+  return parseResult(await Native.DIDAuth(holder, toJSON(options), toJSON(key)));
+}
+
+export async function verifyPresentation(
+  presentation: JSONObject | string,
+  options: ProofOptions = { proofPurpose: "authentication" }
+): Promise<VerificationReport> {
+  return JSON.parse(await Native.verifyPresentation(toJSON(presentation), toJSON(options)));
+}
@@ -0,0 +1,154 @@
+//! Verification report credentials, for audit trails.
+//!
+//! [`report_credential`] describes the verification of a presentation as a credential issued by
+//! the verifier: which presentation (by holder, ID and SHA-256 digest) was verified, when, with
+//! which checks and proofs, and with what outcome, including for each embedded credential.
+//! [`attest_presentation`] builds and issues it with the verifier's signer.
+//!
+//! The report's terms are defined by an embedded context with `@vocab`, so that it can be signed
+//! with a linked data proof without loading a remote context.
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::credential::{CredentialBuilder, CredentialError};
+use crate::hex;
+use crate::signer::{Signer, SignerError};
+use crate::verification::VerificationReport;
+use crate::{
+    issue_credential, CredentialOrJWT, DIDResolver, IssueOptions, PresentationOrJWT,
+    VerifiableCredential,
+};
+
+/// Type of verification report credentials.
+pub const REPORT_CREDENTIAL_TYPE: &str = "VerificationReportCredential";
+
+/// Vocabulary of the terms of verification report credentials.
+pub const REPORT_VOCAB: &str = "https://spruceid.dev/didkit/verification-report#";
+
+#[derive(Error, Debug)]
+pub enum AttestationError {
+    #[error("Unable to serialize presentation: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Credential: {0}")]
+    Credential(#[from] CredentialError),
+    #[error("Signer: {0}")]
+    Signer(#[from] SignerError),
+}
+
+/// SHA-256 digest, in hex, of a presentation as it was verified: the JWT, or the JSON
+/// serialization of the presentation.
+pub fn presentation_digest(presentation: &PresentationOrJWT) -> Result<String, AttestationError> {
+    let digest = match presentation {
+        PresentationOrJWT::VP(vp) => Sha256::digest(&serde_json::to_vec(vp)?),
+        PresentationOrJWT::JWT(jwt) => Sha256::digest(jwt.trim().as_bytes()),
+    };
+    Ok(hex::encode(&digest))
+}
+
+/// Build an (unsigned) credential, issued by `verifier`, reporting the verification of a
+/// presentation.
+pub fn report_credential(
+    presentation: &PresentationOrJWT,
+    report: &VerificationReport,
+    verifier: &str,
+) -> Result<VerifiableCredential, AttestationError> {
+    let presentation_id = match presentation {
+        PresentationOrJWT::VP(vp) => vp.id.as_ref().map(|id| id.to_string()),
+        PresentationOrJWT::JWT(_) => None,
+    };
+    let verified_at = report
+        .timing
+        .as_ref()
+        .map(|timing| timing.finished)
+        .unwrap_or_else(Utc::now);
+    let credentials: Vec<Value> = report
+        .credentials
+        .iter()
+        .map(|credential| {
+            json!({
+                "issuer": credential.issuer,
+                "verified": credential.is_success(),
+            })
+        })
+        .collect();
+    let subject = json!({
+        "type": "PresentationVerification",
+        "verifier": verifier,
+        "presentation": {
+            "id": presentation_id,
+            "holder": report.holder,
+            "digestSHA256": presentation_digest(presentation)?,
+        },
+        "verified": report.is_success(),
+        "verificationTime": verified_at.to_rfc3339(),
+        "checks": report.checks,
+        "policyChecks": report.policy_checks,
+        "proofs": report.proofs,
+        "errors": report.errors,
+        "warnings": report.warnings,
+        "credentials": credentials,
+    });
+    Ok(CredentialBuilder::new()
+        .context(json!({ "@vocab": REPORT_VOCAB }))
+        .type_(REPORT_CREDENTIAL_TYPE)
+        .issuer(verifier)
+        .issuance_date(verified_at)
+        .subject(strip_nulls(subject))
+        .build()?)
+}
+
+/// Remove null properties, which JSON-LD would drop when signing.
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| (name, strip_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(strip_nulls).collect()),
+        value => value,
+    }
+}
+
+/// Issue a verification report credential for a presentation, signed by the verifier.
+pub async fn attest_presentation(
+    presentation: &PresentationOrJWT,
+    report: &VerificationReport,
+    verifier: &str,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<CredentialOrJWT, AttestationError> {
+    let credential = report_credential(presentation, report, verifier)?;
+    Ok(issue_credential(credential, options, signer, resolver).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::ErrorCode;
+
+    #[test]
+    fn report_subject() {
+        let presentation = PresentationOrJWT::JWT("a.b.c".to_string());
+        let mut report = VerificationReport::error(ErrorCode::InvalidJWT, "Invalid JWT");
+        report.holder = Some("did:example:holder".to_string());
+        let credential = report_credential(&presentation, &report, "did:example:verifier").unwrap();
+        let credential = serde_json::to_value(credential).unwrap();
+        let subject = &credential["credentialSubject"];
+        assert_eq!(credential["issuer"], "did:example:verifier");
+        assert_eq!(subject["verified"], false);
+        assert_eq!(subject["presentation"]["holder"], "did:example:holder");
+        assert!(subject["presentation"].get("id").is_none());
+        assert_eq!(
+            subject["presentation"]["digestSHA256"],
+            presentation_digest(&presentation).unwrap()
+        );
+        assert_eq!(subject["errors"][0]["code"], "invalidJWT");
+    }
+}
@@ -0,0 +1,559 @@
+//! Credentials in PDF417 and Aztec barcodes, for printing on physical documents.
+//!
+//! A credential is compressed in the manner of [CBOR-LD][cbor-ld]: with a compression profile,
+//! the terms of its JSON-LD contexts are replaced by integer codes, its context URLs by their
+//! index in the profile, and its date-times and multibase values by their binary forms, and the
+//! result is encoded as CBOR. The payload has the CBOR-LD envelope, a `0xcb1d` tag of the
+//! profile's registry entry ID and the compressed credential, and is written in barcodes as text
+//! compatible with their alphanumeric modes: `VC1-` followed by the payload in base32.
+//!
+//! The compression profiles are DIDKit's own, not entries of the CBOR-LD registry, so that
+//! their payloads are decoded by DIDKit. Compression is lossless: the decoded credential is the
+//! original one, so its proof can be verified offline by field devices.
+//!
+//! [cbor-ld]: https://json-ld.github.io/cbor-ld-spec/
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde_cbor::Value as CborValue;
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+/// CBOR tag of CBOR-LD payloads.
+pub const CBOR_LD_TAG: u64 = 0xcb1d;
+/// Prefix of the barcode text of a credential.
+pub const BARCODE_TEXT_PREFIX: &str = "VC1-";
+
+/// Codes of JSON-LD keywords, as in CBOR-LD.
+const KEYWORDS: &[&str] = &["@context", "@type", "@id", "@value", "@direction", "@graph"];
+/// First code of the terms of a profile.
+const FIRST_TERM_CODE: u64 = 100;
+/// Terms whose values are RFC 3339 date-times.
+const DATE_TIME_TERMS: &[&str] = &[
+    "issuanceDate",
+    "expirationDate",
+    "validFrom",
+    "validUntil",
+    "created",
+    "expires",
+];
+/// Terms whose values are multibase-encoded.
+const MULTIBASE_TERMS: &[&str] = &["proofValue", "publicKeyMultibase"];
+/// Terms whose values may be terms.
+const TYPE_TERMS: &[&str] = &[
+    "@type",
+    "type",
+    "proofPurpose",
+    "cryptosuite",
+    "statusPurpose",
+];
+
+const VC_CONTEXTS: &[&str] = &[
+    "https://www.w3.org/2018/credentials/v1",
+    "https://www.w3.org/ns/credentials/v2",
+    "https://w3id.org/security/data-integrity/v2",
+    "https://w3id.org/security/suites/ed25519-2020/v1",
+    "https://w3id.org/security/multikey/v1",
+    "https://w3id.org/vc/status-list/2021/v1",
+];
+
+const VC_TERMS: &[&str] = &[
+    "BitstringStatusListEntry",
+    "DataIntegrityProof",
+    "Ed25519Signature2020",
+    "JsonSchema",
+    "StatusList2021Entry",
+    "VerifiableCredential",
+    "VerifiablePresentation",
+    "assertionMethod",
+    "authentication",
+    "challenge",
+    "created",
+    "credentialSchema",
+    "credentialStatus",
+    "credentialSubject",
+    "cryptosuite",
+    "description",
+    "domain",
+    "evidence",
+    "expirationDate",
+    "expires",
+    "holder",
+    "id",
+    "issuanceDate",
+    "issued",
+    "issuer",
+    "name",
+    "nonce",
+    "proof",
+    "proofPurpose",
+    "proofValue",
+    "refreshService",
+    "statusListCredential",
+    "statusListIndex",
+    "statusPurpose",
+    "termsOfUse",
+    "type",
+    "validFrom",
+    "validUntil",
+    "verifiableCredential",
+    "verificationMethod",
+];
+
+const CITIZENSHIP_CONTEXTS: &[&str] = &[
+    "https://www.w3.org/2018/credentials/v1",
+    "https://www.w3.org/ns/credentials/v2",
+    "https://w3id.org/citizenship/v1",
+    "https://w3id.org/citizenship/v2",
+    "https://w3id.org/security/data-integrity/v2",
+    "https://w3id.org/security/suites/ed25519-2020/v1",
+];
+
+const CITIZENSHIP_TERMS: &[&str] = &[
+    "EmployablePerson",
+    "PermanentResident",
+    "PermanentResidentCard",
+    "Person",
+    "birthCountry",
+    "birthDate",
+    "commuterClassification",
+    "familyName",
+    "gender",
+    "givenName",
+    "identifier",
+    "image",
+    "lprCategory",
+    "lprNumber",
+    "residentSince",
+];
+
+/// A compression profile: the contexts and terms that it encodes as integers.
+#[derive(Debug)]
+pub struct CompressionProfile {
+    /// Name of the profile, e.g. `vc`
+    pub name: &'static str,
+    /// Registry entry ID identifying the profile in payloads
+    pub registry_entry_id: u64,
+    /// Context URLs, encoded as their index
+    pub contexts: &'static [&'static str],
+    /// Lists of terms, encoded as even codes from 100 in order, and odd codes for array values
+    pub terms: &'static [&'static [&'static str]],
+}
+
+/// Compression profiles.
+pub const PROFILES: &[CompressionProfile] = &[
+    CompressionProfile {
+        name: "vc",
+        registry_entry_id: 0x6400,
+        contexts: VC_CONTEXTS,
+        terms: &[VC_TERMS],
+    },
+    CompressionProfile {
+        name: "citizenship",
+        registry_entry_id: 0x6401,
+        contexts: CITIZENSHIP_CONTEXTS,
+        terms: &[VC_TERMS, CITIZENSHIP_TERMS],
+    },
+];
+
+/// Barcode symbologies for credentials.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarcodeFormat {
+    PDF417,
+    Aztec,
+}
+
+#[derive(Error, Debug)]
+pub enum BarcodeError {
+    #[error("Unknown compression profile: {0}")]
+    UnknownProfile(String),
+    #[error("Unknown registry entry ID: {0}")]
+    UnknownRegistryEntry(u64),
+    #[error("Unknown barcode format: {0}")]
+    UnknownFormat(String),
+    #[error("Unable to compress value of {0}: {1}")]
+    Uncompressible(String, String),
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(String),
+    #[error("Invalid barcode text")]
+    InvalidText,
+    #[error("Barcode text is {0} characters, more than the {1} that a {2} barcode holds")]
+    TooLarge(usize, usize, BarcodeFormat),
+    #[error("CBOR: {0}")]
+    CBOR(#[from] serde_cbor::Error),
+}
+
+impl FromStr for BarcodeFormat {
+    type Err = BarcodeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pdf417" => Ok(Self::PDF417),
+            "aztec" => Ok(Self::Aztec),
+            _ => Err(BarcodeError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for BarcodeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PDF417 => "pdf417",
+            Self::Aztec => "aztec",
+        })
+    }
+}
+
+impl BarcodeFormat {
+    /// Maximum characters of barcode text in a barcode of the format.
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::PDF417 => 1850,
+            Self::Aztec => 3067,
+        }
+    }
+}
+
+/// Get a compression profile by name.
+pub fn profile(name: &str) -> Result<&'static CompressionProfile, BarcodeError> {
+    PROFILES
+        .iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| BarcodeError::UnknownProfile(name.to_string()))
+}
+
+impl CompressionProfile {
+    fn term_code(&self, term: &str) -> Option<u64> {
+        if let Some(i) = KEYWORDS.iter().position(|keyword| *keyword == term) {
+            return Some(2 * i as u64);
+        }
+        self.terms
+            .iter()
+            .flat_map(|terms| terms.iter())
+            .position(|t| *t == term)
+            .map(|i| FIRST_TERM_CODE + 2 * i as u64)
+    }
+
+    fn term(&self, code: u64) -> Option<&'static str> {
+        if code < FIRST_TERM_CODE {
+            return KEYWORDS.get(code as usize / 2).copied();
+        }
+        self.terms
+            .iter()
+            .flat_map(|terms| terms.iter())
+            .nth((code - FIRST_TERM_CODE) as usize / 2)
+            .copied()
+    }
+
+    fn compress_value(&self, term: &str, value: &Value) -> Result<CborValue, BarcodeError> {
+        let uncompressible =
+            |reason: &str| BarcodeError::Uncompressible(term.to_string(), reason.to_string());
+        Ok(match value {
+            Value::Null => CborValue::Null,
+            Value::Bool(b) => CborValue::Bool(*b),
+            Value::Number(n) => {
+                if term == "@context"
+                    || TYPE_TERMS.contains(&term)
+                    || DATE_TIME_TERMS.contains(&term)
+                    || MULTIBASE_TERMS.contains(&term)
+                {
+                    return Err(uncompressible("expected string"));
+                }
+                match (n.as_i64(), n.as_u64(), n.as_f64()) {
+                    (Some(i), _, _) => CborValue::Integer(i.into()),
+                    (_, Some(u), _) => CborValue::Integer(u.into()),
+                    (_, _, Some(f)) => CborValue::Float(f),
+                    _ => return Err(uncompressible("invalid number")),
+                }
+            }
+            Value::String(s) => self
+                .compress_string(term, s)
+                .unwrap_or_else(|| CborValue::Text(s.to_string())),
+            Value::Array(values) => CborValue::Array(
+                values
+                    .iter()
+                    .map(|value| self.compress_value(term, value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Object(object) => self.compress_object(object)?,
+        })
+    }
+
+    fn compress_string(&self, term: &str, s: &str) -> Option<CborValue> {
+        if term == "@context" {
+            let i = self.contexts.iter().position(|url| *url == s)?;
+            return Some(CborValue::Integer(i as i128));
+        }
+        if TYPE_TERMS.contains(&term) {
+            return Some(CborValue::Integer(self.term_code(s)?.into()));
+        }
+        if DATE_TIME_TERMS.contains(&term) {
+            let date_time = DateTime::parse_from_rfc3339(s).ok()?.with_timezone(&Utc);
+            if date_time.to_rfc3339_opts(SecondsFormat::Secs, true) != s {
+                return None;
+            }
+            return Some(CborValue::Integer(date_time.timestamp().into()));
+        }
+        if MULTIBASE_TERMS.contains(&term) {
+            let mut bytes = vec![b'z'];
+            bytes.extend(bs58::decode(s.strip_prefix('z')?).into_vec().ok()?);
+            if bs58::encode(&bytes[1..]).into_string() != s[1..] {
+                return None;
+            }
+            return Some(CborValue::Bytes(bytes));
+        }
+        None
+    }
+
+    fn compress_object(&self, object: &Map<String, Value>) -> Result<CborValue, BarcodeError> {
+        let mut map = BTreeMap::new();
+        for (term, value) in object {
+            let key = match self.term_code(term) {
+                Some(code) if value.is_array() => CborValue::Integer((code + 1).into()),
+                Some(code) => CborValue::Integer(code.into()),
+                None => CborValue::Text(term.to_string()),
+            };
+            map.insert(key, self.compress_value(term, value)?);
+        }
+        Ok(CborValue::Map(map))
+    }
+
+    fn decompress_value(&self, term: &str, value: CborValue) -> Result<Value, BarcodeError> {
+        let invalid =
+            |reason: &str| BarcodeError::InvalidPayload(format!("value of {}: {}", term, reason));
+        Ok(match value {
+            CborValue::Null => Value::Null,
+            CborValue::Bool(b) => Value::Bool(b),
+            CborValue::Integer(i) if term == "@context" => {
+                let url = usize::try_from(i)
+                    .ok()
+                    .and_then(|i| self.contexts.get(i))
+                    .ok_or_else(|| invalid("unknown context"))?;
+                Value::String(url.to_string())
+            }
+            CborValue::Integer(i) if TYPE_TERMS.contains(&term) => {
+                let code = u64::try_from(i).map_err(|_| invalid("unknown term"))?;
+                Value::String(
+                    self.term(code)
+                        .ok_or_else(|| invalid("unknown term"))?
+                        .to_string(),
+                )
+            }
+            CborValue::Integer(i) if DATE_TIME_TERMS.contains(&term) => {
+                let timestamp = i64::try_from(i).map_err(|_| invalid("invalid date-time"))?;
+                let date_time = Utc
+                    .timestamp_opt(timestamp, 0)
+                    .single()
+                    .ok_or_else(|| invalid("invalid date-time"))?;
+                Value::String(date_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            }
+            CborValue::Integer(i) => {
+                let number = i64::try_from(i)
+                    .map(Number::from)
+                    .or_else(|_| u64::try_from(i).map(Number::from))
+                    .map_err(|_| invalid("integer out of range"))?;
+                Value::Number(number)
+            }
+            CborValue::Float(f) => {
+                Value::Number(Number::from_f64(f).ok_or_else(|| invalid("invalid number"))?)
+            }
+            CborValue::Bytes(bytes) if MULTIBASE_TERMS.contains(&term) => match bytes.split_first()
+            {
+                Some((b'z', bytes)) => {
+                    Value::String(format!("z{}", bs58::encode(bytes).into_string()))
+                }
+                _ => return Err(invalid("unsupported multibase")),
+            },
+            CborValue::Text(s) => Value::String(s),
+            CborValue::Array(values) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|value| self.decompress_value(term, value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            CborValue::Map(map) => self.decompress_object(map)?,
+            _ => return Err(invalid("unexpected CBOR type")),
+        })
+    }
+
+    fn decompress_object(
+        &self,
+        map: BTreeMap<CborValue, CborValue>,
+    ) -> Result<Value, BarcodeError> {
+        let mut object = Map::new();
+        for (key, value) in map {
+            let term = match key {
+                CborValue::Text(term) => term,
+                CborValue::Integer(code) => u64::try_from(code)
+                    .ok()
+                    .and_then(|code| self.term(code))
+                    .ok_or_else(|| BarcodeError::InvalidPayload(format!("unknown term {}", code)))?
+                    .to_string(),
+                _ => return Err(BarcodeError::InvalidPayload("unexpected key".to_string())),
+            };
+            let value = self.decompress_value(&term, value)?;
+            object.insert(term, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+/// Compress a credential, or another JSON-LD document, with a compression profile, into a
+/// CBOR-LD payload. Fails if the credential would not be decompressed to the same JSON.
+pub fn compress(credential: &Value, profile: &CompressionProfile) -> Result<Vec<u8>, BarcodeError> {
+    let compressed = profile.compress_value("", credential)?;
+    let payload = serde_cbor::to_vec(&CborValue::Tag(
+        CBOR_LD_TAG,
+        Box::new(CborValue::Array(vec![
+            CborValue::Integer(profile.registry_entry_id.into()),
+            compressed,
+        ])),
+    ))?;
+    if &decompress(&payload)? != credential {
+        return Err(BarcodeError::Uncompressible(
+            "credential".to_string(),
+            "not decompressed losslessly".to_string(),
+        ));
+    }
+    Ok(payload)
+}
+
+/// Decompress a CBOR-LD payload, with the compression profile of its registry entry ID.
+pub fn decompress(payload: &[u8]) -> Result<Value, BarcodeError> {
+    let invalid = |reason: &str| BarcodeError::InvalidPayload(reason.to_string());
+    let mut items = match serde_cbor::from_slice(payload)? {
+        CborValue::Tag(CBOR_LD_TAG, value) => match *value {
+            CborValue::Array(items) if items.len() == 2 => items,
+            _ => return Err(invalid("expected registry entry ID and value")),
+        },
+        _ => return Err(invalid("expected CBOR-LD tag")),
+    };
+    let value = items.pop().unwrap_or(CborValue::Null);
+    let id = match items.pop() {
+        Some(CborValue::Integer(id)) => {
+            u64::try_from(id).map_err(|_| invalid("invalid registry entry ID"))?
+        }
+        _ => return Err(invalid("expected registry entry ID")),
+    };
+    let profile = PROFILES
+        .iter()
+        .find(|profile| profile.registry_entry_id == id)
+        .ok_or(BarcodeError::UnknownRegistryEntry(id))?;
+    profile.decompress_value("", value)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Barcode text of a payload: `VC1-` and the payload in unpadded uppercase RFC 4648 base32.
+pub fn to_barcode_text(payload: &[u8]) -> String {
+    let mut text = BARCODE_TEXT_PREFIX.to_string();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in payload {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        text.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    text
+}
+
+/// Payload of a barcode text.
+pub fn from_barcode_text(text: &str) -> Result<Vec<u8>, BarcodeError> {
+    let data = text
+        .trim()
+        .strip_prefix(BARCODE_TEXT_PREFIX)
+        .ok_or(BarcodeError::InvalidText)?;
+    let mut payload = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in data.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or(BarcodeError::InvalidText)?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            payload.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(payload)
+}
+
+/// Encode a credential as barcode text, checking that it fits in a barcode of the given format.
+pub fn encode(
+    credential: &Value,
+    profile: &CompressionProfile,
+    format: BarcodeFormat,
+) -> Result<String, BarcodeError> {
+    let text = to_barcode_text(&compress(credential, profile)?);
+    if text.len() > format.capacity() {
+        return Err(BarcodeError::TooLarge(
+            text.len(),
+            format.capacity(),
+            format,
+        ));
+    }
+    Ok(text)
+}
+
+/// Decode a credential from barcode text.
+pub fn decode(text: &str) -> Result<Value, BarcodeError> {
+    decompress(&from_barcode_text(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn barcode_round_trip() {
+        let credential = json!({
+            "@context": [
+                "https://www.w3.org/2018/credentials/v1",
+                "https://w3id.org/citizenship/v1"
+            ],
+            "type": ["VerifiableCredential", "PermanentResidentCard"],
+            "issuer": "did:key:z6MkjLrk3gKS2nnkeWcmcxiZPGskmesDpuwRBorgHxUXfxnG",
+            "issuanceDate": "2024-01-01T00:00:00Z",
+            "credentialSubject": {
+                "type": ["PermanentResident", "Person"],
+                "givenName": "JOHN",
+                "familyName": "SMITH",
+                "birthDate": "1958-07-17",
+                "lprNumber": "999-999-999"
+            },
+            "proof": {
+                "type": "Ed25519Signature2020",
+                "created": "2024-01-01T00:00:00.123Z",
+                "proofPurpose": "assertionMethod",
+                "proofValue": "z5vgs4Nes1NbgwRkFjs9XRu3EYoCe4EiS1GcS8GUPHsAFTRLGNKj1j6dPWrXLbXfLXU",
+                "verificationMethod": "did:key:z6MkjLrk3gKS2nnkeWcmcxiZPGskmesDpuwRBorgHxUXfxnG#z6MkjLrk3gKS2nnkeWcmcxiZPGskmesDpuwRBorgHxUXfxnG"
+            }
+        });
+        let profile = profile("citizenship").unwrap();
+        let text = encode(&credential, profile, BarcodeFormat::PDF417).unwrap();
+        assert!(text.starts_with(BARCODE_TEXT_PREFIX));
+        assert!(text.len() < to_barcode_text(credential.to_string().as_bytes()).len());
+        assert_eq!(decode(&text).unwrap(), credential);
+
+        let vc_text = encode(
+            &credential,
+            super::profile("vc").unwrap(),
+            BarcodeFormat::Aztec,
+        );
+        assert_eq!(decode(&vc_text.unwrap()).unwrap(), credential);
+        assert!(decode("VC1-0").is_err());
+    }
+}
@@ -0,0 +1,566 @@
+//! [BBS signatures][bbs], with the BLS12-381-SHA-256 ciphersuite, for JSON Web Proofs (see
+//! [`crate::jwp`]).
+//!
+//! A BBS signature covers a header and a list of messages. The holder of a signature derives
+//! zero-knowledge proofs of it that disclose only some of the messages, bound to a presentation
+//! header; proofs are unlinkable to each other and to the signature.
+//!
+//! Keys, signatures and proofs are in the octet encodings of the draft: 32-byte secret keys,
+//! 96-byte compressed G2 public keys, 80-byte signatures, and proofs of 272 bytes plus 32 bytes
+//! per undisclosed message.
+//!
+//! [bbs]: https://datatracker.ietf.org/doc/draft-irtf-cfrg-bbs-signatures/
+
+use std::convert::TryInto;
+
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+const CIPHERSUITE_ID: &[u8] = b"BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_";
+const API_ID: &[u8] = b"BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_H2G_HM2S_";
+/// Length of the uniform bytes hashed to a scalar
+const EXPAND_LEN: usize = 48;
+const POINT_LENGTH: usize = 48;
+const SCALAR_LENGTH: usize = 32;
+
+pub const SECRET_KEY_LENGTH: usize = 32;
+pub const PUBLIC_KEY_LENGTH: usize = 96;
+pub const SIGNATURE_LENGTH: usize = POINT_LENGTH + SCALAR_LENGTH;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BbsError {
+    #[error("Key material must be at least 32 bytes")]
+    KeyMaterial,
+    #[error("Invalid secret key")]
+    InvalidSecretKey,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Invalid proof")]
+    InvalidProof,
+    #[error("Message index out of range: {0}")]
+    MessageIndex(usize),
+}
+
+lazy_static! {
+    /// Base point of signatures
+    static ref P1: G1Projective =
+        create_generators(&api_dst(b"BP_MESSAGE_GENERATOR_SEED"), 1)[0];
+}
+
+fn api_dst(suffix: &[u8]) -> Vec<u8> {
+    [API_ID, suffix].concat()
+}
+
+/// `expand_message_xmd` of RFC 9380, with SHA-256.
+fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let ell = (len + 31) / 32;
+    let mut dst_prime = dst.to_vec();
+    dst_prime.push(dst.len() as u8);
+    let b_0 = Sha256::new()
+        .chain(&[0u8; 64][..])
+        .chain(msg)
+        .chain((len as u16).to_be_bytes())
+        .chain([0u8])
+        .chain(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain(&b_0)
+        .chain([1u8])
+        .chain(&dst_prime)
+        .finalize();
+    let mut uniform = b_i.to_vec();
+    for i in 2..=ell {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new()
+            .chain(&xored)
+            .chain([i as u8])
+            .chain(&dst_prime)
+            .finalize();
+        uniform.extend_from_slice(&b_i);
+    }
+    uniform.truncate(len);
+    uniform
+}
+
+fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Scalar {
+    let uniform = expand_message_xmd(msg, dst, EXPAND_LEN);
+    // The uniform bytes are big-endian; from_bytes_wide takes little-endian bytes.
+    let mut wide = [0u8; 64];
+    for (i, byte) in uniform.iter().rev().enumerate() {
+        wide[i] = *byte;
+    }
+    Scalar::from_bytes_wide(&wide)
+}
+
+fn hash_to_g1(msg: &[u8], dst: &[u8]) -> G1Projective {
+    <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, dst)
+}
+
+fn random_scalar() -> Scalar {
+    let mut wide = [0u8; 64];
+    rand::thread_rng().fill_bytes(&mut wide);
+    Scalar::from_bytes_wide(&wide)
+}
+
+fn scalar_to_bytes(scalar: &Scalar) -> [u8; SCALAR_LENGTH] {
+    let mut bytes = scalar.to_bytes();
+    bytes.reverse();
+    bytes
+}
+
+/// Decode a big-endian scalar, less than the group order.
+fn scalar_from_bytes(bytes: &[u8]) -> Option<Scalar> {
+    let mut bytes: [u8; SCALAR_LENGTH] = bytes.try_into().ok()?;
+    bytes.reverse();
+    Option::from(Scalar::from_bytes(&bytes))
+}
+
+fn point_to_bytes(point: &G1Projective) -> [u8; POINT_LENGTH] {
+    G1Affine::from(point).to_compressed()
+}
+
+/// Decode a compressed G1 point, other than the identity.
+fn point_from_bytes(bytes: &[u8]) -> Option<G1Projective> {
+    let bytes: [u8; POINT_LENGTH] = bytes.try_into().ok()?;
+    let point: Option<G1Affine> = Option::from(G1Affine::from_compressed(&bytes));
+    point
+        .filter(|point| !bool::from(point.is_identity()))
+        .map(G1Projective::from)
+}
+
+fn parse_public_key(public_key: &[u8]) -> Result<G2Affine, BbsError> {
+    let bytes: [u8; PUBLIC_KEY_LENGTH] = public_key
+        .try_into()
+        .map_err(|_| BbsError::InvalidPublicKey)?;
+    let point: Option<G2Affine> = Option::from(G2Affine::from_compressed(&bytes));
+    point
+        .filter(|point| !bool::from(point.is_identity()))
+        .ok_or(BbsError::InvalidPublicKey)
+}
+
+fn parse_secret_key(secret_key: &[u8]) -> Result<Scalar, BbsError> {
+    scalar_from_bytes(secret_key)
+        .filter(|secret_key| secret_key != &Scalar::zero())
+        .ok_or(BbsError::InvalidSecretKey)
+}
+
+fn parse_signature(signature: &[u8]) -> Result<(G1Projective, Scalar), BbsError> {
+    if signature.len() != SIGNATURE_LENGTH {
+        return Err(BbsError::InvalidSignature);
+    }
+    let a = point_from_bytes(&signature[..POINT_LENGTH]).ok_or(BbsError::InvalidSignature)?;
+    let e = scalar_from_bytes(&signature[POINT_LENGTH..])
+        .filter(|e| e != &Scalar::zero())
+        .ok_or(BbsError::InvalidSignature)?;
+    Ok((a, e))
+}
+
+/// Generators of G1 from a seed, deterministically.
+fn create_generators(generator_seed: &[u8], count: usize) -> Vec<G1Projective> {
+    let seed_dst = api_dst(b"SIG_GENERATOR_SEED_");
+    let generator_dst = api_dst(b"SIG_GENERATOR_DST_");
+    let mut v = expand_message_xmd(generator_seed, &seed_dst, EXPAND_LEN);
+    (1..=count as u64)
+        .map(|i| {
+            v = expand_message_xmd(&[&v[..], &i.to_be_bytes()].concat(), &seed_dst, EXPAND_LEN);
+            hash_to_g1(&v, &generator_dst)
+        })
+        .collect()
+}
+
+/// The domain generator `Q_1` and the generators of `count` messages.
+fn generators(count: usize) -> (G1Projective, Vec<G1Projective>) {
+    let mut generators = create_generators(&api_dst(b"MESSAGE_GENERATOR_SEED"), count + 1);
+    let q_1 = generators.remove(0);
+    (q_1, generators)
+}
+
+fn message_scalars<M: AsRef<[u8]>>(messages: &[M]) -> Vec<Scalar> {
+    let dst = api_dst(b"MAP_MSG_TO_SCALAR_AS_HASH_");
+    messages
+        .iter()
+        .map(|message| hash_to_scalar(message.as_ref(), &dst))
+        .collect()
+}
+
+/// Scalar binding a signature to the public key, the generators and the header.
+fn calculate_domain(
+    public_key: &[u8],
+    q_1: &G1Projective,
+    h: &[G1Projective],
+    header: &[u8],
+) -> Scalar {
+    let mut input = public_key.to_vec();
+    input.extend_from_slice(&(h.len() as u64).to_be_bytes());
+    input.extend_from_slice(&point_to_bytes(q_1));
+    for h_i in h {
+        input.extend_from_slice(&point_to_bytes(h_i));
+    }
+    input.extend_from_slice(API_ID);
+    input.extend_from_slice(&(header.len() as u64).to_be_bytes());
+    input.extend_from_slice(header);
+    hash_to_scalar(&input, &api_dst(b"H2S_"))
+}
+
+/// `P1 + Q_1 * domain + H_i * msg_i` over the given messages.
+fn commitment(
+    domain: &Scalar,
+    q_1: &G1Projective,
+    h: &[G1Projective],
+    messages: impl IntoIterator<Item = (usize, Scalar)>,
+) -> G1Projective {
+    let mut b = *P1 + q_1 * domain;
+    for (i, message) in messages {
+        b += h[i] * message;
+    }
+    b
+}
+
+/// Whether `e(a, x) * e(b, -BP2)` is the identity of the target group.
+fn pairings_cancel(a: &G1Projective, x: &G2Affine, b: &G1Projective) -> bool {
+    let a = G1Affine::from(a);
+    let b = G1Affine::from(b);
+    let x = G2Prepared::from(*x);
+    let minus_bp2 = G2Prepared::from(-G2Affine::generator());
+    multi_miller_loop(&[(&a, &x), (&b, &minus_bp2)]).final_exponentiation() == Gt::identity()
+}
+
+/// Challenge of a proof, over its commitments and the disclosed messages.
+fn proof_challenge(
+    points: &[G1Projective],
+    domain: &Scalar,
+    disclosed: &[(usize, Scalar)],
+    presentation_header: &[u8],
+) -> Scalar {
+    let mut input = (disclosed.len() as u64).to_be_bytes().to_vec();
+    for (i, message) in disclosed {
+        input.extend_from_slice(&(*i as u64).to_be_bytes());
+        input.extend_from_slice(&scalar_to_bytes(message));
+    }
+    for point in points {
+        input.extend_from_slice(&point_to_bytes(point));
+    }
+    input.extend_from_slice(&scalar_to_bytes(domain));
+    input.extend_from_slice(&(presentation_header.len() as u64).to_be_bytes());
+    input.extend_from_slice(presentation_header);
+    hash_to_scalar(&input, &api_dst(b"H2S_"))
+}
+
+/// Derive a secret key from at least 32 bytes of secret key material and optional key
+/// information.
+pub fn keygen(key_material: &[u8], key_info: &[u8]) -> Result<[u8; SECRET_KEY_LENGTH], BbsError> {
+    if key_material.len() < 32 || key_info.len() > u16::MAX as usize {
+        return Err(BbsError::KeyMaterial);
+    }
+    let mut input = key_material.to_vec();
+    input.extend_from_slice(&(key_info.len() as u16).to_be_bytes());
+    input.extend_from_slice(key_info);
+    let secret_key = hash_to_scalar(&input, &[CIPHERSUITE_ID, b"KEYGEN_DST_"].concat());
+    if secret_key == Scalar::zero() {
+        return Err(BbsError::KeyMaterial);
+    }
+    Ok(scalar_to_bytes(&secret_key))
+}
+
+/// Generate a secret key from random key material.
+pub fn generate_secret_key() -> [u8; SECRET_KEY_LENGTH] {
+    let mut key_material = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key_material);
+    // Hashed key material is zero with negligible probability.
+    keygen(&key_material, b"").unwrap_or_else(|_| generate_secret_key())
+}
+
+/// Public key of a secret key.
+pub fn public_key(secret_key: &[u8]) -> Result<[u8; PUBLIC_KEY_LENGTH], BbsError> {
+    let secret_key = parse_secret_key(secret_key)?;
+    Ok(G2Affine::from(G2Projective::generator() * secret_key).to_compressed())
+}
+
+/// Affine coordinates of a public key, as in its JWK.
+pub fn public_key_coordinates(public_key: &[u8]) -> Result<([u8; 96], [u8; 96]), BbsError> {
+    let uncompressed = parse_public_key(public_key)?.to_uncompressed();
+    let mut x = [0u8; 96];
+    let mut y = [0u8; 96];
+    x.copy_from_slice(&uncompressed[..96]);
+    y.copy_from_slice(&uncompressed[96..]);
+    Ok((x, y))
+}
+
+/// Public key of affine coordinates, as in its JWK.
+pub fn public_key_from_coordinates(
+    x: &[u8],
+    y: &[u8],
+) -> Result<[u8; PUBLIC_KEY_LENGTH], BbsError> {
+    let uncompressed: [u8; 192] = [x, y]
+        .concat()
+        .as_slice()
+        .try_into()
+        .map_err(|_| BbsError::InvalidPublicKey)?;
+    let point: Option<G2Affine> = Option::from(G2Affine::from_uncompressed(&uncompressed));
+    point
+        .filter(|point| !bool::from(point.is_identity()))
+        .map(|point| point.to_compressed())
+        .ok_or(BbsError::InvalidPublicKey)
+}
+
+/// Sign a header and messages.
+pub fn sign<M: AsRef<[u8]>>(
+    secret_key: &[u8],
+    header: &[u8],
+    messages: &[M],
+) -> Result<Vec<u8>, BbsError> {
+    let public_key = public_key(secret_key)?;
+    let secret_key = parse_secret_key(secret_key)?;
+    let (q_1, h) = generators(messages.len());
+    let scalars = message_scalars(messages);
+    let domain = calculate_domain(&public_key, &q_1, &h, header);
+    let mut e_input = scalar_to_bytes(&secret_key).to_vec();
+    for message in &scalars {
+        e_input.extend_from_slice(&scalar_to_bytes(message));
+    }
+    e_input.extend_from_slice(&scalar_to_bytes(&domain));
+    let e = hash_to_scalar(&e_input, &api_dst(b"H2S_"));
+    let b = commitment(&domain, &q_1, &h, scalars.into_iter().enumerate());
+    let inverse: Option<Scalar> = Option::from((secret_key + e).invert());
+    let a = b * inverse.ok_or(BbsError::InvalidSecretKey)?;
+    let mut signature = point_to_bytes(&a).to_vec();
+    signature.extend_from_slice(&scalar_to_bytes(&e));
+    Ok(signature)
+}
+
+/// Verify a signature of a header and messages.
+pub fn verify<M: AsRef<[u8]>>(
+    public_key: &[u8],
+    signature: &[u8],
+    header: &[u8],
+    messages: &[M],
+) -> Result<(), BbsError> {
+    let w = parse_public_key(public_key)?;
+    let (a, e) = parse_signature(signature)?;
+    let (q_1, h) = generators(messages.len());
+    let domain = calculate_domain(public_key, &q_1, &h, header);
+    let b = commitment(
+        &domain,
+        &q_1,
+        &h,
+        message_scalars(messages).into_iter().enumerate(),
+    );
+    let w_e = G2Affine::from(G2Projective::from(w) + G2Projective::generator() * e);
+    if !pairings_cancel(&a, &w_e, &b) {
+        return Err(BbsError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Derive a proof of a signature disclosing the messages at the given indexes, bound to a
+/// presentation header. The signature is verified first.
+pub fn proof_gen<M: AsRef<[u8]>>(
+    public_key: &[u8],
+    signature: &[u8],
+    header: &[u8],
+    presentation_header: &[u8],
+    messages: &[M],
+    disclosed_indexes: &[usize],
+) -> Result<Vec<u8>, BbsError> {
+    verify(public_key, signature, header, messages)?;
+    let (a, e) = parse_signature(signature)?;
+    if let Some(i) = disclosed_indexes.iter().find(|i| **i >= messages.len()) {
+        return Err(BbsError::MessageIndex(*i));
+    }
+    let (q_1, h) = generators(messages.len());
+    let scalars = message_scalars(messages);
+    let domain = calculate_domain(public_key, &q_1, &h, header);
+    let (disclosed, undisclosed): (Vec<usize>, Vec<usize>) =
+        (0..messages.len()).partition(|i| disclosed_indexes.contains(i));
+    let b = commitment(&domain, &q_1, &h, scalars.iter().copied().enumerate());
+
+    let r1 = random_scalar();
+    let r2 = random_scalar();
+    let e_tilde = random_scalar();
+    let r1_tilde = random_scalar();
+    let r3_tilde = random_scalar();
+    let m_tildes: Vec<Scalar> = undisclosed.iter().map(|_| random_scalar()).collect();
+
+    let d = b * r2;
+    let abar = a * (r1 * r2);
+    let bbar = d * r1 - abar * e;
+    let t1 = abar * e_tilde + d * r1_tilde;
+    let mut t2 = d * r3_tilde;
+    for (j, m_tilde) in undisclosed.iter().zip(&m_tildes) {
+        t2 += h[*j] * m_tilde;
+    }
+    let disclosed: Vec<(usize, Scalar)> = disclosed.into_iter().map(|i| (i, scalars[i])).collect();
+    let challenge = proof_challenge(
+        &[abar, bbar, d, t1, t2],
+        &domain,
+        &disclosed,
+        presentation_header,
+    );
+    let r3: Option<Scalar> = Option::from(r2.invert());
+    let r3 = r3.ok_or(BbsError::InvalidSignature)?;
+
+    let mut proof = Vec::with_capacity(3 * POINT_LENGTH + (4 + undisclosed.len()) * SCALAR_LENGTH);
+    for point in &[abar, bbar, d] {
+        proof.extend_from_slice(&point_to_bytes(point));
+    }
+    for scalar in &[
+        e_tilde + e * challenge,
+        r1_tilde - r1 * challenge,
+        r3_tilde - r3 * challenge,
+    ] {
+        proof.extend_from_slice(&scalar_to_bytes(scalar));
+    }
+    for (j, m_tilde) in undisclosed.iter().zip(&m_tildes) {
+        proof.extend_from_slice(&scalar_to_bytes(&(m_tilde + scalars[*j] * challenge)));
+    }
+    proof.extend_from_slice(&scalar_to_bytes(&challenge));
+    Ok(proof)
+}
+
+/// Verify a proof of a signature, given the messages of the signature that it discloses (`Some`)
+/// and the number of those it does not (`None`).
+pub fn proof_verify(
+    public_key: &[u8],
+    proof: &[u8],
+    header: &[u8],
+    presentation_header: &[u8],
+    messages: &[Option<&[u8]>],
+) -> Result<(), BbsError> {
+    let w = parse_public_key(public_key)?;
+    let undisclosed: Vec<usize> = (0..messages.len())
+        .filter(|i| messages[*i].is_none())
+        .collect();
+    if proof.len() != 3 * POINT_LENGTH + (4 + undisclosed.len()) * SCALAR_LENGTH {
+        return Err(BbsError::InvalidProof);
+    }
+    let (points, scalars) = proof.split_at(3 * POINT_LENGTH);
+    let points = points
+        .chunks(POINT_LENGTH)
+        .map(point_from_bytes)
+        .collect::<Option<Vec<_>>>()
+        .ok_or(BbsError::InvalidProof)?;
+    let scalars = scalars
+        .chunks(SCALAR_LENGTH)
+        .map(scalar_from_bytes)
+        .collect::<Option<Vec<_>>>()
+        .ok_or(BbsError::InvalidProof)?;
+    let (abar, bbar, d) = (points[0], points[1], points[2]);
+    let (e_hat, r1_hat, r3_hat) = (scalars[0], scalars[1], scalars[2]);
+    let m_hats = &scalars[3..scalars.len() - 1];
+    let challenge = scalars[scalars.len() - 1];
+
+    let (q_1, h) = generators(messages.len());
+    let domain = calculate_domain(public_key, &q_1, &h, header);
+    let dst = api_dst(b"MAP_MSG_TO_SCALAR_AS_HASH_");
+    let disclosed: Vec<(usize, Scalar)> = messages
+        .iter()
+        .enumerate()
+        .filter_map(|(i, message)| message.map(|message| (i, hash_to_scalar(message, &dst))))
+        .collect();
+    let t1 = bbar * challenge + abar * e_hat + d * r1_hat;
+    let bv = commitment(&domain, &q_1, &h, disclosed.iter().copied());
+    let mut t2 = bv * challenge + d * r3_hat;
+    for (j, m_hat) in undisclosed.iter().zip(m_hats) {
+        t2 += h[*j] * m_hat;
+    }
+    let expected = proof_challenge(
+        &[abar, bbar, d, t1, t2],
+        &domain,
+        &disclosed,
+        presentation_header,
+    );
+    if expected != challenge || !pairings_cancel(&abar, &w, &bbar) {
+        return Err(BbsError::InvalidProof);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_message_xmd_vectors() {
+        // RFC 9380, appendix K.1
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let expected: [u8; 32] = [
+            0x68, 0xa9, 0x85, 0xb8, 0x7e, 0xb6, 0xb4, 0x69, 0x52, 0x12, 0x89, 0x11, 0xf2, 0xa4,
+            0x41, 0x2b, 0xbc, 0x30, 0x2a, 0x9d, 0x75, 0x96, 0x67, 0xf8, 0x7f, 0x7a, 0x21, 0xd8,
+            0x03, 0xf0, 0x72, 0x35,
+        ];
+        assert_eq!(expand_message_xmd(b"", dst, 32), expected.to_vec());
+        assert_eq!(expand_message_xmd(b"abc", dst, 128).len(), 128);
+    }
+
+    #[test]
+    fn sign_verify_and_prove() {
+        let secret_key = generate_secret_key();
+        let public_key = public_key(&secret_key).unwrap();
+        let (x, y) = public_key_coordinates(&public_key).unwrap();
+        assert_eq!(public_key_from_coordinates(&x, &y).unwrap(), public_key);
+
+        let header = b"header";
+        let messages: Vec<&[u8]> = vec![b"alice", b"1990-01-01", b"paris"];
+        let signature = sign(&secret_key, header, &messages).unwrap();
+        assert_eq!(signature.len(), SIGNATURE_LENGTH);
+        verify(&public_key, &signature, header, &messages).unwrap();
+        let tampered: Vec<&[u8]> = vec![b"mallory", b"1990-01-01", b"paris"];
+        assert_eq!(
+            verify(&public_key, &signature, header, &tampered),
+            Err(BbsError::InvalidSignature)
+        );
+        assert_eq!(
+            verify(&public_key, &signature, b"other header", &messages),
+            Err(BbsError::InvalidSignature)
+        );
+
+        let proof = proof_gen(
+            &public_key,
+            &signature,
+            header,
+            b"nonce",
+            &messages,
+            &[0, 2],
+        )
+        .unwrap();
+        assert_eq!(proof.len(), 3 * POINT_LENGTH + 5 * SCALAR_LENGTH);
+        let disclosed = [Some(&b"alice"[..]), None, Some(&b"paris"[..])];
+        proof_verify(&public_key, &proof, header, b"nonce", &disclosed).unwrap();
+        // Proofs are randomized.
+        let other = proof_gen(
+            &public_key,
+            &signature,
+            header,
+            b"nonce",
+            &messages,
+            &[0, 2],
+        )
+        .unwrap();
+        assert_ne!(proof, other);
+        assert_eq!(
+            proof_verify(&public_key, &proof, header, b"other nonce", &disclosed),
+            Err(BbsError::InvalidProof)
+        );
+        let forged = [Some(&b"mallory"[..]), None, Some(&b"paris"[..])];
+        assert_eq!(
+            proof_verify(&public_key, &proof, header, b"nonce", &forged),
+            Err(BbsError::InvalidProof)
+        );
+        let other_key = self::public_key(&generate_secret_key()).unwrap();
+        assert_eq!(
+            proof_verify(&other_key, &proof, header, b"nonce", &disclosed),
+            Err(BbsError::InvalidProof)
+        );
+        assert_eq!(
+            proof_gen(&public_key, &signature, header, b"", &messages, &[3]),
+            Err(BbsError::MessageIndex(3))
+        );
+    }
+}
@@ -1,20 +1,32 @@
+use std::convert::TryFrom;
+use std::ffi::c_void;
 use std::ffi::CStr;
 use std::ffi::CString;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int, c_uint};
 use std::ptr;
+use std::time::Duration;
 
+use serde_json::Value;
+
+use crate::cancellation::Cancellation;
 use crate::error::Error;
 #[cfg(doc)]
 use crate::error::{didkit_error_code, didkit_error_message};
 use crate::get_verification_method;
+use crate::i18n;
+use crate::render::{self, CredentialManifest};
+use crate::resolution_error;
 use crate::runtime;
+use crate::signer::{algorithm_name, FnSigner};
+use crate::verification::VerificationReport;
 use crate::Source;
 use crate::VerifiableCredential;
 use crate::VerifiablePresentation;
 use crate::DID_METHODS;
 use crate::JWK;
 use crate::{dereference, DereferencingInputMetadata, ResolutionInputMetadata, ResolutionResult};
-use crate::{JWTOrLDPOptions, ProofFormat};
+use crate::{CredentialOrJWT, PresentationOrJWT, VerificationResult};
+use crate::{IssueOptions, JWTOrLDPOptions, ProofFormat, Signer};
 
 /// The version of the DIDKit library, as a NULL-terminated string
 pub static VERSION_C: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
@@ -124,21 +136,16 @@ fn issue_credential(
     let credential_json = unsafe { CStr::from_ptr(credential_json_ptr) }.to_str()?;
     let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
     let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
-    let mut credential = VerifiableCredential::from_json_unsigned(credential_json)?;
+    let credential = VerifiableCredential::from_json_unsigned(credential_json)?;
     let key: JWK = serde_json::from_str(key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let out = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(credential.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(credential.generate_proof(&key, &options.ldp_options, resolver))?;
-            credential.add_proof(proof);
-            serde_json::to_string(&credential)?
-        }
+    let out = match rt.block_on(crate::issue_credential(
+        credential, &options, &key, resolver,
+    ))? {
+        CredentialOrJWT::JWT(jwt) => jwt,
+        vc => serde_json::to_string(&vc)?,
     };
     Ok(CString::new(out)?.into_raw())
 }
@@ -164,24 +171,25 @@ pub extern "C" fn didkit_vc_issue_credential(
 fn verify_credential(
     credential_ptr: *const c_char,
     proof_options_json_ptr: *const c_char,
+    cancellation: &Cancellation,
 ) -> Result<*const c_char, Error> {
     let vc_str = unsafe { CStr::from_ptr(credential_ptr) }.to_str()?;
     let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
     let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let proof_format = options.proof_format.clone().unwrap_or_default();
     let rt = runtime::get()?;
-    let resolver = DID_METHODS.to_resolver();
-    let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiableCredential::verify_jwt(
-            &vc_str,
-            Some(options.ldp_options),
-            resolver,
-        )),
+    let resolver = cancellation.resolver(DID_METHODS.to_resolver());
+    let vc = match proof_format {
+        ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
+            CredentialOrJWT::JWT(vc_str.to_string())
+        }
         ProofFormat::LDP => {
-            let vc = VerifiableCredential::from_json_unsigned(vc_str)?;
-            rt.block_on(vc.verify(Some(options.ldp_options), resolver))
+            CredentialOrJWT::Credential(VerifiableCredential::from_json_unsigned(vc_str)?)
         }
     };
+    let verification = crate::verify_credential(&vc, &options, &resolver);
+    let report = rt.block_on(cancellation.run(verification))??;
+    let result = VerificationResult::from(report);
     Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
 }
 #[no_mangle]
@@ -198,7 +206,30 @@ pub extern "C" fn didkit_vc_verify_credential(
     credential: *const c_char,
     proof_options_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(verify_credential(credential, proof_options_json))
+    ccchar_or_error(verify_credential(
+        credential,
+        proof_options_json,
+        &Cancellation::new(),
+    ))
+}
+
+#[no_mangle]
+/// Verify a Verifiable Credential, like [`didkit_vc_verify_credential`], until a cancellation
+/// token from [`didkit_cancellation_new`] (or `NULL` for none) is cancelled or times out. In that
+/// case, `NULL` is returned and the error code is 14 (`cancelled`). The token must not be freed
+/// before this function returns.
+pub extern "C" fn didkit_vc_verify_credential_cancellable(
+    credential: *const c_char,
+    proof_options_json: *const c_char,
+    cancellation: *const Cancellation,
+) -> *const c_char {
+    let none = Cancellation::new();
+    let cancellation = unsafe { cancellation.as_ref() }.unwrap_or(&none);
+    ccchar_or_error(verify_credential(
+        credential,
+        proof_options_json,
+        cancellation,
+    ))
 }
 
 // Issue Presentation
@@ -211,21 +242,19 @@ fn issue_presentation(
     let presentation_json = unsafe { CStr::from_ptr(presentation_json_ptr) }.to_str()?;
     let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
     let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
-    let mut presentation = VerifiablePresentation::from_json_unsigned(presentation_json)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(presentation_json)?;
     let key: JWK = serde_json::from_str(key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let out = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(presentation.generate_proof(&key, &options.ldp_options, resolver))?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
+    let out = match rt.block_on(crate::issue_presentation(
+        presentation,
+        &options,
+        &key,
+        resolver,
+    ))? {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(CString::new(out)?.into_raw())
 }
@@ -261,18 +290,16 @@ fn did_auth(
     presentation.holder = Some(ssi::vc::URI::String(holder.to_string()));
     let key: JWK = serde_json::from_str(key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let out = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(presentation.generate_proof(&key, &options.ldp_options, resolver))?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
+    let out = match rt.block_on(crate::issue_presentation(
+        presentation,
+        &options,
+        &key,
+        resolver,
+    ))? {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(CString::new(out)?.into_raw())
 }
@@ -293,25 +320,26 @@ pub extern "C" fn didkit_did_auth(
 fn verify_presentation(
     presentation_ptr: *const c_char,
     proof_options_json_ptr: *const c_char,
+    cancellation: &Cancellation,
 ) -> Result<*const c_char, Error> {
     let vp_str = unsafe { CStr::from_ptr(presentation_ptr) }.to_str()?;
     let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
     // TODO
     let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let proof_format = options.proof_format.clone().unwrap_or_default();
     let rt = runtime::get()?;
-    let resolver = DID_METHODS.to_resolver();
-    let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiablePresentation::verify_jwt(
-            &vp_str,
-            Some(options.ldp_options),
-            resolver,
-        )),
+    let resolver = cancellation.resolver(DID_METHODS.to_resolver());
+    let vp = match proof_format {
+        ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
+            PresentationOrJWT::JWT(vp_str.to_string())
+        }
         ProofFormat::LDP => {
-            let vp = VerifiablePresentation::from_json_unsigned(vp_str)?;
-            rt.block_on(vp.verify(Some(options.ldp_options), DID_METHODS.to_resolver()))
+            PresentationOrJWT::VP(VerifiablePresentation::from_json_unsigned(vp_str)?)
         }
     };
+    let verification = crate::verify_presentation(&vp, &options, &resolver);
+    let report = rt.block_on(cancellation.run(verification))??;
+    let result = VerificationResult::from(report);
     Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
 }
 #[no_mangle]
@@ -328,7 +356,182 @@ pub extern "C" fn didkit_vc_verify_presentation(
     presentation: *const c_char,
     proof_options_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(verify_presentation(presentation, proof_options_json))
+    ccchar_or_error(verify_presentation(
+        presentation,
+        proof_options_json,
+        &Cancellation::new(),
+    ))
+}
+
+#[no_mangle]
+/// Verify a Verifiable Presentation, like [`didkit_vc_verify_presentation`], until a
+/// cancellation token from [`didkit_cancellation_new`] (or `NULL` for none) is cancelled or times
+/// out. In that case, `NULL` is returned and the error code is 14 (`cancelled`). The token must
+/// not be freed before this function returns.
+pub extern "C" fn didkit_vc_verify_presentation_cancellable(
+    presentation: *const c_char,
+    proof_options_json: *const c_char,
+    cancellation: *const Cancellation,
+) -> *const c_char {
+    let none = Cancellation::new();
+    let cancellation = unsafe { cancellation.as_ref() }.unwrap_or(&none);
+    ccchar_or_error(verify_presentation(
+        presentation,
+        proof_options_json,
+        cancellation,
+    ))
+}
+
+#[no_mangle]
+/// Create a cancellation token, for [`didkit_vc_verify_credential_cancellable`] and
+/// [`didkit_vc_verify_presentation_cancellable`]. The argument is a timeout in milliseconds,
+/// after which operations using the token fail, or 0 for no timeout. The token should be freed
+/// using [`didkit_cancellation_free`].
+pub extern "C" fn didkit_cancellation_new(timeout_ms: c_uint) -> *mut Cancellation {
+    let cancellation = match timeout_ms {
+        0 => Cancellation::new(),
+        ms => Cancellation::with_timeout(Duration::from_millis(ms.into())),
+    };
+    Box::into_raw(Box::new(cancellation))
+}
+
+#[no_mangle]
+/// Cancel the operations using a cancellation token. This may be called from another thread than
+/// the one running the operations, which then fail as soon as possible.
+pub extern "C" fn didkit_cancellation_cancel(cancellation: *const Cancellation) {
+    if let Some(cancellation) = unsafe { cancellation.as_ref() } {
+        cancellation.cancel();
+    }
+}
+
+#[no_mangle]
+/// Free a cancellation token created by [`didkit_cancellation_new`], once no operation uses it.
+pub extern "C" fn didkit_cancellation_free(cancellation: *mut Cancellation) {
+    if cancellation.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(cancellation));
+    }
+}
+
+// Localize verification result
+fn localize_verification_result(
+    result_json_ptr: *const c_char,
+    locale_ptr: *const c_char,
+    catalog_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let result_json = unsafe { CStr::from_ptr(result_json_ptr) }.to_str()?;
+    let locale = unsafe { CStr::from_ptr(locale_ptr) }.to_str()?;
+    let result: VerificationResult = serde_json::from_str(result_json)?;
+    let catalog = if catalog_json_ptr.is_null() {
+        i18n::Catalog::default_catalog()
+    } else {
+        let catalog_json = unsafe { CStr::from_ptr(catalog_json_ptr) }.to_str()?;
+        i18n::Catalog {
+            locale: locale.to_string(),
+            messages: serde_json::from_str(catalog_json)?,
+        }
+    };
+    let localized = catalog.localize(&VerificationReport::from(result));
+    Ok(CString::new(serde_json::to_string(&localized)?)?.into_raw())
+}
+#[no_mangle]
+/// Render the checks, errors and warnings of a verification result, as returned by
+/// [`didkit_vc_verify_credential`] or [`didkit_vc_verify_presentation`], in a locale. Arguments
+/// are C strings containing the verification result JSON, the locale, e.g. `fr`, and a JSON
+/// object of message keys and templates for the locale (see [`crate::i18n`]), or `NULL` for the
+/// built-in English messages. Keys missing from the catalog fall back to English. On success,
+/// returns a newly-allocated C string containing a JSON object with `locale`, `checks`, `errors`
+/// and `warnings` properties, which should be freed using [`didkit_free_string`]. On failure,
+/// returns `NULL`; the error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_localize_verification_result(
+    result_json: *const c_char,
+    locale: *const c_char,
+    catalog_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(localize_verification_result(
+        result_json,
+        locale,
+        catalog_json,
+    ))
+}
+
+// Credential card
+fn card_arguments<'a>(
+    credential_json_ptr: *const c_char,
+    manifest_json_ptr: *const c_char,
+    descriptor_id_ptr: *const c_char,
+) -> Result<(Value, CredentialManifest, Option<&'a str>), Error> {
+    let credential_json = unsafe { CStr::from_ptr(credential_json_ptr) }.to_str()?;
+    let manifest_json = unsafe { CStr::from_ptr(manifest_json_ptr) }.to_str()?;
+    let descriptor_id = if descriptor_id_ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(descriptor_id_ptr) }.to_str()?)
+    };
+    let credential = render::parse_credential(credential_json)?;
+    let manifest = serde_json::from_str(manifest_json)?;
+    Ok((credential, manifest, descriptor_id))
+}
+fn credential_card(
+    credential_json_ptr: *const c_char,
+    manifest_json_ptr: *const c_char,
+    descriptor_id_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let (credential, manifest, descriptor_id) =
+        card_arguments(credential_json_ptr, manifest_json_ptr, descriptor_id_ptr)?;
+    let card = render::card(&manifest, &credential, descriptor_id)?;
+    Ok(CString::new(serde_json::to_string(&card)?)?.into_raw())
+}
+fn validate_credential_display(
+    credential_json_ptr: *const c_char,
+    manifest_json_ptr: *const c_char,
+    descriptor_id_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let (credential, manifest, descriptor_id) =
+        card_arguments(credential_json_ptr, manifest_json_ptr, descriptor_id_ptr)?;
+    let problems = render::validate_display(&manifest, &credential, descriptor_id)?;
+    Ok(CString::new(serde_json::to_string(&problems)?)?.into_raw())
+}
+#[no_mangle]
+/// Resolve the display of a credential by an output descriptor of a Credential Manifest, for
+/// rendering it as a card (see [`crate::render::card`]). Arguments are C strings containing the
+/// credential, as JSON or a JWT, the manifest JSON, and the id of the output descriptor, or `NULL`
+/// to select it by the credential's types and schemas. On success, returns a newly-allocated C
+/// string containing a JSON object with `descriptorId`, `title`, `subtitle`, `description`,
+/// `properties` (objects with `label` and `value`), and the entity `styles` of the descriptor and
+/// `issuer` of the manifest, which should be freed using [`didkit_free_string`]. On failure,
+/// returns `NULL`; the error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_credential_card(
+    credential_json: *const c_char,
+    manifest_json: *const c_char,
+    descriptor_id: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(credential_card(
+        credential_json,
+        manifest_json,
+        descriptor_id,
+    ))
+}
+
+#[no_mangle]
+/// Check the display mappings and styles of an output descriptor of a Credential Manifest against
+/// a credential (see [`crate::render::validate_display`]). Arguments are as for
+/// [`didkit_credential_card`]. On success, returns a newly-allocated C string containing a JSON
+/// array of the problems found, empty if there are none, which should be freed using
+/// [`didkit_free_string`]. On failure, returns `NULL`; the error message can be retrieved using
+/// [`didkit_error_message`].
+pub extern "C" fn didkit_validate_credential_display(
+    credential_json: *const c_char,
+    manifest_json: *const c_char,
+    descriptor_id: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(validate_credential_display(
+        credential_json,
+        manifest_json,
+        descriptor_id,
+    ))
 }
 
 // Resolve DID
@@ -345,7 +548,8 @@ fn resolve_did(
     let input_metadata: ResolutionInputMetadata = serde_json::from_str(input_metadata_json)?;
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let (res_meta, doc_opt, doc_meta_opt) = rt.block_on(resolver.resolve(did, &input_metadata));
+    let (mut res_meta, doc_opt, doc_meta_opt) = rt.block_on(resolver.resolve(did, &input_metadata));
+    resolution_error::normalize_resolution_metadata(&mut res_meta);
     let result = ResolutionResult {
         did_document: doc_opt,
         did_resolution_metadata: Some(res_meta),
@@ -382,7 +586,8 @@ fn dereference_did_url(
     let input_metadata: DereferencingInputMetadata = serde_json::from_str(input_metadata_json)?;
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let deref_result = rt.block_on(dereference(resolver, did_url, &input_metadata));
+    let mut deref_result = rt.block_on(dereference(resolver, did_url, &input_metadata));
+    resolution_error::normalize_dereferencing_metadata(&mut deref_result.0);
     use serde_json::json;
     let result = json!(deref_result);
     Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
@@ -401,6 +606,208 @@ pub extern "C" fn didkit_did_url_dereference(
     ccchar_or_error(dereference_did_url(did_url, input_metadata_json))
 }
 
+/// Signing callback provided by the host application, e.g. backed by a platform keystore. It is
+/// called with the `context` pointer given to the issuing function, the signing input, and the
+/// JWS algorithm name (e.g. `EdDSA`) as a C string. It must write the raw signature to
+/// `signature`, which has room for `*signature_len` bytes, set `*signature_len` to the length
+/// written, and return 0; or return non-zero on failure. Callbacks given to the `_prehashed`
+/// issuing functions are called with the digest of the signing input instead (SHA-256, or SHA-512
+/// for `RS512`), for keys that only sign digests.
+pub type DIDKitSignCallback = extern "C" fn(
+    context: *mut c_void,
+    signing_input: *const u8,
+    signing_input_len: usize,
+    algorithm: *const c_char,
+    signature: *mut u8,
+    signature_len: *mut usize,
+) -> c_int;
+
+/// Room given to a signing callback for the signature.
+const MAX_SIGNATURE_LEN: usize = 1024;
+
+struct CallbackContext(*mut c_void);
+
+// The context is only passed back to the callback, which is called on the calling thread while
+// the issuing function blocks.
+unsafe impl Send for CallbackContext {}
+unsafe impl Sync for CallbackContext {}
+
+fn callback_signer(
+    public_key_json_ptr: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+    prehashed: bool,
+) -> Result<impl Signer, Error> {
+    let public_key_json = unsafe { CStr::from_ptr(public_key_json_ptr) }.to_str()?;
+    let public_key: JWK = serde_json::from_str(public_key_json)?;
+    let context = CallbackContext(context);
+    let sign = move |signing_input: &[u8], algorithm| {
+        let algorithm = CString::new(algorithm_name(algorithm)).map_err(|e| e.to_string())?;
+        let mut signature = vec![0u8; MAX_SIGNATURE_LEN];
+        let mut signature_len = signature.len();
+        let status = callback(
+            context.0,
+            signing_input.as_ptr(),
+            signing_input.len(),
+            algorithm.as_ptr(),
+            signature.as_mut_ptr(),
+            &mut signature_len,
+        );
+        if status != 0 {
+            return Err(format!("Signing callback failed: {}", status));
+        }
+        if signature_len > MAX_SIGNATURE_LEN {
+            return Err("Signature too long".to_string());
+        }
+        signature.truncate(signature_len);
+        Ok(signature)
+    };
+    Ok(if prehashed {
+        FnSigner::prehashed(public_key, sign)
+    } else {
+        FnSigner::new(public_key, sign)
+    })
+}
+
+fn issue_credential_with_signer(
+    credential_json_ptr: *const c_char,
+    proof_options_json_ptr: *const c_char,
+    public_key_json_ptr: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+    prehashed: bool,
+) -> Result<*const c_char, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let credential_json = unsafe { CStr::from_ptr(credential_json_ptr) }.to_str()?;
+    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
+    let credential = VerifiableCredential::from_json_unsigned(credential_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+    let options = IssueOptions::try_from(options)?;
+    let signer = callback_signer(public_key_json_ptr, callback, context, prehashed)?;
+    let rt = runtime::get()?;
+    let out = match rt.block_on(crate::issue_credential(
+        credential, &options, &signer, resolver,
+    ))? {
+        CredentialOrJWT::JWT(jwt) => jwt,
+        vc => serde_json::to_string(&vc)?,
+    };
+    Ok(CString::new(out)?.into_raw())
+}
+#[no_mangle]
+/// Issue a Verifiable Credential, signing with a callback instead of a private key. Input
+/// parameters are JSON C strings for the unsigned credential and the proof options, the public
+/// JWK of the signing key, a [`DIDKitSignCallback`], and a context pointer passed to the
+/// callback. The callback is called on the calling thread before this function returns. On
+/// success, the newly-issued verifiable credential (or JWT) is returned as a newly-allocated C
+/// string, which should be freed using [`didkit_free_string`]. On failure, `NULL` is returned,
+/// and the error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_vc_issue_credential_with_signer(
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+) -> *const c_char {
+    ccchar_or_error(issue_credential_with_signer(
+        credential_json,
+        proof_options_json,
+        public_key_json,
+        callback,
+        context,
+        false,
+    ))
+}
+
+#[no_mangle]
+/// Issue a Verifiable Credential as with [`didkit_vc_issue_credential_with_signer`], calling the
+/// callback with the digest of the signing input instead of the signing input, for keys in HSMs
+/// or KMSs that only sign digests. EdDSA is not supported.
+pub extern "C" fn didkit_vc_issue_credential_with_prehashed_signer(
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+) -> *const c_char {
+    ccchar_or_error(issue_credential_with_signer(
+        credential_json,
+        proof_options_json,
+        public_key_json,
+        callback,
+        context,
+        true,
+    ))
+}
+
+fn issue_presentation_with_signer(
+    presentation_json_ptr: *const c_char,
+    proof_options_json_ptr: *const c_char,
+    public_key_json_ptr: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+    prehashed: bool,
+) -> Result<*const c_char, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let presentation_json = unsafe { CStr::from_ptr(presentation_json_ptr) }.to_str()?;
+    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
+    let presentation = VerifiablePresentation::from_json_unsigned(presentation_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+    let options = IssueOptions::try_from(options)?;
+    let signer = callback_signer(public_key_json_ptr, callback, context, prehashed)?;
+    let rt = runtime::get()?;
+    let out = match rt.block_on(crate::issue_presentation(
+        presentation,
+        &options,
+        &signer,
+        resolver,
+    ))? {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
+    };
+    Ok(CString::new(out)?.into_raw())
+}
+#[no_mangle]
+/// Issue a Verifiable Presentation, signing with a callback instead of a private key. Arguments
+/// are as for [`didkit_vc_issue_credential_with_signer`], with an unsigned presentation instead
+/// of a credential.
+pub extern "C" fn didkit_vc_issue_presentation_with_signer(
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+) -> *const c_char {
+    ccchar_or_error(issue_presentation_with_signer(
+        presentation_json,
+        proof_options_json,
+        public_key_json,
+        callback,
+        context,
+        false,
+    ))
+}
+
+#[no_mangle]
+/// Issue a Verifiable Presentation as with [`didkit_vc_issue_presentation_with_signer`], calling the
+/// callback with the digest of the signing input instead of the signing input, for keys in HSMs
+/// or KMSs that only sign digests. EdDSA is not supported.
+pub extern "C" fn didkit_vc_issue_presentation_with_prehashed_signer(
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitSignCallback,
+    context: *mut c_void,
+) -> *const c_char {
+    ccchar_or_error(issue_presentation_with_signer(
+        presentation_json,
+        proof_options_json,
+        public_key_json,
+        callback,
+        context,
+        true,
+    ))
+}
+
 #[no_mangle]
 /// Free a C string that has been dynamically allocated by DIDKit. This should be used for strings
 /// returned from most DIDKit C functions, per their respective documentation.
@@ -0,0 +1,491 @@
+//! [CACAO][caip-74] capability objects from [Sign-In with Ethereum][eip-4361] (SIWE) messages.
+//!
+//! A CACAO packages the fields of a signed SIWE message, so that the message can be
+//! reconstructed and its `eip191` signature checked against the `did:pkh:eip155` issuer. DIDKit
+//! accepts a CACAO as the holder's proof of control in presentation verification, via
+//! [`crate::VerificationOptions::cacao`]. As the signature covers only the SIWE message, the
+//! CACAO must be bound to the presentation by listing its [`presentation_resource`] in the
+//! message's resources, and to the verifier's session by its nonce and domain, which must match
+//! the challenge and domain of the verification.
+//!
+//! [caip-74]: https://github.com/ChainAgnostic/CAIPs/blob/master/CAIPs/caip-74.md
+//! [eip-4361]: https://eips.ethereum.org/EIPS/eip-4361
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use k256::ecdsa::signature::Signer as _;
+use k256::ecdsa::{recoverable, SigningKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+use ssi::jwk::Params;
+use thiserror::Error;
+
+use crate::canonical_json::jcs;
+use crate::content_id::URN_PREFIX;
+use crate::hex;
+use crate::JWK;
+
+pub const HEADER_TYPE_EIP4361: &str = "eip4361";
+pub const SIGNATURE_TYPE_EIP191: &str = "eip191";
+const SIWE_PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+#[derive(Error, Debug)]
+pub enum CacaoError {
+    #[error("Invalid SIWE message: {0}")]
+    InvalidMessage(String),
+    #[error("Unsupported CACAO type: {0}")]
+    UnsupportedType(String),
+    #[error("Expected did:pkh:eip155 issuer: {0}")]
+    InvalidIssuer(String),
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Signer address does not match: {0}")]
+    AddressMismatch(String),
+    #[error("Expected secp256k1 private key")]
+    UnsupportedKey,
+    #[error("CACAO expired")]
+    Expired,
+    #[error("CACAO not yet valid")]
+    NotYetValid,
+    #[error("Invalid time: {0}")]
+    Time(#[from] chrono::ParseError),
+}
+
+/// Sign-In with Ethereum message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: String,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+impl fmt::Display for SiweMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}{}", self.domain, SIWE_PREAMBLE)?;
+        writeln!(f, "{}", self.address)?;
+        writeln!(f)?;
+        if let Some(ref statement) = self.statement {
+            writeln!(f, "{}", statement)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", self.issued_at)?;
+        if let Some(ref exp) = self.expiration_time {
+            write!(f, "\nExpiration Time: {}", exp)?;
+        }
+        if let Some(ref nbf) = self.not_before {
+            write!(f, "\nNot Before: {}", nbf)?;
+        }
+        if let Some(ref request_id) = self.request_id {
+            write!(f, "\nRequest ID: {}", request_id)?;
+        }
+        if !self.resources.is_empty() {
+            write!(f, "\nResources:")?;
+            for resource in &self.resources {
+                write!(f, "\n- {}", resource)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SiweMessage {
+    type Err = CacaoError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| CacaoError::InvalidMessage(msg.to_string());
+        let mut lines = s.trim_end_matches('\n').split('\n');
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(SIWE_PREAMBLE))
+            .ok_or_else(|| invalid("expected preamble"))?;
+        let address = lines.next().ok_or_else(|| invalid("expected address"))?;
+        if lines.next() != Some("") {
+            return Err(invalid("expected empty line after address"));
+        }
+        let statement = match lines.next() {
+            Some("") => None,
+            Some(statement) => {
+                if lines.next() != Some("") {
+                    return Err(invalid("expected empty line after statement"));
+                }
+                Some(statement.to_string())
+            }
+            None => return Err(invalid("expected statement or empty line")),
+        };
+        let mut message = SiweMessage {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            statement,
+            uri: String::new(),
+            version: String::new(),
+            chain_id: 0,
+            nonce: String::new(),
+            issued_at: String::new(),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        };
+        let mut in_resources = false;
+        for line in lines {
+            if in_resources {
+                let resource = line
+                    .strip_prefix("- ")
+                    .ok_or_else(|| invalid("expected resource"))?;
+                message.resources.push(resource.to_string());
+                continue;
+            }
+            if line == "Resources:" {
+                in_resources = true;
+                continue;
+            }
+            let mut parts = line.splitn(2, ": ");
+            let (tag, value) = match (parts.next(), parts.next()) {
+                (Some(tag), Some(value)) => (tag, value.to_string()),
+                _ => return Err(invalid(line)),
+            };
+            match tag {
+                "URI" => message.uri = value,
+                "Version" => message.version = value,
+                "Chain ID" => {
+                    message.chain_id = value.parse().map_err(|_| invalid("invalid chain ID"))?
+                }
+                "Nonce" => message.nonce = value,
+                "Issued At" => message.issued_at = value,
+                "Expiration Time" => message.expiration_time = Some(value),
+                "Not Before" => message.not_before = Some(value),
+                "Request ID" => message.request_id = Some(value),
+                _ => return Err(invalid(line)),
+            }
+        }
+        if message.uri.is_empty() || message.nonce.is_empty() || message.issued_at.is_empty() {
+            return Err(invalid("missing URI, nonce or issued-at time"));
+        }
+        Ok(message)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacaoHeader {
+    pub t: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacaoPayload {
+    pub domain: String,
+    /// `did:pkh` of the signer
+    pub iss: String,
+    /// SIWE URI
+    pub aud: String,
+    pub version: String,
+    pub nonce: String,
+    pub iat: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacaoSignature {
+    pub t: String,
+    /// Hex-encoded signature
+    pub s: String,
+}
+
+/// CACAO, in its JSON representation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Cacao {
+    pub h: CacaoHeader,
+    pub p: CacaoPayload,
+    pub s: CacaoSignature,
+}
+
+fn eip191_message(message: &str) -> Vec<u8> {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message.as_bytes());
+    data
+}
+
+fn eth_address(key: &k256::ecdsa::VerifyingKey) -> String {
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    "0x".to_string() + &hex::encode(&hash[12..])
+}
+
+fn signing_key(jwk: &JWK) -> Result<SigningKey, CacaoError> {
+    let secret = match jwk.params {
+        Params::EC(ref params) if params.curve.as_deref() == Some("secp256k1") => params
+            .ecc_private_key
+            .as_ref()
+            .ok_or(CacaoError::UnsupportedKey)?,
+        _ => return Err(CacaoError::UnsupportedKey),
+    };
+    SigningKey::from_bytes(&secret.0).map_err(|_| CacaoError::UnsupportedKey)
+}
+
+/// Ethereum address of a secp256k1 private key, to sign SIWE messages with.
+pub fn key_address(jwk: &JWK) -> Result<String, CacaoError> {
+    Ok(eth_address(&signing_key(jwk)?.verify_key()))
+}
+
+/// Resource binding a CACAO to a presentation: the `urn:hash::sha256:` URN of the SHA-256 hash
+/// of the canonical JSON of the presentation, without its proof.
+pub fn presentation_resource(presentation: &Value) -> String {
+    let mut presentation = presentation.clone();
+    if let Some(presentation) = presentation.as_object_mut() {
+        presentation.remove("proof");
+    }
+    let hash = <sha2::Sha256 as sha2::Digest>::digest(jcs(&presentation).as_bytes());
+    format!("{}{}", URN_PREFIX, hex::encode(&hash))
+}
+
+impl Cacao {
+    /// Package a SIWE message and its hex-encoded EIP-191 signature.
+    pub fn from_siwe(message: &SiweMessage, signature: String) -> Self {
+        Self {
+            h: CacaoHeader {
+                t: HEADER_TYPE_EIP4361.to_string(),
+            },
+            p: CacaoPayload {
+                domain: message.domain.clone(),
+                iss: format!("did:pkh:eip155:{}:{}", message.chain_id, message.address),
+                aud: message.uri.clone(),
+                version: message.version.clone(),
+                nonce: message.nonce.clone(),
+                iat: message.issued_at.clone(),
+                nbf: message.not_before.clone(),
+                exp: message.expiration_time.clone(),
+                statement: message.statement.clone(),
+                request_id: message.request_id.clone(),
+                resources: if message.resources.is_empty() {
+                    None
+                } else {
+                    Some(message.resources.clone())
+                },
+            },
+            s: CacaoSignature {
+                t: SIGNATURE_TYPE_EIP191.to_string(),
+                s: signature,
+            },
+        }
+    }
+
+    /// Reconstruct the signed SIWE message.
+    pub fn to_siwe(&self) -> Result<SiweMessage, CacaoError> {
+        if self.h.t != HEADER_TYPE_EIP4361 {
+            return Err(CacaoError::UnsupportedType(self.h.t.clone()));
+        }
+        let account = self
+            .p
+            .iss
+            .strip_prefix("did:pkh:eip155:")
+            .ok_or_else(|| CacaoError::InvalidIssuer(self.p.iss.clone()))?;
+        let mut parts = account.splitn(2, ':');
+        let (chain_id, address) = match (parts.next().map(u64::from_str), parts.next()) {
+            (Some(Ok(chain_id)), Some(address)) => (chain_id, address),
+            _ => return Err(CacaoError::InvalidIssuer(self.p.iss.clone())),
+        };
+        Ok(SiweMessage {
+            domain: self.p.domain.clone(),
+            address: address.to_string(),
+            statement: self.p.statement.clone(),
+            uri: self.p.aud.clone(),
+            version: self.p.version.clone(),
+            chain_id,
+            nonce: self.p.nonce.clone(),
+            issued_at: self.p.iat.clone(),
+            expiration_time: self.p.exp.clone(),
+            not_before: self.p.nbf.clone(),
+            request_id: self.p.request_id.clone(),
+            resources: self.p.resources.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Sign a SIWE message with a secp256k1 key, whose address must match the message.
+    pub fn sign(message: &SiweMessage, jwk: &JWK) -> Result<Self, CacaoError> {
+        let signing_key = signing_key(jwk)?;
+        let address = eth_address(&signing_key.verify_key());
+        if !address.eq_ignore_ascii_case(&message.address) {
+            return Err(CacaoError::AddressMismatch(address));
+        }
+        let signature: recoverable::Signature =
+            signing_key.sign(&eip191_message(&message.to_string()));
+        let mut bytes = signature.as_ref().to_vec();
+        bytes[64] += 27;
+        Ok(Self::from_siwe(
+            message,
+            "0x".to_string() + &hex::encode(&bytes),
+        ))
+    }
+
+    /// Whether the SIWE message lists the [`presentation_resource`] of a presentation.
+    pub fn binds(&self, presentation: &Value) -> bool {
+        let resource = presentation_resource(presentation);
+        self.p
+            .resources
+            .iter()
+            .flatten()
+            .any(|listed| *listed == resource)
+    }
+
+    /// Verify the signature, and the validity period as of the given time.
+    pub fn verify(&self, as_of: DateTime<Utc>) -> Result<(), CacaoError> {
+        if self.s.t != SIGNATURE_TYPE_EIP191 {
+            return Err(CacaoError::UnsupportedType(self.s.t.clone()));
+        }
+        let message = self.to_siwe()?;
+        let hex = self.s.s.strip_prefix("0x").unwrap_or(&self.s.s);
+        if hex.len() != 130 {
+            return Err(CacaoError::InvalidSignature);
+        }
+        let mut bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| CacaoError::InvalidSignature)?;
+        if bytes[64] >= 27 {
+            bytes[64] -= 27;
+        }
+        let signature = recoverable::Signature::try_from(&bytes[..])
+            .map_err(|_| CacaoError::InvalidSignature)?;
+        let key = signature
+            .recover_verify_key(&eip191_message(&message.to_string()))
+            .map_err(|_| CacaoError::InvalidSignature)?;
+        let address = eth_address(&key);
+        if !address.eq_ignore_ascii_case(&message.address) {
+            return Err(CacaoError::AddressMismatch(address));
+        }
+        if let Some(ref exp) = self.p.exp {
+            if DateTime::parse_from_rfc3339(exp)? <= as_of {
+                return Err(CacaoError::Expired);
+            }
+        }
+        if let Some(ref nbf) = self.p.nbf {
+            if DateTime::parse_from_rfc3339(nbf)? > as_of {
+                return Err(CacaoError::NotYetValid);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(address: &str) -> SiweMessage {
+        SiweMessage {
+            domain: "service.example.org".to_string(),
+            address: address.to_string(),
+            statement: Some("Sign in to the example service".to_string()),
+            uri: "https://service.example.org/login".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            nonce: "32891756".to_string(),
+            issued_at: "2021-09-30T16:25:24Z".to_string(),
+            expiration_time: Some("2031-09-30T16:25:24Z".to_string()),
+            not_before: None,
+            request_id: None,
+            resources: vec!["https://example.org/my-web2-claim.json".to_string()],
+        }
+    }
+
+    #[test]
+    fn siwe_round_trip() {
+        let message = message("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let text = message.to_string();
+        assert!(text.starts_with("service.example.org wants you to sign in"));
+        assert_eq!(SiweMessage::from_str(&text).unwrap(), message);
+
+        let no_statement = SiweMessage {
+            statement: None,
+            resources: Vec::new(),
+            ..message
+        };
+        let text = no_statement.to_string();
+        assert!(text.contains("\n\n\nURI: "));
+        assert_eq!(SiweMessage::from_str(&text).unwrap(), no_statement);
+    }
+
+    fn secp256k1_key() -> JWK {
+        serde_json::from_value(serde_json::json!({
+            "kty": "EC",
+            "crv": "secp256k1",
+            "x": "yclqMZ0MtyVkKm1eBh2AyaUtsqT0l5RJM3g4SzRT96A",
+            "y": "yQzUwKnftWCJPGs-faGaHiYi1sxA6fGJVw2Px_LCNe8",
+            "d": "meTmccmR_6ZsOa2YuTTkKkJ4ZPYsKdAH1Wx_RRf2j_E"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let jwk = secp256k1_key();
+        let wrong = message("0x0000000000000000000000000000000000000000");
+        assert!(Cacao::sign(&wrong, &jwk).is_err());
+
+        let address = key_address(&jwk).unwrap();
+        let cacao = Cacao::sign(&message(&address), &jwk).unwrap();
+        assert!(cacao.p.iss.starts_with("did:pkh:eip155:1:0x"));
+        cacao.verify(Utc::now()).unwrap();
+
+        let mut tampered = cacao.clone();
+        tampered.p.nonce = "00000000".to_string();
+        assert!(tampered.verify(Utc::now()).is_err());
+        let far_future = DateTime::parse_from_rfc3339("2040-01-01T00:00:00Z").unwrap();
+        assert!(matches!(
+            cacao.verify(far_future.with_timezone(&Utc)),
+            Err(CacaoError::Expired)
+        ));
+    }
+
+    #[test]
+    fn bind_presentation() {
+        let jwk = secp256k1_key();
+        let mut message = message(&key_address(&jwk).unwrap());
+        let presentation = serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{ "id": "urn:uuid:1" }],
+        });
+        message.resources = vec![presentation_resource(&presentation)];
+        let cacao = Cacao::sign(&message, &jwk).unwrap();
+        assert!(cacao.binds(&presentation));
+        // Members are bound whatever their order, and without a proof.
+        let mut reordered = serde_json::json!({
+            "verifiableCredential": [{ "id": "urn:uuid:1" }],
+            "type": ["VerifiablePresentation"],
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+        });
+        assert!(cacao.binds(&reordered));
+        reordered["proof"] = serde_json::json!({ "type": "Ed25519Signature2018" });
+        assert!(cacao.binds(&reordered));
+        let mut swapped = presentation.clone();
+        swapped["verifiableCredential"] = serde_json::json!([{ "id": "urn:uuid:2" }]);
+        assert!(!cacao.binds(&swapped));
+    }
+}
@@ -0,0 +1,295 @@
+//! Cancellation and deadlines of long-running operations.
+//!
+//! A [`Cancellation`] is shared between an operation and whoever may cancel it: another thread,
+//! an FFI caller, or an elapsed deadline. Dropping a future cancels everything it awaits, so
+//! running an operation with [`Cancellation::run`], e.g. [`verify_credential`], cancels the DID
+//! resolutions, context and resource fetches and status list fetches it is waiting for. Code that
+//! only passes a resolver on can bound the resolutions instead, with [`CancellableResolver`].
+//!
+//! Deadlines use Tokio timers, and are not available on WebAssembly.
+//!
+//! [`verify_credential`]: crate::verify_credential
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::future::{self, Either};
+use ssi::did::PrimaryDIDURL;
+use thiserror::Error;
+
+use crate::resolution_error::{ERROR_INTERNAL, PROPERTY_ERROR_MESSAGE};
+use crate::{
+    Content, ContentMetadata, DIDResolver, DereferencingInputMetadata, Document, DocumentMetadata,
+    Metadata, ResolutionInputMetadata, ResolutionMetadata,
+};
+
+type ResolutionOutput = (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+);
+
+const ACTIVE: u8 = 0;
+const CANCELLED: u8 = 1;
+const TIMED_OUT: u8 = 2;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum CancelError {
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Operation timed out")]
+    TimedOut,
+}
+
+#[derive(Default)]
+struct State {
+    status: AtomicU8,
+    wakers: Mutex<Vec<Waker>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    deadline: Option<Instant>,
+}
+
+/// A cancellation token, with an optional deadline. Clones share the same state.
+#[derive(Clone, Default)]
+pub struct Cancellation {
+    state: Arc<State>,
+}
+
+impl std::fmt::Debug for Cancellation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cancellation")
+            .field("status", &self.check())
+            .finish()
+    }
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that times out at a deadline.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            state: Arc::new(State {
+                deadline: Some(deadline),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// A token that times out after a duration.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.state.deadline
+    }
+
+    fn finish(&self, status: u8) {
+        // The first of cancellation and timeout wins.
+        let current = &self.state.status;
+        current
+            .compare_exchange(ACTIVE, status, Ordering::SeqCst, Ordering::SeqCst)
+            .ok();
+        let wakers = match self.state.wakers.lock() {
+            Ok(mut wakers) => std::mem::take(&mut *wakers),
+            Err(_) => Vec::new(),
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Cancel the operations using this token, or any of its clones.
+    pub fn cancel(&self) {
+        self.finish(CANCELLED);
+    }
+
+    /// Whether the token was cancelled or its deadline has passed.
+    pub fn check(&self) -> Result<(), CancelError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(deadline) = self.state.deadline {
+            if Instant::now() >= deadline {
+                self.finish(TIMED_OUT);
+            }
+        }
+        match self.state.status.load(Ordering::SeqCst) {
+            ACTIVE => Ok(()),
+            TIMED_OUT => Err(CancelError::TimedOut),
+            _ => Err(CancelError::Cancelled),
+        }
+    }
+
+    fn wait_cancelled(&self) -> impl Future<Output = CancelError> + '_ {
+        future::poll_fn(move |cx| {
+            if let Err(err) = self.check() {
+                return Poll::Ready(err);
+            }
+            if let Ok(mut wakers) = self.state.wakers.lock() {
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+            }
+            // Cancelled while registering
+            match self.check() {
+                Ok(()) => Poll::Pending,
+                Err(err) => Poll::Ready(err),
+            }
+        })
+    }
+
+    /// Wait until the token is cancelled or times out.
+    pub async fn cancelled(&self) -> CancelError {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(deadline) = self.state.deadline {
+            let sleep = Box::pin(tokio::time::sleep_until(deadline.into()));
+            let cancelled = Box::pin(self.wait_cancelled());
+            return match future::select(cancelled, sleep).await {
+                Either::Left((err, _)) => err,
+                Either::Right(_) => {
+                    self.finish(TIMED_OUT);
+                    self.check().err().unwrap_or(CancelError::TimedOut)
+                }
+            };
+        }
+        self.wait_cancelled().await
+    }
+
+    /// Run an operation until it completes, or until the token is cancelled or times out, in
+    /// which case the operation is dropped.
+    pub async fn run<F: Future>(&self, operation: F) -> Result<F::Output, CancelError> {
+        self.check()?;
+        let operation = Box::pin(operation);
+        let cancelled = Box::pin(self.cancelled());
+        match future::select(operation, cancelled).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right((err, _)) => Err(err),
+        }
+    }
+
+    /// Wrap a resolver, to fail its resolutions when the token is cancelled or times out.
+    pub fn resolver<'a>(&self, resolver: &'a dyn DIDResolver) -> CancellableResolver<'a> {
+        CancellableResolver {
+            inner: resolver,
+            cancellation: self.clone(),
+        }
+    }
+}
+
+/// DID resolver failing resolutions with `internalError` once its [`Cancellation`] is cancelled
+/// or times out, including resolutions in progress.
+pub struct CancellableResolver<'a> {
+    inner: &'a dyn DIDResolver,
+    cancellation: Cancellation,
+}
+
+pub(crate) fn cancelled_metadata(err: CancelError) -> ResolutionMetadata {
+    let mut property_set = HashMap::new();
+    property_set.insert(
+        PROPERTY_ERROR_MESSAGE.to_string(),
+        Metadata::String(err.to_string()),
+    );
+    ResolutionMetadata {
+        error: Some(ERROR_INTERNAL.to_string()),
+        property_set: Some(property_set),
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl<'a> DIDResolver for CancellableResolver<'a> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        let resolution = self.inner.resolve(did, input_metadata);
+        match self.cancellation.run(resolution).await {
+            Ok(output) => output,
+            Err(err) => (cancelled_metadata(err), None, None),
+        }
+    }
+
+    async fn resolve_representation(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (ResolutionMetadata, Vec<u8>, Option<DocumentMetadata>) {
+        let resolution = self.inner.resolve_representation(did, input_metadata);
+        match self.cancellation.run(resolution).await {
+            Ok(output) => output,
+            Err(err) => (cancelled_metadata(err), Vec::new(), None),
+        }
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(ResolutionMetadata, Content, ContentMetadata)> {
+        let dereferencing = self.inner.dereference(primary_did_url, input_metadata);
+        match self.cancellation.run(dereferencing).await {
+            Ok(output) => output,
+            Err(err) => Some((
+                cancelled_metadata(err),
+                Content::Null,
+                ContentMetadata::default(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SlowResolver;
+
+    #[async_trait]
+    impl DIDResolver for SlowResolver {
+        async fn resolve(
+            &self,
+            _did: &str,
+            _input_metadata: &ResolutionInputMetadata,
+        ) -> ResolutionOutput {
+            future::pending().await
+        }
+    }
+
+    #[test]
+    fn cancel_and_time_out() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let cancellation = Cancellation::new();
+            assert_eq!(cancellation.run(async { 1 }).await, Ok(1));
+            let canceller = cancellation.clone();
+            let pending = cancellation.run(future::pending::<()>());
+            let cancel = async {
+                tokio::task::yield_now().await;
+                canceller.cancel();
+            };
+            let (result, ()) = future::join(pending, cancel).await;
+            assert_eq!(result, Err(CancelError::Cancelled));
+            assert_eq!(cancellation.check(), Err(CancelError::Cancelled));
+
+            let cancellation = Cancellation::with_timeout(Duration::from_millis(10));
+            let resolver = cancellation.resolver(&SlowResolver);
+            let input = ResolutionInputMetadata::default();
+            let (res_meta, doc, _) = resolver.resolve("did:example:slow", &input).await;
+            assert!(doc.is_none());
+            assert_eq!(res_meta.error.as_deref(), Some(ERROR_INTERNAL));
+            assert_eq!(cancellation.check(), Err(CancelError::TimedOut));
+        });
+    }
+}
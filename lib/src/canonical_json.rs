@@ -0,0 +1,113 @@
+//! Canonical JSON output.
+//!
+//! Serializing the same data twice gives the same JSON, byte for byte, with the [JSON
+//! Canonicalization Scheme][RFC 8785] ([`jcs`]): object members are sorted by name, without
+//! whitespace, and numbers are formatted as in ECMAScript. [`to_string`] also sorts the arrays of
+//! verification results whose order does not carry meaning ([`UNORDERED_ARRAYS`]), such as the
+//! checks and warnings of a report, which may be listed in a different order from one run to the
+//! next. Arrays of documents are left in order, as is the `type` of a credential, which is signed
+//! as is by JCS-based proofs.
+//!
+//! This gives output that can be compared between runs, and signed or hashed reproducibly, e.g.
+//! by audit pipelines.
+//!
+//! [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Properties of verification results whose arrays are sets, sorted by [`to_string`].
+pub const UNORDERED_ARRAYS: &[&str] = &[
+    "checks",
+    "warnings",
+    "errors",
+    "policyChecks",
+    "resolutions",
+];
+
+/// JSON Canonicalization Scheme ([RFC 8785]) serialization: object members sorted by the UTF-16
+/// code units of their names, without whitespace.
+///
+/// [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+pub fn jcs(value: &Value) -> String {
+    match value {
+        Value::Object(object) => {
+            let mut members: Vec<(&String, &Value)> = object.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            let members: Vec<String> = members
+                .into_iter()
+                .map(|(name, value)| format!("{}:{}", Value::String(name.clone()), jcs(value)))
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(jcs).collect();
+            format!("[{}]", values.join(","))
+        }
+        Value::Number(number) => match number.as_f64() {
+            // Integral floats are serialized as integers, as in ECMAScript.
+            Some(float)
+                if !number.is_i64()
+                    && !number.is_u64()
+                    && float.fract() == 0.0
+                    && float.abs() < 1e21 =>
+            {
+                format!("{}", float as i128)
+            }
+            _ => number.to_string(),
+        },
+        value => value.to_string(),
+    }
+}
+
+/// Sort the arrays of the [`UNORDERED_ARRAYS`] properties, in a value and the values it
+/// contains, by the JCS serialization of their items.
+pub fn sort_unordered(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if let Value::Array(values) = value {
+                    if UNORDERED_ARRAYS.contains(&name.as_str()) {
+                        values.sort_by_cached_key(jcs);
+                    }
+                }
+                sort_unordered(value);
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_unordered),
+        _ => {}
+    }
+}
+
+/// Serialize a value as canonical JSON, with its unordered arrays sorted.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(value)?;
+    sort_unordered(&mut value);
+    Ok(jcs(&value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_output() {
+        let report = json!({
+            "warnings": [{ "message": "b", "code": "other" }, { "code": "other", "message": "a" }],
+            "checks": ["proof", "credentialStatus"],
+            "credentials": [{ "errors": ["z", "y"] }, { "errors": [] }],
+            "type": ["VerifiableCredential", "A"],
+            "count": 2.0
+        });
+        assert_eq!(
+            to_string(&report).unwrap(),
+            concat!(
+                r#"{"checks":["credentialStatus","proof"],"count":2,"#,
+                r#""credentials":[{"errors":["y","z"]},{"errors":[]}],"#,
+                r#""type":["VerifiableCredential","A"],"#,
+                r#""warnings":[{"code":"other","message":"a"},{"code":"other","message":"b"}]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Caching of linked data proof preparation.
+//!
+//! Preparing a linked data proof canonicalizes (URDNA2015) and hashes both the document and the
+//! proof options, which dominates the cost of issuing with a fast signer. A
+//! [`CanonicalizationCache`] keeps prepared proofs keyed by a SHA-256 hash of the document, the
+//! proof options and the public key, so that issuing the same document again with the same
+//! options, e.g. when retrying a bulk job or re-signing with a remote signer, skips
+//! canonicalization. Setting it in [`IssueOptions`](crate::IssueOptions) enables it for
+//! [`issue_credential`](crate::issue_credential) and
+//! [`issue_presentation`](crate::issue_presentation).
+//!
+//! The key includes the proof's `created` time, so options built once for a batch (with
+//! [`ProofOptionsBuilder`](crate::ProofOptionsBuilder)) share cache entries, while options with a
+//! fresh `created` time for each call do not. Options without a `created` time are not cached:
+//! `ssi` sets it to the current time when preparing the proof, and a cached preparation would
+//! keep the time of the first call.
+//!
+//! Only identical documents share entries. Credentials issued from the same template with
+//! different subject values miss, and neither JSON-LD context processing nor the expansion of
+//! a template is reused between them: both happen inside `ssi`'s proof preparation, which loads
+//! contexts with its own static loader and does not expose the expansion. The cache therefore
+//! does not reduce the canonicalization cost of a bulk job issuing different credentials; that
+//! needs a caching context loader in `ssi`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use ssi::ldp::{LinkedDataDocument, LinkedDataProofs, ProofPreparation};
+
+use crate::observe::{self, error_message, Event, Timer};
+use crate::signer::SignerError;
+use crate::{DIDResolver, LinkedDataProofOptions, JWK};
+
+type CacheKey = [u8; 32];
+
+#[derive(Debug)]
+struct CacheEntry {
+    preparation: ProofPreparation,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    counter: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// Hit and miss counts of a [`CanonicalizationCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// LRU cache of prepared linked data proofs.
+#[derive(Debug)]
+pub struct CanonicalizationCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl Default for CanonicalizationCache {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl CanonicalizationCache {
+    /// Create a cache of at most `capacity` prepared proofs.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Prepare a proof, or get a copy of a proof prepared earlier for the same document, options
+    /// and key. Proofs without a `created` time in the options are prepared every time.
+    pub async fn prepare<T>(
+        &self,
+        document: &T,
+        options: &LinkedDataProofOptions,
+        resolver: &dyn DIDResolver,
+        public_jwk: &JWK,
+        extra_proof_properties: Option<Map<String, Value>>,
+    ) -> Result<ProofPreparation, SignerError>
+    where
+        T: LinkedDataDocument + Serialize + Sync,
+    {
+        let timer = Timer::start();
+        let key = match options.created {
+            Some(_) => Some(cache_key(
+                document,
+                options,
+                public_jwk,
+                &extra_proof_properties,
+            )?),
+            None => None,
+        };
+        if let Some(preparation) = key.as_ref().and_then(|key| self.get(key)) {
+            observe::emit(|| Event::Canonicalized {
+                cached: true,
+                error: None,
+                elapsed: timer.elapsed(),
+            });
+            return Ok(preparation);
+        }
+        let preparation = LinkedDataProofs::prepare(
+            document,
+            options,
+            resolver,
+            public_jwk,
+            extra_proof_properties,
+        )
+        .await;
+        let error = error_message(&preparation);
+        observe::emit(|| Event::Canonicalized {
+            cached: false,
+            error: error.as_deref(),
+            elapsed: timer.elapsed(),
+        });
+        let preparation = preparation?;
+        if let Some(key) = key {
+            self.insert(key, preparation.clone());
+        }
+        Ok(preparation)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        match self.state.lock() {
+            Ok(state) => CacheStats {
+                hits: state.hits,
+                misses: state.misses,
+                entries: state.entries.len(),
+            },
+            Err(_) => CacheStats::default(),
+        }
+    }
+
+    /// Remove all cached proofs.
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<ProofPreparation> {
+        let mut state = self.state.lock().ok()?;
+        state.counter += 1;
+        let counter = state.counter;
+        match state.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = counter;
+                let preparation = entry.preparation.clone();
+                state.hits += 1;
+                Some(preparation)
+            }
+            None => {
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: CacheKey, preparation: ProofPreparation) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            let lru = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(lru) = lru {
+                state.entries.remove(&lru);
+            }
+        }
+        state.counter += 1;
+        let last_used = state.counter;
+        state.entries.insert(
+            key,
+            CacheEntry {
+                preparation,
+                last_used,
+            },
+        );
+    }
+}
+
+/// Hash of the inputs of proof preparation. JSON objects serialize with sorted keys, so the hash
+/// does not depend on property order.
+fn cache_key<T: Serialize>(
+    document: &T,
+    options: &LinkedDataProofOptions,
+    public_jwk: &JWK,
+    extra_proof_properties: &Option<Map<String, Value>>,
+) -> Result<CacheKey, serde_json::Error> {
+    let input = json!([
+        serde_json::to_value(document)?,
+        serde_json::to_value(options)?,
+        serde_json::to_value(public_jwk.to_public())?,
+        extra_proof_properties,
+    ]);
+    let mut key = [0; 32];
+    key.copy_from_slice(&Sha256::digest(&serde_json::to_vec(&input)?));
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_property_order() {
+        let options = LinkedDataProofOptions::default();
+        let jwk = JWK::generate_ed25519().unwrap();
+        let a: Value = serde_json::from_str(r#"{"a": 1, "b": {"c": 2, "d": 3}}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"b": {"d": 3, "c": 2}, "a": 1}"#).unwrap();
+        let c: Value = serde_json::from_str(r#"{"a": 1, "b": {"c": 2, "d": 4}}"#).unwrap();
+        let key = |doc: &Value| cache_key(doc, &options, &jwk, &None).unwrap();
+        assert_eq!(key(&a), key(&b));
+        assert_ne!(key(&a), key(&c));
+    }
+
+    #[test]
+    fn cache_only_options_with_created_time() {
+        use crate::issue::find_verification_method;
+        use crate::{CredentialBuilder, DIDMethod, ProofPurpose, Source, DID_METHODS};
+        use chrono::{TimeZone, Utc};
+        use ssi::vc::URI;
+
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let purpose = ProofPurpose::AssertionMethod;
+        let verification_method = rt
+            .block_on(find_verification_method(&did, &key, &purpose, resolver))
+            .unwrap();
+        let credential = CredentialBuilder::new()
+            .issuer(&did)
+            .issuance_date(Utc.timestamp_opt(1_600_000_000, 0).unwrap())
+            .subject(json!({ "id": "did:example:subject" }))
+            .build()
+            .unwrap();
+        let mut options = LinkedDataProofOptions {
+            verification_method: Some(URI::String(verification_method)),
+            proof_purpose: Some(purpose),
+            ..Default::default()
+        };
+        let cache = CanonicalizationCache::default();
+        let prepare = |options: &LinkedDataProofOptions| {
+            rt.block_on(cache.prepare(&credential, options, resolver, &key, None))
+                .unwrap()
+        };
+
+        prepare(&options);
+        prepare(&options);
+        assert_eq!(cache.stats(), CacheStats::default());
+
+        options.created = Some(Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+        prepare(&options);
+        prepare(&options);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                entries: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Content hashes and content-addressable IDs of credentials.
+//!
+//! The content hash of a credential is the SHA-256 hash of its URDNA2015 canonical form, without
+//! its proofs or `id`: credentials with the same claims have the same hash, however their JSON is
+//! serialized, signed or identified. A JWT credential has the hash of the credential that its
+//! claims encode. Contexts are loaded as for linked data proofs, so a credential whose contexts
+//! `ssi` does not know has no content hash.
+//!
+//! A content-addressable ID is the `urn:hash::sha256:<hex>` URN of a content hash. Setting
+//! [`IssueOptions::content_id`](crate::IssueOptions) issues credentials with it as their `id`, so
+//! that issuing the same claims again gives the same ID, by which the [`store`](crate::store)
+//! deduplicates them.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ssi::ldp::LinkedDataDocument;
+use thiserror::Error;
+
+use crate::hex;
+use crate::{VerifiableCredential, URI};
+
+/// Prefix of content-addressable IDs, before the hash in hex.
+pub const URN_PREFIX: &str = "urn:hash::sha256:";
+
+#[derive(Error, Debug)]
+pub enum ContentIdError {
+    #[error("Unable to canonicalize credential: {0}")]
+    Canonicalization(#[from] ssi::error::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Credential ID {0} is not its content ID {1}")]
+    IdMismatch(String, String),
+}
+
+/// SHA-256 hash of the canonical form of a credential, without its proofs or `id`.
+pub async fn content_hash(credential: &VerifiableCredential) -> Result<[u8; 32], ContentIdError> {
+    let mut credential = credential.clone();
+    credential.id = None;
+    credential.proof = None;
+    let dataset = credential.to_dataset_for_signing(None).await?;
+    let nquads = ssi::urdna2015::normalize(&dataset)?.to_nquads()?;
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(nquads.as_bytes()));
+    Ok(hash)
+}
+
+/// Content hash of a credential in JSON, or of a JWT credential given as a string, in hex.
+pub async fn content_hash_hex(credential: &Value) -> Result<String, ContentIdError> {
+    let credential = match credential {
+        Value::String(jwt) => VerifiableCredential::from_jwt_unsigned(jwt)?,
+        credential => serde_json::from_value(credential.clone())?,
+    };
+    Ok(hex::encode(&content_hash(&credential).await?))
+}
+
+/// Content-addressable ID of a credential.
+pub async fn content_id(credential: &VerifiableCredential) -> Result<String, ContentIdError> {
+    let hash = content_hash(credential).await?;
+    Ok(format!("{}{}", URN_PREFIX, hex::encode(&hash)))
+}
+
+/// Set the `id` of a credential to its content-addressable ID, and return it. A credential with a
+/// different `id` is an error.
+pub async fn set_content_id(
+    credential: &mut VerifiableCredential,
+) -> Result<String, ContentIdError> {
+    let id = content_id(credential).await?;
+    match credential.id {
+        Some(ref existing) if existing.to_string() != id => {
+            Err(ContentIdError::IdMismatch(existing.to_string(), id))
+        }
+        _ => {
+            credential.id = Some(URI::String(id.clone()));
+            Ok(id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn content_ids() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let credential = json!({
+                "@context": "https://www.w3.org/2018/credentials/v1",
+                "type": "VerifiableCredential",
+                "issuer": "did:example:issuer",
+                "issuanceDate": "2021-01-01T00:00:00Z",
+                "credentialSubject": { "id": "did:example:alice" }
+            });
+            let mut vc: VerifiableCredential = serde_json::from_value(credential.clone()).unwrap();
+            let id = set_content_id(&mut vc).await.unwrap();
+            assert!(id.starts_with(URN_PREFIX));
+            assert_eq!(id.len(), URN_PREFIX.len() + 64);
+            // The ID is excluded from the hash.
+            assert_eq!(set_content_id(&mut vc).await.unwrap(), id);
+            assert_eq!(
+                content_hash_hex(&serde_json::to_value(&vc).unwrap())
+                    .await
+                    .unwrap(),
+                content_hash_hex(&credential).await.unwrap()
+            );
+
+            let mut other = credential;
+            other["credentialSubject"]["id"] = json!("did:example:bob");
+            let mut other: VerifiableCredential = serde_json::from_value(other).unwrap();
+            assert_ne!(content_id(&other).await.unwrap(), id);
+            other.id = Some(URI::String(id));
+            assert!(matches!(
+                set_content_id(&mut other).await,
+                Err(ContentIdError::IdMismatch(_, _))
+            ));
+        });
+    }
+}
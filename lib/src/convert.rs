@@ -0,0 +1,362 @@
+//! Conversion of credentials between proof formats.
+//!
+//! [`convert_credential`] verifies a credential, removes its proof or decodes its JWT claims, and
+//! issues the resulting credential again in another proof format, with the issuer's key. The
+//! claims of a VC-JWT map to credential properties (`iss` to `issuer`, `nbf` to `issuanceDate`,
+//! `exp` to `expirationDate`, `jti` to `id`, `sub` to the subject's `id`), and back. Anything
+//! that does not map, such as other JWT claims, or proof properties other than those set from
+//! the issue options, is dropped and reported in the conversion's warnings. The claims map with
+//! the JWT claim mapping of the issue options (see [`crate::jwt_claims`]), both when decoding a JWT
+//! and when issuing one.
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::error::Error;
+use crate::issue::{find_verification_method, IssueOptions, KeyDiscoveryError};
+use crate::jwt_claims::{self, JWTClaimsError, JWTClaimsOptions};
+use crate::securing;
+use crate::signer::{Signer, SignerError};
+use crate::verification::decode_jwt_unverified;
+use crate::{
+    issue_credential, verify_credential, CredentialOrJWT, DIDResolver, JWTOrLDPOptions,
+    PresentationOrJWT, ProofFormat, ProofPurpose, VerifiableCredential, URI,
+};
+
+/// JWT claims that map to credential properties.
+const MAPPED_CLAIMS: &[&str] = &["iss", "sub", "nbf", "exp", "jti", "iat", "vc"];
+
+/// Proof properties that are set again from the issue options.
+const MAPPED_PROOF_PROPERTIES: &[&str] = &[
+    "type",
+    "proofPurpose",
+    "verificationMethod",
+    "created",
+    "jws",
+    "proofValue",
+];
+
+#[derive(Error, Debug)]
+pub enum ConvertError {
+    #[error("Invalid credential: {0}")]
+    InvalidCredential(String),
+    #[error("Credential does not verify: {0}")]
+    Verification(String),
+    #[error("Credential is already in proof format {0}")]
+    SameFormat(ProofFormat),
+    #[error("Credential has no issuer DID")]
+    MissingIssuer,
+    #[error(transparent)]
+    KeyDiscovery(#[from] KeyDiscoveryError),
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    #[error(transparent)]
+    JWTClaims(#[from] JWTClaimsError),
+    #[error(transparent)]
+    DIDKit(#[from] Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// A credential converted to another proof format.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    pub credential: CredentialOrJWT,
+    /// Properties or claims of the original credential that could not be carried over
+    pub warnings: Vec<String>,
+}
+
+/// Proof format of a credential.
+pub fn proof_format(credential: &CredentialOrJWT) -> ProofFormat {
+    match credential {
+        CredentialOrJWT::Credential(_) => ProofFormat::LDP,
+        CredentialOrJWT::JWT(jwt) if securing::is_secured(jwt) => {
+            match securing::Secured::decode(jwt) {
+                Ok(secured) => secured.proof_format,
+                Err(_) => ProofFormat::JWT,
+            }
+        }
+        CredentialOrJWT::JWT(_) => ProofFormat::JWT,
+    }
+}
+
+/// Get the unsigned credential of a credential, with warnings for what is dropped.
+fn unsigned(
+    credential: &CredentialOrJWT,
+    jwt_claims: &JWTClaimsOptions,
+) -> Result<(VerifiableCredential, Vec<String>), ConvertError> {
+    let mut warnings = Vec::new();
+    let invalid = |e: ssi::error::Error| ConvertError::InvalidCredential(e.to_string());
+    let vc = match credential {
+        CredentialOrJWT::Credential(vc) => {
+            let proofs = serde_json::to_value(&vc.proof)?;
+            let proofs = match proofs {
+                Value::Array(proofs) => proofs,
+                Value::Null => Vec::new(),
+                proof => vec![proof],
+            };
+            if proofs.len() > 1 {
+                warnings.push(format!(
+                    "Credential has {} proofs; only one signature is issued",
+                    proofs.len()
+                ));
+            }
+            for proof in &proofs {
+                let properties = proof.as_object().into_iter().flatten();
+                for (name, _) in properties {
+                    if !MAPPED_PROOF_PROPERTIES.contains(&name.as_str()) {
+                        warnings.push(format!("Proof property '{}' is not carried over", name));
+                    }
+                }
+            }
+            let mut vc = vc.clone();
+            vc.proof = None;
+            vc
+        }
+        CredentialOrJWT::JWT(jwt) => match securing::payload_unverified(jwt) {
+            Some(payload) => serde_json::from_value(payload)?,
+            None => {
+                let (_, claims) = decode_jwt_unverified(jwt)
+                    .ok_or_else(|| ConvertError::InvalidCredential("Invalid JWT".to_string()))?;
+                for (name, _) in claims.as_object().into_iter().flatten() {
+                    if !MAPPED_CLAIMS.contains(&name.as_str()) {
+                        warnings.push(format!("JWT claim '{}' is not carried over", name));
+                    }
+                }
+                if jwt_claims == &JWTClaimsOptions::default() {
+                    VerifiableCredential::from_jwt_unsigned(jwt).map_err(invalid)?
+                } else {
+                    serde_json::from_value(jwt_claims::decode_credential(&claims, jwt_claims)?)?
+                }
+            }
+        },
+    };
+    Ok((vc, warnings))
+}
+
+/// Decode a credential to the data model, e.g. to output it after verification. The claims of a
+/// VC-JWT map to credential properties, as when converting it, and the payload of a VC-JOSE or
+/// VC-COSE credential is taken as is. A credential with a linked data proof is returned as is.
+pub fn decode_credential(
+    credential: &CredentialOrJWT,
+    jwt_claims: &JWTClaimsOptions,
+) -> Result<VerifiableCredential, ConvertError> {
+    match credential {
+        CredentialOrJWT::Credential(vc) => Ok(vc.clone()),
+        CredentialOrJWT::JWT(_) => Ok(unsigned(credential, jwt_claims)?.0),
+    }
+}
+
+/// Decode a presentation to the data model, with its credentials decoded with
+/// [`decode_credential`]. The `iss` and `jti` claims of a JWT presentation map to its `holder`
+/// and `id`.
+pub fn decode_presentation(
+    presentation: &PresentationOrJWT,
+    jwt_claims: &JWTClaimsOptions,
+) -> Result<Value, ConvertError> {
+    let mut vp = match presentation {
+        PresentationOrJWT::VP(vp) => serde_json::to_value(vp)?,
+        PresentationOrJWT::JWT(jwt) => match securing::payload_unverified(jwt) {
+            Some(payload) => payload,
+            None => {
+                let invalid = || ConvertError::InvalidCredential("Invalid JWT".to_string());
+                let (_, claims) = decode_jwt_unverified(jwt).ok_or_else(invalid)?;
+                let mut vp = claims.get("vp").cloned().ok_or_else(invalid)?;
+                let object = vp.as_object_mut().ok_or_else(invalid)?;
+                for (claim, property) in &[("iss", "holder"), ("jti", "id")] {
+                    if let Some(value) = claims.get(claim) {
+                        object
+                            .entry(property.to_string())
+                            .or_insert_with(|| value.clone());
+                    }
+                }
+                vp
+            }
+        },
+    };
+    let decode = |credential: &mut Value| -> Result<(), ConvertError> {
+        let vc: CredentialOrJWT = serde_json::from_value(credential.clone())?;
+        *credential = serde_json::to_value(decode_credential(&vc, jwt_claims)?)?;
+        Ok(())
+    };
+    match vp.get_mut("verifiableCredential") {
+        Some(Value::Array(credentials)) => credentials.iter_mut().try_for_each(decode)?,
+        Some(credential) => decode(credential)?,
+        None => {}
+    }
+    Ok(vp)
+}
+
+/// Convert a credential to another proof format: verify it, and issue it again with `signer`.
+///
+/// If the options give no verification method, the verification method of the issuer's DID
+/// document matching the signer's key is used, so that the converted credential can only be
+/// issued with the issuer's key.
+pub async fn convert_credential(
+    credential: &CredentialOrJWT,
+    to: ProofFormat,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<Conversion, ConvertError> {
+    let from = proof_format(credential);
+    if from == to {
+        return Err(ConvertError::SameFormat(to));
+    }
+    let report = verify_credential(credential, &JWTOrLDPOptions::default(), resolver).await?;
+    if !report.is_success() {
+        let errors: Vec<String> = report.errors.into_iter().map(|e| e.message).collect();
+        return Err(ConvertError::Verification(errors.join("; ")));
+    }
+    let (vc, mut warnings) = unsigned(credential, &options.jwt_claims)?;
+    if to == ProofFormat::JWT {
+        let subjects = serde_json::to_value(&vc.credential_subject)?;
+        let subjects = subjects.as_array().map_or(1, Vec::len);
+        if subjects > 1 {
+            let warning = "Credential has several subjects; the JWT has no 'sub' claim";
+            warnings.push(warning.to_string());
+        }
+    }
+
+    let mut options = options.clone();
+    options.proof_format = to;
+    if options.ldp_options.verification_method.is_none() {
+        let issuer = report.issuer.ok_or(ConvertError::MissingIssuer)?;
+        let purpose = options
+            .ldp_options
+            .proof_purpose
+            .clone()
+            .unwrap_or(ProofPurpose::AssertionMethod);
+        let public_key = signer.public_jwk();
+        let vm = find_verification_method(&issuer, &public_key, &purpose, resolver).await?;
+        options.ldp_options.verification_method = Some(URI::String(vm));
+    }
+    let credential = issue_credential(vc, &options, signer, resolver).await?;
+    Ok(Conversion {
+        credential,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DIDMethod, Source, DID_METHODS, JWK};
+    use serde_json::json;
+
+    #[test]
+    fn convert_ldp_jwt() {
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let vc: VerifiableCredential = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "id": "urn:uuid:7e1d6f3a-5b1d-4b8c-9f5e-3c2a1d0e9b8a",
+            "type": ["VerifiableCredential"],
+            "issuer": did,
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": { "id": "did:example:subject" }
+        }))
+        .unwrap();
+        let options = IssueOptions::default();
+        let ldp = rt
+            .block_on(issue_credential(vc, &options, &key, resolver))
+            .unwrap();
+        let err = rt
+            .block_on(convert_credential(
+                &ldp,
+                ProofFormat::LDP,
+                &options,
+                &key,
+                resolver,
+            ))
+            .unwrap_err();
+        assert!(matches!(err, ConvertError::SameFormat(ProofFormat::LDP)));
+
+        let jwt = rt
+            .block_on(convert_credential(
+                &ldp,
+                ProofFormat::JWT,
+                &options,
+                &key,
+                resolver,
+            ))
+            .unwrap();
+        assert!(jwt.warnings.is_empty());
+        let (_, claims) = match jwt.credential {
+            CredentialOrJWT::JWT(ref jwt) => decode_jwt_unverified(jwt).unwrap(),
+            _ => panic!("Expected a JWT"),
+        };
+        assert_eq!(claims["iss"], did);
+        assert_eq!(claims["sub"], "did:example:subject");
+
+        let back = rt
+            .block_on(convert_credential(
+                &jwt.credential,
+                ProofFormat::LDP,
+                &options,
+                &key,
+                resolver,
+            ))
+            .unwrap();
+        assert!(back.warnings.is_empty());
+        let report = rt
+            .block_on(verify_credential(
+                &back.credential,
+                &JWTOrLDPOptions::default(),
+                resolver,
+            ))
+            .unwrap();
+        assert!(report.is_success());
+
+        let other = JWK::generate_ed25519().unwrap();
+        let err = rt
+            .block_on(convert_credential(
+                &ldp,
+                ProofFormat::JWT,
+                &options,
+                &other,
+                resolver,
+            ))
+            .unwrap_err();
+        assert!(matches!(err, ConvertError::KeyDiscovery(_)));
+    }
+
+    fn unsigned_jwt(claims: Value) -> String {
+        let encode =
+            |value: &Value| base64::encode_config(value.to_string(), base64::URL_SAFE_NO_PAD);
+        let header = json!({ "alg": "EdDSA" });
+        format!("{}.{}.c2ln", encode(&header), encode(&claims))
+    }
+
+    #[test]
+    fn decode_jwt_presentation() {
+        let vc = unsigned_jwt(json!({
+            "iss": "did:example:issuer",
+            "sub": "did:example:subject",
+            "nbf": 1609459200,
+            "jti": "urn:uuid:7e1d6f3a-5b1d-4b8c-9f5e-3c2a1d0e9b8a",
+            "vc": {
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "type": ["VerifiableCredential"],
+                "credentialSubject": { "name": "Alice" }
+            }
+        }));
+        let vp = unsigned_jwt(json!({
+            "iss": "did:example:holder",
+            "vp": {
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "type": ["VerifiablePresentation"],
+                "verifiableCredential": [vc]
+            }
+        }));
+        let options = JWTClaimsOptions::default();
+        let decoded = decode_presentation(&PresentationOrJWT::JWT(vp), &options).unwrap();
+        assert_eq!(decoded["holder"], "did:example:holder");
+        let credential = &decoded["verifiableCredential"][0];
+        assert_eq!(credential["issuer"], "did:example:issuer");
+        assert_eq!(credential["credentialSubject"]["id"], "did:example:subject");
+        assert_eq!(credential["credentialSubject"]["name"], "Alice");
+    }
+}
@@ -0,0 +1,423 @@
+//! Typed construction of credentials.
+//!
+//! [`CredentialBuilder`] assembles a [`VerifiableCredential`] from typed parts, adds the JSON-LD
+//! contexts needed by the features used (such as a status list entry or a proof suite), and
+//! validates the result, so that it can be passed directly to [`crate::issue_credential`].
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::VerifiableCredential;
+
+pub const CREDENTIALS_V1_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// Properties that are set using their own builder methods.
+const RESERVED_PROPERTIES: &[&str] = &[
+    "@context",
+    "id",
+    "type",
+    "issuer",
+    "issuanceDate",
+    "expirationDate",
+    "credentialSubject",
+    "credentialStatus",
+    "credentialSchema",
+    "evidence",
+    "proof",
+];
+
+/// Contexts defining the terms of credential status types.
+const STATUS_CONTEXTS: &[(&str, &str)] = &[
+    (
+        "StatusList2021Entry",
+        "https://w3id.org/vc/status-list/2021/v1",
+    ),
+    (
+        "RevocationList2020Status",
+        "https://w3id.org/vc-revocation-list-2020/v1",
+    ),
+];
+
+/// Contexts defining the terms of proof types, for proof types not defined by the base context.
+const PROOF_CONTEXTS: &[(&str, &str)] = &[
+    (
+        "Ed25519Signature2020",
+        "https://w3id.org/security/suites/ed25519-2020/v1",
+    ),
+    (
+        "JsonWebSignature2020",
+        "https://w3id.org/security/suites/jws-2020/v1",
+    ),
+    (
+        "EcdsaSecp256k1Signature2019",
+        "https://w3id.org/security/suites/secp256k1-2019/v1",
+    ),
+];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CredentialError {
+    #[error("Missing issuer")]
+    MissingIssuer,
+    #[error("Missing credential subject")]
+    MissingSubject,
+    #[error("Credential subject must be a non-empty object")]
+    InvalidSubject,
+    #[error("Duplicate credential subject: {0}")]
+    DuplicateSubject(String),
+    #[error("Expected absolute URI for {0}: {1}")]
+    InvalidURI(&'static str, String),
+    #[error("Expiration date ({0}) must be after issuance date ({1})")]
+    ExpiresBeforeIssued(String, String),
+    #[error("Property '{0}' must be set using its own method")]
+    ReservedProperty(String),
+    #[error("Invalid credential: {0}")]
+    Invalid(String),
+}
+
+/// Builder for [`VerifiableCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct CredentialBuilder {
+    contexts: Vec<Value>,
+    id: Option<String>,
+    types: Vec<String>,
+    issuer: Option<Value>,
+    issuance_date: Option<DateTime<Utc>>,
+    expiration_date: Option<DateTime<Utc>>,
+    subjects: Vec<Value>,
+    status: Option<Value>,
+    schemas: Vec<Value>,
+    evidence: Vec<Value>,
+    proof_type: Option<String>,
+    properties: Map<String, Value>,
+}
+
+fn check_uri(name: &'static str, uri: &str) -> Result<(), CredentialError> {
+    // An absolute URI has a scheme, e.g. "did:", "urn:" or "https:".
+    match uri.find(':') {
+        Some(i) if i > 0 => Ok(()),
+        _ => Err(CredentialError::InvalidURI(name, uri.to_string())),
+    }
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl CredentialBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a JSON-LD context, after the base credentials context. Contexts for the features used
+    /// are added automatically.
+    pub fn context(mut self, context: impl Into<Value>) -> Self {
+        self.contexts.push(context.into());
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Add a credential type, in addition to `VerifiableCredential`.
+    pub fn type_(mut self, type_: impl Into<String>) -> Self {
+        self.types.push(type_.into());
+        self
+    }
+
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(Value::String(issuer.into()));
+        self
+    }
+
+    /// Issuer with additional properties, e.g. `name`.
+    pub fn issuer_with_properties(
+        mut self,
+        issuer: impl Into<String>,
+        mut properties: Map<String, Value>,
+    ) -> Self {
+        properties.insert("id".to_string(), Value::String(issuer.into()));
+        self.issuer = Some(Value::Object(properties));
+        self
+    }
+
+    /// Issuance date. Defaults to the current time.
+    pub fn issuance_date(mut self, issuance_date: DateTime<Utc>) -> Self {
+        self.issuance_date = Some(issuance_date);
+        self
+    }
+
+    pub fn expiration_date(mut self, expiration_date: DateTime<Utc>) -> Self {
+        self.expiration_date = Some(expiration_date);
+        self
+    }
+
+    /// Add a credential subject: an object of claims, with an optional `id`. With several
+    /// subjects, `credentialSubject` is an array of them, in order.
+    pub fn subject(mut self, subject: Value) -> Self {
+        self.subjects.push(subject);
+        self
+    }
+
+    /// Add several credential subjects, e.g. the members of a cohort.
+    pub fn subjects(mut self, subjects: impl IntoIterator<Item = Value>) -> Self {
+        self.subjects.extend(subjects);
+        self
+    }
+
+    /// Credential status, e.g. of type `StatusList2021Entry`, with its type-specific properties.
+    pub fn status(
+        mut self,
+        id: impl Into<String>,
+        type_: impl Into<String>,
+        mut properties: Map<String, Value>,
+    ) -> Self {
+        properties.insert("id".to_string(), Value::String(id.into()));
+        properties.insert("type".to_string(), Value::String(type_.into()));
+        self.status = Some(Value::Object(properties));
+        self
+    }
+
+    /// Add a credential schema, e.g. of type `JsonSchemaValidator2018`.
+    pub fn schema(mut self, id: impl Into<String>, type_: impl Into<String>) -> Self {
+        self.schemas.push(json!({
+            "id": id.into(),
+            "type": type_.into(),
+        }));
+        self
+    }
+
+    pub fn evidence(mut self, evidence: Value) -> Self {
+        self.evidence.push(evidence);
+        self
+    }
+
+    /// Proof type the credential will be signed with, so that its context can be included.
+    pub fn proof_type(mut self, proof_type: impl Into<String>) -> Self {
+        self.proof_type = Some(proof_type.into());
+        self
+    }
+
+    /// Additional top-level property, e.g. `termsOfUse`.
+    pub fn property(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.properties.insert(name.into(), value);
+        self
+    }
+
+    fn add_context(contexts: &mut Vec<Value>, context: &str) {
+        if !contexts.iter().any(|c| c.as_str() == Some(context)) {
+            contexts.push(Value::String(context.to_string()));
+        }
+    }
+
+    pub fn build(self) -> Result<VerifiableCredential, CredentialError> {
+        if let Some(name) = self
+            .properties
+            .keys()
+            .find(|name| RESERVED_PROPERTIES.contains(&name.as_str()))
+        {
+            return Err(CredentialError::ReservedProperty(name.clone()));
+        }
+        let issuer = self.issuer.ok_or(CredentialError::MissingIssuer)?;
+        match issuer {
+            Value::String(ref id) => check_uri("issuer", id)?,
+            Value::Object(ref object) => check_uri(
+                "issuer",
+                object.get("id").and_then(Value::as_str).unwrap_or_default(),
+            )?,
+            _ => return Err(CredentialError::MissingIssuer),
+        }
+        if self.subjects.is_empty() {
+            return Err(CredentialError::MissingSubject);
+        }
+        let mut subject_ids = Vec::new();
+        for subject in &self.subjects {
+            match subject {
+                Value::Object(object) if !object.is_empty() => {
+                    if let Some(id) = object.get("id") {
+                        let id = id.as_str().unwrap_or_default();
+                        check_uri("credentialSubject.id", id)?;
+                        if subject_ids.contains(&id) {
+                            return Err(CredentialError::DuplicateSubject(id.to_string()));
+                        }
+                        subject_ids.push(id);
+                    }
+                }
+                _ => return Err(CredentialError::InvalidSubject),
+            }
+        }
+        if let Some(ref id) = self.id {
+            check_uri("id", id)?;
+        }
+        let issuance_date = self.issuance_date.unwrap_or_else(Utc::now);
+        if let Some(ref expiration_date) = self.expiration_date {
+            if *expiration_date <= issuance_date {
+                return Err(CredentialError::ExpiresBeforeIssued(
+                    format_date(expiration_date),
+                    format_date(&issuance_date),
+                ));
+            }
+        }
+
+        let mut contexts = vec![Value::String(CREDENTIALS_V1_CONTEXT.to_string())];
+        contexts.extend(self.contexts);
+        if let Some(ref status) = self.status {
+            let type_ = status["type"].as_str().unwrap_or_default();
+            if let Some((_, context)) = STATUS_CONTEXTS.iter().find(|(t, _)| *t == type_) {
+                Self::add_context(&mut contexts, context);
+            }
+        }
+        if let Some(ref proof_type) = self.proof_type {
+            if let Some((_, context)) = PROOF_CONTEXTS.iter().find(|(t, _)| t == proof_type) {
+                Self::add_context(&mut contexts, context);
+            }
+        }
+
+        let mut types = vec!["VerifiableCredential".to_string()];
+        types.extend(
+            self.types
+                .into_iter()
+                .filter(|t| t != "VerifiableCredential"),
+        );
+
+        let mut credential = self.properties;
+        credential.insert("@context".to_string(), Value::Array(contexts));
+        if let Some(id) = self.id {
+            credential.insert("id".to_string(), Value::String(id));
+        }
+        credential.insert("type".to_string(), json!(types));
+        credential.insert("issuer".to_string(), issuer);
+        credential.insert(
+            "issuanceDate".to_string(),
+            Value::String(format_date(&issuance_date)),
+        );
+        if let Some(ref expiration_date) = self.expiration_date {
+            credential.insert(
+                "expirationDate".to_string(),
+                Value::String(format_date(expiration_date)),
+            );
+        }
+        let mut subjects = self.subjects;
+        credential.insert(
+            "credentialSubject".to_string(),
+            if subjects.len() == 1 {
+                subjects.remove(0)
+            } else {
+                Value::Array(subjects)
+            },
+        );
+        if let Some(status) = self.status {
+            credential.insert("credentialStatus".to_string(), status);
+        }
+        if !self.schemas.is_empty() {
+            credential.insert("credentialSchema".to_string(), Value::Array(self.schemas));
+        }
+        if !self.evidence.is_empty() {
+            credential.insert("evidence".to_string(), Value::Array(self.evidence));
+        }
+
+        let credential: VerifiableCredential = serde_json::from_value(Value::Object(credential))
+            .map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        credential
+            .validate_unsigned()
+            .map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        Ok(credential)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_credential() {
+        let credential = CredentialBuilder::new()
+            .id("urn:uuid:7c2a9f0e-3c2b-4d7e-9a51-3f4e0b6f8d21")
+            .type_("UniversityDegreeCredential")
+            .issuer("did:example:issuer")
+            .subject(json!({
+                "id": "did:example:subject",
+                "degree": "Bachelor of Science"
+            }))
+            .status(
+                "https://example.org/status/1#94567",
+                "StatusList2021Entry",
+                json!({
+                    "statusPurpose": "revocation",
+                    "statusListIndex": "94567",
+                    "statusListCredential": "https://example.org/status/1"
+                })
+                .as_object()
+                .cloned()
+                .unwrap(),
+            )
+            .proof_type("Ed25519Signature2020")
+            .build()
+            .unwrap();
+        let credential = serde_json::to_value(credential).unwrap();
+        assert_eq!(
+            credential["@context"],
+            json!([
+                CREDENTIALS_V1_CONTEXT,
+                "https://w3id.org/vc/status-list/2021/v1",
+                "https://w3id.org/security/suites/ed25519-2020/v1"
+            ])
+        );
+        assert_eq!(
+            credential["type"],
+            json!(["VerifiableCredential", "UniversityDegreeCredential"])
+        );
+
+        assert_eq!(
+            CredentialBuilder::new()
+                .subject(json!({"id": "did:example:subject"}))
+                .build()
+                .unwrap_err(),
+            CredentialError::MissingIssuer
+        );
+        assert_eq!(
+            CredentialBuilder::new()
+                .issuer("did:example:issuer")
+                .subject(json!({}))
+                .build()
+                .unwrap_err(),
+            CredentialError::InvalidSubject
+        );
+        assert_eq!(
+            CredentialBuilder::new()
+                .issuer("did:example:issuer")
+                .subject(json!({"id": "did:example:subject"}))
+                .property("proof", json!({}))
+                .build()
+                .unwrap_err(),
+            CredentialError::ReservedProperty("proof".to_string())
+        );
+    }
+
+    #[test]
+    fn build_multiple_subjects() {
+        let members = vec![
+            json!({"id": "did:example:alice", "cohort": "2021"}),
+            json!({"id": "did:example:bob", "cohort": "2021"}),
+        ];
+        let credential = CredentialBuilder::new()
+            .issuer("did:example:issuer")
+            .subjects(members.clone())
+            .build()
+            .unwrap();
+        let credential = serde_json::to_value(credential).unwrap();
+        assert_eq!(credential["credentialSubject"], json!(members));
+
+        assert_eq!(
+            CredentialBuilder::new()
+                .issuer("did:example:issuer")
+                .subjects(members.clone())
+                .subject(members[0].clone())
+                .build()
+                .unwrap_err(),
+            CredentialError::DuplicateSubject("did:example:alice".to_string())
+        );
+    }
+}
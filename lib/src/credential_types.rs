@@ -0,0 +1,317 @@
+//! Registry of known credential types.
+//!
+//! A [`CredentialTypeRegistry`] maps credential types to the [validators](CredentialValidator)
+//! of credentials of that type: a JSON Schema of the type ([`SchemaValidator`]), or the business
+//! rules of an ecosystem (a function). With the `checkCredentialTypes` verification option, each
+//! credential is validated by the validators of each of its types, and types that are not in the
+//! registry get an `unknownCredentialType` warning.
+//!
+//! Types are looked up as they appear in the credential's `type` property, as terms (e.g.
+//! `PermanentResidentCard`) or IRIs; they are not expanded with the credential's contexts. The
+//! registry used by verification starts with [`CredentialTypeRegistry::builtin`], and
+//! downstream crates add their types to it with [`register`] and [`register_type`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use serde_json::{json, Value};
+
+use crate::schema;
+use crate::verification::{ErrorCode, PolicyCheck, VerificationReport, WarningCode};
+
+/// Types known without validators: the base credential type, and types of credentials that
+/// DIDKit issues or verifies itself.
+const BASE_TYPES: &[&str] = &[
+    "VerifiableCredential",
+    "https://www.w3.org/2018/credentials#VerifiableCredential",
+    "VerifiableAttestation",
+    "StatusList2021Credential",
+    "BitstringStatusListCredential",
+];
+
+/// Validates credentials of a type.
+pub trait CredentialValidator: Send + Sync {
+    /// Validate a credential, as JSON, returning the problems found.
+    fn validate(&self, credential: &Value) -> Vec<String>;
+}
+
+impl<F> CredentialValidator for F
+where
+    F: Fn(&Value) -> Vec<String> + Send + Sync,
+{
+    fn validate(&self, credential: &Value) -> Vec<String> {
+        self(credential)
+    }
+}
+
+/// Validator checking credentials against a JSON Schema of credentials, with the subset of JSON
+/// Schema of the [`schema`] module. A schema of `credentialSubject` as an object applies to each
+/// subject.
+pub struct SchemaValidator(pub Value);
+
+impl CredentialValidator for SchemaValidator {
+    fn validate(&self, credential: &Value) -> Vec<String> {
+        schema::validate_credential(&self.0, credential)
+    }
+}
+
+/// Credential types, with their validators.
+#[derive(Clone, Default)]
+pub struct CredentialTypeRegistry {
+    types: HashMap<String, Vec<Arc<dyn CredentialValidator>>>,
+}
+
+impl std::fmt::Debug for CredentialTypeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut types: Vec<&String> = self.types.keys().collect();
+        types.sort();
+        f.debug_struct("CredentialTypeRegistry")
+            .field("types", &types)
+            .finish()
+    }
+}
+
+/// Sample validator of the `PermanentResidentCard` of the [Citizenship Vocabulary][citizenship].
+///
+/// [citizenship]: https://w3c-ccg.github.io/citizenship-vocab/
+fn permanent_resident_card() -> Vec<Arc<dyn CredentialValidator>> {
+    let schema = SchemaValidator(json!({
+        "type": "object",
+        "properties": {
+            "credentialSubject": {
+                "type": "object",
+                "required": ["type", "givenName", "familyName"],
+                "properties": {
+                    "type": {
+                        "type": "array",
+                        "contains": { "const": "PermanentResident" }
+                    },
+                    "givenName": { "type": "string" },
+                    "familyName": { "type": "string" },
+                    "lprNumber": { "type": "string" },
+                    "birthDate": { "type": "string" },
+                    "residentSince": { "type": "string" }
+                }
+            }
+        }
+    }));
+    let resident_since_birth = |credential: &Value| {
+        let mut errors = Vec::new();
+        for subject in schema::subjects(credential) {
+            let birth_date = subject.get("birthDate").and_then(Value::as_str);
+            let resident_since = subject.get("residentSince").and_then(Value::as_str);
+            if let (Some(birth_date), Some(resident_since)) = (birth_date, resident_since) {
+                // Dates of the same format compare as strings.
+                if resident_since < birth_date {
+                    errors.push("residentSince is before birthDate".to_string());
+                }
+            }
+        }
+        errors
+    };
+    vec![
+        Arc::new(schema) as Arc<dyn CredentialValidator>,
+        Arc::new(resident_since_birth),
+    ]
+}
+
+/// Sample validator of the `VerifiableDiploma` of the [EBSI][ebsi] diploma use case.
+///
+/// [ebsi]: https://hub.ebsi.eu/vc-framework
+fn verifiable_diploma() -> Vec<Arc<dyn CredentialValidator>> {
+    let schema = SchemaValidator(json!({
+        "type": "object",
+        "required": ["credentialSubject"],
+        "properties": {
+            "credentialSubject": {
+                "type": "object",
+                "required": ["id", "learningAchievement"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "learningAchievement": {
+                        "type": "object",
+                        "required": ["title"],
+                        "properties": { "title": { "type": "string" } }
+                    }
+                }
+            }
+        }
+    }));
+    vec![Arc::new(schema) as Arc<dyn CredentialValidator>]
+}
+
+impl CredentialTypeRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in types: the base types, and samples of [`PermanentResidentCard`] (also by its
+    /// IRI) and [`VerifiableDiploma`], whose validators check the properties of their subjects.
+    ///
+    /// [`PermanentResidentCard`]: https://w3c-ccg.github.io/citizenship-vocab/
+    /// [`VerifiableDiploma`]: https://hub.ebsi.eu/vc-framework
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        for type_ in BASE_TYPES {
+            registry.register_type(type_);
+        }
+        for type_ in &[
+            "PermanentResidentCard",
+            "https://w3id.org/citizenship#PermanentResidentCard",
+        ] {
+            for validator in permanent_resident_card() {
+                registry.register(type_, validator);
+            }
+        }
+        for validator in verifiable_diploma() {
+            registry.register("VerifiableDiploma", validator);
+        }
+        registry
+    }
+
+    /// Make a type known, without validators.
+    pub fn register_type(&mut self, type_: &str) {
+        self.types.entry(type_.to_string()).or_default();
+    }
+
+    /// Add a validator of credentials of a type, in addition to those registered earlier.
+    pub fn register(&mut self, type_: &str, validator: Arc<dyn CredentialValidator>) {
+        self.types
+            .entry(type_.to_string())
+            .or_default()
+            .push(validator);
+    }
+
+    pub fn is_known(&self, type_: &str) -> bool {
+        self.types.contains_key(type_)
+    }
+
+    /// Validate a credential with the validators of its types, reporting problems as
+    /// `credentialType` errors, and unknown types as `unknownCredentialType` warnings.
+    pub fn check(&self, credential: &Value, report: &mut VerificationReport) {
+        let types = match credential.get("type") {
+            Some(Value::String(type_)) => vec![type_.as_str()],
+            Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let mut valid = true;
+        for type_ in types {
+            let validators = match self.types.get(type_) {
+                Some(validators) => validators,
+                None => {
+                    let message = format!("Unknown credential type: {}", type_);
+                    report.push_warning(WarningCode::UnknownCredentialType, &message);
+                    continue;
+                }
+            };
+            for validator in validators {
+                let errors = validator.validate(credential);
+                if !errors.is_empty() {
+                    let message = format!("Invalid {}: {}", type_, errors.join("; "));
+                    report.push_error(ErrorCode::CredentialType, &message);
+                    valid = false;
+                }
+            }
+        }
+        if valid {
+            report.policy_checks.push(PolicyCheck::CredentialType);
+        }
+    }
+}
+
+lazy_static! {
+    static ref REGISTRY: RwLock<CredentialTypeRegistry> =
+        RwLock::new(CredentialTypeRegistry::builtin());
+}
+
+/// Add a validator of a type to the registry used by verification.
+pub fn register(type_: &str, validator: Arc<dyn CredentialValidator>) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.register(type_, validator);
+    }
+}
+
+/// Make a type known to the registry used by verification, without validators.
+pub fn register_type(type_: &str) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.register_type(type_);
+    }
+}
+
+/// The registry used by verification.
+pub fn registry() -> CredentialTypeRegistry {
+    match REGISTRY.read() {
+        Ok(registry) => registry.clone(),
+        Err(_) => CredentialTypeRegistry::builtin(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(subject: Value) -> Value {
+        json!({
+            "@context": [
+                "https://www.w3.org/2018/credentials/v1",
+                "https://w3id.org/citizenship/v1"
+            ],
+            "type": ["VerifiableCredential", "PermanentResidentCard"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2024-01-01T00:00:00Z",
+            "credentialSubject": subject
+        })
+    }
+
+    #[test]
+    fn check_credential_types() {
+        let registry = CredentialTypeRegistry::builtin();
+        let credential = card(json!({
+            "type": ["PermanentResident", "Person"],
+            "givenName": "Jane",
+            "familyName": "Doe",
+            "birthDate": "1980-01-01",
+            "residentSince": "2015-01-01"
+        }));
+        let mut report = VerificationReport::new();
+        registry.check(&credential, &mut report);
+        assert!(report.is_success(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.policy_checks, vec![PolicyCheck::CredentialType]);
+
+        let credential = card(json!({
+            "type": ["PermanentResident"],
+            "givenName": "Jane",
+            "birthDate": "1980-01-01",
+            "residentSince": "1970-01-01"
+        }));
+        let mut report = VerificationReport::new();
+        registry.check(&credential, &mut report);
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.has_error(ErrorCode::CredentialType));
+        assert!(report.policy_checks.is_empty());
+
+        let mut credential = credential;
+        credential["type"] = json!(["VerifiableCredential", "ExampleCredential"]);
+        let mut report = VerificationReport::new();
+        registry.check(&credential, &mut report);
+        assert!(report.is_success());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].code, WarningCode::UnknownCredentialType);
+
+        let mut registry = registry;
+        let no_subject_id = |credential: &Value| match credential.pointer("/credentialSubject/id") {
+            Some(_) => Vec::new(),
+            None => vec!["Missing subject ID".to_string()],
+        };
+        registry.register("ExampleCredential", Arc::new(no_subject_id));
+        let mut report = VerificationReport::new();
+        registry.check(&credential, &mut report);
+        assert!(report.warnings.is_empty());
+        assert_eq!(
+            report.errors[0].message,
+            "Invalid ExampleCredential: Missing subject ID"
+        );
+    }
+}
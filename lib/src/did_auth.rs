@@ -0,0 +1,258 @@
+//! [DID Authentication][did-auth]: login by proving control of a DID.
+//!
+//! A relying party sends a DIDAuth request with a fresh challenge ([`request`]). The holder
+//! answers with a presentation without credentials, signed for the `authentication` proof
+//! purpose, e.g. with `didkit did-auth`. The relying party checks it with [`verify`], which
+//! returns the holder's DID, and can then issue a short-lived session assertion, a JWT signed with
+//! its own key ([`issue_session`]), that its other services check with [`verify_session`] instead
+//! of repeating DIDAuth.
+//!
+//! [did-auth]: https://w3c-ccg.github.io/vp-request-spec/#did-authentication
+
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::error::Error;
+use crate::key_cache;
+use crate::signer::{self, Signer, SignerError};
+use crate::verification::embedded_credentials;
+use crate::{
+    verify_presentation, DIDResolver, JWTOrLDPOptions, PresentationOrJWT, ProofPurpose, JWK,
+};
+
+/// Query type of a DIDAuth request.
+pub const QUERY_DID_AUTHENTICATION: &str = "DIDAuthentication";
+
+#[derive(Error, Debug)]
+pub enum DIDAuthError {
+    #[error("Presentation has no holder")]
+    MissingHolder,
+    #[error("DIDAuth presentation must not contain credentials")]
+    UnexpectedCredentials,
+    #[error("Presentation verification failed: {0}")]
+    Verification(String),
+    #[error(transparent)]
+    DIDKit(#[from] Error),
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Invalid session token: {0}")]
+    InvalidSession(String),
+    #[error("Session expired")]
+    SessionExpired,
+    #[error("Session audience mismatch")]
+    AudienceMismatch,
+    #[error("Session issued by {0}, not by the relying party")]
+    IssuerMismatch(String),
+}
+
+/// Create a DIDAuth request, as in the Verifiable Presentation Request Specification.
+pub fn request(challenge: &str, domain: Option<&str>) -> Value {
+    let mut request = json!({
+        "query": [{ "type": QUERY_DID_AUTHENTICATION }],
+        "challenge": challenge,
+    });
+    if let Some(domain) = domain {
+        request["domain"] = Value::String(domain.to_string());
+    }
+    request
+}
+
+/// Verify a DIDAuth presentation: its proof must be for authentication with the given challenge
+/// (and domain, if given), and it must not contain credentials. Returns the holder's DID.
+///
+/// The caller is responsible for checking that the challenge was issued by it and is not reused.
+pub async fn verify(
+    presentation: &PresentationOrJWT,
+    challenge: &str,
+    domain: Option<&str>,
+    resolver: &dyn DIDResolver,
+) -> Result<String, DIDAuthError> {
+    if !embedded_credentials(presentation).is_empty() {
+        return Err(DIDAuthError::UnexpectedCredentials);
+    }
+    let mut options = JWTOrLDPOptions::default_for_vp();
+    options.ldp_options.proof_purpose = Some(ProofPurpose::Authentication);
+    options.ldp_options.challenge = Some(challenge.to_string());
+    options.ldp_options.domain = domain.map(|domain| domain.to_string());
+    let report = verify_presentation(presentation, &options, resolver).await?;
+    if !report.is_success() {
+        let errors: Vec<String> = report.errors.into_iter().map(|e| e.message).collect();
+        return Err(DIDAuthError::Verification(errors.join("; ")));
+    }
+    report.holder.ok_or(DIDAuthError::MissingHolder)
+}
+
+/// Claims of a session assertion.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SessionClaims {
+    /// DID of the relying party
+    pub iss: String,
+    /// DID of the authenticated holder
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+}
+
+fn b64(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Issue a session assertion for an authenticated holder, valid for `ttl`. The JWT is signed by
+/// `signer` with `kid` set to `verification_method`, a DID URL of the relying party, whose DID is
+/// the `iss` claim.
+pub async fn issue_session(
+    holder: &str,
+    verification_method: &str,
+    audience: Option<&str>,
+    ttl: Duration,
+    signer: &dyn Signer,
+) -> Result<String, DIDAuthError> {
+    let algorithm = signer.algorithm().ok_or(SignerError::MissingAlgorithm)?;
+    let issuer = verification_method.split('#').next().unwrap_or_default();
+    let now = Utc::now();
+    let mut jti = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut jti);
+    let claims = SessionClaims {
+        iss: issuer.to_string(),
+        sub: holder.to_string(),
+        aud: audience.map(|audience| audience.to_string()),
+        iat: now.timestamp(),
+        exp: (now + ttl).timestamp(),
+        jti: b64(&jti),
+    };
+    let header = json!({
+        "alg": algorithm,
+        "typ": "JWT",
+        "kid": verification_method,
+    });
+    let signing_input = [
+        b64(&serde_json::to_vec(&header)?),
+        b64(&serde_json::to_vec(&claims)?),
+    ]
+    .join(".");
+    let sig = signer::sign(signer, signing_input.as_bytes(), algorithm).await?;
+    Ok([signing_input, b64(&sig)].join("."))
+}
+
+/// Verify a session assertion: that it was issued by the relying party `issuer` (a DID), its
+/// signature by a verification method of the issuer, its expiration, and that its audience is
+/// `audience` (without an audience, that it has none).
+pub async fn verify_session(
+    token: &str,
+    issuer: &str,
+    audience: Option<&str>,
+    resolver: &dyn DIDResolver,
+) -> Result<SessionClaims, DIDAuthError> {
+    let invalid = |e: ssi::error::Error| DIDAuthError::InvalidSession(e.to_string());
+    let (header, payload) = ssi::jws::decode_unverified(token).map_err(invalid)?;
+    let claims: SessionClaims = serde_json::from_slice(&payload)?;
+    if claims.iss != issuer {
+        return Err(DIDAuthError::IssuerMismatch(claims.iss));
+    }
+    let key_id = header
+        .key_id
+        .ok_or_else(|| DIDAuthError::InvalidSession("missing kid".to_string()))?;
+    if key_id.split('#').next() != Some(claims.iss.as_str()) {
+        return Err(DIDAuthError::InvalidSession(
+            "kid is not a verification method of the issuer".to_string(),
+        ));
+    }
+    let key: JWK = key_cache::resolve_key(&key_id, resolver)
+        .await
+        .map_err(invalid)?;
+    ssi::jws::decode_verify(token, &key).map_err(invalid)?;
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(DIDAuthError::SessionExpired);
+    }
+    if claims.aud.as_deref() != audience {
+        return Err(DIDAuthError::AudienceMismatch);
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_verification_method, DIDMethod, Source, DID_METHODS};
+
+    #[test]
+    fn session_roundtrip() {
+        let rt = crate::runtime::get().unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let verification_method = rt
+            .block_on(get_verification_method(&did, resolver))
+            .unwrap();
+        let token = rt
+            .block_on(issue_session(
+                "did:example:holder",
+                &verification_method,
+                Some("example.org"),
+                Duration::minutes(5),
+                &key,
+            ))
+            .unwrap();
+        let claims = rt
+            .block_on(verify_session(&token, &did, Some("example.org"), resolver))
+            .unwrap();
+        assert_eq!(claims.iss, did);
+        assert_eq!(claims.sub, "did:example:holder");
+        let err = rt
+            .block_on(verify_session(
+                &token,
+                &did,
+                Some("other.example"),
+                resolver,
+            ))
+            .unwrap_err();
+        assert!(matches!(err, DIDAuthError::AudienceMismatch));
+        let err = rt
+            .block_on(verify_session(&token, &did, None, resolver))
+            .unwrap_err();
+        assert!(matches!(err, DIDAuthError::AudienceMismatch));
+    }
+
+    #[test]
+    fn session_of_foreign_issuer_rejected() {
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let relying_party_key = JWK::generate_ed25519().unwrap();
+        let relying_party = did_method_key::DIDKey
+            .generate(&Source::Key(&relying_party_key))
+            .unwrap();
+        // A session for the holder, validly signed by another DID for the same audience
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let verification_method = rt
+            .block_on(get_verification_method(&did, resolver))
+            .unwrap();
+        let token = rt
+            .block_on(issue_session(
+                "did:example:victim",
+                &verification_method,
+                Some("example.org"),
+                Duration::minutes(5),
+                &key,
+            ))
+            .unwrap();
+        let err = rt
+            .block_on(verify_session(
+                &token,
+                &relying_party,
+                Some("example.org"),
+                resolver,
+            ))
+            .unwrap_err();
+        assert!(matches!(err, DIDAuthError::IssuerMismatch(iss) if iss == did));
+    }
+}
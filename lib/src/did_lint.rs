@@ -0,0 +1,448 @@
+//! Validation of DID documents against [DID Core][did-core].
+//!
+//! [`lint`] checks a DID document as JSON, so that documents that do not deserialize as a
+//! [`Document`](crate::Document) can still be diagnosed. Errors are violations of DID Core:
+//! missing required properties, malformed DIDs and DID URLs, invalid public keys, verification
+//! relationships referring to verification methods that are not in the document, and duplicate
+//! ids. Warnings are valid constructs that commonly cause interoperability problems, such as
+//! relative ids, legacy key formats, or verification methods controlled by another DID.
+//!
+//! [did-core]: https://www.w3.org/TR/did-core/
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::multikey;
+use crate::JWK;
+
+/// JSON-LD context of DID documents.
+pub const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+
+/// Verification relationships of DID Core.
+pub const VERIFICATION_RELATIONSHIPS: &[&str] = &[
+    "authentication",
+    "assertionMethod",
+    "keyAgreement",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+/// Properties of a verification method holding its public key.
+const KEY_PROPERTIES: &[&str] = &[
+    "publicKeyJwk",
+    "publicKeyMultibase",
+    "publicKeyBase58",
+    "publicKeyHex",
+    "blockchainAccountId",
+];
+
+/// A problem found in a DID document.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LintIssue {
+    /// JSON pointer of the property concerned
+    pub path: String,
+    pub message: String,
+}
+
+/// Result of linting a DID document.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LintReport {
+    pub errors: Vec<LintIssue>,
+    pub warnings: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// The document has no errors. It may have warnings.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(LintIssue {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, path: &str, message: impl Into<String>) {
+        self.warnings.push(LintIssue {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Whether a string is a DID: `did:<method>:<method-specific-id>`.
+fn is_did(s: &str) -> bool {
+    let mut parts = s.splitn(3, ':');
+    let method_ok = |method: &str| {
+        !method.is_empty()
+            && method
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    };
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("did"), Some(method), Some(id)) => {
+            method_ok(method) && !id.is_empty() && !id.contains(|c: char| "/?#".contains(c))
+        }
+        _ => false,
+    }
+}
+
+/// Whether a string is a DID URL.
+fn is_did_url(s: &str) -> bool {
+    let end = s.find(|c: char| "/?#".contains(c)).unwrap_or(s.len());
+    is_did(&s[..end])
+}
+
+/// Resolve a relative DID URL, e.g. `#key-1`, against the document's DID.
+fn absolute(id: &str, did: &str) -> String {
+    match id.starts_with('#') || id.starts_with('?') || id.starts_with('/') {
+        true => format!("{}{}", did, id),
+        false => id.to_string(),
+    }
+}
+
+struct Linter<'a> {
+    did: &'a str,
+    report: LintReport,
+    ids: HashSet<String>,
+    methods: HashSet<String>,
+}
+
+impl<'a> Linter<'a> {
+    fn id(&mut self, path: &str, id: &str) {
+        if id.starts_with('#') {
+            self.report.warning(
+                path,
+                format!(
+                    "Relative id '{}'; some verifiers expect absolute DID URLs",
+                    id
+                ),
+            );
+        } else if !is_did_url(id) && !id.contains(':') {
+            self.report
+                .error(path, format!("Id '{}' is not a DID URL or URI", id));
+        }
+        if !self.ids.insert(absolute(id, self.did)) {
+            self.report.error(path, format!("Duplicate id '{}'", id));
+        }
+    }
+
+    fn verification_method(&mut self, path: &str, vm: &Map<String, Value>) {
+        let id = match vm.get("id") {
+            Some(Value::String(id)) => id.as_str(),
+            _ => {
+                self.report
+                    .error(path, "Verification method has no string 'id'");
+                ""
+            }
+        };
+        if !id.is_empty() {
+            self.id(&format!("{}/id", path), id);
+            let id = absolute(id, self.did);
+            if is_did_url(&id) && !id.starts_with(&format!("{}#", self.did)) {
+                self.report.warning(
+                    &format!("{}/id", path),
+                    "Verification method id is not a DID URL of the document's DID",
+                );
+            }
+            self.methods.insert(id);
+        }
+        match vm.get("type") {
+            Some(Value::String(_)) => {}
+            _ => self
+                .report
+                .error(path, "Verification method has no string 'type'"),
+        }
+        match vm.get("controller") {
+            Some(Value::String(controller)) if is_did(controller) => {
+                if controller != self.did {
+                    self.report.warning(
+                        &format!("{}/controller", path),
+                        format!(
+                            "Verification method is controlled by another DID: {}",
+                            controller
+                        ),
+                    );
+                }
+            }
+            Some(_) => self
+                .report
+                .error(&format!("{}/controller", path), "Controller must be a DID"),
+            None => self
+                .report
+                .error(path, "Verification method has no 'controller'"),
+        }
+        let keys: Vec<&str> = KEY_PROPERTIES
+            .iter()
+            .copied()
+            .filter(|name| vm.contains_key(*name))
+            .collect();
+        match keys.len() {
+            0 => self
+                .report
+                .warning(path, "Verification method has no public key property"),
+            1 => {}
+            _ => self.report.error(
+                path,
+                format!(
+                    "Verification method has several public keys: {}",
+                    keys.join(", ")
+                ),
+            ),
+        }
+        if let Some(jwk) = vm.get("publicKeyJwk") {
+            self.jwk(&format!("{}/publicKeyJwk", path), jwk);
+        }
+        if let Some(multibase) = vm.get("publicKeyMultibase") {
+            let path = format!("{}/publicKeyMultibase", path);
+            match multibase.as_str() {
+                Some(multibase) if !multibase.starts_with('z') => self.report.warning(
+                    &path,
+                    "publicKeyMultibase is not base58btc ('z'), as some verifiers require",
+                ),
+                Some(multibase) => {
+                    if let Err(err) = multikey::from_multibase(multibase) {
+                        self.report
+                            .warning(&path, format!("Unsupported or invalid Multikey: {}", err));
+                    }
+                }
+                None => self
+                    .report
+                    .error(&path, "publicKeyMultibase must be a string"),
+            }
+        }
+        for name in &["publicKeyBase58", "publicKeyHex"] {
+            if vm.contains_key(*name) {
+                self.report.warning(
+                    &format!("{}/{}", path, name),
+                    format!(
+                        "{} is a legacy key format; prefer publicKeyJwk or publicKeyMultibase",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+
+    fn jwk(&mut self, path: &str, jwk: &Value) {
+        if jwk.get("d").is_some() {
+            self.report
+                .error(path, "publicKeyJwk contains a private key ('d')");
+        }
+        if let Err(err) = serde_json::from_value::<JWK>(jwk.clone()) {
+            self.report
+                .error(path, format!("publicKeyJwk is not a valid JWK: {}", err));
+        }
+    }
+
+    fn relationship(&mut self, name: &str, value: &Value) -> Vec<(String, String)> {
+        let path = format!("/{}", name);
+        let entries = match value {
+            Value::Array(entries) => entries,
+            _ => {
+                self.report
+                    .error(&path, format!("'{}' must be an array", name));
+                return Vec::new();
+            }
+        };
+        let mut references = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let path = format!("{}/{}", path, i);
+            match entry {
+                Value::String(reference) => references.push((path, reference.to_string())),
+                Value::Object(vm) => self.verification_method(&path, vm),
+                _ => self.report.error(
+                    &path,
+                    "Expected a verification method or a reference to one",
+                ),
+            }
+        }
+        references
+    }
+
+    fn service(&mut self, path: &str, service: &Value) {
+        let service = match service.as_object() {
+            Some(service) => service,
+            None => return self.report.error(path, "Service must be an object"),
+        };
+        match service.get("id").and_then(Value::as_str) {
+            Some(id) => self.id(&format!("{}/id", path), id),
+            None => self.report.error(path, "Service has no string 'id'"),
+        }
+        if service.get("type").is_none() {
+            self.report.error(path, "Service has no 'type'");
+        }
+        match service.get("serviceEndpoint") {
+            Some(Value::String(_)) | Some(Value::Object(_)) | Some(Value::Array(_)) => {}
+            Some(_) => self.report.error(
+                &format!("{}/serviceEndpoint", path),
+                "serviceEndpoint must be a string, map or set",
+            ),
+            None => self.report.error(path, "Service has no 'serviceEndpoint'"),
+        }
+    }
+}
+
+/// Lint a DID document.
+pub fn lint(document: &Value) -> LintReport {
+    let mut report = LintReport::default();
+    let document = match document.as_object() {
+        Some(document) => document,
+        None => {
+            report.error("", "DID document must be a JSON object");
+            return report;
+        }
+    };
+    let did = match document.get("id") {
+        Some(Value::String(id)) if is_did(id) => id.as_str(),
+        Some(Value::String(id)) => {
+            report.error("/id", format!("'{}' is not a DID", id));
+            id.as_str()
+        }
+        _ => {
+            report.error("", "DID document has no string 'id'");
+            ""
+        }
+    };
+    match document.get("@context") {
+        None => report.warning(
+            "",
+            "No '@context': the document is plain JSON, not JSON-LD; some verifiers require it",
+        ),
+        Some(Value::String(context)) if context == DID_CONTEXT => {}
+        Some(Value::Array(contexts)) if contexts.first() == Some(&json_str(DID_CONTEXT)) => {}
+        Some(_) => report.error(
+            "/@context",
+            format!("The first context must be {}", DID_CONTEXT),
+        ),
+    }
+    let mut linter = Linter {
+        did,
+        report,
+        ids: HashSet::new(),
+        methods: HashSet::new(),
+    };
+    if let Some(controller) = document.get("controller") {
+        let controllers = match controller {
+            Value::Array(controllers) => controllers.iter().collect(),
+            controller => vec![controller],
+        };
+        for controller in controllers {
+            if !controller.as_str().map_or(false, is_did) {
+                linter
+                    .report
+                    .error("/controller", "Controller must be a DID or set of DIDs");
+            }
+        }
+    }
+    if let Some(also_known_as) = document.get("alsoKnownAs") {
+        if !also_known_as.is_array() {
+            linter
+                .report
+                .error("/alsoKnownAs", "alsoKnownAs must be an array of URIs");
+        }
+    }
+    match document.get("verificationMethod") {
+        Some(Value::Array(vms)) => {
+            for (i, vm) in vms.iter().enumerate() {
+                let path = format!("/verificationMethod/{}", i);
+                match vm.as_object() {
+                    Some(vm) => linter.verification_method(&path, vm),
+                    None => linter
+                        .report
+                        .error(&path, "Verification method must be an object"),
+                }
+            }
+        }
+        Some(_) => linter
+            .report
+            .error("/verificationMethod", "verificationMethod must be an array"),
+        None => {}
+    }
+    let mut references = Vec::new();
+    for name in VERIFICATION_RELATIONSHIPS {
+        if let Some(value) = document.get(*name) {
+            references.extend(linter.relationship(name, value));
+        }
+    }
+    for (path, reference) in references {
+        if !linter.methods.contains(&absolute(&reference, did)) {
+            let external = is_did_url(&reference) && !reference.starts_with(did);
+            match external {
+                true => linter.report.warning(
+                    &path,
+                    format!(
+                        "Reference to a verification method of another DID: {}",
+                        reference
+                    ),
+                ),
+                false => linter.report.error(
+                    &path,
+                    format!(
+                        "Reference to a verification method not in the document: {}",
+                        reference
+                    ),
+                ),
+            }
+        }
+    }
+    match document.get("service") {
+        Some(Value::Array(services)) => {
+            for (i, service) in services.iter().enumerate() {
+                linter.service(&format!("/service/{}", i), service);
+            }
+        }
+        Some(_) => linter.report.error("/service", "service must be an array"),
+        None => {}
+    }
+    linter.report
+}
+
+fn json_str(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lint_document() {
+        let did = "did:web:example.org";
+        let document = json!({
+            "@context": [DID_CONTEXT],
+            "id": did,
+            "verificationMethod": [{
+                "id": "did:web:example.org#key-1",
+                "type": "JsonWebKey2020",
+                "controller": did,
+                "publicKeyJwk": {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": "G80iskrv_nE69qbGLSpeOHJgmV4MKIzsy5l5iT6pCww"
+                }
+            }],
+            "assertionMethod": ["#key-1"],
+            "authentication": ["did:web:example.org#key-2"],
+            "service": [{ "id": "#key-1", "type": "LinkedDomains" }]
+        });
+        let report = lint(&document);
+        assert!(!report.is_valid());
+        let paths: Vec<&str> = report.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/authentication/0", "/service/0/id", "/service/0"]
+        );
+        // The relative service id is also a duplicate of the verification method's id.
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].path, "/service/0/id");
+
+        let report = lint(&json!({ "id": "did:Example:1" }));
+        assert_eq!(report.errors[0].path, "/id");
+    }
+}
@@ -1,26 +1,121 @@
+//! DID methods of [`DID_METHODS`], each included with its feature, e.g. `did-key` or `did-web`.
+
+#[cfg(feature = "did-ethr")]
 use did_ethr::DIDEthr;
+#[cfg(feature = "did-key")]
 use did_method_key::DIDKey;
+#[cfg(all(feature = "did-onion", not(target_os = "wasi")))]
 use did_onion::DIDOnion;
-use did_pkh::DIDPKH;
+#[cfg(feature = "did-sol")]
 use did_sol::DIDSol;
+#[cfg(all(feature = "did-tz", not(target_os = "wasi")))]
 use did_tz::DIDTz;
+#[cfg(all(feature = "did-web", not(target_os = "wasi")))]
 use did_web::DIDWeb;
+#[cfg(all(feature = "did-webkey", not(target_os = "wasi")))]
 use did_webkey::DIDWebKey;
 use ssi::did::DIDMethods;
 
+#[cfg(feature = "did-peer")]
+use crate::did_peer::DIDPeer;
+#[cfg(all(feature = "did-webvh", not(target_os = "wasi")))]
+use crate::did_webvh::DIDWebVH;
+#[cfg(any(
+    feature = "did-web",
+    all(feature = "did-webvh", not(target_os = "wasi"))
+))]
+use crate::multikey::MultikeyMethod;
+#[cfg(feature = "did-pkh")]
+use crate::pkh_chains::DIDPKHChains;
+#[cfg(all(feature = "did-web", target_os = "wasi"))]
+use crate::wasi::DIDWebHost;
+
+#[cfg(all(feature = "did-tz", not(target_os = "wasi")))]
 lazy_static! {
     static ref DIDTZ: DIDTz = DIDTz::default();
+}
+
+#[cfg(all(feature = "did-onion", not(target_os = "wasi")))]
+lazy_static! {
     static ref DIDONION: DIDOnion = DIDOnion::default();
+}
+
+#[cfg(all(feature = "did-web", not(target_os = "wasi")))]
+lazy_static! {
+    static ref DIDWEB: MultikeyMethod<DIDWeb> = MultikeyMethod(DIDWeb);
+}
+
+#[cfg(all(feature = "did-webvh", not(target_os = "wasi")))]
+lazy_static! {
+    static ref DIDWEBVH: MultikeyMethod<DIDWebVH> = MultikeyMethod(DIDWebVH);
+}
+
+// On WASI, did:web resolves with the host's HTTP function, and methods making other HTTP
+// requests are not available.
+#[cfg(all(feature = "did-web", target_os = "wasi"))]
+lazy_static! {
+    static ref DIDWEB: MultikeyMethod<DIDWebHost> = MultikeyMethod(DIDWebHost);
+}
+
+lazy_static! {
     pub static ref DID_METHODS: DIDMethods<'static> = {
+        #[allow(unused_mut)]
         let mut methods = DIDMethods::default();
+        #[cfg(feature = "did-key")]
         methods.insert(&DIDKey);
+        #[cfg(feature = "did-peer")]
+        methods.insert(&DIDPeer);
+        #[cfg(all(feature = "did-tz", not(target_os = "wasi")))]
         methods.insert(&*DIDTZ);
+        #[cfg(feature = "did-ethr")]
         methods.insert(&DIDEthr);
+        #[cfg(feature = "did-sol")]
         methods.insert(&DIDSol);
-        methods.insert(&DIDWeb);
+        #[cfg(feature = "did-web")]
+        methods.insert(&*DIDWEB);
+        #[cfg(all(feature = "did-webvh", not(target_os = "wasi")))]
+        methods.insert(&*DIDWEBVH);
+        #[cfg(all(feature = "did-webkey", not(target_os = "wasi")))]
         methods.insert(&DIDWebKey);
-        methods.insert(&DIDPKH);
+        #[cfg(feature = "did-pkh")]
+        methods.insert(&DIDPKHChains);
+        #[cfg(all(feature = "did-onion", not(target_os = "wasi")))]
         methods.insert(&*DIDONION);
         methods
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ssi::did_resolve::{DIDResolver, ResolutionInputMetadata, ERROR_METHOD_NOT_SUPPORTED};
+
+    #[test]
+    fn methods_of_features() {
+        crate::runtime::get()
+            .unwrap()
+            .block_on(methods_of_features_async());
+    }
+
+    async fn methods_of_features_async() {
+        let resolver = DID_METHODS.to_resolver();
+        let input = ResolutionInputMetadata::default();
+        let did = "did:key:z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK";
+        let (res_meta, document, _) = resolver.resolve(did, &input).await;
+        if cfg!(feature = "did-key") {
+            assert_eq!(res_meta.error, None);
+            assert!(document.is_some());
+        } else {
+            assert_eq!(res_meta.error.as_deref(), Some(ERROR_METHOD_NOT_SUPPORTED));
+        }
+        // Methods of disabled features are not supported, without any request being made.
+        #[cfg(not(feature = "did-web"))]
+        {
+            let (res_meta, _, _) = resolver.resolve("did:web:example.org", &input).await;
+            assert_eq!(res_meta.error.as_deref(), Some(ERROR_METHOD_NOT_SUPPORTED));
+        }
+        let (res_meta, _, _) = resolver.resolve("did:example:a", &input).await;
+        assert_eq!(res_meta.error.as_deref(), Some(ERROR_METHOD_NOT_SUPPORTED));
+    }
+}
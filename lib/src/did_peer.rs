@@ -0,0 +1,122 @@
+//! [did:peer][did-peer] DIDs of numalgo 0: a single inception key.
+//!
+//! A `did:peer:0` DID is `did:peer:0` followed by the `publicKeyMultibase` of its key, the same
+//! method-specific ID as the `did:key` DID of the key, and its DID document is that of the
+//! `did:key` DID, with the DID replaced. Other numalgos are not supported.
+//!
+//! [did-peer]: https://identity.foundation/peer-did-method-spec/
+
+use async_trait::async_trait;
+use did_method_key::DIDKey;
+use ssi::did::{DIDMethod, Document, Source};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata, ERROR_INVALID_DID,
+    ERROR_METHOD_NOT_SUPPORTED,
+};
+
+use crate::multikey::to_multibase;
+
+const DID_PEER_0: &str = "did:peer:0";
+
+pub struct DIDPeer;
+
+fn error_output(
+    error: &str,
+) -> (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+) {
+    (
+        ResolutionMetadata {
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+}
+
+/// Replace the `did:key` DID in the DID document of a `did:key` DID.
+fn peer_document(document: &Document, key_did: &str, did: &str) -> Option<Document> {
+    let json = serde_json::to_string(document).ok()?;
+    serde_json::from_str(&json.replace(key_did, did)).ok()
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for DIDPeer {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let multibase = match did.strip_prefix(DID_PEER_0) {
+            Some(multibase) if multibase.starts_with('z') => multibase,
+            Some(_) => return error_output(ERROR_INVALID_DID),
+            None if did.starts_with("did:peer:") => {
+                return error_output(ERROR_METHOD_NOT_SUPPORTED)
+            }
+            None => return error_output(ERROR_INVALID_DID),
+        };
+        let key_did = format!("did:key:{}", multibase);
+        let (res_meta, doc, doc_meta) = DIDKey.resolve(&key_did, input_metadata).await;
+        match doc {
+            Some(doc) => match peer_document(&doc, &key_did, did) {
+                Some(doc) => (res_meta, Some(doc), doc_meta),
+                None => error_output(ERROR_INVALID_DID),
+            },
+            None => (res_meta, None, doc_meta),
+        }
+    }
+}
+
+impl DIDMethod for DIDPeer {
+    fn name(&self) -> &'static str {
+        "peer"
+    }
+
+    fn generate(&self, source: &Source) -> Option<String> {
+        match source {
+            Source::Key(jwk) | Source::KeyAndPattern(jwk, "0") => {
+                Some(format!("{}{}", DID_PEER_0, to_multibase(jwk).ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssi::jwk::JWK;
+
+    #[test]
+    fn generate_and_resolve() {
+        let key = JWK::generate_ed25519().unwrap();
+        let did = DIDPeer.generate(&Source::Key(&key)).unwrap();
+        let key_did = DIDKey.generate(&Source::Key(&key)).unwrap();
+        assert_eq!(&did["did:peer:0".len()..], &key_did["did:key:".len()..]);
+
+        let rt = crate::runtime::get().unwrap();
+        let input_metadata = ResolutionInputMetadata::default();
+        let (res_meta, doc, _) = rt.block_on(DIDPeer.resolve(&did, &input_metadata));
+        assert_eq!(res_meta.error, None);
+        let doc = serde_json::to_value(doc.unwrap()).unwrap();
+        assert_eq!(doc["id"], did);
+        assert!(doc["verificationMethod"][0]["id"]
+            .as_str()
+            .unwrap()
+            .starts_with(&did));
+        let (res_meta, _, _) = rt.block_on(DIDPeer.resolve("did:peer:2.Ez6LS", &input_metadata));
+        assert_eq!(res_meta.error.as_deref(), Some(ERROR_METHOD_NOT_SUPPORTED));
+    }
+}
@@ -0,0 +1,791 @@
+//! [did:webvh][did-webvh] DIDs: `did:web` with a verifiable history (formerly `did:tdw`).
+//!
+//! A `did:webvh` DID is `did:webvh:{SCID}:{domain}[:{path}]`. Its DID log, `did.jsonl`, is
+//! published where the `did.json` document of the corresponding `did:web` DID would be, with one
+//! JSON entry per line. Each entry has a `versionId` (`{number}-{hash}`), a `versionTime`, the
+//! DID's `parameters` that change, the DID document of the version (`state`) and an
+//! `eddsa-jcs-2022` proof by one of the DID's update keys.
+//!
+//! Resolution verifies the whole log: the SCID (self-certifying identifier) is the hash of the
+//! first entry, each entry's hash chains it to the previous one, each proof is by an update key
+//! authorized by the previous entry, and, with pre-rotation, each new update key was committed
+//! to by its hash, in the `nextKeyHashes` of the previous entry. [`create`] and [`update`]
+//! produce log entries, signed with an Ed25519 update key.
+//!
+//! Version 0.5 of the specification is implemented, without witnesses: logs that require
+//! witness approval are rejected.
+//!
+//! [did-webvh]: https://identity.foundation/didwebvh/
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use ssi::did::{DIDMethod, Document};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, Metadata, ResolutionInputMetadata, ResolutionMetadata,
+    ERROR_INVALID_DID, ERROR_NOT_FOUND, TYPE_DID_LD_JSON,
+};
+use ssi::jwk::{Algorithm, JWK};
+use thiserror::Error;
+
+use crate::canonical_json::jcs;
+use crate::multikey::{from_multibase, to_multibase, MultikeyError};
+
+/// `method` parameter of the log entries produced, and the only one accepted.
+pub const METHOD_VERSION: &str = "did:webvh:0.5";
+
+const DID_WEBVH: &str = "did:webvh:";
+const SCID_PLACEHOLDER: &str = "{SCID}";
+const DID_PLACEHOLDER: &str = "{DID}";
+const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+const PROOF_PURPOSE: &str = "assertionMethod";
+
+#[derive(Error, Debug)]
+pub enum WebVHError {
+    #[error("Invalid did:webvh DID: {0}")]
+    InvalidDID(String),
+    #[error("Empty DID log")]
+    EmptyLog,
+    #[error("Invalid DID log entry {0}: {1}")]
+    InvalidEntry(usize, String),
+    #[error("Witnessed DID logs are not supported")]
+    UnsupportedWitness,
+    #[error("No version of the DID matches the requested version")]
+    VersionNotFound,
+    #[error("The DID is deactivated")]
+    Deactivated,
+    #[error("Key is not authorized to update the DID")]
+    UnauthorizedKey,
+    #[error("Unable to fetch {0}: {1}")]
+    Fetch(String, String),
+    #[error("Key: {0}")]
+    Multikey(#[from] MultikeyError),
+    #[error("Signing: {0}")]
+    Sign(#[from] ssi::error::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// Parameters of a DID, as given in log entries. Each entry gives only the parameters that
+/// change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scid: Option<String>,
+    /// Multikeys (`publicKeyMultibase`) authorized to sign the next entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_keys: Option<Vec<String>>,
+    /// [Hashes](key_hash) of the update keys of the next entry, for pre-rotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_key_hashes: Option<Vec<String>>,
+    /// Whether the DID may move to another location, keeping its SCID. Only set in the first
+    /// entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub portable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub witness: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+    /// Seconds for which resolvers may cache the DID document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+impl Parameters {
+    fn apply(&mut self, changes: &Parameters) {
+        fn set<T: Clone>(value: &mut Option<T>, change: &Option<T>) {
+            if change.is_some() {
+                *value = change.clone();
+            }
+        }
+        set(&mut self.method, &changes.method);
+        set(&mut self.scid, &changes.scid);
+        set(&mut self.update_keys, &changes.update_keys);
+        set(&mut self.next_key_hashes, &changes.next_key_hashes);
+        set(&mut self.portable, &changes.portable);
+        set(&mut self.witness, &changes.witness);
+        set(&mut self.deactivated, &changes.deactivated);
+        set(&mut self.ttl, &changes.ttl);
+    }
+
+    /// Whether the next entry must rotate to update keys committed to in `nextKeyHashes`.
+    fn prerotation(&self) -> bool {
+        matches!(&self.next_key_hashes, Some(hashes) if !hashes.is_empty())
+    }
+
+    fn witnessed(&self) -> bool {
+        match &self.witness {
+            None | Some(Value::Null) => false,
+            Some(Value::Object(witness)) => !witness.is_empty(),
+            Some(_) => true,
+        }
+    }
+}
+
+/// A verified version of a DID.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub version_id: String,
+    pub version_time: DateTime<Utc>,
+    /// DID document of the version
+    pub document: Value,
+    /// Parameters in effect after the version's entry
+    pub parameters: Parameters,
+}
+
+/// SCID and location (domain and path) of a DID.
+fn parse_did(did: &str) -> Option<(&str, &str)> {
+    let (scid, address) = did.strip_prefix(DID_WEBVH)?.split_once(':')?;
+    if scid.is_empty() || address.is_empty() || address.starts_with(':') {
+        return None;
+    }
+    Some((scid, address))
+}
+
+/// Base URL of the files of a DID, e.g. `https://example.com/dids/issuer/`.
+fn base_url(did: &str) -> Option<String> {
+    let (_, address) = parse_did(did)?;
+    let mut parts = address.split(':');
+    let host = parts.next()?.replace("%3A", ":");
+    let path: Vec<&str> = parts.collect();
+    Some(if path.is_empty() {
+        format!("https://{}/", host)
+    } else {
+        format!("https://{}/{}/", host, path.join("/"))
+    })
+}
+
+/// URL of the DID log of a DID: `https://{domain}/.well-known/did.jsonl`, or
+/// `https://{domain}/{path}/did.jsonl`.
+pub fn log_url(did: &str) -> Option<String> {
+    let (_, address) = parse_did(did)?;
+    let base = base_url(did)?;
+    Some(if address.contains(':') {
+        format!("{}did.jsonl", base)
+    } else {
+        format!("{}.well-known/did.jsonl", base)
+    })
+}
+
+/// Base58btc encoding of the SHA-256 multihash of some data.
+fn multihash(data: &[u8]) -> String {
+    let mut bytes = vec![0x12, 0x20];
+    bytes.extend_from_slice(&Sha256::digest(data));
+    bs58::encode(bytes).into_string()
+}
+
+/// Hash of a multikey, as listed in `nextKeyHashes`.
+fn multikey_hash(multikey: &str) -> String {
+    multihash(multikey.as_bytes())
+}
+
+/// Hash of the public key of a JWK, to commit to it as a next update key in `nextKeyHashes`.
+pub fn key_hash(key: &JWK) -> Result<String, WebVHError> {
+    Ok(multikey_hash(&to_multibase(key)?))
+}
+
+/// Hash of an entry without its proof, chained to the previous entry by its `versionId`.
+fn entry_hash(entry: &Value, previous_version_id: &str) -> String {
+    let mut entry = entry.clone();
+    entry["versionId"] = Value::String(previous_version_id.to_string());
+    multihash(jcs(&entry).as_bytes())
+}
+
+/// SCID of a first entry without its proof, i.e. the hash of the entry with the SCID replaced
+/// by the placeholder.
+fn entry_scid(entry: &Value, scid: &str) -> Result<String, WebVHError> {
+    let mut entry: Value =
+        serde_json::from_str(&entry.to_string().replace(scid, SCID_PLACEHOLDER))?;
+    entry["versionId"] = Value::String(SCID_PLACEHOLDER.to_string());
+    Ok(multihash(jcs(&entry).as_bytes()))
+}
+
+fn split_version_id(version_id: &str) -> Option<(usize, &str)> {
+    let (number, hash) = version_id.split_once('-')?;
+    Some((number.parse().ok()?, hash))
+}
+
+/// Data signed by an `eddsa-jcs-2022` proof: the hashes of the proof configuration and of the
+/// document.
+fn signing_input(proof_config: &Value, document: &Value) -> Vec<u8> {
+    let mut data = Sha256::digest(jcs(proof_config).as_bytes()).to_vec();
+    data.extend_from_slice(&Sha256::digest(jcs(document).as_bytes()));
+    data
+}
+
+fn sign_entry(entry: &Value, key: &JWK) -> Result<Value, WebVHError> {
+    let multikey = to_multibase(key)?;
+    let mut proof = json!({
+        "type": "DataIntegrityProof",
+        "cryptosuite": CRYPTOSUITE,
+        "verificationMethod": format!("did:key:{}#{}", multikey, multikey),
+        "created": now(),
+        "proofPurpose": PROOF_PURPOSE,
+    });
+    let signature = ssi::jws::sign_bytes(Algorithm::EdDSA, &signing_input(&proof, entry), key)?;
+    proof["proofValue"] = Value::String(format!("z{}", bs58::encode(signature).into_string()));
+    Ok(proof)
+}
+
+/// Verify a proof of an entry by one of the authorized update keys.
+fn verify_proof(entry: &Value, proof: &Value, authorized: &[String]) -> Result<(), String> {
+    let string = |name: &str| proof.get(name).and_then(Value::as_str);
+    if string("type") != Some("DataIntegrityProof") || string("cryptosuite") != Some(CRYPTOSUITE) {
+        return Err(format!("expected a {} proof", CRYPTOSUITE));
+    }
+    if string("proofPurpose") != Some(PROOF_PURPOSE) {
+        return Err(format!("expected proof purpose {}", PROOF_PURPOSE));
+    }
+    let multikey = string("verificationMethod")
+        .and_then(|vm| vm.strip_prefix("did:key:"))
+        .and_then(|vm| vm.split('#').next())
+        .ok_or("expected a did:key verification method")?;
+    if !authorized.iter().any(|key| key == multikey) {
+        return Err(format!("{} is not an authorized update key", multikey));
+    }
+    let key = from_multibase(multikey).map_err(|e| e.to_string())?;
+    let signature = string("proofValue")
+        .and_then(|value| value.strip_prefix('z'))
+        .and_then(|value| bs58::decode(value).into_vec().ok())
+        .ok_or("malformed proofValue")?;
+    let mut config = proof.clone();
+    if let Some(config) = config.as_object_mut() {
+        config.remove("proofValue");
+    }
+    ssi::jws::verify_bytes(
+        Algorithm::EdDSA,
+        &signing_input(&config, entry),
+        &key,
+        &signature,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Read and verify a DID log, and return its versions.
+pub fn read_log(log: &str) -> Result<Vec<Version>, WebVHError> {
+    let mut versions: Vec<Version> = Vec::new();
+    let mut parameters = Parameters::default();
+    let lines = log.lines().filter(|line| !line.trim().is_empty());
+    for (i, line) in lines.enumerate() {
+        let invalid = |reason: &str| WebVHError::InvalidEntry(i + 1, reason.to_string());
+        let mut entry: Value = serde_json::from_str(line)?;
+        let proofs = match entry
+            .as_object_mut()
+            .and_then(|entry| entry.remove("proof"))
+        {
+            Some(Value::Array(proofs)) => proofs,
+            Some(proof) => vec![proof],
+            None => return Err(invalid("missing proof")),
+        };
+        let version_id = entry
+            .get("versionId")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing versionId"))?
+            .to_string();
+        let (number, hash) =
+            split_version_id(&version_id).ok_or_else(|| invalid("malformed versionId"))?;
+        if number != i + 1 {
+            return Err(invalid("unexpected version number"));
+        }
+        let version_time = entry
+            .get("versionTime")
+            .and_then(Value::as_str)
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .ok_or_else(|| invalid("missing or malformed versionTime"))?
+            .with_timezone(&Utc);
+        if version_time > Utc::now() {
+            return Err(invalid("versionTime is in the future"));
+        }
+        let changes: Parameters = serde_json::from_value(
+            entry
+                .get("parameters")
+                .cloned()
+                .ok_or_else(|| invalid("missing parameters"))?,
+        )?;
+        if let Some(ref method) = changes.method {
+            if method != METHOD_VERSION {
+                return Err(invalid(&format!("unsupported method version {}", method)));
+            }
+        }
+
+        let previous_version_id = match versions.last() {
+            None => {
+                if changes.method.is_none() {
+                    return Err(invalid("missing method"));
+                }
+                let scid = changes
+                    .scid
+                    .as_deref()
+                    .ok_or_else(|| invalid("missing scid"))?;
+                if entry_scid(&entry, scid)? != scid {
+                    return Err(invalid("scid does not match the entry"));
+                }
+                scid.to_string()
+            }
+            Some(previous) => {
+                if version_time < previous.version_time {
+                    return Err(invalid("versionTime is before that of the previous entry"));
+                }
+                if parameters.deactivated == Some(true) {
+                    return Err(invalid("entry after deactivation"));
+                }
+                if changes.scid.is_some() && changes.scid != parameters.scid {
+                    return Err(invalid("scid changed"));
+                }
+                if changes.portable == Some(true) && parameters.portable != Some(true) {
+                    return Err(invalid("portable can only be set in the first entry"));
+                }
+                previous.version_id.clone()
+            }
+        };
+        if entry_hash(&entry, &previous_version_id) != hash {
+            return Err(invalid("versionId does not match the entry hash"));
+        }
+
+        let authorized = if parameters.prerotation() {
+            let update_keys = changes
+                .update_keys
+                .as_ref()
+                .ok_or_else(|| invalid("pre-rotation requires new updateKeys"))?;
+            let next_key_hashes = parameters.next_key_hashes.as_deref().unwrap_or_default();
+            if update_keys
+                .iter()
+                .any(|key| !next_key_hashes.contains(&multikey_hash(key)))
+            {
+                return Err(invalid("update key not committed to in nextKeyHashes"));
+            }
+            update_keys.clone()
+        } else if versions.is_empty() {
+            changes
+                .update_keys
+                .clone()
+                .ok_or_else(|| invalid("missing updateKeys"))?
+        } else {
+            parameters.update_keys.clone().unwrap_or_default()
+        };
+        let mut verified = Err("no proof".to_string());
+        for proof in &proofs {
+            verified = verify_proof(&entry, proof, &authorized);
+            if verified.is_ok() {
+                break;
+            }
+        }
+        verified.map_err(|reason| invalid(&reason))?;
+
+        parameters.apply(&changes);
+        if parameters.witnessed() {
+            return Err(WebVHError::UnsupportedWitness);
+        }
+        let document = entry
+            .get("state")
+            .filter(|state| state.is_object())
+            .cloned()
+            .ok_or_else(|| invalid("missing state"))?;
+        let id = document
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing DID document id"))?;
+        match parse_did(id) {
+            Some((scid, _)) if Some(scid) == parameters.scid.as_deref() => {}
+            _ => {
+                return Err(invalid(
+                    "DID document id is not a did:webvh DID with the scid",
+                ))
+            }
+        }
+        if let Some(previous) = versions.last() {
+            if previous.document.get("id") != document.get("id")
+                && parameters.portable != Some(true)
+            {
+                return Err(invalid("DID moved, but is not portable"));
+            }
+        }
+        versions.push(Version {
+            version_id,
+            version_time,
+            document,
+            parameters: parameters.clone(),
+        });
+    }
+    if versions.is_empty() {
+        return Err(WebVHError::EmptyLog);
+    }
+    Ok(versions)
+}
+
+/// Version requested by the `versionId` (or version number) or `versionTime` input metadata,
+/// or the last version.
+fn select_version<'a>(
+    versions: &'a [Version],
+    input_metadata: &ResolutionInputMetadata,
+) -> Result<&'a Version, WebVHError> {
+    let version = if let Some(ref version_id) = input_metadata.version_id {
+        versions.iter().find(|version| {
+            version.version_id == *version_id
+                || version.version_id.split('-').next() == Some(version_id.as_str())
+        })
+    } else if let Some(ref version_time) = input_metadata.version_time {
+        let time =
+            DateTime::parse_from_rfc3339(version_time).map_err(|_| WebVHError::VersionNotFound)?;
+        versions
+            .iter()
+            .rev()
+            .find(|version| version.version_time <= time)
+    } else {
+        versions.last()
+    };
+    version.ok_or(WebVHError::VersionNotFound)
+}
+
+/// Add the implicit `#files` and `#whois` services of a DID, unless defined by its document.
+fn add_implicit_services(document: &mut Value, did: &str) {
+    let base = match base_url(did) {
+        Some(base) => base,
+        None => return,
+    };
+    let implicit = [
+        ("files", "relativeRef", base.clone()),
+        (
+            "whois",
+            "LinkedVerifiablePresentation",
+            format!("{}whois.vp", base),
+        ),
+    ];
+    let services = match document.as_object_mut() {
+        Some(document) => document.entry("service").or_insert_with(|| json!([])),
+        None => return,
+    };
+    if let Some(services) = services.as_array_mut() {
+        for (fragment, type_, endpoint) in implicit.iter() {
+            let id = format!("{}#{}", did, fragment);
+            let relative = format!("#{}", fragment);
+            let defined = services.iter().any(|service| {
+                let service_id = service.get("id").and_then(Value::as_str);
+                service_id == Some(id.as_str()) || service_id == Some(relative.as_str())
+            });
+            if !defined {
+                services.push(json!({ "id": id, "type": type_, "serviceEndpoint": endpoint }));
+            }
+        }
+    }
+}
+
+/// Resolve a DID from its DID log: verify the log, and return the DID document and metadata of
+/// the requested version.
+pub fn resolve_log(
+    did: &str,
+    log: &str,
+    input_metadata: &ResolutionInputMetadata,
+) -> Result<(Document, DocumentMetadata), WebVHError> {
+    if parse_did(did).is_none() {
+        return Err(WebVHError::InvalidDID(did.to_string()));
+    }
+    let versions = read_log(log)?;
+    let last = versions.last().ok_or(WebVHError::EmptyLog)?;
+    if last.document.get("id").and_then(Value::as_str) != Some(did) {
+        return Err(WebVHError::InvalidDID(format!(
+            "{} (the log is of another DID)",
+            did
+        )));
+    }
+    let version = select_version(&versions, input_metadata)?;
+    let mut document = version.document.clone();
+    add_implicit_services(&mut document, did);
+    let document: Document = serde_json::from_value(document)?;
+    let mut property_set = HashMap::new();
+    property_set.insert(
+        "versionId".to_string(),
+        Metadata::String(version.version_id.clone()),
+    );
+    if let Some(ref scid) = version.parameters.scid {
+        property_set.insert("scid".to_string(), Metadata::String(scid.clone()));
+    }
+    let doc_meta = DocumentMetadata {
+        created: Some(versions[0].version_time),
+        updated: Some(version.version_time),
+        deactivated: Some(version.parameters.deactivated == Some(true)),
+        property_set: Some(property_set),
+    };
+    Ok((document, doc_meta))
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// DID document with a key as its `Multikey` verification method, for authentication and
+/// assertions.
+fn key_document(did: &str, key: &JWK) -> Result<Value, WebVHError> {
+    let multikey = to_multibase(key)?;
+    let vm = format!("{}#{}", did, &multikey[multikey.len() - 8..]);
+    Ok(json!({
+        "@context": ["https://www.w3.org/ns/did/v1", "https://w3id.org/security/multikey/v1"],
+        "id": did,
+        "verificationMethod": [{
+            "id": vm,
+            "type": "Multikey",
+            "controller": did,
+            "publicKeyMultibase": multikey,
+        }],
+        "authentication": [vm],
+        "assertionMethod": [vm],
+    }))
+}
+
+/// Set the `versionId` of an entry from its hash, and sign it.
+fn complete_entry(
+    mut entry: Value,
+    previous_version_id: &str,
+    number: usize,
+    key: &JWK,
+) -> Result<Value, WebVHError> {
+    let hash = entry_hash(&entry, previous_version_id);
+    entry["versionId"] = Value::String(format!("{}-{}", number, hash));
+    let proof = sign_entry(&entry, key)?;
+    entry["proof"] = json!([proof]);
+    Ok(entry)
+}
+
+/// Create a DID, and return the first entry of its log, signed with an update key.
+///
+/// `address` is the domain and optional path of the DID, as in a `did:web` DID, e.g.
+/// `example.com:dids:issuer`. The DID document is `document`, in which `{DID}` stands for the
+/// DID, or by default has the key as its verification method. The update keys are the key's,
+/// unless given in `parameters`, which may also set `nextKeyHashes`, `portable` and `ttl`.
+pub fn create(
+    address: &str,
+    key: &JWK,
+    document: Option<Value>,
+    mut parameters: Parameters,
+) -> Result<Value, WebVHError> {
+    let did = format!("{}{}:{}", DID_WEBVH, SCID_PLACEHOLDER, address);
+    if parse_did(&did).is_none() {
+        return Err(WebVHError::InvalidDID(did));
+    }
+    let document = match document {
+        Some(document) => {
+            serde_json::from_str(&document.to_string().replace(DID_PLACEHOLDER, &did))?
+        }
+        None => key_document(&did, key)?,
+    };
+    let multikey = to_multibase(key)?;
+    let update_keys = parameters
+        .update_keys
+        .get_or_insert_with(|| vec![multikey.clone()]);
+    if !update_keys.contains(&multikey) {
+        return Err(WebVHError::UnauthorizedKey);
+    }
+    parameters.method = Some(METHOD_VERSION.to_string());
+    parameters.scid = Some(SCID_PLACEHOLDER.to_string());
+    let entry = json!({
+        "versionId": SCID_PLACEHOLDER,
+        "versionTime": now(),
+        "parameters": parameters,
+        "state": document,
+    });
+    let scid = multihash(jcs(&entry).as_bytes());
+    let entry: Value = serde_json::from_str(&entry.to_string().replace(SCID_PLACEHOLDER, &scid))?;
+    complete_entry(entry, &scid, 1, key)
+}
+
+/// Update a DID, and return the next entry of its log, with the new DID document, the
+/// parameters that change, and a proof by an update key.
+///
+/// With pre-rotation, `parameters` must give the new update keys, committed to by the previous
+/// entry, and the key must be one of them. A DID is deactivated by setting `deactivated`.
+pub fn update(
+    log: &str,
+    document: Value,
+    parameters: Parameters,
+    key: &JWK,
+) -> Result<Value, WebVHError> {
+    let versions = read_log(log)?;
+    let last = versions.last().ok_or(WebVHError::EmptyLog)?;
+    if last.parameters.deactivated == Some(true) {
+        return Err(WebVHError::Deactivated);
+    }
+    let authorized = if last.parameters.prerotation() {
+        let update_keys = parameters.update_keys.clone().unwrap_or_default();
+        let next_key_hashes = last
+            .parameters
+            .next_key_hashes
+            .as_deref()
+            .unwrap_or_default();
+        if update_keys
+            .iter()
+            .any(|key| !next_key_hashes.contains(&multikey_hash(key)))
+        {
+            return Err(WebVHError::UnauthorizedKey);
+        }
+        update_keys
+    } else {
+        last.parameters.update_keys.clone().unwrap_or_default()
+    };
+    if !authorized.contains(&to_multibase(key)?) {
+        return Err(WebVHError::UnauthorizedKey);
+    }
+    let entry = json!({
+        "versionId": last.version_id,
+        "versionTime": now(),
+        "parameters": parameters,
+        "state": document,
+    });
+    complete_entry(entry, &last.version_id, versions.len() + 1, key)
+}
+
+async fn fetch_log(url: &str) -> Result<Option<String>, WebVHError> {
+    let error = |e: reqwest::Error| WebVHError::Fetch(url.to_string(), e.to_string());
+    let response = reqwest::get(url).await.map_err(error)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let response = response.error_for_status().map_err(error)?;
+    Ok(Some(response.text().await.map_err(error)?))
+}
+
+pub struct DIDWebVH;
+
+fn error_output(
+    error: &str,
+) -> (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+) {
+    (
+        ResolutionMetadata {
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for DIDWebVH {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let url = match log_url(did) {
+            Some(url) => url,
+            None => return error_output(ERROR_INVALID_DID),
+        };
+        let log = match fetch_log(&url).await {
+            Ok(Some(log)) => log,
+            Ok(None) => return error_output(ERROR_NOT_FOUND),
+            Err(err) => return error_output(&err.to_string()),
+        };
+        match resolve_log(did, &log, input_metadata) {
+            Ok((document, doc_meta)) => {
+                let res_meta = ResolutionMetadata {
+                    content_type: Some(TYPE_DID_LD_JSON.to_string()),
+                    ..Default::default()
+                };
+                (res_meta, Some(document), Some(doc_meta))
+            }
+            Err(WebVHError::VersionNotFound) => error_output(ERROR_NOT_FOUND),
+            Err(WebVHError::InvalidDID(_)) => error_output(ERROR_INVALID_DID),
+            Err(err) => error_output(&err.to_string()),
+        }
+    }
+}
+
+impl DIDMethod for DIDWebVH {
+    fn name(&self) -> &'static str {
+        "webvh"
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_json() {
+        let value = json!({ "b": [1, "x\n"], "a": { "d": 1.0, "c": null } });
+        assert_eq!(jcs(&value), r#"{"a":{"c":null,"d":1},"b":[1,"x\n"]}"#);
+    }
+
+    #[test]
+    fn create_update_resolve() {
+        let key = JWK::generate_ed25519().unwrap();
+        let next_key = JWK::generate_ed25519().unwrap();
+        let parameters = Parameters {
+            next_key_hashes: Some(vec![key_hash(&next_key).unwrap()]),
+            ..Default::default()
+        };
+        let first = create("example.com:dids:issuer", &key, None, parameters).unwrap();
+        let did = first["state"]["id"].as_str().unwrap().to_string();
+        let scid = first["parameters"]["scid"].as_str().unwrap();
+        assert_eq!(did, format!("did:webvh:{}:example.com:dids:issuer", scid));
+        assert_eq!(
+            log_url(&did).unwrap(),
+            "https://example.com/dids/issuer/did.jsonl"
+        );
+        let mut log = format!("{}\n", first);
+
+        // The next update keys must have been committed to.
+        let mut document = first["state"].clone();
+        document["capabilityInvocation"] = document["assertionMethod"].clone();
+        let uncommitted = Parameters {
+            update_keys: Some(vec![to_multibase(&key).unwrap()]),
+            ..Default::default()
+        };
+        assert!(update(&log, document.clone(), uncommitted, &key).is_err());
+        let rotation = Parameters {
+            update_keys: Some(vec![to_multibase(&next_key).unwrap()]),
+            next_key_hashes: Some(vec![]),
+            ..Default::default()
+        };
+        let second = update(&log, document, rotation, &next_key).unwrap();
+        assert!(second["versionId"].as_str().unwrap().starts_with("2-"));
+        log.push_str(&format!("{}\n", second));
+
+        let (doc, doc_meta) = resolve_log(&did, &log, &ResolutionInputMetadata::default()).unwrap();
+        let doc = serde_json::to_value(doc).unwrap();
+        assert!(doc.get("capabilityInvocation").is_some());
+        assert!(doc["service"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|service| service["id"] == format!("{}#files", did)));
+        let doc_meta = serde_json::to_value(doc_meta).unwrap();
+        assert_eq!(doc_meta["versionId"], second["versionId"]);
+
+        let input_metadata = ResolutionInputMetadata {
+            version_id: Some("1".to_string()),
+            ..Default::default()
+        };
+        let (doc, _) = resolve_log(&did, &log, &input_metadata).unwrap();
+        assert!(serde_json::to_value(doc)
+            .unwrap()
+            .get("capabilityInvocation")
+            .is_none());
+
+        // Changing an entry breaks its hash and proof.
+        let tampered = log.replacen("capabilityInvocation", "keyAgreement", 1);
+        assert!(matches!(
+            read_log(&tampered),
+            Err(WebVHError::InvalidEntry(2, _))
+        ));
+    }
+}
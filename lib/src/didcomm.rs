@@ -0,0 +1,806 @@
+//! [DIDComm v2][didcomm] messages, and a client for mediation and message pickup.
+//!
+//! An agent that cannot receive messages directly, e.g. behind a firewall, registers with a
+//! mediator using [Coordinate Mediation 2.0][coordinate-mediation]: the mediator grants it a
+//! routing DID to advertise as its endpoint, and keeps a list of the agent's recipient DIDs.
+//! Senders wrap messages for the agent in [forward][routing] messages to the mediator, which
+//! queues them until the agent retrieves them with [Message Pickup 3.0][pickup].
+//!
+//! [`MediationClient`] is a transport-independent state machine: its methods return the messages
+//! to send to the mediator, and [`MediationClient::handle`] processes the mediator's replies. It
+//! can be serialized, to keep the state of a mediation between runs.
+//!
+//! Connections start with an [Out of Band 2.0][oob] invitation: [`invitation`] creates one from
+//! the inviter's DID, [`invitation_url`] encodes it in a URL (e.g. to show as a QR code), and
+//! [`accept_invitation`] parses it and resolves the inviter's service endpoint and routing keys,
+//! for the invitee to store as its connection record (see [`crate::wallet::Connection`]).
+//!
+//! [`pack`] and [`unpack`] encrypt and decrypt messages with [`crate::jwe`], so envelopes are
+//! anonymous JWEs in compact serialization, for a single recipient, using ECDH-ES direct key
+//! agreement. Authenticated encryption (ECDH-1PU), key wrapping (ECDH-ES+A256KW) and the JSON
+//! serialization for multiple recipients are not implemented yet.
+//!
+//! [didcomm]: https://identity.foundation/didcomm-messaging/spec/v2.0/
+//! [coordinate-mediation]: https://didcomm.org/coordinate-mediation/2.0/
+//! [routing]: https://identity.foundation/didcomm-messaging/spec/v2.0/#routing-protocol-20
+//! [pickup]: https://didcomm.org/messagepickup/3.0/
+//! [oob]: https://identity.foundation/didcomm-messaging/spec/v2.0/#out-of-band-messages
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::hex;
+use crate::jwe::{self, JWEError};
+use crate::{DIDResolver, ResolutionInputMetadata, JWK};
+
+/// Media type of plaintext messages
+pub const PLAINTEXT_MEDIA_TYPE: &str = "application/didcomm-plain+json";
+/// Media type of encrypted messages
+pub const ENCRYPTED_MEDIA_TYPE: &str = "application/didcomm-encrypted+json";
+/// DID document service type of DIDComm v2 endpoints
+pub const SERVICE_TYPE: &str = "DIDCommMessaging";
+
+pub const MEDIATE_REQUEST: &str = "https://didcomm.org/coordinate-mediation/2.0/mediate-request";
+pub const MEDIATE_GRANT: &str = "https://didcomm.org/coordinate-mediation/2.0/mediate-grant";
+pub const MEDIATE_DENY: &str = "https://didcomm.org/coordinate-mediation/2.0/mediate-deny";
+pub const RECIPIENT_UPDATE: &str = "https://didcomm.org/coordinate-mediation/2.0/recipient-update";
+pub const RECIPIENT_UPDATE_RESPONSE: &str =
+    "https://didcomm.org/coordinate-mediation/2.0/recipient-update-response";
+pub const RECIPIENT_QUERY: &str = "https://didcomm.org/coordinate-mediation/2.0/recipient-query";
+pub const RECIPIENT: &str = "https://didcomm.org/coordinate-mediation/2.0/recipient";
+pub const STATUS_REQUEST: &str = "https://didcomm.org/messagepickup/3.0/status-request";
+pub const STATUS: &str = "https://didcomm.org/messagepickup/3.0/status";
+pub const DELIVERY_REQUEST: &str = "https://didcomm.org/messagepickup/3.0/delivery-request";
+pub const DELIVERY: &str = "https://didcomm.org/messagepickup/3.0/delivery";
+pub const MESSAGES_RECEIVED: &str = "https://didcomm.org/messagepickup/3.0/messages-received";
+pub const LIVE_DELIVERY_CHANGE: &str = "https://didcomm.org/messagepickup/3.0/live-delivery-change";
+pub const FORWARD: &str = "https://didcomm.org/routing/2.0/forward";
+pub const PROBLEM_REPORT: &str = "https://didcomm.org/report-problem/2.0/problem-report";
+pub const OFFER_CREDENTIAL: &str = "https://didcomm.org/issue-credential/3.0/offer-credential";
+pub const REQUEST_CREDENTIAL: &str = "https://didcomm.org/issue-credential/3.0/request-credential";
+pub const ISSUE_CREDENTIAL: &str = "https://didcomm.org/issue-credential/3.0/issue-credential";
+pub const REQUEST_PRESENTATION: &str = "https://didcomm.org/present-proof/3.0/request-presentation";
+pub const PRESENTATION: &str = "https://didcomm.org/present-proof/3.0/presentation";
+pub const OOB_INVITATION: &str = "https://didcomm.org/out-of-band/2.0/invitation";
+
+/// Query parameter of the invitation in an out-of-band invitation URL
+pub const OOB_PARAMETER: &str = "_oob";
+
+#[derive(Error, Debug)]
+pub enum DIDCommError {
+    #[error("JWE: {0}")]
+    JWE(#[from] JWEError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("Unable to resolve DID: {0}")]
+    Resolution(String),
+    #[error("No DIDComm service endpoint found for DID: {0}")]
+    MissingEndpoint(String),
+    #[error("Mediation has not been granted")]
+    NotGranted,
+    #[error("Unexpected message type: {0}")]
+    UnexpectedMessage(String),
+    #[error("Message is not a reply to the mediation request")]
+    UnexpectedThread,
+    #[error("Invalid message body: {0}")]
+    InvalidBody(String),
+    #[error("Invalid out-of-band invitation: {0}")]
+    InvalidInvitation(String),
+}
+
+/// Plaintext DIDComm message.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Message {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub to: Vec<String>,
+    /// ID of the thread the message belongs to, i.e. of the message it replies to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<i64>,
+    /// Ask the recipient to reply on the same connection, e.g. in the HTTP response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_route: Option<String>,
+    #[serde(default)]
+    pub body: Value,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Attachment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Format of the attachment data, e.g. of credentials in issue-credential messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    pub data: AttachmentData,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct AttachmentData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+}
+
+impl Attachment {
+    /// Attachment of an encrypted message.
+    pub fn envelope(id: Option<String>, envelope: &str) -> Self {
+        Self {
+            id,
+            media_type: Some(ENCRYPTED_MEDIA_TYPE.to_string()),
+            format: None,
+            data: AttachmentData {
+                base64: Some(base64::encode_config(envelope, base64::URL_SAFE_NO_PAD)),
+                json: None,
+            },
+        }
+    }
+
+    /// Attachment of JSON data.
+    pub fn json(id: Option<String>, format: Option<String>, json: Value) -> Self {
+        Self {
+            id,
+            media_type: Some("application/json".to_string()),
+            format,
+            data: AttachmentData {
+                base64: None,
+                json: Some(json),
+            },
+        }
+    }
+
+    /// Get the data of a JSON attachment, or a string if it is not JSON, e.g. a JWT.
+    pub fn to_json(&self) -> Result<Value, DIDCommError> {
+        if let Some(ref json) = self.data.json {
+            return Ok(json.clone());
+        }
+        let data = self
+            .data
+            .base64
+            .as_ref()
+            .ok_or_else(|| DIDCommError::InvalidBody("Attachment has no data".to_string()))?;
+        let bytes = base64::decode_config(data.trim_end_matches('='), base64::URL_SAFE_NO_PAD)?;
+        Ok(serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).trim().to_string())))
+    }
+
+    /// Get the encrypted message of an attachment, in compact serialization.
+    pub fn to_envelope(&self) -> Result<String, DIDCommError> {
+        if let Some(ref data) = self.data.base64 {
+            let bytes = base64::decode_config(data.trim_end_matches('='), base64::URL_SAFE_NO_PAD)?;
+            return String::from_utf8(bytes)
+                .map_err(|e| DIDCommError::InvalidBody(format!("Attachment: {}", e)));
+        }
+        match self.data.json {
+            Some(Value::String(ref envelope)) => Ok(envelope.to_string()),
+            Some(ref json) => Ok(serde_json::to_string(json)?),
+            None => Err(DIDCommError::InvalidBody(
+                "Attachment has no data".to_string(),
+            )),
+        }
+    }
+}
+
+impl Message {
+    /// Create a message with a new ID, created now.
+    pub fn new(type_: impl Into<String>, body: Value) -> Self {
+        Self {
+            id: hex::random_id(),
+            type_: type_.into(),
+            from: None,
+            to: Vec::new(),
+            thid: None,
+            created_time: Some(Utc::now().timestamp()),
+            return_route: None,
+            body,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// ID of the thread of the message: its `thid`, or its own ID if it starts a thread.
+    pub fn thread_id(&self) -> &str {
+        self.thid.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Forward message, asking a mediator to deliver an encrypted message to `next`.
+pub fn forward(next: &str, envelope: &str, mediator: &str) -> Message {
+    let mut message = Message::new(FORWARD, json!({ "next": next }));
+    message.to = vec![mediator.to_string()];
+    message.attachments = vec![Attachment::envelope(None, envelope)];
+    message
+}
+
+/// Get the recipient and encrypted message of a forward message.
+pub fn forwarded_envelope(message: &Message) -> Result<(String, String), DIDCommError> {
+    if message.type_ != FORWARD {
+        return Err(DIDCommError::UnexpectedMessage(message.type_.clone()));
+    }
+    let next = message
+        .body
+        .get("next")
+        .and_then(Value::as_str)
+        .ok_or_else(|| DIDCommError::InvalidBody("Missing next".to_string()))?;
+    let attachment = message
+        .attachments
+        .first()
+        .ok_or_else(|| DIDCommError::InvalidBody("Missing attachment".to_string()))?;
+    Ok((next.to_string(), attachment.to_envelope()?))
+}
+
+/// Encrypt a message to the key agreement key of a DID.
+pub async fn pack(
+    message: &Message,
+    to: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<String, DIDCommError> {
+    let plaintext = serde_json::to_vec(message)?;
+    Ok(jwe::encrypt_to_did(
+        &plaintext,
+        to,
+        Some(PLAINTEXT_MEDIA_TYPE.to_string()),
+        resolver,
+    )
+    .await?)
+}
+
+/// Decrypt an encrypted message with the recipient's private key.
+pub fn unpack(envelope: &str, key: &JWK) -> Result<Message, DIDCommError> {
+    let (_, plaintext) = jwe::decrypt(envelope, key)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// DIDComm v2 service endpoint of a DID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceEndpoint {
+    pub uri: String,
+    /// Keys of the mediators to route messages through, outermost first
+    pub routing_keys: Vec<String>,
+}
+
+impl ServiceEndpoint {
+    fn from_value(endpoint: &Value, service: &Value) -> Option<Self> {
+        let (uri, routing_keys) = match endpoint {
+            Value::String(uri) => (uri.as_str(), service.get("routingKeys")),
+            Value::Object(endpoint) => (
+                endpoint.get("uri").and_then(Value::as_str)?,
+                endpoint.get("routingKeys"),
+            ),
+            _ => return None,
+        };
+        let routing_keys = routing_keys
+            .and_then(Value::as_array)
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(Value::as_str)
+                    .map(|key| key.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            uri: uri.to_string(),
+            routing_keys,
+        })
+    }
+}
+
+/// Find the DIDComm v2 service endpoint of a DID.
+pub async fn resolve_service(
+    did: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<ServiceEndpoint, DIDCommError> {
+    let (res_meta, doc_opt, _) = resolver
+        .resolve(did, &ResolutionInputMetadata::default())
+        .await;
+    if let Some(error) = res_meta.error {
+        return Err(DIDCommError::Resolution(error));
+    }
+    let doc = doc_opt.ok_or_else(|| DIDCommError::Resolution(did.to_string()))?;
+    let doc = serde_json::to_value(doc)?;
+    let services = match doc.get("service") {
+        Some(Value::Array(services)) => services.clone(),
+        Some(service) => vec![service.clone()],
+        None => Vec::new(),
+    };
+    services
+        .iter()
+        .filter(|service| match service.get("type") {
+            Some(Value::String(type_)) => type_ == SERVICE_TYPE,
+            Some(Value::Array(types)) => types.iter().any(|type_| type_ == SERVICE_TYPE),
+            _ => false,
+        })
+        .find_map(|service| match service.get("serviceEndpoint")? {
+            Value::Array(endpoints) => endpoints
+                .iter()
+                .find_map(|endpoint| ServiceEndpoint::from_value(endpoint, service)),
+            endpoint => ServiceEndpoint::from_value(endpoint, service),
+        })
+        .ok_or_else(|| DIDCommError::MissingEndpoint(did.to_string()))
+}
+
+/// Find the DIDComm v2 service endpoint URI of a DID.
+pub async fn service_endpoint(
+    did: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<String, DIDCommError> {
+    Ok(resolve_service(did, resolver).await?.uri)
+}
+
+/// Encrypt a message to a DID, wrapped in forward messages for the mediators of its service
+/// endpoint, if any. Returns the endpoint URI to deliver the envelope to.
+///
+/// Routing keys are resolved to the key agreement key of their DID.
+pub async fn pack_for_delivery(
+    message: &Message,
+    to: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<(String, String), DIDCommError> {
+    let service = resolve_service(to, resolver).await?;
+    let mut envelope = pack(message, to, resolver).await?;
+    let mut next = to.to_string();
+    for routing_key in service.routing_keys.iter().rev() {
+        let mediator = routing_key.split('#').next().unwrap_or(routing_key);
+        let forward = forward(&next, &envelope, mediator);
+        envelope = pack(&forward, mediator, resolver).await?;
+        next = mediator.to_string();
+    }
+    Ok((service.uri, envelope))
+}
+
+/// Out-of-band invitation from a DID, accepting DIDComm v2 replies.
+pub fn invitation(
+    from: &str,
+    label: Option<&str>,
+    goal_code: Option<&str>,
+    goal: Option<&str>,
+) -> Message {
+    let mut body = json!({ "accept": ["didcomm/v2"] });
+    for (name, value) in [("label", label), ("goal_code", goal_code), ("goal", goal)].iter() {
+        if let Some(value) = value {
+            body[*name] = Value::String(value.to_string());
+        }
+    }
+    let mut message = Message::new(OOB_INVITATION, body);
+    message.from = Some(from.to_string());
+    message
+}
+
+/// URL of an invitation: a base URL, e.g. `https://example.org/invite`, with the invitation in
+/// its `_oob` query parameter, as base64url JSON.
+pub fn invitation_url(invitation: &Message, base_url: &str) -> Result<String, DIDCommError> {
+    let encoded = base64::encode_config(serde_json::to_vec(invitation)?, base64::URL_SAFE_NO_PAD);
+    let separator = if base_url.contains('?') { '&' } else { '?' };
+    Ok(format!(
+        "{}{}{}={}",
+        base_url, separator, OOB_PARAMETER, encoded
+    ))
+}
+
+/// Parse an invitation, given as a URL with an `_oob` query parameter, or as JSON.
+pub fn parse_invitation(invitation: &str) -> Result<Message, DIDCommError> {
+    let invitation = invitation.trim();
+    let message: Message = if invitation.starts_with('{') {
+        serde_json::from_str(invitation)?
+    } else {
+        let query = invitation
+            .split('#')
+            .next()
+            .and_then(|url| url.splitn(2, '?').nth(1))
+            .unwrap_or_default();
+        let encoded = query
+            .split('&')
+            .find_map(|parameter| {
+                let mut parts = parameter.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(OOB_PARAMETER), Some(value)) => Some(value),
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| {
+                DIDCommError::InvalidInvitation(format!("missing {} parameter", OOB_PARAMETER))
+            })?;
+        // Padding may be percent-encoded.
+        let encoded = encoded.trim_end_matches("%3D").trim_end_matches('=');
+        let json = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+        serde_json::from_slice(&json)?
+    };
+    if message.type_ != OOB_INVITATION {
+        return Err(DIDCommError::UnexpectedMessage(message.type_));
+    }
+    if message.from.is_none() {
+        return Err(DIDCommError::InvalidInvitation("missing from".to_string()));
+    }
+    Ok(message)
+}
+
+/// Inviter of an accepted invitation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inviter {
+    /// ID of the invitation, the parent thread of the messages of the connection
+    pub invitation_id: String,
+    /// DID of the inviter
+    pub did: String,
+    pub label: Option<String>,
+    pub service: ServiceEndpoint,
+}
+
+/// Accept an invitation, given as a URL or JSON: parse it and resolve the DIDComm service
+/// endpoint of the inviter.
+pub async fn accept_invitation(
+    invitation: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<Inviter, DIDCommError> {
+    let invitation = parse_invitation(invitation)?;
+    let did = invitation.from.clone().unwrap_or_default();
+    let service = resolve_service(&did, resolver).await?;
+    Ok(Inviter {
+        invitation_id: invitation.id,
+        did,
+        label: invitation
+            .body
+            .get("label")
+            .and_then(Value::as_str)
+            .map(String::from),
+        service,
+    })
+}
+
+/// State of a mediation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum MediationState {
+    /// Mediation not requested yet
+    Unregistered,
+    /// Mediation requested, with the given request message ID
+    #[serde(rename_all = "camelCase")]
+    Requested {
+        request_id: String,
+    },
+    /// Mediation granted, with the DID to use as routing key
+    #[serde(rename_all = "camelCase")]
+    Granted {
+        routing_did: String,
+    },
+    Denied,
+}
+
+/// Result of a recipient update, for one DID.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecipientUpdate {
+    pub recipient_did: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+/// Message delivered by the mediator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveredMessage {
+    /// ID to acknowledge the message with
+    pub id: String,
+    /// Encrypted message, in compact serialization
+    pub envelope: String,
+}
+
+/// Outcome of a message from the mediator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediationEvent {
+    Granted {
+        routing_did: String,
+    },
+    Denied,
+    RecipientsUpdated(Vec<RecipientUpdate>),
+    Recipients(Vec<String>),
+    Status {
+        message_count: u64,
+        live_delivery: Option<bool>,
+    },
+    Delivered(Vec<DeliveredMessage>),
+    Problem {
+        code: String,
+        comment: Option<String>,
+    },
+}
+
+/// Client side of a mediation, by an agent with the DID `did`, with a mediator.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MediationClient {
+    pub did: String,
+    pub mediator: String,
+    #[serde(flatten)]
+    pub state: MediationState,
+}
+
+impl MediationClient {
+    pub fn new(did: impl Into<String>, mediator: impl Into<String>) -> Self {
+        Self {
+            did: did.into(),
+            mediator: mediator.into(),
+            state: MediationState::Unregistered,
+        }
+    }
+
+    /// DID to advertise as the routing key of the agent's endpoint, once mediation is granted.
+    pub fn routing_did(&self) -> Option<&str> {
+        match self.state {
+            MediationState::Granted { ref routing_did } => Some(routing_did),
+            _ => None,
+        }
+    }
+
+    /// Message from the agent to the mediator, asking for replies on the same connection.
+    fn message(&self, type_: &str, body: Value) -> Message {
+        let mut message = Message::new(type_, body);
+        message.from = Some(self.did.clone());
+        message.to = vec![self.mediator.clone()];
+        message.return_route = Some("all".to_string());
+        message
+    }
+
+    fn granted_message(&self, type_: &str, body: Value) -> Result<Message, DIDCommError> {
+        match self.state {
+            MediationState::Granted { .. } => Ok(self.message(type_, body)),
+            _ => Err(DIDCommError::NotGranted),
+        }
+    }
+
+    /// Request mediation. The mediator replies with a grant or a denial.
+    pub fn request_mediation(&mut self) -> Message {
+        let message = self.message(MEDIATE_REQUEST, json!({}));
+        self.state = MediationState::Requested {
+            request_id: message.id.clone(),
+        };
+        message
+    }
+
+    /// Add and remove recipient DIDs for which the mediator accepts forwarded messages.
+    pub fn update_recipients(
+        &self,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<Message, DIDCommError> {
+        let updates: Vec<Value> = add
+            .iter()
+            .map(|did| json!({ "recipient_did": did, "action": "add" }))
+            .chain(
+                remove
+                    .iter()
+                    .map(|did| json!({ "recipient_did": did, "action": "remove" })),
+            )
+            .collect();
+        self.granted_message(RECIPIENT_UPDATE, json!({ "updates": updates }))
+    }
+
+    /// List the recipient DIDs registered with the mediator.
+    pub fn query_recipients(&self) -> Result<Message, DIDCommError> {
+        self.granted_message(RECIPIENT_QUERY, json!({}))
+    }
+
+    /// Ask how many messages are queued.
+    pub fn status_request(&self) -> Result<Message, DIDCommError> {
+        self.granted_message(STATUS_REQUEST, json!({}))
+    }
+
+    /// Ask for up to `limit` queued messages.
+    pub fn delivery_request(&self, limit: usize) -> Result<Message, DIDCommError> {
+        self.granted_message(DELIVERY_REQUEST, json!({ "limit": limit }))
+    }
+
+    /// Acknowledge delivered messages, so that the mediator removes them from the queue.
+    pub fn messages_received(&self, ids: &[String]) -> Result<Message, DIDCommError> {
+        self.granted_message(MESSAGES_RECEIVED, json!({ "message_id_list": ids }))
+    }
+
+    /// Ask the mediator to push messages as they arrive, on a connection that stays open, or to
+    /// stop doing so.
+    pub fn live_delivery_change(&self, live_delivery: bool) -> Result<Message, DIDCommError> {
+        self.granted_message(
+            LIVE_DELIVERY_CHANGE,
+            json!({ "live_delivery": live_delivery }),
+        )
+    }
+
+    /// Process a message from the mediator.
+    pub fn handle(&mut self, message: &Message) -> Result<MediationEvent, DIDCommError> {
+        let body = &message.body;
+        match &message.type_[..] {
+            MEDIATE_GRANT | MEDIATE_DENY => {
+                match self.state {
+                    MediationState::Requested { ref request_id }
+                        if message.thid.as_ref() == Some(request_id) => {}
+                    _ => return Err(DIDCommError::UnexpectedThread),
+                }
+                if message.type_ == MEDIATE_DENY {
+                    self.state = MediationState::Denied;
+                    return Ok(MediationEvent::Denied);
+                }
+                let routing_did = match body.get("routing_did") {
+                    Some(Value::String(did)) => did.to_string(),
+                    Some(Value::Array(dids)) => dids
+                        .first()
+                        .and_then(Value::as_str)
+                        .map(|did| did.to_string())
+                        .ok_or_else(|| DIDCommError::InvalidBody("Empty routing_did".into()))?,
+                    _ => return Err(DIDCommError::InvalidBody("Missing routing_did".into())),
+                };
+                self.state = MediationState::Granted {
+                    routing_did: routing_did.clone(),
+                };
+                Ok(MediationEvent::Granted { routing_did })
+            }
+            RECIPIENT_UPDATE_RESPONSE => {
+                let updates = body.get("updated").cloned().unwrap_or_else(|| json!([]));
+                Ok(MediationEvent::RecipientsUpdated(serde_json::from_value(
+                    updates,
+                )?))
+            }
+            RECIPIENT => {
+                let dids = body
+                    .get("dids")
+                    .and_then(Value::as_array)
+                    .map(|dids| {
+                        dids.iter()
+                            .filter_map(|did| did.get("recipient_did").and_then(Value::as_str))
+                            .map(|did| did.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Ok(MediationEvent::Recipients(dids))
+            }
+            STATUS => Ok(MediationEvent::Status {
+                message_count: body
+                    .get("message_count")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0),
+                live_delivery: body.get("live_delivery").and_then(Value::as_bool),
+            }),
+            DELIVERY => {
+                let messages = message
+                    .attachments
+                    .iter()
+                    .map(|attachment| {
+                        Ok(DeliveredMessage {
+                            id: attachment.id.clone().ok_or_else(|| {
+                                DIDCommError::InvalidBody("Missing attachment id".into())
+                            })?,
+                            envelope: attachment.to_envelope()?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, DIDCommError>>()?;
+                Ok(MediationEvent::Delivered(messages))
+            }
+            PROBLEM_REPORT => Ok(MediationEvent::Problem {
+                code: body
+                    .get("code")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                comment: body
+                    .get("comment")
+                    .and_then(Value::as_str)
+                    .map(|comment| comment.to_string()),
+            }),
+            type_ => Err(DIDCommError::UnexpectedMessage(type_.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(type_: &str, thid: &str, body: Value) -> Message {
+        let mut message = Message::new(type_, body);
+        message.thid = Some(thid.to_string());
+        message
+    }
+
+    #[test]
+    fn mediation_client() {
+        let mut client = MediationClient::new("did:example:agent", "did:example:mediator");
+        assert!(matches!(
+            client.status_request(),
+            Err(DIDCommError::NotGranted)
+        ));
+        let request = client.request_mediation();
+        assert_eq!(request.type_, MEDIATE_REQUEST);
+        assert_eq!(request.to, vec!["did:example:mediator"]);
+
+        let grant = json!({ "routing_did": "did:example:routing" });
+        assert!(client
+            .handle(&reply(MEDIATE_GRANT, "other", grant.clone()))
+            .is_err());
+        let event = client
+            .handle(&reply(MEDIATE_GRANT, &request.id, grant))
+            .unwrap();
+        assert_eq!(
+            event,
+            MediationEvent::Granted {
+                routing_did: "did:example:routing".to_string()
+            }
+        );
+        assert_eq!(client.routing_did(), Some("did:example:routing"));
+
+        let state: MediationClient =
+            serde_json::from_value(serde_json::to_value(&client).unwrap()).unwrap();
+        assert_eq!(state, client);
+
+        let update = client
+            .update_recipients(&["did:example:agent".to_string()], &[])
+            .unwrap();
+        assert_eq!(update.body["updates"][0]["action"], "add");
+
+        let envelope = "eyJhbGciOiJFQ0RILUVTIn0..aXY.Y3Q.dGFn";
+        let mut delivery = reply(DELIVERY, &update.id, json!({}));
+        delivery.attachments = vec![Attachment::envelope(Some("1".to_string()), envelope)];
+        let event = client.handle(&delivery).unwrap();
+        assert_eq!(
+            event,
+            MediationEvent::Delivered(vec![DeliveredMessage {
+                id: "1".to_string(),
+                envelope: envelope.to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn oob_invitation() {
+        let invitation = invitation("did:example:inviter", Some("Issuer"), None, None);
+        assert_eq!(invitation.body["label"], "Issuer");
+        assert_eq!(invitation.body["accept"], json!(["didcomm/v2"]));
+        let url = invitation_url(&invitation, "https://example.org/invite?lang=en").unwrap();
+        assert!(url.starts_with("https://example.org/invite?lang=en&_oob="));
+        assert_eq!(parse_invitation(&url).unwrap(), invitation);
+        let json = serde_json::to_string(&invitation).unwrap();
+        assert_eq!(parse_invitation(&json).unwrap(), invitation);
+        assert!(matches!(
+            parse_invitation("https://example.org/invite"),
+            Err(DIDCommError::InvalidInvitation(_))
+        ));
+        let other = Message::new(FORWARD, json!({}));
+        assert!(parse_invitation(&serde_json::to_string(&other).unwrap()).is_err());
+    }
+
+    #[test]
+    fn service_routing_keys() {
+        let service = json!({
+            "type": SERVICE_TYPE,
+            "serviceEndpoint": {
+                "uri": "https://mediator.example/",
+                "routingKeys": ["did:example:mediator#key-1"]
+            }
+        });
+        let endpoint = ServiceEndpoint::from_value(&service["serviceEndpoint"], &service).unwrap();
+        assert_eq!(endpoint.uri, "https://mediator.example/");
+        assert_eq!(endpoint.routing_keys, vec!["did:example:mediator#key-1"]);
+    }
+
+    #[test]
+    fn pack_unpack_forward() {
+        let key = JWK::generate_ed25519().unwrap();
+        let inner = "eyJhbGciOiJFQ0RILUVTIn0..aXY.Y3Q.dGFn";
+        let message = forward("did:example:agent", inner, "did:example:mediator");
+        let plaintext = serde_json::to_vec(&message).unwrap();
+        let envelope = jwe::encrypt(&plaintext, &key.to_public(), None, None).unwrap();
+        let unpacked = unpack(&envelope, &key).unwrap();
+        assert_eq!(unpacked, message);
+        let (next, forwarded) = forwarded_envelope(&unpacked).unwrap();
+        assert_eq!(next, "did:example:agent");
+        assert_eq!(forwarded, inner);
+    }
+}
@@ -0,0 +1,517 @@
+//! [Encrypted Data Vault][edv] (DIF Confidential Storage) client.
+//!
+//! Documents are encrypted client-side with [`crate::jwe`] before they are sent to the vault, and
+//! indexed using blinded (HMAC) attribute names and values, so that the storage provider learns
+//! neither the content of a document nor the attributes it can be queried by. Requests are
+//! authorized with an OAuth bearer token, or by invoking an authorization capability (ZCAP) with
+//! an HTTP signature.
+//!
+//! [edv]: https://identity.foundation/confidential-storage/
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use rand::RngCore;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, LOCATION};
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use ssi::jwk::{Base64urlUInt, Params, SymmetricParams};
+use thiserror::Error;
+
+use crate::jwe::{self, JWEError};
+use crate::{DIDResolver, JWK};
+
+/// Verification method type of vault HMAC keys
+pub const HMAC_KEY_TYPE: &str = "Sha256HmacKey2019";
+/// Verification method type of vault key agreement keys
+pub const KEY_AGREEMENT_KEY_TYPE: &str = "X25519KeyAgreementKey2019";
+
+#[derive(Error, Debug)]
+pub enum EDVError {
+    #[error("HTTP: {0}")]
+    HTTP(#[from] reqwest::Error),
+    #[error("Vault returned HTTP status {0}: {1}")]
+    Status(u16, String),
+    #[error("Invalid URL: {0}")]
+    URL(#[from] url::ParseError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("JWE: {0}")]
+    JWE(#[from] JWEError),
+    #[error("Missing Location header in vault response")]
+    MissingLocation,
+    #[error("HMAC key must be a symmetric JWK with a key ID")]
+    InvalidHmacKey,
+    #[error("Missing algorithm in invocation key")]
+    MissingAlgorithm,
+    #[error("Invalid header value")]
+    InvalidHeader,
+    #[error("Unable to sign capability invocation: {0}")]
+    Sign(#[from] ssi::error::Error),
+}
+
+/// Reference to a key held by the vault controller.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KeyReference {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// Data vault configuration, submitted to create a vault.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub sequence: u64,
+    pub controller: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_id: Option<String>,
+    pub key_agreement_key: KeyReference,
+    pub hmac: KeyReference,
+}
+
+/// Blinded index attribute.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexAttribute {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+}
+
+/// Attributes indexed with one HMAC key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexedAttributes {
+    pub sequence: u64,
+    pub hmac: KeyReference,
+    pub attributes: Vec<IndexAttribute>,
+}
+
+/// JWE in general JSON serialization.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JWEJson {
+    pub protected: String,
+    pub recipients: Vec<Value>,
+    pub iv: String,
+    pub ciphertext: String,
+    pub tag: String,
+}
+
+/// Document as stored in a vault.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EncryptedDocument {
+    pub id: String,
+    pub sequence: u64,
+    #[serde(default)]
+    pub indexed: Vec<IndexedAttributes>,
+    pub jwe: JWEJson,
+}
+
+impl EncryptedDocument {
+    /// Decrypt the document using the recipient's private key.
+    pub fn decrypt(&self, key: &JWK) -> Result<Vec<u8>, EDVError> {
+        let compact = [
+            &self.jwe.protected[..],
+            "",
+            &self.jwe.iv,
+            &self.jwe.ciphertext,
+            &self.jwe.tag,
+        ]
+        .join(".");
+        let (_header, plaintext) = jwe::decrypt(&compact, key)?;
+        Ok(plaintext)
+    }
+}
+
+/// Convert a direct key agreement JWE from compact to general JSON serialization.
+fn jwe_to_json(compact: &str) -> Result<JWEJson, EDVError> {
+    let parts: Vec<&str> = compact.split('.').collect();
+    if parts.len() != 5 {
+        return Err(JWEError::InvalidParts(parts.len()).into());
+    }
+    let header: jwe::Header = serde_json::from_slice(
+        &base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD).map_err(JWEError::from)?,
+    )?;
+    Ok(JWEJson {
+        protected: parts[0].to_string(),
+        recipients: vec![json!({
+            "header": {
+                "kid": header.kid,
+                "alg": header.alg,
+            },
+            "encrypted_key": parts[1],
+        })],
+        iv: parts[2].to_string(),
+        ciphertext: parts[3].to_string(),
+        tag: parts[4].to_string(),
+    })
+}
+
+/// HMAC key used to blind index attributes.
+#[derive(Debug, Clone)]
+pub struct HmacKey {
+    pub id: String,
+    key: Vec<u8>,
+}
+
+impl HmacKey {
+    /// Use a symmetric (`oct`) JWK, identified by its `kid`.
+    pub fn from_jwk(jwk: &JWK) -> Result<Self, EDVError> {
+        let id = jwk.key_id.clone().ok_or(EDVError::InvalidHmacKey)?;
+        match jwk.params {
+            Params::Symmetric(ref params) => match params.key_value {
+                Some(ref key) => Ok(Self {
+                    id,
+                    key: key.0.clone(),
+                }),
+                None => Err(EDVError::InvalidHmacKey),
+            },
+            _ => Err(EDVError::InvalidHmacKey),
+        }
+    }
+
+    /// Generate a random 256-bit HMAC key, as a JWK.
+    pub fn generate_jwk(id: String) -> JWK {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        JWK {
+            key_id: Some(id),
+            ..JWK::from(Params::Symmetric(SymmetricParams {
+                key_value: Some(Base64urlUInt(key.to_vec())),
+            }))
+        }
+    }
+
+    pub fn key_reference(&self) -> KeyReference {
+        KeyReference {
+            id: self.id.clone(),
+            type_: HMAC_KEY_TYPE.to_string(),
+        }
+    }
+
+    fn mac(&self, data: &[u8]) -> String {
+        // HMAC accepts keys of any length.
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).unwrap();
+        mac.update(data);
+        base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Blind an attribute. The value is blinded together with its name, so that equal values of
+    /// different attributes cannot be correlated.
+    pub fn blind(&self, name: &str, value: &Value) -> IndexAttribute {
+        let mut named_value = BTreeMap::new();
+        named_value.insert(name, value);
+        let named_value = serde_json::to_string(&named_value).unwrap_or_default();
+        IndexAttribute {
+            name: self.mac(name.as_bytes()),
+            value: self.mac(named_value.as_bytes()),
+            unique: false,
+        }
+    }
+}
+
+/// Authorization for vault requests.
+#[derive(Debug, Clone)]
+pub enum Authorization {
+    None,
+    /// OAuth 2.0 bearer token
+    Bearer(String),
+    /// Invoke a capability with an HTTP signature.
+    Capability {
+        /// Capability ID; if `None`, the root capability of the requested URL is invoked.
+        capability: Option<String>,
+        /// Verification method of the invoker
+        invoker: String,
+        key: JWK,
+    },
+}
+
+/// Generate a random document or vault ID: 128 bits, multibase base58btc.
+pub fn generate_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    "z".to_string() + &bs58::encode(bytes).into_string()
+}
+
+/// Client for one vault.
+#[derive(Debug, Clone)]
+pub struct EDVClient {
+    pub vault_url: Url,
+    authorization: Authorization,
+    http: reqwest::Client,
+}
+
+impl EDVClient {
+    pub fn new(vault_url: &str, authorization: Authorization) -> Result<Self, EDVError> {
+        Ok(Self {
+            vault_url: Url::parse(vault_url.trim_end_matches('/'))?,
+            authorization,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Create a vault at the given EDV server.
+    pub async fn create_vault(
+        server_url: &str,
+        config: &VaultConfiguration,
+        authorization: Authorization,
+    ) -> Result<Self, EDVError> {
+        let client = Self::new(server_url, authorization)?;
+        let url = client.url("edvs")?;
+        let response = client
+            .send(
+                Method::POST,
+                url.clone(),
+                Some(serde_json::to_value(config)?),
+                "write",
+            )
+            .await?;
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(EDVError::MissingLocation)?;
+        Ok(Self {
+            vault_url: url.join(location)?,
+            ..client
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, EDVError> {
+        Ok(Url::parse(&format!("{}/{}", self.vault_url, path))?)
+    }
+
+    /// Encrypt a document to a DID and insert it, indexed by the given attributes. Returns the
+    /// new document's ID.
+    pub async fn insert(
+        &self,
+        document: &[u8],
+        cty: Option<String>,
+        recipient: &str,
+        hmac: &HmacKey,
+        attributes: &[(String, Value)],
+        resolver: &dyn DIDResolver,
+    ) -> Result<String, EDVError> {
+        let compact = jwe::encrypt_to_did(document, recipient, cty, resolver).await?;
+        let id = generate_id();
+        let encrypted = EncryptedDocument {
+            id: id.clone(),
+            sequence: 0,
+            indexed: vec![IndexedAttributes {
+                sequence: 0,
+                hmac: hmac.key_reference(),
+                attributes: attributes
+                    .iter()
+                    .map(|(name, value)| hmac.blind(name, value))
+                    .collect(),
+            }],
+            jwe: jwe_to_json(&compact)?,
+        };
+        self.send(
+            Method::POST,
+            self.url("documents")?,
+            Some(serde_json::to_value(&encrypted)?),
+            "write",
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// Get a document by ID.
+    pub async fn get(&self, id: &str) -> Result<EncryptedDocument, EDVError> {
+        let url = self.url(&format!("documents/{}", id))?;
+        let response = self.send(Method::GET, url, None, "read").await?;
+        Ok(response.json().await?)
+    }
+
+    /// Find documents with an attribute of the given value.
+    pub async fn query(
+        &self,
+        hmac: &HmacKey,
+        name: &str,
+        value: &Value,
+    ) -> Result<Vec<EncryptedDocument>, EDVError> {
+        let attribute = hmac.blind(name, value);
+        let mut equals = serde_json::Map::new();
+        equals.insert(attribute.name, Value::String(attribute.value));
+        let query = json!({
+            "index": hmac.id,
+            "equals": [equals],
+        });
+        let response = self
+            .send(Method::POST, self.url("query")?, Some(query), "read")
+            .await?;
+        // Servers return either an array of documents or an object with a documents array.
+        let documents = match response.json().await? {
+            Value::Object(mut object) => object.remove("documents").unwrap_or_default(),
+            documents => documents,
+        };
+        Ok(serde_json::from_value(documents)?)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<Value>,
+        action: &str,
+    ) -> Result<reqwest::Response, EDVError> {
+        let body = body.map(|body| serde_json::to_vec(&body)).transpose()?;
+        let mut request = self.http.request(method.clone(), url.clone());
+        if body.is_some() {
+            request = request.header(CONTENT_TYPE, "application/json");
+        }
+        match self.authorization {
+            Authorization::None => {}
+            Authorization::Bearer(ref token) => request = request.bearer_auth(token),
+            Authorization::Capability {
+                ref capability,
+                ref invoker,
+                ref key,
+            } => {
+                let headers = invocation_headers(
+                    &method,
+                    &url,
+                    body.as_deref(),
+                    capability.as_deref(),
+                    action,
+                    invoker,
+                    key,
+                )?;
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+            }
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(EDVError::Status(status.as_u16(), text));
+        }
+        Ok(response)
+    }
+}
+
+/// Root capability ID for a URL.
+pub fn root_capability(url: &Url) -> String {
+    let target = url.as_str();
+    "urn:zcap:root:".to_string()
+        + &percent_encoding::utf8_percent_encode(target, percent_encoding::NON_ALPHANUMERIC)
+            .to_string()
+}
+
+/// Build the headers of a capability invocation using an HTTP signature.
+fn invocation_headers(
+    method: &Method,
+    url: &Url,
+    body: Option<&[u8]>,
+    capability: Option<&str>,
+    action: &str,
+    invoker: &str,
+    key: &JWK,
+) -> Result<Vec<(HeaderName, HeaderValue)>, EDVError> {
+    let algorithm = key.get_algorithm().ok_or(EDVError::MissingAlgorithm)?;
+    let capability = match capability {
+        Some(capability) => capability.to_string(),
+        None => root_capability(url),
+    };
+    let created = Utc::now().timestamp();
+    let expires = created + 600;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => String::new(),
+    };
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let invocation = format!("zcap id=\"{}\",action=\"{}\"", capability, action);
+    let mut signed = vec![
+        ("(key-id)", invoker.to_string()),
+        ("(created)", created.to_string()),
+        ("(expires)", expires.to_string()),
+        (
+            "(request-target)",
+            format!("{} {}", method.as_str().to_lowercase(), target),
+        ),
+        ("host", host.clone()),
+        ("capability-invocation", invocation.clone()),
+    ];
+    let mut headers = vec![
+        (HeaderName::from_static("host"), header_value(&host)?),
+        (
+            HeaderName::from_static("capability-invocation"),
+            header_value(&invocation)?,
+        ),
+    ];
+    if let Some(body) = body {
+        // Multihash (sha2-256), multibase base64url
+        let mut multihash = vec![0x12, 0x20];
+        multihash.extend_from_slice(&Sha256::digest(body));
+        let digest =
+            "mh=u".to_string() + &base64::encode_config(multihash, base64::URL_SAFE_NO_PAD);
+        signed.push(("content-type", "application/json".to_string()));
+        signed.push(("digest", digest.clone()));
+        headers.push((HeaderName::from_static("digest"), header_value(&digest)?));
+    }
+    let signing_string = signed
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let signature = ssi::jws::sign_bytes(algorithm, signing_string.as_bytes(), key)?;
+    let header_names = signed
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let authorization = format!(
+        "Signature keyId=\"{}\",headers=\"{}\",signature=\"{}\",created=\"{}\",expires=\"{}\"",
+        invoker,
+        header_names,
+        base64::encode(signature),
+        created,
+        expires
+    );
+    headers.push((AUTHORIZATION, header_value(&authorization)?));
+    Ok(headers)
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, EDVError> {
+    HeaderValue::from_str(value).map_err(|_| EDVError::InvalidHeader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blind_attributes() {
+        let jwk: JWK = serde_json::from_value(json!({
+            "kty": "oct",
+            "kid": "did:example:controller#hmac",
+            "k": "c2VjcmV0LWhtYWMta2V5LWZvci10ZXN0aW5nLW9ubHk"
+        }))
+        .unwrap();
+        let hmac = HmacKey::from_jwk(&jwk).unwrap();
+        let a = hmac.blind("type", &json!("VerifiableCredential"));
+        let b = hmac.blind("type", &json!("VerifiableCredential"));
+        let c = hmac.blind("issuer", &json!("VerifiableCredential"));
+        assert_eq!(a, b);
+        assert_ne!(a.name, c.name);
+        assert_ne!(a.value, c.value);
+        assert!(!a.value.contains("Verifiable"));
+
+        assert!(HmacKey::from_jwk(&JWK::generate_ed25519().unwrap()).is_err());
+    }
+}
@@ -5,16 +5,117 @@ use std::fmt;
 use std::os::raw::{c_char, c_int};
 use std::ptr;
 
-use serde_json::Error as JSONError;
+use serde::Serialize;
+use serde_json::{json, Error as JSONError, Value};
 use ssi::error::Error as SSIError;
 use std::io::Error as IOError;
 use std::str::Utf8Error;
 
 static UNKNOWN_ERROR: &str = "Unable to create error string\0";
+static UNKNOWN_ERROR_JSON: &str =
+    "{\"code\":4,\"kind\":\"borrow\",\"message\":\"Unable to create error string\"}\0";
 
 use std::cell::RefCell;
 thread_local! {
-    pub static LAST_ERROR: RefCell<Option<(i32, CString)>> = RefCell::new(None);
+    pub static LAST_ERROR: RefCell<Option<(i32, CString, CString)>> = RefCell::new(None);
+}
+
+/// Structured representation of an [`Error`], passed to SDK consumers so that they can branch on
+/// the numeric `code`, which is stable across releases, rather than on the message.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ErrorInfo {
+    pub code: i32,
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+/// Cause of an `ssi` error, in the `details` of its [`ErrorInfo`]. Errors without their own
+/// cause are `other`.
+fn ssi_cause(err: &SSIError) -> &'static str {
+    match err {
+        SSIError::InvalidSignature => "invalidSignature",
+        SSIError::MissingProof => "missingProof",
+        SSIError::KeyMismatch => "keyMismatch",
+        SSIError::VerificationMethodMismatch => "verificationMethodMismatch",
+        SSIError::AlgorithmMismatch => "algorithmMismatch",
+        SSIError::InvalidJWS => "invalidJWS",
+        SSIError::MissingKey => "missingKey",
+        SSIError::ResourceNotFound(_) => "resourceNotFound",
+        _ => "other",
+    }
+}
+
+fn io_cause(err: &IOError) -> &'static str {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::NotFound => "notFound",
+        ErrorKind::PermissionDenied => "permissionDenied",
+        ErrorKind::AlreadyExists => "alreadyExists",
+        ErrorKind::InvalidInput => "invalidInput",
+        ErrorKind::InvalidData => "invalidData",
+        ErrorKind::UnexpectedEof => "unexpectedEof",
+        ErrorKind::TimedOut => "timedOut",
+        ErrorKind::Interrupted => "interrupted",
+        ErrorKind::ConnectionRefused => "connectionRefused",
+        ErrorKind::ConnectionReset => "connectionReset",
+        _ => "other",
+    }
+}
+
+fn signer_cause(err: &crate::signer::SignerError) -> &'static str {
+    use crate::signer::SignerError;
+    match err {
+        SignerError::Sign(_) => "sign",
+        SignerError::MissingAlgorithm => "missingAlgorithm",
+        SignerError::UnsupportedSigningInputFormat => "unsupportedSigningInputFormat",
+        SignerError::Nondeterministic(_) => "nondeterministic",
+        SignerError::UnsupportedPrehash(_) => "unsupportedPrehash",
+        SignerError::SSI(_) => "ssi",
+        SignerError::JSON(_) => "json",
+        SignerError::Securing(_) => "securing",
+        SignerError::JWTClaims(_) => "jwtClaims",
+        SignerError::Suite(_) => "suite",
+        SignerError::ContentId(_) => "contentId",
+        #[cfg(feature = "format-jwp")]
+        SignerError::JWP(_) => "jwp",
+    }
+}
+
+fn options_cause(err: &crate::issue::OptionsError) -> &'static str {
+    use crate::issue::OptionsError;
+    match err {
+        OptionsError::InvalidVerificationMethod(_) => "invalidVerificationMethod",
+        OptionsError::ReservedProperty(_) => "reservedProperty",
+        OptionsError::ExpiresBeforeCreated(..) => "expiresBeforeCreated",
+        OptionsError::UnsupportedForFormat(..) => "unsupportedForFormat",
+        OptionsError::Empty(_) => "empty",
+        OptionsError::DeterministicWithoutCreated => "deterministicWithoutCreated",
+        OptionsError::UnusableProofPurpose(_) => "unusableProofPurpose",
+        OptionsError::JWTClaims(_) => "jwtClaims",
+    }
+}
+
+fn render_cause(err: &crate::render::RenderError) -> &'static str {
+    use crate::render::RenderError;
+    match err {
+        RenderError::UnknownFormat(_) => "unknownFormat",
+        RenderError::DescriptorNotFound(_) => "descriptorNotFound",
+        RenderError::NoMatchingDescriptor => "noMatchingDescriptor",
+        RenderError::InvalidCredential(_) => "invalidCredential",
+        RenderError::JSONPath(_) => "jsonPath",
+        RenderError::JSON(_) => "json",
+    }
+}
+
+fn cancel_cause(err: &crate::cancellation::CancelError) -> &'static str {
+    use crate::cancellation::CancelError;
+    match err {
+        CancelError::Cancelled => "cancelled",
+        CancelError::TimedOut => "timedOut",
+    }
 }
 
 #[derive(Debug)]
@@ -28,6 +129,11 @@ pub enum Error {
     UnknownDIDMethod,
     UnableToGetVerificationMethod,
     UnknownProofFormat(String),
+    ProofFormatMismatch(String),
+    Signer(crate::signer::SignerError),
+    Options(crate::issue::OptionsError),
+    Render(crate::render::RenderError),
+    Cancelled(crate::cancellation::CancelError),
 
     #[doc(hidden)]
     __Nonexhaustive,
@@ -35,23 +141,100 @@ pub enum Error {
 
 impl Error {
     pub fn stash(self) {
+        let info = self.info();
+        let json = serde_json::to_string(&info).unwrap_or_default();
         LAST_ERROR.with(|stash| {
             stash.replace(Some((
-                self.get_code(),
-                CString::new(self.to_string()).unwrap(),
+                info.code,
+                CString::new(info.message).unwrap(),
+                CString::new(json).unwrap(),
             )))
         });
     }
 
-    fn get_code(&self) -> c_int {
-        // TODO: try to give each individual error its own number
+    /// Numeric code of the error. Codes are never reused for a different kind of error:
+    ///
+    /// | Code | Kind |
+    /// |-----:|------|
+    /// | 1 | `ssi` |
+    /// | 2 | `nul` |
+    /// | 3 | `utf8` |
+    /// | 4 | `borrow` |
+    /// | 5 | `io` |
+    /// | 6 | `unableToGenerateDID` |
+    /// | 7 | `unknownDIDMethod` |
+    /// | 8 | `unableToGetVerificationMethod` |
+    /// | 9 | `unknownProofFormat` |
+    /// | 10 | `proofFormatMismatch` |
+    /// | 11 | `signer` |
+    /// | 12 | `options` |
+    /// | 13 | `render` |
+    /// | 14 | `cancelled` |
+    /// | -1 | `unknown` |
+    pub fn code(&self) -> c_int {
         match self {
             Error::SSI(_) => 1,
             Error::Null(_) => 2,
             Error::Utf8(_) => 3,
+            Error::Borrow(_) => 4,
+            Error::IO(_) => 5,
+            Error::UnableToGenerateDID => 6,
+            Error::UnknownDIDMethod => 7,
+            Error::UnableToGetVerificationMethod => 8,
+            Error::UnknownProofFormat(_) => 9,
+            Error::ProofFormatMismatch(_) => 10,
+            Error::Signer(_) => 11,
+            Error::Options(_) => 12,
+            Error::Render(_) => 13,
+            Error::Cancelled(_) => 14,
             _ => -1,
         }
     }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::SSI(_) => "ssi",
+            Error::Null(_) => "nul",
+            Error::Utf8(_) => "utf8",
+            Error::Borrow(_) => "borrow",
+            Error::IO(_) => "io",
+            Error::UnableToGenerateDID => "unableToGenerateDID",
+            Error::UnknownDIDMethod => "unknownDIDMethod",
+            Error::UnableToGetVerificationMethod => "unableToGetVerificationMethod",
+            Error::UnknownProofFormat(_) => "unknownProofFormat",
+            Error::ProofFormatMismatch(_) => "proofFormatMismatch",
+            Error::Signer(_) => "signer",
+            Error::Options(_) => "options",
+            Error::Render(_) => "render",
+            Error::Cancelled(_) => "cancelled",
+            _ => "unknown",
+        }
+    }
+
+    /// Machine-readable details of the error, specific to its kind.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            Error::SSI(e) => Some(json!({ "cause": ssi_cause(e) })),
+            Error::IO(e) => Some(json!({ "cause": io_cause(e) })),
+            Error::UnknownProofFormat(format) | Error::ProofFormatMismatch(format) => {
+                Some(json!({ "proofFormat": format }))
+            }
+            Error::Signer(e) => Some(json!({ "cause": signer_cause(e) })),
+            Error::Options(e) => Some(json!({ "cause": options_cause(e) })),
+            Error::Render(e) => Some(json!({ "cause": render_cause(e) })),
+            Error::Cancelled(e) => Some(json!({ "cause": cancel_cause(e) })),
+            _ => None,
+        }
+    }
+
+    pub fn info(&self) -> ErrorInfo {
+        ErrorInfo {
+            code: self.code(),
+            kind: self.kind(),
+            message: self.to_string(),
+            details: self.details(),
+        }
+    }
 }
 
 #[no_mangle]
@@ -77,7 +260,22 @@ pub extern "C" fn didkit_error_code() -> c_int {
             Some(err) => err.0,
             None => 0,
         },
-        Err(err) => Error::from(err).get_code(),
+        Err(err) => Error::from(err).code(),
+    })
+}
+
+#[no_mangle]
+/// Retrieve the most recent error encountered by a DIDKit C function as a JSON object, with
+/// properties `code`, `kind`, `message` and, depending on the kind, `details`. The returned string
+/// is valid until the next call to a DIDKit function in the current thread, and should not be
+/// mutated or freed. If there has not been any error, `NULL` is returned.
+pub extern "C" fn didkit_error_json() -> *const c_char {
+    LAST_ERROR.with(|error| match error.try_borrow() {
+        Ok(maybe_err_ref) => match &*maybe_err_ref {
+            Some(err) => err.2.as_ptr() as *const c_char,
+            None => ptr::null(),
+        },
+        Err(_) => UNKNOWN_ERROR_JSON.as_ptr() as *const c_char,
     })
 }
 
@@ -87,11 +285,20 @@ impl fmt::Display for Error {
             Error::SSI(e) => e.fmt(f),
             Error::Null(e) => e.fmt(f),
             Error::Utf8(e) => e.fmt(f),
+            Error::Borrow(e) => e.fmt(f),
+            Error::IO(e) => e.fmt(f),
             Error::UnableToGenerateDID => write!(f, "Unable to generate DID"),
             Error::UnknownDIDMethod => write!(f, "Unknown DID method"),
             Error::UnableToGetVerificationMethod => write!(f, "Unable to get verification method"),
             Error::UnknownProofFormat(format) => write!(f, "Unknown proof format: {}", format),
-            _ => unreachable!(),
+            Error::ProofFormatMismatch(format) => {
+                write!(f, "Document does not match proof format: {}", format)
+            }
+            Error::Signer(e) => e.fmt(f),
+            Error::Options(e) => e.fmt(f),
+            Error::Render(e) => e.fmt(f),
+            Error::Cancelled(e) => e.fmt(f),
+            Error::__Nonexhaustive => unreachable!(),
         }
     }
 }
@@ -108,6 +315,30 @@ impl From<JSONError> for Error {
     }
 }
 
+impl From<crate::signer::SignerError> for Error {
+    fn from(err: crate::signer::SignerError) -> Error {
+        Error::Signer(err)
+    }
+}
+
+impl From<crate::issue::OptionsError> for Error {
+    fn from(err: crate::issue::OptionsError) -> Error {
+        Error::Options(err)
+    }
+}
+
+impl From<crate::render::RenderError> for Error {
+    fn from(err: crate::render::RenderError) -> Error {
+        Error::Render(err)
+    }
+}
+
+impl From<crate::cancellation::CancelError> for Error {
+    fn from(err: crate::cancellation::CancelError) -> Error {
+        Error::Cancelled(err)
+    }
+}
+
 impl From<NulError> for Error {
     fn from(err: NulError) -> Error {
         Error::Null(err)
@@ -151,5 +382,39 @@ mod tests {
         let code = didkit_error_code();
         assert_ne!(code, 0);
         println!("code: {:?} msg: {:?}", code, msg);
+        let json = unsafe { CStr::from_ptr(didkit_error_json()) }
+            .to_str()
+            .unwrap();
+        let info: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(info["code"], code);
+        assert_eq!(info["message"], msg);
+    }
+
+    #[test]
+    fn error_info() {
+        let info = Error::UnknownProofFormat("cbor".to_string()).info();
+        assert_eq!((info.code, info.kind), (9, "unknownProofFormat"));
+        assert_eq!(info.details, Some(json!({ "proofFormat": "cbor" })));
+        let info = Error::UnknownDIDMethod.info();
+        assert_eq!((info.code, info.kind), (7, "unknownDIDMethod"));
+        assert_eq!(info.details, None);
+        assert_eq!(info.message, Error::UnknownDIDMethod.to_string());
+        let info = Error::IO(IOError::new(std::io::ErrorKind::NotFound, "missing")).info();
+        assert_eq!((info.code, info.kind), (5, "io"));
+        assert_eq!(info.message, "missing");
+        assert_eq!(info.details, Some(json!({ "cause": "notFound" })));
+        let cell = RefCell::new(());
+        let _borrowed = cell.borrow_mut();
+        let info = Error::from(cell.try_borrow().unwrap_err()).info();
+        assert_eq!((info.code, info.kind), (4, "borrow"));
+        assert!(!info.message.is_empty());
+        let info = Error::SSI(SSIError::KeyMismatch).info();
+        assert_eq!(info.details, Some(json!({ "cause": "keyMismatch" })));
+        let info = Error::Cancelled(crate::cancellation::CancelError::TimedOut).info();
+        assert_eq!(info.details, Some(json!({ "cause": "timedOut" })));
+        // The JSON of an error without details has no `details` property.
+        let json = serde_json::to_value(Error::UnableToGenerateDID.info()).unwrap();
+        assert!(json.get("details").is_none());
+        assert_eq!(json["kind"], "unableToGenerateDID");
     }
 }
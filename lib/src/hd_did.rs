@@ -0,0 +1,368 @@
+//! Hierarchical deterministic DIDs: a tree of DIDs derived from an organizational root seed.
+//!
+//! An issuer with many DIDs, e.g. one per department, service and device, need not store a
+//! private key for each: the Ed25519 key of each DID is derived from the root seed along a
+//! derivation path, with [SLIP-0010] (hardened derivation only, as for all Ed25519 keys), and the
+//! DID is the `did:key` or `did:peer:0` DID of that key. An [`HDTree`] records the name, path and
+//! DID of each node, and no private keys, so that any key can be regenerated from the seed.
+//!
+//! Nodes are named by their position in the tree, e.g. `engineering/ci/runner-1`. A node added
+//! without a path gets the next unused index under its parent, e.g. `m/0'/2'/1'`, the parent of
+//! a top-level node being the root seed (`m`).
+//!
+//! [SLIP-0010]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac, NewMac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use thiserror::Error;
+
+use ssi::did::Source;
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+
+use crate::multikey::to_multibase;
+use crate::pairwise::{ed25519_public_key, MIN_SEED_LENGTH, PAIRWISE_METHODS};
+use crate::DID_METHODS;
+
+/// HMAC key of the master key derivation of SLIP-0010 for Ed25519.
+const MASTER_KEY: &[u8] = b"ed25519 seed";
+
+/// Offset of hardened child indexes.
+pub const HARDENED: u32 = 0x8000_0000;
+
+#[derive(Error, Debug)]
+pub enum HDError {
+    #[error("Root seed is too short: {0} bytes (minimum 16)")]
+    SeedTooShort(usize),
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+    #[error("Unsupported DID method for hierarchical DIDs: {0} (expected key or peer)")]
+    UnsupportedMethod(String),
+    #[error("Invalid node name: {0}")]
+    InvalidName(String),
+    #[error("Node already exists: {0}")]
+    NodeExists(String),
+    #[error("Derivation path already used by {0}")]
+    PathExists(String),
+    #[error("Node not found: {0}")]
+    NodeNotFound(String),
+    #[error("Node {0} was not derived from this seed")]
+    SeedMismatch(String),
+    #[error("Unable to generate DID")]
+    UnableToGenerateDID,
+}
+
+/// A derivation path of hardened child indexes, e.g. `m/0'/1'`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DerivationPath(pub Vec<u32>);
+
+impl DerivationPath {
+    /// Path of a child of this path, at an index below [`HARDENED`].
+    pub fn child(&self, index: u32) -> Self {
+        let mut indexes = self.0.clone();
+        indexes.push(index);
+        Self(indexes)
+    }
+
+    /// Whether this path is a path of a child of another path.
+    pub fn is_child_of(&self, parent: &Self) -> bool {
+        self.0.len() == parent.0.len() + 1 && self.0.starts_with(&parent.0)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = HDError;
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || HDError::InvalidPath(path.to_string());
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            return Err(invalid());
+        }
+        let indexes = segments
+            .map(|segment| {
+                let index = segment
+                    .strip_suffix('\'')
+                    .or_else(|| segment.strip_suffix('h'))
+                    .ok_or_else(invalid)?;
+                match index.parse::<u32>() {
+                    Ok(index) if index < HARDENED => Ok(index),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self(indexes))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for DerivationPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        path.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// SLIP-0010 private key and chain code.
+struct ExtendedKey {
+    secret: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any size");
+        for data in data {
+            mac.update(data);
+        }
+        let output = mac.finalize().into_bytes();
+        let mut secret = [0u8; 32];
+        let mut chain_code = [0u8; 32];
+        secret.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+        Self { secret, chain_code }
+    }
+
+    fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(MASTER_KEY, &[seed])
+    }
+
+    fn child(&self, index: u32) -> Self {
+        let index = (index | HARDENED).to_be_bytes();
+        Self::from_hmac(&self.chain_code, &[&[0], &self.secret, &index])
+    }
+}
+
+/// Derive the Ed25519 key of a derivation path from a root seed.
+pub fn derive_key(seed: &[u8], path: &DerivationPath) -> Result<JWK, HDError> {
+    if seed.len() < MIN_SEED_LENGTH {
+        return Err(HDError::SeedTooShort(seed.len()));
+    }
+    let key = path
+        .0
+        .iter()
+        .fold(ExtendedKey::master(seed), |key, index| key.child(*index));
+    let public_key = ed25519_public_key(&key.secret);
+    Ok(JWK::from(Params::OKP(OctetParams {
+        curve: "Ed25519".to_string(),
+        public_key: Base64urlUInt(public_key.to_vec()),
+        private_key: Some(Base64urlUInt(key.secret.to_vec())),
+    })))
+}
+
+/// A node of a tree of hierarchical DIDs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HDNode {
+    /// Name of the node, e.g. `engineering/ci/runner-1`
+    pub name: String,
+    pub path: DerivationPath,
+    /// DID method: `key` or `peer`
+    pub method: String,
+    pub did: String,
+    /// Verification method of the key, for authentication and assertions
+    pub verification_method: String,
+}
+
+/// Derive the DID of a node and its private key.
+pub fn derive_node(
+    seed: &[u8],
+    name: &str,
+    path: DerivationPath,
+    method: &str,
+) -> Result<(HDNode, JWK), HDError> {
+    if !PAIRWISE_METHODS.contains(&method) {
+        return Err(HDError::UnsupportedMethod(method.to_string()));
+    }
+    let key = derive_key(seed, &path)?;
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, method))
+        .ok_or(HDError::UnableToGenerateDID)?;
+    let multibase = to_multibase(&key).map_err(|_| HDError::UnableToGenerateDID)?;
+    let node = HDNode {
+        name: name.to_string(),
+        path,
+        method: method.to_string(),
+        verification_method: format!("{}#{}", did, multibase),
+        did,
+    };
+    Ok((node, key))
+}
+
+/// A tree of hierarchical DIDs: the record of the derivation paths of the DIDs of a root seed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HDTree {
+    pub nodes: Vec<HDNode>,
+}
+
+impl HDTree {
+    pub fn get(&self, name: &str) -> Option<&HDNode> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Nodes whose parent is the named node, or the top-level nodes if `None`.
+    pub fn children<'a>(&'a self, name: Option<&str>) -> impl Iterator<Item = &'a HDNode> {
+        let parent = match name {
+            Some(name) => self.get(name).map(|node| node.path.clone()),
+            None => Some(DerivationPath::default()),
+        };
+        self.nodes
+            .iter()
+            .filter(move |node| parent.as_ref().map_or(false, |p| node.path.is_child_of(p)))
+    }
+
+    /// Derive and record a node, at a path or else at the next unused index under its parent,
+    /// and return it with its private key.
+    pub fn add(
+        &mut self,
+        seed: &[u8],
+        name: &str,
+        path: Option<DerivationPath>,
+        method: &str,
+    ) -> Result<(HDNode, JWK), HDError> {
+        if name.is_empty() || name.split('/').any(str::is_empty) {
+            return Err(HDError::InvalidName(name.to_string()));
+        }
+        if self.get(name).is_some() {
+            return Err(HDError::NodeExists(name.to_string()));
+        }
+        let path = match path {
+            Some(path) => path,
+            None => {
+                let parent = match name.rsplit_once('/') {
+                    Some((parent, _)) => self
+                        .get(parent)
+                        .ok_or_else(|| HDError::NodeNotFound(parent.to_string()))?
+                        .path
+                        .clone(),
+                    None => DerivationPath::default(),
+                };
+                let index = self
+                    .nodes
+                    .iter()
+                    .filter(|node| node.path.is_child_of(&parent))
+                    .filter_map(|node| node.path.0.last())
+                    .max()
+                    .map_or(0, |index| index + 1);
+                parent.child(index)
+            }
+        };
+        if let Some(node) = self.nodes.iter().find(|node| node.path == path) {
+            return Err(HDError::PathExists(node.name.clone()));
+        }
+        let (node, key) = derive_node(seed, name, path, method)?;
+        self.nodes.push(node.clone());
+        Ok((node, key))
+    }
+
+    /// Regenerate the private key of a node, checking that it was derived from the seed.
+    pub fn regenerate(&self, seed: &[u8], name: &str) -> Result<JWK, HDError> {
+        let node = self
+            .get(name)
+            .ok_or_else(|| HDError::NodeNotFound(name.to_string()))?;
+        let (derived, key) = derive_node(seed, name, node.path.clone(), &node.method)?;
+        if derived.did != node.did {
+            return Err(HDError::SeedMismatch(name.to_string()));
+        }
+        Ok(key)
+    }
+
+    /// Names of the nodes that were not derived from the seed.
+    pub fn verify(&self, seed: &[u8]) -> Result<Vec<String>, HDError> {
+        let mut mismatched = Vec::new();
+        for node in &self.nodes {
+            match self.regenerate(seed, &node.name) {
+                Ok(_) => {}
+                Err(HDError::SeedMismatch(name)) => mismatched.push(name),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(mismatched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_decode(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn derive_tree() {
+        // SLIP-0010 test vector 1 for ed25519
+        let seed = hex_decode("000102030405060708090a0b0c0d0e0f");
+        let key = derive_key(&seed, &"m/0'".parse().unwrap()).unwrap();
+        let params = match key.params {
+            Params::OKP(params) => params,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            params.private_key.unwrap().0,
+            hex_decode("68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3")
+        );
+        assert_eq!(
+            params.public_key.0,
+            hex_decode("8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c")
+        );
+
+        assert_eq!(
+            "m/1'/2h".parse::<DerivationPath>().unwrap().to_string(),
+            "m/1'/2'"
+        );
+        assert!("m/1".parse::<DerivationPath>().is_err());
+        assert!("1'/2'".parse::<DerivationPath>().is_err());
+
+        let seed = [7u8; 32];
+        let mut tree = HDTree::default();
+        tree.add(&seed, "engineering", None, "key").unwrap();
+        tree.add(&seed, "sales", None, "key").unwrap();
+        tree.add(&seed, "engineering/ci", None, "key").unwrap();
+        let (runner, _) = tree
+            .add(&seed, "engineering/ci/runner-1", None, "peer")
+            .unwrap();
+        assert_eq!(tree.get("sales").unwrap().path.to_string(), "m/1'");
+        assert_eq!(runner.path.to_string(), "m/0'/0'/0'");
+        assert!(runner.did.starts_with("did:peer:0z6Mk"));
+        assert_eq!(tree.children(None).count(), 2);
+        assert_eq!(tree.children(Some("engineering")).count(), 1);
+        assert!(matches!(
+            tree.add(&seed, "marketing/web", None, "key"),
+            Err(HDError::NodeNotFound(_))
+        ));
+        assert!(matches!(
+            tree.add(&seed, "support", Some("m/1'".parse().unwrap()), "key"),
+            Err(HDError::PathExists(_))
+        ));
+
+        let key = tree.regenerate(&seed, "engineering/ci/runner-1").unwrap();
+        assert_eq!(
+            key,
+            derive_key(&seed, &"m/0'/0'/0'".parse().unwrap()).unwrap()
+        );
+        assert!(tree.verify(&seed).unwrap().is_empty());
+        assert_eq!(tree.verify(&[8u8; 32]).unwrap().len(), 4);
+    }
+}
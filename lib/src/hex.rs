@@ -0,0 +1,28 @@
+//! Hex encoding, and random IDs in hex.
+
+use rand::RngCore;
+
+/// Encode bytes in lowercase hex.
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Generate a random ID: 128 bits, in hex.
+pub fn random_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    encode(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_hex() {
+        assert_eq!(encode(&[0x00, 0x0f, 0xa0, 0xff]), "000fa0ff");
+        let id = random_id();
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, random_id());
+    }
+}
@@ -0,0 +1,317 @@
+//! Localized verification messages.
+//!
+//! A [`Catalog`] maps message keys to templates in one locale. Keys are `check.<check>` for the
+//! checks of a [`VerificationReport`], and `error.<code>` and `warning.<code>` for its errors and
+//! warnings, by their camelCase code, e.g. `error.revoked`. In a template, `{message}` is replaced
+//! by the detailed message of the error or warning, which is not localized. Keys missing from a
+//! catalog fall back to the built-in English catalog.
+//!
+//! Catalogs are JSON objects of keys and templates, loaded at runtime, e.g. from a directory of
+//! `<locale>.json` files with [`Catalogs::load_dir`].
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::verification::{Check, VerificationError, VerificationReport, VerificationWarning};
+
+/// Locale of the built-in catalog.
+pub const DEFAULT_LOCALE: &str = "en";
+
+const DEFAULT_MESSAGES: &[(&str, &str)] = &[
+    ("check.proof", "Proof"),
+    ("check.status", "Credential status"),
+    (
+        "error.invalidDocument",
+        "The document is not valid: {message}",
+    ),
+    ("error.noApplicableProof", "No applicable proof: {message}"),
+    (
+        "error.invalidSignature",
+        "The signature is not valid: {message}",
+    ),
+    (
+        "error.proofPurposeMismatch",
+        "Unexpected proof purpose: {message}",
+    ),
+    (
+        "error.verificationMethodMismatch",
+        "Unexpected verification method: {message}",
+    ),
+    ("error.challengeMismatch", "Unexpected challenge: {message}"),
+    ("error.domainMismatch", "Unexpected domain: {message}"),
+    ("error.resolutionFailed", "Unable to resolve DID: {message}"),
+    ("error.expired", "Expired: {message}"),
+    ("error.invalidJWT", "Invalid JWT: {message}"),
+    (
+        "error.disallowedDIDMethod",
+        "DID method not allowed: {message}",
+    ),
+    ("error.notYetValid", "Not yet valid: {message}"),
+    (
+        "error.missingExpiration",
+        "Missing expiration date: {message}",
+    ),
+    (
+        "error.holderBindingMismatch",
+        "Credential not bound to the holder: {message}",
+    ),
+    (
+        "error.untrustedCertificate",
+        "Issuer certificate not trusted: {message}",
+    ),
+    ("error.revoked", "Revoked or suspended: {message}"),
+    ("error.statusUnavailable", "Status unavailable: {message}"),
+    ("error.contextIntegrity", "Context integrity: {message}"),
+    ("error.disallowedProof", "Proof not accepted: {message}"),
+    ("error.credentialSchema", "Credential schema: {message}"),
+    (
+        "error.subjectMismatch",
+        "Unexpected credential subject: {message}",
+    ),
+    ("error.missingChallenge", "Missing challenge: {message}"),
+    ("error.missingDomain", "Missing domain: {message}"),
+    (
+        "error.unauthorizedVerificationMethod",
+        "Verification method not authorized: {message}",
+    ),
+    (
+        "error.relatedResourceIntegrity",
+        "Related resource integrity: {message}",
+    ),
+    (
+        "error.verificationMethodExpired",
+        "Verification method expired: {message}",
+    ),
+    (
+        "error.verificationMethodRevoked",
+        "Verification method revoked: {message}",
+    ),
+    (
+        "error.statusListMismatch",
+        "Status list mismatch: {message}",
+    ),
+    ("error.other", "{message}"),
+    ("warning.missingExpiration", "No expiration date: {message}"),
+    ("warning.other", "{message}"),
+];
+
+#[derive(Error, Debug)]
+pub enum I18nError {
+    #[error("IO: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Invalid catalog file name: {0}")]
+    InvalidFileName(String),
+}
+
+/// Message templates of one locale.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    pub locale: String,
+    pub messages: HashMap<String, String>,
+}
+
+/// Messages of a verification report in one locale, in the order of the report's checks, errors
+/// and warnings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedReport {
+    pub locale: String,
+    pub checks: Vec<String>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Name of a serializable enum value, e.g. `revoked` for [`ErrorCode::Revoked`].
+///
+/// [`ErrorCode::Revoked`]: crate::verification::ErrorCode::Revoked
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+impl Catalog {
+    /// Create a catalog from a JSON object of keys and templates.
+    pub fn from_json(locale: &str, json: &str) -> Result<Self, I18nError> {
+        Ok(Self {
+            locale: locale.to_string(),
+            messages: serde_json::from_str(json)?,
+        })
+    }
+
+    /// The built-in English catalog.
+    pub fn default_catalog() -> Self {
+        Self {
+            locale: DEFAULT_LOCALE.to_string(),
+            messages: DEFAULT_MESSAGES
+                .iter()
+                .map(|(key, template)| (key.to_string(), template.to_string()))
+                .collect(),
+        }
+    }
+
+    fn template(&self, key: &str) -> Option<&str> {
+        if let Some(template) = self.messages.get(key) {
+            return Some(template);
+        }
+        DEFAULT_MESSAGES
+            .iter()
+            .find(|(default_key, _)| *default_key == key)
+            .map(|(_, template)| *template)
+    }
+
+    /// Render the message of a key, replacing `{message}` with `message`.
+    pub fn render(&self, key: &str, message: &str) -> String {
+        match self.template(key) {
+            Some(template) => template.replace("{message}", message),
+            None => message.to_string(),
+        }
+    }
+
+    pub fn check(&self, check: &Check) -> String {
+        let name = name(check);
+        self.render(&format!("check.{}", name), &name)
+    }
+
+    pub fn error(&self, error: &VerificationError) -> String {
+        self.render(&format!("error.{}", name(&error.code)), &error.message)
+    }
+
+    pub fn warning(&self, warning: &VerificationWarning) -> String {
+        self.render(
+            &format!("warning.{}", name(&warning.code)),
+            &warning.message,
+        )
+    }
+
+    /// Render the checks, errors and warnings of a verification report.
+    pub fn localize(&self, report: &VerificationReport) -> LocalizedReport {
+        LocalizedReport {
+            locale: self.locale.clone(),
+            checks: report
+                .checks
+                .iter()
+                .map(|check| self.check(check))
+                .collect(),
+            errors: report
+                .errors
+                .iter()
+                .map(|error| self.error(error))
+                .collect(),
+            warnings: report
+                .warnings
+                .iter()
+                .map(|warning| self.warning(warning))
+                .collect(),
+        }
+    }
+}
+
+/// Catalogs of several locales.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogs {
+    catalogs: HashMap<String, Catalog>,
+}
+
+impl Catalogs {
+    /// Create a set of catalogs containing the built-in English catalog.
+    pub fn new() -> Self {
+        let mut catalogs = Self::default();
+        catalogs.insert(Catalog::default_catalog());
+        catalogs
+    }
+
+    /// Add a catalog, replacing any catalog of the same locale.
+    pub fn insert(&mut self, catalog: Catalog) {
+        self.catalogs.insert(catalog.locale.to_lowercase(), catalog);
+    }
+
+    /// Load the `<locale>.json` files of a directory, e.g. `fr.json` or `pt-BR.json`.
+    pub fn load_dir(&mut self, path: &Path) -> Result<(), I18nError> {
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let locale = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| I18nError::InvalidFileName(path.display().to_string()))?;
+            let json = std::fs::read_to_string(&path)?;
+            self.insert(Catalog::from_json(locale, &json)?);
+        }
+        Ok(())
+    }
+
+    /// Get the catalog of a locale, or of its primary language, e.g. `pt` for `pt-BR`.
+    pub fn get(&self, locale: &str) -> Option<&Catalog> {
+        let locale = locale.trim().to_lowercase();
+        self.catalogs.get(&locale).or_else(|| {
+            let language = locale.split('-').next()?;
+            self.catalogs.get(language)
+        })
+    }
+
+    /// Choose the catalog for an `Accept-Language` header value, by preference. Returns `None` if
+    /// no catalog matches any listed language.
+    pub fn negotiate(&self, accept_language: &str) -> Option<&Catalog> {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                Some((tag, quality))
+            })
+            .filter(|(tag, quality)| !tag.is_empty() && *tag != "*" && *quality > 0.0)
+            .collect();
+        ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranges.into_iter().find_map(|(tag, _)| self.get(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::ErrorCode;
+
+    #[test]
+    fn localize_report() {
+        let mut catalogs = Catalogs::new();
+        catalogs.insert(
+            Catalog::from_json(
+                "fr",
+                r#"{"error.revoked": "Attestation révoquée ou suspendue : {message}"}"#,
+            )
+            .unwrap(),
+        );
+        let report = VerificationReport::error(ErrorCode::Revoked, "index 7");
+
+        let catalog = catalogs.negotiate("de-DE, fr-CA;q=0.8, en;q=0.5").unwrap();
+        assert_eq!(catalog.locale, "fr");
+        let localized = catalog.localize(&report);
+        assert_eq!(
+            localized.errors,
+            vec!["Attestation révoquée ou suspendue : index 7".to_string()]
+        );
+
+        // Keys missing from a catalog fall back to English.
+        let error = VerificationError {
+            code: ErrorCode::Expired,
+            message: "2020-01-01".to_string(),
+        };
+        assert_eq!(catalog.error(&error), "Expired: 2020-01-01");
+        assert!(catalogs.negotiate("de").is_none());
+        assert_eq!(catalogs.negotiate("en-GB").unwrap().locale, "en");
+    }
+}
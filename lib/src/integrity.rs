@@ -0,0 +1,646 @@
+//! Integrity of resources referenced by credentials, using the VC Data Model 2.0
+//! [`relatedResource`][related-resource] property.
+//!
+//! An issuer can pin the JSON-LD contexts of a credential by listing them in `relatedResource`
+//! with a [`digestSRI`][sri] of the context document. A verifier then checks the context
+//! documents it uses, whether supplied by the caller or fetched, against those digests, so that
+//! a context altered after issuance, e.g. one whose terms were redefined, is detected.
+//!
+//! Any other resource, e.g. an image or a document that a credential is about, can be listed
+//! likewise, with a `digestSRI` or a [`digestMultibase`][multibase] (a multibase-encoded
+//! multihash), and [checked](check_related_resources) as a whole by fetching each resource.
+//!
+//! [related-resource]: https://www.w3.org/TR/vc-data-model-2.0/#integrity-of-related-resources
+//! [sri]: https://www.w3.org/TR/SRI/
+//! [multibase]: https://www.w3.org/TR/vc-data-integrity/#multibase-0
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use thiserror::Error;
+
+#[cfg(feature = "resource-fetch")]
+use crate::observe::{self, error_message, Event, Timer};
+
+#[derive(Error, Debug)]
+pub enum IntegrityError {
+    #[error("Invalid relatedResource: {0}")]
+    InvalidRelatedResource(String),
+    #[error("Invalid digest: {0}")]
+    InvalidDigest(String),
+    #[error("Unsupported digest algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Digest mismatch for {0}")]
+    DigestMismatch(String),
+    #[error("Unable to fetch {0}: {1}")]
+    Fetch(String, String),
+    #[error("Context document not available: {0}")]
+    MissingDocument(String),
+    #[error("Invalid context loader entry for {0}: {1}")]
+    InvalidContextEntry(String, String),
+    #[error("IO: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Hash algorithm of a `digestSRI` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SriAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl SriAlgorithm {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha384 => "sha384",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha384 => Sha384::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    /// Multihash code of the algorithm.
+    fn multihash_code(&self) -> u8 {
+        match self {
+            Self::Sha256 => 0x12,
+            Self::Sha384 => 0x20,
+            Self::Sha512 => 0x13,
+        }
+    }
+
+    fn from_multihash_code(code: u8) -> Option<Self> {
+        [Self::Sha256, Self::Sha384, Self::Sha512]
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.multihash_code() == code)
+    }
+}
+
+impl std::str::FromStr for SriAlgorithm {
+    type Err = IntegrityError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(Self::Sha256),
+            "sha384" => Ok(Self::Sha384),
+            "sha512" => Ok(Self::Sha512),
+            _ => Err(IntegrityError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// Compute a `digestSRI` value, e.g. `sha384-…`.
+pub fn digest_sri(data: &[u8], algorithm: SriAlgorithm) -> String {
+    format!(
+        "{}-{}",
+        algorithm.prefix(),
+        base64::encode(algorithm.digest(data))
+    )
+}
+
+/// Check whether data matches a `digestSRI` value. As in Subresource Integrity, the value may
+/// list several space-separated digests, of which one must match.
+pub fn check_digest_sri(digest_sri: &str, data: &[u8]) -> Result<bool, IntegrityError> {
+    let mut supported = false;
+    for digest in digest_sri.split_whitespace() {
+        let (algorithm, expected) = match digest.find('-') {
+            Some(i) => (&digest[..i], &digest[i + 1..]),
+            None => return Err(IntegrityError::InvalidDigest(digest.to_string())),
+        };
+        let algorithm = match algorithm.parse::<SriAlgorithm>() {
+            Ok(algorithm) => algorithm,
+            Err(_) => continue,
+        };
+        supported = true;
+        let expected = base64::decode(expected)
+            .map_err(|_| IntegrityError::InvalidDigest(digest.to_string()))?;
+        if algorithm.digest(data) == expected {
+            return Ok(true);
+        }
+    }
+    if !supported {
+        return Err(IntegrityError::UnsupportedAlgorithm(digest_sri.to_string()));
+    }
+    Ok(false)
+}
+
+/// Compute a `digestMultibase` value: the multihash of the data, base64url-encoded (`u…`).
+pub fn digest_multibase(data: &[u8], algorithm: SriAlgorithm) -> String {
+    let digest = algorithm.digest(data);
+    let mut multihash = vec![algorithm.multihash_code(), digest.len() as u8];
+    multihash.extend(digest);
+    format!(
+        "u{}",
+        base64::encode_config(multihash, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+/// Check whether data matches a `digestMultibase` value, base64url- (`u…`) or base58btc-encoded
+/// (`z…`).
+pub fn check_digest_multibase(digest_multibase: &str, data: &[u8]) -> Result<bool, IntegrityError> {
+    let invalid = || IntegrityError::InvalidDigest(digest_multibase.to_string());
+    let multihash = match digest_multibase.chars().next() {
+        Some('u') => base64::decode_config(&digest_multibase[1..], base64::URL_SAFE_NO_PAD)
+            .map_err(|_| invalid())?,
+        Some('z') => bs58::decode(&digest_multibase[1..])
+            .into_vec()
+            .map_err(|_| invalid())?,
+        _ => return Err(invalid()),
+    };
+    let (code, length, expected) = match multihash.as_slice() {
+        [code, length, expected @ ..] if *length as usize == expected.len() => {
+            (*code, *length, expected)
+        }
+        _ => return Err(invalid()),
+    };
+    let algorithm = SriAlgorithm::from_multihash_code(code).ok_or_else(|| {
+        IntegrityError::UnsupportedAlgorithm(format!("multihash 0x{:02x}/{}", code, length))
+    })?;
+    Ok(algorithm.digest(data) == expected)
+}
+
+/// Digest property of `relatedResource` entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFormat {
+    /// `digestSRI`
+    SRI,
+    /// `digestMultibase`
+    Multibase,
+}
+
+impl std::str::FromStr for DigestFormat {
+    type Err = IntegrityError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sri" => Ok(Self::SRI),
+            "multibase" => Ok(Self::Multibase),
+            _ => Err(IntegrityError::UnsupportedAlgorithm(format!(
+                "{} (expected sri or multibase)",
+                s
+            ))),
+        }
+    }
+}
+
+/// An entry of the `relatedResource` property.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedResource {
+    pub id: String,
+    #[serde(rename = "digestSRI", skip_serializing_if = "Option::is_none")]
+    pub digest_sri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_multibase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+}
+
+impl RelatedResource {
+    /// Describe a resource by its `digestSRI`, using SHA-384.
+    pub fn new(id: &str, data: &[u8]) -> Self {
+        Self {
+            id: id.to_string(),
+            digest_sri: Some(digest_sri(data, SriAlgorithm::Sha384)),
+            digest_multibase: None,
+            media_type: None,
+        }
+    }
+
+    /// Describe a resource by digests of the given formats, using SHA-384 for `digestSRI` and
+    /// SHA-256 for `digestMultibase`.
+    pub fn with_digests(id: &str, data: &[u8], formats: &[DigestFormat]) -> Self {
+        let digest = |format| formats.contains(&format);
+        Self {
+            id: id.to_string(),
+            digest_sri: Some(digest_sri(data, SriAlgorithm::Sha384))
+                .filter(|_| digest(DigestFormat::SRI)),
+            digest_multibase: Some(digest_multibase(data, SriAlgorithm::Sha256))
+                .filter(|_| digest(DigestFormat::Multibase)),
+            media_type: None,
+        }
+    }
+
+    pub fn has_digest(&self) -> bool {
+        self.digest_sri.is_some() || self.digest_multibase.is_some()
+    }
+
+    /// Check data against the digests of the entry, each of which must match.
+    pub fn check(&self, data: &[u8]) -> Result<(), IntegrityError> {
+        if !self.has_digest() {
+            return Err(IntegrityError::InvalidRelatedResource(format!(
+                "no digest for {}",
+                self.id
+            )));
+        }
+        let matches = match self.digest_sri {
+            Some(ref digest_sri) => check_digest_sri(digest_sri, data)?,
+            None => true,
+        } && match self.digest_multibase {
+            Some(ref digest_multibase) => check_digest_multibase(digest_multibase, data)?,
+            None => true,
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(IntegrityError::DigestMismatch(self.id.clone()))
+        }
+    }
+}
+
+/// Get the `relatedResource` entries of a credential or presentation.
+pub fn related_resources(document: &Value) -> Result<Vec<RelatedResource>, IntegrityError> {
+    let value = match document.get("relatedResource") {
+        Some(Value::Array(resources)) => Value::Array(resources.clone()),
+        Some(resource) => Value::Array(vec![resource.clone()]),
+        None => return Ok(Vec::new()),
+    };
+    serde_json::from_value(value).map_err(|e| IntegrityError::InvalidRelatedResource(e.to_string()))
+}
+
+/// Add entries to the `relatedResource` property of a credential or presentation, replacing any
+/// existing entries with the same `id`.
+pub fn embed_related_resources(
+    document: &mut Value,
+    resources: &[RelatedResource],
+) -> Result<(), IntegrityError> {
+    let mut entries = related_resources(document)?;
+    for resource in resources {
+        entries.retain(|entry| entry.id != resource.id);
+        entries.push(resource.clone());
+    }
+    let entries = serde_json::to_value(entries)
+        .map_err(|e| IntegrityError::InvalidRelatedResource(e.to_string()))?;
+    match document.as_object_mut() {
+        Some(object) => {
+            object.insert("relatedResource".to_string(), entries);
+            Ok(())
+        }
+        None => Err(IntegrityError::InvalidRelatedResource(
+            "document is not an object".to_string(),
+        )),
+    }
+}
+
+/// URLs of the remote contexts in `@context`.
+fn context_urls(document: &Value) -> Vec<&str> {
+    match document.get("@context") {
+        Some(Value::String(url)) => vec![url],
+        Some(Value::Array(contexts)) => contexts.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fetch a resource over HTTPS, or, with the `ipfs` feature, an `ipfs://` URL from the configured
+/// [gateway](crate::ipfs::set_gateway), checked against its CID.
+#[cfg(feature = "resource-fetch")]
+pub async fn fetch(url: &str) -> Result<Vec<u8>, IntegrityError> {
+    let error = |e: reqwest::Error| IntegrityError::Fetch(url.to_string(), e.to_string());
+    if url.starts_with("ipfs://") {
+        return fetch_ipfs(url).await;
+    }
+    if !url.starts_with("https://") {
+        return Err(IntegrityError::Fetch(
+            url.to_string(),
+            "expected HTTPS URL".to_string(),
+        ));
+    }
+    let response = reqwest::get(url)
+        .await
+        .map_err(error)?
+        .error_for_status()
+        .map_err(error)?;
+    Ok(response.bytes().await.map_err(error)?.to_vec())
+}
+
+#[cfg(feature = "ipfs")]
+async fn fetch_ipfs(url: &str) -> Result<Vec<u8>, IntegrityError> {
+    crate::ipfs::gateway()
+        .fetch(url)
+        .await
+        .map_err(|e| IntegrityError::Fetch(url.to_string(), e.to_string()))
+}
+
+#[cfg(all(feature = "resource-fetch", not(feature = "ipfs")))]
+async fn fetch_ipfs(url: &str) -> Result<Vec<u8>, IntegrityError> {
+    Err(IntegrityError::Fetch(
+        url.to_string(),
+        "ipfs feature not enabled".to_string(),
+    ))
+}
+
+/// Check the contexts of a credential or presentation that have a digest in its
+/// `relatedResource` entries, and return their URLs.
+///
+/// Context documents are taken from `documents`, by URL. With the `resource-fetch` feature, other
+/// context documents are fetched; without it, they must be supplied.
+pub async fn check_contexts(
+    document: &Value,
+    documents: Option<&HashMap<String, String>>,
+) -> Result<Vec<String>, IntegrityError> {
+    let resources = related_resources(document)?;
+    let mut checked = Vec::new();
+    for url in context_urls(document) {
+        let resource = match resources
+            .iter()
+            .find(|resource| resource.id == url && resource.has_digest())
+        {
+            Some(resource) => resource,
+            None => continue,
+        };
+        let data = match documents.and_then(|documents| documents.get(url)) {
+            Some(body) => body.as_bytes().to_vec(),
+            #[cfg(feature = "resource-fetch")]
+            None => fetch_context(url).await?,
+            #[cfg(not(feature = "resource-fetch"))]
+            None => return Err(IntegrityError::MissingDocument(url.to_string())),
+        };
+        resource.check(&data)?;
+        checked.push(url.to_string());
+    }
+    Ok(checked)
+}
+
+/// Outcome of checking a `relatedResource` entry.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceStatus {
+    /// The resource matches its digests.
+    Verified,
+    /// The resource does not match a digest.
+    Mismatch,
+    /// The resource could not be obtained.
+    Unavailable,
+    /// The entry has no digest, or an invalid or unsupported one.
+    Invalid,
+}
+
+/// Result of checking a `relatedResource` entry against the resource.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceCheck {
+    pub id: String,
+    pub status: ResourceStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Check every `relatedResource` entry of a credential or presentation against its resource.
+///
+/// Resources are taken from `documents`, by URL. With the `resource-fetch` feature, other
+/// resources are fetched; without it, they are reported as unavailable.
+pub async fn check_related_resources(
+    document: &Value,
+    documents: Option<&HashMap<String, String>>,
+) -> Result<Vec<ResourceCheck>, IntegrityError> {
+    let mut checks = Vec::new();
+    for resource in related_resources(document)? {
+        let data = match documents.and_then(|documents| documents.get(&resource.id)) {
+            Some(body) => Ok(body.as_bytes().to_vec()),
+            #[cfg(feature = "resource-fetch")]
+            None => fetch(&resource.id).await,
+            #[cfg(not(feature = "resource-fetch"))]
+            None => Err(IntegrityError::MissingDocument(resource.id.clone())),
+        };
+        let result = data
+            .map_err(|err| (ResourceStatus::Unavailable, err))
+            .and_then(|data| {
+                resource.check(&data).map_err(|err| match err {
+                    IntegrityError::DigestMismatch(_) => (ResourceStatus::Mismatch, err),
+                    err => (ResourceStatus::Invalid, err),
+                })
+            });
+        checks.push(match result {
+            Ok(()) => ResourceCheck {
+                id: resource.id,
+                status: ResourceStatus::Verified,
+                message: None,
+            },
+            Err((status, err)) => ResourceCheck {
+                id: resource.id,
+                status,
+                message: Some(err.to_string()),
+            },
+        });
+    }
+    Ok(checks)
+}
+
+/// A context document to preload, e.g. from an `--additional-contexts` file: read from a file
+/// (`docBodyFilePath`), given inline (`docBody`), or fetched once (`docUrl`) and checked against
+/// its `digestSRI`, or its CID for an `ipfs://` URL.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextLoaderEntry {
+    /// URL of the context, as used in `@context`
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_body_file_path: Option<PathBuf>,
+    /// Context document, as a string, or as a JSON object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_body: Option<Value>,
+    /// URL to fetch the context document from: the context URL, or a mirror
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_url: Option<String>,
+    /// Digest of the context document. Required with `docUrl`, unless it is an `ipfs://` URL;
+    /// checked if given otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_sri: Option<String>,
+}
+
+impl ContextLoaderEntry {
+    /// Load the context document of the entry.
+    pub async fn load(&self) -> Result<String, IntegrityError> {
+        let invalid = |reason: &str| {
+            IntegrityError::InvalidContextEntry(self.url.clone(), reason.to_string())
+        };
+        let body = match (&self.doc_body_file_path, &self.doc_body, &self.doc_url) {
+            (Some(path), None, None) => std::fs::read_to_string(path)?,
+            (None, Some(Value::String(body)), None) => body.clone(),
+            (None, Some(body), None) => body.to_string(),
+            (None, None, Some(url)) => {
+                // The content of an ipfs:// URL is checked against its CID instead.
+                let content_addressed = cfg!(feature = "ipfs") && url.starts_with("ipfs://");
+                if self.digest_sri.is_none() && !content_addressed {
+                    return Err(invalid("docUrl requires digestSRI"));
+                }
+                String::from_utf8(fetch_context(url).await?)
+                    .map_err(|_| invalid("context document is not UTF-8"))?
+            }
+            _ => {
+                return Err(invalid(
+                    "expected one of docBodyFilePath, docBody or docUrl",
+                ))
+            }
+        };
+        if let Some(ref digest_sri) = self.digest_sri {
+            if !check_digest_sri(digest_sri, body.as_bytes())? {
+                return Err(IntegrityError::DigestMismatch(self.url.clone()));
+            }
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(feature = "resource-fetch")]
+async fn fetch_context(url: &str) -> Result<Vec<u8>, IntegrityError> {
+    let timer = Timer::start();
+    let result = fetch(url).await;
+    let error = error_message(&result);
+    observe::emit(|| Event::ContextFetched {
+        url,
+        error: error.as_deref(),
+        elapsed: timer.elapsed(),
+    });
+    result
+}
+
+#[cfg(not(feature = "resource-fetch"))]
+async fn fetch_context(url: &str) -> Result<Vec<u8>, IntegrityError> {
+    Err(IntegrityError::Fetch(
+        url.to_string(),
+        "resource-fetch feature not enabled".to_string(),
+    ))
+}
+
+/// Load context documents, by URL, e.g. for [`check_contexts`] or the `contextDocuments`
+/// verification option.
+pub async fn load_contexts(
+    entries: &[ContextLoaderEntry],
+) -> Result<HashMap<String, String>, IntegrityError> {
+    let mut documents = HashMap::new();
+    for entry in entries {
+        documents.insert(entry.url.clone(), entry.load().await?);
+    }
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn context_integrity() {
+        let context = r#"{"@context": {"name": "https://schema.org/name"}}"#;
+        let url = "https://example.org/contexts/v1";
+        let mut credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1", url],
+            "type": ["VerifiableCredential"],
+        });
+        embed_related_resources(
+            &mut credential,
+            &[RelatedResource::new(url, context.as_bytes())],
+        )
+        .unwrap();
+        assert!(credential["relatedResource"][0]["digestSRI"]
+            .as_str()
+            .unwrap()
+            .starts_with("sha384-"));
+
+        let rt = crate::runtime::get().unwrap();
+        let mut documents = HashMap::new();
+        documents.insert(url.to_string(), context.to_string());
+        let checked = rt
+            .block_on(check_contexts(&credential, Some(&documents)))
+            .unwrap();
+        assert_eq!(checked, vec![url.to_string()]);
+
+        documents.insert(
+            url.to_string(),
+            r#"{"@context": {"name": "https://example.org/name"}}"#.to_string(),
+        );
+        let err = rt
+            .block_on(check_contexts(&credential, Some(&documents)))
+            .unwrap_err();
+        assert!(matches!(err, IntegrityError::DigestMismatch(ref u) if u == url));
+    }
+
+    #[test]
+    fn related_resource_digests() {
+        let image = b"image data";
+        let url = "https://example.org/photo.png";
+        let resource = RelatedResource::with_digests(
+            url,
+            image,
+            &[DigestFormat::SRI, DigestFormat::Multibase],
+        );
+        let digest_multibase = resource.digest_multibase.clone().unwrap();
+        // SHA-256 multihash: 0x12 0x20, base64url "EiA…"
+        assert!(digest_multibase.starts_with("uEiA"));
+        assert!(check_digest_multibase(&digest_multibase, image).unwrap());
+        let base58 = format!(
+            "z{}",
+            bs58::encode(
+                base64::decode_config(&digest_multibase[1..], base64::URL_SAFE_NO_PAD).unwrap()
+            )
+            .into_string()
+        );
+        assert!(check_digest_multibase(&base58, image).unwrap());
+        assert!(!check_digest_multibase(&digest_multibase, b"other").unwrap());
+        assert!(check_digest_multibase("mAAAA", image).is_err());
+
+        let mut credential = json!({ "type": ["VerifiableCredential"] });
+        let unlisted = RelatedResource {
+            id: "https://example.org/missing.pdf".to_string(),
+            digest_sri: None,
+            digest_multibase: None,
+            media_type: None,
+        };
+        embed_related_resources(&mut credential, &[resource, unlisted]).unwrap();
+        let mut documents = HashMap::new();
+        documents.insert(url.to_string(), "image data".to_string());
+        documents.insert("https://example.org/missing.pdf".to_string(), String::new());
+        let rt = crate::runtime::get().unwrap();
+        let checks = rt
+            .block_on(check_related_resources(&credential, Some(&documents)))
+            .unwrap();
+        assert_eq!(checks[0].status, ResourceStatus::Verified);
+        assert_eq!(checks[1].status, ResourceStatus::Invalid);
+        documents.insert(url.to_string(), "altered".to_string());
+        let checks = rt
+            .block_on(check_related_resources(&credential, Some(&documents)))
+            .unwrap();
+        assert_eq!(checks[0].status, ResourceStatus::Mismatch);
+    }
+
+    #[test]
+    fn context_loader_entries() {
+        let context = json!({"@context": {"name": "https://schema.org/name"}});
+        let entries: Vec<ContextLoaderEntry> = serde_json::from_value(json!([
+            { "url": "https://example.org/contexts/v1", "docBody": context },
+            { "url": "https://example.org/contexts/v2", "docBody": context.to_string() },
+        ]))
+        .unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let documents = rt.block_on(load_contexts(&entries)).unwrap();
+        let body = context.to_string();
+        assert_eq!(documents["https://example.org/contexts/v1"], body);
+        assert_eq!(documents["https://example.org/contexts/v2"], body);
+
+        let entry = ContextLoaderEntry {
+            url: "https://example.org/contexts/v3".to_string(),
+            doc_url: Some("https://example.org/contexts/v3".to_string()),
+            ..Default::default()
+        };
+        let err = rt.block_on(entry.load()).unwrap_err();
+        assert!(matches!(err, IntegrityError::InvalidContextEntry(..)));
+        let entry = ContextLoaderEntry {
+            doc_body: Some(context),
+            doc_url: None,
+            digest_sri: Some(digest_sri(b"other", SriAlgorithm::Sha384)),
+            ..entry
+        };
+        let err = rt.block_on(entry.load()).unwrap_err();
+        assert!(matches!(err, IntegrityError::DigestMismatch(_)));
+    }
+}
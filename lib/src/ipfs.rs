@@ -0,0 +1,440 @@
+//! Fetching content from IPFS through HTTP gateways, with content-hash verification.
+//!
+//! An [`IpfsGateway`] fetches `ipfs://` URLs, e.g. of JSON-LD contexts and credential schemas
+//! (see [`crate::integrity::fetch`]) or of DID service endpoints, and resolves `did:ipid` DIDs. A
+//! content identifier (CID) is the hash of its content, so the gateways need not be trusted: the
+//! raw block is requested from each gateway in turn (as in the [trustless gateway][trustless]
+//! protocol) and checked against the CID before its content is used. Supported CIDs are CIDv0,
+//! and CIDv1 in base32 or base58btc, with SHA-256 hashes, of raw, JSON, DAG-JSON or single-block
+//! DAG-PB (UnixFS file) content. Paths within a CID are not supported.
+//!
+//! `did:ipid` DID documents are published under IPNS names, which are fetched from the gateway
+//! without verification of their IPNS records: only the `id` of the DID document is checked.
+//!
+//! [trustless]: https://specs.ipfs.tech/http-gateways/trustless-gateway/
+
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use ssi::did::Document;
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata, ERROR_INVALID_DID,
+    ERROR_METHOD_NOT_SUPPORTED, ERROR_NOT_FOUND, TYPE_DID_LD_JSON,
+};
+use thiserror::Error;
+
+/// Scheme of IPFS URLs.
+pub const IPFS_SCHEME: &str = "ipfs://";
+
+/// Gateway used if none is configured.
+pub const DEFAULT_GATEWAY: &str = "https://ipfs.io";
+
+const DID_IPID: &str = "did:ipid:";
+
+const CODEC_RAW: u64 = 0x55;
+const CODEC_DAG_PB: u64 = 0x70;
+const CODEC_DAG_JSON: u64 = 0x0129;
+const CODEC_JSON: u64 = 0x0200;
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IpfsError {
+    #[error("Invalid CID: {0}")]
+    InvalidCID(String),
+    #[error("Unsupported CID codec: 0x{0:x}")]
+    UnsupportedCodec(u64),
+    #[error("Unsupported multihash: 0x{0:x} (expected SHA-256)")]
+    UnsupportedHash(u64),
+    #[error("Paths within a CID are not supported: {0}")]
+    UnsupportedPath(String),
+    #[error("Not an IPFS URL: {0}")]
+    NotIPFSURL(String),
+    #[error("Invalid gateway URL: {0}")]
+    InvalidGateway(String),
+    #[error("Unable to fetch {0}: {1}")]
+    Fetch(String, String),
+    #[error("Content of {0} does not match its CID")]
+    HashMismatch(String),
+    #[error("Invalid block of {0}: {1}")]
+    InvalidBlock(String, String),
+}
+
+/// A parsed content identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CID {
+    pub version: u64,
+    /// Multicodec of the content, e.g. `0x55` for raw bytes
+    pub codec: u64,
+    /// SHA-256 digest of the block
+    pub digest: Vec<u8>,
+}
+
+/// Read an unsigned varint, returning it and the rest of the input.
+fn read_varint(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+/// Decode unpadded lowercase RFC 4648 base32.
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in s.bytes() {
+        let value = match c {
+            b'a'..=b'z' => c - b'a',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+impl CID {
+    fn read_multihash(cid: &str, data: &[u8]) -> Result<Vec<u8>, IpfsError> {
+        let invalid = || IpfsError::InvalidCID(cid.to_string());
+        let (code, data) = read_varint(data).ok_or_else(invalid)?;
+        if code != MULTIHASH_SHA2_256 {
+            return Err(IpfsError::UnsupportedHash(code));
+        }
+        let (length, digest) = read_varint(data).ok_or_else(invalid)?;
+        if length != 32 || digest.len() != 32 {
+            return Err(invalid());
+        }
+        Ok(digest.to_vec())
+    }
+
+    /// Check a block against the CID, and return its content.
+    pub fn content(&self, block: &[u8], cid: &str) -> Result<Vec<u8>, IpfsError> {
+        if Sha256::digest(block).as_slice() != &self.digest[..] {
+            return Err(IpfsError::HashMismatch(cid.to_string()));
+        }
+        match self.codec {
+            CODEC_RAW | CODEC_JSON | CODEC_DAG_JSON => Ok(block.to_vec()),
+            CODEC_DAG_PB => unixfs_file(block)
+                .map_err(|reason| IpfsError::InvalidBlock(cid.to_string(), reason.to_string())),
+            codec => Err(IpfsError::UnsupportedCodec(codec)),
+        }
+    }
+}
+
+impl FromStr for CID {
+    type Err = IpfsError;
+    fn from_str(cid: &str) -> Result<Self, Self::Err> {
+        let invalid = || IpfsError::InvalidCID(cid.to_string());
+        if cid.len() == 46 && cid.starts_with("Qm") {
+            let multihash = bs58::decode(cid).into_vec().map_err(|_| invalid())?;
+            return Ok(Self {
+                version: 0,
+                codec: CODEC_DAG_PB,
+                digest: Self::read_multihash(cid, &multihash)?,
+            });
+        }
+        let bytes = match cid.chars().next() {
+            Some('b') => base32_decode(&cid[1..]).ok_or_else(invalid)?,
+            Some('z') => bs58::decode(&cid[1..]).into_vec().map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        let (version, rest) = read_varint(&bytes).ok_or_else(invalid)?;
+        if version != 1 {
+            return Err(invalid());
+        }
+        let (codec, multihash) = read_varint(rest).ok_or_else(invalid)?;
+        Ok(Self {
+            version,
+            codec,
+            digest: Self::read_multihash(cid, multihash)?,
+        })
+    }
+}
+
+/// Fields of a protobuf message, as (field number, value) pairs, with the value of a varint field
+/// empty.
+fn protobuf_fields(mut data: &[u8]) -> Result<Vec<(u64, u64, &[u8])>, &'static str> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let (key, rest) = read_varint(data).ok_or("invalid field key")?;
+        let (number, wire_type) = (key >> 3, key & 7);
+        match wire_type {
+            0 => {
+                let (value, rest) = read_varint(rest).ok_or("invalid varint")?;
+                fields.push((number, value, &[][..]));
+                data = rest;
+            }
+            2 => {
+                let (length, rest) = read_varint(rest).ok_or("invalid length")?;
+                let length = length as usize;
+                if length > rest.len() {
+                    return Err("truncated field");
+                }
+                fields.push((number, 0, &rest[..length]));
+                data = &rest[length..];
+            }
+            _ => return Err("unsupported wire type"),
+        }
+    }
+    Ok(fields)
+}
+
+/// Content of a DAG-PB block of a UnixFS file without links.
+fn unixfs_file(block: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut data = None;
+    for (number, _, value) in protobuf_fields(block)? {
+        match number {
+            1 => data = Some(value),
+            2 => return Err("multi-block files are not supported"),
+            _ => {}
+        }
+    }
+    let mut content = Vec::new();
+    let mut file = false;
+    for (number, varint, value) in protobuf_fields(data.ok_or("missing UnixFS data")?)? {
+        match number {
+            // Raw (0) or File (2)
+            1 => file = varint == 0 || varint == 2,
+            2 => content = value.to_vec(),
+            _ => {}
+        }
+    }
+    if !file {
+        return Err("not a UnixFS file");
+    }
+    Ok(content)
+}
+
+/// Split an `ipfs://` URL, or `/ipfs/` path, into its CID and path.
+fn split_ipfs_url(url: &str) -> Result<(&str, &str), IpfsError> {
+    let rest = url
+        .strip_prefix(IPFS_SCHEME)
+        .or_else(|| url.strip_prefix("/ipfs/"))
+        .ok_or_else(|| IpfsError::NotIPFSURL(url.to_string()))?;
+    Ok(match rest.find(|c| c == '/' || c == '?' || c == '#') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    })
+}
+
+/// HTTP gateways to fetch IPFS content from, tried in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpfsGateway {
+    pub gateways: Vec<String>,
+}
+
+impl Default for IpfsGateway {
+    fn default() -> Self {
+        Self {
+            gateways: vec![DEFAULT_GATEWAY.to_string()],
+        }
+    }
+}
+
+/// Parse comma-separated gateway URLs, e.g. `https://ipfs.io,http://127.0.0.1:8080`.
+impl FromStr for IpfsGateway {
+    type Err = IpfsError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut gateways = Vec::new();
+        for gateway in s.split(',').map(str::trim) {
+            if !gateway.starts_with("https://") && !gateway.starts_with("http://") {
+                return Err(IpfsError::InvalidGateway(gateway.to_string()));
+            }
+            gateways.push(gateway.trim_end_matches('/').to_string());
+        }
+        Ok(Self { gateways })
+    }
+}
+
+impl IpfsGateway {
+    /// HTTP URL of an `ipfs://` URL at the first gateway, e.g. to redirect to.
+    pub fn gateway_url(&self, url: &str) -> Result<String, IpfsError> {
+        let (cid, path) = split_ipfs_url(url)?;
+        let gateway = self
+            .gateways
+            .first()
+            .map_or(DEFAULT_GATEWAY, String::as_str);
+        Ok(format!("{}/ipfs/{}{}", gateway, cid, path))
+    }
+
+    async fn get(url: &str, accept: &str) -> Result<Vec<u8>, String> {
+        let response = reqwest::Client::new()
+            .get(url)
+            .header("Accept", accept)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok(body.to_vec())
+    }
+
+    /// Fetch the content of an `ipfs://` URL, or `/ipfs/` path, and check it against its CID.
+    ///
+    /// A gateway whose block does not match the CID is skipped, as is one that fails.
+    pub async fn fetch(&self, url: &str) -> Result<Vec<u8>, IpfsError> {
+        let (cid_str, path) = split_ipfs_url(url)?;
+        if !path.is_empty() {
+            return Err(IpfsError::UnsupportedPath(url.to_string()));
+        }
+        let cid: CID = cid_str.parse()?;
+        let mut errors = Vec::new();
+        for gateway in &self.gateways {
+            let block_url = format!("{}/ipfs/{}?format=raw", gateway, cid_str);
+            match Self::get(&block_url, "application/vnd.ipld.raw").await {
+                Ok(block) => match cid.content(&block, cid_str) {
+                    Ok(content) => return Ok(content),
+                    Err(err) => errors.push(format!("{}: {}", gateway, err)),
+                },
+                Err(err) => errors.push(format!("{}: {}", gateway, err)),
+            }
+        }
+        Err(IpfsError::Fetch(url.to_string(), errors.join("; ")))
+    }
+
+    /// Fetch the content published under an IPNS name, without verifying the IPNS record.
+    pub async fn fetch_ipns(&self, name: &str) -> Result<Vec<u8>, IpfsError> {
+        let mut errors = Vec::new();
+        for gateway in &self.gateways {
+            let url = format!("{}/ipns/{}", gateway, name);
+            match Self::get(&url, "application/json").await {
+                Ok(content) => return Ok(content),
+                Err(err) => errors.push(format!("{}: {}", gateway, err)),
+            }
+        }
+        Err(IpfsError::Fetch(
+            format!("/ipns/{}", name),
+            errors.join("; "),
+        ))
+    }
+}
+
+fn error_output(
+    error: &str,
+) -> (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+) {
+    (
+        ResolutionMetadata {
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+}
+
+/// Resolve `did:ipid` DIDs, whose DID documents are published under the IPNS name of the DID's
+/// method-specific ID.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for IpfsGateway {
+    async fn resolve(
+        &self,
+        did: &str,
+        _input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let name = match did.strip_prefix(DID_IPID) {
+            Some(name) if !name.is_empty() && !name.contains(|c| c == ':' || c == '/') => name,
+            Some(_) => return error_output(ERROR_INVALID_DID),
+            None => return error_output(ERROR_METHOD_NOT_SUPPORTED),
+        };
+        let content = match self.fetch_ipns(name).await {
+            Ok(content) => content,
+            Err(_) => return error_output(ERROR_NOT_FOUND),
+        };
+        let document: Document = match serde_json::from_slice(&content) {
+            Ok(document) => document,
+            Err(_) => return error_output(ERROR_INVALID_DID),
+        };
+        if document.id != did {
+            return error_output(ERROR_INVALID_DID);
+        }
+        let res_meta = ResolutionMetadata {
+            content_type: Some(TYPE_DID_LD_JSON.to_string()),
+            ..Default::default()
+        };
+        (res_meta, Some(document), Some(DocumentMetadata::default()))
+    }
+}
+
+lazy_static! {
+    static ref GATEWAY: RwLock<IpfsGateway> = RwLock::new(IpfsGateway::default());
+}
+
+/// Set the gateway of the `ipfs://` URLs fetched by [`crate::integrity::fetch`], e.g. of
+/// contexts and schemas.
+pub fn set_gateway(gateway: IpfsGateway) {
+    if let Ok(mut current) = GATEWAY.write() {
+        *current = gateway;
+    }
+}
+
+/// The gateway of the `ipfs://` URLs fetched by [`crate::integrity::fetch`].
+pub fn gateway() -> IpfsGateway {
+    match GATEWAY.read() {
+        Ok(gateway) => gateway.clone(),
+        Err(_) => IpfsGateway::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cid_content() {
+        // UnixFS file of "hello\n"
+        let block = [
+            0x0a, 0x0c, 0x08, 0x02, 0x12, 0x06, b'h', b'e', b'l', b'l', b'o', b'\n', 0x18, 0x06,
+        ];
+        let v0 = "QmZULkCELmmk5XNfCgTnCyFgAVxBRBXyDHGGMVoLFLiXEN";
+        let cid: CID = v0.parse().unwrap();
+        assert_eq!((cid.version, cid.codec), (0, CODEC_DAG_PB));
+        assert_eq!(cid.content(&block, v0).unwrap(), b"hello\n");
+        assert_eq!(
+            cid.content(b"hello\n", v0),
+            Err(IpfsError::HashMismatch(v0.to_string()))
+        );
+
+        let context = br#"{"@context":{"name":"https://schema.org/name"}}"#;
+        let v1 = "bafkreihh6vf2denwaaix5dt6cha2544f5pv6sjeo3bl2oj72g7kxnchqjm";
+        let cid: CID = v1.parse().unwrap();
+        assert_eq!((cid.version, cid.codec), (1, CODEC_RAW));
+        assert_eq!(cid.content(context, v1).unwrap(), context.to_vec());
+        assert!("bafyinvalid".parse::<CID>().is_err());
+    }
+
+    #[test]
+    fn gateway_urls() {
+        let gateway: IpfsGateway = "https://gateway.example/,http://127.0.0.1:8080"
+            .parse()
+            .unwrap();
+        assert_eq!(gateway.gateways[0], "https://gateway.example");
+        assert_eq!(
+            gateway
+                .gateway_url("ipfs://bafybeicid/schema.json")
+                .unwrap(),
+            "https://gateway.example/ipfs/bafybeicid/schema.json"
+        );
+        assert!(gateway.gateway_url("https://example.org/").is_err());
+        assert!("ftp://gateway.example".parse::<IpfsGateway>().is_err());
+    }
+}
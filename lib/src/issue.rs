@@ -0,0 +1,959 @@
+//! Typed issuance options.
+//!
+//! [`ProofOptionsBuilder`] constructs validated [`IssueOptions`], as an alternative to
+//! deserializing [`JWTOrLDPOptions`] from JSON. [`issue_credential`] and [`issue_presentation`]
+//! sign using them, with any [`Signer`]. [`find_verification_method`] finds the verification
+//! method of an issuer's DID document for a signing key, so that only the issuer DID need be given.
+//! With the [`dry_run`](ProofOptionsBuilder::dry_run) option, they validate and prepare the
+//! document as for signing, and output it with a placeholder signature, without using the key.
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use ssi::ldp::LinkedDataDocument;
+use thiserror::Error;
+
+use crate::canonicalization::CanonicalizationCache;
+use crate::content_id;
+use crate::jwt_claims::{self, JWTClaimsError, JWTClaimsOptions};
+use crate::proof_purpose::{self, purpose_name};
+use crate::securing;
+use crate::signer::{self, DryRunSigner, Signer, SignerError};
+use crate::suite;
+use crate::{
+    resolve_key, CredentialOrJWT, DIDResolver, JWTOrLDPOptions, LinkedDataProofOptions,
+    PresentationOrJWT, ProofFormat, ProofPurpose, ResolutionInputMetadata, VerifiableCredential,
+    VerifiablePresentation, JWK, URI,
+};
+
+/// Proof properties that are set from the typed options and may not be given as additional
+/// properties.
+const RESERVED_PROOF_PROPERTIES: &[&str] = &[
+    "@context",
+    "type",
+    "proofPurpose",
+    "verificationMethod",
+    "created",
+    "challenge",
+    "domain",
+    "expires",
+    "nonce",
+    "jws",
+    "proofValue",
+];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OptionsError {
+    #[error("Verification method must be an absolute URI: {0}")]
+    InvalidVerificationMethod(String),
+    #[error("Proof property '{0}' must be set using its own option")]
+    ReservedProperty(String),
+    #[error("Proof expiration ({0}) must be after proof creation ({1})")]
+    ExpiresBeforeCreated(String, String),
+    #[error("Option '{0}' is not supported for proof format {1}")]
+    UnsupportedForFormat(String, ProofFormat),
+    #[error("Empty value for option '{0}'")]
+    Empty(String),
+    #[error("Option 'created' is required for deterministic issuance")]
+    DeterministicWithoutCreated,
+    #[error("Proof purpose {0} is not usable for proofs")]
+    UnusableProofPurpose(String),
+    #[error(transparent)]
+    JWTClaims(#[from] JWTClaimsError),
+}
+
+#[derive(Error, Debug)]
+pub enum KeyDiscoveryError {
+    #[error("Unable to resolve {0}: {1}")]
+    Resolution(String, String),
+    #[error("No verification method of {0} for {1} matches the key")]
+    NoMatchingKey(String, String),
+    #[error("Verification method {0} does not match the key")]
+    Mismatch(String),
+    #[error("Verification method {0} does not match the key; the key matches {1}")]
+    MismatchOther(String, String),
+}
+
+/// Validated options for issuing a credential or presentation.
+#[derive(Debug, Clone, Default)]
+pub struct IssueOptions {
+    pub ldp_options: LinkedDataProofOptions,
+    pub proof_format: ProofFormat,
+    /// Properties to add to the proof before signing, e.g. `cryptosuite` or `expires`
+    pub extra_proof_properties: Option<Map<String, Value>>,
+    /// Cache of prepared linked data proofs, to share between issuances
+    pub canonicalization_cache: Option<Arc<CanonicalizationCache>>,
+    /// Only sign with algorithms whose signatures are deterministic. With the `created` time
+    /// pinned, issuing the same document twice with the same key gives identical output.
+    pub deterministic: bool,
+    /// X.509 certificate chain of the signing key (base64 DER, leaf first), for the `x5c` header
+    /// of a JWT
+    pub certificate_chain: Option<Vec<String>>,
+    /// Mapping of the JWT claims of a credential to its properties
+    pub jwt_claims: JWTClaimsOptions,
+    /// Set the `id` of credentials to their content-addressable ID (see [`content_id`])
+    pub content_id: bool,
+    /// Prepare the document as for signing, but sign with a placeholder signature
+    /// ([`DryRunSigner`]), without using the key
+    pub dry_run: bool,
+}
+
+/// Builder for [`IssueOptions`].
+#[derive(Debug, Clone, Default)]
+pub struct ProofOptionsBuilder {
+    proof_type: Option<String>,
+    verification_method: Option<String>,
+    proof_purpose: Option<ProofPurpose>,
+    created: Option<DateTime<Utc>>,
+    challenge: Option<String>,
+    domain: Option<String>,
+    proof_format: ProofFormat,
+    expires: Option<DateTime<Utc>>,
+    nonce: Option<String>,
+    properties: Map<String, Value>,
+    canonicalization_cache: Option<Arc<CanonicalizationCache>>,
+    deterministic: bool,
+    certificate_chain: Option<Vec<String>>,
+    jwt_claims: Option<JWTClaimsOptions>,
+    content_id: bool,
+    dry_run: bool,
+}
+
+impl ProofOptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Proof suite type, e.g. `Ed25519Signature2018`. If unset, it is chosen based on the key.
+    pub fn proof_type(mut self, proof_type: impl Into<String>) -> Self {
+        self.proof_type = Some(proof_type.into());
+        self
+    }
+
+    pub fn verification_method(mut self, verification_method: impl Into<String>) -> Self {
+        self.verification_method = Some(verification_method.into());
+        self
+    }
+
+    pub fn proof_purpose(mut self, proof_purpose: ProofPurpose) -> Self {
+        self.proof_purpose = Some(proof_purpose);
+        self
+    }
+
+    /// Creation time of the proof. Defaults to the current time.
+    pub fn created(mut self, created: DateTime<Utc>) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    pub fn challenge(mut self, challenge: impl Into<String>) -> Self {
+        self.challenge = Some(challenge.into());
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub fn proof_format(mut self, proof_format: ProofFormat) -> Self {
+        self.proof_format = proof_format;
+        self
+    }
+
+    /// Data Integrity `cryptosuite` proof property.
+    pub fn cryptosuite(self, cryptosuite: impl Into<String>) -> Self {
+        self.property("cryptosuite", Value::String(cryptosuite.into()))
+    }
+
+    /// Data Integrity `expires` proof property.
+    pub fn expires(mut self, expires: DateTime<Utc>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Data Integrity `nonce` proof property, e.g. to make proofs of the same document unique.
+    pub fn nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Additional property to include in the proof.
+    pub fn property(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Reuse prepared linked data proofs of identical documents, from a shared cache. Only
+    /// options with a [`created`](Self::created) time are cached.
+    pub fn canonicalization_cache(mut self, cache: Arc<CanonicalizationCache>) -> Self {
+        self.canonicalization_cache = Some(cache);
+        self
+    }
+
+    /// Issue reproducibly: require the `created` time to be set, and reject signers with
+    /// randomized signature algorithms.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// X.509 certificate chain of the signing key (base64 DER, leaf first), to include in a JWT
+    /// as its `x5c` header.
+    pub fn certificate_chain(mut self, certificate_chain: Vec<String>) -> Self {
+        self.certificate_chain = Some(certificate_chain);
+        self
+    }
+
+    /// Which JWT claims of a credential map to its properties, and whether they replace or
+    /// duplicate them. Defaults to all claims, replacing the properties. Used when issuing a JWT
+    /// credential, and when converting one to another proof format.
+    pub fn jwt_claims(mut self, jwt_claims: JWTClaimsOptions) -> Self {
+        self.jwt_claims = Some(jwt_claims);
+        self
+    }
+
+    /// Identify credentials by their content: set their `id` to the `urn:hash` URN of the hash
+    /// of their canonical form, so that issuing the same claims again gives the same ID.
+    pub fn content_id(mut self, content_id: bool) -> Self {
+        self.content_id = content_id;
+        self
+    }
+
+    /// Do a dry run: validate and prepare the document to sign, and give it a placeholder
+    /// signature instead of signing it.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn build(self) -> Result<IssueOptions, OptionsError> {
+        if self.deterministic && self.created.is_none() {
+            return Err(OptionsError::DeterministicWithoutCreated);
+        }
+        let mut ldp_options = LinkedDataProofOptions::default();
+        if let Some(verification_method) = self.verification_method {
+            // An absolute URI has a scheme, e.g. "did:" or "https:".
+            match verification_method.find(':') {
+                Some(i) if i > 0 => {}
+                _ => {
+                    return Err(OptionsError::InvalidVerificationMethod(
+                        verification_method,
+                    ))
+                }
+            }
+            ldp_options.verification_method = Some(URI::String(verification_method));
+        }
+        for (name, value) in [
+            ("challenge", &self.challenge),
+            ("domain", &self.domain),
+            ("nonce", &self.nonce),
+        ]
+        .iter()
+        {
+            if let Some(value) = value {
+                if value.is_empty() {
+                    return Err(OptionsError::Empty(name.to_string()));
+                }
+            }
+        }
+        if let Some(proof_purpose) = self.proof_purpose {
+            if !proof_purpose::is_proof_purpose(&proof_purpose) {
+                return Err(OptionsError::UnusableProofPurpose(purpose_name(
+                    &proof_purpose,
+                )));
+            }
+            ldp_options.proof_purpose = Some(proof_purpose);
+        }
+        if let Some(created) = self.created {
+            ldp_options.created = Some(created);
+        }
+        ldp_options.challenge = self.challenge;
+        ldp_options.domain = self.domain;
+        if let Some(name) = self
+            .properties
+            .keys()
+            .find(|name| RESERVED_PROOF_PROPERTIES.contains(&&name[..]))
+        {
+            return Err(OptionsError::ReservedProperty(name.to_string()));
+        }
+        let mut properties = self.properties;
+        if let Some(expires) = self.expires {
+            let created = ldp_options.created.unwrap_or_else(Utc::now);
+            if expires <= created {
+                return Err(OptionsError::ExpiresBeforeCreated(
+                    expires.to_rfc3339(),
+                    created.to_rfc3339(),
+                ));
+            }
+            properties.insert(
+                "expires".to_string(),
+                Value::String(expires.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            );
+        }
+        if let Some(nonce) = self.nonce {
+            properties.insert("nonce".to_string(), Value::String(nonce));
+        }
+        if self.proof_format != ProofFormat::LDP {
+            if self.proof_type.is_some() {
+                return Err(OptionsError::UnsupportedForFormat(
+                    "type".to_string(),
+                    self.proof_format.clone(),
+                ));
+            }
+            if let Some(name) = properties.keys().next() {
+                return Err(OptionsError::UnsupportedForFormat(
+                    name.to_string(),
+                    self.proof_format.clone(),
+                ));
+            }
+        }
+        if let Some(ref certificate_chain) = self.certificate_chain {
+            if self.proof_format != ProofFormat::JWT {
+                return Err(OptionsError::UnsupportedForFormat(
+                    "x5c".to_string(),
+                    self.proof_format.clone(),
+                ));
+            }
+            if certificate_chain.is_empty() {
+                return Err(OptionsError::Empty("x5c".to_string()));
+            }
+        }
+        if let Some(ref jwt_claims) = self.jwt_claims {
+            jwt_claims.validate()?;
+        }
+        ldp_options.type_ = self.proof_type;
+        Ok(IssueOptions {
+            ldp_options,
+            proof_format: self.proof_format,
+            extra_proof_properties: if properties.is_empty() {
+                None
+            } else {
+                Some(properties)
+            },
+            canonicalization_cache: self.canonicalization_cache,
+            deterministic: self.deterministic,
+            certificate_chain: self.certificate_chain,
+            jwt_claims: self.jwt_claims.unwrap_or_default(),
+            content_id: self.content_id,
+            dry_run: self.dry_run,
+        })
+    }
+}
+
+impl TryFrom<JWTOrLDPOptions> for IssueOptions {
+    type Error = OptionsError;
+    fn try_from(options: JWTOrLDPOptions) -> Result<Self, Self::Error> {
+        let ldp_options = options.ldp_options.clone();
+        let mut builder = ProofOptionsBuilder::new()
+            .proof_format(options.proof_format.unwrap_or_default())
+            .deterministic(options.deterministic)
+            .content_id(options.content_id)
+            .dry_run(options.dry_run);
+        if let Some(proof_type) = ldp_options.type_ {
+            builder = builder.proof_type(proof_type);
+        }
+        if let Some(verification_method) = ldp_options.verification_method {
+            builder = builder.verification_method(verification_method.to_string());
+        }
+        if let Some(proof_purpose) = ldp_options.proof_purpose {
+            builder = builder.proof_purpose(proof_purpose);
+        }
+        if let Some(created) = ldp_options.created {
+            builder = builder.created(created);
+        }
+        if let Some(challenge) = ldp_options.challenge {
+            builder = builder.challenge(challenge);
+        }
+        if let Some(domain) = ldp_options.domain {
+            builder = builder.domain(domain);
+        }
+        if let Some(expires) = options.expires {
+            builder = builder.expires(expires);
+        }
+        if let Some(nonce) = options.nonce {
+            builder = builder.nonce(nonce);
+        }
+        for (name, value) in options.proof_properties.unwrap_or_default() {
+            builder = builder.property(name, value);
+        }
+        if let Some(jwt_claims) = options.jwt_claims {
+            builder = builder.jwt_claims(jwt_claims);
+        }
+        let mut issue_options = builder.build()?;
+        // The builder validates the options it has setters for; others, such as `checks` and
+        // `eip712Domain`, are kept as given.
+        let validated = issue_options.ldp_options;
+        issue_options.ldp_options = LinkedDataProofOptions {
+            type_: validated.type_,
+            verification_method: validated.verification_method,
+            proof_purpose: validated.proof_purpose,
+            created: validated.created,
+            challenge: validated.challenge,
+            domain: validated.domain,
+            ..options.ldp_options
+        };
+        Ok(issue_options)
+    }
+}
+
+/// Check that the signer's algorithm is deterministic, if the options require it.
+fn check_deterministic(options: &IssueOptions, signer: &dyn Signer) -> Result<(), SignerError> {
+    if !options.deterministic {
+        return Ok(());
+    }
+    let algorithm = signer.algorithm().ok_or(SignerError::MissingAlgorithm)?;
+    if signer::is_deterministic(algorithm) {
+        Ok(())
+    } else {
+        Err(SignerError::Nondeterministic(algorithm))
+    }
+}
+
+/// Generate a linked data proof, using the canonicalization cache of the options if set.
+async fn generate_proof<T>(
+    document: &T,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<ssi::vc::Proof, SignerError>
+where
+    T: LinkedDataDocument + Serialize + Sync,
+{
+    match options.canonicalization_cache {
+        Some(ref cache) => {
+            let prep = cache
+                .prepare(
+                    document,
+                    &options.ldp_options,
+                    resolver,
+                    &signer.public_jwk(),
+                    options.extra_proof_properties.clone(),
+                )
+                .await?;
+            if !signer::is_bytes_preparation(&prep) && signer.jwk().is_some() {
+                // Typed data and Micheline signing inputs are signed by the proof suite.
+                return signer::generate_proof(
+                    document,
+                    &options.ldp_options,
+                    resolver,
+                    signer,
+                    options.extra_proof_properties.clone(),
+                )
+                .await;
+            }
+            signer::complete_proof(prep, signer).await
+        }
+        None => {
+            signer::generate_proof(
+                document,
+                &options.ldp_options,
+                resolver,
+                signer,
+                options.extra_proof_properties.clone(),
+            )
+            .await
+        }
+    }
+}
+
+/// Generate a linked data proof with the registered proof suite of the proof type of the options
+/// (see [`suite`]), or with the built-in suites.
+async fn generate_ldp_proof<T>(
+    document: &T,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<ssi::vc::Proof, SignerError>
+where
+    T: LinkedDataDocument + Serialize + Sync,
+{
+    match suite::suite_for_options(&options.ldp_options) {
+        Some(suite) => {
+            let document = serde_json::to_value(document)?;
+            Ok(suite::generate_proof(&*suite, &document, options, signer).await?)
+        }
+        None => generate_proof(document, options, signer, resolver).await,
+    }
+}
+
+/// Sign JWT claims, with the certificate chain of the options, if any, as the `x5c` header.
+async fn sign_jwt<T: Serialize>(
+    claims: &T,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    let mut header = Map::new();
+    if let Some(ref certificate_chain) = options.certificate_chain {
+        header.insert("x5c".to_string(), json!(certificate_chain));
+    }
+    signer::sign_jwt_with_header(claims, &options.ldp_options, &header, signer).await
+}
+
+/// Issue a credential as a JSON Proof Token with the BBS algorithm, with the signer's BBS key,
+/// or a throwaway key for a dry run.
+#[cfg(feature = "format-jwp")]
+fn issue_jpt(
+    credential: &VerifiableCredential,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    use crate::jwp;
+    let throwaway;
+    let key = if options.dry_run {
+        throwaway = jwp::generate_bbs_key()?;
+        &throwaway
+    } else {
+        signer.jwk().ok_or_else(|| {
+            SignerError::Sign("JSON Proof Tokens need a BBS key in memory".to_string())
+        })?
+    };
+    let verification_method = options
+        .ldp_options
+        .verification_method
+        .as_ref()
+        .map(|vm| vm.to_string());
+    let credential = serde_json::to_value(credential)?;
+    let jpt = jwp::issue_credential(&credential, verification_method, &jwp::Bbs, key)?;
+    Ok(jpt.to_compact()?)
+}
+
+#[cfg(not(feature = "format-jwp"))]
+fn issue_jpt(
+    _credential: &VerifiableCredential,
+    _options: &IssueOptions,
+    _signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    Err(SignerError::Sign(
+        "JSON Proof Tokens require the format-jwp feature".to_string(),
+    ))
+}
+
+/// Issue a credential, as a credential with a linked data proof, as a JWT, secured with VC-JOSE
+/// or VC-COSE (see [`securing`]), or as a JSON Proof Token.
+pub async fn issue_credential(
+    mut credential: VerifiableCredential,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<CredentialOrJWT, SignerError> {
+    check_deterministic(options, signer)?;
+    let dry_run;
+    let signer: &dyn Signer = if options.dry_run {
+        dry_run = DryRunSigner::new(signer);
+        &dry_run
+    } else {
+        signer
+    };
+    if options.content_id {
+        content_id::set_content_id(&mut credential).await?;
+    }
+    match options.proof_format {
+        ProofFormat::JWT if options.jwt_claims == JWTClaimsOptions::default() => {
+            let claims = credential.to_jwt_claims()?;
+            let jwt = sign_jwt(&claims, options, signer).await?;
+            Ok(CredentialOrJWT::JWT(jwt))
+        }
+        ProofFormat::JWT => {
+            let credential = serde_json::to_value(&credential)?;
+            let claims = jwt_claims::encode_credential(&credential, &options.jwt_claims)?;
+            let jwt = sign_jwt(&claims, options, signer).await?;
+            Ok(CredentialOrJWT::JWT(jwt))
+        }
+        ProofFormat::VcJose | ProofFormat::VcCose => {
+            let secured = securing::secure_credential(
+                &credential,
+                &options.proof_format,
+                &options.ldp_options,
+                signer,
+            )
+            .await?;
+            Ok(CredentialOrJWT::JWT(secured))
+        }
+        ProofFormat::JPT => {
+            credential.validate_unsigned()?;
+            let jpt = issue_jpt(&credential, options, signer)?;
+            Ok(CredentialOrJWT::JWT(jpt))
+        }
+        ProofFormat::LDP => {
+            credential.validate_unsigned()?;
+            let proof = generate_ldp_proof(&credential, options, signer, resolver).await?;
+            credential.add_proof(proof);
+            Ok(CredentialOrJWT::Credential(credential))
+        }
+    }
+}
+
+/// Issue a presentation, as a presentation with a linked data proof, as a JWT, or secured with
+/// VC-JOSE or VC-COSE.
+pub async fn issue_presentation(
+    mut presentation: VerifiablePresentation,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+    resolver: &dyn DIDResolver,
+) -> Result<PresentationOrJWT, SignerError> {
+    check_deterministic(options, signer)?;
+    let dry_run;
+    let signer: &dyn Signer = if options.dry_run {
+        dry_run = DryRunSigner::new(signer);
+        &dry_run
+    } else {
+        signer
+    };
+    match options.proof_format {
+        ProofFormat::JWT => {
+            let claims = presentation.to_jwt_claims()?;
+            let jwt = sign_jwt(&claims, options, signer).await?;
+            Ok(PresentationOrJWT::JWT(jwt))
+        }
+        ProofFormat::VcJose | ProofFormat::VcCose => {
+            let secured = securing::secure_presentation(
+                &presentation,
+                &options.proof_format,
+                &options.ldp_options,
+                signer,
+            )
+            .await?;
+            Ok(PresentationOrJWT::JWT(secured))
+        }
+        ProofFormat::JPT => Err(SignerError::Sign(
+            "Presentations of JSON Proof Tokens are derived from the credential".to_string(),
+        )),
+        ProofFormat::LDP => {
+            presentation.validate_unsigned()?;
+            let proof = generate_ldp_proof(&presentation, options, signer, resolver).await?;
+            presentation.add_proof(proof);
+            Ok(PresentationOrJWT::VP(presentation))
+        }
+    }
+}
+
+/// Find the verification method of a DID's document, for a proof purpose, whose public key is
+/// that of `key`.
+pub async fn find_verification_method(
+    did: &str,
+    key: &JWK,
+    proof_purpose: &ProofPurpose,
+    resolver: &dyn DIDResolver,
+) -> Result<String, KeyDiscoveryError> {
+    let (res_meta, doc, _) = resolver
+        .resolve(did, &ResolutionInputMetadata::default())
+        .await;
+    let doc = match doc {
+        Some(doc) => serde_json::to_value(doc)
+            .map_err(|e| KeyDiscoveryError::Resolution(did.to_string(), e.to_string()))?,
+        None => {
+            return Err(KeyDiscoveryError::Resolution(
+                did.to_string(),
+                res_meta.error.unwrap_or_default(),
+            ))
+        }
+    };
+    let purpose = purpose_name(proof_purpose);
+    for id in proof_purpose::relationship_ids(&doc, did, &purpose) {
+        match resolve_key(&id, resolver).await {
+            Ok(vm_key) if vm_key.equals_public(key) => return Ok(id),
+            _ => {}
+        }
+    }
+    Err(KeyDiscoveryError::NoMatchingKey(did.to_string(), purpose))
+}
+
+/// Check that the public key of a verification method is that of `key`. On mismatch, the error
+/// names the verification method of the same DID that matches the key, if any. Verification
+/// methods without a public key, e.g. blockchain accounts, are not checked.
+pub async fn check_verification_method(
+    verification_method: &str,
+    key: &JWK,
+    proof_purpose: &ProofPurpose,
+    resolver: &dyn DIDResolver,
+) -> Result<(), KeyDiscoveryError> {
+    let vm_key = match resolve_key(verification_method, resolver).await {
+        Ok(vm_key) => vm_key,
+        Err(_) => return Ok(()),
+    };
+    if vm_key.equals_public(key) {
+        return Ok(());
+    }
+    let did = verification_method
+        .split('#')
+        .next()
+        .unwrap_or(verification_method);
+    match find_verification_method(did, key, proof_purpose, resolver).await {
+        Ok(matching) => Err(KeyDiscoveryError::MismatchOther(
+            verification_method.to_string(),
+            matching,
+        )),
+        Err(_) => Err(KeyDiscoveryError::Mismatch(verification_method.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CredentialBuilder, JWK};
+    use chrono::Duration;
+
+    #[test]
+    fn build_options() {
+        let created = Utc::now();
+        let options = ProofOptionsBuilder::new()
+            .verification_method("did:example:foo#key1")
+            .proof_purpose(ProofPurpose::AssertionMethod)
+            .created(created)
+            .cryptosuite("eddsa-2022")
+            .expires(created + Duration::days(1))
+            .build()
+            .unwrap();
+        let properties = options.extra_proof_properties.unwrap();
+        assert_eq!(properties["cryptosuite"], "eddsa-2022");
+        assert!(properties.contains_key("expires"));
+
+        let err = ProofOptionsBuilder::new()
+            .verification_method("key1")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OptionsError::InvalidVerificationMethod("key1".to_string())
+        );
+
+        let err = ProofOptionsBuilder::new()
+            .created(created)
+            .expires(created - Duration::seconds(1))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, OptionsError::ExpiresBeforeCreated(_, _)));
+
+        assert!(ProofOptionsBuilder::new()
+            .proof_purpose(ProofPurpose::CapabilityInvocation)
+            .build()
+            .is_ok());
+        let err = ProofOptionsBuilder::new()
+            .proof_purpose(ProofPurpose::KeyAgreement)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OptionsError::UnusableProofPurpose("keyAgreement".to_string())
+        );
+
+        let err = ProofOptionsBuilder::new()
+            .proof_format(ProofFormat::JWT)
+            .cryptosuite("eddsa-2022")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, OptionsError::UnsupportedForFormat(_, _)));
+
+        let err = ProofOptionsBuilder::new()
+            .property("nonce", Value::from("abc"))
+            .build()
+            .unwrap_err();
+        assert_eq!(err, OptionsError::ReservedProperty("nonce".to_string()));
+
+        let options: JWTOrLDPOptions = serde_json::from_value(json!({
+            "expires": "2030-01-01T00:00:00Z",
+            "nonce": "abc",
+            "proofProperties": { "invocationTarget": "https://example.org/" }
+        }))
+        .unwrap();
+        let options = IssueOptions::try_from(options).unwrap();
+        let properties = options.extra_proof_properties.unwrap();
+        assert_eq!(properties["expires"], "2030-01-01T00:00:00Z");
+        assert_eq!(properties["nonce"], "abc");
+        assert_eq!(properties["invocationTarget"], "https://example.org/");
+    }
+
+    #[test]
+    fn issue_options_from_request_options() {
+        let ldp_options = json!({
+            "type": "Ed25519Signature2020",
+            "verificationMethod": "did:example:foo#key1",
+            "proofPurpose": "authentication",
+            "created": "2021-01-01T00:00:00Z",
+            "challenge": "c0ae1c8e-c7e7-469f-b252-86e6a0e7387e",
+            "domain": "example.org",
+            "checks": ["proof"],
+        });
+        let mut request = ldp_options.clone();
+        request.as_object_mut().unwrap().extend(
+            json!({
+                "proofFormat": "ldp",
+                "deterministic": true,
+                "expires": "2030-01-01T00:00:00Z",
+                "nonce": "abc",
+                "proofProperties": { "invocationTarget": "https://example.org/" },
+                "jwtClaims": { "claims": ["iss", "jti"] },
+                "contentId": true,
+                "dryRun": true,
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        );
+        let options: JWTOrLDPOptions = serde_json::from_value(request).unwrap();
+        let options = IssueOptions::try_from(options).unwrap();
+        let expected: LinkedDataProofOptions = serde_json::from_value(ldp_options).unwrap();
+        assert_eq!(
+            serde_json::to_value(&options.ldp_options).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        assert_eq!(options.proof_format, ProofFormat::LDP);
+        assert!(options.deterministic);
+        assert!(options.content_id);
+        assert!(options.dry_run);
+        assert_eq!(
+            options.jwt_claims.claims,
+            Some(vec!["iss".to_string(), "jti".to_string()])
+        );
+        let properties = options.extra_proof_properties.unwrap();
+        assert_eq!(properties["expires"], "2030-01-01T00:00:00Z");
+        assert_eq!(properties["nonce"], "abc");
+        assert_eq!(properties["invocationTarget"], "https://example.org/");
+    }
+
+    #[test]
+    fn deterministic_issuance() {
+        let err = ProofOptionsBuilder::new()
+            .deterministic(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, OptionsError::DeterministicWithoutCreated);
+
+        let created = Utc::now();
+        let options = ProofOptionsBuilder::new()
+            .proof_format(ProofFormat::JWT)
+            .created(created)
+            .deterministic(true)
+            .build()
+            .unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+        let credential = CredentialBuilder::new()
+            .issuer("did:example:issuer")
+            .issuance_date(created)
+            .subject(serde_json::json!({ "id": "did:example:subject" }))
+            .build()
+            .unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let resolver = crate::DID_METHODS.to_resolver();
+        let issue = || {
+            rt.block_on(issue_credential(
+                credential.clone(),
+                &options,
+                &key,
+                resolver,
+            ))
+            .unwrap()
+        };
+        match (issue(), issue()) {
+            (CredentialOrJWT::JWT(a), CredentialOrJWT::JWT(b)) => assert_eq!(a, b),
+            _ => panic!("Expected JWTs"),
+        }
+    }
+
+    #[test]
+    fn dry_run_issuance() {
+        use crate::{DIDMethod, Source, DID_METHODS};
+
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let purpose = ProofPurpose::AssertionMethod;
+        let vm = rt
+            .block_on(find_verification_method(&did, &key, &purpose, resolver))
+            .unwrap();
+        let options = ProofOptionsBuilder::new()
+            .verification_method(vm)
+            .proof_purpose(purpose)
+            .dry_run(true)
+            .build()
+            .unwrap();
+        let credential = CredentialBuilder::new()
+            .issuer(&did)
+            .subject(json!({ "id": "did:example:subject" }))
+            .build()
+            .unwrap();
+        // Only the public key is needed.
+        let key = key.to_public();
+        let vc = match rt
+            .block_on(issue_credential(credential, &options, &key, resolver))
+            .unwrap()
+        {
+            CredentialOrJWT::Credential(vc) => vc,
+            _ => panic!("Expected credential"),
+        };
+        let proof = serde_json::to_value(vc.proof).unwrap();
+        let placeholder = base64::encode_config([0; 64], base64::URL_SAFE_NO_PAD);
+        let jws = proof["jws"].as_str().unwrap();
+        assert!(jws.ends_with(&format!("..{}", placeholder)), "{}", jws);
+    }
+    #[test]
+    fn discover_verification_method() {
+        use crate::{get_verification_method, DIDMethod, Source, DID_METHODS};
+
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let purpose = ProofPurpose::AssertionMethod;
+        let vm = rt
+            .block_on(find_verification_method(&did, &key, &purpose, resolver))
+            .unwrap();
+        assert_eq!(
+            Some(vm.clone()),
+            rt.block_on(get_verification_method(&did, resolver))
+        );
+        rt.block_on(check_verification_method(&vm, &key, &purpose, resolver))
+            .unwrap();
+
+        let other = JWK::generate_ed25519().unwrap();
+        let err = rt
+            .block_on(find_verification_method(&did, &other, &purpose, resolver))
+            .unwrap_err();
+        assert!(matches!(err, KeyDiscoveryError::NoMatchingKey(_, _)));
+        let err = rt
+            .block_on(check_verification_method(&vm, &other, &purpose, resolver))
+            .unwrap_err();
+        assert!(matches!(err, KeyDiscoveryError::Mismatch(_)));
+    }
+
+    #[test]
+    fn certificate_chain_header() {
+        let err = ProofOptionsBuilder::new()
+            .certificate_chain(vec!["AAEC".to_string()])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OptionsError::UnsupportedForFormat("x5c".to_string(), ProofFormat::LDP)
+        );
+
+        let options = ProofOptionsBuilder::new()
+            .proof_format(ProofFormat::JWT)
+            .certificate_chain(vec!["AAEC".to_string()])
+            .build()
+            .unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+        let credential = CredentialBuilder::new()
+            .issuer("did:example:issuer")
+            .subject(serde_json::json!({ "id": "did:example:subject" }))
+            .build()
+            .unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let resolver = crate::DID_METHODS.to_resolver();
+        let jwt = match rt
+            .block_on(issue_credential(credential, &options, &key, resolver))
+            .unwrap()
+        {
+            CredentialOrJWT::JWT(jwt) => jwt,
+            _ => panic!("Expected JWT"),
+        };
+        let (header, _) = crate::verification::decode_jwt_unverified(&jwt).unwrap();
+        assert_eq!(header["x5c"], json!(["AAEC"]));
+        assert_eq!(header["alg"], "EdDSA");
+    }
+}
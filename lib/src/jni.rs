@@ -1,11 +1,15 @@
+use std::convert::TryFrom;
 use std::ptr;
 
-use jni::objects::{JClass, JString};
+use jni::objects::{JClass, JObject, JString, JThrowable, JValue};
 use jni::sys::jstring;
 use jni::JNIEnv;
+use serde_json::Value;
 
 use crate::error::Error;
 use crate::get_verification_method;
+use crate::render::{self, CredentialManifest};
+use crate::resolution_error;
 use crate::runtime;
 use crate::ResolutionResult;
 use crate::Source;
@@ -14,7 +18,8 @@ use crate::VerifiablePresentation;
 use crate::DID_METHODS;
 use crate::JWK;
 use crate::{dereference, DereferencingInputMetadata, ResolutionInputMetadata};
-use crate::{JWTOrLDPOptions, ProofFormat};
+use crate::{CredentialOrJWT, PresentationOrJWT, VerificationResult};
+use crate::{IssueOptions, JWTOrLDPOptions, ProofFormat};
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 pub static DIDKIT_EXCEPTION_CLASS: &str = "com/spruceid/DIDKitException";
@@ -30,14 +35,33 @@ fn jstring_or_error(env: &JNIEnv, result: Result<jstring, Error>) -> jstring {
     match result {
         Ok(jstring) => jstring,
         Err(err) => {
-            // TODO: pass the error code into the constructor somehow
-            env.throw_new(DIDKIT_EXCEPTION_CLASS, err.to_string())
-                .unwrap();
+            throw_error(env, err);
             ptr::null_mut()
         }
     }
 }
 
+fn throw_error(env: &JNIEnv, err: Error) {
+    let info = err.info();
+    let details = match info.details {
+        Some(details) => JObject::from(env.new_string(details.to_string()).unwrap()),
+        None => JObject::null(),
+    };
+    let exception = env
+        .new_object(
+            DIDKIT_EXCEPTION_CLASS,
+            "(ILjava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
+            &[
+                JValue::Int(info.code),
+                JValue::Object(env.new_string(info.kind).unwrap().into()),
+                JValue::Object(env.new_string(info.message).unwrap().into()),
+                JValue::Object(details),
+            ],
+        )
+        .unwrap();
+    env.throw(JThrowable::from(exception)).unwrap();
+}
+
 fn generate_ed25519_key(env: &JNIEnv) -> Result<jstring, Error> {
     let jwk = JWK::generate_ed25519()?;
     let jwk_json = serde_json::to_string(&jwk)?;
@@ -115,21 +139,16 @@ fn issue_credential(
     let credential_json: String = env.get_string(credential_jstring).unwrap().into();
     let proof_options_json: String = env.get_string(proof_options_jstring).unwrap().into();
     let key_json: String = env.get_string(key_jstring).unwrap().into();
-    let mut credential = VerifiableCredential::from_json_unsigned(&credential_json)?;
+    let credential = VerifiableCredential::from_json_unsigned(&credential_json)?;
     let key: JWK = serde_json::from_str(&key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let proof_format = options.proof_format.unwrap_or_default();
-    let vc_string = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(credential.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(credential.generate_proof(&key, &options.ldp_options, resolver))?;
-            credential.add_proof(proof);
-            serde_json::to_string(&credential)?
-        }
+    let vc_string = match rt.block_on(crate::issue_credential(
+        credential, &options, &key, resolver,
+    ))? {
+        CredentialOrJWT::JWT(jwt) => jwt,
+        vc => serde_json::to_string(&vc)?,
     };
     Ok(env.new_string(vc_string).unwrap().into_inner())
 }
@@ -153,20 +172,19 @@ fn verify_credential(
     let vc_string: String = env.get_string(vc_jstring).unwrap().into();
     let proof_options_json: String = env.get_string(proof_options_jstring).unwrap().into();
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let proof_format = options.proof_format.clone().unwrap_or_default();
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiableCredential::verify_jwt(
-            &vc_string,
-            Some(options.ldp_options),
-            resolver,
-        )),
+    let vc = match proof_format {
+        ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
+            CredentialOrJWT::JWT(vc_string)
+        }
         ProofFormat::LDP => {
-            let vc = VerifiableCredential::from_json_unsigned(&vc_string)?;
-            rt.block_on(vc.verify(Some(options.ldp_options), resolver))
+            CredentialOrJWT::Credential(VerifiableCredential::from_json_unsigned(&vc_string)?)
         }
     };
+    let report = rt.block_on(crate::verify_credential(&vc, &options, resolver))?;
+    let result = VerificationResult::from(report);
     let result_json = serde_json::to_string(&result)?;
     Ok(env.new_string(result_json).unwrap().into_inner())
 }
@@ -191,21 +209,19 @@ fn issue_presentation(
     let presentation_json: String = env.get_string(presentation_jstring).unwrap().into();
     let proof_options_json: String = env.get_string(proof_options_jstring).unwrap().into();
     let key_json: String = env.get_string(key_jstring).unwrap().into();
-    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
     let key: JWK = serde_json::from_str(&key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let vp_string = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(presentation.generate_proof(&key, &options.ldp_options, resolver))?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
+    let vp_string = match rt.block_on(crate::issue_presentation(
+        presentation,
+        &options,
+        &key,
+        resolver,
+    ))? {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(env.new_string(vp_string).unwrap().into_inner())
 }
@@ -235,18 +251,16 @@ fn did_auth(
     presentation.holder = Some(ssi::vc::URI::String(holder));
     let key: JWK = serde_json::from_str(&key_json)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let rt = runtime::get()?;
-    let vp_string = match proof_format {
-        ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
-        }
-        ProofFormat::LDP => {
-            let proof =
-                rt.block_on(presentation.generate_proof(&key, &options.ldp_options, resolver))?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
+    let vp_string = match rt.block_on(crate::issue_presentation(
+        presentation,
+        &options,
+        &key,
+        resolver,
+    ))? {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(env.new_string(vp_string).unwrap().into_inner())
 }
@@ -272,18 +286,17 @@ fn verify_presentation(
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let proof_format = options.proof_format.unwrap_or_default();
-    let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiablePresentation::verify_jwt(
-            &vp_string,
-            Some(options.ldp_options),
-            resolver,
-        )),
+    let proof_format = options.proof_format.clone().unwrap_or_default();
+    let vp = match proof_format {
+        ProofFormat::JWT | ProofFormat::VcJose | ProofFormat::VcCose | ProofFormat::JPT => {
+            PresentationOrJWT::JWT(vp_string)
+        }
         ProofFormat::LDP => {
-            let vp = VerifiablePresentation::from_json_unsigned(&vp_string)?;
-            rt.block_on(vp.verify(Some(options.ldp_options), resolver))
+            PresentationOrJWT::VP(VerifiablePresentation::from_json_unsigned(&vp_string)?)
         }
     };
+    let report = rt.block_on(crate::verify_presentation(&vp, &options, resolver))?;
+    let result = VerificationResult::from(report);
     let result_json = serde_json::to_string(&result)?;
     Ok(env.new_string(result_json).unwrap().into_inner())
 }
@@ -312,7 +325,9 @@ fn resolve_did(
     let input_metadata: ResolutionInputMetadata = serde_json::from_str(&input_metadata_json)?;
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let (res_meta, doc_opt, doc_meta_opt) = rt.block_on(resolver.resolve(&did, &input_metadata));
+    let (mut res_meta, doc_opt, doc_meta_opt) =
+        rt.block_on(resolver.resolve(&did, &input_metadata));
+    resolution_error::normalize_resolution_metadata(&mut res_meta);
     let result = ResolutionResult {
         did_document: doc_opt,
         did_resolution_metadata: Some(res_meta),
@@ -347,7 +362,8 @@ fn dereference_did_url(
     let input_metadata: DereferencingInputMetadata = serde_json::from_str(&input_metadata_json)?;
     let resolver = DID_METHODS.to_resolver();
     let rt = runtime::get()?;
-    let deref_result = rt.block_on(dereference(resolver, &did_url, &input_metadata));
+    let mut deref_result = rt.block_on(dereference(resolver, &did_url, &input_metadata));
+    resolution_error::normalize_dereferencing_metadata(&mut deref_result.0);
     let result_json = serde_json::to_string(&deref_result)?;
     Ok(env.new_string(result_json).unwrap().into_inner())
 }
@@ -361,3 +377,83 @@ pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURL(
 ) -> jstring {
     jstring_or_error(&env, dereference_did_url(&env, did_url, input_metadata))
 }
+
+fn card_arguments(
+    env: &JNIEnv,
+    credential_jstring: JString,
+    manifest_jstring: JString,
+    descriptor_id_jstring: JString,
+) -> Result<(Value, CredentialManifest, Option<String>), Error> {
+    let credential_input: String = env.get_string(credential_jstring).unwrap().into();
+    let manifest_json: String = env.get_string(manifest_jstring).unwrap().into();
+    let descriptor_id: Option<String> = if descriptor_id_jstring.is_null() {
+        None
+    } else {
+        Some(env.get_string(descriptor_id_jstring).unwrap().into())
+    };
+    let credential = render::parse_credential(&credential_input)?;
+    let manifest = serde_json::from_str(&manifest_json)?;
+    Ok((credential, manifest, descriptor_id))
+}
+
+fn credential_card(
+    env: &JNIEnv,
+    credential_jstring: JString,
+    manifest_jstring: JString,
+    descriptor_id_jstring: JString,
+) -> Result<jstring, Error> {
+    let (credential, manifest, descriptor_id) = card_arguments(
+        env,
+        credential_jstring,
+        manifest_jstring,
+        descriptor_id_jstring,
+    )?;
+    let card = render::card(&manifest, &credential, descriptor_id.as_deref())?;
+    let card_json = serde_json::to_string(&card)?;
+    Ok(env.new_string(card_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_credentialCard(
+    env: JNIEnv,
+    _class: JClass,
+    credential: JString,
+    manifest: JString,
+    descriptor_id: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        credential_card(&env, credential, manifest, descriptor_id),
+    )
+}
+
+fn validate_credential_display(
+    env: &JNIEnv,
+    credential_jstring: JString,
+    manifest_jstring: JString,
+    descriptor_id_jstring: JString,
+) -> Result<jstring, Error> {
+    let (credential, manifest, descriptor_id) = card_arguments(
+        env,
+        credential_jstring,
+        manifest_jstring,
+        descriptor_id_jstring,
+    )?;
+    let problems = render::validate_display(&manifest, &credential, descriptor_id.as_deref())?;
+    let problems_json = serde_json::to_string(&problems)?;
+    Ok(env.new_string(problems_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_validateCredentialDisplay(
+    env: JNIEnv,
+    _class: JClass,
+    credential: JString,
+    manifest: JString,
+    descriptor_id: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        validate_credential_display(&env, credential, manifest, descriptor_id),
+    )
+}
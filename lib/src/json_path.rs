@@ -0,0 +1,233 @@
+//! JSONPath selection, for the `path` arrays of display mappings and presentation definitions.
+//!
+//! Supported are the root (`$`), member names, in dot or bracket notation (`$.a.b`, `$['a']`),
+//! array indexes (`$.a[0]`, negative from the end) and wildcards (`$.a[*]`, `$.a.*`). Filter
+//! and script expressions are not supported. Selected values can be located by their JSON
+//! pointers, e.g. to disclose them selectively.
+
+use std::convert::TryFrom;
+
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum JSONPathError {
+    #[error("Invalid JSONPath: {0}")]
+    Invalid(String),
+    #[error("Unsupported JSONPath expression: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Member(String),
+    Index(i64),
+    Wildcard,
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, JSONPathError> {
+    let invalid = || JSONPathError::Invalid(path.to_string());
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.starts_with('.') {
+                return Err(JSONPathError::Unsupported(path.to_string()));
+            }
+            let end = after.find(&['.', '['][..]).unwrap_or(after.len());
+            let name = &after[..end];
+            segments.push(match name {
+                "" => return Err(invalid()),
+                "*" => Segment::Wildcard,
+                name => Segment::Member(name.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (segment, len) = match after.chars().next() {
+                Some(quote @ '\'') | Some(quote @ '"') => {
+                    let end = after[1..].find(quote).ok_or_else(invalid)? + 1;
+                    (Segment::Member(after[1..end].to_string()), end + 1)
+                }
+                Some('?') | Some('(') => return Err(JSONPathError::Unsupported(path.to_string())),
+                _ => {
+                    let end = after.find(']').ok_or_else(invalid)?;
+                    let segment = match after[..end].trim() {
+                        "*" => Segment::Wildcard,
+                        index => Segment::Index(index.parse().map_err(|_| invalid())?),
+                    };
+                    (segment, end)
+                }
+            };
+            rest = after[len..].strip_prefix(']').ok_or_else(invalid)?;
+            segments.push(segment);
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+/// Select the values of a document at a JSONPath expression.
+pub fn select<'a>(document: &'a Value, path: &str) -> Result<Vec<&'a Value>, JSONPathError> {
+    Ok(locate(document, path, false)?
+        .into_iter()
+        .map(|(_pointer, value)| value)
+        .collect())
+}
+
+fn pointer_token(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// Select the values of a document at a JSONPath expression, with the JSON pointer of each.
+///
+/// If `through_arrays` is set, a member name applied to an array selects the member of each of
+/// its items, e.g. `$.credentialSubject.name` selects the name of each subject of a credential
+/// with several.
+pub fn locate<'a>(
+    document: &'a Value,
+    path: &str,
+    through_arrays: bool,
+) -> Result<Vec<(String, &'a Value)>, JSONPathError> {
+    let mut values = vec![(String::new(), document)];
+    for segment in parse(path)? {
+        values = values
+            .into_iter()
+            .flat_map(|(pointer, value)| -> Vec<(String, &Value)> {
+                let child = |token: &str, value| (format!("{}/{}", pointer, token), value);
+                match (&segment, value) {
+                    (Segment::Member(name), Value::Object(object)) => object
+                        .get(name)
+                        .map(|value| child(&pointer_token(name), value))
+                        .into_iter()
+                        .collect(),
+                    (Segment::Member(name), Value::Array(array)) if through_arrays => array
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, item)| {
+                            let value = item.get(name)?;
+                            let pointer = format!("{}/{}/{}", pointer, i, pointer_token(name));
+                            Some((pointer, value))
+                        })
+                        .collect(),
+                    (Segment::Index(index), Value::Array(array)) => {
+                        let index = if *index < 0 {
+                            array.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|index| Some(child(&index.to_string(), array.get(index)?)))
+                            .into_iter()
+                            .collect()
+                    }
+                    (Segment::Wildcard, Value::Object(object)) => object
+                        .iter()
+                        .map(|(name, value)| child(&pointer_token(name), value))
+                        .collect(),
+                    (Segment::Wildcard, Value::Array(array)) => array
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| child(&i.to_string(), value))
+                        .collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    Ok(values)
+}
+
+/// Select the first value of a document matching one of the JSONPath expressions, in order.
+pub fn select_first<'a, S: AsRef<str>>(
+    document: &'a Value,
+    paths: &[S],
+) -> Result<Option<&'a Value>, JSONPathError> {
+    for path in paths {
+        if let Some(value) = select(document, path.as_ref())?.into_iter().next() {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn select_paths() {
+        let doc = json!({
+            "credentialSubject": {
+                "name": "Alice",
+                "degree": { "type": "BachelorDegree" },
+                "courses": [{ "title": "Math" }, { "title": "Art" }]
+            }
+        });
+        assert_eq!(
+            select(&doc, "$.credentialSubject.name").unwrap(),
+            vec!["Alice"]
+        );
+        assert_eq!(
+            select(&doc, "$['credentialSubject'][\"degree\"].type").unwrap(),
+            vec!["BachelorDegree"]
+        );
+        assert_eq!(
+            select(&doc, "$.credentialSubject.courses[*].title").unwrap(),
+            vec!["Math", "Art"]
+        );
+        assert_eq!(
+            select(&doc, "$.credentialSubject.courses[-1].title").unwrap(),
+            vec!["Art"]
+        );
+        assert!(select(&doc, "$.missing").unwrap().is_empty());
+        assert_eq!(
+            select_first(&doc, &["$.missing", "$.credentialSubject.name"]).unwrap(),
+            Some(&json!("Alice"))
+        );
+        assert!(select(&doc, "credentialSubject").is_err());
+        assert!(matches!(
+            select(&doc, "$..name"),
+            Err(JSONPathError::Unsupported(_))
+        ));
+        assert!(matches!(
+            select(&doc, "$.courses[?(@.title)]"),
+            Err(JSONPathError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn locate_paths() {
+        let doc = json!({
+            "credentialSubject": [
+                { "id": "did:example:alice", "a/b": 1 },
+                { "id": "did:example:bob" }
+            ]
+        });
+        assert_eq!(
+            locate(&doc, "$.credentialSubject[1].id", false).unwrap(),
+            vec![(
+                "/credentialSubject/1/id".to_string(),
+                &json!("did:example:bob")
+            )]
+        );
+        assert!(locate(&doc, "$.credentialSubject.id", false)
+            .unwrap()
+            .is_empty());
+        let pointers: Vec<String> = locate(&doc, "$.credentialSubject.id", true)
+            .unwrap()
+            .into_iter()
+            .map(|(pointer, _)| pointer)
+            .collect();
+        assert_eq!(
+            pointers,
+            vec!["/credentialSubject/0/id", "/credentialSubject/1/id"]
+        );
+        assert_eq!(
+            locate(&doc, "$.credentialSubject[0]['a/b']", false).unwrap()[0].0,
+            "/credentialSubject/0/a~1b"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! JSON Schemas of the [ssi] types of DIDKit's API, for describing the API, e.g. in the OpenAPI
+//! document of the HTTP server.
+//!
+//! DIDKit's own types derive [`JsonSchema`] with the `schemars` feature. The ssi types do not, so
+//! they are described by the types of this module, used with `#[schemars(with = "...")]`: the
+//! schemas describe their serialization, without every constraint of the data models.
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::json;
+
+macro_rules! schema {
+    ($(#[$attr:meta])* $name:ident, $schema:tt) => {
+        $(#[$attr])*
+        pub struct $name;
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                serde_json::from_value(json!($schema)).expect("Invalid JSON Schema")
+            }
+        }
+    };
+}
+
+schema!(
+    /// [`ssi::vc::Credential`]: a verifiable credential, or a credential to issue
+    Credential,
+    {
+        "type": "object",
+        "required": ["@context", "type", "issuer", "credentialSubject"],
+        "properties": {
+            "@context": {},
+            "id": { "type": "string" },
+            "type": { "type": ["string", "array"], "items": { "type": "string" } },
+            "issuer": { "type": ["string", "object"], "properties": { "id": { "type": "string" } } },
+            "issuanceDate": { "type": "string", "format": "date-time" },
+            "validFrom": { "type": "string", "format": "date-time" },
+            "expirationDate": { "type": "string", "format": "date-time" },
+            "validUntil": { "type": "string", "format": "date-time" },
+            "credentialSubject": { "type": ["object", "array"] },
+            "credentialStatus": { "type": "object" },
+            "credentialSchema": { "type": ["object", "array"] },
+            "proof": { "type": ["object", "array"] }
+        }
+    }
+);
+
+schema!(
+    /// [`ssi::vc::Presentation`]: a verifiable presentation, or a presentation to prove
+    Presentation,
+    {
+        "type": "object",
+        "required": ["@context", "type"],
+        "properties": {
+            "@context": {},
+            "id": { "type": "string" },
+            "type": { "type": ["string", "array"], "items": { "type": "string" } },
+            "holder": { "type": "string" },
+            "verifiableCredential": {},
+            "proof": { "type": ["object", "array"] }
+        }
+    }
+);
+
+/// A document, or a JWT (or VC-JOSE or VC-COSE) string.
+fn or_jwt(gen: &mut SchemaGenerator, document: Schema) -> Schema {
+    let jwt = gen.subschema_for::<String>();
+    serde_json::from_value(json!({ "anyOf": [document, jwt] })).expect("Invalid JSON Schema")
+}
+
+/// [`ssi::vc::CredentialOrJWT`]: a verifiable credential, or a JWT (or VC-JOSE or VC-COSE)
+/// string
+pub struct CredentialOrJWT;
+
+impl JsonSchema for CredentialOrJWT {
+    fn schema_name() -> String {
+        "CredentialOrJWT".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let credential = gen.subschema_for::<Credential>();
+        or_jwt(gen, credential)
+    }
+}
+
+/// [`crate::PresentationOrJWT`]: a verifiable presentation, or a JWT (or VC-JOSE or VC-COSE)
+/// string
+pub struct PresentationOrJWT;
+
+impl JsonSchema for PresentationOrJWT {
+    fn schema_name() -> String {
+        "PresentationOrJWT".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let presentation = gen.subschema_for::<Presentation>();
+        or_jwt(gen, presentation)
+    }
+}
+
+schema!(
+    /// [`ssi::vc::VerificationResult`]: checks passed, and warnings and errors
+    VerificationResult,
+    {
+        "type": "object",
+        "required": ["checks", "warnings", "errors"],
+        "properties": {
+            "checks": { "type": "array", "items": { "type": "string" } },
+            "warnings": { "type": "array", "items": { "type": "string" } },
+            "errors": { "type": "array", "items": { "type": "string" } }
+        }
+    }
+);
+
+schema!(
+    /// [`ssi::did_resolve::ResolutionResult`]: a DID document with its resolution and document
+    /// metadata
+    ResolutionResult,
+    {
+        "type": "object",
+        "properties": {
+            "@context": {},
+            "didDocument": { "type": ["object", "null"] },
+            "didResolutionMetadata": { "type": "object" },
+            "didDocumentMetadata": { "type": ["object", "null"] }
+        }
+    }
+);
+
+/// [`ssi::vc::ProofPurpose`]
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ProofPurpose {
+    AssertionMethod,
+    Authentication,
+    KeyAgreement,
+    ContractAgreement,
+    CapabilityInvocation,
+    CapabilityDelegation,
+}
+
+/// [`ssi::vc::LinkedDataProofOptions`]: linked data proof options of vc-http-api
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedDataProofOptions {
+    /// Proof suite type, e.g. `Ed25519Signature2018`
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub verification_method: Option<String>,
+    pub proof_purpose: Option<ProofPurpose>,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub challenge: Option<String>,
+    pub domain: Option<String>,
+}
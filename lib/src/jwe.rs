@@ -0,0 +1,342 @@
+//! [JSON Web Encryption (JWE)][jwe] of credentials and presentations.
+//!
+//! Documents are encrypted to a recipient's key agreement key using ECDH-ES (direct key
+//! agreement, with [Concat KDF][concat-kdf]) and A256GCM content encryption, in the compact
+//! serialization. X25519 keys are supported; Ed25519 keys are converted to their X25519
+//! equivalents, so that a DID with only an Ed25519 verification method (such as `did:key`) can
+//! still receive encrypted documents.
+//!
+//! [jwe]: https://datatracker.ietf.org/doc/html/rfc7516
+//! [concat-kdf]: https://datatracker.ietf.org/doc/html/rfc7518#section-4.6.2
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha512};
+use ssi::jwk::{Base64urlUInt, OctetParams, Params};
+use thiserror::Error;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{get_verification_method, resolve_key, DIDResolver, ResolutionInputMetadata, JWK};
+
+/// Key management algorithm: Elliptic Curve Diffie-Hellman Ephemeral Static, direct key agreement
+pub const ALG_ECDH_ES: &str = "ECDH-ES";
+/// Content encryption algorithm: AES-GCM with a 256-bit key
+pub const ENC_A256GCM: &str = "A256GCM";
+/// Content type of a credential or presentation in JSON-LD
+pub const CTY_LD_JSON: &str = "ld+json";
+/// Content type of a credential or presentation as a JWT
+pub const CTY_JWT: &str = "jwt";
+
+#[derive(Error, Debug)]
+pub enum JWEError {
+    #[error("Expected JWE compact serialization with 5 parts, found {0}")]
+    InvalidParts(usize),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Unsupported content encryption: {0}")]
+    UnsupportedEncryption(String),
+    #[error("Unsupported key for key agreement")]
+    UnsupportedKey,
+    #[error("Missing private key")]
+    MissingPrivateKey,
+    #[error("Encrypted key must be empty for direct key agreement")]
+    UnexpectedEncryptedKey,
+    #[error("Unable to encrypt")]
+    Encrypt,
+    #[error("Unable to decrypt")]
+    Decrypt,
+    #[error("Unable to resolve DID: {0}")]
+    Resolution(String),
+    #[error("No key agreement key found for DID: {0}")]
+    MissingKeyAgreementKey(String),
+    #[error("Unable to resolve key: {0}")]
+    SSI(#[from] ssi::error::Error),
+}
+
+/// JWE protected header
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Header {
+    pub alg: String,
+    pub enc: String,
+    /// Ephemeral public key
+    pub epk: JWK,
+    /// Recipient key ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cty: Option<String>,
+}
+
+/// Get the X25519 public key for a JWK, converting it from Ed25519 if needed.
+fn x25519_public_key(jwk: &JWK) -> Result<PublicKey, JWEError> {
+    let params = match jwk.params {
+        Params::OKP(ref params) => params,
+        _ => return Err(JWEError::UnsupportedKey),
+    };
+    let bytes = to_array(&params.public_key.0)?;
+    match &params.curve[..] {
+        "X25519" => Ok(PublicKey::from(bytes)),
+        "Ed25519" => {
+            let point = curve25519_dalek::edwards::CompressedEdwardsY(bytes)
+                .decompress()
+                .ok_or(JWEError::UnsupportedKey)?;
+            Ok(PublicKey::from(point.to_montgomery().to_bytes()))
+        }
+        _ => Err(JWEError::UnsupportedKey),
+    }
+}
+
+/// Get the X25519 secret key for a JWK, converting it from Ed25519 if needed.
+fn x25519_secret_key(jwk: &JWK) -> Result<StaticSecret, JWEError> {
+    let params = match jwk.params {
+        Params::OKP(ref params) => params,
+        _ => return Err(JWEError::UnsupportedKey),
+    };
+    let private_key = params
+        .private_key
+        .as_ref()
+        .ok_or(JWEError::MissingPrivateKey)?;
+    match &params.curve[..] {
+        "X25519" => Ok(StaticSecret::from(to_array(&private_key.0)?)),
+        "Ed25519" => {
+            // RFC 8032 section 5.1.5: the scalar is the lower half of the hash of the seed.
+            // StaticSecret clamps it.
+            let hash = Sha512::digest(&private_key.0);
+            Ok(StaticSecret::from(to_array(&hash[..32])?))
+        }
+        _ => Err(JWEError::UnsupportedKey),
+    }
+}
+
+fn to_array(bytes: &[u8]) -> Result<[u8; 32], JWEError> {
+    if bytes.len() != 32 {
+        return Err(JWEError::UnsupportedKey);
+    }
+    let mut array = [0u8; 32];
+    array.copy_from_slice(bytes);
+    Ok(array)
+}
+
+/// Derive the content encryption key from the shared secret, using Concat KDF with the `enc`
+/// algorithm as the algorithm ID and empty party info, as for ECDH-ES direct key agreement.
+fn concat_kdf(shared_secret: &[u8], enc: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(1u32.to_be_bytes());
+    hasher.update(shared_secret);
+    hasher.update((enc.len() as u32).to_be_bytes());
+    hasher.update(enc.as_bytes());
+    // PartyUInfo, PartyVInfo
+    hasher.update(0u32.to_be_bytes());
+    hasher.update(0u32.to_be_bytes());
+    // SuppPubInfo: key length in bits
+    hasher.update(256u32.to_be_bytes());
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hasher.finalize());
+    key
+}
+
+/// Encrypt a payload to a recipient public key, returning a JWE in compact serialization.
+pub fn encrypt(
+    plaintext: &[u8],
+    recipient: &JWK,
+    kid: Option<String>,
+    cty: Option<String>,
+) -> Result<String, JWEError> {
+    let recipient_key = x25519_public_key(recipient)?;
+    let ephemeral_secret = StaticSecret::new(rand::rngs::OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(&recipient_key);
+    let cek = concat_kdf(shared_secret.as_bytes(), ENC_A256GCM);
+
+    let header = Header {
+        alg: ALG_ECDH_ES.to_string(),
+        enc: ENC_A256GCM.to_string(),
+        epk: JWK::from(Params::OKP(OctetParams {
+            curve: "X25519".to_string(),
+            public_key: Base64urlUInt(ephemeral_public.as_bytes().to_vec()),
+            private_key: None,
+        })),
+        kid,
+        cty,
+    };
+    let header_b64 = base64::encode_config(serde_json::to_vec(&header)?, base64::URL_SAFE_NO_PAD);
+    let mut iv = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut iv);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&cek));
+    let mut ciphertext = cipher
+        .encrypt(
+            GenericArray::from_slice(&iv),
+            Payload {
+                msg: plaintext,
+                aad: header_b64.as_bytes(),
+            },
+        )
+        .map_err(|_| JWEError::Encrypt)?;
+    let tag = ciphertext.split_off(ciphertext.len() - 16);
+    Ok([
+        header_b64,
+        String::new(),
+        base64::encode_config(iv, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(ciphertext, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(tag, base64::URL_SAFE_NO_PAD),
+    ]
+    .join("."))
+}
+
+/// Decrypt a JWE in compact serialization using the recipient's private key.
+pub fn decrypt(jwe: &str, key: &JWK) -> Result<(Header, Vec<u8>), JWEError> {
+    let parts: Vec<&str> = jwe.trim().split('.').collect();
+    if parts.len() != 5 {
+        return Err(JWEError::InvalidParts(parts.len()));
+    }
+    let header: Header =
+        serde_json::from_slice(&base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?)?;
+    if header.alg != ALG_ECDH_ES {
+        return Err(JWEError::UnsupportedAlgorithm(header.alg));
+    }
+    if header.enc != ENC_A256GCM {
+        return Err(JWEError::UnsupportedEncryption(header.enc));
+    }
+    if !parts[1].is_empty() {
+        return Err(JWEError::UnexpectedEncryptedKey);
+    }
+    let iv = base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD)?;
+    if iv.len() != 12 {
+        return Err(JWEError::Decrypt);
+    }
+    let mut ciphertext = base64::decode_config(parts[3], base64::URL_SAFE_NO_PAD)?;
+    ciphertext.extend(base64::decode_config(parts[4], base64::URL_SAFE_NO_PAD)?);
+
+    let secret = x25519_secret_key(key)?;
+    let ephemeral_public = x25519_public_key(&header.epk)?;
+    let shared_secret = secret.diffie_hellman(&ephemeral_public);
+    let cek = concat_kdf(shared_secret.as_bytes(), &header.enc);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&cek));
+    let plaintext = cipher
+        .decrypt(
+            GenericArray::from_slice(&iv),
+            Payload {
+                msg: &ciphertext,
+                aad: parts[0].as_bytes(),
+            },
+        )
+        .map_err(|_| JWEError::Decrypt)?;
+    Ok((header, plaintext))
+}
+
+/// Find the key agreement key of a DID. The first `keyAgreement` verification method is used;
+/// if the DID document has none, its first verification method is used.
+pub async fn key_agreement_key(
+    did: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<(String, JWK), JWEError> {
+    let (res_meta, doc_opt, _) = resolver
+        .resolve(did, &ResolutionInputMetadata::default())
+        .await;
+    if let Some(error) = res_meta.error {
+        return Err(JWEError::Resolution(error));
+    }
+    let doc = doc_opt.ok_or_else(|| JWEError::Resolution(did.to_string()))?;
+    let doc = serde_json::to_value(doc)?;
+    let absolute = |id: &str| {
+        if id.starts_with('#') {
+            did.to_string() + id
+        } else {
+            id.to_string()
+        }
+    };
+    let key_agreement = match doc.get("keyAgreement") {
+        Some(Value::Array(vms)) => vms.first().cloned(),
+        Some(vm) => Some(vm.clone()),
+        None => None,
+    };
+    match key_agreement {
+        Some(Value::String(id)) => {
+            let id = absolute(&id);
+            let jwk = resolve_key(&id, resolver).await?;
+            Ok((id, jwk))
+        }
+        Some(Value::Object(vm)) => {
+            let id = vm
+                .get("id")
+                .and_then(Value::as_str)
+                .map(absolute)
+                .ok_or_else(|| JWEError::MissingKeyAgreementKey(did.to_string()))?;
+            if let Some(jwk) = vm.get("publicKeyJwk") {
+                return Ok((id, serde_json::from_value(jwk.clone())?));
+            }
+            match vm.get("publicKeyBase58").and_then(Value::as_str) {
+                Some(key) if vm.get("type") == Some(&Value::from("X25519KeyAgreementKey2019")) => {
+                    let public_key = bs58::decode(key)
+                        .into_vec()
+                        .map_err(|_| JWEError::UnsupportedKey)?;
+                    let jwk = JWK::from(Params::OKP(OctetParams {
+                        curve: "X25519".to_string(),
+                        public_key: Base64urlUInt(public_key),
+                        private_key: None,
+                    }));
+                    Ok((id, jwk))
+                }
+                _ => Err(JWEError::UnsupportedKey),
+            }
+        }
+        _ => {
+            let id = get_verification_method(did, resolver)
+                .await
+                .ok_or_else(|| JWEError::MissingKeyAgreementKey(did.to_string()))?;
+            let jwk = resolve_key(&id, resolver).await?;
+            Ok((id, jwk))
+        }
+    }
+}
+
+/// Encrypt a payload to the key agreement key of a DID.
+pub async fn encrypt_to_did(
+    plaintext: &[u8],
+    did: &str,
+    cty: Option<String>,
+    resolver: &dyn DIDResolver,
+) -> Result<String, JWEError> {
+    let (kid, jwk) = key_agreement_key(did, resolver).await?;
+    encrypt(plaintext, &jwk, Some(kid), cty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt() {
+        let secret = StaticSecret::new(rand::rngs::OsRng);
+        let public = PublicKey::from(&secret);
+        let x25519_key = JWK::from(Params::OKP(OctetParams {
+            curve: "X25519".to_string(),
+            public_key: Base64urlUInt(public.as_bytes().to_vec()),
+            private_key: Some(Base64urlUInt(secret.to_bytes().to_vec())),
+        }));
+        let ed25519_key = JWK::generate_ed25519().unwrap();
+        let other_key = JWK::generate_ed25519().unwrap();
+        for key in &[x25519_key, ed25519_key] {
+            let jwe = encrypt(
+                b"{}",
+                &key.to_public(),
+                Some("did:example:foo#key".to_string()),
+                Some(CTY_LD_JSON.to_string()),
+            )
+            .unwrap();
+            let (header, plaintext) = decrypt(&jwe, key).unwrap();
+            assert_eq!(plaintext, b"{}");
+            assert_eq!(header.kid.unwrap(), "did:example:foo#key");
+            assert_eq!(header.cty.unwrap(), CTY_LD_JSON);
+            assert!(decrypt(&jwe, &other_key).is_err());
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! [JSON Web Proof (JWP)][jwp] containers and [JSON Proof Tokens (JPT)][jpt].
+//!
+//! A JWP carries an issuer protected header, a list of individually disclosable payloads, and a
+//! proof over them. The proof algorithm is pluggable through [`ProofAlgorithm`]; [`Bbs`]
+//! implements the `BBS` algorithm (see [`crate::bbs`]), with keys in JWKs of curve
+//! [`CURVE_BLS12381G2`], as [`ProofFormat::JPT`](crate::ProofFormat::JPT) of credentials.
+//!
+//! In the compact serialization, payloads are separated by `~`; an undisclosed payload is empty,
+//! and an empty payload is `_`. A single undisclosed payload is indistinguishable from no
+//! payloads, so the issuer header of such a JWP must name its claim.
+//!
+//! [jwp]: https://datatracker.ietf.org/doc/draft-ietf-jose-json-web-proof/
+//! [jpt]: https://datatracker.ietf.org/doc/draft-ietf-jose-json-proof-token/
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ssi::jwk::{Base64urlUInt, ECParams, Params};
+use thiserror::Error;
+
+use crate::bbs::{self, BbsError};
+use crate::JWK;
+
+/// Algorithm identifier registered for BBS in JSON Web Proof.
+pub const ALG_BBS: &str = "BBS";
+/// `typ` header value for JSON Proof Tokens.
+pub const TYP_JPT: &str = "JPT";
+/// JWK curve of BBS keys.
+pub const CURVE_BLS12381G2: &str = "BLS12381G2";
+/// Compact serialization of an empty payload, to tell it from an undisclosed one.
+const EMPTY_PAYLOAD: &str = "_";
+
+#[derive(Error, Debug)]
+pub enum JWPError {
+    #[error("Expected JWP compact serialization with {0} parts, found {1}")]
+    InvalidParts(usize, usize),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Unsupported proof algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Number of claims ({0}) does not match number of payloads ({1})")]
+    ClaimsMismatch(usize, usize),
+    #[error("Payload {0} is not disclosed")]
+    PayloadNotDisclosed(usize),
+    #[error("Payload index out of range: {0}")]
+    PayloadIndex(usize),
+    #[error("Unable to sign: {0}")]
+    Sign(String),
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+    #[error("A single undisclosed payload requires the claims header")]
+    AmbiguousPayloads,
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    #[error("Expected a credential object")]
+    InvalidCredential,
+    #[error("BBS: {0}")]
+    Bbs(#[from] BbsError),
+}
+
+/// Issuer protected header
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuerHeader {
+    pub alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    /// Claim names, in payload order (JPT)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claims: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub property_set: Map<String, Value>,
+}
+
+/// Presentation protected header, used when a holder derives a presentation from an issued JWP.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationHeader {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub property_set: Map<String, Value>,
+}
+
+/// A JSON Web Proof. Payloads that are not disclosed are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonWebProof {
+    pub issuer_header: IssuerHeader,
+    pub presentation_header: Option<PresentationHeader>,
+    pub payloads: Vec<Option<Vec<u8>>>,
+    pub proof: Vec<u8>,
+}
+
+/// Implementation of a JWP proof algorithm, passed to [`issue`], [`present`] and [`verify`], e.g.
+/// [`Bbs`].
+pub trait ProofAlgorithm: Sync {
+    /// Value of the `alg` header parameter.
+    fn alg(&self) -> &str;
+    /// Create an issued proof over the encoded issuer header and all payloads.
+    fn sign(&self, header: &[u8], payloads: &[Vec<u8>], key: &JWK) -> Result<Vec<u8>, JWPError>;
+    /// Derive a presented proof from an issued proof over all payloads, disclosing only those
+    /// at the given indexes. The key is the issuer's public key.
+    fn derive(
+        &self,
+        issuer_header: &[u8],
+        presentation_header: &[u8],
+        payloads: &[Vec<u8>],
+        disclosed: &[usize],
+        issued_proof: &[u8],
+        key: &JWK,
+    ) -> Result<Vec<u8>, JWPError>;
+    /// Verify an issued or presented proof.
+    fn verify(
+        &self,
+        issuer_header: &[u8],
+        presentation_header: Option<&[u8]>,
+        payloads: &[Option<Vec<u8>>],
+        proof: &[u8],
+        key: &JWK,
+    ) -> Result<(), JWPError>;
+}
+
+fn b64_encode(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn b64_decode(data: &str) -> Result<Vec<u8>, JWPError> {
+    Ok(base64::decode_config(data, base64::URL_SAFE_NO_PAD)?)
+}
+
+impl JsonWebProof {
+    /// Serialize using the JWP compact serialization. Issued JWPs have three parts; presented JWPs
+    /// have four, with the presentation header second.
+    pub fn to_compact(&self) -> Result<String, JWPError> {
+        let mut parts = vec![b64_encode(&serde_json::to_vec(&self.issuer_header)?)];
+        if let Some(ref presentation_header) = self.presentation_header {
+            parts.push(b64_encode(&serde_json::to_vec(presentation_header)?));
+        }
+        if self.payloads == [None] && self.claim_count() != Some(1) {
+            return Err(JWPError::AmbiguousPayloads);
+        }
+        let payloads: Vec<String> = self
+            .payloads
+            .iter()
+            .map(|payload| match payload {
+                Some(bytes) if bytes.is_empty() => EMPTY_PAYLOAD.to_string(),
+                Some(bytes) => b64_encode(bytes),
+                None => String::new(),
+            })
+            .collect();
+        parts.push(payloads.join("~"));
+        parts.push(b64_encode(&self.proof));
+        Ok(parts.join("."))
+    }
+
+    /// Parse a JWP from its compact serialization.
+    pub fn from_compact(jwp: &str) -> Result<Self, JWPError> {
+        let parts: Vec<&str> = jwp.split('.').collect();
+        let (issuer_header, presentation_header, payloads, proof) = match parts.len() {
+            3 => (parts[0], None, parts[1], parts[2]),
+            4 => (parts[0], Some(parts[1]), parts[2], parts[3]),
+            n => return Err(JWPError::InvalidParts(3, n)),
+        };
+        let issuer_header: IssuerHeader = serde_json::from_slice(&b64_decode(issuer_header)?)?;
+        let presentation_header = match presentation_header {
+            Some(header) => Some(serde_json::from_slice(&b64_decode(header)?)?),
+            None => None,
+        };
+        let payloads = if payloads.is_empty() {
+            // No payloads, or a single undisclosed one, as the claims header tells.
+            match issuer_header.claims {
+                Some(ref claims) if claims.len() == 1 => vec![None],
+                _ => Vec::new(),
+            }
+        } else {
+            payloads
+                .split('~')
+                .map(|payload| match payload {
+                    "" => Ok(None),
+                    EMPTY_PAYLOAD => Ok(Some(Vec::new())),
+                    payload => b64_decode(payload).map(Some),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        Ok(Self {
+            issuer_header,
+            presentation_header,
+            payloads,
+            proof: b64_decode(proof)?,
+        })
+    }
+
+    fn claim_count(&self) -> Option<usize> {
+        self.issuer_header.claims.as_ref().map(Vec::len)
+    }
+
+    fn encoded_issuer_header(&self) -> Result<Vec<u8>, JWPError> {
+        Ok(b64_encode(&serde_json::to_vec(&self.issuer_header)?).into_bytes())
+    }
+
+    fn encoded_presentation_header(&self) -> Result<Option<Vec<u8>>, JWPError> {
+        Ok(match self.presentation_header {
+            Some(ref header) => Some(b64_encode(&serde_json::to_vec(header)?).into_bytes()),
+            None => None,
+        })
+    }
+
+    /// Get the claims of a JPT as a JSON object. Undisclosed claims are omitted.
+    pub fn claims(&self) -> Result<Map<String, Value>, JWPError> {
+        let names = self.issuer_header.claims.clone().unwrap_or_default();
+        if names.len() != self.payloads.len() {
+            return Err(JWPError::ClaimsMismatch(names.len(), self.payloads.len()));
+        }
+        let mut claims = Map::new();
+        for (name, payload) in names.into_iter().zip(self.payloads.iter()) {
+            if let Some(payload) = payload {
+                claims.insert(name, serde_json::from_slice(payload)?);
+            }
+        }
+        Ok(claims)
+    }
+}
+
+/// Issue a JSON Proof Token for the given claims.
+pub fn issue(
+    claims: &Map<String, Value>,
+    mut header: IssuerHeader,
+    algorithm: &dyn ProofAlgorithm,
+    key: &JWK,
+) -> Result<JsonWebProof, JWPError> {
+    header.alg = algorithm.alg().to_string();
+    if header.typ.is_none() {
+        header.typ = Some(TYP_JPT.to_string());
+    }
+    if header.kid.is_none() {
+        header.kid = key.key_id.clone();
+    }
+    let mut names = Vec::with_capacity(claims.len());
+    let mut payloads = Vec::with_capacity(claims.len());
+    for (name, value) in claims {
+        names.push(name.to_string());
+        payloads.push(serde_json::to_vec(value)?);
+    }
+    header.claims = Some(names);
+    let mut jwp = JsonWebProof {
+        issuer_header: header,
+        presentation_header: None,
+        payloads: payloads.iter().cloned().map(Some).collect(),
+        proof: Vec::new(),
+    };
+    jwp.proof = algorithm.sign(&jwp.encoded_issuer_header()?, &payloads, key)?;
+    Ok(jwp)
+}
+
+/// Derive a presented JWP from an issued one, disclosing only the claims named in `disclose`.
+/// The key is the issuer's public key.
+pub fn present(
+    issued: &JsonWebProof,
+    disclose: &[&str],
+    presentation_header: PresentationHeader,
+    algorithm: &dyn ProofAlgorithm,
+    key: &JWK,
+) -> Result<JsonWebProof, JWPError> {
+    check_algorithm(issued, algorithm)?;
+    let names = issued.issuer_header.claims.clone().unwrap_or_default();
+    if names.len() != issued.payloads.len() {
+        return Err(JWPError::ClaimsMismatch(names.len(), issued.payloads.len()));
+    }
+    let all = issued
+        .payloads
+        .iter()
+        .enumerate()
+        .map(|(i, payload)| payload.clone().ok_or(JWPError::PayloadNotDisclosed(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let disclosed: Vec<usize> = (0..names.len())
+        .filter(|i| disclose.contains(&&names[*i][..]))
+        .collect();
+    let mut jwp = JsonWebProof {
+        issuer_header: issued.issuer_header.clone(),
+        presentation_header: Some(presentation_header),
+        payloads: all
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| Some(payload.clone()).filter(|_| disclosed.contains(&i)))
+            .collect(),
+        proof: Vec::new(),
+    };
+    let presentation_header = jwp.encoded_presentation_header()?.unwrap_or_default();
+    jwp.proof = algorithm.derive(
+        &jwp.encoded_issuer_header()?,
+        &presentation_header,
+        &all,
+        &disclosed,
+        &issued.proof,
+        key,
+    )?;
+    Ok(jwp)
+}
+
+/// Verify an issued or presented JWP, returning the disclosed claims.
+pub fn verify(
+    jwp: &JsonWebProof,
+    algorithm: &dyn ProofAlgorithm,
+    key: &JWK,
+) -> Result<Map<String, Value>, JWPError> {
+    check_algorithm(jwp, algorithm)?;
+    let presentation_header = jwp.encoded_presentation_header()?;
+    algorithm.verify(
+        &jwp.encoded_issuer_header()?,
+        presentation_header.as_deref(),
+        &jwp.payloads,
+        &jwp.proof,
+        key,
+    )?;
+    jwp.claims()
+}
+
+fn check_algorithm(jwp: &JsonWebProof, algorithm: &dyn ProofAlgorithm) -> Result<(), JWPError> {
+    if jwp.issuer_header.alg != algorithm.alg() {
+        return Err(JWPError::UnsupportedAlgorithm(
+            jwp.issuer_header.alg.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Public key of a BBS JWK, and its secret key if it has one.
+fn bbs_key(key: &JWK) -> Result<([u8; bbs::PUBLIC_KEY_LENGTH], Option<&[u8]>), JWPError> {
+    let params = match key.params {
+        Params::EC(ref params) if params.curve.as_deref() == Some(CURVE_BLS12381G2) => params,
+        _ => {
+            return Err(JWPError::InvalidKey(format!(
+                "expected an EC key of curve {}",
+                CURVE_BLS12381G2
+            )))
+        }
+    };
+    let public_key = match (&params.x_coordinate, &params.y_coordinate) {
+        (Some(x), Some(y)) => bbs::public_key_from_coordinates(&x.0, &y.0)?,
+        _ => return Err(JWPError::InvalidKey("missing coordinates".to_string())),
+    };
+    let secret_key = params.ecc_private_key.as_ref().map(|d| &d.0[..]);
+    Ok((public_key, secret_key))
+}
+
+/// Generate a BBS key, a JWK of curve [`CURVE_BLS12381G2`].
+pub fn generate_bbs_key() -> Result<JWK, JWPError> {
+    let secret_key = bbs::generate_secret_key();
+    let (x, y) = bbs::public_key_coordinates(&bbs::public_key(&secret_key)?)?;
+    Ok(JWK::from(Params::EC(ECParams {
+        curve: Some(CURVE_BLS12381G2.to_string()),
+        x_coordinate: Some(Base64urlUInt(x.to_vec())),
+        y_coordinate: Some(Base64urlUInt(y.to_vec())),
+        ecc_private_key: Some(Base64urlUInt(secret_key.to_vec())),
+    })))
+}
+
+/// The `BBS` proof algorithm, with the BLS12-381-SHA-256 ciphersuite. The encoded issuer header
+/// is the BBS header, and the encoded presentation header the BBS presentation header.
+pub struct Bbs;
+
+impl ProofAlgorithm for Bbs {
+    fn alg(&self) -> &str {
+        ALG_BBS
+    }
+
+    fn sign(&self, header: &[u8], payloads: &[Vec<u8>], key: &JWK) -> Result<Vec<u8>, JWPError> {
+        let secret_key = bbs_key(key)?
+            .1
+            .ok_or_else(|| JWPError::Sign("missing private key".to_string()))?;
+        Ok(bbs::sign(secret_key, header, payloads)?)
+    }
+
+    fn derive(
+        &self,
+        issuer_header: &[u8],
+        presentation_header: &[u8],
+        payloads: &[Vec<u8>],
+        disclosed: &[usize],
+        issued_proof: &[u8],
+        key: &JWK,
+    ) -> Result<Vec<u8>, JWPError> {
+        let (public_key, _) = bbs_key(key)?;
+        Ok(bbs::proof_gen(
+            &public_key,
+            issued_proof,
+            issuer_header,
+            presentation_header,
+            payloads,
+            disclosed,
+        )?)
+    }
+
+    fn verify(
+        &self,
+        issuer_header: &[u8],
+        presentation_header: Option<&[u8]>,
+        payloads: &[Option<Vec<u8>>],
+        proof: &[u8],
+        key: &JWK,
+    ) -> Result<(), JWPError> {
+        let (public_key, _) = bbs_key(key)?;
+        let result = match presentation_header {
+            Some(presentation_header) => {
+                let payloads: Vec<Option<&[u8]>> = payloads.iter().map(Option::as_deref).collect();
+                bbs::proof_verify(
+                    &public_key,
+                    proof,
+                    issuer_header,
+                    presentation_header,
+                    &payloads,
+                )
+            }
+            None => {
+                let payloads = payloads
+                    .iter()
+                    .enumerate()
+                    .map(|(i, payload)| payload.as_deref().ok_or(JWPError::PayloadNotDisclosed(i)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                bbs::verify(&public_key, proof, issuer_header, &payloads)
+            }
+        };
+        result.map_err(|e| JWPError::InvalidProof(e.to_string()))
+    }
+}
+
+/// Issue a credential as a JSON Proof Token, with a claim per top-level property of the
+/// credential, so that the holder can disclose them separately. The `kid` header is the
+/// verification method, if any.
+pub fn issue_credential(
+    credential: &Value,
+    verification_method: Option<String>,
+    algorithm: &dyn ProofAlgorithm,
+    key: &JWK,
+) -> Result<JsonWebProof, JWPError> {
+    let mut claims = credential
+        .as_object()
+        .cloned()
+        .ok_or(JWPError::InvalidCredential)?;
+    claims.remove("proof");
+    let header = IssuerHeader {
+        kid: verification_method,
+        ..Default::default()
+    };
+    issue(&claims, header, algorithm, key)
+}
+
+/// Whether a compact serialization is that of a JSON Proof Token, by its issuer header.
+pub fn is_jpt(compact: &str) -> bool {
+    let header = compact
+        .split('.')
+        .next()
+        .and_then(|header| b64_decode(header).ok())
+        .and_then(|header| serde_json::from_slice::<Value>(&header).ok());
+    match header {
+        Some(header) => {
+            header.get("typ").and_then(Value::as_str) == Some(TYP_JPT)
+                || header.get("claims").is_some()
+        }
+        None => false,
+    }
+}
+
+/// Disclosed claims of a JSON Proof Token, without verifying it.
+pub fn claims_unverified(compact: &str) -> Option<Map<String, Value>> {
+    if !is_jpt(compact) {
+        return None;
+    }
+    JsonWebProof::from_compact(compact).ok()?.claims().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compact_roundtrip() {
+        let jwp = JsonWebProof {
+            issuer_header: IssuerHeader {
+                alg: ALG_BBS.to_string(),
+                typ: Some(TYP_JPT.to_string()),
+                claims: Some(vec!["iss".to_string(), "age".to_string()]),
+                ..Default::default()
+            },
+            presentation_header: Some(PresentationHeader {
+                nonce: Some("abc".to_string()),
+                ..Default::default()
+            }),
+            payloads: vec![None, Some(b"21".to_vec())],
+            proof: vec![1, 2, 3],
+        };
+        let compact = jwp.to_compact().unwrap();
+        assert_eq!(compact.split('.').count(), 4);
+        let parsed = JsonWebProof::from_compact(&compact).unwrap();
+        assert_eq!(parsed, jwp);
+        let claims = parsed.claims().unwrap();
+        assert_eq!(Value::Object(claims), json!({"age": 21}));
+    }
+
+    #[test]
+    fn compact_roundtrip_empty_and_absent_payloads() {
+        let jwp = |claims: Option<Vec<&str>>, payloads: Vec<Option<Vec<u8>>>| JsonWebProof {
+            issuer_header: IssuerHeader {
+                alg: ALG_BBS.to_string(),
+                claims: claims.map(|claims| claims.into_iter().map(String::from).collect()),
+                ..Default::default()
+            },
+            presentation_header: None,
+            payloads,
+            proof: vec![1],
+        };
+        for jwp in vec![
+            jwp(None, vec![]),
+            jwp(Some(vec![]), vec![]),
+            jwp(Some(vec!["a"]), vec![None]),
+            jwp(None, vec![Some(vec![])]),
+            jwp(None, vec![Some(vec![]), None, Some(vec![])]),
+            jwp(None, vec![None, None]),
+        ] {
+            let compact = jwp.to_compact().unwrap();
+            assert_eq!(JsonWebProof::from_compact(&compact).unwrap(), jwp);
+        }
+        assert!(matches!(
+            jwp(None, vec![None]).to_compact(),
+            Err(JWPError::AmbiguousPayloads)
+        ));
+    }
+
+    #[test]
+    fn bbs_issue_present_verify() {
+        let key = generate_bbs_key().unwrap();
+        let public_key = key.to_public();
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject", "age": 21},
+        });
+        let issued = issue_credential(
+            &credential,
+            Some("did:example:issuer#bbs".to_string()),
+            &Bbs,
+            &key,
+        )
+        .unwrap();
+        let compact = issued.to_compact().unwrap();
+        assert!(is_jpt(&compact));
+        let issued = JsonWebProof::from_compact(&compact).unwrap();
+        assert_eq!(
+            Value::Object(verify(&issued, &Bbs, &public_key).unwrap()),
+            credential
+        );
+
+        let presentation_header = PresentationHeader {
+            nonce: Some("n-0S6".to_string()),
+            ..Default::default()
+        };
+        let presented = present(
+            &issued,
+            &["issuer", "type"],
+            presentation_header,
+            &Bbs,
+            &public_key,
+        )
+        .unwrap();
+        let compact = presented.to_compact().unwrap();
+        assert_eq!(compact.split('.').count(), 4);
+        let presented = JsonWebProof::from_compact(&compact).unwrap();
+        assert_eq!(
+            Value::Object(verify(&presented, &Bbs, &public_key).unwrap()),
+            json!({"issuer": "did:example:issuer", "type": ["VerifiableCredential"]})
+        );
+
+        // The proof covers the disclosed payloads, the presentation header and the issuer key.
+        let mut forged = presented.clone();
+        let names = presented.issuer_header.claims.clone().unwrap();
+        let issuer = names.iter().position(|name| name == "issuer").unwrap();
+        forged.payloads[issuer] = Some(b"\"did:example:mallory\"".to_vec());
+        assert!(verify(&forged, &Bbs, &public_key).is_err());
+        let mut replayed = presented.clone();
+        replayed.presentation_header.as_mut().unwrap().nonce = Some("other".to_string());
+        assert!(verify(&replayed, &Bbs, &public_key).is_err());
+        let other_key = generate_bbs_key().unwrap().to_public();
+        assert!(verify(&presented, &Bbs, &other_key).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! Mapping between the registered claims of a VC-JWT and the properties of its credential.
+//!
+//! The VC Data Model 1.1 maps `iss` to `issuer`, `sub` to the subject's `id`, `nbf` to
+//! `issuanceDate`, `exp` to `expirationDate` and `jti` to `id`, and lets the claims either replace
+//! the properties or duplicate them in the `vc` claim. Relying parties disagree on both, so
+//! [`JWTClaimsOptions`] chooses which claims are mapped, whether they replace or duplicate the
+//! properties when encoding, and which of a claim and its property is kept when they differ when
+//! decoding. The default options are ssi's mapping: all claims, replacing the properties, and the
+//! claims taking precedence.
+//!
+//! When the credential has `validFrom` or `validUntil`, or the VC Data Model 2.0 context but
+//! neither property, `nbf` and `exp` map to them instead.
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Registered claims that map to credential properties.
+pub const MAPPABLE_CLAIMS: &[&str] = &["iss", "sub", "nbf", "exp", "jti"];
+
+const CREDENTIALS_V2_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum JWTClaimsError {
+    #[error("Unknown JWT claim to map: {0} (expected iss, sub, nbf, exp or jti)")]
+    UnknownClaim(String),
+    #[error("Credential is not a JSON object")]
+    NotAnObject,
+    #[error("JWT has no 'vc' claim")]
+    MissingCredential,
+    #[error("Invalid date in '{0}': {1}")]
+    InvalidDate(String, String),
+    #[error("JWT claim '{0}' differs from credential property '{1}'")]
+    Conflict(String, String),
+}
+
+/// Whether claims replace or duplicate the properties they map, when encoding.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ClaimMapping {
+    /// Remove the properties from the `vc` claim
+    Replace,
+    /// Keep the properties in the `vc` claim
+    Duplicate,
+}
+// ClaimMapping and ClaimPrecedence implement Display and FromStr for structopt. These should be
+// kept in sync with the serde (de)serialization (rename_all = ...)
+
+impl Default for ClaimMapping {
+    fn default() -> Self {
+        Self::Replace
+    }
+}
+
+impl std::fmt::Display for ClaimMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Replace => write!(f, "replace"),
+            Self::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+impl std::str::FromStr for ClaimMapping {
+    type Err = String;
+    fn from_str(mapping: &str) -> Result<Self, Self::Err> {
+        match mapping {
+            "replace" => Ok(Self::Replace),
+            "duplicate" => Ok(Self::Duplicate),
+            _ => Err(format!("Unexpected JWT claim mapping: {}", mapping)),
+        }
+    }
+}
+
+/// Which of a claim and the property it maps is kept when they differ, when decoding.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ClaimPrecedence {
+    /// Keep the claim
+    Claims,
+    /// Keep the property of the `vc` claim
+    Credential,
+    /// Reject the JWT
+    Error,
+}
+
+impl Default for ClaimPrecedence {
+    fn default() -> Self {
+        Self::Claims
+    }
+}
+
+impl std::fmt::Display for ClaimPrecedence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Claims => write!(f, "claims"),
+            Self::Credential => write!(f, "credential"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl std::str::FromStr for ClaimPrecedence {
+    type Err = String;
+    fn from_str(precedence: &str) -> Result<Self, Self::Err> {
+        match precedence {
+            "claims" => Ok(Self::Claims),
+            "credential" => Ok(Self::Credential),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("Unexpected JWT claim precedence: {}", precedence)),
+        }
+    }
+}
+
+/// Options of the mapping between JWT claims and credential properties.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct JWTClaimsOptions {
+    /// Whether claims replace or duplicate the properties, when encoding
+    #[serde(default)]
+    pub mapping: ClaimMapping,
+    /// Claims to map, among [`MAPPABLE_CLAIMS`]. Defaults to all. Other claims are not set when
+    /// encoding, and ignored when decoding, e.g. without `jti` the credential's `id` stays in the
+    /// `vc` claim.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claims: Option<Vec<String>>,
+    /// Which of a claim and its property is kept when they differ, when decoding
+    #[serde(default)]
+    pub precedence: ClaimPrecedence,
+}
+
+impl JWTClaimsOptions {
+    /// Check that the claims to map are known.
+    pub fn validate(&self) -> Result<(), JWTClaimsError> {
+        match self
+            .claims
+            .iter()
+            .flatten()
+            .find(|claim| !MAPPABLE_CLAIMS.contains(&claim.as_str()))
+        {
+            Some(claim) => Err(JWTClaimsError::UnknownClaim(claim.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn maps(&self, claim: &str) -> bool {
+        match self.claims {
+            Some(ref claims) => claims.iter().any(|c| c == claim),
+            None => true,
+        }
+    }
+}
+
+/// Take a property of an object, removing it if the claims replace the properties.
+fn take(object: &mut Map<String, Value>, property: &str, mapping: ClaimMapping) -> Option<Value> {
+    match mapping {
+        ClaimMapping::Replace => object.remove(property),
+        ClaimMapping::Duplicate => object.get(property).cloned(),
+    }
+}
+
+/// The subject of a credential with a single subject.
+fn single_subject(vc: &mut Map<String, Value>) -> Option<&mut Map<String, Value>> {
+    match vc.get_mut("credentialSubject")? {
+        Value::Array(subjects) if subjects.len() == 1 => subjects[0].as_object_mut(),
+        subject => subject.as_object_mut(),
+    }
+}
+
+/// Property of `nbf` or `exp`.
+fn date_property(vc: &Map<String, Value>, claim: &str) -> &'static str {
+    let (v1, v2) = match claim {
+        "nbf" => ("issuanceDate", "validFrom"),
+        _ => ("expirationDate", "validUntil"),
+    };
+    let context = match vc.get("@context") {
+        Some(Value::Array(contexts)) => contexts.first(),
+        context => context,
+    };
+    let is_v2 = context.and_then(Value::as_str) == Some(CREDENTIALS_V2_CONTEXT);
+    if vc.contains_key(v2) || (is_v2 && !vc.contains_key(v1)) {
+        v2
+    } else {
+        v1
+    }
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    let date = DateTime::parse_from_rfc3339(value.as_str()?).ok()?;
+    Some(date.with_timezone(&Utc))
+}
+
+/// JWT claims of a credential.
+pub fn encode_credential(
+    credential: &Value,
+    options: &JWTClaimsOptions,
+) -> Result<Map<String, Value>, JWTClaimsError> {
+    options.validate()?;
+    let mut vc = credential
+        .as_object()
+        .cloned()
+        .ok_or(JWTClaimsError::NotAnObject)?;
+    let mapping = options.mapping;
+    let mut claims = Map::new();
+    if options.maps("iss") {
+        let iss = match vc.get_mut("issuer") {
+            Some(Value::Object(issuer)) => take(issuer, "id", mapping),
+            _ => take(&mut vc, "issuer", mapping),
+        };
+        if let Some(iss) = iss {
+            claims.insert("iss".to_string(), iss);
+        }
+    }
+    if options.maps("sub") {
+        if let Some(sub) = single_subject(&mut vc).and_then(|subject| take(subject, "id", mapping))
+        {
+            claims.insert("sub".to_string(), sub);
+        }
+    }
+    for claim in &["nbf", "exp"] {
+        if !options.maps(claim) {
+            continue;
+        }
+        let property = date_property(&vc, claim);
+        if let Some(date) = take(&mut vc, property, mapping) {
+            let date = parse_date(&date).ok_or_else(|| {
+                JWTClaimsError::InvalidDate(property.to_string(), date.to_string())
+            })?;
+            claims.insert(claim.to_string(), Value::from(date.timestamp()));
+        }
+    }
+    if options.maps("jti") {
+        if let Some(jti) = take(&mut vc, "id", mapping) {
+            claims.insert("jti".to_string(), jti);
+        }
+    }
+    claims.insert("vc".to_string(), Value::Object(vc));
+    Ok(claims)
+}
+
+/// Set a property from a claim, unless the property is kept.
+fn merge(
+    object: &mut Map<String, Value>,
+    property: &str,
+    claim: &str,
+    value: Value,
+    precedence: ClaimPrecedence,
+) -> Result<(), JWTClaimsError> {
+    let same = match object.get(property) {
+        None => false,
+        Some(existing) => match (parse_date(existing), parse_date(&value)) {
+            (Some(a), Some(b)) => a == b,
+            _ => existing == &value,
+        },
+    };
+    if same {
+        return Ok(());
+    }
+    if object.contains_key(property) {
+        match precedence {
+            ClaimPrecedence::Claims => {}
+            ClaimPrecedence::Credential => return Ok(()),
+            ClaimPrecedence::Error => {
+                return Err(JWTClaimsError::Conflict(
+                    claim.to_string(),
+                    property.to_string(),
+                ))
+            }
+        }
+    }
+    object.insert(property.to_string(), value);
+    Ok(())
+}
+
+/// Credential of the claims of a JWT.
+pub fn decode_credential(
+    claims: &Value,
+    options: &JWTClaimsOptions,
+) -> Result<Value, JWTClaimsError> {
+    options.validate()?;
+    let mut vc = claims
+        .get("vc")
+        .and_then(Value::as_object)
+        .cloned()
+        .ok_or(JWTClaimsError::MissingCredential)?;
+    let precedence = options.precedence;
+    let claim = |name: &str| match claims.get(name) {
+        Some(value) if options.maps(name) => Some(value.clone()),
+        _ => None,
+    };
+    if let Some(iss) = claim("iss") {
+        match vc.get_mut("issuer") {
+            Some(Value::Object(issuer)) => merge(issuer, "id", "iss", iss, precedence)?,
+            _ => merge(&mut vc, "issuer", "iss", iss, precedence)?,
+        }
+    }
+    if let Some(sub) = claim("sub") {
+        if !vc.contains_key("credentialSubject") {
+            vc.insert("credentialSubject".to_string(), Value::Object(Map::new()));
+        }
+        if let Some(subject) = single_subject(&mut vc) {
+            merge(subject, "id", "sub", sub, precedence)?;
+        }
+    }
+    for name in &["nbf", "exp"] {
+        if let Some(date) = claim(name) {
+            let timestamp = date
+                .as_f64()
+                .and_then(|timestamp| Utc.timestamp_opt(timestamp.trunc() as i64, 0).single())
+                .ok_or_else(|| JWTClaimsError::InvalidDate(name.to_string(), date.to_string()))?;
+            let date = Value::String(timestamp.to_rfc3339_opts(SecondsFormat::Secs, true));
+            let property = date_property(&vc, name);
+            merge(&mut vc, property, name, date, precedence)?;
+        }
+    }
+    if let Some(jti) = claim("jti") {
+        merge(&mut vc, "id", "jti", jti, precedence)?;
+    }
+    Ok(Value::Object(vc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn encode_decode() {
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "id": "urn:uuid:7e1d6f3a-5b1d-4b8c-9f5e-3c2a1d0e9b8a",
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": { "id": "did:example:subject" }
+        });
+        let claims = encode_credential(&credential, &JWTClaimsOptions::default()).unwrap();
+        assert_eq!(claims["iss"], "did:example:issuer");
+        assert_eq!(claims["sub"], "did:example:subject");
+        assert_eq!(claims["nbf"], 1609459200);
+        assert_eq!(claims["jti"], credential["id"]);
+        assert_eq!(claims["vc"].get("issuer"), None);
+        assert_eq!(claims["vc"]["credentialSubject"], json!({}));
+        let claims = Value::Object(claims);
+        let decoded = decode_credential(&claims, &JWTClaimsOptions::default()).unwrap();
+        assert_eq!(decoded, credential);
+
+        let options = JWTClaimsOptions {
+            mapping: ClaimMapping::Duplicate,
+            claims: Some(vec!["iss".to_string(), "nbf".to_string()]),
+            ..Default::default()
+        };
+        let claims = encode_credential(&credential, &options).unwrap();
+        assert_eq!(claims["iss"], "did:example:issuer");
+        assert_eq!(claims.get("jti"), None);
+        assert_eq!(claims.get("sub"), None);
+        assert_eq!(claims["vc"], credential);
+
+        // A claim differing from its property.
+        let mut claims = Value::Object(claims);
+        claims["iss"] = json!("did:example:other");
+        let precedence = |precedence| JWTClaimsOptions {
+            precedence,
+            ..Default::default()
+        };
+        let decoded = decode_credential(&claims, &precedence(ClaimPrecedence::Claims)).unwrap();
+        assert_eq!(decoded["issuer"], "did:example:other");
+        let decoded = decode_credential(&claims, &precedence(ClaimPrecedence::Credential)).unwrap();
+        assert_eq!(decoded["issuer"], "did:example:issuer");
+        assert_eq!(
+            decode_credential(&claims, &precedence(ClaimPrecedence::Error)),
+            Err(JWTClaimsError::Conflict(
+                "iss".to_string(),
+                "issuer".to_string()
+            ))
+        );
+
+        let options = JWTClaimsOptions {
+            claims: Some(vec!["iat".to_string()]),
+            ..Default::default()
+        };
+        assert!(encode_credential(&credential, &options).is_err());
+    }
+}
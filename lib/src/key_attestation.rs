@@ -0,0 +1,1071 @@
+//! Key attestations, for issuing credentials only to hardware-bound holder keys.
+//!
+//! A wallet may submit, with its proof of possession of the holder key (e.g. in an OIDC4VCI
+//! credential request), an attestation by the device that the key was generated in, and cannot
+//! leave, its secure hardware. The attestation covers a challenge from the issuer, so that it
+//! cannot be replayed. A [`KeyAttestationPolicy`] validates attestations with a
+//! [`KeyAttestationValidator`] for their format, with trusted root certificates for each format,
+//! and requires a minimum [`SecurityLevel`]. Built-in validators support:
+//!
+//! - [Android Key Attestation][android] (`android-key`): a certificate chain whose leaf has the
+//!   key description extension, with the attestation challenge and security level;
+//! - [Apple App Attest][apple] (`apple-appattest`): an attestation object whose leaf
+//!   certificate's nonce extension is the hash of the authenticator data and of the challenge;
+//! - [FIDO][webauthn] `packed` attestation: a WebAuthn attestation object and client data, the
+//!   client data challenge being the issuer's challenge. Without a certificate chain (self
+//!   attestation), the key is attested at the `software` level only.
+//!
+//! Other formats, such as `tpm`, can be supported by adding validators to a policy.
+//!
+//! An [OIDC4VCI proof of possession][proof] JWT may carry the attestation of its key in the
+//! `key_attestation` header: [`KeyAttestationPolicy::validate_proof`] validates the attestation,
+//! whose challenge must be the proof's `nonce`, and the proof's signature by the attested key.
+//!
+//! [android]: https://developer.android.com/privacy-and-security/security-key-attestation
+//! [apple]: https://developer.apple.com/documentation/devicecheck/validating_apps_that_connect_to_your_server
+//! [webauthn]: https://www.w3.org/TR/webauthn-2/#sctn-packed-attestation
+//! [proof]: https://openid.net/specs/openid-4-verifiable-credential-issuance-1_0.html#name-jwt-proof-type
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value as CborValue;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ssi::jwk::{Algorithm, Base64urlUInt, OctetParams, Params};
+use thiserror::Error;
+
+use crate::multikey;
+use crate::x509::{self, CertificateIdentity, X509Error};
+use crate::{DIDResolver, ResolutionInputMetadata, JWK};
+
+pub const FORMAT_ANDROID_KEY: &str = "android-key";
+pub const FORMAT_APPLE_APP_ATTEST: &str = "apple-appattest";
+pub const FORMAT_PACKED: &str = "packed";
+/// Header of OIDC4VCI proof of possession JWTs carrying the key attestation of their key.
+pub const HEADER_KEY_ATTESTATION: &str = "key_attestation";
+
+/// OID of the key description extension of Android attestation certificates.
+const OID_ANDROID_KEY_DESCRIPTION: &str = "1.3.6.1.4.1.11129.2.1.17";
+/// OID of the nonce extension of Apple App Attest certificates.
+const OID_APPLE_NONCE: &str = "1.2.840.113635.100.8.2";
+/// AAGUIDs of Apple App Attest keys, in production and development.
+const APPLE_AAGUIDS: &[&[u8; 16]] = &[b"appattest\0\0\0\0\0\0\0", b"appattestdevelop"];
+/// COSE algorithms of packed attestation signatures.
+const COSE_ES256: i128 = -7;
+const COSE_EDDSA: i128 = -8;
+
+#[derive(Error, Debug)]
+pub enum KeyAttestationError {
+    #[error("Unsupported key attestation format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Invalid key attestation: {0}")]
+    Invalid(String),
+    #[error("Key attestation does not match the challenge")]
+    Challenge,
+    #[error("Invalid key attestation signature: {0}")]
+    Signature(String),
+    #[error("Key is attested at the {0} security level, below the required {1}")]
+    SecurityLevel(SecurityLevel, SecurityLevel),
+    #[error("Attested key is not a key of {0}")]
+    KeyMismatch(String),
+    #[error("Missing credential subject id")]
+    MissingSubject,
+    #[error("Unable to resolve {0}: {1}")]
+    Resolution(String, String),
+    #[error("Certificate: {0}")]
+    X509(#[from] X509Error),
+    #[error("CBOR: {0}")]
+    CBOR(#[from] serde_cbor::Error),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// A key attestation, as submitted with a proof of possession.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct KeyAttestation {
+    /// Attestation format, e.g. `android-key`, `apple-appattest` or `packed`
+    pub format: String,
+    /// Challenge covered by the attestation, issued by the issuer
+    pub challenge: String,
+    /// Certificate chain, as base64 DER certificates, leaf first, for `android-key`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub x5c: Vec<String>,
+    /// Attestation object, as base64url CBOR, for `apple-appattest` and WebAuthn formats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_object: Option<String>,
+    /// Client data, as base64url JSON, for WebAuthn formats
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        rename = "clientDataJSON"
+    )]
+    pub client_data_json: Option<String>,
+}
+
+/// Where an attested key is kept, from least to most protected.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SecurityLevel {
+    /// The key may be extractable, e.g. self-attested or kept by the operating system
+    Software,
+    /// The key is kept in a trusted execution environment, or an attested authenticator
+    TrustedEnvironment,
+    /// The key is kept in a secure element, e.g. StrongBox or the Secure Enclave
+    SecureElement,
+}
+
+impl std::fmt::Display for SecurityLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Software => "software",
+            Self::TrustedEnvironment => "trustedEnvironment",
+            Self::SecureElement => "secureElement",
+        })
+    }
+}
+
+impl std::str::FromStr for SecurityLevel {
+    type Err = KeyAttestationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(Value::String(s.to_string())).map_err(Into::into)
+    }
+}
+
+/// A key validated by a key attestation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttestedKey {
+    pub jwk: JWK,
+    pub format: String,
+    pub security_level: SecurityLevel,
+    /// Subject of the leaf attestation certificate, if any
+    pub attester: Option<CertificateIdentity>,
+}
+
+/// What an attestation is validated against.
+#[derive(Debug, Clone)]
+pub struct AttestationContext<'a> {
+    /// DER root certificates trusted for the attestation's format
+    pub trusted_certificates: &'a [Vec<u8>],
+    pub as_of: DateTime<Utc>,
+}
+
+/// Validator of key attestations of a format.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait KeyAttestationValidator: Send + Sync {
+    /// Attestation format, e.g. `android-key`.
+    fn format(&self) -> &str;
+    /// Validate an attestation of the format, including its challenge, and get the attested key.
+    async fn validate(
+        &self,
+        attestation: &KeyAttestation,
+        context: &AttestationContext<'_>,
+    ) -> Result<AttestedKey, KeyAttestationError>;
+}
+
+fn invalid(reason: &str) -> KeyAttestationError {
+    KeyAttestationError::Invalid(reason.to_string())
+}
+
+/// Read a DER element with a one-byte tag: its tag, its contents, and the rest of the input.
+fn der_read(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let length = rest[..n]
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+        (length, &rest[n..])
+    };
+    if rest.len() < length {
+        return None;
+    }
+    Some((tag, &rest[..length], &rest[length..]))
+}
+
+/// Read a DER element of an expected tag: its contents, and the rest of the input.
+fn der_field<'a>(
+    input: &'a [u8],
+    tag: u8,
+    name: &str,
+) -> Result<(&'a [u8], &'a [u8]), KeyAttestationError> {
+    match der_read(input) {
+        Some((t, contents, rest)) if t == tag => Ok((contents, rest)),
+        _ => Err(KeyAttestationError::Invalid(format!("invalid {}", name))),
+    }
+}
+
+/// Convert a DER ECDSA signature to the fixed-size `r || s` form of JWS.
+fn ecdsa_der_to_raw(der: &[u8], size: usize) -> Option<Vec<u8>> {
+    let (r, s) = match der_read(der)? {
+        (0x30, sequence, _) => match der_read(sequence)? {
+            (0x02, r, rest) => match der_read(rest)? {
+                (0x02, s, _) => (r, s),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let mut raw = Vec::with_capacity(2 * size);
+    for integer in &[r, s] {
+        let integer = match integer.iter().position(|byte| *byte != 0) {
+            Some(start) => &integer[start..],
+            None => &[][..],
+        };
+        if integer.len() > size {
+            return None;
+        }
+        raw.resize(raw.len() + size - integer.len(), 0);
+        raw.extend_from_slice(integer);
+    }
+    Some(raw)
+}
+
+fn base64url_decode(data: &str) -> Result<Vec<u8>, KeyAttestationError> {
+    Ok(base64::decode_config(data, base64::URL_SAFE_NO_PAD)?)
+}
+
+fn cbor_get<'a>(map: &'a BTreeMap<CborValue, CborValue>, key: &str) -> Option<&'a CborValue> {
+    map.get(&CborValue::Text(key.to_string()))
+}
+
+fn cbor_bytes<'a>(
+    value: Option<&'a CborValue>,
+    name: &str,
+) -> Result<&'a [u8], KeyAttestationError> {
+    match value {
+        Some(CborValue::Bytes(bytes)) => Ok(bytes),
+        _ => Err(KeyAttestationError::Invalid(format!("expected {}", name))),
+    }
+}
+
+/// A WebAuthn attestation object.
+struct AttestationObject {
+    format: String,
+    statement: BTreeMap<CborValue, CborValue>,
+    auth_data: Vec<u8>,
+}
+
+impl AttestationObject {
+    fn decode(attestation: &KeyAttestation) -> Result<Self, KeyAttestationError> {
+        let data = attestation
+            .attestation_object
+            .as_deref()
+            .ok_or_else(|| invalid("missing attestationObject"))?;
+        let mut object = match serde_cbor::from_slice(&base64url_decode(data)?)? {
+            CborValue::Map(object) => object,
+            _ => return Err(invalid("expected attestation object map")),
+        };
+        let format = match object.remove(&CborValue::Text("fmt".to_string())) {
+            Some(CborValue::Text(format)) => format,
+            _ => return Err(invalid("expected fmt")),
+        };
+        if format != attestation.format {
+            return Err(KeyAttestationError::Invalid(format!(
+                "attestation object is of format {}",
+                format
+            )));
+        }
+        let statement = match object.remove(&CborValue::Text("attStmt".to_string())) {
+            Some(CborValue::Map(statement)) => statement,
+            _ => return Err(invalid("expected attStmt")),
+        };
+        let auth_data = match object.remove(&CborValue::Text("authData".to_string())) {
+            Some(CborValue::Bytes(auth_data)) => auth_data,
+            _ => return Err(invalid("expected authData")),
+        };
+        Ok(Self {
+            format,
+            statement,
+            auth_data,
+        })
+    }
+
+    /// DER certificates of the `x5c` of the attestation statement, if any.
+    fn certificates(&self) -> Result<Vec<Vec<u8>>, KeyAttestationError> {
+        match cbor_get(&self.statement, "x5c") {
+            Some(CborValue::Array(certificates)) => certificates
+                .iter()
+                .map(|certificate| Ok(cbor_bytes(Some(certificate), "certificate")?.to_vec()))
+                .collect(),
+            Some(_) => Err(invalid("expected x5c array")),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// AAGUID and credential public key of the attested credential data of the authenticator
+    /// data.
+    fn attested_credential(&self) -> Result<([u8; 16], JWK), KeyAttestationError> {
+        // rpIdHash (32), flags (1), signCount (4), aaguid (16), credentialIdLength (2)
+        let data = &self.auth_data;
+        if data.len() < 55 || data[32] & 0x40 == 0 {
+            return Err(invalid("no attested credential data"));
+        }
+        let mut aaguid = [0u8; 16];
+        aaguid.copy_from_slice(&data[37..53]);
+        let id_length = u16::from_be_bytes([data[53], data[54]]) as usize;
+        let key = data
+            .get(55 + id_length..)
+            .ok_or_else(|| invalid("truncated credential ID"))?;
+        let key = serde_cbor::Deserializer::from_slice(key)
+            .into_iter::<CborValue>()
+            .next()
+            .ok_or_else(|| invalid("missing credential public key"))??;
+        Ok((aaguid, cose_key_jwk(&key)?))
+    }
+
+    fn rp_id_hash(&self) -> &[u8] {
+        &self.auth_data[..32.min(self.auth_data.len())]
+    }
+}
+
+/// Convert a COSE EC2 P-256 or OKP Ed25519 public key to a JWK.
+fn cose_key_jwk(key: &CborValue) -> Result<JWK, KeyAttestationError> {
+    let key = match key {
+        CborValue::Map(key) => key,
+        _ => return Err(invalid("expected COSE key map")),
+    };
+    let get = |label: i128| key.get(&CborValue::Integer(label));
+    match (get(1), get(-1)) {
+        #[cfg(feature = "p256")]
+        (Some(CborValue::Integer(2)), Some(CborValue::Integer(1))) => {
+            let mut point = vec![0x04];
+            point.extend_from_slice(cbor_bytes(get(-2), "x")?);
+            point.extend_from_slice(cbor_bytes(get(-3), "y")?);
+            ssi::jwk::p256_parse(&point)
+                .map_err(|err| KeyAttestationError::Invalid(err.to_string()))
+        }
+        (Some(CborValue::Integer(1)), Some(CborValue::Integer(6))) => {
+            Ok(JWK::from(Params::OKP(OctetParams {
+                curve: "Ed25519".to_string(),
+                public_key: Base64urlUInt(cbor_bytes(get(-2), "x")?.to_vec()),
+                private_key: None,
+            })))
+        }
+        _ => Err(invalid("unsupported COSE key")),
+    }
+}
+
+/// Validate a certificate chain of an attestation to the trusted certificates of its format.
+fn validate_chain(
+    chain: &[Vec<u8>],
+    context: &AttestationContext<'_>,
+) -> Result<CertificateIdentity, KeyAttestationError> {
+    Ok(x509::validate_chain(
+        chain,
+        context.trusted_certificates,
+        context.as_of,
+    )?)
+}
+
+/// Validator of [Android Key Attestation][android] certificate chains.
+///
+/// [android]: https://developer.android.com/privacy-and-security/security-key-attestation
+#[derive(Debug, Clone, Default)]
+pub struct AndroidKeyValidator;
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl KeyAttestationValidator for AndroidKeyValidator {
+    fn format(&self) -> &str {
+        FORMAT_ANDROID_KEY
+    }
+
+    async fn validate(
+        &self,
+        attestation: &KeyAttestation,
+        context: &AttestationContext<'_>,
+    ) -> Result<AttestedKey, KeyAttestationError> {
+        let chain = attestation
+            .x5c
+            .iter()
+            .map(|certificate| base64::decode(certificate))
+            .collect::<Result<Vec<_>, _>>()?;
+        let attester = validate_chain(&chain, context)?;
+        let description = x509::certificate_extension(&chain[0], OID_ANDROID_KEY_DESCRIPTION)?
+            .ok_or_else(|| invalid("missing key description extension"))?;
+        // KeyDescription ::= SEQUENCE { attestationVersion INTEGER, attestationSecurityLevel
+        // ENUMERATED, keymasterVersion INTEGER, keymasterSecurityLevel ENUMERATED,
+        // attestationChallenge OCTET STRING, ... }
+        let (description, _) = der_field(&description[..], 0x30, "key description")?;
+        let (_, rest) = der_field(description, 0x02, "key description")?;
+        let (security_level, rest) = der_field(rest, 0x0a, "key description")?;
+        let (_, rest) = der_field(rest, 0x02, "key description")?;
+        let (_, rest) = der_field(rest, 0x0a, "key description")?;
+        let (challenge, _) = der_field(rest, 0x04, "key description")?;
+        if challenge != attestation.challenge.as_bytes() {
+            return Err(KeyAttestationError::Challenge);
+        }
+        let security_level = match security_level {
+            [1] => SecurityLevel::TrustedEnvironment,
+            [2] => SecurityLevel::SecureElement,
+            _ => SecurityLevel::Software,
+        };
+        Ok(AttestedKey {
+            jwk: x509::certificate_jwk(&chain[0])?,
+            format: FORMAT_ANDROID_KEY.to_string(),
+            security_level,
+            attester: Some(attester),
+        })
+    }
+}
+
+/// Validator of [Apple App Attest][apple] attestation objects.
+///
+/// [apple]: https://developer.apple.com/documentation/devicecheck/validating_apps_that_connect_to_your_server
+#[derive(Debug, Clone, Default)]
+pub struct AppleAppAttestValidator {
+    /// App IDs (team ID and bundle ID, e.g. `ABCDE12345.com.example.wallet`) of the wallets whose
+    /// keys may be attested. If empty, any app is accepted.
+    pub app_ids: Vec<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl KeyAttestationValidator for AppleAppAttestValidator {
+    fn format(&self) -> &str {
+        FORMAT_APPLE_APP_ATTEST
+    }
+
+    async fn validate(
+        &self,
+        attestation: &KeyAttestation,
+        context: &AttestationContext<'_>,
+    ) -> Result<AttestedKey, KeyAttestationError> {
+        let object = AttestationObject::decode(attestation)?;
+        let chain = object.certificates()?;
+        if chain.is_empty() {
+            return Err(invalid("missing x5c"));
+        }
+        let attester = validate_chain(&chain, context)?;
+        let client_data_hash = Sha256::digest(attestation.challenge.as_bytes());
+        let mut hasher = Sha256::new();
+        hasher.update(&object.auth_data);
+        hasher.update(&client_data_hash);
+        let nonce = hasher.finalize();
+        // Nonce ::= SEQUENCE { [1] EXPLICIT OCTET STRING }
+        let extension = x509::certificate_extension(&chain[0], OID_APPLE_NONCE)?
+            .ok_or_else(|| invalid("missing nonce extension"))?;
+        let certificate_nonce = der_read(&extension)
+            .filter(|(tag, _, _)| *tag == 0x30)
+            .and_then(|(_, sequence, _)| der_read(sequence))
+            .filter(|(tag, _, _)| *tag == 0xa1)
+            .and_then(|(_, explicit, _)| der_read(explicit))
+            .filter(|(tag, _, _)| *tag == 0x04)
+            .map(|(_, nonce, _)| nonce);
+        if certificate_nonce != Some(&nonce[..]) {
+            return Err(KeyAttestationError::Challenge);
+        }
+        let (aaguid, jwk) = object.attested_credential()?;
+        if !APPLE_AAGUIDS.iter().any(|a| **a == aaguid) {
+            return Err(invalid("unexpected AAGUID"));
+        }
+        if jwk != x509::certificate_jwk(&chain[0])? {
+            return Err(invalid("credential key does not match the certificate"));
+        }
+        if !self.app_ids.is_empty()
+            && !self
+                .app_ids
+                .iter()
+                .any(|app_id| Sha256::digest(app_id.as_bytes())[..] == *object.rp_id_hash())
+        {
+            return Err(invalid("unexpected app ID"));
+        }
+        Ok(AttestedKey {
+            jwk,
+            format: FORMAT_APPLE_APP_ATTEST.to_string(),
+            security_level: SecurityLevel::SecureElement,
+            attester: Some(attester),
+        })
+    }
+}
+
+/// Validator of [FIDO `packed`][packed] WebAuthn attestations.
+///
+/// [packed]: https://www.w3.org/TR/webauthn-2/#sctn-packed-attestation
+#[derive(Debug, Clone, Default)]
+pub struct PackedValidator;
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl KeyAttestationValidator for PackedValidator {
+    fn format(&self) -> &str {
+        FORMAT_PACKED
+    }
+
+    async fn validate(
+        &self,
+        attestation: &KeyAttestation,
+        context: &AttestationContext<'_>,
+    ) -> Result<AttestedKey, KeyAttestationError> {
+        let object = AttestationObject::decode(attestation)?;
+        let client_data_json = base64url_decode(
+            attestation
+                .client_data_json
+                .as_deref()
+                .ok_or_else(|| invalid("missing clientDataJSON"))?,
+        )?;
+        let client_data: Value = serde_json::from_slice(&client_data_json)?;
+        let challenge = base64::encode_config(&attestation.challenge, base64::URL_SAFE_NO_PAD);
+        if client_data["type"] != "webauthn.create" || client_data["challenge"] != challenge {
+            return Err(KeyAttestationError::Challenge);
+        }
+        let (_, jwk) = object.attested_credential()?;
+        let chain = object.certificates()?;
+        let (signer, security_level, attester) = if chain.is_empty() {
+            (jwk.clone(), SecurityLevel::Software, None)
+        } else {
+            let attester = validate_chain(&chain, context)?;
+            let signer = x509::certificate_jwk(&chain[0])?;
+            (signer, SecurityLevel::TrustedEnvironment, Some(attester))
+        };
+        let signature = cbor_bytes(cbor_get(&object.statement, "sig"), "sig")?;
+        let mut data = object.auth_data.clone();
+        data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let (algorithm, signature) = match cbor_get(&object.statement, "alg") {
+            Some(CborValue::Integer(COSE_ES256)) => (
+                Algorithm::ES256,
+                ecdsa_der_to_raw(signature, 32)
+                    .ok_or_else(|| invalid("invalid ECDSA signature"))?,
+            ),
+            Some(CborValue::Integer(COSE_EDDSA)) => (Algorithm::EdDSA, signature.to_vec()),
+            _ => return Err(invalid("unsupported signature algorithm")),
+        };
+        ssi::jws::verify_bytes(algorithm, &data, &signer, &signature)
+            .map_err(|err| KeyAttestationError::Signature(err.to_string()))?;
+        Ok(AttestedKey {
+            jwk,
+            format: object.format,
+            security_level,
+            attester,
+        })
+    }
+}
+
+/// Requirements of an issuer for key attestations.
+pub struct KeyAttestationPolicy {
+    validators: Vec<Arc<dyn KeyAttestationValidator>>,
+    trusted_certificates: HashMap<String, Vec<Vec<u8>>>,
+    minimum_security_level: SecurityLevel,
+}
+
+impl Default for KeyAttestationPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyAttestationPolicy {
+    /// Policy with the built-in validators, requiring keys in a trusted environment or secure
+    /// element. Trusted certificates must be added for the formats to accept.
+    pub fn new() -> Self {
+        Self {
+            validators: vec![
+                Arc::new(AndroidKeyValidator),
+                Arc::new(AppleAppAttestValidator::default()),
+                Arc::new(PackedValidator),
+            ],
+            trusted_certificates: HashMap::new(),
+            minimum_security_level: SecurityLevel::TrustedEnvironment,
+        }
+    }
+
+    /// Add a validator, replacing any validator of the same format.
+    pub fn with_validator(mut self, validator: Arc<dyn KeyAttestationValidator>) -> Self {
+        self.validators.retain(|v| v.format() != validator.format());
+        self.validators.push(validator);
+        self
+    }
+
+    /// Trust root certificates (PEM or base64 DER) for attestations of a format.
+    pub fn with_trusted_certificates(
+        mut self,
+        format: &str,
+        certificates: &str,
+    ) -> Result<Self, KeyAttestationError> {
+        let certificates = x509::parse_certificates(certificates)?;
+        self.trusted_certificates
+            .entry(format.to_string())
+            .or_default()
+            .extend(certificates);
+        Ok(self)
+    }
+
+    pub fn with_minimum_security_level(mut self, security_level: SecurityLevel) -> Self {
+        self.minimum_security_level = security_level;
+        self
+    }
+
+    /// Validate a key attestation, and check the security level of the attested key. The caller
+    /// checks that the attestation's challenge was issued by it.
+    pub async fn validate(
+        &self,
+        attestation: &KeyAttestation,
+        as_of: DateTime<Utc>,
+    ) -> Result<AttestedKey, KeyAttestationError> {
+        let validator = self
+            .validators
+            .iter()
+            .find(|validator| validator.format() == attestation.format)
+            .ok_or_else(|| KeyAttestationError::UnsupportedFormat(attestation.format.clone()))?;
+        let context = AttestationContext {
+            trusted_certificates: self
+                .trusted_certificates
+                .get(&attestation.format)
+                .map_or(&[][..], |certificates| &certificates[..]),
+            as_of,
+        };
+        let key = validator.validate(attestation, &context).await?;
+        if key.security_level < self.minimum_security_level {
+            return Err(KeyAttestationError::SecurityLevel(
+                key.security_level,
+                self.minimum_security_level,
+            ));
+        }
+        Ok(key)
+    }
+
+    /// Validate the key attestation of an OIDC4VCI proof of possession JWT, from its
+    /// `key_attestation` header, and check that the proof is signed by the attested key, for the
+    /// challenge of the attestation as its `nonce`. Returns the attestation, whose challenge the
+    /// caller checks, and the attested key.
+    pub async fn validate_proof(
+        &self,
+        jwt: &str,
+        as_of: DateTime<Utc>,
+    ) -> Result<(KeyAttestation, AttestedKey), KeyAttestationError> {
+        let (header, claims) = crate::verification::decode_jwt_unverified(jwt)
+            .ok_or_else(|| invalid("invalid proof of possession JWT"))?;
+        let attestation: KeyAttestation = match header.get(HEADER_KEY_ATTESTATION) {
+            Some(attestation) => serde_json::from_value(attestation.clone())?,
+            None => return Err(invalid("missing key_attestation header")),
+        };
+        if claims["nonce"].as_str() != Some(&attestation.challenge) {
+            return Err(KeyAttestationError::Challenge);
+        }
+        let key = self.validate(&attestation, as_of).await?;
+        ssi::jws::decode_verify(jwt, &key.jwk)
+            .map_err(|err| KeyAttestationError::Signature(err.to_string()))?;
+        Ok((attestation, key))
+    }
+}
+
+/// Get the public key of a verification method, from its `publicKeyJwk` or
+/// `publicKeyMultibase`.
+fn verification_method_jwk(vm: &Value) -> Option<JWK> {
+    match (vm.get("publicKeyJwk"), vm["publicKeyMultibase"].as_str()) {
+        (Some(jwk), _) => serde_json::from_value(jwk.clone()).ok(),
+        (None, Some(multibase)) => multikey::from_multibase(multibase).ok(),
+        (None, None) => None,
+    }
+}
+
+/// Check that an attested key is a key of a holder's DID, i.e. the `publicKeyJwk` or
+/// `publicKeyMultibase` of one of the verification methods of its DID document.
+pub async fn check_holder_key(
+    key: &AttestedKey,
+    holder: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<(), KeyAttestationError> {
+    let (res_meta, doc, _) = resolver
+        .resolve(holder, &ResolutionInputMetadata::default())
+        .await;
+    let doc = match doc {
+        Some(doc) => serde_json::to_value(doc)?,
+        None => {
+            return Err(KeyAttestationError::Resolution(
+                holder.to_string(),
+                res_meta.error.unwrap_or_default(),
+            ))
+        }
+    };
+    let thumbprint = key
+        .jwk
+        .thumbprint()
+        .map_err(|err| KeyAttestationError::Invalid(err.to_string()))?;
+    let matches = doc["verificationMethod"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(verification_method_jwk)
+        .any(|jwk| jwk.thumbprint().ok().as_ref() == Some(&thumbprint));
+    if matches {
+        Ok(())
+    } else {
+        Err(KeyAttestationError::KeyMismatch(holder.to_string()))
+    }
+}
+
+/// Check that an attested key is a key of every subject of a credential, given its
+/// `credentialSubject`, so that the attestation of one subject's key does not bind the others.
+pub async fn check_subject_keys(
+    key: &AttestedKey,
+    credential_subject: &Value,
+    resolver: &dyn DIDResolver,
+) -> Result<(), KeyAttestationError> {
+    let subjects = match credential_subject {
+        Value::Array(subjects) => subjects.iter().collect(),
+        subject => vec![subject],
+    };
+    if subjects.is_empty() {
+        return Err(KeyAttestationError::MissingSubject);
+    }
+    for subject in subjects {
+        let holder = subject["id"]
+            .as_str()
+            .ok_or(KeyAttestationError::MissingSubject)?;
+        check_holder_key(key, holder, resolver).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x509::testing::{der, sequence, CertificateTemplate};
+    use serde_json::json;
+
+    const CHALLENGE: &str = "5f1c2b1a6a1c4c1e";
+
+    fn cbor_map(entries: Vec<(CborValue, CborValue)>) -> CborValue {
+        CborValue::Map(entries.into_iter().collect())
+    }
+
+    fn text(text: &str) -> CborValue {
+        CborValue::Text(text.to_string())
+    }
+
+    fn base64url(data: &[u8]) -> String {
+        base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Authenticator data with the attested credential data of an Ed25519 key.
+    fn auth_data(key: &JWK) -> Vec<u8> {
+        let x = match key.params {
+            Params::OKP(ref params) => params.public_key.0.clone(),
+            _ => unreachable!(),
+        };
+        let cose_key = cbor_map(vec![
+            (CborValue::Integer(1), CborValue::Integer(1)),
+            (CborValue::Integer(3), CborValue::Integer(COSE_EDDSA)),
+            (CborValue::Integer(-1), CborValue::Integer(6)),
+            (CborValue::Integer(-2), CborValue::Bytes(x)),
+        ]);
+        let mut data = Sha256::digest(b"wallet.example").to_vec();
+        data.push(0x41);
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&[0, 4, 1, 2, 3, 4]);
+        data.extend(serde_cbor::to_vec(&cose_key).unwrap());
+        data
+    }
+
+    /// Packed attestation of a credential key, signed by the attestation key, with its
+    /// certificate chain if any.
+    fn packed_attestation(
+        key: &JWK,
+        challenge: &str,
+        signer: &JWK,
+        x5c: Vec<Vec<u8>>,
+    ) -> KeyAttestation {
+        let auth_data = auth_data(key);
+        let client_data = json!({
+            "type": "webauthn.create",
+            "challenge": base64url(challenge.as_bytes()),
+            "origin": "https://wallet.example",
+        });
+        let client_data_json = serde_json::to_vec(&client_data).unwrap();
+        let mut data = auth_data.clone();
+        data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature = ssi::jws::sign_bytes(Algorithm::EdDSA, &data, signer).unwrap();
+        let mut statement = vec![
+            (text("alg"), CborValue::Integer(COSE_EDDSA)),
+            (text("sig"), CborValue::Bytes(signature)),
+        ];
+        if !x5c.is_empty() {
+            let x5c = x5c.into_iter().map(CborValue::Bytes).collect();
+            statement.push((text("x5c"), CborValue::Array(x5c)));
+        }
+        let object = cbor_map(vec![
+            (text("fmt"), text(FORMAT_PACKED)),
+            (text("attStmt"), cbor_map(statement)),
+            (text("authData"), CborValue::Bytes(auth_data)),
+        ]);
+        KeyAttestation {
+            format: FORMAT_PACKED.to_string(),
+            challenge: CHALLENGE.to_string(),
+            x5c: Vec::new(),
+            attestation_object: Some(base64url(&serde_cbor::to_vec(&object).unwrap())),
+            client_data_json: Some(base64url(&client_data_json)),
+        }
+    }
+
+    /// Android attestation certificate chain of a key, for a challenge and security level, and
+    /// the chain's root certificate.
+    fn android_attestation(
+        key: &JWK,
+        challenge: &str,
+        security_level: u8,
+    ) -> (KeyAttestation, String) {
+        let root_key = JWK::generate_ed25519().unwrap();
+        let root = CertificateTemplate::ca("Android Root").self_sign(&root_key);
+        let description = sequence(&[
+            der(0x02, &[4]),
+            der(0x0a, &[security_level]),
+            der(0x02, &[41]),
+            der(0x0a, &[security_level]),
+            der(0x04, challenge.as_bytes()),
+            der(0x04, &[]),
+        ]);
+        let mut leaf = CertificateTemplate::leaf("Android Keystore Key");
+        leaf.extensions
+            .push((OID_ANDROID_KEY_DESCRIPTION.to_string(), description));
+        let leaf = leaf.issue(key, "Android Root", &root_key);
+        let attestation = KeyAttestation {
+            format: FORMAT_ANDROID_KEY.to_string(),
+            challenge: CHALLENGE.to_string(),
+            x5c: vec![base64::encode(&leaf), base64::encode(&root)],
+            attestation_object: None,
+            client_data_json: None,
+        };
+        (attestation, base64::encode(&root))
+    }
+
+    #[test]
+    fn ecdsa_signature() {
+        // SEQUENCE { INTEGER 0x00ff.., INTEGER 0x01 }
+        let mut der = vec![0x30, 0x26, 0x02, 0x21, 0x00];
+        der.extend_from_slice(&[0xff; 32]);
+        der.extend_from_slice(&[0x02, 0x01, 0x01]);
+        let raw = ecdsa_der_to_raw(&der, 32).unwrap();
+        assert_eq!(raw.len(), 64);
+        assert_eq!(&raw[..32], &[0xff; 32]);
+        assert_eq!(raw[63], 1);
+        assert!(raw[32..63].iter().all(|byte| *byte == 0));
+        assert!(ecdsa_der_to_raw(&der[..10], 32).is_none());
+    }
+
+    #[test]
+    fn self_attestation_rejected() {
+        let mut key = BTreeMap::new();
+        key.insert(CborValue::Integer(1), CborValue::Integer(1));
+        key.insert(CborValue::Integer(-1), CborValue::Integer(6));
+        key.insert(CborValue::Integer(-2), CborValue::Bytes(vec![7; 32]));
+        let jwk = cose_key_jwk(&CborValue::Map(key)).unwrap();
+        assert!(matches!(jwk.params, Params::OKP(_)));
+
+        let attestation = KeyAttestation {
+            format: "tpm".to_string(),
+            challenge: "abc".to_string(),
+            x5c: Vec::new(),
+            attestation_object: None,
+            client_data_json: None,
+        };
+        let policy = KeyAttestationPolicy::new();
+        let rt = crate::runtime::get().unwrap();
+        let result = rt.block_on(policy.validate(&attestation, Utc::now()));
+        assert!(matches!(
+            result,
+            Err(KeyAttestationError::UnsupportedFormat(_))
+        ));
+        assert!(SecurityLevel::Software < SecurityLevel::TrustedEnvironment);
+        assert_eq!(
+            "secureElement".parse::<SecurityLevel>().unwrap(),
+            SecurityLevel::SecureElement
+        );
+    }
+
+    #[test]
+    fn packed_attestation_validated() {
+        let rt = crate::runtime::get().unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+
+        // Self attestation is at the software level only.
+        let attestation = packed_attestation(&key, CHALLENGE, &key, Vec::new());
+        let result = rt.block_on(KeyAttestationPolicy::new().validate(&attestation, Utc::now()));
+        assert!(matches!(
+            result,
+            Err(KeyAttestationError::SecurityLevel(
+                SecurityLevel::Software,
+                _
+            ))
+        ));
+        let policy =
+            KeyAttestationPolicy::new().with_minimum_security_level(SecurityLevel::Software);
+        let attested = rt
+            .block_on(policy.validate(&attestation, Utc::now()))
+            .unwrap();
+        assert_eq!(attested.jwk, key.to_public());
+        assert!(attested.attester.is_none());
+
+        let other_challenge = packed_attestation(&key, "other", &key, Vec::new());
+        let result = rt.block_on(policy.validate(&other_challenge, Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::Challenge)));
+        let other_key = JWK::generate_ed25519().unwrap();
+        let forged = packed_attestation(&key, CHALLENGE, &other_key, Vec::new());
+        let result = rt.block_on(policy.validate(&forged, Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::Signature(_))));
+
+        // Attestation certificate chaining to a trusted root
+        let root_key = JWK::generate_ed25519().unwrap();
+        let root = CertificateTemplate::ca("FIDO Root").self_sign(&root_key);
+        let attestation_key = JWK::generate_ed25519().unwrap();
+        let certificate = CertificateTemplate::leaf("Authenticator").issue(
+            &attestation_key,
+            "FIDO Root",
+            &root_key,
+        );
+        let attestation = packed_attestation(&key, CHALLENGE, &attestation_key, vec![certificate]);
+        let result = rt.block_on(KeyAttestationPolicy::new().validate(&attestation, Utc::now()));
+        assert!(matches!(
+            result,
+            Err(KeyAttestationError::X509(X509Error::Untrusted))
+        ));
+        let policy = KeyAttestationPolicy::new()
+            .with_trusted_certificates(FORMAT_PACKED, &base64::encode(&root))
+            .unwrap();
+        let attested = rt
+            .block_on(policy.validate(&attestation, Utc::now()))
+            .unwrap();
+        assert_eq!(attested.jwk, key.to_public());
+        assert_eq!(attested.security_level, SecurityLevel::TrustedEnvironment);
+        assert_eq!(attested.attester.unwrap().subject, "CN=Authenticator");
+    }
+
+    #[test]
+    fn android_key_attestation_validated() {
+        let rt = crate::runtime::get().unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+        let (attestation, root) = android_attestation(&key, CHALLENGE, 2);
+        let policy = KeyAttestationPolicy::new()
+            .with_trusted_certificates(FORMAT_ANDROID_KEY, &root)
+            .unwrap();
+        let attested = rt
+            .block_on(policy.validate(&attestation, Utc::now()))
+            .unwrap();
+        assert_eq!(attested.jwk, key.to_public());
+        assert_eq!(attested.security_level, SecurityLevel::SecureElement);
+
+        // Chain to another root
+        let result = rt.block_on(KeyAttestationPolicy::new().validate(&attestation, Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::X509(_))));
+        let (other_challenge, root) = android_attestation(&key, "other", 2);
+        let policy = KeyAttestationPolicy::new()
+            .with_trusted_certificates(FORMAT_ANDROID_KEY, &root)
+            .unwrap();
+        let result = rt.block_on(policy.validate(&other_challenge, Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::Challenge)));
+        let (software, root) = android_attestation(&key, CHALLENGE, 0);
+        let policy = KeyAttestationPolicy::new()
+            .with_trusted_certificates(FORMAT_ANDROID_KEY, &root)
+            .unwrap();
+        let result = rt.block_on(policy.validate(&software, Utc::now()));
+        assert!(matches!(
+            result,
+            Err(KeyAttestationError::SecurityLevel(..))
+        ));
+    }
+
+    #[test]
+    fn proof_of_possession_validated() {
+        let rt = crate::runtime::get().unwrap();
+        let key = JWK::generate_ed25519().unwrap();
+        let (attestation, root) = android_attestation(&key, CHALLENGE, 1);
+        let policy = KeyAttestationPolicy::new()
+            .with_trusted_certificates(FORMAT_ANDROID_KEY, &root)
+            .unwrap();
+        let proof = |signer: &JWK, nonce: &str| {
+            let header = json!({
+                "alg": "EdDSA",
+                "typ": "openid4vci-proof+jwt",
+                HEADER_KEY_ATTESTATION: attestation,
+            });
+            let claims = json!({ "aud": "https://issuer.example", "nonce": nonce });
+            let signing_input = format!(
+                "{}.{}",
+                base64url(&serde_json::to_vec(&header).unwrap()),
+                base64url(&serde_json::to_vec(&claims).unwrap())
+            );
+            let signature =
+                ssi::jws::sign_bytes(Algorithm::EdDSA, signing_input.as_bytes(), signer).unwrap();
+            format!("{}.{}", signing_input, base64url(&signature))
+        };
+        let (proof_attestation, attested) = rt
+            .block_on(policy.validate_proof(&proof(&key, CHALLENGE), Utc::now()))
+            .unwrap();
+        assert_eq!(proof_attestation, attestation);
+        assert_eq!(attested.jwk, key.to_public());
+
+        let result = rt.block_on(policy.validate_proof(&proof(&key, "other"), Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::Challenge)));
+        let other_key = JWK::generate_ed25519().unwrap();
+        let result = rt.block_on(policy.validate_proof(&proof(&other_key, CHALLENGE), Utc::now()));
+        assert!(matches!(result, Err(KeyAttestationError::Signature(_))));
+    }
+
+    #[test]
+    fn holder_keys_of_verification_methods() {
+        let key = JWK::generate_ed25519().unwrap().to_public();
+        let multikey = json!({
+            "type": "Multikey",
+            "publicKeyMultibase": multikey::to_multibase(&key).unwrap(),
+        });
+        let jwk = json!({ "type": "JsonWebKey2020", "publicKeyJwk": key });
+        assert_eq!(verification_method_jwk(&multikey), Some(key.clone()));
+        assert_eq!(verification_method_jwk(&jwk), Some(key));
+        assert_eq!(
+            verification_method_jwk(&json!({ "type": "Multikey" })),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "did-key")]
+    fn subject_keys_checked() {
+        use crate::{DIDMethod, Source, DID_METHODS};
+
+        let rt = crate::runtime::get().unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let key = JWK::generate_ed25519().unwrap();
+        let did = did_method_key::DIDKey.generate(&Source::Key(&key)).unwrap();
+        let other_key = JWK::generate_ed25519().unwrap();
+        let other_did = did_method_key::DIDKey
+            .generate(&Source::Key(&other_key))
+            .unwrap();
+        let attested = AttestedKey {
+            jwk: key.to_public(),
+            format: FORMAT_PACKED.to_string(),
+            security_level: SecurityLevel::Software,
+            attester: None,
+        };
+        let check = |subject: Value| rt.block_on(check_subject_keys(&attested, &subject, resolver));
+
+        assert!(check(json!({ "id": did })).is_ok());
+        assert!(check(json!([{ "id": did }, { "id": did }])).is_ok());
+        // Each subject must hold the attested key, not only the first one.
+        let result = check(json!([{ "id": did }, { "id": other_did }]));
+        assert!(
+            matches!(result, Err(KeyAttestationError::KeyMismatch(holder)) if holder == other_did)
+        );
+        let result = check(json!([{ "id": did }, { "name": "Alice" }]));
+        assert!(matches!(result, Err(KeyAttestationError::MissingSubject)));
+        let result = check(json!([]));
+        assert!(matches!(result, Err(KeyAttestationError::MissingSubject)));
+    }
+}
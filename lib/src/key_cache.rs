@@ -0,0 +1,172 @@
+//! Verifier-side cache of the public keys of verification methods.
+//!
+//! A [`ResolverCache`](crate::resolver::ResolverCache) keeps whole resolution results; this cache
+//! keeps only the public key of each verification method DIDKit resolves during verification,
+//! keyed by the verification method's DID URL, so that verifying many documents from the same
+//! issuers does not dereference the issuers' DID documents again for each one. It is used, once
+//! [set](set_key_cache), for VC-JOSE and VC-COSE signatures, proofs of [registered
+//! suites](crate::suite), holder binding and DIDAuth session assertions. The built-in linked
+//! data proof and JWT verification of [ssi] resolves keys by itself.
+//!
+//! Only successful resolutions of absolute DID URLs are cached. A key cached for a verification
+//! method is used until its TTL expires, even if the DID document changes in the meantime.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{DIDResolver, JWK};
+
+#[derive(Debug)]
+struct KeyEntry {
+    key: JWK,
+    expires: Instant,
+}
+
+/// Cache of verification method public keys, with a TTL.
+#[derive(Debug)]
+pub struct KeyCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, KeyEntry>>,
+}
+
+impl KeyCache {
+    /// Create a cache of at most `capacity` keys, each kept for `ttl`.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Get the cached key of a verification method, if it has not expired.
+    pub fn get(&self, verification_method: &str) -> Option<JWK> {
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(verification_method) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.key.clone()),
+            Some(_) => {
+                entries.remove(verification_method);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache the key of a verification method.
+    pub fn insert(&self, verification_method: &str, key: JWK) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        if entries.len() >= self.capacity && !entries.contains_key(verification_method) {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        if entries.len() >= self.capacity && !entries.contains_key(verification_method) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(id, _)| id.to_string());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            verification_method.to_string(),
+            KeyEntry {
+                key,
+                expires: now + self.ttl,
+            },
+        );
+    }
+
+    /// Number of cached keys, including expired ones not yet removed.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached keys.
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+lazy_static! {
+    static ref KEY_CACHE: RwLock<Option<Arc<KeyCache>>> = RwLock::new(None);
+}
+
+/// Set the key cache used for verification, or stop caching keys with `None`.
+pub fn set_key_cache(cache: Option<Arc<KeyCache>>) {
+    if let Ok(mut key_cache) = KEY_CACHE.write() {
+        *key_cache = cache;
+    }
+}
+
+/// The key cache used for verification, if any.
+pub fn key_cache() -> Option<Arc<KeyCache>> {
+    KEY_CACHE.read().ok()?.clone()
+}
+
+/// Resolve the public key of a verification method, using the [key cache](set_key_cache).
+pub(crate) async fn resolve_key(
+    verification_method: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<JWK, ssi::error::Error> {
+    let cache = match key_cache() {
+        Some(cache) if is_cacheable(verification_method) => cache,
+        _ => return crate::resolve_key(verification_method, resolver).await,
+    };
+    if let Some(key) = cache.get(verification_method) {
+        return Ok(key);
+    }
+    let key = crate::resolve_key(verification_method, resolver).await?;
+    cache.insert(verification_method, key.clone());
+    Ok(key)
+}
+
+fn is_cacheable(verification_method: &str) -> bool {
+    verification_method.starts_with("did:") && verification_method.contains('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_keys() {
+        let key = JWK::generate_ed25519().unwrap();
+        let cache = KeyCache::new(1, Duration::from_secs(60));
+        cache.insert("did:example:issuer#key-1", key.clone());
+        assert_eq!(cache.get("did:example:issuer#key-1"), Some(key.clone()));
+        cache.insert("did:example:issuer#key-2", key.clone());
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get("did:example:issuer#key-1").is_none());
+
+        let cache = KeyCache::new(10, Duration::from_secs(0));
+        cache.insert("did:example:issuer#key-1", key);
+        assert!(cache.get("did:example:issuer#key-1").is_none());
+        assert!(cache.is_empty());
+
+        assert!(is_cacheable("did:example:issuer#key-1"));
+        assert!(!is_cacheable("did:example:issuer"));
+        assert!(!is_cacheable("#key-1"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Validity of verification methods over time.
+//!
+//! A verification method of a DID document may have an `expires` or a `revoked` date ([DID
+//! Specification Registries][props]): proofs made with its key after that date are not valid,
+//! while proofs made before remain valid. Verification checks the verification method of each
+//! verified proof as of the proof's signing time: its `created` date (or JWT `iat`), or, without
+//! one, the verification time. The verification method is looked up in the current DID document
+//! of its controller, where a revocation is recorded, and, for DID methods that resolve a
+//! [`versionTime`][did-parameters] ([`VERSIONED_METHODS`]), in the DID document as of the
+//! signing time, so that an expiration date since changed or removed still applies.
+//!
+//! [props]: https://www.w3.org/TR/did-spec-registries/#verification-method-properties
+//! [did-parameters]: https://www.w3.org/TR/did-core/#did-parameters
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::resolver::VERSIONED_METHODS;
+use crate::{DIDResolver, ResolutionInputMetadata};
+
+/// Properties of a DID document that may embed verification methods.
+const VERIFICATION_METHOD_PROPERTIES: &[&str] = &[
+    "verificationMethod",
+    "authentication",
+    "assertionMethod",
+    "keyAgreement",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum KeyValidityError {
+    #[error("Verification method {0} expired at {1}, before the proof was made at {2}")]
+    Expired(String, String, String),
+    #[error("Verification method {0} was revoked at {1}, before the proof was made at {2}")]
+    Revoked(String, String, String),
+    #[error("Invalid {0} date of verification method {1}: {2}")]
+    InvalidDate(&'static str, String, String),
+}
+
+/// `expires` and `revoked` dates of a verification method.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyValidity {
+    pub expires: Option<DateTime<Utc>>,
+    pub revoked: Option<DateTime<Utc>>,
+}
+
+fn rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl KeyValidity {
+    /// Read the dates of a verification method.
+    pub fn of_verification_method(vm: &Value) -> Result<Self, KeyValidityError> {
+        let date = |property: &'static str| match vm.get(property).and_then(Value::as_str) {
+            Some(date) => DateTime::parse_from_rfc3339(date)
+                .map(|date| Some(date.with_timezone(&Utc)))
+                .map_err(|_| {
+                    let id = vm.get("id").and_then(Value::as_str).unwrap_or_default();
+                    KeyValidityError::InvalidDate(property, id.to_string(), date.to_string())
+                }),
+            None => Ok(None),
+        };
+        Ok(Self {
+            expires: date("expires")?,
+            revoked: date("revoked")?,
+        })
+    }
+
+    /// Check that a proof made at a time with the verification method is valid: that the
+    /// verification method was not expired or revoked then.
+    pub fn check(&self, id: &str, signed_at: DateTime<Utc>) -> Result<(), KeyValidityError> {
+        if let Some(revoked) = self.revoked {
+            if signed_at >= revoked {
+                return Err(KeyValidityError::Revoked(
+                    id.to_string(),
+                    rfc3339(&revoked),
+                    rfc3339(&signed_at),
+                ));
+            }
+        }
+        if let Some(expires) = self.expires {
+            if signed_at > expires {
+                return Err(KeyValidityError::Expired(
+                    id.to_string(),
+                    rfc3339(&expires),
+                    rfc3339(&signed_at),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find a verification method of a DID document by its ID, which may be relative to the DID.
+pub fn find_verification_method<'a>(document: &'a Value, did: &str, id: &str) -> Option<&'a Value> {
+    VERIFICATION_METHOD_PROPERTIES
+        .iter()
+        .filter_map(|property| document.get(property).and_then(Value::as_array))
+        .flatten()
+        .find(|vm| match vm.get("id").and_then(Value::as_str) {
+            Some(vm_id) => match vm_id.strip_prefix('#') {
+                Some(fragment) => {
+                    id.strip_prefix(did).and_then(|id| id.strip_prefix('#')) == Some(fragment)
+                }
+                None => vm_id == id,
+            },
+            None => false,
+        })
+}
+
+/// Check that a proof made at a time with a verification method (DID URL) is valid, as of the
+/// current DID document of its controller, and of the document at that time for
+/// [`VERSIONED_METHODS`]. Verification methods that are not DID URLs, or not found, are not
+/// checked.
+pub async fn check_verification_method(
+    id: &str,
+    signed_at: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+) -> Result<(), KeyValidityError> {
+    let did = match id.split('#').next() {
+        Some(did) if did.starts_with("did:") => did,
+        _ => return Ok(()),
+    };
+    let mut inputs = vec![ResolutionInputMetadata::default()];
+    let method = did.split(':').nth(1).unwrap_or_default();
+    if VERSIONED_METHODS.contains(&method) {
+        inputs.push(ResolutionInputMetadata {
+            version_time: Some(rfc3339(&signed_at)),
+            ..Default::default()
+        });
+    }
+    for input_metadata in inputs {
+        let (_, document, _) = resolver.resolve(did, &input_metadata).await;
+        let document = match document.and_then(|document| serde_json::to_value(document).ok()) {
+            Some(document) => document,
+            None => continue,
+        };
+        if let Some(vm) = find_verification_method(&document, did, id) {
+            KeyValidity::of_verification_method(vm)?.check(id, signed_at)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn verification_method_dates() {
+        let did = "did:example:issuer";
+        let document = json!({
+            "id": did,
+            "verificationMethod": [
+                { "id": "#key-1", "expires": "2022-01-01T00:00:00Z" },
+                { "id": "did:example:issuer#key-2", "revoked": "2021-01-01T00:00:00Z" },
+                { "id": "#key-3", "expires": "next year" },
+            ],
+        });
+        let date = |date: &str| {
+            DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let validity = |id: &str| {
+            let vm = find_verification_method(&document, did, id).unwrap();
+            KeyValidity::of_verification_method(vm)
+        };
+        assert!(find_verification_method(&document, did, "did:example:other#key-1").is_none());
+
+        let key_1 = validity("did:example:issuer#key-1").unwrap();
+        assert_eq!(key_1.expires, Some(date("2022-01-01T00:00:00Z")));
+        assert!(key_1.check("#key-1", date("2021-06-01T00:00:00Z")).is_ok());
+        assert_eq!(
+            key_1.check("#key-1", date("2022-06-01T00:00:00Z")),
+            Err(KeyValidityError::Expired(
+                "#key-1".to_string(),
+                "2022-01-01T00:00:00Z".to_string(),
+                "2022-06-01T00:00:00Z".to_string()
+            ))
+        );
+
+        let key_2 = validity("did:example:issuer#key-2").unwrap();
+        assert!(key_2.check("#key-2", date("2020-06-01T00:00:00Z")).is_ok());
+        assert!(matches!(
+            key_2.check("#key-2", date("2021-01-01T00:00:00Z")),
+            Err(KeyValidityError::Revoked(..))
+        ));
+
+        assert!(matches!(
+            validity("did:example:issuer#key-3"),
+            Err(KeyValidityError::InvalidDate("expires", ..))
+        ));
+    }
+}
@@ -1,19 +1,102 @@
+pub mod attestation;
+#[cfg(feature = "format-barcode")]
+pub mod barcode;
+#[cfg(feature = "format-jwp")]
+pub mod bbs;
 #[cfg(not(feature = "wasm"))]
 pub mod c;
+#[cfg(feature = "format-cacao")]
+pub mod cacao;
+pub mod cancellation;
+pub mod canonical_json;
+pub mod canonicalization;
+pub mod content_id;
+pub mod convert;
+pub mod credential;
+pub mod credential_types;
+pub mod did_auth;
+pub mod did_lint;
 mod did_methods;
+#[cfg(feature = "did-peer")]
+pub mod did_peer;
+#[cfg(all(feature = "did-webvh", not(target_os = "wasi")))]
+pub mod did_webvh;
+#[cfg(feature = "format-didcomm")]
+pub mod didcomm;
+#[cfg(feature = "edv")]
+pub mod edv;
 pub mod error;
+#[cfg(feature = "hd-did")]
+pub mod hd_did;
+pub mod hex;
+pub mod i18n;
+pub mod integrity;
+#[cfg(feature = "ipfs")]
+pub mod ipfs;
+pub mod issue;
 #[cfg(not(feature = "wasm"))]
 pub mod jni;
+pub mod json_path;
+#[cfg(feature = "schemars")]
+pub mod json_schema;
+pub mod jwe;
+#[cfg(feature = "format-jwp")]
+pub mod jwp;
+pub mod jwt_claims;
+#[cfg(feature = "x509")]
+pub mod key_attestation;
+pub mod key_cache;
+pub mod key_validity;
+pub mod multikey;
+pub mod observe;
+pub mod pairwise;
+pub mod pex;
+#[cfg(feature = "did-pkh")]
+pub mod pkh_chains;
+pub mod profile;
+pub mod proof_purpose;
+pub mod query;
+#[cfg(feature = "format-jwp")]
+pub mod redact;
+pub mod render;
+pub mod resolution_error;
+#[cfg(not(feature = "wasm"))]
+pub mod resolver;
+#[cfg(feature = "resolver-config")]
+pub mod resolver_config;
 #[cfg(not(feature = "wasm"))]
 pub mod runtime;
+pub mod schema;
+pub mod securing;
+pub mod signer;
 #[cfg(not(feature = "wasm"))]
 pub mod ssh_agent;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod suite;
+pub mod verification;
+#[cfg(feature = "verify-core")]
+pub mod verify_core;
+#[cfg(all(not(feature = "wasm"), feature = "did-key"))]
+pub mod wallet;
+#[cfg(target_os = "wasi")]
+pub mod wasi;
+pub mod x509;
 
 #[macro_use]
 extern crate lazy_static;
 
+pub use crate::credential::CredentialBuilder;
 pub use crate::did_methods::DID_METHODS;
 pub use crate::error::Error;
+pub use crate::issue::{issue_credential, issue_presentation, IssueOptions, ProofOptionsBuilder};
+pub use crate::jwt_claims::JWTClaimsOptions;
+pub use crate::signer::Signer;
+pub use crate::suite::{register_suite, ProofSuite};
+pub use crate::verification::{
+    verify_credential, verify_presentation, PresentationOrJWT, VerificationOptions,
+    VerificationReport,
+};
 pub use ssi::did::{DIDMethod, Document, Source};
 #[cfg(feature = "http-did")]
 pub use ssi::did_resolve::HTTPDIDResolver;
@@ -36,20 +119,54 @@ pub use ssi::vc::URI;
 pub use ssi::zcap::{Delegation, Invocation};
 pub use ssi::tzkey::jwk_from_tezos_key;
 
+use chrono::{DateTime, Utc};
 use core::str::FromStr;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct JWTOrLDPOptions {
     /// Linked data proof options from vc-http-api
     #[serde(flatten)]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "crate::json_schema::LinkedDataProofOptions")
+    )]
     pub ldp_options: LinkedDataProofOptions,
     /// Proof format (not standard in vc-http-api)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proof_format: Option<ProofFormat>,
+    /// Verifier policy (not standard in vc-http-api)
+    #[serde(flatten)]
+    pub verification_options: VerificationOptions,
+    /// Issue deterministically, with a pinned `created` time (not standard in vc-http-api)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deterministic: bool,
+    /// Data Integrity `expires` proof property, when issuing (not standard in vc-http-api)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    /// Data Integrity `nonce` proof property, when issuing (not standard in vc-http-api)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Additional properties of the proof, when issuing (not standard in vc-http-api)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_properties: Option<Map<String, Value>>,
+    /// Mapping between JWT claims and credential properties, when issuing or verifying a JWT
+    /// credential (not standard in vc-http-api)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwt_claims: Option<JWTClaimsOptions>,
+    /// Set the `id` of a credential to its content-addressable ID, when issuing (not standard in
+    /// vc-http-api)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub content_id: bool,
+    /// Validate and prepare the document as for signing, but give it a placeholder signature
+    /// instead of signing it, when issuing (not standard in vc-http-api)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
 }
 
 impl JWTOrLDPOptions {
@@ -60,11 +177,20 @@ impl JWTOrLDPOptions {
                 ..Default::default()
             },
             proof_format: None,
+            verification_options: VerificationOptions::default(),
+            deterministic: false,
+            expires: None,
+            nonce: None,
+            proof_properties: None,
+            jwt_claims: None,
+            content_id: false,
+            dry_run: false,
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 pub enum ProofFormat {
     /// <https://www.w3.org/TR/vc-data-model/#linked-data-proofs>
@@ -73,6 +199,15 @@ pub enum ProofFormat {
     /// <https://www.w3.org/TR/vc-data-model/#json-web-token>
     #[serde(rename = "jwt")]
     JWT,
+    /// <https://www.w3.org/TR/vc-jose-cose/#securing-with-jose>
+    #[serde(rename = "vc-jose")]
+    VcJose,
+    /// <https://www.w3.org/TR/vc-jose-cose/#securing-with-cose>
+    #[serde(rename = "vc-cose")]
+    VcCose,
+    /// JSON Proof Token with the BBS algorithm (see [`jwp`]), with the `format-jwp` feature
+    #[serde(rename = "jpt")]
+    JPT,
 }
 // ProofFormat implements Display and FromStr for structopt. This should be kept in sync with the
 // serde (de)serialization (rename = ...)
@@ -88,6 +223,9 @@ impl std::fmt::Display for ProofFormat {
         match self {
             Self::LDP => write!(f, "ldp"),
             Self::JWT => write!(f, "jwt"),
+            Self::VcJose => write!(f, "vc-jose"),
+            Self::VcCose => write!(f, "vc-cose"),
+            Self::JPT => write!(f, "jpt"),
         }
     }
 }
@@ -98,6 +236,9 @@ impl FromStr for ProofFormat {
         match &s[..] {
             "ldp" => Ok(Self::LDP),
             "jwt" => Ok(Self::JWT),
+            "vc-jose" => Ok(Self::VcJose),
+            "vc-cose" => Ok(Self::VcCose),
+            "jpt" => Ok(Self::JPT),
             _ => Err(format!("Unexpected proof format: {}", s))?,
         }
     }
@@ -0,0 +1,239 @@
+//! `Multikey` and `publicKeyMultibase` verification methods.
+//!
+//! A [Multikey][] verification method (and an `Ed25519VerificationKey2020` or
+//! `X25519KeyAgreementKey2020` one) gives its public key as `publicKeyMultibase`: the
+//! base58-btc multibase encoding of the multicodec-prefixed key bytes. `ssi` only gets keys from
+//! `publicKeyJwk` (or `publicKeyBase58`), so [`normalize_document`] adds the equivalent
+//! `publicKeyJwk` to each such verification method, and [`MultikeyMethod`] does this for the
+//! documents resolved by a DID method.
+//!
+//! | Key                    | Multicodec | JWK               |
+//! |------------------------|------------|-------------------|
+//! | Ed25519                | `0xed`     | `OKP`, `Ed25519`  |
+//! | X25519                 | `0xec`     | `OKP`, `X25519`   |
+//! | secp256k1 (compressed) | `0xe7`     | `EC`, `secp256k1` |
+//! | P-256 (compressed)     | `0x1200`   | `EC`, `P-256`     |
+//!
+//! [Multikey]: https://www.w3.org/TR/controller-document/#multikey
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use ssi::did::{DIDMethod, Document, Source};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata,
+};
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+
+const ED25519_PUB: &[u8] = &[0xed, 0x01];
+const X25519_PUB: &[u8] = &[0xec, 0x01];
+#[cfg(feature = "secp256k1")]
+const SECP256K1_PUB: &[u8] = &[0xe7, 0x01];
+#[cfg(feature = "p256")]
+const P256_PUB: &[u8] = &[0x80, 0x24];
+
+/// Verification method properties that may contain verification methods.
+const VERIFICATION_RELATIONSHIPS: &[&str] = &[
+    "verificationMethod",
+    "authentication",
+    "assertionMethod",
+    "keyAgreement",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+#[derive(Error, Debug)]
+pub enum MultikeyError {
+    #[error("Expected base58-btc multibase (z...)")]
+    UnsupportedMultibase,
+    #[error("Unable to decode base58: {0}")]
+    Base58(#[from] bs58::decode::Error),
+    #[error("Unsupported multicodec key type")]
+    UnsupportedCodec,
+    #[error("Unsupported key type")]
+    UnsupportedKey,
+    #[error("Invalid key length: {0}")]
+    InvalidLength(usize),
+    #[error("SSI: {0}")]
+    SSI(#[from] ssi::error::Error),
+}
+
+fn multibase(codec: &[u8], key: &[u8]) -> String {
+    let mut bytes = codec.to_vec();
+    bytes.extend_from_slice(key);
+    format!("z{}", bs58::encode(bytes).into_string())
+}
+
+fn okp(curve: &str, key: &[u8]) -> Result<JWK, MultikeyError> {
+    if key.len() != 32 {
+        return Err(MultikeyError::InvalidLength(key.len()));
+    }
+    Ok(JWK::from(Params::OKP(OctetParams {
+        curve: curve.to_string(),
+        public_key: Base64urlUInt(key.to_vec()),
+        private_key: None,
+    })))
+}
+
+/// Encode the public key of a JWK as `publicKeyMultibase`.
+pub fn to_multibase(jwk: &JWK) -> Result<String, MultikeyError> {
+    match jwk.params {
+        Params::OKP(ref params) => match &params.curve[..] {
+            "Ed25519" => Ok(multibase(ED25519_PUB, &params.public_key.0)),
+            "X25519" => Ok(multibase(X25519_PUB, &params.public_key.0)),
+            _ => Err(MultikeyError::UnsupportedKey),
+        },
+        Params::EC(ref params) => match params.curve.as_deref() {
+            #[cfg(feature = "secp256k1")]
+            Some("secp256k1") => Ok(multibase(
+                SECP256K1_PUB,
+                &ssi::jwk::serialize_secp256k1(params)?,
+            )),
+            #[cfg(feature = "p256")]
+            Some("P-256") => Ok(multibase(P256_PUB, &ssi::jwk::serialize_p256(params)?)),
+            _ => Err(MultikeyError::UnsupportedKey),
+        },
+        _ => Err(MultikeyError::UnsupportedKey),
+    }
+}
+
+/// Decode a `publicKeyMultibase` value to a public JWK.
+pub fn from_multibase(multibase: &str) -> Result<JWK, MultikeyError> {
+    let encoded = multibase
+        .strip_prefix('z')
+        .ok_or(MultikeyError::UnsupportedMultibase)?;
+    let bytes = bs58::decode(encoded).into_vec()?;
+    if let Some(key) = bytes.strip_prefix(ED25519_PUB) {
+        return okp("Ed25519", key);
+    }
+    if let Some(key) = bytes.strip_prefix(X25519_PUB) {
+        return okp("X25519", key);
+    }
+    #[cfg(feature = "secp256k1")]
+    if let Some(key) = bytes.strip_prefix(SECP256K1_PUB) {
+        return Ok(ssi::jwk::secp256k1_parse(key)?);
+    }
+    #[cfg(feature = "p256")]
+    if let Some(key) = bytes.strip_prefix(P256_PUB) {
+        return Ok(ssi::jwk::p256_parse(key)?);
+    }
+    Err(MultikeyError::UnsupportedCodec)
+}
+
+fn normalize_verification_method(vm: &mut Map<String, Value>) {
+    if vm.contains_key("publicKeyJwk") {
+        return;
+    }
+    let jwk = match vm.get("publicKeyMultibase").and_then(Value::as_str) {
+        Some(multibase) => match from_multibase(multibase) {
+            Ok(jwk) => jwk,
+            // Leave keys that cannot be converted for ssi to report.
+            Err(_) => return,
+        },
+        None => return,
+    };
+    if let Ok(jwk) = serde_json::to_value(jwk) {
+        vm.insert("publicKeyJwk".to_string(), jwk);
+    }
+}
+
+/// Add `publicKeyJwk` to the verification methods of a DID document that have only
+/// `publicKeyMultibase`.
+pub fn normalize_document(document: Document) -> Document {
+    let mut value = match serde_json::to_value(&document) {
+        Ok(value) => value,
+        Err(_) => return document,
+    };
+    let mut changed = false;
+    for name in VERIFICATION_RELATIONSHIPS {
+        let vms = match value.get_mut(*name) {
+            Some(Value::Array(vms)) => vms,
+            _ => continue,
+        };
+        for vm in vms.iter_mut() {
+            if let Value::Object(vm) = vm {
+                let had_jwk = vm.contains_key("publicKeyJwk");
+                normalize_verification_method(vm);
+                changed |= !had_jwk && vm.contains_key("publicKeyJwk");
+            }
+        }
+    }
+    if !changed {
+        return document;
+    }
+    serde_json::from_value(value).unwrap_or(document)
+}
+
+/// DID method whose resolved documents are normalized with [`normalize_document`].
+pub struct MultikeyMethod<M>(pub M);
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M: DIDResolver> DIDResolver for MultikeyMethod<M> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let (res_meta, doc, doc_meta) = self.0.resolve(did, input_metadata).await;
+        (res_meta, doc.map(normalize_document), doc_meta)
+    }
+}
+
+impl<M: DIDMethod + DIDResolver> DIDMethod for MultikeyMethod<M> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn generate(&self, source: &Source) -> Option<String> {
+        self.0.generate(source)
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ed25519_multibase() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let multibase = to_multibase(&jwk).unwrap();
+        assert!(multibase.starts_with("z6Mk"));
+        let decoded = from_multibase(&multibase).unwrap();
+        assert_eq!(
+            serde_json::to_value(decoded).unwrap(),
+            serde_json::to_value(jwk.to_public()).unwrap()
+        );
+        assert!(from_multibase("uAAAA").is_err());
+    }
+
+    #[test]
+    fn normalize_multikey() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-1",
+                "type": "Multikey",
+                "controller": "did:web:example.com",
+                "publicKeyMultibase": "z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+            }],
+            "assertionMethod": ["did:web:example.com#key-1"]
+        }))
+        .unwrap();
+        let document = serde_json::to_value(normalize_document(document)).unwrap();
+        assert_eq!(
+            document["verificationMethod"][0]["publicKeyJwk"]["crv"],
+            "Ed25519"
+        );
+    }
+}
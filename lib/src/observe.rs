@@ -0,0 +1,162 @@
+//! Instrumentation hooks.
+//!
+//! DIDKit does not choose a telemetry stack for its embedders. Instead, it reports [events](Event)
+//! to the [observers](Observer) registered with [`add_observer`]: the start and end of DID
+//! resolutions through a [`ResolverStack`](crate::resolver::ResolverStack), fetches of context
+//! documents, canonicalization of documents for linked data proofs, signing, and the results of
+//! verifications. An observer can turn them into metrics, logs or trace spans.
+//!
+//! Observers are called synchronously, on the task doing the work, so they should return
+//! quickly. Without observers, events are not constructed.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ssi::jwk::Algorithm;
+
+use crate::verification::VerificationReport;
+
+/// An instrumentation event.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// A DID resolution started.
+    ResolutionStarted { did: &'a str },
+    /// A DID resolution finished, with the error of its resolution metadata, if any.
+    ResolutionFinished {
+        did: &'a str,
+        error: Option<&'a str>,
+        elapsed: Duration,
+    },
+    /// A DID resolver endpoint of a [`ResolverFederation`](crate::resolver_config::ResolverFederation)
+    /// became healthy or unhealthy.
+    EndpointHealth { url: &'a str, healthy: bool },
+    /// A context document was fetched, e.g. to check its `digestSRI`.
+    ContextFetched {
+        url: &'a str,
+        error: Option<&'a str>,
+        elapsed: Duration,
+    },
+    /// A document and proof options were canonicalized to prepare a linked data proof, or
+    /// (`cached`) the preparation was taken from a
+    /// [`CanonicalizationCache`](crate::canonicalization::CanonicalizationCache).
+    Canonicalized {
+        cached: bool,
+        error: Option<&'a str>,
+        elapsed: Duration,
+    },
+    /// A signer was called.
+    Signed {
+        algorithm: Algorithm,
+        error: Option<&'a str>,
+        elapsed: Duration,
+    },
+    /// A credential or presentation was verified. The report lists the checks performed, and
+    /// their errors.
+    Verified {
+        presentation: bool,
+        report: &'a VerificationReport,
+    },
+}
+
+/// Receiver of instrumentation events.
+pub trait Observer: Send + Sync {
+    fn event(&self, event: &Event<'_>);
+}
+
+lazy_static! {
+    static ref OBSERVERS: RwLock<Vec<Arc<dyn Observer>>> = RwLock::new(Vec::new());
+}
+
+/// Register an observer, in addition to those registered earlier.
+pub fn add_observer(observer: Arc<dyn Observer>) {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.push(observer);
+    }
+}
+
+/// Remove all registered observers.
+pub fn clear_observers() {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.clear();
+    }
+}
+
+fn observers() -> Vec<Arc<dyn Observer>> {
+    match OBSERVERS.read() {
+        Ok(observers) => observers.clone(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Report an event to the registered observers. The event is only constructed if there are any.
+pub(crate) fn emit<'a>(event: impl FnOnce() -> Event<'a>) {
+    let observers = observers();
+    if observers.is_empty() {
+        return;
+    }
+    let event = event();
+    for observer in observers {
+        observer.event(&event);
+    }
+}
+
+/// Start time of an operation. Measured with [`chrono`], which, unlike [`std::time::Instant`], is
+/// available on WASM.
+pub(crate) struct Timer(DateTime<Utc>);
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Self(Utc::now())
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        (Utc::now() - self.0).to_std().unwrap_or_default()
+    }
+}
+
+/// Error message of a result, for an event.
+pub(crate) fn error_message<T, E: std::fmt::Display>(result: &Result<T, E>) -> Option<String> {
+    result.as_ref().err().map(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Observer for Recorder {
+        fn event(&self, event: &Event<'_>) {
+            if let Event::ContextFetched { url, error, .. } = event {
+                if url.starts_with("https://example.org/observed/") {
+                    self.0
+                        .lock()
+                        .unwrap()
+                        .push(format!("{} {}", url, error.unwrap_or("ok")));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn observe_events() {
+        let recorder = Arc::new(Recorder::default());
+        add_observer(recorder.clone());
+        let url = "https://example.org/observed/context";
+        emit(|| Event::ContextFetched {
+            url,
+            error: Some("not found"),
+            elapsed: Duration::from_millis(1),
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![format!("{} not found", url)]
+        );
+        let timer = Timer::start();
+        assert!(timer.elapsed() < Duration::from_secs(60));
+    }
+}
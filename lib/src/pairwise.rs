@@ -0,0 +1,156 @@
+//! Pairwise DIDs: a DID per relationship, derived from a master seed.
+//!
+//! An agent that presents a different DID to each verifier need not store a key for each: the
+//! Ed25519 key of a relationship is derived from its master seed and the relationship's
+//! identifier, e.g. the verifier's DID or origin, and the DID is the `did:key` or `did:peer:0`
+//! DID of that key. The same seed and relationship always give the same DID, and the DIDs of
+//! different relationships cannot be linked without the seed.
+//!
+//! The key is the Ed25519 private key (RFC 8032 seed) of 32 bytes of HKDF-SHA256 (RFC 5869)
+//! output, with the master seed as input keying material, [`HKDF_SALT`] as salt, and
+//! `Ed25519:` followed by the relationship identifier as info.
+
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::scalar::Scalar;
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256, Sha512};
+use thiserror::Error;
+
+use ssi::did::Source;
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+
+use crate::multikey::to_multibase;
+use crate::DID_METHODS;
+
+/// DID methods of pairwise DIDs.
+pub const PAIRWISE_METHODS: &[&str] = &["key", "peer"];
+
+/// Minimum length of a master seed, in bytes.
+pub const MIN_SEED_LENGTH: usize = 16;
+
+/// HKDF salt of pairwise key derivation.
+pub const HKDF_SALT: &[u8] = b"didkit-pairwise-did-v1";
+
+#[derive(Error, Debug)]
+pub enum PairwiseError {
+    #[error("Master seed is too short: {0} bytes (minimum 16)")]
+    SeedTooShort(usize),
+    #[error("Relationship identifier is empty")]
+    EmptyRelationship,
+    #[error("Unsupported DID method for pairwise DIDs: {0} (expected key or peer)")]
+    UnsupportedMethod(String),
+    #[error("Unable to generate DID")]
+    UnableToGenerateDID,
+}
+
+/// A pairwise DID, and its key.
+#[derive(Debug, Clone)]
+pub struct PairwiseDID {
+    pub did: String,
+    /// Verification method of the key, for authentication and assertions
+    pub verification_method: String,
+    /// Private key
+    pub key: JWK,
+}
+
+/// Public key of an Ed25519 private key (RFC 8032 section 5.1.5).
+pub(crate) fn ed25519_public_key(secret: &[u8; 32]) -> [u8; 32] {
+    let hash = Sha512::digest(secret);
+    let mut scalar = [0u8; 32];
+    scalar.copy_from_slice(&hash[..32]);
+    scalar[0] &= 248;
+    scalar[31] &= 127;
+    scalar[31] |= 64;
+    (&Scalar::from_bits(scalar) * &ED25519_BASEPOINT_TABLE)
+        .compress()
+        .to_bytes()
+}
+
+/// Derive the Ed25519 key of a relationship from a master seed.
+pub fn derive_key(seed: &[u8], relationship: &str) -> Result<JWK, PairwiseError> {
+    if seed.len() < MIN_SEED_LENGTH {
+        return Err(PairwiseError::SeedTooShort(seed.len()));
+    }
+    if relationship.is_empty() {
+        return Err(PairwiseError::EmptyRelationship);
+    }
+    let info = format!("Ed25519:{}", relationship);
+    let mut secret = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(HKDF_SALT), seed)
+        .expand(info.as_bytes(), &mut secret)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    let public_key = ed25519_public_key(&secret);
+    Ok(JWK::from(Params::OKP(OctetParams {
+        curve: "Ed25519".to_string(),
+        public_key: Base64urlUInt(public_key.to_vec()),
+        private_key: Some(Base64urlUInt(secret.to_vec())),
+    })))
+}
+
+/// Derive the pairwise DID of a relationship from a master seed, with the `key` or `peer` DID
+/// method.
+pub fn derive_did(
+    seed: &[u8],
+    relationship: &str,
+    method: &str,
+) -> Result<PairwiseDID, PairwiseError> {
+    if !PAIRWISE_METHODS.contains(&method) {
+        return Err(PairwiseError::UnsupportedMethod(method.to_string()));
+    }
+    let key = derive_key(seed, relationship)?;
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, method))
+        .ok_or(PairwiseError::UnableToGenerateDID)?;
+    let multibase = to_multibase(&key).map_err(|_| PairwiseError::UnableToGenerateDID)?;
+    Ok(PairwiseDID {
+        verification_method: format!("{}#{}", did, multibase),
+        did,
+        key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_pairwise() {
+        // RFC 8032 section 7.1, test 1
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&hex_decode(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        ));
+        assert_eq!(
+            ed25519_public_key(&secret).to_vec(),
+            hex_decode("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+
+        let seed = [7u8; 32];
+        let alice = derive_did(&seed, "https://verifier.example", "key").unwrap();
+        let again = derive_did(&seed, "https://verifier.example", "key").unwrap();
+        let other = derive_did(&seed, "https://other.example", "key").unwrap();
+        assert!(alice.did.starts_with("did:key:z6Mk"));
+        assert_eq!(alice.did, again.did);
+        assert_ne!(alice.did, other.did);
+        assert!(alice.verification_method.starts_with(&alice.did));
+
+        let peer = derive_did(&seed, "https://verifier.example", "peer").unwrap();
+        assert_eq!(
+            &peer.did["did:peer:0".len()..],
+            &alice.did["did:key:".len()..]
+        );
+        assert!(matches!(
+            derive_did(&seed[..8], "https://verifier.example", "key"),
+            Err(PairwiseError::SeedTooShort(8))
+        ));
+        assert!(derive_did(&seed, "", "key").is_err());
+        assert!(derive_did(&seed, "https://verifier.example", "web").is_err());
+    }
+
+    fn hex_decode(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+}
@@ -0,0 +1,1149 @@
+//! [Presentation Exchange 2.0][pex]: matching credentials to presentation definitions.
+//!
+//! [`evaluate`] finds the credentials satisfying the constraints of each input descriptor of a
+//! [`PresentationDefinition`], and [`select`] chooses credentials satisfying the whole
+//! definition, with the [`PresentationSubmission`] describing them. Supported are:
+//!
+//! - fields, by JSONPath (see [`crate::json_path`]) and JSON Schema `filter` (see
+//!   [`crate::schema`]), and `optional` fields;
+//! - `submission_requirements`, with `all` and `pick` (`count`, `min` and `max`) rules, from
+//!   groups of input descriptors or from nested requirements. `pick` chooses the first
+//!   satisfiable descriptors, as few as allowed;
+//! - `limit_disclosure`: a selectively disclosable credential is selected with the JSON pointers
+//!   of the claims to disclose, for [`crate::redact`]. Other credentials do not satisfy
+//!   `required`;
+//! - `predicate` fields: the value is withheld from a selectively disclosable credential and the
+//!   match only records that it satisfies the filter. The result is asserted by the holder, not
+//!   proven. Credentials that can not withhold the value do not satisfy `required`;
+//! - the `subject_is_issuer`, `is_holder` and `same_subject` relational constraints, with
+//!   `preferred` `same_subject` constraints kept when they can be;
+//! - `format` designations of the definition and of input descriptors, with `alg`, `proof_type`
+//!   and `sd-jwt_alg_values`, and the choice of a presentation format.
+//!
+//! Credentials are given as JSON, with JWT and SD-JWT credentials as strings. Paths are
+//! evaluated on JSON credentials and on the claims of JWTs, with all of the disclosures of
+//! SD-JWTs (with the `format-jwp` feature). Paths go through arrays, so that a path to a subject
+//! property matches a credential with several subjects, and a filter also applies to each item
+//! of an array value, e.g. of `$.type`.
+//!
+//! [pex]: https://identity.foundation/presentation-exchange/spec/v2.0.0/
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::hex;
+use crate::json_path::{self, JSONPathError};
+use crate::schema;
+use crate::verification::decode_jwt_unverified;
+
+/// Presentation format used if a definition does not designate one.
+pub const LDP_VP: &str = "ldp_vp";
+
+/// Presentation formats that can be submitted, in order of preference.
+pub const PRESENTATION_FORMATS: &[&str] = &[LDP_VP, "jwt_vp_json", "jwt_vp"];
+
+const LDP_VC_FORMATS: &[&str] = &["ldp_vc", "di_vc", "ldp"];
+const JWT_VC_FORMATS: &[&str] = &["jwt_vc_json", "jwt_vc", "jwt"];
+const SD_JWT_VC_FORMATS: &[&str] = &["vc+sd-jwt", "dc+sd-jwt"];
+
+#[derive(Error, Debug)]
+pub enum PexError {
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error(transparent)]
+    JSONPath(#[from] JSONPathError),
+    #[error("Invalid submission requirement: {0}")]
+    InvalidRequirement(String),
+    #[error("Predicate field without a filter, in input descriptor {0}")]
+    PredicateWithoutFilter(String),
+    #[error("Input descriptor {0} is not satisfied")]
+    UnsatisfiedDescriptor(String),
+    #[error("Submission requirement {0} is not satisfied")]
+    UnsatisfiedRequirement(String),
+    #[error("The same_subject constraints are not satisfied")]
+    UnsatisfiedSameSubject,
+    #[error("None of the presentation formats is supported: {0}")]
+    UnsupportedPresentationFormat(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Optionality {
+    Required,
+    Preferred,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rule {
+    All,
+    Pick,
+}
+
+/// Algorithms or proof types accepted for a claim format.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FormatDesignation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_type: Option<Vec<String>>,
+    #[serde(rename = "sd-jwt_alg_values", skip_serializing_if = "Option::is_none")]
+    pub sd_jwt_alg_values: Option<Vec<String>>,
+}
+
+/// Claim formats, by name, e.g. `ldp_vc` or `jwt_vp_json`.
+pub type Formats = BTreeMap<String, FormatDesignation>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PresentationDefinition {
+    #[serde(default)]
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Formats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission_requirements: Option<Vec<SubmissionRequirement>>,
+    pub input_descriptors: Vec<InputDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmissionRequirement {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    pub rule: Rule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_nested: Option<Vec<SubmissionRequirement>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InputDescriptor {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Formats>,
+    #[serde(default)]
+    pub constraints: Constraints,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Constraints {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_disclosure: Option<Optionality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_is_issuer: Option<Optionality>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub is_holder: Vec<HolderSubject>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub same_subject: Vec<HolderSubject>,
+}
+
+/// Fields, by `id`, subject to a relational constraint.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HolderSubject {
+    pub field_id: Vec<String>,
+    pub directive: Optionality,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Field {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub path: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<Optionality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intent_to_retain: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PresentationSubmission {
+    pub id: String,
+    pub definition_id: String,
+    pub descriptor_map: Vec<DescriptorMapEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DescriptorMapEntry {
+    pub id: String,
+    pub format: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_nested: Option<Box<DescriptorMapEntry>>,
+}
+
+/// Result of a predicate field.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PredicateResult {
+    /// Field `id`, or its first path
+    pub field: String,
+    pub result: bool,
+}
+
+/// A credential satisfying the constraints of an input descriptor.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DescriptorMatch {
+    pub descriptor_id: String,
+    /// Index of the credential
+    pub credential: usize,
+    /// Claim format of the credential, as designated by the definition
+    pub format: String,
+    /// JSON pointers of the claims to disclose, if disclosure is limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disclose: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predicates: Vec<PredicateResult>,
+    /// Matching values of the fields with an `id`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Vec<Value>>,
+}
+
+/// A credential to present.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedCredential {
+    /// Index of the credential
+    pub credential: usize,
+    pub format: String,
+    /// JSON pointers of the claims to disclose, if disclosure is limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disclose: Option<Vec<String>>,
+}
+
+/// Credentials satisfying a presentation definition.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Selection {
+    /// Format of the presentation to submit, e.g. `ldp_vp`
+    pub presentation_format: String,
+    /// Credentials to present, in the order of the presentation's `verifiableCredential`
+    pub credentials: Vec<SelectedCredential>,
+    /// The match chosen for each submitted input descriptor
+    pub matches: Vec<DescriptorMatch>,
+    pub presentation_submission: PresentationSubmission,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Ldp,
+    Jwt,
+    SdJwt,
+}
+
+impl Kind {
+    fn formats(self) -> &'static [&'static str] {
+        match self {
+            Kind::Ldp => LDP_VC_FORMATS,
+            Kind::Jwt => JWT_VC_FORMATS,
+            Kind::SdJwt => SD_JWT_VC_FORMATS,
+        }
+    }
+}
+
+/// A credential with the claims that paths are evaluated on.
+struct Candidate {
+    kind: Kind,
+    /// JWT `alg`, or the types and cryptosuites of the proofs
+    algs: Vec<String>,
+    claims: Value,
+}
+
+fn jwt_alg(header: &Value) -> Vec<String> {
+    header
+        .get("alg")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .into_iter()
+        .collect()
+}
+
+#[cfg(feature = "format-jwp")]
+fn sd_jwt_candidate(sd_jwt: &str) -> Option<Candidate> {
+    let (header, _payload) = decode_jwt_unverified(sd_jwt.split('~').next()?)?;
+    Some(Candidate {
+        kind: Kind::SdJwt,
+        algs: jwt_alg(&header),
+        claims: crate::redact::sd_jwt_claims(sd_jwt).ok()?,
+    })
+}
+
+#[cfg(not(feature = "format-jwp"))]
+fn sd_jwt_candidate(_sd_jwt: &str) -> Option<Candidate> {
+    None
+}
+
+fn candidate(credential: &Value) -> Option<Candidate> {
+    match credential {
+        Value::String(jwt) if jwt.contains('~') => sd_jwt_candidate(jwt.trim()),
+        Value::String(jwt) => {
+            let (header, claims) = decode_jwt_unverified(jwt)?;
+            Some(Candidate {
+                kind: Kind::Jwt,
+                algs: jwt_alg(&header),
+                claims,
+            })
+        }
+        Value::Object(_) => {
+            let proofs: Vec<&Value> = match credential.get("proof") {
+                Some(Value::Array(proofs)) => proofs.iter().collect(),
+                Some(proof) => vec![proof],
+                None => Vec::new(),
+            };
+            let algs = proofs
+                .into_iter()
+                .flat_map(|proof| {
+                    ["type", "cryptosuite"]
+                        .iter()
+                        .filter_map(move |name| proof.get(*name)?.as_str())
+                })
+                .map(String::from)
+                .collect();
+            Some(Candidate {
+                kind: Kind::Ldp,
+                algs,
+                claims: credential.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn is_presentation_format(name: &str) -> bool {
+    name.ends_with("_vp") || name.ends_with("_vp_json")
+}
+
+impl FormatDesignation {
+    fn accepts(&self, algs: &[String]) -> bool {
+        [&self.alg, &self.proof_type, &self.sd_jwt_alg_values]
+            .iter()
+            .all(|accepted| match accepted {
+                Some(accepted) => algs.iter().any(|alg| accepted.contains(alg)),
+                None => true,
+            })
+    }
+}
+
+/// Name of the designated claim format of a credential, if it is accepted.
+fn format_name(formats: Option<&Formats>, candidate: &Candidate) -> Option<String> {
+    let names = candidate.kind.formats();
+    let formats = match formats {
+        Some(formats) if formats.keys().any(|name| !is_presentation_format(name)) => formats,
+        _ => return Some(names[0].to_string()),
+    };
+    names.iter().find_map(|name| match formats.get(*name) {
+        Some(designation) if designation.accepts(&candidate.algs) => Some(name.to_string()),
+        _ => None,
+    })
+}
+
+/// Negotiate the format of the presentation to submit.
+pub fn presentation_format(definition: &PresentationDefinition) -> Result<&'static str, PexError> {
+    let listed: Vec<&str> = match definition.format {
+        Some(ref formats) => formats
+            .keys()
+            .map(String::as_str)
+            .filter(|name| is_presentation_format(name))
+            .collect(),
+        None => Vec::new(),
+    };
+    if listed.is_empty() {
+        return Ok(LDP_VP);
+    }
+    PRESENTATION_FORMATS
+        .iter()
+        .find(|name| listed.contains(*name))
+        .copied()
+        .ok_or_else(|| PexError::UnsupportedPresentationFormat(listed.join(", ")))
+}
+
+/// Values at the first path of a field that has values matching its filter, with their
+/// pointers.
+fn match_field<'a>(field: &Field, claims: &'a Value) -> Result<Vec<(String, &'a Value)>, PexError> {
+    for path in &field.path {
+        let mut values = Vec::new();
+        for (pointer, value) in json_path::locate(claims, path, true)? {
+            values.push((pointer.clone(), value));
+            if let Value::Array(items) = value {
+                for (i, item) in items.iter().enumerate() {
+                    values.push((format!("{}/{}", pointer, i), item));
+                }
+            }
+        }
+        values.retain(|(_, value)| {
+            field
+                .filter
+                .as_ref()
+                .map_or(true, |filter| schema::validate(filter, value).is_empty())
+        });
+        if !values.is_empty() {
+            return Ok(values);
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn issuer_id(claims: &Value) -> Option<&str> {
+    let issuer = claims
+        .get("issuer")
+        .or_else(|| claims.pointer("/vc/issuer"))
+        .or_else(|| claims.get("iss"))?;
+    issuer
+        .as_str()
+        .or_else(|| issuer.get("id").and_then(Value::as_str))
+}
+
+fn subject_ids(claims: &Value) -> Vec<&str> {
+    let credential = claims.get("vc").unwrap_or(claims);
+    let mut ids: Vec<&str> = schema::subjects(credential)
+        .into_iter()
+        .filter_map(|subject| subject.get("id").and_then(Value::as_str))
+        .collect();
+    ids.extend(claims.get("sub").and_then(Value::as_str));
+    ids
+}
+
+fn match_descriptor(
+    definition: &PresentationDefinition,
+    descriptor: &InputDescriptor,
+    index: usize,
+    candidate: &Candidate,
+    holder: &[String],
+) -> Result<Option<DescriptorMatch>, PexError> {
+    let formats = descriptor
+        .format
+        .as_ref()
+        .or_else(|| definition.format.as_ref());
+    let format = match format_name(formats, candidate) {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+    let constraints = &descriptor.constraints;
+    let disclosable = candidate.kind == Kind::SdJwt;
+    let required = Some(Optionality::Required);
+    let has_required_predicate = constraints
+        .fields
+        .iter()
+        .any(|field| field.predicate == required);
+    let limited = constraints.limit_disclosure.is_some() || has_required_predicate;
+    if !disclosable && (constraints.limit_disclosure == required || has_required_predicate) {
+        return Ok(None);
+    }
+    let mut disclose = Vec::new();
+    let mut predicates = Vec::new();
+    let mut fields = BTreeMap::new();
+    for field in &constraints.fields {
+        if field.predicate.is_some() && field.filter.is_none() {
+            return Err(PexError::PredicateWithoutFilter(descriptor.id.clone()));
+        }
+        let values = match_field(field, &candidate.claims)?;
+        if values.is_empty() {
+            if field.optional {
+                continue;
+            }
+            return Ok(None);
+        }
+        if let Some(ref id) = field.id {
+            let matched = values.iter().map(|(_, value)| (*value).clone()).collect();
+            fields.insert(id.clone(), matched);
+        }
+        if field.predicate.is_some() {
+            predicates.push(PredicateResult {
+                field: field
+                    .id
+                    .clone()
+                    .or_else(|| field.path.first().cloned())
+                    .unwrap_or_default(),
+                result: true,
+            });
+            if disclosable && limited {
+                // The value is withheld.
+                continue;
+            }
+        }
+        disclose.extend(values.into_iter().map(|(pointer, _)| pointer));
+    }
+    if constraints.subject_is_issuer == required {
+        let subjects = subject_ids(&candidate.claims);
+        let self_issued = match issuer_id(&candidate.claims) {
+            Some(issuer) => !subjects.is_empty() && subjects.iter().all(|id| *id == issuer),
+            None => false,
+        };
+        if !self_issued {
+            return Ok(None);
+        }
+    }
+    for is_holder in &constraints.is_holder {
+        let held = is_holder.field_id.iter().all(|id| {
+            fields.get(id).map_or(false, |values| {
+                values
+                    .iter()
+                    .any(|value| holder.iter().any(|did| value == did.as_str()))
+            })
+        });
+        if is_holder.directive == Optionality::Required && !held {
+            return Ok(None);
+        }
+    }
+    let disclose = if disclosable && limited {
+        disclose.sort();
+        disclose.dedup();
+        Some(disclose)
+    } else {
+        None
+    };
+    Ok(Some(DescriptorMatch {
+        descriptor_id: descriptor.id.clone(),
+        credential: index,
+        format,
+        disclose,
+        predicates,
+        fields,
+    }))
+}
+
+/// Find the credentials satisfying the constraints of each input descriptor, in the order of the
+/// descriptors, then of the credentials. `holder` are the DIDs of the holder, for `is_holder`.
+pub fn evaluate(
+    definition: &PresentationDefinition,
+    credentials: &[Value],
+    holder: &[String],
+) -> Result<Vec<DescriptorMatch>, PexError> {
+    let candidates: Vec<(usize, Candidate)> = credentials
+        .iter()
+        .enumerate()
+        .filter_map(|(i, credential)| Some((i, candidate(credential)?)))
+        .collect();
+    let mut matches = Vec::new();
+    for descriptor in &definition.input_descriptors {
+        for (i, candidate) in &candidates {
+            matches.extend(match_descriptor(
+                definition, descriptor, *i, candidate, holder,
+            )?);
+        }
+    }
+    Ok(matches)
+}
+
+fn is_unsatisfied(err: &PexError) -> bool {
+    matches!(
+        err,
+        PexError::UnsatisfiedDescriptor(_) | PexError::UnsatisfiedRequirement(_)
+    )
+}
+
+/// Descriptors chosen to fulfill a submission requirement, by index.
+fn fulfill(
+    requirement: &SubmissionRequirement,
+    definition: &PresentationDefinition,
+    satisfiable: &HashSet<usize>,
+) -> Result<Vec<usize>, PexError> {
+    let name = requirement
+        .name
+        .clone()
+        .or_else(|| requirement.from.clone())
+        .unwrap_or_default();
+    let options: Vec<Option<Vec<usize>>> = match (&requirement.from, &requirement.from_nested) {
+        (Some(group), None) => {
+            let options: Vec<Option<Vec<usize>>> = definition
+                .input_descriptors
+                .iter()
+                .enumerate()
+                .filter(|(_, descriptor)| descriptor.group.contains(group))
+                .map(|(i, _)| Some(vec![i]).filter(|_| satisfiable.contains(&i)))
+                .collect();
+            if options.is_empty() {
+                return Err(PexError::InvalidRequirement(format!(
+                    "no input descriptors in group {}",
+                    group
+                )));
+            }
+            options
+        }
+        (None, Some(nested)) => {
+            let mut options = Vec::new();
+            for requirement in nested {
+                match fulfill(requirement, definition, satisfiable) {
+                    Ok(chosen) => options.push(Some(chosen)),
+                    Err(err) if is_unsatisfied(&err) => options.push(None),
+                    Err(err) => return Err(err),
+                }
+            }
+            options
+        }
+        _ => {
+            return Err(PexError::InvalidRequirement(format!(
+                "{} needs either from or from_nested",
+                name
+            )))
+        }
+    };
+    let total = options.len();
+    let available: Vec<Vec<usize>> = options.into_iter().flatten().collect();
+    let wanted = match requirement.rule {
+        Rule::All => total,
+        Rule::Pick => {
+            let (min, max) = match requirement.count {
+                Some(count) => (count, count),
+                None => (
+                    requirement.min.unwrap_or(0),
+                    requirement.max.unwrap_or(total),
+                ),
+            };
+            if min > max || min > total {
+                return Err(PexError::InvalidRequirement(format!(
+                    "{} picks {} to {} of {}",
+                    name, min, max, total
+                )));
+            }
+            // Pick as few as allowed, but at least one if possible.
+            min.max(available.len().min(1)).min(max)
+        }
+    };
+    if available.len() < wanted {
+        return Err(PexError::UnsatisfiedRequirement(name));
+    }
+    Ok(available.into_iter().take(wanted).flatten().collect())
+}
+
+/// Descriptors to submit, by index, in order.
+fn chosen_descriptors(
+    definition: &PresentationDefinition,
+    satisfiable: &HashSet<usize>,
+) -> Result<Vec<usize>, PexError> {
+    let requirements = match definition.submission_requirements {
+        Some(ref requirements) => requirements,
+        None => {
+            for (i, descriptor) in definition.input_descriptors.iter().enumerate() {
+                if !satisfiable.contains(&i) {
+                    return Err(PexError::UnsatisfiedDescriptor(descriptor.id.clone()));
+                }
+            }
+            return Ok((0..definition.input_descriptors.len()).collect());
+        }
+    };
+    let mut chosen = Vec::new();
+    for requirement in requirements {
+        chosen.extend(fulfill(requirement, definition, satisfiable)?);
+    }
+    chosen.sort_unstable();
+    chosen.dedup();
+    Ok(chosen)
+}
+
+/// Whether the fields of each `same_subject` constraint have a value in common.
+fn same_subjects(constraints: &[&[String]], assigned: &[&DescriptorMatch]) -> bool {
+    constraints.iter().all(|field_ids| {
+        let mut common: Option<Vec<&Value>> = None;
+        let fields = assigned
+            .iter()
+            .flat_map(|m| field_ids.iter().filter_map(move |id| m.fields.get(id)));
+        for values in fields {
+            common = Some(match common {
+                None => values.iter().collect(),
+                Some(common) => common
+                    .into_iter()
+                    .filter(|value| values.contains(*value))
+                    .collect(),
+            });
+        }
+        common.map_or(true, |common| !common.is_empty())
+    })
+}
+
+/// Assign a match to each descriptor, backtracking over the constraints.
+fn assign<'a>(
+    candidates: &[Vec<&'a DescriptorMatch>],
+    constraints: &[&[String]],
+    assigned: &mut Vec<&'a DescriptorMatch>,
+) -> bool {
+    let options = match candidates.get(assigned.len()) {
+        Some(options) => options,
+        None => return true,
+    };
+    // Try the credentials already assigned first, to present as few as possible.
+    let mut options = options.clone();
+    options.sort_by_key(|option| !assigned.iter().any(|m| m.credential == option.credential));
+    for option in options {
+        assigned.push(option);
+        if same_subjects(constraints, assigned) && assign(candidates, constraints, assigned) {
+            return true;
+        }
+        assigned.pop();
+    }
+    false
+}
+
+/// Choose credentials satisfying a presentation definition, and describe them in a presentation
+/// submission. `holder` are the DIDs of the holder, for `is_holder`.
+pub fn select(
+    definition: &PresentationDefinition,
+    credentials: &[Value],
+    holder: &[String],
+) -> Result<Selection, PexError> {
+    let presentation_format = presentation_format(definition)?;
+    let matches = evaluate(definition, credentials, holder)?;
+    let by_descriptor: Vec<Vec<&DescriptorMatch>> = definition
+        .input_descriptors
+        .iter()
+        .map(|descriptor| {
+            matches
+                .iter()
+                .filter(|m| m.descriptor_id == descriptor.id)
+                .collect()
+        })
+        .collect();
+    let satisfiable = by_descriptor
+        .iter()
+        .enumerate()
+        .filter(|(_, matches)| !matches.is_empty())
+        .map(|(i, _)| i)
+        .collect();
+    let chosen = chosen_descriptors(definition, &satisfiable)?;
+    let candidates: Vec<Vec<&DescriptorMatch>> =
+        chosen.iter().map(|i| by_descriptor[*i].clone()).collect();
+    let same_subject: Vec<&HolderSubject> = chosen
+        .iter()
+        .flat_map(|i| &definition.input_descriptors[*i].constraints.same_subject)
+        .collect();
+    let required: Vec<&[String]> = same_subject
+        .iter()
+        .filter(|constraint| constraint.directive == Optionality::Required)
+        .map(|constraint| &constraint.field_id[..])
+        .collect();
+    let all: Vec<&[String]> = same_subject
+        .iter()
+        .map(|constraint| &constraint.field_id[..])
+        .collect();
+    let mut assigned = Vec::new();
+    if !assign(&candidates, &all, &mut assigned) && !assign(&candidates, &required, &mut assigned) {
+        return Err(PexError::UnsatisfiedSameSubject);
+    }
+
+    let mut selected: Vec<SelectedCredential> = Vec::new();
+    let mut descriptor_map = Vec::new();
+    for m in &assigned {
+        let position = match selected.iter().position(|s| s.credential == m.credential) {
+            Some(position) => {
+                let selected = &mut selected[position];
+                selected.disclose = match (selected.disclose.take(), &m.disclose) {
+                    (Some(mut disclose), Some(more)) => {
+                        disclose.extend(more.iter().cloned());
+                        disclose.sort();
+                        disclose.dedup();
+                        Some(disclose)
+                    }
+                    _ => None,
+                };
+                position
+            }
+            None => {
+                selected.push(SelectedCredential {
+                    credential: m.credential,
+                    format: m.format.clone(),
+                    disclose: m.disclose.clone(),
+                });
+                selected.len() - 1
+            }
+        };
+        let entry = match presentation_format {
+            LDP_VP => DescriptorMapEntry {
+                id: m.descriptor_id.clone(),
+                format: m.format.clone(),
+                path: format!("$.verifiableCredential[{}]", position),
+                path_nested: None,
+            },
+            format => DescriptorMapEntry {
+                id: m.descriptor_id.clone(),
+                format: format.to_string(),
+                path: "$".to_string(),
+                path_nested: Some(Box::new(DescriptorMapEntry {
+                    id: m.descriptor_id.clone(),
+                    format: m.format.clone(),
+                    path: format!("$.vp.verifiableCredential[{}]", position),
+                    path_nested: None,
+                })),
+            },
+        };
+        descriptor_map.push(entry);
+    }
+    Ok(Selection {
+        presentation_format: presentation_format.to_string(),
+        credentials: selected,
+        matches: assigned.into_iter().cloned().collect(),
+        presentation_submission: PresentationSubmission {
+            id: hex::random_id(),
+            definition_id: definition.id.clone(),
+            descriptor_map,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use serde_json::json;
+
+    fn definition(value: Value) -> PresentationDefinition {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn credential(types: &[&str], subject: Value) -> Value {
+        json!({
+            "type": types,
+            "issuer": "did:example:issuer",
+            "credentialSubject": subject,
+            "proof": { "type": "Ed25519Signature2018" }
+        })
+    }
+
+    fn encode(value: &Value) -> String {
+        base64::encode_config(serde_json::to_vec(value).unwrap(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn type_descriptor(id: &str, type_: &str, group: &str) -> Value {
+        json!({
+            "id": id,
+            "group": [group],
+            "constraints": {
+                "fields": [{
+                    "path": ["$.type"],
+                    "filter": { "type": "array", "contains": { "const": type_ } }
+                }]
+            }
+        })
+    }
+
+    #[test]
+    fn submission_requirements() {
+        let credentials = vec![
+            credential(
+                &["VerifiableCredential", "A"],
+                json!({ "id": "did:example:alice" }),
+            ),
+            credential(
+                &["VerifiableCredential", "B"],
+                json!({ "id": "did:example:alice" }),
+            ),
+        ];
+        let mut def = definition(json!({
+            "id": "def",
+            "submission_requirements": [{ "rule": "pick", "count": 1, "from": "g" }],
+            "input_descriptors": [
+                type_descriptor("c", "C", "g"),
+                type_descriptor("b", "B", "g"),
+                type_descriptor("a", "A", "g"),
+            ]
+        }));
+        let selection = select(&def, &credentials, &[]).unwrap();
+        assert_eq!(selection.presentation_format, LDP_VP);
+        assert_eq!(
+            selection.presentation_submission.descriptor_map,
+            vec![DescriptorMapEntry {
+                id: "b".to_string(),
+                format: "ldp_vc".to_string(),
+                path: "$.verifiableCredential[0]".to_string(),
+                path_nested: None,
+            }]
+        );
+
+        def.submission_requirements = Some(vec![SubmissionRequirement {
+            name: Some("all".to_string()),
+            purpose: None,
+            rule: Rule::All,
+            count: None,
+            min: None,
+            max: None,
+            from: Some("g".to_string()),
+            from_nested: None,
+        }]);
+        assert!(matches!(
+            select(&def, &credentials, &[]),
+            Err(PexError::UnsatisfiedRequirement(_))
+        ));
+
+        let def = definition(json!({
+            "id": "def",
+            "submission_requirements": [{
+                "rule": "pick",
+                "min": 2,
+                "from_nested": [
+                    { "rule": "all", "from": "a" },
+                    { "rule": "all", "from": "b" },
+                    { "rule": "all", "from": "c" }
+                ]
+            }],
+            "format": { "jwt_vp_json": { "alg": ["EdDSA"] } },
+            "input_descriptors": [
+                type_descriptor("a", "A", "a"),
+                type_descriptor("b", "B", "b"),
+                type_descriptor("c", "C", "c"),
+            ]
+        }));
+        let selection = select(&def, &credentials, &[]).unwrap();
+        assert_eq!(selection.presentation_format, "jwt_vp_json");
+        let map = &selection.presentation_submission.descriptor_map;
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[1].path, "$");
+        assert_eq!(
+            map[1].path_nested.as_ref().unwrap().path,
+            "$.vp.verifiableCredential[1]"
+        );
+    }
+
+    #[test]
+    fn formats_and_relational_constraints() {
+        let jwt = format!(
+            "{}.{}.c2ln",
+            encode(&json!({ "alg": "ES256" })),
+            encode(&json!({
+                "iss": "did:example:issuer",
+                "sub": "did:example:alice",
+                "vc": { "type": ["VerifiableCredential", "A"] }
+            }))
+        );
+        let credentials = vec![
+            credential(
+                &["VerifiableCredential", "A"],
+                json!({ "id": "did:example:bob" }),
+            ),
+            Value::String(jwt),
+            credential(
+                &["VerifiableCredential", "B"],
+                json!({ "id": "did:example:alice" }),
+            ),
+        ];
+        let subject_field =
+            |id: &str| json!({ "id": id, "path": ["$.credentialSubject.id", "$.sub"] });
+        let def = definition(json!({
+            "id": "def",
+            "format": { "jwt_vc_json": { "alg": ["ES256"] }, "ldp_vc": { "proof_type": ["Ed25519Signature2018"] } },
+            "input_descriptors": [{
+                "id": "a",
+                "constraints": {
+                    "fields": [
+                        { "path": ["$.type", "$.vc.type"], "filter": { "contains": { "const": "A" } } },
+                        subject_field("a_subject")
+                    ],
+                    "is_holder": [{ "field_id": ["a_subject"], "directive": "required" }],
+                    "same_subject": [{ "field_id": ["a_subject", "b_subject"], "directive": "required" }]
+                }
+            }, {
+                "id": "b",
+                "constraints": {
+                    "fields": [
+                        { "path": ["$.type"], "filter": { "contains": { "const": "B" } } },
+                        subject_field("b_subject")
+                    ]
+                }
+            }]
+        }));
+        let holder = vec!["did:example:alice".to_string()];
+        let selection = select(&def, &credentials, &holder).unwrap();
+        let chosen: Vec<(usize, &str)> = selection
+            .credentials
+            .iter()
+            .map(|s| (s.credential, &s.format[..]))
+            .collect();
+        assert_eq!(chosen, vec![(1, "jwt_vc_json"), (2, "ldp_vc")]);
+        assert!(matches!(
+            select(&def, &credentials, &[]),
+            Err(PexError::UnsatisfiedDescriptor(_))
+        ));
+
+        let mut def = def;
+        def.format =
+            Some(serde_json::from_value(json!({ "jwt_vc_json": { "alg": ["EdDSA"] } })).unwrap());
+        assert!(evaluate(&def, &credentials, &holder).unwrap().is_empty());
+
+        let def = definition(json!({
+            "id": "def",
+            "input_descriptors": [{
+                "id": "self",
+                "constraints": { "subject_is_issuer": "required" }
+            }]
+        }));
+        let self_issued = credential(
+            &["VerifiableCredential"],
+            json!({ "id": "did:example:issuer" }),
+        );
+        let matches = evaluate(&def, &[credentials[0].clone(), self_issued], &[]).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].credential, 1);
+    }
+
+    #[cfg(feature = "format-jwp")]
+    #[test]
+    fn limit_disclosure_and_predicates() {
+        use sha2::{Digest, Sha256};
+        let disclosure = |value: Value| encode(&value);
+        let digest = |disclosure: &str| {
+            base64::encode_config(
+                Sha256::digest(disclosure.as_bytes()),
+                base64::URL_SAFE_NO_PAD,
+            )
+        };
+        let name = disclosure(json!(["salt1", "given_name", "Alice"]));
+        let birthdate = disclosure(json!(["salt2", "birthdate", "1990-01-01"]));
+        let jwt = format!(
+            "{}.{}.c2ln",
+            encode(&json!({ "alg": "ES256" })),
+            encode(&json!({
+                "iss": "did:example:issuer",
+                "vct": "https://example.org/identity",
+                "_sd_alg": "sha-256",
+                "_sd": [digest(&name), digest(&birthdate)]
+            }))
+        );
+        let sd_jwt = Value::String(format!("{}~{}~{}~", jwt, name, birthdate));
+        let credentials = vec![
+            credential(&["VerifiableCredential"], json!({ "given_name": "Alice" })),
+            sd_jwt,
+        ];
+        let def = definition(json!({
+            "id": "def",
+            "input_descriptors": [{
+                "id": "identity",
+                "constraints": {
+                    "limit_disclosure": "required",
+                    "fields": [
+                        { "path": ["$.given_name", "$.credentialSubject.given_name"] },
+                        {
+                            "id": "adult",
+                            "path": ["$.birthdate"],
+                            "filter": { "type": "string", "formatMaximum": "2005-01-01" },
+                            "predicate": "required"
+                        }
+                    ]
+                }
+            }]
+        }));
+        let selection = select(&def, &credentials, &[]).unwrap();
+        assert_eq!(
+            selection.credentials,
+            vec![SelectedCredential {
+                credential: 1,
+                format: "vc+sd-jwt".to_string(),
+                disclose: Some(vec!["/given_name".to_string()]),
+            }]
+        );
+        assert_eq!(
+            selection.matches[0].predicates,
+            vec![PredicateResult {
+                field: "adult".to_string(),
+                result: true
+            }]
+        );
+    }
+
+    /// Random definitions, over random credentials: every selected credential satisfies the
+    /// filters of its descriptor, and pick rules submit the allowed number of descriptors.
+    #[test]
+    fn selection_properties() {
+        let types = ["A", "B", "C", "D"];
+        let subjects = ["did:example:alice", "did:example:bob"];
+        let mut rng = StdRng::seed_from_u64(184);
+        for _ in 0..200 {
+            let credentials: Vec<Value> = (0..rng.gen_range(0, 5))
+                .map(|_| {
+                    let type_ = types[rng.gen_range(0, types.len())];
+                    let subject = subjects[rng.gen_range(0, subjects.len())];
+                    credential(&["VerifiableCredential", type_], json!({ "id": subject }))
+                })
+                .collect();
+            let count = rng.gen_range(1, 4);
+            let descriptors: Vec<Value> = (0..rng.gen_range(count, 5))
+                .map(|i| {
+                    let type_ = types[rng.gen_range(0, types.len())];
+                    let mut descriptor = type_descriptor(&format!("d{}", i), type_, "g");
+                    if rng.gen_bool(0.3) {
+                        descriptor["constraints"]["fields"]
+                            .as_array_mut()
+                            .unwrap()
+                            .push(json!({
+                                "path": ["$.credentialSubject.id"],
+                                "filter": { "const": subjects[0] }
+                            }));
+                    }
+                    descriptor
+                })
+                .collect();
+            let mut def = json!({ "id": "def", "input_descriptors": descriptors });
+            let pick = rng.gen_bool(0.5);
+            if pick {
+                def["submission_requirements"] =
+                    json!([{ "rule": "pick", "count": count, "from": "g" }]);
+            }
+            let def = definition(def);
+            let matches = evaluate(&def, &credentials, &[]).unwrap();
+            let satisfiable: HashSet<&str> = matches.iter().map(|m| &m.descriptor_id[..]).collect();
+            match select(&def, &credentials, &[]) {
+                Ok(selection) => {
+                    let submitted = selection.presentation_submission.descriptor_map.len();
+                    if pick {
+                        assert_eq!(submitted, count);
+                    } else {
+                        assert_eq!(submitted, def.input_descriptors.len());
+                    }
+                    for m in &selection.matches {
+                        let descriptor = def
+                            .input_descriptors
+                            .iter()
+                            .find(|d| d.id == m.descriptor_id)
+                            .unwrap();
+                        let credential = &credentials[m.credential];
+                        for field in &descriptor.constraints.fields {
+                            let claims = candidate(credential).unwrap().claims;
+                            assert!(!match_field(field, &claims).unwrap().is_empty());
+                        }
+                        assert!(selection
+                            .credentials
+                            .iter()
+                            .any(|s| s.credential == m.credential));
+                    }
+                }
+                Err(PexError::UnsatisfiedDescriptor(id)) => {
+                    assert!(!pick);
+                    assert!(!satisfiable.contains(&id[..]));
+                }
+                Err(PexError::UnsatisfiedRequirement(_)) => {
+                    assert!(pick);
+                    assert!(satisfiable.len() < count);
+                }
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Additional [did:pkh][did-pkh] namespaces: Solana, Stacks, Aptos and Cosmos.
+//!
+//! [`DIDPKHChains`] takes the place of `did_pkh::DIDPKH` in [`crate::DID_METHODS`]: DIDs in the
+//! namespaces below are generated and resolved here, and all others are passed through to
+//! `DIDPKH`.
+//!
+//! | Key pattern    | CAIP-2 chain ID                            | Address                                  |
+//! |----------------|--------------------------------------------|------------------------------------------|
+//! | `pkh:solana`   | `solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp`  | base58 Ed25519 public key                |
+//! | `pkh:stacks`   | `stacks:1`                                 | c32check P2PKH of secp256k1 public key   |
+//! | `pkh:aptos`    | `aptos:1`                                  | SHA3-256 authentication key, hex         |
+//! | `pkh:cosmos`   | `cosmos:cosmoshub-4`                       | bech32 (`cosmos`) of secp256k1 key hash  |
+//!
+//! A Solana address is its public key, so Solana verification methods include it as
+//! `publicKeyJwk`. The other addresses are hashes, so their verification methods carry only the
+//! `blockchainAccountId`, and a proof must provide the public key.
+//!
+//! [did-pkh]: https://github.com/w3c-ccg/did-pkh/blob/main/did-pkh-method-draft.md
+
+use async_trait::async_trait;
+use bech32::ToBase32;
+use did_pkh::DIDPKH;
+use ripemd160::Ripemd160;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sha3::Sha3_256;
+use ssi::did::{DIDMethod, Document, Source};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata, ERROR_INVALID_DID,
+};
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+
+use crate::hex;
+
+pub const SOLANA_MAINNET: &str = "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp";
+pub const STACKS_MAINNET: &str = "stacks:1";
+pub const APTOS_MAINNET: &str = "aptos:1";
+pub const COSMOS_HUB: &str = "cosmos:cosmoshub-4";
+
+/// Stacks mainnet single-signature (P2PKH) address version
+const STACKS_VERSION_P2PKH: u8 = 22;
+const C32_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// did:pkh with additional namespaces.
+pub struct DIDPKHChains;
+
+fn ed25519_public_key(jwk: &JWK) -> Option<&[u8]> {
+    match jwk.params {
+        Params::OKP(ref params) if params.curve == "Ed25519" => Some(&params.public_key.0),
+        _ => None,
+    }
+}
+
+/// Compressed SEC1 encoding of a secp256k1 public key.
+fn secp256k1_compressed_public_key(jwk: &JWK) -> Option<Vec<u8>> {
+    match jwk.params {
+        Params::EC(ref params) if params.curve.as_deref() == Some("secp256k1") => {
+            let x = &params.x_coordinate.as_ref()?.0;
+            let y = &params.y_coordinate.as_ref()?.0;
+            if x.len() != 32 || y.len() != 32 {
+                return None;
+            }
+            let mut key = vec![if y[31] & 1 == 1 { 0x03 } else { 0x02 }];
+            key.extend_from_slice(x);
+            Some(key)
+        }
+        _ => None,
+    }
+}
+
+fn hash160(data: &[u8]) -> Vec<u8> {
+    Ripemd160::digest(&Sha256::digest(data)).to_vec()
+}
+
+/// Crockford base32 encoding as used by Stacks, keeping leading zero bytes as `0` characters.
+fn c32_encode(input: &[u8]) -> String {
+    let mut result = Vec::new();
+    let mut carry: u16 = 0;
+    let mut carry_bits: u16 = 0;
+    for byte in input.iter().rev() {
+        let byte = *byte as u16;
+        let low_bits_to_take = 5 - carry_bits;
+        let low_bits = byte & ((1 << low_bits_to_take) - 1);
+        result.push(C32_ALPHABET[((low_bits << carry_bits) + carry) as usize]);
+        carry_bits = 8 + carry_bits - 5;
+        carry = byte >> (8 - carry_bits);
+        if carry_bits >= 5 {
+            result.push(C32_ALPHABET[(carry & 0x1f) as usize]);
+            carry_bits -= 5;
+            carry >>= 5;
+        }
+    }
+    if carry_bits > 0 {
+        result.push(C32_ALPHABET[carry as usize]);
+    }
+    while result.last() == Some(&C32_ALPHABET[0]) {
+        result.pop();
+    }
+    for byte in input {
+        if *byte != 0 {
+            break;
+        }
+        result.push(C32_ALPHABET[0]);
+    }
+    result.reverse();
+    String::from_utf8(result).unwrap_or_default()
+}
+
+fn c32check_address(version: u8, data: &[u8]) -> String {
+    let mut versioned = vec![version];
+    versioned.extend_from_slice(data);
+    let checksum = Sha256::digest(&Sha256::digest(&versioned));
+    let mut payload = data.to_vec();
+    payload.extend_from_slice(&checksum[..4]);
+    format!(
+        "S{}{}",
+        C32_ALPHABET[version as usize] as char,
+        c32_encode(&payload)
+    )
+}
+
+/// Get the CAIP-10 account ID of a key in one of the namespaces handled here.
+fn account_id(jwk: &JWK, namespace: &str) -> Option<String> {
+    match namespace {
+        "solana" => {
+            let address = bs58::encode(ed25519_public_key(jwk)?).into_string();
+            Some(format!("{}:{}", SOLANA_MAINNET, address))
+        }
+        "stacks" => {
+            let key_hash = hash160(&secp256k1_compressed_public_key(jwk)?);
+            let address = c32check_address(STACKS_VERSION_P2PKH, &key_hash);
+            Some(format!("{}:{}", STACKS_MAINNET, address))
+        }
+        "aptos" => {
+            // Single-signature Ed25519 authentication key
+            let mut hasher = Sha3_256::new();
+            hasher.update(ed25519_public_key(jwk)?);
+            hasher.update([0u8]);
+            let address = hex::encode(&hasher.finalize());
+            Some(format!("{}:0x{}", APTOS_MAINNET, address))
+        }
+        "cosmos" => {
+            let key_hash = hash160(&secp256k1_compressed_public_key(jwk)?);
+            let address =
+                bech32::encode("cosmos", key_hash.to_base32(), bech32::Variant::Bech32).ok()?;
+            Some(format!("{}:{}", COSMOS_HUB, address))
+        }
+        _ => None,
+    }
+}
+
+/// Verification method type and public key, if derivable, for an account ID.
+fn verification_method(account_id: &str) -> Option<(&'static str, Option<JWK>)> {
+    let mut parts = account_id.splitn(3, ':');
+    let (namespace, _reference, address) = (parts.next()?, parts.next()?, parts.next()?);
+    match namespace {
+        "solana" => {
+            let public_key = bs58::decode(address).into_vec().ok()?;
+            if public_key.len() != 32 {
+                return None;
+            }
+            let jwk = JWK::from(Params::OKP(OctetParams {
+                curve: "Ed25519".to_string(),
+                public_key: Base64urlUInt(public_key),
+                private_key: None,
+            }));
+            Some(("Ed25519VerificationKey2018", Some(jwk)))
+        }
+        "stacks" if address.starts_with('S') => Some(("EcdsaSecp256k1RecoveryMethod2020", None)),
+        "aptos" if address.starts_with("0x") && address.len() == 66 => {
+            Some(("Ed25519VerificationKey2018", None))
+        }
+        "cosmos" => {
+            let (hrp, _data, _variant) = bech32::decode(address).ok()?;
+            if hrp.is_empty() {
+                return None;
+            }
+            Some(("EcdsaSecp256k1RecoveryMethod2020", None))
+        }
+        _ => None,
+    }
+}
+
+fn is_chain_namespace(namespace: &str) -> bool {
+    matches!(namespace, "solana" | "stacks" | "aptos" | "cosmos")
+}
+
+fn resolve_chain_did(did: &str) -> Option<Document> {
+    let account_id = did.strip_prefix("did:pkh:")?;
+    let (type_, jwk) = verification_method(account_id)?;
+    let vm_id = format!("{}#blockchainAccountId", did);
+    let mut vm = json!({
+        "id": vm_id,
+        "type": type_,
+        "controller": did,
+        "blockchainAccountId": account_id,
+    });
+    if let Some(jwk) = jwk {
+        vm["publicKeyJwk"] = serde_json::to_value(jwk).ok()?;
+    }
+    let doc = json!({
+        "@context": [
+            "https://www.w3.org/ns/did/v1",
+            {
+                "blockchainAccountId": "https://w3id.org/security#blockchainAccountId",
+                "publicKeyJwk": {
+                    "@id": "https://w3id.org/security#publicKeyJwk",
+                    "@type": "@json"
+                },
+                type_: format!("https://w3id.org/security#{}", type_),
+            }
+        ],
+        "id": did,
+        "verificationMethod": [vm],
+        "authentication": [vm_id],
+        "assertionMethod": [vm_id],
+    });
+    serde_json::from_value(doc).ok()
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for DIDPKHChains {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let namespace = did
+            .strip_prefix("did:pkh:")
+            .and_then(|id| id.split(':').next());
+        match namespace {
+            Some(namespace) if is_chain_namespace(namespace) => match resolve_chain_did(did) {
+                Some(doc) => (
+                    ResolutionMetadata::default(),
+                    Some(doc),
+                    Some(DocumentMetadata::default()),
+                ),
+                None => (
+                    ResolutionMetadata {
+                        error: Some(ERROR_INVALID_DID.to_string()),
+                        ..Default::default()
+                    },
+                    None,
+                    None,
+                ),
+            },
+            _ => DIDPKH.resolve(did, input_metadata).await,
+        }
+    }
+}
+
+impl DIDMethod for DIDPKHChains {
+    fn name(&self) -> &'static str {
+        DIDPKH.name()
+    }
+
+    fn generate(&self, source: &Source) -> Option<String> {
+        match source {
+            Source::KeyAndPattern(jwk, pattern) if is_chain_namespace(pattern) => {
+                Some(format!("did:pkh:{}", account_id(jwk, pattern)?))
+            }
+            _ => DIDPKH.generate(source),
+        }
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c32check() {
+        assert_eq!(c32_encode(&[]), "");
+        assert_eq!(c32_encode(&[0, 0, 1]), "001");
+        assert_eq!(c32_encode(&[0xff]), "7Z");
+        let address = c32check_address(STACKS_VERSION_P2PKH, &[0u8; 20]);
+        assert!(address.starts_with("SP"));
+    }
+
+    #[test]
+    fn generate_and_resolve() {
+        let ed25519_key = JWK::generate_ed25519().unwrap();
+        let did = DIDPKHChains
+            .generate(&Source::KeyAndPattern(&ed25519_key, "solana"))
+            .unwrap();
+        assert!(did.starts_with(&format!("did:pkh:{}:", SOLANA_MAINNET)));
+        let doc = resolve_chain_did(&did).unwrap();
+        let doc = serde_json::to_value(doc).unwrap();
+        assert_eq!(
+            doc["verificationMethod"][0]["publicKeyJwk"],
+            serde_json::to_value(ed25519_key.to_public()).unwrap()
+        );
+
+        let did = DIDPKHChains
+            .generate(&Source::KeyAndPattern(&ed25519_key, "aptos"))
+            .unwrap();
+        assert_eq!(did.len(), "did:pkh:aptos:1:0x".len() + 64);
+        assert!(resolve_chain_did(&did).is_some());
+
+        let secp256k1_key: JWK = serde_json::from_value(json!({
+            "kty": "EC",
+            "crv": "secp256k1",
+            "x": "yclqMZ0MtyVkKm1eBh2AyaUtsqT0l5RJM3g4SzRT96A",
+            "y": "yQzUwKnftWCJPGs-faGaHiYi1sxA6fGJVw2Px_LCNe8"
+        }))
+        .unwrap();
+        let did = DIDPKHChains
+            .generate(&Source::KeyAndPattern(&secp256k1_key, "cosmos"))
+            .unwrap();
+        assert!(did.starts_with("did:pkh:cosmos:cosmoshub-4:cosmos1"));
+        assert!(resolve_chain_did(&did).is_some());
+        let did = DIDPKHChains
+            .generate(&Source::KeyAndPattern(&secp256k1_key, "stacks"))
+            .unwrap();
+        assert!(did.starts_with("did:pkh:stacks:1:SP"));
+        assert!(resolve_chain_did(&did).is_some());
+
+        assert!(DIDPKHChains
+            .generate(&Source::KeyAndPattern(&ed25519_key, "cosmos"))
+            .is_none());
+    }
+}
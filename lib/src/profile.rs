@@ -0,0 +1,336 @@
+//! Named verification profiles.
+//!
+//! A [`Profile`] bundles the [`Policy`] objects that an ecosystem mandates of verifiers: the
+//! checks to perform, the proof formats, proof types and signature algorithms to accept, and the
+//! options that must be given, such as a challenge for presentations. Profiles compose with
+//! [`Profile::and`]: the result enforces the policies of both.
+//!
+//! A profile is applied in two steps: [`Profile::apply`] turns on its checks in the verification
+//! options, failing if a required option is missing, and [`Profile::check`] rejects, in the
+//! verification report, proofs that it does not accept. [`Profile::verify_credential`] and
+//! [`Profile::verify_presentation`] do both around a verification.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::error::Error;
+use crate::securing::DocumentKind;
+use crate::verification::{ErrorCode, MissingExpiration, PolicyCheck, VerificationReport};
+use crate::{
+    verify_credential, verify_presentation, CredentialOrJWT, DIDResolver, JWTOrLDPOptions,
+    PresentationOrJWT, ProofFormat,
+};
+
+/// Names of the built-in profiles.
+pub const PROFILES: &[&str] = &["vc-api", "ebsi", "oid4vp-haip"];
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("Unknown verification profile: {0} (expected one of: {})", PROFILES.join(", "))]
+    UnknownProfile(String),
+    #[error("Verification profile {0} requires the {1} option")]
+    MissingOption(String, &'static str),
+    #[error(transparent)]
+    DIDKit(#[from] Error),
+}
+
+/// A requirement of a verification profile.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Policy {
+    /// Issuers and holders must use one of these DID methods
+    DIDMethods(Vec<String>),
+    /// Proofs must be in one of these proof formats
+    ProofFormats(Vec<ProofFormat>),
+    /// Linked data proofs must be of one of these types
+    ProofTypes(Vec<String>),
+    /// JWT, VC-JOSE and VC-COSE proofs must use one of these signature algorithms
+    Algorithms(Vec<String>),
+    /// Treatment of credentials without an expiration date, unless the options are stricter
+    MissingExpiration(MissingExpiration),
+    /// Credentials in a presentation must be bound to its holder
+    HolderBinding,
+    /// Credentials in a presentation must be verified too
+    VerifyCredentials,
+    /// Presentations must be verified against an expected challenge
+    RequireChallenge,
+    /// Presentations must be verified against an expected domain
+    RequireDomain,
+}
+
+/// A named set of policies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub policies: Vec<Policy>,
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn strictness(missing_expiration: MissingExpiration) -> u8 {
+    match missing_expiration {
+        MissingExpiration::Allow => 0,
+        MissingExpiration::Warn => 1,
+        MissingExpiration::Error => 2,
+    }
+}
+
+impl Profile {
+    pub fn new(name: &str, policies: Vec<Policy>) -> Self {
+        Self {
+            name: name.to_string(),
+            policies,
+        }
+    }
+
+    /// Get a built-in profile by name:
+    ///
+    /// - `vc-api`: [VC API][vc-api] interoperability: Data Integrity proofs, of the suites of the
+    ///   VC API test suites; challenges for presentations, whose credentials are verified too.
+    /// - `ebsi`: [EBSI][ebsi] JWT credentials and presentations, signed with ES256 or ES256K by
+    ///   `did:ebsi` or `did:key` DIDs; credentials bound to the holder, with an expiration date or
+    ///   a warning; presentations with a nonce (challenge) and audience (domain).
+    /// - `oid4vp-haip`: the JWT credentials of the [OpenID4VC High Assurance Interoperability
+    ///   Profile][haip]: ES256 signatures, holder binding and the verifier's nonce and client ID.
+    ///   SD-JWT VCs and mdocs are not supported.
+    ///
+    /// [vc-api]: https://w3c-ccg.github.io/vc-api/
+    /// [ebsi]: https://hub.ebsi.eu/vc-framework
+    /// [haip]: https://openid.net/specs/openid4vc-high-assurance-interoperability-profile-1_0.html
+    pub fn named(name: &str) -> Option<Self> {
+        let policies = match name {
+            "vc-api" => vec![
+                Policy::ProofFormats(vec![ProofFormat::LDP]),
+                Policy::ProofTypes(strings(&[
+                    "Ed25519Signature2018",
+                    "Ed25519Signature2020",
+                    "JsonWebSignature2020",
+                    "EcdsaSecp256k1Signature2019",
+                    "DataIntegrityProof",
+                ])),
+                Policy::RequireChallenge,
+                Policy::VerifyCredentials,
+            ],
+            "ebsi" => vec![
+                Policy::DIDMethods(strings(&["ebsi", "key"])),
+                Policy::ProofFormats(vec![ProofFormat::JWT]),
+                Policy::Algorithms(strings(&["ES256", "ES256K"])),
+                Policy::MissingExpiration(MissingExpiration::Warn),
+                Policy::HolderBinding,
+                Policy::VerifyCredentials,
+                Policy::RequireChallenge,
+                Policy::RequireDomain,
+            ],
+            "oid4vp-haip" => vec![
+                Policy::ProofFormats(vec![ProofFormat::JWT, ProofFormat::VcJose]),
+                Policy::Algorithms(strings(&["ES256"])),
+                Policy::HolderBinding,
+                Policy::VerifyCredentials,
+                Policy::RequireChallenge,
+                Policy::RequireDomain,
+            ],
+            _ => return None,
+        };
+        Some(Self::new(name, policies))
+    }
+
+    /// Combine two profiles, enforcing the policies of both.
+    pub fn and(mut self, other: Profile) -> Self {
+        self.name = match (self.name.is_empty(), other.name.is_empty()) {
+            (_, true) => self.name,
+            (true, false) => other.name,
+            (false, false) => format!("{}+{}", self.name, other.name),
+        };
+        self.policies.extend(other.policies);
+        self
+    }
+
+    /// Turn on the profile's checks in the verification options of a credential or presentation,
+    /// and check that the options it requires are set.
+    pub fn apply(
+        &self,
+        kind: DocumentKind,
+        options: &mut JWTOrLDPOptions,
+    ) -> Result<(), ProfileError> {
+        let missing = |option| Err(ProfileError::MissingOption(self.name.clone(), option));
+        let presentation = kind == DocumentKind::Presentation;
+        let verification_options = &mut options.verification_options;
+        for policy in &self.policies {
+            match policy {
+                Policy::DIDMethods(methods) => {
+                    // Only the methods allowed by both the options and the profile
+                    let allowed = match verification_options.allowed_did_methods.take() {
+                        Some(allowed) => allowed
+                            .into_iter()
+                            .filter(|method| methods.contains(method))
+                            .collect(),
+                        None => methods.clone(),
+                    };
+                    verification_options.allowed_did_methods = Some(allowed);
+                }
+                Policy::MissingExpiration(missing_expiration) => {
+                    let current = verification_options.missing_expiration;
+                    if current.map_or(true, |current| {
+                        strictness(current) < strictness(*missing_expiration)
+                    }) {
+                        verification_options.missing_expiration = Some(*missing_expiration);
+                    }
+                }
+                Policy::HolderBinding => verification_options.require_holder_binding = true,
+                Policy::VerifyCredentials => verification_options.verify_credentials = true,
+                Policy::RequireChallenge if presentation => {
+                    if options.ldp_options.challenge.is_none()
+                        && verification_options.expected_challenge.is_none()
+                    {
+                        return missing("challenge");
+                    }
+                }
+                Policy::RequireDomain if presentation => {
+                    if options.ldp_options.domain.is_none()
+                        && verification_options.expected_domain.is_none()
+                    {
+                        return missing("domain");
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject the proofs that the profile does not accept, in a verification report and the
+    /// reports of its embedded credentials.
+    pub fn check(&self, report: &mut VerificationReport) {
+        let mut rejected = Vec::new();
+        for proof in &report.proofs {
+            let name = proof.type_.as_deref().unwrap_or("unknown").to_string();
+            for policy in &self.policies {
+                let accepted = match (policy, &proof.proof_format) {
+                    (Policy::ProofFormats(formats), Some(format)) => formats.contains(format),
+                    (Policy::ProofFormats(_), None) => false,
+                    (Policy::ProofTypes(types), Some(ProofFormat::LDP)) => proof
+                        .type_
+                        .as_ref()
+                        .map_or(false, |type_| types.contains(type_)),
+                    (Policy::Algorithms(algorithms), Some(format))
+                        if format != &ProofFormat::LDP =>
+                    {
+                        let algorithm = proof.algorithm.as_ref();
+                        algorithm.map_or(false, |algorithm| algorithms.contains(algorithm))
+                    }
+                    _ => true,
+                };
+                if !accepted {
+                    rejected.push(format!("{} (profile {})", name, self.name));
+                    break;
+                }
+            }
+        }
+        for message in rejected {
+            report.push_error(ErrorCode::DisallowedProof, &message);
+        }
+        for credential in &mut report.credentials {
+            self.check(credential);
+        }
+        if !self.policies.is_empty() {
+            report.policy_checks.push(PolicyCheck::Profile);
+        }
+    }
+
+    /// Verify a credential according to the profile.
+    pub async fn verify_credential(
+        &self,
+        credential: &CredentialOrJWT,
+        options: &JWTOrLDPOptions,
+        resolver: &dyn DIDResolver,
+    ) -> Result<VerificationReport, ProfileError> {
+        let mut options = options.clone();
+        self.apply(DocumentKind::Credential, &mut options)?;
+        let mut report = verify_credential(credential, &options, resolver).await?;
+        self.check(&mut report);
+        Ok(report)
+    }
+
+    /// Verify a presentation according to the profile.
+    pub async fn verify_presentation(
+        &self,
+        presentation: &PresentationOrJWT,
+        options: &JWTOrLDPOptions,
+        resolver: &dyn DIDResolver,
+    ) -> Result<VerificationReport, ProfileError> {
+        let mut options = options.clone();
+        self.apply(DocumentKind::Presentation, &mut options)?;
+        let mut report = verify_presentation(presentation, &options, resolver).await?;
+        self.check(&mut report);
+        Ok(report)
+    }
+}
+
+impl FromStr for Profile {
+    type Err = ProfileError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::named(s).ok_or_else(|| ProfileError::UnknownProfile(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::ProofReport;
+
+    #[test]
+    fn compose_profiles() {
+        let profile = Profile::named("ebsi").unwrap().and(Profile::new(
+            "did-key",
+            vec![Policy::DIDMethods(strings(&["key"]))],
+        ));
+        assert_eq!(profile.name, "ebsi+did-key");
+        assert!(Profile::from_str("unknown").is_err());
+
+        let mut options = JWTOrLDPOptions::default();
+        options.verification_options.missing_expiration = Some(MissingExpiration::Error);
+        profile
+            .apply(DocumentKind::Credential, &mut options)
+            .unwrap();
+        let verification_options = &options.verification_options;
+        assert_eq!(
+            verification_options.allowed_did_methods,
+            Some(strings(&["key"]))
+        );
+        assert_eq!(
+            verification_options.missing_expiration,
+            Some(MissingExpiration::Error)
+        );
+        assert!(verification_options.require_holder_binding);
+        let err = profile
+            .apply(DocumentKind::Presentation, &mut options)
+            .unwrap_err();
+        assert!(matches!(err, ProfileError::MissingOption(_, "challenge")));
+
+        let mut report = VerificationReport::new();
+        report.proofs.push(ProofReport {
+            type_: Some("JWT (ES256)".to_string()),
+            proof_format: Some(ProofFormat::JWT),
+            algorithm: Some("ES256".to_string()),
+            ..Default::default()
+        });
+        profile.check(&mut report);
+        assert!(report.is_success());
+        assert_eq!(report.policy_checks, vec![PolicyCheck::Profile]);
+
+        let mut report = VerificationReport::new();
+        report.proofs.push(ProofReport {
+            type_: Some("Ed25519Signature2018".to_string()),
+            proof_format: Some(ProofFormat::LDP),
+            ..Default::default()
+        });
+        profile.check(&mut report);
+        assert!(report.has_error(ErrorCode::DisallowedProof));
+        report.errors.clear();
+        Profile::named("vc-api").unwrap().check(&mut report);
+        assert!(report.is_success());
+    }
+}
@@ -0,0 +1,137 @@
+//! Proof purposes, and the verification relationships that authorize them.
+//!
+//! The `proofPurpose` of a proof names the [verification relationship][vr] of the controller's
+//! DID document that must list the proof's verification method: `assertionMethod` for
+//! credentials, `authentication` for presentations and DIDAuth, `capabilityInvocation` and
+//! `capabilityDelegation` for authorization capabilities, and `contractAgreement`. Keys listed
+//! for `keyAgreement` are for encryption (see [`jwe`](crate::jwe)), not for proofs.
+//!
+//! [vr]: https://www.w3.org/TR/did-core/#verification-relationships
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{DIDResolver, ProofPurpose, ResolutionInputMetadata};
+
+/// Proof purposes for which proofs can be issued.
+pub const PROOF_PURPOSES: &[ProofPurpose] = &[
+    ProofPurpose::AssertionMethod,
+    ProofPurpose::Authentication,
+    ProofPurpose::CapabilityInvocation,
+    ProofPurpose::CapabilityDelegation,
+    ProofPurpose::ContractAgreement,
+];
+
+#[derive(Error, Debug)]
+pub enum ProofPurposeError {
+    #[error("Verification method {0} is not authorized for {1} by {2}")]
+    Unauthorized(String, String, String),
+    #[error("Unable to resolve {0}: {1}")]
+    Resolution(String, String),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// Name of a proof purpose, i.e. of its verification relationship, e.g. `assertionMethod`.
+pub fn purpose_name(proof_purpose: &ProofPurpose) -> String {
+    match serde_json::to_value(proof_purpose) {
+        Ok(Value::String(name)) => name,
+        _ => "assertionMethod".to_string(),
+    }
+}
+
+/// Whether proofs can be issued for a proof purpose, i.e. it is not `keyAgreement`.
+pub fn is_proof_purpose(proof_purpose: &ProofPurpose) -> bool {
+    !matches!(proof_purpose, ProofPurpose::KeyAgreement)
+}
+
+/// IDs of the verification methods of a DID document for a verification relationship, e.g.
+/// `assertionMethod`, whether referenced or embedded. Relative IDs are made absolute using the
+/// DID.
+pub fn relationship_ids(document: &Value, did: &str, relationship: &str) -> Vec<String> {
+    document
+        .get(relationship)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|vm| match vm {
+            Value::String(id) => Some(id.as_str()),
+            vm => vm.get("id").and_then(Value::as_str),
+        })
+        .map(|id| match id.strip_prefix('#') {
+            Some(fragment) => format!("{}#{}", did, fragment),
+            None => id.to_string(),
+        })
+        .collect()
+}
+
+/// Check that a verification method is authorized by a controller DID for a proof purpose (by
+/// name), i.e. that it is listed for the purpose's verification relationship in the controller's
+/// DID document. The verification method may be of another DID.
+pub async fn check_authorized(
+    verification_method: &str,
+    controller: &str,
+    purpose: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<(), ProofPurposeError> {
+    let unauthorized = || {
+        ProofPurposeError::Unauthorized(
+            verification_method.to_string(),
+            purpose.to_string(),
+            controller.to_string(),
+        )
+    };
+    let (res_meta, doc, _) = resolver
+        .resolve(controller, &ResolutionInputMetadata::default())
+        .await;
+    let doc = match doc {
+        Some(doc) => serde_json::to_value(doc)?,
+        None => {
+            return Err(ProofPurposeError::Resolution(
+                controller.to_string(),
+                res_meta.error.unwrap_or_default(),
+            ))
+        }
+    };
+    if relationship_ids(&doc, controller, purpose)
+        .iter()
+        .any(|id| id == verification_method)
+    {
+        Ok(())
+    } else {
+        Err(unauthorized())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn verification_relationships() {
+        let did = "did:example:controller";
+        let document = json!({
+            "id": did,
+            "verificationMethod": [{ "id": "#key-1" }],
+            "assertionMethod": ["#key-1"],
+            "capabilityInvocation": [{ "id": "did:example:controller#key-2" }],
+        });
+        assert_eq!(
+            relationship_ids(&document, did, "assertionMethod"),
+            vec!["did:example:controller#key-1"]
+        );
+        assert_eq!(
+            relationship_ids(&document, did, "capabilityInvocation"),
+            vec!["did:example:controller#key-2"]
+        );
+        assert!(relationship_ids(&document, did, "capabilityDelegation").is_empty());
+
+        assert_eq!(
+            purpose_name(&ProofPurpose::CapabilityDelegation),
+            "capabilityDelegation"
+        );
+        assert!(PROOF_PURPOSES.iter().all(is_proof_purpose));
+        assert!(!is_proof_purpose(&ProofPurpose::KeyAgreement));
+    }
+}
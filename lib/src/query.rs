@@ -0,0 +1,262 @@
+//! Extraction of claim values from credentials, by JSON Pointer or JSONPath queries.
+//!
+//! A query is a [JSON Pointer][pointer], such as `/credentialSubject/degree/name`, or a JSONPath
+//! expression starting with `$`, such as `$.credentialSubject[*].name` (see [`crate::json_path`]
+//! for the supported subset), into the credential decoded to the data model (see
+//! [`decode_credential`]): the claims of a VC-JWT are queried as the credential properties they
+//! map to. A member name of a JSONPath expression applied to an array selects the member of each
+//! of its items, so that `$.credentialSubject.name` selects the name of each subject of a
+//! credential with several. Each selected value is returned with its JSON pointer and its type,
+//! strings in RFC 3339 form being dates.
+//!
+//! [`query_credential`] verifies the credential first, and fails if it does not verify.
+//!
+//! [pointer]: https://www.rfc-editor.org/rfc/rfc6901
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+use crate::convert::{decode_credential, ConvertError};
+use crate::error::Error;
+use crate::json_path::{self, JSONPathError};
+use crate::{verify_credential, CredentialOrJWT, DIDResolver, JWTClaimsOptions, JWTOrLDPOptions};
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("Invalid query (expected a JSON pointer or a JSONPath expression): {0}")]
+    Invalid(String),
+    #[error("Credential does not verify: {0}")]
+    Verification(String),
+    #[error(transparent)]
+    JSONPath(#[from] JSONPathError),
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+    #[error(transparent)]
+    DIDKit(#[from] Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// Query of the claims of a credential.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// JSON pointer, selecting at most one value
+    Pointer(String),
+    /// JSONPath expression
+    Path(String),
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        if query.is_empty() || query.starts_with('/') {
+            Ok(Self::Pointer(query.to_string()))
+        } else if query.starts_with('$') {
+            Ok(Self::Path(query.to_string()))
+        } else {
+            Err(QueryError::Invalid(query.to_string()))
+        }
+    }
+}
+
+impl Query {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Pointer(query) | Self::Path(query) => query,
+        }
+    }
+
+    /// Select the values of a document, with the JSON pointer of each.
+    pub fn select(&self, document: &Value) -> Result<Vec<Claim>, QueryError> {
+        let values = match self {
+            Self::Pointer(pointer) => document
+                .pointer(pointer)
+                .map(|value| (pointer.to_string(), value))
+                .into_iter()
+                .collect(),
+            Self::Path(path) => json_path::locate(document, path, true)?,
+        };
+        Ok(values
+            .into_iter()
+            .map(|(pointer, value)| Claim {
+                pointer,
+                value: ClaimValue::from(value),
+            })
+            .collect())
+    }
+}
+
+/// Claim value, by JSON type, with dates told apart from other strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum ClaimValue {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    DateTime(DateTime<Utc>),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map<String, Value>),
+}
+
+impl From<&Value> for ClaimValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(value) => Self::Boolean(*value),
+            Value::Number(value) => Self::Number(value.clone()),
+            Value::String(value) => match DateTime::parse_from_rfc3339(value) {
+                Ok(date) => Self::DateTime(date.with_timezone(&Utc)),
+                Err(_) => Self::String(value.clone()),
+            },
+            Value::Array(values) => Self::Array(values.clone()),
+            Value::Object(object) => Self::Object(object.clone()),
+        }
+    }
+}
+
+impl ClaimValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Number(value) => value.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => value.as_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_date_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::DateTime(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Value selected by a query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    /// JSON pointer of the value in the decoded credential
+    pub pointer: String,
+    #[serde(flatten)]
+    pub value: ClaimValue,
+}
+
+/// Values selected by a query, in document order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub query: String,
+    pub claims: Vec<Claim>,
+}
+
+/// Query the claims of a document.
+pub fn query(document: &Value, queries: &[Query]) -> Result<Vec<QueryResult>, QueryError> {
+    queries
+        .iter()
+        .map(|query| {
+            Ok(QueryResult {
+                query: query.as_str().to_string(),
+                claims: query.select(document)?,
+            })
+        })
+        .collect()
+}
+
+/// Query the claims of a credential, decoded with the JWT claim mapping options, without
+/// verifying it, e.g. after verification by the caller.
+pub fn query_decoded_credential(
+    credential: &CredentialOrJWT,
+    queries: &[Query],
+    jwt_claims: &JWTClaimsOptions,
+) -> Result<Vec<QueryResult>, QueryError> {
+    let vc = decode_credential(credential, jwt_claims)?;
+    query(&serde_json::to_value(vc)?, queries)
+}
+
+/// Verify a credential, and query its claims.
+pub async fn query_credential(
+    credential: &CredentialOrJWT,
+    queries: &[Query],
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<Vec<QueryResult>, QueryError> {
+    let report = verify_credential(credential, options, resolver).await?;
+    if !report.is_success() {
+        let errors: Vec<String> = report.errors.into_iter().map(|e| e.message).collect();
+        return Err(QueryError::Verification(errors.join("; ")));
+    }
+    let jwt_claims = options.jwt_claims.clone().unwrap_or_default();
+    query_decoded_credential(credential, queries, &jwt_claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn query_claims() {
+        let credential = json!({
+            "issuanceDate": "2024-01-01T00:00:00Z",
+            "credentialSubject": [
+                { "id": "did:example:alice", "name": "Alice", "age": 30 },
+                { "id": "did:example:bob", "name": "Bob", "over18": true },
+            ],
+        });
+        let queries: Vec<Query> = ["/credentialSubject/1/over18", "$.credentialSubject.name"]
+            .iter()
+            .map(|query| query.parse().unwrap())
+            .collect();
+        let results = query(&credential, &queries).unwrap();
+        assert_eq!(results[0].claims[0].value.as_bool(), Some(true));
+        let names: Vec<_> = results[1].claims.iter().map(|c| &c.pointer).collect();
+        assert_eq!(
+            names,
+            ["/credentialSubject/0/name", "/credentialSubject/1/name"]
+        );
+        assert_eq!(results[1].claims[0].value.as_str(), Some("Alice"));
+
+        let date = Query::Pointer("/issuanceDate".to_string());
+        assert!(date.select(&credential).unwrap()[0]
+            .value
+            .as_date_time()
+            .is_some());
+        let age = Query::from_str("$.credentialSubject[0].age").unwrap();
+        assert_eq!(
+            serde_json::to_value(&age.select(&credential).unwrap()[0]).unwrap(),
+            json!({ "pointer": "/credentialSubject/0/age", "type": "number", "value": 30 })
+        );
+        assert!(Query::from_str("/missing")
+            .unwrap()
+            .select(&credential)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            Query::from_str("credentialSubject.name"),
+            Err(QueryError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,719 @@
+//! Selective disclosure of credentials by [JSON Pointer][rfc6901].
+//!
+//! Given pointers to the claims to reveal, [`redact`] derives a credential that discloses only
+//! those claims (and the claims under them) from a credential secured with a
+//! selectively-disclosable proof. Mandatory claims, such as the issuer and type, are always
+//! disclosed; a mandatory pointer that cannot be disclosed is an error.
+//!
+//! Supported proofs:
+//! - [SD-JWT][sd-jwt]: the holder keeps only the disclosures needed for the revealed claims. No
+//!   key is needed. An existing key binding JWT is removed, as it covers the presented
+//!   disclosures; [`bind_sd_jwt`] adds a fresh one for a verifier, signed with the holder key.
+//! - JSON Proof Tokens (see [`crate::jwp`]), with [`redact_jpt`]: pointers select top-level
+//!   claims, and the presentation is derived with a [`ProofAlgorithm`], e.g. [`jwp::Bbs`].
+//!   [`redact_jpt_credential`] derives BBS presentations, resolving the issuer's key.
+//!
+//! Derived proofs of the Data Integrity cryptosuites `bbs-2023` and `ecdsa-sd-2023` are out of
+//! scope: credentials secured with them are rejected with
+//! [`RedactError::UnsupportedCryptosuite`]. Issue BBS credentials as JSON Proof Tokens (the `jpt`
+//! proof format) instead.
+//!
+//! Array indexes in pointers refer to the credential with all claims disclosed.
+//!
+//! [rfc6901]: https://www.rfc-editor.org/rfc/rfc6901
+//! [sd-jwt]: https://datatracker.ietf.org/doc/draft-ietf-oauth-selective-disclosure-jwt/
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::jwp::{self, JWPError, JsonWebProof, PresentationHeader, ProofAlgorithm};
+use crate::key_cache;
+use crate::signer::{sign_jwt_with_header, Signer, SignerError};
+use crate::{DIDResolver, LinkedDataProofOptions, JWK};
+
+/// Claims disclosed whenever the credential has them.
+pub const DEFAULT_MANDATORY: &[&str] = &[
+    "/iss",
+    "/vct",
+    "/@context",
+    "/type",
+    "/issuer",
+    "/vc/@context",
+    "/vc/type",
+    "/vc/issuer",
+];
+
+/// Data Integrity cryptosuites with derived proofs, which are not supported.
+pub const UNSUPPORTED_CRYPTOSUITES: &[&str] = &["bbs-2023", "ecdsa-sd-2023"];
+
+const SD_ALG_SHA256: &str = "sha-256";
+
+/// JWT type of SD-JWT key binding JWTs.
+pub const TYP_KB_JWT: &str = "kb+jwt";
+
+#[derive(Error, Debug)]
+pub enum RedactError {
+    #[error("Invalid JSON pointer: {0}")]
+    InvalidPointer(String),
+    #[error("No claim at {0}")]
+    UnknownPointer(String),
+    #[error("Mandatory claim not disclosed: {0}")]
+    MandatoryNotDisclosed(String),
+    #[error("Malformed SD-JWT: {0}")]
+    Malformed(String),
+    #[error("Unsupported SD-JWT digest algorithm: {0}")]
+    UnsupportedDigestAlgorithm(String),
+    #[error("Selective disclosure is not supported for {0}")]
+    UnsupportedProof(String),
+    #[error("Derived proofs of the {0} cryptosuite are not supported; issue a JSON Proof Token (jpt) or SD-JWT instead")]
+    UnsupportedCryptosuite(String),
+    #[error("Missing key ID in JSON Proof Token")]
+    MissingKeyId,
+    #[error("Unable to resolve issuer key: {0}")]
+    KeyResolution(String),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error(transparent)]
+    JWP(#[from] JWPError),
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactOptions {
+    /// JSON pointers of the claims to disclose
+    pub reveal: Vec<String>,
+    /// JSON pointers of claims that must be disclosed, in addition to [`DEFAULT_MANDATORY`]
+    #[serde(default)]
+    pub mandatory: Vec<String>,
+}
+
+type Path = Vec<String>;
+
+/// Parse a JSON pointer into its reference tokens.
+fn parse_pointer(pointer: &str) -> Result<Path, RedactError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(RedactError::InvalidPointer(pointer.to_string()));
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn is_prefix(prefix: &[String], path: &[String]) -> bool {
+    prefix.len() <= path.len() && prefix.iter().zip(path).all(|(a, b)| a == b)
+}
+
+/// Pointers to reveal and to validate, checked against the fully disclosed claims.
+struct Selection {
+    reveal: Vec<Path>,
+    mandatory: Vec<(String, Path)>,
+}
+
+impl Selection {
+    fn new(options: &RedactOptions, claims: &Value) -> Result<Self, RedactError> {
+        let mut reveal = Vec::new();
+        for pointer in &options.reveal {
+            let path = parse_pointer(pointer)?;
+            if claims.pointer(pointer).is_none() {
+                return Err(RedactError::UnknownPointer(pointer.to_string()));
+            }
+            reveal.push(path);
+        }
+        let mut mandatory = Vec::new();
+        for pointer in DEFAULT_MANDATORY {
+            if claims.pointer(pointer).is_some() {
+                mandatory.push((pointer.to_string(), parse_pointer(pointer)?));
+            }
+        }
+        for pointer in &options.mandatory {
+            let path = parse_pointer(pointer)?;
+            if claims.pointer(pointer).is_none() {
+                return Err(RedactError::MandatoryNotDisclosed(pointer.to_string()));
+            }
+            mandatory.push((pointer.to_string(), path));
+        }
+        reveal.extend(mandatory.iter().map(|(_, path)| path.clone()));
+        Ok(Self { reveal, mandatory })
+    }
+
+    /// Whether the claim at `path` must be disclosed: it is revealed, is under a revealed claim,
+    /// or contains one.
+    fn discloses(&self, path: &[String]) -> bool {
+        self.reveal
+            .iter()
+            .any(|reveal| is_prefix(reveal, path) || is_prefix(path, reveal))
+    }
+}
+
+struct Disclosure<'a> {
+    encoded: &'a str,
+    digest: String,
+    name: Option<String>,
+    value: Value,
+}
+
+impl<'a> Disclosure<'a> {
+    fn parse(encoded: &'a str) -> Result<Self, RedactError> {
+        let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+        let mut array = match serde_json::from_slice(&bytes)? {
+            Value::Array(array) => array,
+            _ => {
+                return Err(RedactError::Malformed(
+                    "disclosure is not an array".to_string(),
+                ))
+            }
+        };
+        let (name, value) = match array.len() {
+            3 => {
+                let value = array.pop().unwrap_or_default();
+                match array.pop() {
+                    Some(Value::String(name)) => (Some(name), value),
+                    _ => {
+                        return Err(RedactError::Malformed(
+                            "disclosure claim name is not a string".to_string(),
+                        ))
+                    }
+                }
+            }
+            2 => (None, array.pop().unwrap_or_default()),
+            n => {
+                return Err(RedactError::Malformed(format!(
+                    "disclosure has {} elements",
+                    n
+                )))
+            }
+        };
+        let digest =
+            base64::encode_config(Sha256::digest(encoded.as_bytes()), base64::URL_SAFE_NO_PAD);
+        Ok(Self {
+            encoded,
+            digest,
+            name,
+            value,
+        })
+    }
+}
+
+/// Reconstruct the claims with all disclosures applied, recording the path of each disclosure.
+fn expand(
+    value: &Value,
+    path: &mut Path,
+    disclosures: &[Disclosure],
+    paths: &mut [Option<Path>],
+) -> Result<Value, RedactError> {
+    let find = |digest: &Value| -> Result<Option<usize>, RedactError> {
+        let digest = digest
+            .as_str()
+            .ok_or_else(|| RedactError::Malformed("digest is not a string".to_string()))?;
+        // Digests without a disclosure are decoys, or were withheld by a previous holder.
+        Ok(disclosures.iter().position(|d| d.digest == digest))
+    };
+    Ok(match value {
+        Value::Object(object) => {
+            let mut expanded = Map::new();
+            for (name, value) in object {
+                match &name[..] {
+                    "_sd" => {
+                        let digests = value.as_array().ok_or_else(|| {
+                            RedactError::Malformed("_sd is not an array".to_string())
+                        })?;
+                        for digest in digests {
+                            let i = match find(digest)? {
+                                Some(i) => i,
+                                None => continue,
+                            };
+                            let disclosure = &disclosures[i];
+                            let name = disclosure.name.clone().ok_or_else(|| {
+                                RedactError::Malformed(
+                                    "array element disclosure in object".to_string(),
+                                )
+                            })?;
+                            path.push(name.clone());
+                            paths[i] = Some(path.clone());
+                            let value = expand(&disclosure.value, path, disclosures, paths)?;
+                            path.pop();
+                            expanded.insert(name, value);
+                        }
+                    }
+                    "_sd_alg" => {}
+                    _ => {
+                        path.push(name.clone());
+                        let value = expand(value, path, disclosures, paths)?;
+                        path.pop();
+                        expanded.insert(name.clone(), value);
+                    }
+                }
+            }
+            Value::Object(expanded)
+        }
+        Value::Array(array) => {
+            let mut expanded = Vec::with_capacity(array.len());
+            for element in array {
+                let digest = element
+                    .as_object()
+                    .filter(|object| object.len() == 1)
+                    .and_then(|object| object.get("..."));
+                if let Some(digest) = digest {
+                    if let Some(i) = find(digest)? {
+                        let disclosure = &disclosures[i];
+                        if disclosure.name.is_some() {
+                            return Err(RedactError::Malformed(
+                                "object property disclosure in array".to_string(),
+                            ));
+                        }
+                        path.push(expanded.len().to_string());
+                        paths[i] = Some(path.clone());
+                        let value = expand(&disclosure.value, path, disclosures, paths)?;
+                        path.pop();
+                        expanded.push(value);
+                    }
+                    continue;
+                }
+                path.push(expanded.len().to_string());
+                let value = expand(element, path, disclosures, paths)?;
+                path.pop();
+                expanded.push(value);
+            }
+            Value::Array(expanded)
+        }
+        _ => value.clone(),
+    })
+}
+
+/// Split an SD-JWT into its issuer-signed JWT, the JWT payload and the disclosures.
+fn parse_sd_jwt(sd_jwt: &str) -> Result<(&str, Value, Vec<Disclosure>), RedactError> {
+    let mut parts = sd_jwt.trim().split('~');
+    let jwt = parts.next().unwrap_or_default();
+    let encoded: Vec<&str> = parts.collect();
+    if encoded.is_empty() {
+        return Err(RedactError::Malformed("missing disclosures".to_string()));
+    }
+    // The last part is empty, or a key binding JWT.
+    let encoded = &encoded[..encoded.len() - 1];
+    let payload = crate::verification::decode_jwt_unverified(jwt)
+        .map(|(_header, payload)| payload)
+        .ok_or_else(|| RedactError::Malformed("unable to decode JWT".to_string()))?;
+    match payload.get("_sd_alg").and_then(Value::as_str) {
+        None | Some(SD_ALG_SHA256) => {}
+        Some(alg) => return Err(RedactError::UnsupportedDigestAlgorithm(alg.to_string())),
+    }
+    let disclosures = encoded
+        .iter()
+        .map(|encoded| Disclosure::parse(encoded))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((jwt, payload, disclosures))
+}
+
+/// Claims of an SD-JWT, with all of its disclosures applied.
+pub fn sd_jwt_claims(sd_jwt: &str) -> Result<Value, RedactError> {
+    let (_jwt, payload, disclosures) = parse_sd_jwt(sd_jwt)?;
+    let mut paths = vec![None; disclosures.len()];
+    expand(&payload, &mut Vec::new(), &disclosures, &mut paths)
+}
+
+/// Derive an SD-JWT disclosing only the selected claims.
+pub fn redact_sd_jwt(sd_jwt: &str, options: &RedactOptions) -> Result<String, RedactError> {
+    let (jwt, payload, disclosures) = parse_sd_jwt(sd_jwt)?;
+    let mut paths = vec![None; disclosures.len()];
+    let claims = expand(&payload, &mut Vec::new(), &disclosures, &mut paths)?;
+    let selection = Selection::new(options, &claims)?;
+    let keep: Vec<bool> = paths
+        .iter()
+        .map(|path| match path {
+            Some(path) => selection.discloses(path),
+            // Not referenced by the issuer-signed claims
+            None => false,
+        })
+        .collect();
+    for (pointer, mandatory) in &selection.mandatory {
+        let withheld = paths
+            .iter()
+            .zip(&keep)
+            .any(|(path, keep)| !keep && matches!(path, Some(path) if is_prefix(path, mandatory)));
+        if withheld {
+            return Err(RedactError::MandatoryNotDisclosed(pointer.to_string()));
+        }
+    }
+    let mut redacted = jwt.to_string();
+    redacted.push('~');
+    for (disclosure, keep) in disclosures.iter().zip(keep) {
+        if keep {
+            redacted.push_str(disclosure.encoded);
+            redacted.push('~');
+        }
+    }
+    Ok(redacted)
+}
+
+/// Whether an SD-JWT is bound to a holder key, with a `cnf` claim, so that its presentations
+/// need a key binding JWT.
+pub fn requires_key_binding(sd_jwt: &str) -> Result<bool, RedactError> {
+    let (_jwt, payload, _disclosures) = parse_sd_jwt(sd_jwt)?;
+    Ok(payload.get("cnf").is_some())
+}
+
+/// Bind an SD-JWT presentation to a verifier (`aud`) and its nonce with a key binding JWT signed
+/// with the holder key, replacing any existing key binding JWT.
+pub async fn bind_sd_jwt(
+    sd_jwt: &str,
+    audience: &str,
+    nonce: &str,
+    signer: &dyn Signer,
+) -> Result<String, RedactError> {
+    let sd_jwt = sd_jwt.trim();
+    parse_sd_jwt(sd_jwt)?;
+    // The key binding JWT covers the issuer-signed JWT and the disclosures, up to the last `~`.
+    let presentation = match sd_jwt.rfind('~') {
+        Some(end) => &sd_jwt[..=end],
+        None => return Err(RedactError::Malformed("missing disclosures".to_string())),
+    };
+    let sd_hash = base64::encode_config(
+        Sha256::digest(presentation.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+    let claims = json!({ "iat": Utc::now().timestamp(), "sd_hash": sd_hash });
+    let options = LinkedDataProofOptions {
+        domain: Some(audience.to_string()),
+        challenge: Some(nonce.to_string()),
+        ..Default::default()
+    };
+    let mut header = Map::new();
+    header.insert("typ".to_string(), json!(TYP_KB_JWT));
+    let kb_jwt = sign_jwt_with_header(&claims, &options, &header, signer).await?;
+    Ok(format!("{}{}", presentation, kb_jwt))
+}
+
+/// Derive a presented JSON Proof Token disclosing only the top-level claims selected by the
+/// pointers.
+pub fn redact_jpt(
+    jpt: &JsonWebProof,
+    options: &RedactOptions,
+    presentation_header: PresentationHeader,
+    algorithm: &dyn ProofAlgorithm,
+    key: &JWK,
+) -> Result<JsonWebProof, RedactError> {
+    let claims = Value::Object(jpt.claims()?);
+    let selection = Selection::new(options, &claims)?;
+    let names = jpt.issuer_header.claims.clone().unwrap_or_default();
+    let disclose: Vec<&str> = names
+        .iter()
+        .map(String::as_str)
+        .filter(|name| selection.discloses(&[name.to_string()]))
+        .collect();
+    for (pointer, mandatory) in &selection.mandatory {
+        match mandatory.first() {
+            Some(name) if !disclose.contains(&&name[..]) => {
+                return Err(RedactError::MandatoryNotDisclosed(pointer.to_string()))
+            }
+            _ => {}
+        }
+    }
+    Ok(jwp::present(
+        jpt,
+        &disclose,
+        presentation_header,
+        algorithm,
+        key,
+    )?)
+}
+
+/// Derive a presented BBS JSON Proof Token, in compact serialization, from an issued one,
+/// resolving the issuer key from the token's key ID.
+pub async fn redact_jpt_credential(
+    jpt: &str,
+    options: &RedactOptions,
+    presentation_header: PresentationHeader,
+    resolver: &dyn DIDResolver,
+) -> Result<String, RedactError> {
+    let jpt = JsonWebProof::from_compact(jpt.trim())?;
+    let verification_method = jpt
+        .issuer_header
+        .kid
+        .as_deref()
+        .ok_or(RedactError::MissingKeyId)?;
+    let key = key_cache::resolve_key(verification_method, resolver)
+        .await
+        .map_err(|err| RedactError::KeyResolution(err.to_string()))?;
+    let presented = redact_jpt(&jpt, options, presentation_header, &jwp::Bbs, &key)?;
+    Ok(presented.to_compact()?)
+}
+
+/// Derive a credential disclosing only the selected claims, detecting the kind of proof.
+///
+/// JSON Proof Tokens need a proof algorithm and key; use [`redact_jpt`] or
+/// [`redact_jpt_credential`] for them.
+pub fn redact(credential: &str, options: &RedactOptions) -> Result<String, RedactError> {
+    let credential = credential.trim();
+    if credential.starts_with('{') {
+        let document: Value = serde_json::from_str(credential)?;
+        let proof = match document.get("proof") {
+            Some(Value::Array(proofs)) => proofs.first(),
+            proof => proof,
+        };
+        let suite = proof
+            .and_then(|proof| proof.get("cryptosuite").or_else(|| proof.get("type")))
+            .and_then(Value::as_str)
+            .unwrap_or("credentials without a proof");
+        if UNSUPPORTED_CRYPTOSUITES.contains(&suite) {
+            return Err(RedactError::UnsupportedCryptosuite(suite.to_string()));
+        }
+        return Err(RedactError::UnsupportedProof(suite.to_string()));
+    }
+    if jwp::is_jpt(credential) {
+        return Err(RedactError::UnsupportedProof(
+            "JSON Proof Tokens without a proof algorithm".to_string(),
+        ));
+    }
+    if !credential.contains('~') {
+        return Err(RedactError::UnsupportedProof(
+            "JWTs without disclosures".to_string(),
+        ));
+    }
+    redact_sd_jwt(credential, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(value: &Value) -> String {
+        base64::encode_config(serde_json::to_vec(value).unwrap(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn digest(disclosure: &str) -> String {
+        base64::encode_config(
+            Sha256::digest(disclosure.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    #[test]
+    fn redact_sd_jwt_pointers() {
+        let given_name = encode(&json!(["salt1", "given_name", "Alice"]));
+        let family_name = encode(&json!(["salt2", "family_name", "Smith"]));
+        let locality = encode(&json!(["salt3", "locality", "Paris"]));
+        let country = encode(&json!(["salt4", "country", "FR"]));
+        let address = encode(&json!([
+            "salt5",
+            "address",
+            {"_sd": [digest(&locality), digest(&country)]}
+        ]));
+        let payload = json!({
+            "iss": "did:example:issuer",
+            "vct": "https://example.org/identity",
+            "_sd_alg": "sha-256",
+            "_sd": [digest(&given_name), digest(&family_name), digest(&address)],
+        });
+        let jwt = format!(
+            "{}.{}.c2ln",
+            encode(&json!({"alg": "ES256"})),
+            encode(&payload)
+        );
+        let sd_jwt = format!(
+            "{}~{}~{}~{}~{}~{}~",
+            jwt, given_name, family_name, address, locality, country
+        );
+
+        let options = RedactOptions {
+            reveal: vec!["/given_name".to_string(), "/address/locality".to_string()],
+            ..Default::default()
+        };
+        let redacted = redact(&sd_jwt, &options).unwrap();
+        assert_eq!(
+            redacted,
+            format!("{}~{}~{}~{}~", jwt, given_name, address, locality)
+        );
+
+        let options = RedactOptions {
+            reveal: vec!["/address".to_string()],
+            ..Default::default()
+        };
+        let redacted = redact(&sd_jwt, &options).unwrap();
+        assert_eq!(
+            redacted,
+            format!("{}~{}~{}~{}~", jwt, address, locality, country)
+        );
+
+        let options = RedactOptions {
+            reveal: vec!["/age".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            redact(&sd_jwt, &options),
+            Err(RedactError::UnknownPointer(_))
+        ));
+
+        let options = RedactOptions {
+            reveal: vec![],
+            mandatory: vec!["/family_name".to_string()],
+        };
+        let redacted = redact(&sd_jwt, &options).unwrap();
+        assert_eq!(redacted, format!("{}~{}~", jwt, family_name));
+    }
+
+    #[test]
+    fn bind_sd_jwt_holder_key() {
+        let disclosure = encode(&json!(["salt1", "given_name", "Alice"]));
+        let payload = json!({
+            "iss": "did:example:issuer",
+            "_sd": [digest(&disclosure)],
+            "cnf": {"jwk": {"kty": "OKP", "crv": "Ed25519", "x": "AA"}},
+        });
+        let jwt = format!(
+            "{}.{}.c2ln",
+            encode(&json!({"alg": "ES256"})),
+            encode(&payload)
+        );
+        let sd_jwt = format!("{}~{}~", jwt, disclosure);
+        assert!(requires_key_binding(&sd_jwt).unwrap());
+
+        let key = JWK::generate_ed25519().unwrap();
+        let rt = crate::runtime::get().unwrap();
+        let bound = rt
+            .block_on(bind_sd_jwt(
+                &sd_jwt,
+                "https://verifier.example",
+                "n-0S6",
+                &key,
+            ))
+            .unwrap();
+        let kb_jwt = bound.strip_prefix(&sd_jwt).unwrap();
+        let (header, claims) = crate::verification::decode_jwt_unverified(kb_jwt).unwrap();
+        assert_eq!(header["typ"], json!(TYP_KB_JWT));
+        assert_eq!(claims["aud"], json!("https://verifier.example"));
+        assert_eq!(claims["nonce"], json!("n-0S6"));
+        assert_eq!(claims["sd_hash"], json!(digest(&sd_jwt)));
+
+        // Binding again replaces the key binding JWT.
+        let rebound = rt
+            .block_on(bind_sd_jwt(&bound, "https://verifier.example", "n-1", &key))
+            .unwrap();
+        assert!(rebound.starts_with(&sd_jwt));
+        assert_eq!(rebound.matches('~').count(), 2);
+    }
+
+    #[test]
+    fn redact_jpt_credential_resolves_issuer_key() {
+        use crate::{Document, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata};
+
+        struct Issuer(Document);
+
+        #[async_trait::async_trait]
+        impl DIDResolver for Issuer {
+            async fn resolve(
+                &self,
+                did: &str,
+                _input_metadata: &ResolutionInputMetadata,
+            ) -> (
+                ResolutionMetadata,
+                Option<Document>,
+                Option<DocumentMetadata>,
+            ) {
+                if did != self.0.id {
+                    return (ResolutionMetadata::from_error("notFound"), None, None);
+                }
+                (
+                    ResolutionMetadata::default(),
+                    Some(self.0.clone()),
+                    Some(DocumentMetadata::default()),
+                )
+            }
+        }
+
+        let key = jwp::generate_bbs_key().unwrap();
+        let did = "did:example:bbs-issuer";
+        let verification_method = format!("{}#bbs", did);
+        let resolver = Issuer(
+            serde_json::from_value(json!({
+                "@context": "https://www.w3.org/ns/did/v1",
+                "id": did,
+                "verificationMethod": [{
+                    "id": verification_method,
+                    "type": "JsonWebKey2020",
+                    "controller": did,
+                    "publicKeyJwk": key.to_public(),
+                }],
+            }))
+            .unwrap(),
+        );
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": did,
+            "credentialSubject": {"id": "did:example:subject", "name": "Alice"},
+            "evidence": {"id": "urn:uuid:0"},
+        });
+        let issued = jwp::issue_credential(&credential, Some(verification_method), &jwp::Bbs, &key)
+            .unwrap()
+            .to_compact()
+            .unwrap();
+        let presentation_header = PresentationHeader {
+            nonce: Some("n-0S6".to_string()),
+            ..Default::default()
+        };
+        let options = RedactOptions {
+            reveal: vec!["/credentialSubject/name".to_string()],
+            ..Default::default()
+        };
+        let rt = crate::runtime::get().unwrap();
+        let presented = rt
+            .block_on(redact_jpt_credential(
+                &issued,
+                &options,
+                presentation_header,
+                &resolver,
+            ))
+            .unwrap();
+        let presented = JsonWebProof::from_compact(&presented).unwrap();
+        let claims = jwp::verify(&presented, &jwp::Bbs, &key.to_public()).unwrap();
+        assert!(claims.contains_key("issuer"));
+        assert!(claims.contains_key("credentialSubject"));
+        assert!(!claims.contains_key("evidence"));
+
+        // Without a key ID, the issuer key cannot be resolved.
+        let mut anonymous = JsonWebProof::from_compact(&issued).unwrap();
+        anonymous.issuer_header.kid = None;
+        assert!(matches!(
+            rt.block_on(redact_jpt_credential(
+                &anonymous.to_compact().unwrap(),
+                &options,
+                PresentationHeader::default(),
+                &resolver,
+            )),
+            Err(RedactError::MissingKeyId)
+        ));
+    }
+
+    #[test]
+    fn redact_rejects_data_integrity_derived_proofs() {
+        for cryptosuite in UNSUPPORTED_CRYPTOSUITES {
+            let credential = json!({
+                "@context": ["https://www.w3.org/ns/credentials/v2"],
+                "type": ["VerifiableCredential"],
+                "issuer": "did:example:issuer",
+                "credentialSubject": {"name": "Alice"},
+                "proof": {"type": "DataIntegrityProof", "cryptosuite": cryptosuite},
+            });
+            let options = RedactOptions {
+                reveal: vec!["/credentialSubject/name".to_string()],
+                ..Default::default()
+            };
+            assert!(matches!(
+                redact(&credential.to_string(), &options),
+                Err(RedactError::UnsupportedCryptosuite(suite)) if suite == *cryptosuite
+            ));
+        }
+    }
+}
@@ -0,0 +1,844 @@
+//! Human-readable renderings of credentials, as text, Markdown or HTML.
+//!
+//! Display hints come from the [output descriptor][descriptor] of a Credential Manifest for the
+//! credential, or from the credential's `render` property, an object or array of objects with a
+//! `display` in the same form as that of an output descriptor. Each display mapping gives a
+//! constant `text`, or JSONPath expressions (`path`) into the credential, with a `schema` to
+//! format the selected value by and a `fallback` text. Without display hints, the title is the
+//! credential's most specific type, and the properties are the credential subject's claims.
+//!
+//! For wallets that draw credentials themselves, [`card`] resolves the display of a credential by
+//! its output descriptor, with the [entity styles][styles] of the descriptor and of the
+//! manifest's issuer, and [`validate_display`] checks that the display mappings of a descriptor
+//! select values of a credential, in the form of their schemas, and that its styles are
+//! well-formed.
+//!
+//! [descriptor]: https://identity.foundation/credential-manifest/#output-descriptor
+//! [styles]: https://identity.foundation/wallet-rendering/#entity-styles
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::json_path::{self, JSONPathError};
+use crate::securing;
+use crate::VerifiableCredential;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("Unknown render format: {0} (expected text, markdown or html)")]
+    UnknownFormat(String),
+    #[error("Output descriptor not found: {0}")]
+    DescriptorNotFound(String),
+    #[error("No output descriptor of the manifest matches the credential; select one by id")]
+    NoMatchingDescriptor,
+    #[error("Invalid credential: {0}")]
+    InvalidCredential(String),
+    #[error(transparent)]
+    JSONPath(#[from] JSONPathError),
+    #[error(transparent)]
+    JSON(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderFormat {
+    Text,
+    Markdown,
+    HTML,
+}
+
+impl FromStr for RenderFormat {
+    type Err = RenderError;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::HTML),
+            other => Err(RenderError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+/// A Credential Manifest, of which only the issuer and output descriptors are used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialManifest {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<ManifestIssuer>,
+    pub output_descriptors: Vec<OutputDescriptor>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ManifestIssuer {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub styles: Option<Styles>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputDescriptor {
+    pub id: String,
+    /// URI of the schema, or type, of the credential
+    pub schema: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayHints>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub styles: Option<Styles>,
+}
+
+/// Display properties of an output descriptor.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisplayHints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<DisplayMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<DisplayMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<DisplayMapping>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<LabeledDisplayMapping>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisplayMapping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<DisplaySchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LabeledDisplayMapping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub mapping: DisplayMapping,
+}
+
+/// How to format a selected value: `type` `string` (with a `format` of `date-time`, `date`,
+/// `email` or `uri`), `boolean`, `number` or `integer`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisplaySchema {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+/// Entity styles of an output descriptor or issuer, of which the colors are used in HTML
+/// renderings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Styles {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Image>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hero: Option<Image>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Color>,
+}
+
+/// A color, as `#RGB` or `#RRGGBB`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub color: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt: Option<String>,
+}
+
+/// A rendering of a credential, before formatting.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rendering {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub description: Option<String>,
+    /// Labels and values of the displayed properties
+    pub properties: Vec<(String, String)>,
+    pub issuer: Option<String>,
+    pub issuance_date: Option<String>,
+    pub expiration_date: Option<String>,
+    background_color: Option<String>,
+    text_color: Option<String>,
+}
+
+/// A credential card: the display of a credential by an output descriptor, resolved against the
+/// credential, with the styles to draw it in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    pub descriptor_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub properties: Vec<CardProperty>,
+    /// Styles of the output descriptor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub styles: Option<Styles>,
+    /// Issuer of the manifest, with its styles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<ManifestIssuer>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CardProperty {
+    pub label: String,
+    pub value: String,
+}
+
+/// Parse a credential, as JSON or a JWT, without verifying it.
+pub fn parse_credential(input: &str) -> Result<Value, RenderError> {
+    let input = input.trim();
+    if input.starts_with('{') {
+        return Ok(serde_json::from_str(input)?);
+    }
+    if let Some(payload) = securing::payload_unverified(input) {
+        return Ok(payload);
+    }
+    let vc = VerifiableCredential::from_jwt_unsigned(input)
+        .map_err(|err| RenderError::InvalidCredential(err.to_string()))?;
+    Ok(serde_json::to_value(vc)?)
+}
+
+/// Strings of a value that is a string or an array of strings.
+fn strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The output descriptor of a manifest for a credential: the one of the given id, or else the
+/// one whose schema is one of the credential's types or schemas, or else the only one.
+pub fn select_descriptor<'a>(
+    manifest: &'a CredentialManifest,
+    credential: &Value,
+    id: Option<&str>,
+) -> Result<&'a OutputDescriptor, RenderError> {
+    let descriptors = &manifest.output_descriptors;
+    if let Some(id) = id {
+        return descriptors
+            .iter()
+            .find(|descriptor| descriptor.id == id)
+            .ok_or_else(|| RenderError::DescriptorNotFound(id.to_string()));
+    }
+    let mut schemas = strings(&credential["type"]);
+    let credential_schemas = match &credential["credentialSchema"] {
+        Value::Array(schemas) => schemas.iter().collect(),
+        schema => vec![schema],
+    };
+    schemas.extend(credential_schemas.iter().filter_map(|s| s["id"].as_str()));
+    match descriptors
+        .iter()
+        .find(|descriptor| schemas.contains(&descriptor.schema.as_str()))
+    {
+        Some(descriptor) => Ok(descriptor),
+        None if descriptors.len() == 1 => Ok(&descriptors[0]),
+        None => Err(RenderError::NoMatchingDescriptor),
+    }
+}
+
+/// Display hints of the `render` property of a credential, if any.
+pub fn render_property_hints(credential: &Value) -> Result<Option<DisplayHints>, RenderError> {
+    let entries = match &credential["render"] {
+        Value::Array(entries) => entries.iter().collect(),
+        Value::Null => Vec::new(),
+        entry => vec![entry],
+    };
+    match entries.into_iter().find_map(|entry| entry.get("display")) {
+        Some(display) => Ok(Some(serde_json::from_value(display.clone())?)),
+        None => Ok(None),
+    }
+}
+
+/// Format a value by a display schema.
+fn format_value(value: &Value, schema: Option<&DisplaySchema>) -> String {
+    let format = schema.and_then(|schema| schema.format.as_deref());
+    match value {
+        Value::String(s) if format == Some("date-time") => match s.parse::<DateTime<Utc>>() {
+            Ok(date_time) => date_time.format("%Y-%m-%d %H:%M UTC").to_string(),
+            Err(_) => s.clone(),
+        },
+        Value::String(s) => s.clone(),
+        Value::Bool(true) => "Yes".to_string(),
+        Value::Bool(false) => "No".to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| format_value(value, schema))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Object(object) => match object.get("name").or_else(|| object.get("id")) {
+            Some(value) => format_value(value, schema),
+            None => value.to_string(),
+        },
+        Value::Null => String::new(),
+    }
+}
+
+fn resolve(mapping: &DisplayMapping, credential: &Value) -> Result<Option<String>, RenderError> {
+    if let Some(ref text) = mapping.text {
+        return Ok(Some(text.clone()));
+    }
+    match json_path::select_first(credential, &mapping.path)? {
+        Some(value) => Ok(Some(format_value(value, mapping.schema.as_ref()))),
+        None => Ok(mapping.fallback.clone()),
+    }
+}
+
+/// Label of a claim name, e.g. `Date of birth` for `dateOfBirth`.
+fn label(name: &str) -> String {
+    let mut label = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i == 0 {
+            label.extend(c.to_uppercase());
+        } else if c.is_uppercase() {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else if c == '_' {
+            label.push(' ');
+        } else {
+            label.push(c);
+        }
+    }
+    label
+}
+
+/// Properties of the claims of a credential subject, nested claims labeled by their path.
+fn claims(prefix: &str, value: &Value, properties: &mut Vec<(String, String)>) {
+    for (name, value) in value.as_object().into_iter().flatten() {
+        if prefix.is_empty() && (name == "id" || name == "type") {
+            continue;
+        }
+        let name = match prefix {
+            "" => label(name),
+            _ => format!("{} / {}", prefix, label(name)),
+        };
+        match value {
+            Value::Object(object) if !object.contains_key("name") => {
+                claims(&name, value, properties)
+            }
+            value => properties.push((name, format_value(value, None))),
+        }
+    }
+}
+
+/// Render a credential with display hints, if any.
+pub fn render(
+    credential: &Value,
+    hints: Option<&DisplayHints>,
+    styles: Option<&Styles>,
+) -> Result<Rendering, RenderError> {
+    let mut rendering = Rendering::default();
+    let hints = hints.cloned().unwrap_or_default();
+    let title = match hints.title {
+        Some(ref title) => resolve(title, credential)?,
+        None => None,
+    };
+    rendering.title = match title {
+        Some(title) => title,
+        None => {
+            let types = strings(&credential["type"]);
+            let type_ = types
+                .iter()
+                .rev()
+                .find(|t| **t != "VerifiableCredential")
+                .unwrap_or(&"VerifiableCredential");
+            label(type_)
+        }
+    };
+    if let Some(ref subtitle) = hints.subtitle {
+        rendering.subtitle = resolve(subtitle, credential)?;
+    }
+    if let Some(ref description) = hints.description {
+        rendering.description = resolve(description, credential)?;
+    }
+    if hints.properties.is_empty() {
+        let subjects = match &credential["credentialSubject"] {
+            Value::Array(subjects) => subjects.iter().collect(),
+            subject => vec![subject],
+        };
+        for subject in subjects {
+            claims("", subject, &mut rendering.properties);
+        }
+    }
+    for property in &hints.properties {
+        if let Some(value) = resolve(&property.mapping, credential)? {
+            let label = property.label.clone().unwrap_or_default();
+            rendering.properties.push((label, value));
+        }
+    }
+    let date_time = DisplaySchema {
+        type_: "string".to_string(),
+        format: Some("date-time".to_string()),
+    };
+    let issuer = &credential["issuer"];
+    rendering.issuer = Some(format_value(issuer, None)).filter(|issuer| !issuer.is_empty());
+    let date = |fields: &[&str]| {
+        fields
+            .iter()
+            .find_map(|field| credential.get(*field))
+            .map(|value| format_value(value, Some(&date_time)))
+    };
+    rendering.issuance_date = date(&["issuanceDate", "validFrom"]);
+    rendering.expiration_date = date(&["expirationDate", "validUntil"]);
+    if let Some(styles) = styles {
+        rendering.background_color = styles.background.as_ref().map(|c| c.color.clone());
+        rendering.text_color = styles.text.as_ref().map(|c| c.color.clone());
+    }
+    Ok(rendering)
+}
+
+/// The card of a credential, by the output descriptor of a manifest (see [`select_descriptor`]).
+pub fn card(
+    manifest: &CredentialManifest,
+    credential: &Value,
+    descriptor_id: Option<&str>,
+) -> Result<Card, RenderError> {
+    let descriptor = select_descriptor(manifest, credential, descriptor_id)?;
+    let rendering = render(
+        credential,
+        descriptor.display.as_ref(),
+        descriptor.styles.as_ref(),
+    )?;
+    Ok(Card {
+        descriptor_id: descriptor.id.clone(),
+        title: rendering.title,
+        subtitle: rendering.subtitle,
+        description: rendering.description,
+        properties: rendering
+            .properties
+            .into_iter()
+            .map(|(label, value)| CardProperty { label, value })
+            .collect(),
+        styles: descriptor.styles.clone(),
+        issuer: manifest.issuer.clone(),
+    })
+}
+
+/// Problems of a value selected by a display mapping, by the mapping's schema.
+fn check_value(value: &Value, schema: &DisplaySchema) -> Option<String> {
+    let matches = match &schema.type_[..] {
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        other => return Some(format!("unknown schema type {}", other)),
+    };
+    if !matches {
+        return Some(format!("value {} is not of type {}", value, schema.type_));
+    }
+    let string = match value.as_str() {
+        Some(string) => string,
+        None => return None,
+    };
+    let valid = match schema.format.as_deref() {
+        None => true,
+        Some("date-time") => DateTime::parse_from_rfc3339(string).is_ok(),
+        Some("date") => chrono::NaiveDate::parse_from_str(string, "%Y-%m-%d").is_ok(),
+        Some("email") => string.contains('@'),
+        Some("uri") => string.contains(':'),
+        Some(other) => return Some(format!("unknown string format {}", other)),
+    };
+    if valid {
+        return None;
+    }
+    let format = schema.format.as_deref().unwrap_or_default();
+    Some(format!("value {} is not in format {}", value, format))
+}
+
+fn check_mapping(
+    name: &str,
+    mapping: &DisplayMapping,
+    credential: &Value,
+    problems: &mut Vec<String>,
+) {
+    if mapping.text.is_some() {
+        return;
+    }
+    if mapping.path.is_empty() {
+        problems.push(format!("{}: neither text nor path", name));
+        return;
+    }
+    match json_path::select_first(credential, &mapping.path) {
+        Ok(Some(value)) => {
+            let problem = mapping
+                .schema
+                .as_ref()
+                .and_then(|schema| check_value(value, schema));
+            problems.extend(problem.map(|problem| format!("{}: {}", name, problem)));
+        }
+        Ok(None) if mapping.fallback.is_none() => problems.push(format!(
+            "{}: no value at {} and no fallback",
+            name,
+            mapping.path.join(", ")
+        )),
+        Ok(None) => {}
+        Err(err) => problems.push(format!("{}: {}", name, err)),
+    }
+}
+
+fn check_styles(name: &str, styles: &Styles, problems: &mut Vec<String>) {
+    let images = [("thumbnail", &styles.thumbnail), ("hero", &styles.hero)];
+    for (image_name, image) in images.iter() {
+        if let Some(image) = image {
+            if !image.uri.contains(':') {
+                problems.push(format!(
+                    "{}.{}: invalid URI {}",
+                    name, image_name, image.uri
+                ));
+            }
+        }
+    }
+    let colors = [("background", &styles.background), ("text", &styles.text)];
+    for (color_name, color) in colors.iter() {
+        if let Some(color) = color {
+            let digits = color.color.strip_prefix('#').unwrap_or_default();
+            let valid = (digits.len() == 3 || digits.len() == 6)
+                && digits.chars().all(|c| c.is_ascii_hexdigit());
+            if !valid {
+                problems.push(format!(
+                    "{}.{}: invalid color {}",
+                    name, color_name, color.color
+                ));
+            }
+        }
+    }
+}
+
+/// Check the display mappings and styles of the output descriptor of a manifest for a credential
+/// (see [`select_descriptor`]), and the styles of the manifest's issuer. Returns the problems
+/// found, each prefixed by the mapping or style it is in, e.g. `display.properties[0]`: a mapping
+/// with neither text nor path, an invalid path, a path selecting no value of the credential when
+/// there is no fallback, a value not of the type or format of the mapping's schema, an image
+/// without a URI or a color that is not hexadecimal.
+pub fn validate_display(
+    manifest: &CredentialManifest,
+    credential: &Value,
+    descriptor_id: Option<&str>,
+) -> Result<Vec<String>, RenderError> {
+    let descriptor = select_descriptor(manifest, credential, descriptor_id)?;
+    let mut problems = Vec::new();
+    if let Some(ref display) = descriptor.display {
+        let mappings = [
+            ("title", &display.title),
+            ("subtitle", &display.subtitle),
+            ("description", &display.description),
+        ];
+        for (name, mapping) in mappings.iter() {
+            if let Some(mapping) = mapping {
+                let name = format!("display.{}", name);
+                check_mapping(&name, mapping, credential, &mut problems);
+            }
+        }
+        for (i, property) in display.properties.iter().enumerate() {
+            let name = format!("display.properties[{}]", i);
+            check_mapping(&name, &property.mapping, credential, &mut problems);
+        }
+    }
+    if let Some(ref styles) = descriptor.styles {
+        check_styles("styles", styles, &mut problems);
+    }
+    if let Some(styles) = manifest
+        .issuer
+        .as_ref()
+        .and_then(|issuer| issuer.styles.as_ref())
+    {
+        check_styles("issuer.styles", styles, &mut problems);
+    }
+    Ok(problems)
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+impl Rendering {
+    /// Labels and values of the issuer and dates, after the properties.
+    fn footer(&self) -> Vec<(&str, &str)> {
+        let mut footer = Vec::new();
+        if let Some(ref issuer) = self.issuer {
+            footer.push(("Issuer", issuer.as_str()));
+        }
+        if let Some(ref date) = self.issuance_date {
+            footer.push(("Issued", date.as_str()));
+        }
+        if let Some(ref date) = self.expiration_date {
+            footer.push(("Expires", date.as_str()));
+        }
+        footer
+    }
+
+    fn properties(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties
+            .iter()
+            .map(|(label, value)| (label.as_str(), value.as_str()))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = self.title.clone();
+        if let Some(ref subtitle) = self.subtitle {
+            let _ = write!(text, "\n{}", subtitle);
+        }
+        if let Some(ref description) = self.description {
+            let _ = write!(text, "\n\n{}", description);
+        }
+        text.push('\n');
+        for (label, value) in self.properties().chain(self.footer()) {
+            let _ = write!(text, "\n{}: {}", label, value);
+        }
+        text.push('\n');
+        text
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {}\n", escape_markdown(&self.title));
+        if let Some(ref subtitle) = self.subtitle {
+            let _ = write!(md, "\n*{}*\n", escape_markdown(subtitle));
+        }
+        if let Some(ref description) = self.description {
+            let _ = write!(md, "\n{}\n", escape_markdown(description));
+        }
+        md.push('\n');
+        for (label, value) in self.properties() {
+            let _ = writeln!(
+                md,
+                "- **{}:** {}",
+                escape_markdown(label),
+                escape_markdown(value)
+            );
+        }
+        md.push('\n');
+        for (label, value) in self.footer() {
+            let _ = writeln!(md, "{}: {}  ", label, escape_markdown(value));
+        }
+        md
+    }
+
+    /// An HTML fragment, with inline styles only, to be embedded e.g. in email notifications.
+    pub fn to_html(&self) -> String {
+        let mut style = String::from("font-family:sans-serif;padding:1em;border-radius:8px;");
+        if let Some(ref color) = self.background_color {
+            let _ = write!(style, "background-color:{};", escape_html(color));
+        }
+        if let Some(ref color) = self.text_color {
+            let _ = write!(style, "color:{};", escape_html(color));
+        }
+        let mut html = format!(
+            "<article class=\"credential\" style=\"{}\">\n<h1>{}</h1>\n",
+            style,
+            escape_html(&self.title)
+        );
+        if let Some(ref subtitle) = self.subtitle {
+            let _ = writeln!(html, "<p><em>{}</em></p>", escape_html(subtitle));
+        }
+        if let Some(ref description) = self.description {
+            let _ = writeln!(html, "<p>{}</p>", escape_html(description));
+        }
+        html.push_str("<dl>\n");
+        for (label, value) in self.properties().chain(self.footer()) {
+            let _ = writeln!(
+                html,
+                "<dt>{}</dt><dd>{}</dd>",
+                escape_html(label),
+                escape_html(value)
+            );
+        }
+        html.push_str("</dl>\n</article>\n");
+        html
+    }
+
+    pub fn format(&self, format: RenderFormat) -> String {
+        match format {
+            RenderFormat::Text => self.to_text(),
+            RenderFormat::Markdown => self.to_markdown(),
+            RenderFormat::HTML => self.to_html(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_credential() {
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential", "UniversityDegreeCredential"],
+            "issuer": { "id": "did:example:university", "name": "Example University" },
+            "issuanceDate": "2024-06-01T12:00:00Z",
+            "credentialSubject": {
+                "id": "did:example:alice",
+                "givenName": "Alice",
+                "degree": { "type": "BachelorDegree", "field": "Physics <Honours>" }
+            }
+        });
+        let rendering = render(&credential, None, None).unwrap();
+        assert_eq!(rendering.title, "University degree credential");
+        assert_eq!(rendering.properties.len(), 3);
+        assert!(rendering
+            .properties
+            .contains(&("Given name".to_string(), "Alice".to_string())));
+        assert!(rendering.properties.contains(&(
+            "Degree / Field".to_string(),
+            "Physics <Honours>".to_string()
+        )));
+        assert_eq!(rendering.issuer.as_deref(), Some("Example University"));
+        assert_eq!(
+            rendering.issuance_date.as_deref(),
+            Some("2024-06-01 12:00 UTC")
+        );
+        assert!(rendering
+            .to_html()
+            .contains("<dd>Physics &lt;Honours&gt;</dd>"));
+
+        let manifest: CredentialManifest = serde_json::from_value(json!({
+            "id": "degree-manifest",
+            "output_descriptors": [
+                { "id": "other", "schema": "OtherCredential" },
+                {
+                    "id": "degree",
+                    "schema": "UniversityDegreeCredential",
+                    "display": {
+                        "title": { "text": "Degree" },
+                        "subtitle": { "path": ["$.issuer.name"] },
+                        "properties": [
+                            { "label": "Name", "path": ["$.credentialSubject.givenName"] },
+                            { "label": "GPA", "path": ["$.credentialSubject.gpa"], "fallback": "n/a" }
+                        ]
+                    },
+                    "styles": { "background": { "color": "#123456" } }
+                }
+            ]
+        }))
+        .unwrap();
+        let descriptor = select_descriptor(&manifest, &credential, None).unwrap();
+        assert_eq!(descriptor.id, "degree");
+        let rendering = render(
+            &credential,
+            descriptor.display.as_ref(),
+            descriptor.styles.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(rendering.title, "Degree");
+        assert_eq!(rendering.subtitle.as_deref(), Some("Example University"));
+        assert_eq!(
+            rendering.properties[1],
+            ("GPA".to_string(), "n/a".to_string())
+        );
+        assert!(rendering.to_text().contains("\nName: Alice\n"));
+        assert!(rendering.to_markdown().contains("- **Name:** Alice\n"));
+        assert!(rendering.to_html().contains("background-color:#123456;"));
+        assert!(select_descriptor(&manifest, &credential, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn card_and_display_validation() {
+        let credential = json!({
+            "type": ["VerifiableCredential", "MembershipCredential"],
+            "issuer": "did:example:club",
+            "credentialSubject": { "name": "Alice", "since": "2020-01-01", "level": "gold" }
+        });
+        let mut manifest: CredentialManifest = serde_json::from_value(json!({
+            "id": "membership-manifest",
+            "issuer": {
+                "id": "did:example:club",
+                "name": "Example Club",
+                "styles": { "thumbnail": { "uri": "https://club.example/logo.png", "alt": "Logo" } }
+            },
+            "output_descriptors": [{
+                "id": "membership",
+                "schema": "MembershipCredential",
+                "display": {
+                    "title": { "text": "Membership" },
+                    "properties": [
+                        { "label": "Member", "path": ["$.credentialSubject.name"] },
+                        {
+                            "label": "Since",
+                            "path": ["$.credentialSubject.since"],
+                            "schema": { "type": "string", "format": "date" }
+                        }
+                    ]
+                },
+                "styles": {
+                    "hero": { "uri": "https://club.example/hero.png" },
+                    "background": { "color": "#ffd700" },
+                    "text": { "color": "#000" }
+                }
+            }]
+        }))
+        .unwrap();
+        let card = card(&manifest, &credential, None).unwrap();
+        assert_eq!(card.descriptor_id, "membership");
+        assert_eq!(card.title, "Membership");
+        assert_eq!(
+            card.properties[0],
+            CardProperty {
+                label: "Member".to_string(),
+                value: "Alice".to_string()
+            }
+        );
+        let card_json = serde_json::to_value(&card).unwrap();
+        assert_eq!(card_json["styles"]["background"]["color"], "#ffd700");
+        assert_eq!(card_json["issuer"]["styles"]["thumbnail"]["alt"], "Logo");
+        assert!(validate_display(&manifest, &credential, None)
+            .unwrap()
+            .is_empty());
+
+        let descriptor = &mut manifest.output_descriptors[0];
+        let display = descriptor.display.as_mut().unwrap();
+        display.subtitle = Some(DisplayMapping::default());
+        display.properties[0].mapping.path = vec!["$.credentialSubject.rank".to_string()];
+        display.properties[1].mapping.schema = Some(DisplaySchema {
+            type_: "integer".to_string(),
+            format: None,
+        });
+        descriptor.styles.as_mut().unwrap().text = Some(Color {
+            color: "black".to_string(),
+        });
+        let problems = validate_display(&manifest, &credential, None).unwrap();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].starts_with("display.subtitle: "));
+        assert!(problems[1].starts_with("display.properties[0]: no value"));
+        assert!(problems[2].starts_with("display.properties[1]: "));
+        assert_eq!(problems[3], "styles.text: invalid color black");
+    }
+}
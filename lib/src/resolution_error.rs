@@ -0,0 +1,138 @@
+//! Standard DID resolution error codes.
+//!
+//! DID methods report some resolution failures with the `error` codes of [DID Core] and [DID
+//! Resolution], and others with free-form messages, e.g. of a failed HTTP request. Normalizing
+//! resolution or dereferencing metadata replaces a free-form message with the code it most
+//! likely corresponds to, or `internalError`, and keeps the message in the `errorMessage`
+//! property, so that clients can act on the code alone.
+//!
+//! [DID Core]: https://www.w3.org/TR/did-core/#did-resolution-metadata
+//! [DID Resolution]: https://w3c-ccg.github.io/did-resolution/#errors
+
+use std::collections::HashMap;
+
+use ssi::did_resolve::{
+    DereferencingMetadata, Metadata, ERROR_INVALID_DID, ERROR_METHOD_NOT_SUPPORTED,
+    ERROR_NOT_FOUND, ERROR_REPRESENTATION_NOT_SUPPORTED,
+};
+
+use crate::ResolutionMetadata;
+
+/// Error code of a DID URL that can not be parsed.
+pub const ERROR_INVALID_DID_URL: &str = "invalidDidUrl";
+/// Error code of a failure that has no more specific code.
+pub const ERROR_INTERNAL: &str = "internalError";
+/// Metadata property with the original message of a normalized error.
+pub const PROPERTY_ERROR_MESSAGE: &str = "errorMessage";
+
+/// Error codes passed through unchanged.
+pub const ERROR_CODES: &[&str] = &[
+    ERROR_INVALID_DID,
+    ERROR_INVALID_DID_URL,
+    ERROR_NOT_FOUND,
+    ERROR_REPRESENTATION_NOT_SUPPORTED,
+    ERROR_METHOD_NOT_SUPPORTED,
+    ERROR_INTERNAL,
+];
+
+/// Standard error code for a resolution error, which may already be one.
+pub fn error_code(error: &str) -> &'static str {
+    if let Some(code) = ERROR_CODES.iter().find(|code| **code == error) {
+        return code;
+    }
+    let message = error.to_lowercase();
+    if message.contains("not found") || message.contains("404") {
+        ERROR_NOT_FOUND
+    } else if message.contains("method not supported")
+        || message.contains("unsupported method")
+        || message.contains("unknown did method")
+    {
+        ERROR_METHOD_NOT_SUPPORTED
+    } else if message.contains("representation") || message.contains("content type") {
+        ERROR_REPRESENTATION_NOT_SUPPORTED
+    } else if message.contains("did url") {
+        ERROR_INVALID_DID_URL
+    } else if message.contains("invalid did") || message.contains("parse did") {
+        ERROR_INVALID_DID
+    } else {
+        ERROR_INTERNAL
+    }
+}
+
+/// HTTP status code for a resolution error code, per the DID Resolution HTTP(S) binding.
+pub fn status_code(code: &str) -> u16 {
+    match error_code(code) {
+        ERROR_INVALID_DID | ERROR_INVALID_DID_URL => 400,
+        ERROR_NOT_FOUND => 404,
+        ERROR_REPRESENTATION_NOT_SUPPORTED => 406,
+        ERROR_METHOD_NOT_SUPPORTED => 501,
+        _ => 500,
+    }
+}
+
+fn normalize(error: &mut Option<String>, property_set: &mut Option<HashMap<String, Metadata>>) {
+    let message = match error {
+        Some(message) => message,
+        None => return,
+    };
+    let code = error_code(message);
+    if code == message {
+        return;
+    }
+    let message = std::mem::replace(message, code.to_string());
+    property_set
+        .get_or_insert_with(HashMap::new)
+        .entry(PROPERTY_ERROR_MESSAGE.to_string())
+        .or_insert(Metadata::String(message));
+}
+
+/// Replace a free-form error in resolution metadata with its standard code.
+pub fn normalize_resolution_metadata(res_meta: &mut ResolutionMetadata) {
+    normalize(&mut res_meta.error, &mut res_meta.property_set);
+}
+
+/// Replace a free-form error in dereferencing metadata with its standard code.
+pub fn normalize_dereferencing_metadata(deref_meta: &mut DereferencingMetadata) {
+    normalize(&mut deref_meta.error, &mut deref_meta.property_set);
+}
+
+/// Error message of normalized metadata, `code: message` if a free-form message was replaced.
+pub fn describe(error: &str, property_set: &Option<HashMap<String, Metadata>>) -> String {
+    match property_set
+        .as_ref()
+        .and_then(|props| props.get(PROPERTY_ERROR_MESSAGE))
+    {
+        Some(Metadata::String(message)) => format!("{}: {}", error, message),
+        _ => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_errors() {
+        let mut res_meta = ResolutionMetadata {
+            error: Some(ERROR_NOT_FOUND.to_string()),
+            ..Default::default()
+        };
+        normalize_resolution_metadata(&mut res_meta);
+        assert_eq!(res_meta.error.as_deref(), Some(ERROR_NOT_FOUND));
+        assert!(res_meta.property_set.is_none());
+
+        res_meta.error = Some("Error sending HTTP request: connection refused".to_string());
+        normalize_resolution_metadata(&mut res_meta);
+        assert_eq!(res_meta.error.as_deref(), Some(ERROR_INTERNAL));
+        assert_eq!(
+            describe(ERROR_INTERNAL, &res_meta.property_set),
+            "internalError: Error sending HTTP request: connection refused"
+        );
+
+        assert_eq!(error_code("HTTP error: 404 Not Found"), ERROR_NOT_FOUND);
+        assert_eq!(error_code("Unable to parse DID URL"), ERROR_INVALID_DID_URL);
+        assert_eq!(status_code(ERROR_METHOD_NOT_SUPPORTED), 501);
+        assert_eq!(status_code("Unsupported method"), 501);
+        assert_eq!(status_code(ERROR_INVALID_DID), 400);
+    }
+}
@@ -0,0 +1,938 @@
+//! DID resolver wrappers and middleware.
+
+use std::collections::HashMap;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ssi::did::PrimaryDIDURL;
+use ssi::did_resolve::{Metadata, ERROR_METHOD_NOT_SUPPORTED, ERROR_NOT_FOUND, TYPE_DID_LD_JSON};
+
+use crate::cancellation::{cancelled_metadata, Cancellation};
+use crate::multikey::normalize_document;
+use crate::observe::{self, Event, Timer};
+use crate::{
+    Content, ContentMetadata, DIDResolver, DereferencingInputMetadata, Document, DocumentMetadata,
+    ResolutionInputMetadata, ResolutionMetadata,
+};
+
+type ResolutionOutput = (ResolutionMetadata, Option<Document>, Option<DocumentMetadata>);
+
+/// Resolution metadata property set by [`ResolverCache`] on results served from the cache.
+pub const PROPERTY_CACHED: &str = "cached";
+
+/// DID methods whose resolvers select a version of the DID document by the `versionTime` input
+/// metadata: `did:webvh`, and `did:ion` through a universal resolver.
+pub const VERSIONED_METHODS: &[&str] = &["webvh", "ion"];
+
+/// Configuration for a [`ResolverCache`].
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Maximum number of cached resolution results
+    pub capacity: usize,
+    /// Time to keep a resolved DID document
+    pub ttl: Duration,
+    /// Per-method overrides of `ttl`, keyed by method name, e.g. `web`
+    pub method_ttls: HashMap<String, Duration>,
+    /// Time to remember that a DID was not found. Not-found results are not cached if `None`.
+    pub negative_ttl: Option<Duration>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            ttl: Duration::from_secs(300),
+            method_ttls: HashMap::new(),
+            negative_ttl: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl CacheConfig {
+    fn ttl_for(&self, did: &str, output: &ResolutionOutput) -> Option<Duration> {
+        match output.0.error {
+            None => {}
+            Some(ref error) if error == ERROR_NOT_FOUND => return self.negative_ttl,
+            Some(_) => return None,
+        }
+        Some(*self.method_ttls.get(did_method(did)).unwrap_or(&self.ttl))
+    }
+}
+
+/// Parse a cache configuration from a TTL in seconds, optionally followed by per-method TTLs,
+/// e.g. `300` or `300,web=60,key=86400`.
+impl FromStr for CacheConfig {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let ttl = parts.next().unwrap_or_default().trim().parse()?;
+        let mut config = Self {
+            ttl: Duration::from_secs(ttl),
+            ..Default::default()
+        };
+        for part in parts {
+            let mut kv = part.splitn(2, '=');
+            let method = kv.next().unwrap_or_default().trim().to_string();
+            let ttl = kv.next().unwrap_or_default().trim().parse()?;
+            config.method_ttls.insert(method, Duration::from_secs(ttl));
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    output: ResolutionOutput,
+    expires: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    counter: u64,
+}
+
+/// LRU cache of DID resolution results, shared between [`CachingResolver`]s.
+#[derive(Debug)]
+pub struct ResolverCache {
+    config: CacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl ResolverCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Parse a cache configuration (see [`CacheConfig::from_str`]) into a shareable cache.
+    pub fn from_config_str(s: &str) -> Result<Arc<Self>, ParseIntError> {
+        Ok(Arc::new(Self::new(CacheConfig::from_str(s)?)))
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    fn get(&self, key: &str) -> Option<ResolutionOutput> {
+        let mut state = self.state.lock().ok()?;
+        state.counter += 1;
+        let counter = state.counter;
+        match state.entries.get_mut(key) {
+            Some(entry) if entry.expires > Instant::now() => {
+                entry.last_used = counter;
+                Some(entry.output.clone())
+            }
+            Some(_) => {
+                state.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, output: ResolutionOutput, ttl: Duration) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        if state.entries.len() >= self.config.capacity && !state.entries.contains_key(&key) {
+            state.entries.retain(|_, entry| entry.expires > now);
+        }
+        if state.entries.len() >= self.config.capacity && !state.entries.contains_key(&key) {
+            let lru = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.to_string());
+            if let Some(lru) = lru {
+                state.entries.remove(&lru);
+            }
+        }
+        state.counter += 1;
+        let last_used = state.counter;
+        state.entries.insert(
+            key,
+            CacheEntry {
+                output,
+                expires: now + ttl,
+                last_used,
+            },
+        );
+    }
+
+    /// Remove all cached results.
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+        }
+    }
+}
+
+/// DID resolver that caches the results of another resolver.
+pub struct CachingResolver<R> {
+    inner: R,
+    cache: Option<Arc<ResolverCache>>,
+}
+
+impl<R: DIDResolver> CachingResolver<R> {
+    /// Wrap a resolver. If `cache` is `None`, results are not cached.
+    pub fn new(inner: R, cache: Option<Arc<ResolverCache>>) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[async_trait]
+impl<R: DIDResolver> DIDResolver for CachingResolver<R> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        match self.cache {
+            Some(ref cache) => {
+                let next = Next::new(&self.inner);
+                cache.resolve(did, input_metadata, next).await
+            }
+            None => self.inner.resolve(did, input_metadata).await,
+        }
+    }
+
+    async fn resolve_representation(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (ResolutionMetadata, Vec<u8>, Option<DocumentMetadata>) {
+        self.inner.resolve_representation(did, input_metadata).await
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(ResolutionMetadata, Content, ContentMetadata)> {
+        self.inner.dereference(primary_did_url, input_metadata).await
+    }
+}
+
+/// A layer in a [`ResolverStack`]. Middleware may inspect or rewrite a resolution request, pass it
+/// on to the rest of the stack using [`Next::resolve`], or answer it directly.
+#[async_trait]
+pub trait ResolverMiddleware: Send + Sync {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput;
+}
+
+/// The remainder of a [`ResolverStack`], after the current middleware.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn ResolverMiddleware>],
+    resolver: &'a dyn DIDResolver,
+}
+
+impl<'a> Next<'a> {
+    /// Start of a stack with no middleware.
+    pub fn new(resolver: &'a dyn DIDResolver) -> Self {
+        Self {
+            middleware: &[],
+            resolver,
+        }
+    }
+
+    pub async fn resolve(
+        self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => {
+                let next = Next {
+                    middleware: rest,
+                    resolver: self.resolver,
+                };
+                middleware.resolve(did, input_metadata, next).await
+            }
+            None => self.resolver.resolve(did, input_metadata).await,
+        }
+    }
+}
+
+/// DID resolver composed of middleware layers around a resolver. Layers are applied in order,
+/// the first being the outermost. Resolutions are reported to the [observers](crate::observe).
+pub struct ResolverStack<R> {
+    middleware: Vec<Arc<dyn ResolverMiddleware>>,
+    resolver: R,
+}
+
+impl<R: DIDResolver> ResolverStack<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            middleware: Vec::new(),
+            resolver,
+        }
+    }
+
+    /// Add a layer inside the existing layers.
+    pub fn layer(mut self, middleware: Arc<dyn ResolverMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+}
+
+#[async_trait]
+impl<R: DIDResolver> DIDResolver for ResolverStack<R> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        let next = Next {
+            middleware: &self.middleware,
+            resolver: &self.resolver,
+        };
+        observe::emit(|| Event::ResolutionStarted { did });
+        let timer = Timer::start();
+        let output = next.resolve(did, input_metadata).await;
+        observe::emit(|| Event::ResolutionFinished {
+            did,
+            error: output.0.error.as_deref(),
+            elapsed: timer.elapsed(),
+        });
+        output
+    }
+
+    /// Resolve the DID through the layers, and serialize the DID document as JSON-LD.
+    async fn resolve_representation(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (ResolutionMetadata, Vec<u8>, Option<DocumentMetadata>) {
+        let (mut res_meta, doc, doc_meta) = self.resolve(did, input_metadata).await;
+        let representation = match doc {
+            Some(doc) => match serde_json::to_vec_pretty(&doc) {
+                Ok(representation) => representation,
+                Err(err) => {
+                    let error = format!("Unable to serialize resolved document: {}", err);
+                    return (error_output(&error).0, Vec::new(), None);
+                }
+            },
+            None => return (res_meta, Vec::new(), doc_meta),
+        };
+        res_meta.content_type = Some(TYPE_DID_LD_JSON.to_string());
+        (res_meta, representation, doc_meta)
+    }
+
+    /// DID URLs are not dereferenced by the resolver, so that [`dereference`](crate::dereference)
+    /// resolves their DID through the layers and selects the resource from the DID document.
+    async fn dereference(
+        &self,
+        _primary_did_url: &PrimaryDIDURL,
+        _input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(ResolutionMetadata, Content, ContentMetadata)> {
+        None
+    }
+}
+
+fn did_method(did: &str) -> &str {
+    did.split(':').nth(1).unwrap_or_default()
+}
+
+fn error_output(error: &str) -> ResolutionOutput {
+    let res_meta = ResolutionMetadata {
+        error: Some(error.to_string()),
+        ..Default::default()
+    };
+    (res_meta, None, None)
+}
+
+#[async_trait]
+impl ResolverMiddleware for ResolverCache {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        // Resolution input metadata (e.g. versionId) can change the result.
+        let key = match serde_json::to_string(input_metadata) {
+            Ok(input_metadata) => format!("{} {}", did, input_metadata),
+            Err(_) => return next.resolve(did, input_metadata).await,
+        };
+        if let Some(mut output) = self.get(&key) {
+            output
+                .0
+                .property_set
+                .get_or_insert_with(HashMap::new)
+                .insert(PROPERTY_CACHED.to_string(), Metadata::Boolean(true));
+            return output;
+        }
+        let output = next.resolve(did, input_metadata).await;
+        if let Some(ttl) = self.config.ttl_for(did, &output) {
+            self.insert(key, output.clone(), ttl);
+        }
+        output
+    }
+}
+
+/// Middleware that rejects DIDs by method, with `methodNotSupported`.
+#[derive(Debug, Clone, Default)]
+pub struct MethodFilter {
+    /// If set, only these methods are resolved.
+    pub allow: Option<Vec<String>>,
+    /// Methods that are never resolved.
+    pub deny: Vec<String>,
+}
+
+impl MethodFilter {
+    pub fn is_allowed(&self, method: &str) -> bool {
+        if self.deny.iter().any(|m| m == method) {
+            return false;
+        }
+        match self.allow {
+            Some(ref allow) => allow.iter().any(|m| m == method),
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl ResolverMiddleware for MethodFilter {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        if !self.is_allowed(did_method(did)) {
+            return error_output(ERROR_METHOD_NOT_SUPPORTED);
+        }
+        next.resolve(did, input_metadata).await
+    }
+}
+
+/// Middleware that sends DIDs of specific methods to a specific resolver, e.g. an HTTP(S)
+/// resolver endpoint, instead of the rest of the stack.
+#[derive(Clone, Default)]
+pub struct MethodRouter {
+    routes: HashMap<String, Arc<dyn DIDResolver + Send + Sync>>,
+}
+
+impl MethodRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, method: &str, resolver: Arc<dyn DIDResolver + Send + Sync>) -> Self {
+        self.routes.insert(method.to_string(), resolver);
+        self
+    }
+}
+
+#[async_trait]
+impl ResolverMiddleware for MethodRouter {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        match self.routes.get(did_method(did)) {
+            Some(resolver) => resolver.resolve(did, input_metadata).await,
+            None => next.resolve(did, input_metadata).await,
+        }
+    }
+}
+
+/// Middleware that logs each resolution to standard error.
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionLogger;
+
+#[async_trait]
+impl ResolverMiddleware for ResolutionLogger {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        let started = Instant::now();
+        let output = next.resolve(did, input_metadata).await;
+        let elapsed = started.elapsed().as_millis();
+        match output.0.error {
+            Some(ref error) => eprintln!("resolve {}: {} ({} ms)", did, error, elapsed),
+            None => eprintln!("resolve {}: ok ({} ms)", did, elapsed),
+        }
+        output
+    }
+}
+
+/// Middleware that fails resolutions taking longer than a timeout with `internalError`, e.g.
+/// for a slow `did:web` host.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolutionTimeout(pub Duration);
+
+#[async_trait]
+impl ResolverMiddleware for ResolutionTimeout {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        let cancellation = Cancellation::with_timeout(self.0);
+        match cancellation.run(next.resolve(did, input_metadata)).await {
+            Ok(output) => output,
+            Err(err) => (cancelled_metadata(err), None, None),
+        }
+    }
+}
+
+/// Middleware that adds `publicKeyJwk` to `Multikey` and other `publicKeyMultibase`
+/// verification methods, with [`normalize_document`], for resolvers other than the built-in
+/// DID methods (e.g. a universal resolver).
+#[derive(Debug, Clone, Default)]
+pub struct MultikeyNormalizer;
+
+#[async_trait]
+impl ResolverMiddleware for MultikeyNormalizer {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        let (res_meta, doc, doc_meta) = next.resolve(did, input_metadata).await;
+        (res_meta, doc.map(normalize_document), doc_meta)
+    }
+}
+
+/// Resolution counts and timing for a DID method.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodMetrics {
+    pub requests: u64,
+    pub errors: u64,
+    pub total_time: Duration,
+}
+
+/// Middleware that records resolution metrics per DID method.
+#[derive(Debug, Default)]
+pub struct ResolutionMetrics {
+    methods: Mutex<HashMap<String, MethodMetrics>>,
+}
+
+impl ResolutionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the metrics recorded so far, by DID method.
+    pub fn snapshot(&self) -> HashMap<String, MethodMetrics> {
+        match self.methods.lock() {
+            Ok(methods) => methods.clone(),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ResolverMiddleware for ResolutionMetrics {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        let started = Instant::now();
+        let output = next.resolve(did, input_metadata).await;
+        let elapsed = started.elapsed();
+        if let Ok(mut methods) = self.methods.lock() {
+            let metrics = methods.entry(did_method(did).to_string()).or_default();
+            metrics.requests += 1;
+            if output.0.error.is_some() {
+                metrics.errors += 1;
+            }
+            metrics.total_time += elapsed;
+        }
+        output
+    }
+}
+
+/// Record of a DID resolution performed during verification, identifying the state of the DID
+/// document that was used.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionRecord {
+    pub did: String,
+    pub method: String,
+    /// Resolver driver endpoint, if reported by the resolver, e.g. the `driverUrl` of a
+    /// universal resolver
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    /// The result was served from a [`ResolverCache`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+}
+
+impl ResolutionRecord {
+    fn new(did: &str, res_meta: &ResolutionMetadata, doc_meta: Option<&DocumentMetadata>) -> Self {
+        let res_meta = serde_json::to_value(res_meta).unwrap_or_default();
+        let doc_meta = serde_json::to_value(doc_meta).unwrap_or_default();
+        let string = |value: &Value, name: &str| value.get(name)?.as_str().map(String::from);
+        Self {
+            did: did.to_string(),
+            method: did_method(did).to_string(),
+            driver: string(&res_meta, "driverUrl"),
+            cached: res_meta.get(PROPERTY_CACHED).and_then(Value::as_bool) == Some(true),
+            error: string(&res_meta, "error"),
+            version_id: string(&doc_meta, "versionId"),
+            created: string(&doc_meta, "created"),
+            updated: string(&doc_meta, "updated"),
+            deactivated: doc_meta.get("deactivated").and_then(Value::as_bool),
+        }
+    }
+}
+
+/// DID resolver that records the resolutions made through it, once per DID.
+pub struct RecordingResolver<'a> {
+    inner: &'a dyn DIDResolver,
+    records: Mutex<Vec<ResolutionRecord>>,
+}
+
+impl<'a> RecordingResolver<'a> {
+    pub fn new(inner: &'a dyn DIDResolver) -> Self {
+        Self {
+            inner,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn record(
+        &self,
+        did: &str,
+        res_meta: &ResolutionMetadata,
+        doc_meta: Option<&DocumentMetadata>,
+    ) {
+        if let Ok(mut records) = self.records.lock() {
+            if !records.iter().any(|record| record.did == did) {
+                records.push(ResolutionRecord::new(did, res_meta, doc_meta));
+            }
+        }
+    }
+
+    /// The resolutions recorded so far, in order.
+    pub fn records(&self) -> Vec<ResolutionRecord> {
+        match self.records.lock() {
+            Ok(records) => records.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> DIDResolver for RecordingResolver<'a> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        let output = self.inner.resolve(did, input_metadata).await;
+        self.record(did, &output.0, output.2.as_ref());
+        output
+    }
+
+    async fn resolve_representation(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (ResolutionMetadata, Vec<u8>, Option<DocumentMetadata>) {
+        let output = self.inner.resolve_representation(did, input_metadata).await;
+        self.record(did, &output.0, output.2.as_ref());
+        output
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(ResolutionMetadata, Content, ContentMetadata)> {
+        self.inner.dereference(primary_did_url, input_metadata).await
+    }
+}
+
+/// DID resolver that resolves DIDs of [`VERSIONED_METHODS`] as of a time, with the
+/// `versionTime` input metadata, unless the resolution requests a version already. DIDs of other
+/// methods are resolved as they are now.
+pub struct VersionTimeResolver<'a> {
+    inner: &'a dyn DIDResolver,
+    version_time: String,
+}
+
+impl<'a> VersionTimeResolver<'a> {
+    pub fn new(inner: &'a dyn DIDResolver, version_time: DateTime<Utc>) -> Self {
+        Self {
+            inner,
+            version_time: version_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    fn input_metadata(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> Option<ResolutionInputMetadata> {
+        if !VERSIONED_METHODS.contains(&did_method(did))
+            || input_metadata.version_id.is_some()
+            || input_metadata.version_time.is_some()
+        {
+            return None;
+        }
+        Some(ResolutionInputMetadata {
+            version_time: Some(self.version_time.clone()),
+            ..input_metadata.clone()
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> DIDResolver for VersionTimeResolver<'a> {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        match self.input_metadata(did, input_metadata) {
+            Some(ref input_metadata) => self.inner.resolve(did, input_metadata).await,
+            None => self.inner.resolve(did, input_metadata).await,
+        }
+    }
+
+    async fn resolve_representation(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (ResolutionMetadata, Vec<u8>, Option<DocumentMetadata>) {
+        match self.input_metadata(did, input_metadata) {
+            Some(ref input_metadata) => {
+                self.inner.resolve_representation(did, input_metadata).await
+            }
+            None => self.inner.resolve_representation(did, input_metadata).await,
+        }
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(ResolutionMetadata, Content, ContentMetadata)> {
+        self.inner
+            .dereference(primary_did_url, input_metadata)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingResolver(Mutex<usize>);
+
+    #[async_trait]
+    impl DIDResolver for CountingResolver {
+        async fn resolve(
+            &self,
+            did: &str,
+            _input_metadata: &ResolutionInputMetadata,
+        ) -> ResolutionOutput {
+            *self.0.lock().unwrap() += 1;
+            if did == "did:example:missing" {
+                return error_output(ERROR_NOT_FOUND);
+            }
+            (ResolutionMetadata::default(), None, None)
+        }
+    }
+
+    #[test]
+    fn cache_hits_and_eviction() {
+        crate::runtime::get()
+            .unwrap()
+            .block_on(cache_hits_and_eviction_async());
+    }
+
+    async fn cache_hits_and_eviction_async() {
+        let mut config = CacheConfig::from_str("300,web=0").unwrap();
+        config.capacity = 2;
+        let cache = Arc::new(ResolverCache::new(config));
+        let resolver = CachingResolver::new(CountingResolver(Mutex::new(0)), Some(cache));
+        let input = ResolutionInputMetadata::default();
+        resolver.resolve("did:example:a", &input).await;
+        resolver.resolve("did:example:a", &input).await;
+        assert_eq!(*resolver.inner.0.lock().unwrap(), 1);
+        // Negative caching
+        resolver.resolve("did:example:missing", &input).await;
+        resolver.resolve("did:example:missing", &input).await;
+        assert_eq!(*resolver.inner.0.lock().unwrap(), 2);
+        // Zero per-method TTL expires immediately
+        resolver.resolve("did:web:example.org", &input).await;
+        resolver.resolve("did:web:example.org", &input).await;
+        assert_eq!(*resolver.inner.0.lock().unwrap(), 4);
+        // Least recently used entry was evicted
+        resolver.resolve("did:example:a", &input).await;
+        assert_eq!(*resolver.inner.0.lock().unwrap(), 5);
+
+        // Cache hits are marked in the resolution metadata
+        let recorder = RecordingResolver::new(&resolver);
+        recorder.resolve("did:example:a", &input).await;
+        recorder.resolve("did:example:a", &input).await;
+        let records = recorder.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].method, "example");
+        assert!(records[0].cached);
+    }
+
+    #[test]
+    fn middleware_stack() {
+        crate::runtime::get()
+            .unwrap()
+            .block_on(middleware_stack_async());
+    }
+
+    async fn middleware_stack_async() {
+        let metrics = Arc::new(ResolutionMetrics::new());
+        let filter = MethodFilter {
+            allow: None,
+            deny: vec!["web".to_string()],
+        };
+        let resolver = ResolverStack::new(CountingResolver(Mutex::new(0)))
+            .layer(metrics.clone())
+            .layer(Arc::new(filter));
+        let input = ResolutionInputMetadata::default();
+        let (res_meta, _, _) = resolver.resolve("did:web:example.org", &input).await;
+        assert_eq!(res_meta.error.unwrap(), ERROR_METHOD_NOT_SUPPORTED);
+        resolver.resolve("did:example:a", &input).await;
+        assert_eq!(*resolver.resolver.0.lock().unwrap(), 1);
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["web"].errors, 1);
+        assert_eq!(snapshot["example"].requests, 1);
+
+        // Representations and dereferencing go through the layers too
+        let (res_meta, representation, _) = resolver
+            .resolve_representation("did:web:example.org", &input)
+            .await;
+        assert_eq!(res_meta.error.unwrap(), ERROR_METHOD_NOT_SUPPORTED);
+        assert!(representation.is_empty());
+        let (deref_meta, _, _) =
+            crate::dereference(&resolver, "did:web:example.org#key-1", &Default::default()).await;
+        assert_eq!(deref_meta.error.unwrap(), ERROR_METHOD_NOT_SUPPORTED);
+        assert_eq!(*resolver.resolver.0.lock().unwrap(), 1);
+        assert_eq!(metrics.snapshot()["web"].errors, 3);
+    }
+
+    struct VersionTimeRecorder(Mutex<Vec<Option<String>>>);
+
+    #[async_trait]
+    impl DIDResolver for VersionTimeRecorder {
+        async fn resolve(
+            &self,
+            _did: &str,
+            input_metadata: &ResolutionInputMetadata,
+        ) -> ResolutionOutput {
+            let version_time = input_metadata.version_time.clone();
+            self.0.lock().unwrap().push(version_time);
+            (ResolutionMetadata::default(), None, None)
+        }
+    }
+
+    #[test]
+    fn version_time_resolution() {
+        crate::runtime::get()
+            .unwrap()
+            .block_on(version_time_resolution_async());
+    }
+
+    async fn version_time_resolution_async() {
+        let inner = VersionTimeRecorder(Mutex::new(vec![]));
+        let time = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap();
+        let resolver = VersionTimeResolver::new(&inner, time.with_timezone(&Utc));
+        let input = ResolutionInputMetadata::default();
+        let did = "did:webvh:QmScid:example.com";
+        resolver.resolve(did, &input).await;
+        resolver.resolve("did:key:z6Mk", &input).await;
+        let version_time = Some("2023-01-01T00:00:00Z".to_string());
+        let requested = ResolutionInputMetadata {
+            version_time: version_time.clone(),
+            ..Default::default()
+        };
+        resolver.resolve("did:ion:EiA", &requested).await;
+        assert_eq!(
+            *inner.0.lock().unwrap(),
+            vec![Some("2024-05-01T12:00:00Z".to_string()), None, version_time]
+        );
+    }
+
+    #[test]
+    fn resolution_record() {
+        let res_meta: ResolutionMetadata = serde_json::from_value(serde_json::json!({
+            "driverUrl": "https://uniresolver.example/1.0/identifiers/",
+            "contentType": "application/did+ld+json",
+        }))
+        .unwrap();
+        let doc_meta: DocumentMetadata = serde_json::from_value(serde_json::json!({
+            "created": "2023-01-01T00:00:00Z",
+            "deactivated": false,
+            "versionId": "2",
+        }))
+        .unwrap();
+        let record = ResolutionRecord::new("did:web:example.org", &res_meta, Some(&doc_meta));
+        assert_eq!(record.method, "web");
+        assert_eq!(
+            record.driver.as_deref(),
+            Some("https://uniresolver.example/1.0/identifiers/")
+        );
+        assert!(!record.cached);
+        assert_eq!(record.error, None);
+        assert_eq!(record.version_id.as_deref(), Some("2"));
+        assert_eq!(record.created.as_deref(), Some("2023-01-01T00:00:00Z"));
+        assert_eq!(record.deactivated, Some(false));
+
+        let (res_meta, _, _) = error_output(ERROR_NOT_FOUND);
+        let record = ResolutionRecord::new("did:example:missing", &res_meta, None);
+        assert_eq!(record.error.as_deref(), Some(ERROR_NOT_FOUND));
+        // Records omit unset properties.
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "did": "did:example:missing",
+                "method": "example",
+                "error": ERROR_NOT_FOUND,
+            })
+        );
+    }
+}
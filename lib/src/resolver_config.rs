@@ -0,0 +1,416 @@
+//! Resolver configuration files, for federating DID resolution across universal resolver
+//! endpoints.
+//!
+//! A configuration lists DID resolver HTTP(S) endpoints by DID method, in failover order. DIDs of
+//! a listed method are resolved with its endpoints instead of the built-in DID methods; the `*`
+//! endpoints resolve DIDs of other methods that are not built in, or, with `overrideBuiltIn`, of
+//! all other methods. For example:
+//!
+//! ```json
+//! {
+//!   "healthCheckInterval": 60,
+//!   "methods": {
+//!     "ion": [
+//!       { "url": "https://ion.example.com/1.0/identifiers/", "healthCheckInterval": 10 },
+//!       { "url": "https://dev.uniresolver.io/1.0/identifiers/" }
+//!     ],
+//!     "*": [{ "url": "https://dev.uniresolver.io/1.0/identifiers/" }]
+//!   }
+//! }
+//! ```
+//!
+//! An endpoint that fails to respond, or responds with an error other than `notFound`,
+//! `invalidDid` or `methodNotSupported`, is marked unhealthy, and resolution fails over to the
+//! next endpoint. Unhealthy endpoints are tried after the healthy ones, and are checked again
+//! every health check interval, by [`ResolverFederation::check_health`] or by resolutions.
+//! Changes of health are reported to the [observers](crate::observe) as
+//! [`Event::EndpointHealth`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use ssi::did_resolve::{ERROR_INVALID_DID, ERROR_METHOD_NOT_SUPPORTED, ERROR_NOT_FOUND};
+use thiserror::Error;
+
+use crate::observe::{self, Event};
+use crate::resolver::{Next, ResolverMiddleware};
+use crate::{
+    DIDResolver, Document, DocumentMetadata, HTTPDIDResolver, ResolutionInputMetadata,
+    ResolutionMetadata,
+};
+
+type ResolutionOutput = (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+);
+
+/// Method name of the endpoints for other DID methods.
+pub const OTHER_METHODS: &str = "*";
+/// Default seconds between health checks of an endpoint.
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: u64 = 60;
+
+#[derive(Error, Debug)]
+pub enum ResolverConfigError {
+    #[error("Unable to read resolver configuration: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Invalid resolver configuration: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("No endpoints for DID method {0}")]
+    NoEndpoints(String),
+}
+
+/// A resolver configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ResolverConfig {
+    /// Seconds between health checks of endpoints, unless set for an endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_interval: Option<u64>,
+    /// Resolve DIDs of the built-in DID methods with the `*` endpoints too
+    #[serde(default)]
+    pub override_built_in: bool,
+    /// Endpoints by DID method, in failover order
+    pub methods: BTreeMap<String, Vec<EndpointConfig>>,
+}
+
+/// A DID resolver HTTP(S) endpoint of a resolver configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EndpointConfig {
+    /// Base URL of the endpoint, to which DIDs are appended, e.g.
+    /// `https://dev.uniresolver.io/1.0/identifiers/`
+    pub url: String,
+    /// URL requested to check the health of the endpoint. Default is the endpoint URL. Any
+    /// response other than a server error means the endpoint is healthy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_url: Option<String>,
+    /// Seconds between health checks of the endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_interval: Option<u64>,
+}
+
+/// Health of an endpoint, as exposed in metrics.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointHealth {
+    pub url: String,
+    /// DID methods resolved with the endpoint
+    pub methods: Vec<String>,
+    pub healthy: bool,
+    /// Failed resolutions and health checks since the endpoint was last healthy
+    pub consecutive_failures: u64,
+    /// Seconds since the endpoint was last checked, by a resolution or health check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_checked: Option<u64>,
+}
+
+#[derive(Debug)]
+struct HealthState {
+    healthy: bool,
+    consecutive_failures: u64,
+    checked: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    health_check_url: String,
+    interval: Duration,
+    resolver: HTTPDIDResolver,
+    health: RwLock<HealthState>,
+}
+
+impl Endpoint {
+    fn is_healthy(&self) -> bool {
+        self.health.read().map(|h| h.healthy).unwrap_or(true)
+    }
+
+    fn is_due(&self) -> bool {
+        match self.health.read() {
+            Ok(health) => health
+                .checked
+                .map_or(true, |t| t.elapsed() >= self.interval),
+            Err(_) => true,
+        }
+    }
+
+    fn record(&self, healthy: bool) {
+        let changed = match self.health.write() {
+            Ok(mut health) => {
+                let changed = health.healthy != healthy;
+                health.healthy = healthy;
+                health.checked = Some(Instant::now());
+                health.consecutive_failures = if healthy {
+                    0
+                } else {
+                    health.consecutive_failures + 1
+                };
+                changed
+            }
+            Err(_) => false,
+        };
+        if changed {
+            observe::emit(|| Event::EndpointHealth {
+                url: &self.url,
+                healthy,
+            });
+        }
+    }
+}
+
+/// Whether a resolution error is the endpoint's answer about the DID, rather than a failure of
+/// the endpoint.
+fn is_definitive(error: Option<&str>) -> bool {
+    matches!(
+        error,
+        None | Some(ERROR_NOT_FOUND) | Some(ERROR_INVALID_DID) | Some(ERROR_METHOD_NOT_SUPPORTED)
+    )
+}
+
+/// Resolver middleware resolving DIDs with the endpoints of a [`ResolverConfig`], with failover
+/// and health checks.
+#[derive(Debug)]
+pub struct ResolverFederation {
+    config: ResolverConfig,
+    methods: HashMap<String, Vec<Arc<Endpoint>>>,
+    endpoints: Vec<Arc<Endpoint>>,
+}
+
+impl ResolverFederation {
+    pub fn new(config: ResolverConfig) -> Result<Self, ResolverConfigError> {
+        let default_interval = config
+            .health_check_interval
+            .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL);
+        let mut endpoints: Vec<Arc<Endpoint>> = Vec::new();
+        let mut methods = HashMap::new();
+        for (method, endpoint_configs) in &config.methods {
+            if endpoint_configs.is_empty() {
+                return Err(ResolverConfigError::NoEndpoints(method.to_string()));
+            }
+            let mut method_endpoints = Vec::new();
+            for endpoint_config in endpoint_configs {
+                // An endpoint listed for several methods has one health state.
+                let endpoint = match endpoints.iter().find(|e| e.url == endpoint_config.url) {
+                    Some(endpoint) => endpoint.clone(),
+                    None => {
+                        let interval = endpoint_config
+                            .health_check_interval
+                            .unwrap_or(default_interval);
+                        let endpoint = Arc::new(Endpoint {
+                            url: endpoint_config.url.clone(),
+                            health_check_url: endpoint_config
+                                .health_check_url
+                                .clone()
+                                .unwrap_or_else(|| endpoint_config.url.clone()),
+                            interval: Duration::from_secs(interval),
+                            resolver: HTTPDIDResolver::new(&endpoint_config.url),
+                            health: RwLock::new(HealthState {
+                                healthy: true,
+                                consecutive_failures: 0,
+                                checked: None,
+                            }),
+                        });
+                        endpoints.push(endpoint.clone());
+                        endpoint
+                    }
+                };
+                method_endpoints.push(endpoint);
+            }
+            methods.insert(method.to_string(), method_endpoints);
+        }
+        Ok(Self {
+            config,
+            methods,
+            endpoints,
+        })
+    }
+
+    /// Load a resolver configuration file.
+    pub fn from_file(path: &str) -> Result<Arc<Self>, ResolverConfigError> {
+        let file = std::fs::File::open(Path::new(path))?;
+        let config = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Ok(Arc::new(Self::new(config)?))
+    }
+
+    pub fn config(&self) -> &ResolverConfig {
+        &self.config
+    }
+
+    /// Shortest health check interval of the endpoints.
+    pub fn health_check_interval(&self) -> Duration {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.interval)
+            .min()
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_HEALTH_CHECK_INTERVAL))
+    }
+
+    /// Health of each endpoint.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let methods = self
+                    .methods
+                    .iter()
+                    .filter(|(_, endpoints)| endpoints.iter().any(|e| Arc::ptr_eq(e, endpoint)))
+                    .map(|(method, _)| method.to_string());
+                let mut methods: Vec<String> = methods.collect();
+                methods.sort();
+                let (healthy, consecutive_failures, last_checked) = match endpoint.health.read() {
+                    Ok(health) => (
+                        health.healthy,
+                        health.consecutive_failures,
+                        health.checked.map(|t| t.elapsed().as_secs()),
+                    ),
+                    Err(_) => (false, 0, None),
+                };
+                EndpointHealth {
+                    url: endpoint.url.clone(),
+                    methods,
+                    healthy,
+                    consecutive_failures,
+                    last_checked,
+                }
+            })
+            .collect()
+    }
+
+    /// Check the health of the endpoints due for a check.
+    pub async fn check_health(&self) {
+        let client = reqwest::Client::new();
+        for endpoint in self.endpoints.iter().filter(|endpoint| endpoint.is_due()) {
+            let healthy = match client.get(&endpoint.health_check_url).send().await {
+                Ok(resp) => !resp.status().is_server_error(),
+                Err(_) => false,
+            };
+            endpoint.record(healthy);
+        }
+    }
+
+    /// Endpoints of a method in the order to try them: healthy endpoints, then unhealthy
+    /// endpoints due for a check, then the other unhealthy endpoints.
+    fn candidates(endpoints: &[Arc<Endpoint>]) -> Vec<&Arc<Endpoint>> {
+        let mut candidates: Vec<&Arc<Endpoint>> = endpoints.iter().collect();
+        candidates.sort_by_key(
+            |endpoint| match (endpoint.is_healthy(), endpoint.is_due()) {
+                (true, _) => 0,
+                (false, true) => 1,
+                (false, false) => 2,
+            },
+        );
+        candidates
+    }
+
+    async fn resolve_with(
+        &self,
+        endpoints: &[Arc<Endpoint>],
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> ResolutionOutput {
+        let mut output = None;
+        for endpoint in Self::candidates(endpoints) {
+            let result = endpoint.resolver.resolve(did, input_metadata).await;
+            let error = result.0.error.as_deref();
+            if is_definitive(error) {
+                endpoint.record(true);
+                if error != Some(ERROR_METHOD_NOT_SUPPORTED) {
+                    return result;
+                }
+            } else {
+                endpoint.record(false);
+            }
+            output = Some(result);
+        }
+        output.unwrap_or_else(|| {
+            let res_meta = ResolutionMetadata {
+                error: Some(ERROR_METHOD_NOT_SUPPORTED.to_string()),
+                ..Default::default()
+            };
+            (res_meta, None, None)
+        })
+    }
+}
+
+#[async_trait]
+impl ResolverMiddleware for ResolverFederation {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+        next: Next<'_>,
+    ) -> ResolutionOutput {
+        let method = did.split(':').nth(1).unwrap_or_default();
+        if let Some(endpoints) = self.methods.get(method) {
+            return self.resolve_with(endpoints, did, input_metadata).await;
+        }
+        let other = match self.methods.get(OTHER_METHODS) {
+            Some(other) => other,
+            None => return next.resolve(did, input_metadata).await,
+        };
+        if self.config.override_built_in {
+            return self.resolve_with(other, did, input_metadata).await;
+        }
+        let output = next.resolve(did, input_metadata).await;
+        if output.0.error.as_deref() == Some(ERROR_METHOD_NOT_SUPPORTED) {
+            return self.resolve_with(other, did, input_metadata).await;
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn federation() {
+        let config: ResolverConfig = serde_json::from_value(json!({
+            "healthCheckInterval": 30,
+            "methods": {
+                "ion": [
+                    { "url": "https://ion.example.com/1.0/identifiers/", "healthCheckInterval": 0 },
+                    { "url": "https://resolver.example.com/1.0/identifiers/" }
+                ],
+                "*": [{ "url": "https://resolver.example.com/1.0/identifiers/" }]
+            }
+        }))
+        .unwrap();
+        let federation = ResolverFederation::new(config).unwrap();
+        assert_eq!(federation.health_check_interval(), Duration::from_secs(0));
+        let health = federation.health();
+        assert_eq!(health.len(), 2);
+        assert_eq!(
+            health[0].url,
+            "https://resolver.example.com/1.0/identifiers/"
+        );
+        assert_eq!(health[0].methods, vec!["*", "ion"]);
+        assert!(health.iter().all(|h| h.healthy && h.last_checked.is_none()));
+
+        let ion = &federation.methods["ion"];
+        ion[0].record(false);
+        let candidates = ResolverFederation::candidates(ion);
+        assert_eq!(
+            candidates[0].url,
+            "https://resolver.example.com/1.0/identifiers/"
+        );
+        assert!(!federation.health()[1].healthy);
+        assert_eq!(federation.health()[1].consecutive_failures, 1);
+        ion[0].record(true);
+        let candidates = ResolverFederation::candidates(ion);
+        assert_eq!(
+            candidates[0].url,
+            "https://ion.example.com/1.0/identifiers/"
+        );
+
+        let empty = json!({ "methods": { "ion": [] } });
+        assert!(ResolverFederation::new(serde_json::from_value(empty).unwrap()).is_err());
+    }
+}
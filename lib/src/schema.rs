@@ -0,0 +1,378 @@
+//! Validation of credentials against their [`credentialSchema`][credential-schema], for
+//! credentials with one or several subjects.
+//!
+//! A `JsonSchema` (or `JsonSchemaValidator2018`) schema describes the credential. If the
+//! credential has an array of subjects, such as a cohort credential, and the schema describes
+//! `credentialSubject` as a single object, each subject is validated against that object schema,
+//! so that a schema written for one subject also validates credentials with several. A schema
+//! that describes `credentialSubject` as an array validates the array as a whole.
+//!
+//! Only a subset of JSON Schema is implemented: `type`, `const`, `enum`, `required`,
+//! `properties`, `additionalProperties`, `items`, `contains`, `minItems`, `maxItems`,
+//! `minLength`, `maxLength`, `pattern`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `formatMinimum`, `formatMaximum` (comparing dates as strings), `allOf`,
+//! `anyOf`, `oneOf` and `not`, as used by presentation definition filters. Other keywords, such
+//! as `$ref` and `format`, are ignored.
+//!
+//! [credential-schema]: https://www.w3.org/TR/vc-data-model-2.0/#data-schemas
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::integrity::{self, IntegrityError};
+
+/// Types of `credentialSchema` entries that can be checked.
+pub const SCHEMA_TYPES: &[&str] = &["JsonSchema", "JsonSchemaValidator2018"];
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Invalid credentialSchema: {0}")]
+    InvalidCredentialSchema(String),
+    #[error("Unsupported credentialSchema type: {0}")]
+    UnsupportedType(String),
+    #[error("Invalid schema {0}: {1}")]
+    InvalidSchema(String, String),
+    #[error("Schema document not available: {0}")]
+    MissingDocument(String),
+    #[error("Credential does not match schema {0}: {}", .1.join("; "))]
+    Mismatch(String, Vec<String>),
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+}
+
+/// Subjects of a credential: its `credentialSubject` object, or each of the objects in its
+/// `credentialSubject` array.
+pub fn subjects(credential: &Value) -> Vec<&Value> {
+    match credential.get("credentialSubject") {
+        Some(Value::Array(subjects)) => subjects.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(subject) => vec![subject],
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, type_: &str) -> bool {
+    match type_ {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        type_ => type_name(value) == type_,
+    }
+}
+
+/// Whether a schema describes arrays.
+fn is_array_schema(schema: &Value) -> bool {
+    match schema.get("type") {
+        Some(Value::String(type_)) => type_ == "array",
+        Some(Value::Array(types)) => types.iter().any(|type_| type_ == "array"),
+        _ => schema.get("items").is_some(),
+    }
+}
+
+fn check(schema: &Value, instance: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return errors.push(format!("{}: not allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+    let mut error = |message: String| errors.push(format!("{}: {}", path, message));
+    match schema.get("type") {
+        Some(Value::String(type_)) if !has_type(instance, type_) => {
+            return error(format!("expected {}", type_));
+        }
+        Some(Value::Array(types)) => {
+            let types: Vec<&str> = types.iter().filter_map(Value::as_str).collect();
+            if !types.iter().any(|type_| has_type(instance, type_)) {
+                return error(format!("expected {}", types.join(" or ")));
+            }
+        }
+        _ => {}
+    }
+    if let Some(value) = schema.get("const") {
+        if value != instance {
+            error(format!("expected {}", value));
+        }
+    }
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        if !values.contains(instance) {
+            error("not one of the allowed values".to_string());
+        }
+    }
+    let limit = |name: &str| schema.get(name).and_then(Value::as_f64);
+    if let Some(number) = instance.as_f64() {
+        if limit("minimum").map_or(false, |minimum| number < minimum) {
+            error(format!("less than {}", schema["minimum"]));
+        }
+        if limit("maximum").map_or(false, |maximum| number > maximum) {
+            error(format!("greater than {}", schema["maximum"]));
+        }
+        if limit("exclusiveMinimum").map_or(false, |minimum| number <= minimum) {
+            error(format!("not greater than {}", schema["exclusiveMinimum"]));
+        }
+        if limit("exclusiveMaximum").map_or(false, |maximum| number >= maximum) {
+            error(format!("not less than {}", schema["exclusiveMaximum"]));
+        }
+    }
+    if let Some(string) = instance.as_str() {
+        let length = string.chars().count() as f64;
+        if limit("minLength").map_or(false, |min| length < min) {
+            error(format!("shorter than {}", schema["minLength"]));
+        }
+        if limit("maxLength").map_or(false, |max| length > max) {
+            error(format!("longer than {}", schema["maxLength"]));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            match Regex::new(pattern) {
+                Ok(regex) if regex.is_match(string) => {}
+                Ok(_) => error(format!("does not match {}", pattern)),
+                Err(_) => error(format!("invalid pattern {}", pattern)),
+            }
+        }
+        let bound = |name: &str| schema.get(name).and_then(Value::as_str);
+        if bound("formatMinimum").map_or(false, |min| string < min) {
+            error(format!("before {}", schema["formatMinimum"]));
+        }
+        if bound("formatMaximum").map_or(false, |max| string > max) {
+            error(format!("after {}", schema["formatMaximum"]));
+        }
+    }
+    if let Some(items) = instance.as_array() {
+        let length = items.len() as f64;
+        if limit("minItems").map_or(false, |min| length < min) {
+            error(format!("fewer than {} items", schema["minItems"]));
+        }
+        if limit("maxItems").map_or(false, |max| length > max) {
+            error(format!("more than {} items", schema["maxItems"]));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                check(item_schema, item, &format!("{}[{}]", path, i), errors);
+            }
+        }
+        if let Some(contains) = schema.get("contains") {
+            if !items.iter().any(|item| validate(contains, item).is_empty()) {
+                errors.push(format!("{}: no item matches contains", path));
+            }
+        }
+    }
+    if let Some(object) = instance.as_object() {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}: missing property {}", path, name));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let property_path = format!("{}.{}", path, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => check(property_schema, value, &property_path, errors),
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties") {
+                        check(additional, value, &property_path, errors);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            check(schema, instance, path, errors);
+        }
+    }
+    let matching = |schemas: &Vec<Value>| {
+        schemas
+            .iter()
+            .filter(|schema| validate(schema, instance).is_empty())
+            .count()
+    };
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        if matching(schemas) == 0 {
+            errors.push(format!("{}: matches none of anyOf", path));
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+        if matching(schemas) != 1 {
+            errors.push(format!("{}: does not match exactly one of oneOf", path));
+        }
+    }
+    if let Some(not) = schema.get("not") {
+        if validate(not, instance).is_empty() {
+            errors.push(format!("{}: matches not", path));
+        }
+    }
+}
+
+/// Validate a JSON value against a JSON Schema. Returns the errors, each prefixed with the path
+/// of the invalid value, e.g. `$.credentialSubject.name`.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, instance, "$", &mut errors);
+    errors
+}
+
+/// Validate a credential against a schema of credentials, applying the schema of
+/// `credentialSubject` to each subject if the credential has several.
+pub fn validate_credential(schema: &Value, credential: &Value) -> Vec<String> {
+    let subject_schema = match schema.pointer("/properties/credentialSubject") {
+        Some(subject_schema)
+            if credential["credentialSubject"].is_array() && !is_array_schema(subject_schema) =>
+        {
+            subject_schema
+        }
+        _ => return validate(schema, credential),
+    };
+    let mut credential_schema = schema.clone();
+    credential_schema["properties"]["credentialSubject"] = Value::Bool(true);
+    let mut errors = validate(&credential_schema, credential);
+    for (i, subject) in subjects(credential).into_iter().enumerate() {
+        let path = format!("$.credentialSubject[{}]", i);
+        check(subject_schema, subject, &path, &mut errors);
+    }
+    errors
+}
+
+/// Check a credential against each of its `credentialSchema` entries, and return their IDs.
+///
+/// Schema documents are taken from `documents`, by ID. With the `resource-fetch` feature, other
+/// schema documents are fetched; without it, they must be supplied. A `digestSRI` of an entry is
+/// checked against the schema document.
+pub async fn check_schemas(
+    credential: &Value,
+    documents: Option<&HashMap<String, String>>,
+) -> Result<Vec<String>, SchemaError> {
+    let entries = match credential.get("credentialSchema") {
+        Some(Value::Array(entries)) => entries.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(entry) => vec![entry],
+    };
+    let mut checked = Vec::new();
+    for entry in entries {
+        let id = entry
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| SchemaError::InvalidCredentialSchema(entry.to_string()))?;
+        let type_ = entry
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !SCHEMA_TYPES.contains(&type_) {
+            return Err(SchemaError::UnsupportedType(type_.to_string()));
+        }
+        let data = match documents.and_then(|documents| documents.get(id)) {
+            Some(body) => body.as_bytes().to_vec(),
+            #[cfg(feature = "resource-fetch")]
+            None => integrity::fetch(id).await?,
+            #[cfg(not(feature = "resource-fetch"))]
+            None => return Err(SchemaError::MissingDocument(id.to_string())),
+        };
+        if let Some(digest_sri) = entry.get("digestSRI").and_then(Value::as_str) {
+            if !integrity::check_digest_sri(digest_sri, &data)? {
+                return Err(IntegrityError::DigestMismatch(id.to_string()).into());
+            }
+        }
+        let schema: Value = serde_json::from_slice(&data)
+            .map_err(|err| SchemaError::InvalidSchema(id.to_string(), err.to_string()))?;
+        let errors = validate_credential(&schema, credential);
+        if !errors.is_empty() {
+            return Err(SchemaError::Mismatch(id.to_string(), errors));
+        }
+        checked.push(id.to_string());
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validate_subjects() {
+        let schema = json!({
+            "type": "object",
+            "required": ["credentialSubject"],
+            "properties": {
+                "credentialSubject": {
+                    "type": "object",
+                    "required": ["id", "cohort"],
+                    "properties": { "cohort": { "type": "integer", "minimum": 2020 } }
+                }
+            }
+        });
+        let credential = json!({
+            "credentialSubject": [
+                { "id": "did:example:alice", "cohort": 2021 },
+                { "id": "did:example:bob", "cohort": 2019 },
+                { "cohort": 2021 }
+            ]
+        });
+        assert_eq!(subjects(&credential).len(), 3);
+        assert_eq!(
+            validate_credential(&schema, &credential),
+            vec![
+                "$.credentialSubject[1].cohort: less than 2020".to_string(),
+                "$.credentialSubject[2]: missing property id".to_string(),
+            ]
+        );
+        let credential = json!({
+            "credentialSubject": { "id": "did:example:alice", "cohort": 2021 }
+        });
+        assert!(validate_credential(&schema, &credential).is_empty());
+
+        let schema = json!({
+            "properties": { "credentialSubject": { "type": "array", "maxItems": 1 } }
+        });
+        let credential = json!({ "credentialSubject": [{}, {}] });
+        assert_eq!(
+            validate_credential(&schema, &credential),
+            vec!["$.credentialSubject: more than 1 items".to_string()]
+        );
+
+        let credential = json!({
+            "credentialSchema": { "id": "https://example.org/schema.json", "type": "JsonSchema" },
+            "credentialSubject": [{ "id": "did:example:alice" }]
+        });
+        let mut documents = HashMap::new();
+        documents.insert(
+            "https://example.org/schema.json".to_string(),
+            json!({ "properties": { "credentialSubject": { "required": ["name"] } } }).to_string(),
+        );
+        let rt = crate::runtime::get().unwrap();
+        let err = rt
+            .block_on(check_schemas(&credential, Some(&documents)))
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::Mismatch(_, ref errors) if errors.len() == 1));
+    }
+
+    #[test]
+    fn validate_filter_keywords() {
+        let types = json!(["VerifiableCredential", "UniversityDegreeCredential"]);
+        let contains = |type_: &str| json!({ "type": "array", "contains": { "const": type_ } });
+        assert!(validate(&contains("UniversityDegreeCredential"), &types).is_empty());
+        assert!(!validate(&contains("DriversLicense"), &types).is_empty());
+        let pattern = json!({ "type": "string", "pattern": "^did:example:" });
+        assert!(validate(&pattern, &json!("did:example:alice")).is_empty());
+        assert!(!validate(&pattern, &json!("did:key:z6Mk")).is_empty());
+        let date = json!({ "type": "string", "format": "date", "formatMaximum": "2005-01-01" });
+        assert!(validate(&date, &json!("1999-12-31")).is_empty());
+        assert!(!validate(&date, &json!("2010-06-01")).is_empty());
+        let range = json!({ "exclusiveMinimum": 18, "not": { "const": 21 } });
+        assert!(validate(&range, &json!(19)).is_empty());
+        assert!(!validate(&range, &json!(18)).is_empty());
+        assert!(!validate(&range, &json!(21)).is_empty());
+    }
+}
@@ -0,0 +1,519 @@
+//! Securing Verifiable Credentials using JOSE and COSE.
+//!
+//! Unlike the JWT encoding of the VC Data Model 1.1 ([`ProofFormat::JWT`]), which maps the
+//! credential to JWT claims under `vc`, the credential or presentation itself is the payload:
+//!
+//! - [`ProofFormat::VcJose`]: a compact JWS with `typ` `vc+jwt` (`vp+jwt` for presentations) and
+//!   `cty` `vc` (`vp`);
+//! - [`ProofFormat::VcCose`]: a tagged COSE_Sign1 structure with `typ` `application/vc+cose`
+//!   (`application/vp+cose`) and content type `application/vc` (`application/vp`), encoded in
+//!   base64url to be carried as a string.
+//!
+//! The `kid` header must be a DID URL of the issuer (or holder), or a fragment relative to it.
+//! Media types of earlier drafts of the specification (`vc+ld+jwt`, `vc+ld+json`, ...) are
+//! accepted when verifying.
+//!
+//! <https://www.w3.org/TR/vc-jose-cose/>
+
+use std::collections::BTreeMap;
+
+use serde_cbor::Value as CborValue;
+use serde_json::{json, Value};
+use ssi::jwk::Algorithm;
+use thiserror::Error;
+
+use crate::key_cache;
+use crate::signer::{self, Signer, SignerError};
+use crate::{
+    DIDResolver, LinkedDataProofOptions, ProofFormat, VerifiableCredential, VerifiablePresentation,
+    JWK,
+};
+
+pub const TYP_VC_JWT: &str = "vc+jwt";
+pub const TYP_VP_JWT: &str = "vp+jwt";
+pub const CTY_VC: &str = "vc";
+pub const CTY_VP: &str = "vp";
+pub const TYP_VC_COSE: &str = "application/vc+cose";
+pub const TYP_VP_COSE: &str = "application/vp+cose";
+pub const CONTENT_TYPE_VC: &str = "application/vc";
+pub const CONTENT_TYPE_VP: &str = "application/vp";
+
+/// CBOR tag of COSE_Sign1 (RFC 9052), as its single-byte encoding
+const COSE_SIGN1_TAG: u8 = 0xd2;
+
+// COSE header labels (RFC 9052)
+const HEADER_ALG: i128 = 1;
+const HEADER_CONTENT_TYPE: i128 = 3;
+const HEADER_KID: i128 = 4;
+const HEADER_TYP: i128 = 16;
+
+#[derive(Error, Debug)]
+pub enum SecuringError {
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("CBOR: {0}")]
+    CBOR(#[from] serde_cbor::Error),
+    #[error("Malformed {0}")]
+    Malformed(&'static str),
+    #[error("Unexpected media type: {0}")]
+    UnexpectedMediaType(String),
+    #[error("Algorithm {0:?} is not supported for COSE")]
+    UnsupportedCoseAlgorithm(Algorithm),
+    #[error("Unsupported COSE algorithm: {0}")]
+    UnknownCoseAlgorithm(i128),
+    #[error("Proof format {0} is not a JOSE or COSE securing mechanism")]
+    UnsupportedFormat(ProofFormat),
+    #[error("Missing key ID")]
+    MissingKeyId,
+    #[error("Key ID {0} is not a verification method of {1}")]
+    KeyIdMismatch(String, String),
+    #[error("Unable to resolve key: {0}")]
+    Resolution(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+}
+
+/// What is secured: a credential or a presentation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentKind {
+    Credential,
+    Presentation,
+}
+
+impl DocumentKind {
+    fn jose_typ(self) -> &'static str {
+        match self {
+            Self::Credential => TYP_VC_JWT,
+            Self::Presentation => TYP_VP_JWT,
+        }
+    }
+
+    fn jose_cty(self) -> &'static str {
+        match self {
+            Self::Credential => CTY_VC,
+            Self::Presentation => CTY_VP,
+        }
+    }
+
+    fn cose_typ(self) -> &'static str {
+        match self {
+            Self::Credential => TYP_VC_COSE,
+            Self::Presentation => TYP_VP_COSE,
+        }
+    }
+
+    fn cose_content_type(self) -> &'static str {
+        match self {
+            Self::Credential => CONTENT_TYPE_VC,
+            Self::Presentation => CONTENT_TYPE_VP,
+        }
+    }
+
+    /// Accepted `typ` values, including those of earlier drafts.
+    fn accepts_typ(self, typ: &str) -> bool {
+        let accepted: &[&str] = match self {
+            Self::Credential => &[
+                TYP_VC_JWT,
+                "vc+ld+jwt",
+                TYP_VC_COSE,
+                "application/vc+ld+cose",
+            ],
+            Self::Presentation => &[
+                TYP_VP_JWT,
+                "vp+ld+jwt",
+                TYP_VP_COSE,
+                "application/vp+ld+cose",
+            ],
+        };
+        accepted.contains(&typ)
+    }
+
+    /// Accepted `cty` values, including those of earlier drafts.
+    fn accepts_content_type(self, cty: &str) -> bool {
+        let accepted: &[&str] = match self {
+            Self::Credential => &[
+                CTY_VC,
+                CONTENT_TYPE_VC,
+                "vc+ld+json",
+                "credential+ld+json",
+                "application/vc+ld+json",
+            ],
+            Self::Presentation => &[
+                CTY_VP,
+                CONTENT_TYPE_VP,
+                "vp+ld+json",
+                "presentation+ld+json",
+                "application/vp+ld+json",
+            ],
+        };
+        accepted.contains(&cty)
+    }
+}
+
+fn cose_algorithm(algorithm: Algorithm) -> Result<i128, SecuringError> {
+    // https://www.iana.org/assignments/cose/cose.xhtml#algorithms
+    match algorithm {
+        Algorithm::EdDSA => Ok(-8),
+        Algorithm::ES256 => Ok(-7),
+        Algorithm::ES256K => Ok(-47),
+        Algorithm::RS256 => Ok(-257),
+        algorithm => Err(SecuringError::UnsupportedCoseAlgorithm(algorithm)),
+    }
+}
+
+fn jose_algorithm(cose_algorithm: i128) -> Result<Algorithm, SecuringError> {
+    match cose_algorithm {
+        -8 => Ok(Algorithm::EdDSA),
+        -7 => Ok(Algorithm::ES256),
+        -47 => Ok(Algorithm::ES256K),
+        -257 => Ok(Algorithm::RS256),
+        alg => Err(SecuringError::UnknownCoseAlgorithm(alg)),
+    }
+}
+
+fn b64(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// COSE Sig_structure of a COSE_Sign1 without external AAD.
+fn cose_signing_input(protected: &[u8], payload: &[u8]) -> Result<Vec<u8>, SecuringError> {
+    Ok(serde_cbor::to_vec(&CborValue::Array(vec![
+        CborValue::Text("Signature1".to_string()),
+        CborValue::Bytes(protected.to_vec()),
+        CborValue::Bytes(Vec::new()),
+        CborValue::Bytes(payload.to_vec()),
+    ]))?)
+}
+
+/// Secure a credential or presentation document, in the JSON form to use as payload. The `kid`
+/// is set from the verification method (or the key ID of the public key). For presentations, the
+/// challenge and domain options are added as `nonce` and `aud` properties.
+pub async fn secure(
+    document: &Value,
+    kind: DocumentKind,
+    proof_format: &ProofFormat,
+    options: &LinkedDataProofOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    let algorithm = signer.algorithm().ok_or(SignerError::MissingAlgorithm)?;
+    let key_id = match options.verification_method {
+        Some(ref verification_method) => Some(verification_method.to_string()),
+        None => signer.public_jwk().key_id,
+    };
+    let mut document = document.clone();
+    if let (DocumentKind::Presentation, Value::Object(object)) = (kind, &mut document) {
+        if let Some(ref domain) = options.domain {
+            object.insert("aud".to_string(), Value::String(domain.to_string()));
+        }
+        if let Some(ref challenge) = options.challenge {
+            object.insert("nonce".to_string(), Value::String(challenge.to_string()));
+        }
+    }
+    let payload = serde_json::to_vec(&document)?;
+    match proof_format {
+        ProofFormat::VcJose => {
+            let mut header = json!({
+                "alg": algorithm,
+                "typ": kind.jose_typ(),
+                "cty": kind.jose_cty(),
+            });
+            if let Some(key_id) = key_id {
+                header["kid"] = Value::String(key_id);
+            }
+            let signing_input = [b64(&serde_json::to_vec(&header)?), b64(&payload)].join(".");
+            let sig = signer::sign(signer, signing_input.as_bytes(), algorithm).await?;
+            Ok([signing_input, b64(&sig)].join("."))
+        }
+        ProofFormat::VcCose => {
+            let mut header = BTreeMap::new();
+            header.insert(
+                CborValue::Integer(HEADER_ALG),
+                CborValue::Integer(cose_algorithm(algorithm)?),
+            );
+            header.insert(
+                CborValue::Integer(HEADER_CONTENT_TYPE),
+                CborValue::Text(kind.cose_content_type().to_string()),
+            );
+            if let Some(key_id) = key_id {
+                header.insert(
+                    CborValue::Integer(HEADER_KID),
+                    CborValue::Bytes(key_id.into_bytes()),
+                );
+            }
+            header.insert(
+                CborValue::Integer(HEADER_TYP),
+                CborValue::Text(kind.cose_typ().to_string()),
+            );
+            let protected =
+                serde_cbor::to_vec(&CborValue::Map(header)).map_err(SecuringError::from)?;
+            let signing_input = cose_signing_input(&protected, &payload)?;
+            let sig = signer::sign(signer, &signing_input, algorithm).await?;
+            let sign1 = serde_cbor::to_vec(&CborValue::Array(vec![
+                CborValue::Bytes(protected),
+                CborValue::Map(BTreeMap::new()),
+                CborValue::Bytes(payload),
+                CborValue::Bytes(sig),
+            ]))
+            .map_err(SecuringError::from)?;
+            let mut tagged = vec![COSE_SIGN1_TAG];
+            tagged.extend(sign1);
+            Ok(b64(&tagged))
+        }
+        proof_format => Err(SecuringError::UnsupportedFormat(proof_format.clone()).into()),
+    }
+}
+
+/// Secure a credential with VC-JOSE or VC-COSE.
+pub async fn secure_credential(
+    credential: &VerifiableCredential,
+    proof_format: &ProofFormat,
+    options: &LinkedDataProofOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    credential.validate_unsigned()?;
+    let document = serde_json::to_value(credential)?;
+    secure(
+        &document,
+        DocumentKind::Credential,
+        proof_format,
+        options,
+        signer,
+    )
+    .await
+}
+
+/// Secure a presentation with VC-JOSE or VC-COSE.
+pub async fn secure_presentation(
+    presentation: &VerifiablePresentation,
+    proof_format: &ProofFormat,
+    options: &LinkedDataProofOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    presentation.validate_unsigned()?;
+    let document = serde_json::to_value(presentation)?;
+    secure(
+        &document,
+        DocumentKind::Presentation,
+        proof_format,
+        options,
+        signer,
+    )
+    .await
+}
+
+/// Decoded, not yet verified, VC-JOSE or VC-COSE secured document.
+#[derive(Debug, Clone)]
+pub struct Secured {
+    /// [`ProofFormat::VcJose`] or [`ProofFormat::VcCose`]
+    pub proof_format: ProofFormat,
+    pub algorithm: Algorithm,
+    pub key_id: Option<String>,
+    pub typ: Option<String>,
+    pub content_type: Option<String>,
+    /// The credential or presentation
+    pub payload: Value,
+    signing_input: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Secured {
+    /// Decode a compact JWS (VC-JOSE), or a base64url-encoded COSE_Sign1 (VC-COSE).
+    pub fn decode(secured: &str) -> Result<Self, SecuringError> {
+        let secured = secured.trim();
+        if secured.contains('.') {
+            Self::decode_jose(secured)
+        } else {
+            Self::decode_cose(secured)
+        }
+    }
+
+    fn decode_jose(jws: &str) -> Result<Self, SecuringError> {
+        let mut parts = jws.split('.');
+        let (header_b64, payload_b64, sig_b64) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(payload), Some(sig)) if parts.next().is_none() => {
+                (header, payload, sig)
+            }
+            _ => return Err(SecuringError::Malformed("JWS")),
+        };
+        let decode = |part: &str| base64::decode_config(part, base64::URL_SAFE_NO_PAD);
+        let header: Value = serde_json::from_slice(&decode(header_b64)?)?;
+        let string = |name: &str| header.get(name).and_then(Value::as_str).map(str::to_string);
+        let algorithm = serde_json::from_value(header.get("alg").cloned().unwrap_or_default())?;
+        Ok(Self {
+            proof_format: ProofFormat::VcJose,
+            algorithm,
+            key_id: string("kid"),
+            typ: string("typ"),
+            content_type: string("cty"),
+            payload: serde_json::from_slice(&decode(payload_b64)?)?,
+            signing_input: [header_b64, payload_b64].join(".").into_bytes(),
+            signature: decode(sig_b64)?,
+        })
+    }
+
+    fn decode_cose(encoded: &str) -> Result<Self, SecuringError> {
+        let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+        let sign1 = match bytes.split_first() {
+            Some((&COSE_SIGN1_TAG, untagged)) => untagged,
+            _ => &bytes[..],
+        };
+        let (protected, payload, signature) = match serde_cbor::from_slice(sign1)? {
+            CborValue::Array(items) if items.len() == 4 => match &items[..] {
+                [CborValue::Bytes(protected), CborValue::Map(_), CborValue::Bytes(payload), CborValue::Bytes(signature)] => {
+                    (protected.clone(), payload.clone(), signature.clone())
+                }
+                _ => return Err(SecuringError::Malformed("COSE_Sign1")),
+            },
+            _ => return Err(SecuringError::Malformed("COSE_Sign1")),
+        };
+        let header = match serde_cbor::from_slice(&protected)? {
+            CborValue::Map(header) => header,
+            _ => return Err(SecuringError::Malformed("COSE protected header")),
+        };
+        let text = |label: i128| match header.get(&CborValue::Integer(label)) {
+            Some(CborValue::Text(text)) => Some(text.to_string()),
+            _ => None,
+        };
+        let algorithm = match header.get(&CborValue::Integer(HEADER_ALG)) {
+            Some(CborValue::Integer(alg)) => jose_algorithm(*alg)?,
+            _ => return Err(SecuringError::Malformed("COSE protected header")),
+        };
+        let key_id = match header.get(&CborValue::Integer(HEADER_KID)) {
+            Some(CborValue::Bytes(kid)) => String::from_utf8(kid.clone()).ok(),
+            _ => None,
+        };
+        Ok(Self {
+            proof_format: ProofFormat::VcCose,
+            algorithm,
+            key_id,
+            typ: text(HEADER_TYP),
+            content_type: text(HEADER_CONTENT_TYPE),
+            signing_input: cose_signing_input(&protected, &payload)?,
+            payload: serde_json::from_slice(&payload)?,
+            signature,
+        })
+    }
+
+    /// Check the `typ` and content type headers. Both are optional, but must match the kind of
+    /// document if present.
+    pub fn check_media_type(&self, kind: DocumentKind) -> Result<(), SecuringError> {
+        if let Some(ref typ) = self.typ {
+            if !kind.accepts_typ(typ) {
+                return Err(SecuringError::UnexpectedMediaType(typ.to_string()));
+            }
+        }
+        if let Some(ref content_type) = self.content_type {
+            if !kind.accepts_content_type(content_type) {
+                return Err(SecuringError::UnexpectedMediaType(content_type.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the `kid` as a verification method of `controller` (a DID), and verify the
+    /// signature. Returns the absolute verification method ID.
+    pub async fn verify_signature(
+        &self,
+        controller: &str,
+        resolver: &dyn DIDResolver,
+    ) -> Result<String, SecuringError> {
+        let key_id = self.key_id.as_deref().ok_or(SecuringError::MissingKeyId)?;
+        let verification_method = if key_id.starts_with('#') {
+            format!("{}{}", controller, key_id)
+        } else {
+            key_id.to_string()
+        };
+        let did = verification_method.split('#').next().unwrap_or_default();
+        if did != controller {
+            return Err(SecuringError::KeyIdMismatch(
+                verification_method,
+                controller.to_string(),
+            ));
+        }
+        let key: JWK = key_cache::resolve_key(&verification_method, resolver)
+            .await
+            .map_err(|e| SecuringError::Resolution(e.to_string()))?;
+        ssi::jws::verify_bytes(self.algorithm, &self.signing_input, &key, &self.signature)
+            .map_err(|e| SecuringError::InvalidSignature(e.to_string()))?;
+        Ok(verification_method)
+    }
+}
+
+/// Whether a string is a VC-JOSE or VC-COSE secured document rather than a VC-JWT: a JWS with
+/// a VC-JOSE `typ`, or a COSE_Sign1.
+pub fn is_secured(secured: &str) -> bool {
+    match Secured::decode(secured) {
+        Ok(secured) => match (&secured.proof_format, secured.typ) {
+            (ProofFormat::VcCose, _) => true,
+            (_, Some(typ)) => {
+                DocumentKind::Credential.accepts_typ(&typ)
+                    || DocumentKind::Presentation.accepts_typ(&typ)
+            }
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Payload of a VC-JOSE or VC-COSE secured document, without verifying it.
+pub(crate) fn payload_unverified(secured: &str) -> Option<Value> {
+    if !is_secured(secured) {
+        return None;
+    }
+    Secured::decode(secured).ok().map(|secured| secured.payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secure_roundtrip() {
+        let key = JWK::generate_ed25519().unwrap();
+        let did = "did:example:issuer";
+        let options = LinkedDataProofOptions {
+            verification_method: Some(ssi::vc::URI::String(format!("{}#key-1", did))),
+            ..Default::default()
+        };
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": did,
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": { "id": "did:example:subject" }
+        });
+        let rt = crate::runtime::get().unwrap();
+        for proof_format in &[ProofFormat::VcJose, ProofFormat::VcCose] {
+            let secured = rt
+                .block_on(secure(
+                    &credential,
+                    DocumentKind::Credential,
+                    proof_format,
+                    &options,
+                    &key,
+                ))
+                .unwrap();
+            assert!(is_secured(&secured));
+            let decoded = Secured::decode(&secured).unwrap();
+            assert_eq!(&decoded.proof_format, proof_format);
+            assert_eq!(decoded.payload, credential);
+            assert_eq!(decoded.key_id.as_deref(), Some("did:example:issuer#key-1"));
+            decoded.check_media_type(DocumentKind::Credential).unwrap();
+            assert!(decoded
+                .check_media_type(DocumentKind::Presentation)
+                .is_err());
+            ssi::jws::verify_bytes(
+                decoded.algorithm,
+                &decoded.signing_input,
+                &key.to_public(),
+                &decoded.signature,
+            )
+            .unwrap();
+        }
+        // A VC-JWT is not a VC-JOSE secured credential.
+        let header = b64(br#"{"alg":"EdDSA","typ":"JWT"}"#);
+        assert!(!is_secured(&format!("{}.{}.sig", header, b64(b"{}"))));
+    }
+}
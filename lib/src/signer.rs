@@ -0,0 +1,487 @@
+//! Signing abstraction for issuance.
+//!
+//! A [`Signer`] produces signatures for a public key without exposing the private key, so that
+//! HSM, KMS or remote signing services can be used to issue credentials and presentations. [`JWK`]
+//! implements `Signer` for keys held in memory.
+//!
+//! Linked data proofs of a signer holding its [private key](Signer::jwk) are signed by the proof
+//! suites of `ssi` directly, as without a signer, which supports all of their proof types. Other
+//! signers receive the signing input of the suite: proof types signing bytes, with a JWS or a
+//! multibase `proofValue`, are supported, while proof types signing EIP-712 typed data, Ethereum
+//! personal messages or Micheline (`Eip712Signature2021`, `EthereumPersonalSignature2021`,
+//! `TezosSignature2021`, ...) need the private key.
+//!
+//! Signers that only accept digests, such as many HSMs, or that should not be sent large signing
+//! inputs, can be [prehashed](Signer::prehashed): DIDKit computes the digest for the algorithm
+//! with [`prehash`] and signs it with [`Signer::sign_digest`]. This is supported for ECDSA and
+//! RSASSA-PKCS1-v1_5 algorithms, where the signature over the digest is the signature over the
+//! signing input. Ed25519ph signatures are not EdDSA signatures, so EdDSA can not be prehashed.
+//!
+//! A [`DryRunSigner`] stands in for a signer in a dry run of issuance: the document is prepared
+//! and canonicalized as for signing, but the signature is a placeholder, and the key is not used.
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use ssi::jwk::Algorithm;
+use ssi::ldp::{LinkedDataDocument, LinkedDataProofs, ProofPreparation, SigningInput};
+use thiserror::Error;
+
+use crate::observe::{self, error_message, Event, Timer};
+use crate::{DIDResolver, LinkedDataProofOptions, JWK};
+
+#[derive(Error, Debug)]
+pub enum SignerError {
+    #[error("Unable to sign: {0}")]
+    Sign(String),
+    #[error("Unable to get JWS algorithm")]
+    MissingAlgorithm,
+    #[error("Unsupported signing input format")]
+    UnsupportedSigningInputFormat,
+    #[error("Algorithm {0:?} does not sign deterministically")]
+    Nondeterministic(Algorithm),
+    #[error("Algorithm {0:?} can not be used with a prehashed signer")]
+    UnsupportedPrehash(Algorithm),
+    #[error("SSI: {0}")]
+    SSI(#[from] ssi::error::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Securing: {0}")]
+    Securing(#[from] crate::securing::SecuringError),
+    #[error("JWT claims: {0}")]
+    JWTClaims(#[from] crate::jwt_claims::JWTClaimsError),
+    #[error("Proof suite: {0}")]
+    Suite(#[from] crate::suite::SuiteError),
+    #[error("Content ID: {0}")]
+    ContentId(#[from] crate::content_id::ContentIdError),
+    #[cfg(feature = "format-jwp")]
+    #[error("JWP: {0}")]
+    JWP(#[from] crate::jwp::JWPError),
+}
+
+/// Something that can sign with a private key.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Signer: Send + Sync {
+    /// Public key corresponding to the signing key.
+    fn public_jwk(&self) -> JWK;
+
+    /// JWS algorithm to sign with. Defaults to the algorithm of the public key.
+    fn algorithm(&self) -> Option<Algorithm> {
+        self.public_jwk().get_algorithm()
+    }
+
+    /// Sign the given signing input, returning the raw signature bytes as used in a JWS.
+    async fn sign(
+        &self,
+        signing_input: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError>;
+
+    /// Whether to sign digests of signing inputs with [`Signer::sign_digest`], instead of the
+    /// signing inputs themselves.
+    fn prehashed(&self) -> bool {
+        false
+    }
+
+    /// Sign a digest of the signing input computed with [`prehash`], returning the raw signature
+    /// bytes as used in a JWS. Only called if the signer is [prehashed](Signer::prehashed).
+    async fn sign_digest(
+        &self,
+        _digest: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        Err(SignerError::UnsupportedPrehash(algorithm))
+    }
+
+    /// The private key, for signers holding it in memory, to sign linked data proofs with the
+    /// proof suites directly.
+    fn jwk(&self) -> Option<&JWK> {
+        None
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Signer for JWK {
+    fn public_jwk(&self) -> JWK {
+        self.to_public()
+    }
+
+    fn algorithm(&self) -> Option<Algorithm> {
+        self.get_algorithm()
+    }
+
+    async fn sign(
+        &self,
+        signing_input: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        Ok(ssi::jws::sign_bytes(algorithm, signing_input, self)?)
+    }
+
+    fn jwk(&self) -> Option<&JWK> {
+        Some(self)
+    }
+}
+
+/// Signer calling a synchronous signing function, such as one provided by a host application
+/// across an FFI boundary (e.g. backed by Android Keystore or the iOS Secure Enclave), so that
+/// the private key never has to be passed to DIDKit.
+pub struct FnSigner<F> {
+    public_jwk: JWK,
+    sign: F,
+    prehashed: bool,
+}
+
+impl<F> FnSigner<F>
+where
+    F: Fn(&[u8], Algorithm) -> Result<Vec<u8>, String> + Send + Sync,
+{
+    pub fn new(public_jwk: JWK, sign: F) -> Self {
+        Self {
+            public_jwk,
+            sign,
+            prehashed: false,
+        }
+    }
+
+    /// Signer calling a function with the [digest](prehash) of the signing input instead of the
+    /// signing input.
+    pub fn prehashed(public_jwk: JWK, sign: F) -> Self {
+        Self {
+            public_jwk,
+            sign,
+            prehashed: true,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<F> Signer for FnSigner<F>
+where
+    F: Fn(&[u8], Algorithm) -> Result<Vec<u8>, String> + Send + Sync,
+{
+    fn public_jwk(&self) -> JWK {
+        self.public_jwk.to_public()
+    }
+
+    async fn sign(
+        &self,
+        signing_input: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        if self.prehashed {
+            let digest = prehash(algorithm, signing_input)?;
+            return self.sign_digest(&digest, algorithm).await;
+        }
+        (self.sign)(signing_input, algorithm).map_err(SignerError::Sign)
+    }
+
+    fn prehashed(&self) -> bool {
+        self.prehashed
+    }
+
+    async fn sign_digest(
+        &self,
+        digest: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        if !self.prehashed {
+            return Err(SignerError::UnsupportedPrehash(algorithm));
+        }
+        (self.sign)(digest, algorithm).map_err(SignerError::Sign)
+    }
+}
+
+/// Signer for dry runs, with the public key of another signer, giving [placeholder
+/// signatures](placeholder_signature) without signing.
+#[derive(Debug, Clone)]
+pub struct DryRunSigner {
+    public_jwk: JWK,
+    algorithm: Option<Algorithm>,
+}
+
+impl DryRunSigner {
+    /// Stand in for a signer, which may hold only a public key.
+    pub fn new(signer: &dyn Signer) -> Self {
+        Self {
+            public_jwk: signer.public_jwk(),
+            algorithm: signer.algorithm(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Signer for DryRunSigner {
+    fn public_jwk(&self) -> JWK {
+        self.public_jwk.clone()
+    }
+
+    fn algorithm(&self) -> Option<Algorithm> {
+        self.algorithm
+    }
+
+    async fn sign(
+        &self,
+        _signing_input: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        Ok(placeholder_signature(algorithm))
+    }
+}
+
+/// Placeholder signature of a dry run: zeros, as long as a signature with the algorithm (for RSA,
+/// with a 2048-bit key).
+pub fn placeholder_signature(algorithm: Algorithm) -> Vec<u8> {
+    let len = match algorithm {
+        Algorithm::ES256KR => 65,
+        Algorithm::ES384 => 96,
+        Algorithm::ES512 => 132,
+        Algorithm::RS256 | Algorithm::RS512 => 256,
+        _ => 64,
+    };
+    vec![0; len]
+}
+
+/// Digest of a signing input to be signed by a [prehashed](Signer::prehashed) signer: SHA-256
+/// for ES256, ES256K, ES256KR and RS256, and SHA-512 for RS512.
+pub fn prehash(algorithm: Algorithm, signing_input: &[u8]) -> Result<Vec<u8>, SignerError> {
+    use sha2::{Digest, Sha256, Sha512};
+    match algorithm {
+        Algorithm::ES256 | Algorithm::ES256K | Algorithm::ES256KR | Algorithm::RS256 => {
+            Ok(Sha256::digest(signing_input).to_vec())
+        }
+        Algorithm::RS512 => Ok(Sha512::digest(signing_input).to_vec()),
+        _ => Err(SignerError::UnsupportedPrehash(algorithm)),
+    }
+}
+
+/// Whether signatures with an algorithm depend only on the key and signing input (EdDSA,
+/// RFC 6979 ECDSA and RSASSA-PKCS1-v1_5), so that signing the same input twice gives the same
+/// signature.
+pub fn is_deterministic(algorithm: Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::EdDSA
+            | Algorithm::ES256
+            | Algorithm::ES256K
+            | Algorithm::ES256KR
+            | Algorithm::RS256
+    )
+}
+
+/// Name of a JWS algorithm, e.g. `EdDSA`, for passing to a signing callback.
+pub fn algorithm_name(algorithm: Algorithm) -> String {
+    match serde_json::to_value(algorithm) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Generate a linked data proof over a document, using a signer.
+pub async fn generate_proof(
+    document: &(dyn LinkedDataDocument + Sync),
+    options: &LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    signer: &dyn Signer,
+    extra_proof_properties: Option<Map<String, Value>>,
+) -> Result<ssi::vc::Proof, SignerError> {
+    if let Some(key) = signer.jwk() {
+        let timer = Timer::start();
+        let proof =
+            LinkedDataProofs::sign(document, options, resolver, key, extra_proof_properties).await;
+        if let Some(algorithm) = key.get_algorithm() {
+            let error = error_message(&proof);
+            observe::emit(|| Event::Signed {
+                algorithm,
+                error: error.as_deref(),
+                elapsed: timer.elapsed(),
+            });
+        }
+        return Ok(proof?);
+    }
+    let public_jwk = signer.public_jwk();
+    let timer = Timer::start();
+    let prep = LinkedDataProofs::prepare(
+        document,
+        options,
+        resolver,
+        &public_jwk,
+        extra_proof_properties,
+    )
+    .await;
+    let error = error_message(&prep);
+    observe::emit(|| Event::Canonicalized {
+        cached: false,
+        error: error.as_deref(),
+        elapsed: timer.elapsed(),
+    });
+    complete_proof(prep?, signer).await
+}
+
+/// Whether a prepared linked data proof signs bytes, so that any signer can complete it with
+/// [`complete_proof`].
+pub fn is_bytes_preparation(prep: &ProofPreparation) -> bool {
+    matches!(prep.signing_input, SigningInput::Bytes(_))
+}
+
+/// Sign a prepared linked data proof, using a signer: the signature is base64url-encoded in a
+/// detached JWS for proof types with a JWS header, and otherwise multibase-encoded (base58btc)
+/// as the `proofValue`. Other signing inputs than bytes are not supported (see
+/// [`is_bytes_preparation`]).
+pub async fn complete_proof(
+    prep: ProofPreparation,
+    signer: &dyn Signer,
+) -> Result<ssi::vc::Proof, SignerError> {
+    let signing_input = match prep.signing_input {
+        SigningInput::Bytes(ref bytes) => bytes.0.to_vec(),
+        _ => return Err(SignerError::UnsupportedSigningInputFormat),
+    };
+    let (algorithm, jws) = match prep.jws_header {
+        Some(ref header) => (header.algorithm, true),
+        None => (
+            signer.algorithm().ok_or(SignerError::MissingAlgorithm)?,
+            false,
+        ),
+    };
+    let sig = sign(signer, &signing_input, algorithm).await?;
+    let signature = if jws {
+        base64::encode_config(sig, base64::URL_SAFE_NO_PAD)
+    } else {
+        format!("z{}", bs58::encode(sig).into_string())
+    };
+    Ok(prep.complete(&signature).await?)
+}
+
+/// Sign with a signer, reporting the signature to the [observers](crate::observe).
+pub(crate) async fn sign(
+    signer: &dyn Signer,
+    signing_input: &[u8],
+    algorithm: Algorithm,
+) -> Result<Vec<u8>, SignerError> {
+    let timer = Timer::start();
+    let result = if signer.prehashed() {
+        match prehash(algorithm, signing_input) {
+            Ok(digest) => signer.sign_digest(&digest, algorithm).await,
+            Err(err) => Err(err),
+        }
+    } else {
+        signer.sign(signing_input, algorithm).await
+    };
+    let error = error_message(&result);
+    observe::emit(|| Event::Signed {
+        algorithm,
+        error: error.as_deref(),
+        elapsed: timer.elapsed(),
+    });
+    result
+}
+
+/// Encode and sign JWT claims using a signer. The `kid` header is set from the verification
+/// method (or the key ID of the public key), and the `aud` and `nonce` claims from the domain and
+/// challenge options.
+pub async fn sign_jwt<T: serde::Serialize>(
+    claims: &T,
+    options: &LinkedDataProofOptions,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    sign_jwt_with_header(claims, options, &Map::new(), signer).await
+}
+
+/// Encode and sign JWT claims as with [`sign_jwt`], with additional header parameters, e.g. an
+/// `x5c` certificate chain. The `alg` parameter cannot be overridden.
+pub async fn sign_jwt_with_header<T: serde::Serialize>(
+    claims: &T,
+    options: &LinkedDataProofOptions,
+    header_params: &Map<String, Value>,
+    signer: &dyn Signer,
+) -> Result<String, SignerError> {
+    let algorithm = signer.algorithm().ok_or(SignerError::MissingAlgorithm)?;
+    let mut header = json!({ "alg": algorithm });
+    for (name, value) in header_params {
+        if name != "alg" {
+            header[name] = value.clone();
+        }
+    }
+    let key_id = match options.verification_method {
+        Some(ref verification_method) => Some(verification_method.to_string()),
+        None => signer.public_jwk().key_id,
+    };
+    if let Some(key_id) = key_id {
+        header["kid"] = Value::String(key_id);
+    }
+    let mut claims = serde_json::to_value(claims)?;
+    if let Value::Object(ref mut claims) = claims {
+        if let Some(ref domain) = options.domain {
+            claims.insert("aud".to_string(), Value::String(domain.to_string()));
+        }
+        if let Some(ref challenge) = options.challenge {
+            claims.insert("nonce".to_string(), Value::String(challenge.to_string()));
+        }
+    }
+    let signing_input = [
+        base64::encode_config(serde_json::to_vec(&header)?, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(serde_json::to_vec(&claims)?, base64::URL_SAFE_NO_PAD),
+    ]
+    .join(".");
+    let sig = sign(signer, signing_input.as_bytes(), algorithm).await?;
+    let sig_b64 = base64::encode_config(sig, base64::URL_SAFE_NO_PAD);
+    Ok([signing_input, sig_b64].join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn key(alg: &str) -> JWK {
+        serde_json::from_value(json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "alg": alg,
+            "x": "gJ4VpTNYBfAeMYrOPb-w1xvG4lZSQZW7Av4PE0Kmh_Y",
+            "y": "IjRsneN5Onc26bVgh5b5HsWeAkw_vx0L0GfbQtAuPTk"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn prehashed_signer() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            // Return the digest as the "signature", to see what the signer was given.
+            let signer = FnSigner::prehashed(key("ES256"), |digest, _| Ok(digest.to_vec()));
+            let input = vec![0u8; 1 << 20];
+            let sig = sign(&signer, &input, Algorithm::ES256).await.unwrap();
+            assert_eq!(sig, Sha256::digest(&input).to_vec());
+            assert_eq!(signer.sign(&input, Algorithm::ES256).await.unwrap(), sig);
+
+            let err = sign(&signer, &input, Algorithm::EdDSA).await.unwrap_err();
+            assert!(matches!(
+                err,
+                SignerError::UnsupportedPrehash(Algorithm::EdDSA)
+            ));
+
+            let signer = FnSigner::new(key("ES256"), |input, _| Ok(input.to_vec()));
+            assert_eq!(
+                sign(&signer, b"abc", Algorithm::ES256).await.unwrap(),
+                b"abc"
+            );
+        });
+    }
+
+    #[test]
+    fn dry_run_signer() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let signer = FnSigner::new(key("ES256"), |_, _| Err("Key used".to_string()));
+            let dry_run = DryRunSigner::new(&signer);
+            assert_eq!(dry_run.public_jwk(), signer.public_jwk());
+            assert_eq!(dry_run.algorithm(), Some(Algorithm::ES256));
+            let sig = sign(&dry_run, b"abc", Algorithm::ES256).await.unwrap();
+            assert_eq!(sig, vec![0; 64]);
+        });
+    }
+}
@@ -0,0 +1,1150 @@
+//! Queryable store of issued and received credentials, in SQLite or PostgreSQL.
+//!
+//! With the `store` feature, and `store-sqlite` and/or `store-postgres` for the databases to
+//! support. [`CredentialStore::connect`] takes a database URL, e.g. `sqlite://wallet.db`,
+//! `sqlite::memory:` or `postgres://user@host/db`, and creates the tables if needed.
+//!
+//! Credentials are indexed by type, issuer, subject, status and direction (issued or received).
+//! Credentials are stored as they are: encrypt the database, or use [`crate::wallet`], if they
+//! must be encrypted at rest.
+//!
+//! Issued credentials can also be tracked through their lifecycle, as [`CredentialState`]s:
+//! pending (stored before signing), issued, suspended and revoked. [`CredentialStore::transition`]
+//! changes the state of a credential, recording the transition in an audit log. A credential can
+//! be assigned an index in a status list, whose bits [`CredentialStore::status_list_indexes`]
+//! gives from the states of the credentials and the [`StatusPurpose`] of the list.
+//!
+//! Credentials are also indexed by their [content hash](crate::content_id): storing a credential
+//! with the content of one already stored in the same direction returns the ID of the stored
+//! one, so that re-issuing or re-receiving a credential does not duplicate it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "store-postgres")]
+use sqlx::any::AnyKind;
+use sqlx::any::{Any, AnyPool, AnyPoolOptions, AnyRow};
+use sqlx::{Row, Transaction};
+use thiserror::Error;
+
+use crate::content_id;
+use crate::hex;
+
+/// Status of newly stored credentials
+pub const STATUS_ACTIVE: &str = "active";
+
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS didkit_credentials (
+        id TEXT PRIMARY KEY,
+        credential_id TEXT,
+        credential TEXT NOT NULL,
+        issuer TEXT,
+        status TEXT NOT NULL,
+        direction TEXT NOT NULL,
+        issuance_date TEXT,
+        expiration_date TEXT,
+        stored TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS didkit_credentials_issuer ON didkit_credentials (issuer)",
+    "CREATE INDEX IF NOT EXISTS didkit_credentials_status ON didkit_credentials (status)",
+    "CREATE TABLE IF NOT EXISTS didkit_credential_terms (
+        credential TEXT NOT NULL,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS didkit_credential_terms_value
+        ON didkit_credential_terms (name, value)",
+    "CREATE INDEX IF NOT EXISTS didkit_credential_terms_credential
+        ON didkit_credential_terms (credential)",
+    "CREATE TABLE IF NOT EXISTS didkit_status_entries (
+        list TEXT NOT NULL,
+        list_index BIGINT NOT NULL,
+        credential TEXT NOT NULL,
+        PRIMARY KEY (list, list_index)
+    )",
+    "CREATE INDEX IF NOT EXISTS didkit_status_entries_credential
+        ON didkit_status_entries (credential)",
+    "CREATE TABLE IF NOT EXISTS didkit_audit_log (
+        id TEXT PRIMARY KEY,
+        credential TEXT NOT NULL,
+        from_state TEXT,
+        to_state TEXT NOT NULL,
+        reason TEXT,
+        time TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS didkit_audit_log_credential ON didkit_audit_log (credential)",
+];
+
+/// Attempts at picking a free random index in a status list.
+const STATUS_INDEX_ATTEMPTS: usize = 32;
+
+const TERM_TYPE: &str = "type";
+const TERM_SUBJECT: &str = "subject";
+const TERM_CONTENT_HASH: &str = "contentHash";
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Invalid direction: {0}")]
+    InvalidDirection(String),
+    #[error("Invalid credential state: {0}")]
+    InvalidState(String),
+    #[error("Credential not found: {0}")]
+    NotFound(String),
+    #[error("Credential cannot go from {0} to {1}")]
+    InvalidTransition(CredentialState, CredentialState),
+    #[error("No free index in status list: {0}")]
+    StatusListFull(String),
+    #[error("Unknown status purpose: {0}")]
+    InvalidStatusPurpose(String),
+}
+
+/// Whether a credential was issued or received by the store's owner.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    Issued,
+    Received,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Issued => write!(f, "issued"),
+            Direction::Received => write!(f, "received"),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = StoreError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "issued" => Ok(Direction::Issued),
+            "received" => Ok(Direction::Received),
+            _ => Err(StoreError::InvalidDirection(s.to_string())),
+        }
+    }
+}
+
+/// Lifecycle state of an issued credential, stored as its status.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialState {
+    /// Stored, but not signed yet
+    Pending,
+    Issued,
+    /// Temporarily not valid
+    Suspended,
+    /// Permanently not valid
+    Revoked,
+}
+
+impl fmt::Display for CredentialState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialState::Pending => write!(f, "pending"),
+            CredentialState::Issued => write!(f, "issued"),
+            CredentialState::Suspended => write!(f, "suspended"),
+            CredentialState::Revoked => write!(f, "revoked"),
+        }
+    }
+}
+
+impl FromStr for CredentialState {
+    type Err = StoreError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(CredentialState::Pending),
+            // Credentials stored without a lifecycle are active.
+            "issued" | STATUS_ACTIVE => Ok(CredentialState::Issued),
+            "suspended" => Ok(CredentialState::Suspended),
+            "revoked" => Ok(CredentialState::Revoked),
+            _ => Err(StoreError::InvalidState(s.to_string())),
+        }
+    }
+}
+
+impl CredentialState {
+    /// Whether a credential may go from this state to another: pending to issued or revoked,
+    /// issued to suspended or revoked, and suspended back to issued, or to revoked.
+    pub fn can_transition(self, to: CredentialState) -> bool {
+        use CredentialState::*;
+        matches!(
+            (self, to),
+            (Pending, Issued)
+                | (Pending, Revoked)
+                | (Issued, Suspended)
+                | (Issued, Revoked)
+                | (Suspended, Issued)
+                | (Suspended, Revoked)
+        )
+    }
+
+    /// Whether the bit of a credential in this state is set in a status list with the given
+    /// purpose.
+    pub fn is_status_set(self, purpose: StatusPurpose) -> bool {
+        match purpose {
+            StatusPurpose::Revocation => self == CredentialState::Revoked,
+            StatusPurpose::Suspension => {
+                matches!(self, CredentialState::Suspended | CredentialState::Revoked)
+            }
+        }
+    }
+}
+
+/// `statusPurpose` of a status list.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum StatusPurpose {
+    /// Bits are set when credentials are revoked, and never cleared.
+    Revocation,
+    /// Bits are set while credentials are suspended, and stay set once they are revoked.
+    Suspension,
+}
+
+impl Default for StatusPurpose {
+    fn default() -> Self {
+        StatusPurpose::Revocation
+    }
+}
+
+impl fmt::Display for StatusPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusPurpose::Revocation => write!(f, "revocation"),
+            StatusPurpose::Suspension => write!(f, "suspension"),
+        }
+    }
+}
+
+impl FromStr for StatusPurpose {
+    type Err = StoreError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "revocation" => Ok(StatusPurpose::Revocation),
+            "suspension" => Ok(StatusPurpose::Suspension),
+            _ => Err(StoreError::InvalidStatusPurpose(s.to_string())),
+        }
+    }
+}
+
+/// Index of a credential in a status list.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEntry {
+    /// URL of the status list credential
+    pub list: String,
+    pub index: u64,
+}
+
+/// Transition of a credential between lifecycle states.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// ID of the credential in the store
+    pub credential: String,
+    /// Previous state, unless the credential was just stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<CredentialState>,
+    pub to: CredentialState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub time: DateTime<Utc>,
+}
+
+/// Stored credential, with its indexed properties.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRecord {
+    /// ID in the store
+    pub id: String,
+    /// Credential, or JWT string
+    pub credential: Value,
+    /// `id` of the credential
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    pub types: Vec<String>,
+    pub subjects: Vec<String>,
+    /// [Content hash](crate::content_id) of the credential, in hex, if it could be computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    pub status: String,
+    pub direction: Direction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuance_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    pub stored: DateTime<Utc>,
+}
+
+/// Criteria of a credential query. Unset criteria match any credential.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialQuery {
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub status: Option<String>,
+    pub direction: Option<Direction>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Indexed properties of a credential in JSON, or of the `vc` claim of a JWT.
+struct Metadata {
+    credential_id: Option<String>,
+    issuer: Option<String>,
+    types: Vec<String>,
+    subjects: Vec<String>,
+    issuance_date: Option<String>,
+    expiration_date: Option<String>,
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(string)) => vec![string.to_string()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|string| string.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn string(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(Value::as_str)
+        .map(|string| string.to_string())
+}
+
+fn timestamp(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(date) => Some(date.to_string()),
+        Value::Number(seconds) => {
+            let datetime = chrono::NaiveDateTime::from_timestamp_opt(seconds.as_i64()?, 0)?;
+            Some(DateTime::<Utc>::from_utc(datetime, Utc).to_rfc3339())
+        }
+        _ => None,
+    }
+}
+
+impl Metadata {
+    fn from_credential(credential: &Value) -> Self {
+        if let Value::String(jwt) = credential {
+            let claims = jwt
+                .split('.')
+                .nth(1)
+                .and_then(|payload| base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok())
+                .and_then(|payload| serde_json::from_slice::<Value>(&payload).ok())
+                .unwrap_or_default();
+            let vc = claims.get("vc").cloned().unwrap_or_default();
+            let mut metadata = Self::from_credential(&vc);
+            metadata.credential_id = metadata.credential_id.or_else(|| string(claims.get("jti")));
+            metadata.issuer = metadata.issuer.or_else(|| string(claims.get("iss")));
+            if metadata.subjects.is_empty() {
+                metadata.subjects = strings(claims.get("sub"));
+            }
+            metadata.issuance_date = metadata
+                .issuance_date
+                .or_else(|| timestamp(claims.get("nbf")));
+            metadata.expiration_date = metadata
+                .expiration_date
+                .or_else(|| timestamp(claims.get("exp")));
+            return metadata;
+        }
+        let issuer = match credential.get("issuer") {
+            Some(Value::Object(issuer)) => string(issuer.get("id")),
+            issuer => string(issuer),
+        };
+        let subjects = match credential.get("credentialSubject") {
+            Some(Value::Array(subjects)) => subjects
+                .iter()
+                .filter_map(|subject| string(subject.get("id")))
+                .collect(),
+            Some(subject) => string(subject.get("id")).into_iter().collect(),
+            None => Vec::new(),
+        };
+        Self {
+            credential_id: string(credential.get("id")),
+            issuer,
+            types: strings(credential.get("type")),
+            subjects,
+            issuance_date: string(credential.get("issuanceDate")),
+            expiration_date: string(credential.get("expirationDate")),
+        }
+    }
+}
+
+pub struct CredentialStore {
+    pool: AnyPool,
+}
+
+impl CredentialStore {
+    /// Connect to a database, and create the store's tables if needed.
+    pub async fn connect(url: &str) -> Result<Self, StoreError> {
+        let mut options = AnyPoolOptions::new();
+        if url.starts_with("sqlite::memory:") {
+            // Each connection to an in-memory database opens a different database.
+            options = options.max_connections(1);
+        }
+        let pool = options.connect(url).await?;
+        for statement in SCHEMA {
+            sqlx::query(*statement).execute(&pool).await?;
+        }
+        Ok(Self { pool })
+    }
+
+    /// `LIMIT` of no limit: `ALL` on PostgreSQL, -1 on SQLite, which has no `ALL`.
+    fn no_limit(&self) -> &'static str {
+        #[cfg(feature = "store-postgres")]
+        if matches!(self.pool.any_kind(), AnyKind::Postgres) {
+            return "ALL";
+        }
+        "-1"
+    }
+
+    /// Store a credential (or JWT string), and return its ID in the store. If a credential with
+    /// the same content hash is stored in the same direction, return its ID instead.
+    pub async fn insert(
+        &self,
+        credential: &Value,
+        direction: Direction,
+    ) -> Result<String, StoreError> {
+        let content_hash = content_id::content_hash_hex(credential).await.ok();
+        let mut tx = self.pool.begin().await?;
+        if let Some(ref content_hash) = content_hash {
+            if let Some(id) = Self::find_content_hash(&mut tx, content_hash, direction).await? {
+                return Ok(id);
+            }
+        }
+        let id = Self::insert_with_status(
+            &mut tx,
+            credential,
+            direction,
+            STATUS_ACTIVE,
+            content_hash.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Find a credential stored in a direction by its content hash, in hex.
+    pub async fn find_by_content_hash(
+        &self,
+        content_hash: &str,
+        direction: Direction,
+    ) -> Result<Option<String>, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let id = Self::find_content_hash(&mut tx, content_hash, direction).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn find_content_hash(
+        tx: &mut Transaction<'_, Any>,
+        content_hash: &str,
+        direction: Direction,
+    ) -> Result<Option<String>, StoreError> {
+        let row = sqlx::query(
+            "SELECT c.id FROM didkit_credentials c
+            JOIN didkit_credential_terms t ON t.credential = c.id
+            WHERE t.name = $1 AND t.value = $2 AND c.direction = $3
+            ORDER BY c.stored, c.id LIMIT 1",
+        )
+        .bind(TERM_CONTENT_HASH)
+        .bind(content_hash)
+        .bind(direction.to_string())
+        .fetch_optional(&mut *tx)
+        .await?;
+        match row {
+            Some(row) => Ok(Some(row.try_get("id")?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the content hash term of a credential.
+    async fn set_content_hash(
+        tx: &mut Transaction<'_, Any>,
+        id: &str,
+        content_hash: Option<&str>,
+    ) -> Result<(), StoreError> {
+        sqlx::query("DELETE FROM didkit_credential_terms WHERE credential = $1 AND name = $2")
+            .bind(id)
+            .bind(TERM_CONTENT_HASH)
+            .execute(&mut *tx)
+            .await?;
+        if let Some(content_hash) = content_hash {
+            sqlx::query(
+                "INSERT INTO didkit_credential_terms (credential, name, value) VALUES ($1, $2, $3)",
+            )
+            .bind(id)
+            .bind(TERM_CONTENT_HASH)
+            .bind(content_hash)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Store an issued credential before signing it, in the pending state, and return its ID in
+    /// the store.
+    pub async fn insert_pending(&self, credential: &Value) -> Result<String, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let pending = CredentialState::Pending.to_string();
+        let id = Self::insert_with_status(&mut tx, credential, Direction::Issued, &pending, None)
+            .await?;
+        Self::audit(&mut tx, &id, None, CredentialState::Pending, None).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn insert_with_status(
+        tx: &mut Transaction<'_, Any>,
+        credential: &Value,
+        direction: Direction,
+        status: &str,
+        content_hash: Option<&str>,
+    ) -> Result<String, StoreError> {
+        let id = hex::random_id();
+        let metadata = Metadata::from_credential(credential);
+        let credential = match credential {
+            Value::String(jwt) => jwt.to_string(),
+            credential => serde_json::to_string(credential)?,
+        };
+        sqlx::query(
+            "INSERT INTO didkit_credentials (id, credential_id, credential, issuer, status,
+                direction, issuance_date, expiration_date, stored)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(&id)
+        .bind(metadata.credential_id)
+        .bind(credential)
+        .bind(metadata.issuer)
+        .bind(status)
+        .bind(direction.to_string())
+        .bind(metadata.issuance_date)
+        .bind(metadata.expiration_date)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        let terms = metadata.types.iter().map(|type_| (TERM_TYPE, type_)).chain(
+            metadata
+                .subjects
+                .iter()
+                .map(|subject| (TERM_SUBJECT, subject)),
+        );
+        for (name, value) in terms {
+            sqlx::query(
+                "INSERT INTO didkit_credential_terms (credential, name, value) VALUES ($1, $2, $3)",
+            )
+            .bind(&id)
+            .bind(name)
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Self::set_content_hash(tx, &id, content_hash).await?;
+        Ok(id)
+    }
+
+    async fn audit(
+        tx: &mut Transaction<'_, Any>,
+        id: &str,
+        from: Option<CredentialState>,
+        to: CredentialState,
+        reason: Option<&str>,
+    ) -> Result<AuditEntry, StoreError> {
+        let entry = AuditEntry {
+            credential: id.to_string(),
+            from,
+            to,
+            reason: reason.map(|reason| reason.to_string()),
+            time: Utc::now(),
+        };
+        sqlx::query(
+            "INSERT INTO didkit_audit_log (id, credential, from_state, to_state, reason, time)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(hex::random_id())
+        .bind(id)
+        .bind(from.map(|from| from.to_string()))
+        .bind(to.to_string())
+        .bind(reason)
+        .bind(entry.time.to_rfc3339_opts(SecondsFormat::Nanos, true))
+        .execute(&mut *tx)
+        .await?;
+        Ok(entry)
+    }
+
+    /// Get the lifecycle state of a credential, in a transaction.
+    async fn state(tx: &mut Transaction<'_, Any>, id: &str) -> Result<CredentialState, StoreError> {
+        let row = sqlx::query("SELECT status FROM didkit_credentials WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| StoreError::NotFound(id.to_string()))?;
+        let status: String = row.try_get("status")?;
+        status.parse()
+    }
+
+    /// Replace a pending credential with the signed credential, and mark it issued.
+    pub async fn complete_issuance(&self, id: &str, credential: &Value) -> Result<(), StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let from = Self::state(&mut tx, id).await?;
+        if from != CredentialState::Pending {
+            return Err(StoreError::InvalidTransition(from, CredentialState::Issued));
+        }
+        let content_hash = content_id::content_hash_hex(credential).await.ok();
+        let metadata = Metadata::from_credential(credential);
+        let credential = match credential {
+            Value::String(jwt) => jwt.to_string(),
+            credential => serde_json::to_string(credential)?,
+        };
+        sqlx::query(
+            "UPDATE didkit_credentials
+            SET credential = $1, credential_id = $2, issuance_date = $3, expiration_date = $4,
+                status = $5
+            WHERE id = $6",
+        )
+        .bind(credential)
+        .bind(metadata.credential_id)
+        .bind(metadata.issuance_date)
+        .bind(metadata.expiration_date)
+        .bind(CredentialState::Issued.to_string())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        Self::set_content_hash(&mut tx, id, content_hash.as_deref()).await?;
+        Self::audit(&mut tx, id, Some(from), CredentialState::Issued, None).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Change the lifecycle state of a credential, and record the transition in the audit log.
+    ///
+    /// A pending credential can only be issued with [`CredentialStore::complete_issuance`].
+    pub async fn transition(
+        &self,
+        id: &str,
+        to: CredentialState,
+        reason: Option<&str>,
+    ) -> Result<AuditEntry, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let from = Self::state(&mut tx, id).await?;
+        // A pending credential is issued by signing it.
+        let issuing = from == CredentialState::Pending && to == CredentialState::Issued;
+        if !from.can_transition(to) || issuing {
+            return Err(StoreError::InvalidTransition(from, to));
+        }
+        sqlx::query("UPDATE didkit_credentials SET status = $1 WHERE id = $2")
+            .bind(to.to_string())
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        let entry = Self::audit(&mut tx, id, Some(from), to, reason).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    /// Get the lifecycle transitions of a credential, oldest first.
+    pub async fn audit_log(&self, id: &str) -> Result<Vec<AuditEntry>, StoreError> {
+        let rows =
+            sqlx::query("SELECT * FROM didkit_audit_log WHERE credential = $1 ORDER BY time, id")
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await?;
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let from: Option<String> = row.try_get("from_state")?;
+            let to: String = row.try_get("to_state")?;
+            let time: String = row.try_get("time")?;
+            entries.push(AuditEntry {
+                credential: row.try_get("credential")?,
+                from: from.map(|from| from.parse()).transpose()?,
+                to: to.parse()?,
+                reason: row.try_get("reason")?,
+                time: DateTime::parse_from_rfc3339(&time)
+                    .map(|time| time.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Assign a credential a random free index in a status list of `size` entries. Indexes are
+    /// never reused, even if the credential is removed.
+    pub async fn assign_status_index(
+        &self,
+        id: &str,
+        list: &str,
+        size: u64,
+    ) -> Result<StatusEntry, StoreError> {
+        if size == 0 {
+            return Err(StoreError::StatusListFull(list.to_string()));
+        }
+        for _ in 0..STATUS_INDEX_ATTEMPTS {
+            let index = rand::thread_rng().next_u64() % size;
+            let result = sqlx::query(
+                "INSERT INTO didkit_status_entries (list, list_index, credential)
+                VALUES ($1, $2, $3)",
+            )
+            .bind(list)
+            .bind(index as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await;
+            match result {
+                Ok(_) => {
+                    return Ok(StatusEntry {
+                        list: list.to_string(),
+                        index,
+                    })
+                }
+                // The index is taken.
+                Err(ref err) if is_unique_violation(err) => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(StoreError::StatusListFull(list.to_string()))
+    }
+
+    /// Get the status list entry of a credential, if it has one.
+    pub async fn status_entry(&self, id: &str) -> Result<Option<StatusEntry>, StoreError> {
+        let row =
+            sqlx::query("SELECT list, list_index FROM didkit_status_entries WHERE credential = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match row {
+            Some(row) => {
+                let index: i64 = row.try_get("list_index")?;
+                Ok(Some(StatusEntry {
+                    list: row.try_get("list")?,
+                    index: index as u64,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the indexes of a status list whose bit is set for its purpose: those of revoked
+    /// credentials, and, for suspension, of suspended credentials, and those of removed
+    /// credentials.
+    pub async fn status_list_indexes(
+        &self,
+        list: &str,
+        purpose: StatusPurpose,
+    ) -> Result<Vec<u64>, StoreError> {
+        let states = match purpose {
+            StatusPurpose::Revocation => "$2",
+            StatusPurpose::Suspension => "$2, $3",
+        };
+        let sql = format!(
+            "SELECT e.list_index FROM didkit_status_entries e
+            LEFT JOIN didkit_credentials c ON c.id = e.credential
+            WHERE e.list = $1 AND (c.status IS NULL OR c.status IN ({}))
+            ORDER BY e.list_index",
+            states
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(list)
+            .bind(CredentialState::Revoked.to_string());
+        if purpose == StatusPurpose::Suspension {
+            query = query.bind(CredentialState::Suspended.to_string());
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+        let mut indexes = Vec::with_capacity(rows.len());
+        for row in rows {
+            let index: i64 = row.try_get("list_index")?;
+            indexes.push(index as u64);
+        }
+        Ok(indexes)
+    }
+
+    async fn record(&self, row: AnyRow) -> Result<CredentialRecord, StoreError> {
+        let id: String = row.try_get("id")?;
+        let credential: String = row.try_get("credential")?;
+        let credential = serde_json::from_str(&credential).unwrap_or(Value::String(credential));
+        let direction: String = row.try_get("direction")?;
+        let stored: String = row.try_get("stored")?;
+        let mut record = CredentialRecord {
+            credential,
+            credential_id: row.try_get("credential_id")?,
+            issuer: row.try_get("issuer")?,
+            types: Vec::new(),
+            subjects: Vec::new(),
+            content_hash: None,
+            status: row.try_get("status")?,
+            direction: direction.parse()?,
+            issuance_date: row.try_get("issuance_date")?,
+            expiration_date: row.try_get("expiration_date")?,
+            stored: DateTime::parse_from_rfc3339(&stored)
+                .map(|stored| stored.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            id,
+        };
+        let terms =
+            sqlx::query("SELECT name, value FROM didkit_credential_terms WHERE credential = $1")
+                .bind(&record.id)
+                .fetch_all(&self.pool)
+                .await?;
+        for term in terms {
+            let name: String = term.try_get("name")?;
+            let value: String = term.try_get("value")?;
+            match &name[..] {
+                TERM_TYPE => record.types.push(value),
+                TERM_SUBJECT => record.subjects.push(value),
+                TERM_CONTENT_HASH => record.content_hash = Some(value),
+                _ => {}
+            }
+        }
+        Ok(record)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<CredentialRecord>, StoreError> {
+        let row = sqlx::query("SELECT * FROM didkit_credentials WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(self.record(row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Find credentials matching all the criteria of a query, most recently stored first.
+    pub async fn query(
+        &self,
+        query: &CredentialQuery,
+    ) -> Result<Vec<CredentialRecord>, StoreError> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let mut condition = |sql: &str, value: String| {
+            values.push(value);
+            conditions.push(sql.replace("$?", &format!("${}", values.len())));
+        };
+        let term_condition = |name: &str| {
+            format!(
+                "EXISTS (SELECT 1 FROM didkit_credential_terms t
+                    WHERE t.credential = didkit_credentials.id AND t.name = '{}' AND t.value = $?)",
+                name
+            )
+        };
+        if let Some(ref type_) = query.type_ {
+            condition(&term_condition(TERM_TYPE), type_.to_string());
+        }
+        if let Some(ref subject) = query.subject {
+            condition(&term_condition(TERM_SUBJECT), subject.to_string());
+        }
+        if let Some(ref issuer) = query.issuer {
+            condition("issuer = $?", issuer.to_string());
+        }
+        if let Some(ref status) = query.status {
+            condition("status = $?", status.to_string());
+        }
+        if let Some(direction) = query.direction {
+            condition("direction = $?", direction.to_string());
+        }
+        let mut sql = "SELECT * FROM didkit_credentials".to_string();
+        if !conditions.is_empty() {
+            sql += " WHERE ";
+            sql += &conditions.join(" AND ");
+        }
+        sql += " ORDER BY stored DESC, id";
+        match (query.limit, query.offset) {
+            (Some(limit), _) => sql += &format!(" LIMIT {}", limit),
+            // SQLite has no OFFSET without LIMIT.
+            (None, Some(_)) => sql += &format!(" LIMIT {}", self.no_limit()),
+            (None, None) => {}
+        }
+        if let Some(offset) = query.offset {
+            sql += &format!(" OFFSET {}", offset);
+        }
+        let mut statement = sqlx::query(&sql);
+        for value in &values {
+            statement = statement.bind(value);
+        }
+        let rows = statement.fetch_all(&self.pool).await?;
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            records.push(self.record(row).await?);
+        }
+        Ok(records)
+    }
+
+    /// Set the status of a credential, e.g. `revoked`. Returns whether it is in the store.
+    pub async fn set_status(&self, id: &str, status: &str) -> Result<bool, StoreError> {
+        let result = sqlx::query("UPDATE didkit_credentials SET status = $1 WHERE id = $2")
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a credential. Returns whether it was in the store.
+    pub async fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM didkit_credential_terms WHERE credential = $1")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM didkit_credentials WHERE id = $1")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Whether an error is the violation of a unique or primary key constraint, by its PostgreSQL
+/// (`23505`) or SQLite extended result code (`SQLITE_CONSTRAINT_PRIMARYKEY`,
+/// `SQLITE_CONSTRAINT_UNIQUE`).
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Database(err) => {
+            matches!(
+                err.code().as_deref(),
+                Some("23505") | Some("1555") | Some("2067")
+            )
+        }
+        _ => false,
+    }
+}
+
+#[cfg(all(test, feature = "store-sqlite"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn query_credentials() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let store = CredentialStore::connect("sqlite::memory:").await.unwrap();
+            let degree = json!({
+                "type": ["VerifiableCredential", "UniversityDegreeCredential"],
+                "issuer": { "id": "did:example:university" },
+                "issuanceDate": "2021-01-01T00:00:00Z",
+                "credentialSubject": { "id": "did:example:alice" }
+            });
+            let id = store.insert(&degree, Direction::Received).await.unwrap();
+            let other = json!({
+                "type": "VerifiableCredential",
+                "issuer": "did:example:issuer",
+                "credentialSubject": [{ "id": "did:example:alice" }, { "id": "did:example:bob" }]
+            });
+            store.insert(&other, Direction::Issued).await.unwrap();
+
+            let query = CredentialQuery {
+                type_: Some("UniversityDegreeCredential".to_string()),
+                ..Default::default()
+            };
+            let records = store.query(&query).await.unwrap();
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].id, id);
+            assert_eq!(records[0].issuer.as_deref(), Some("did:example:university"));
+            assert_eq!(records[0].credential, degree);
+
+            let query = CredentialQuery {
+                subject: Some("did:example:alice".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(store.query(&query).await.unwrap().len(), 2);
+
+            // Pages, with or without a limit
+            let query = CredentialQuery {
+                limit: Some(1),
+                ..Default::default()
+            };
+            assert_eq!(store.query(&query).await.unwrap().len(), 1);
+            let query = CredentialQuery {
+                offset: Some(1),
+                ..Default::default()
+            };
+            let records = store.query(&query).await.unwrap();
+            assert_eq!(records.len(), 1);
+            let query = CredentialQuery {
+                limit: Some(1),
+                offset: Some(1),
+                ..Default::default()
+            };
+            assert_eq!(store.query(&query).await.unwrap()[0].id, records[0].id);
+
+            assert!(store.set_status(&id, "revoked").await.unwrap());
+            let query = CredentialQuery {
+                status: Some(STATUS_ACTIVE.to_string()),
+                ..Default::default()
+            };
+            let records = store.query(&query).await.unwrap();
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].direction, Direction::Issued);
+
+            assert!(store.remove(&id).await.unwrap());
+            assert!(store.get(&id).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn credential_lifecycle() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let store = CredentialStore::connect("sqlite::memory:").await.unwrap();
+            let list = "https://issuer.example/status/1";
+            let credential = json!({
+                "type": "VerifiableCredential",
+                "issuer": "did:example:issuer",
+                "credentialSubject": { "id": "did:example:alice" }
+            });
+            let id = store.insert_pending(&credential).await.unwrap();
+            let entry = store.assign_status_index(&id, list, 8).await.unwrap();
+            assert_eq!(store.status_entry(&id).await.unwrap(), Some(entry.clone()));
+            // Taken indexes are retried, until the list is found full.
+            let other = store.insert_pending(&credential).await.unwrap();
+            let full = "https://issuer.example/status/full";
+            let taken = store.assign_status_index(&other, full, 1).await.unwrap();
+            assert_eq!(taken.index, 0);
+            let err = store.assign_status_index(&id, full, 1).await.unwrap_err();
+            assert!(matches!(err, StoreError::StatusListFull(_)));
+            let err = store.assign_status_index(&id, full, 0).await.unwrap_err();
+            assert!(matches!(err, StoreError::StatusListFull(_)));
+            let err = store
+                .transition(&id, CredentialState::Issued, None)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, StoreError::InvalidTransition(..)));
+
+            let mut signed = credential.clone();
+            signed["id"] = json!("urn:example:credential");
+            store.complete_issuance(&id, &signed).await.unwrap();
+            let record = store.get(&id).await.unwrap().unwrap();
+            assert_eq!(record.status, "issued");
+            assert_eq!(record.credential, signed);
+            for purpose in &[StatusPurpose::Revocation, StatusPurpose::Suspension] {
+                assert!(store
+                    .status_list_indexes(list, *purpose)
+                    .await
+                    .unwrap()
+                    .is_empty());
+            }
+
+            let reason = Some("investigation");
+            store
+                .transition(&id, CredentialState::Suspended, reason)
+                .await
+                .unwrap();
+            assert_eq!(
+                store
+                    .status_list_indexes(list, StatusPurpose::Suspension)
+                    .await
+                    .unwrap(),
+                vec![entry.index]
+            );
+            // Suspension does not set revocation bits, which are never cleared.
+            assert!(store
+                .status_list_indexes(list, StatusPurpose::Revocation)
+                .await
+                .unwrap()
+                .is_empty());
+            store
+                .transition(&id, CredentialState::Issued, None)
+                .await
+                .unwrap();
+            assert!(store
+                .status_list_indexes(list, StatusPurpose::Suspension)
+                .await
+                .unwrap()
+                .is_empty());
+            store
+                .transition(&id, CredentialState::Revoked, None)
+                .await
+                .unwrap();
+            for purpose in &[StatusPurpose::Revocation, StatusPurpose::Suspension] {
+                assert_eq!(
+                    store.status_list_indexes(list, *purpose).await.unwrap(),
+                    vec![entry.index]
+                );
+            }
+            assert!(store
+                .transition(&id, CredentialState::Issued, None)
+                .await
+                .is_err());
+
+            let log = store.audit_log(&id).await.unwrap();
+            let states: Vec<CredentialState> = log.iter().map(|entry| entry.to).collect();
+            use CredentialState::*;
+            assert_eq!(states, vec![Pending, Issued, Suspended, Issued, Revoked]);
+            assert_eq!(log[2].reason.as_deref(), reason);
+            assert_eq!(log[0].from, None);
+
+            // Removed credentials stay set in the status list.
+            assert!(store.remove(&id).await.unwrap());
+            assert_eq!(
+                store
+                    .status_list_indexes(list, StatusPurpose::Revocation)
+                    .await
+                    .unwrap(),
+                vec![entry.index]
+            );
+        });
+    }
+
+    #[test]
+    fn deduplicate_by_content_hash() {
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(async {
+            let store = CredentialStore::connect("sqlite::memory:").await.unwrap();
+            let credential = json!({
+                "@context": "https://www.w3.org/2018/credentials/v1",
+                "type": "VerifiableCredential",
+                "issuer": "did:example:issuer",
+                "issuanceDate": "2021-01-01T00:00:00Z",
+                "credentialSubject": { "id": "did:example:alice" }
+            });
+            let id = store.insert(&credential, Direction::Issued).await.unwrap();
+            let mut identified = credential.clone();
+            identified["id"] = json!("urn:example:credential");
+            assert_eq!(
+                store.insert(&identified, Direction::Issued).await.unwrap(),
+                id
+            );
+            assert_ne!(
+                store
+                    .insert(&credential, Direction::Received)
+                    .await
+                    .unwrap(),
+                id
+            );
+            let content_hash = content_id::content_hash_hex(&credential).await.unwrap();
+            let record = store.get(&id).await.unwrap().unwrap();
+            assert_eq!(record.content_hash.as_ref(), Some(&content_hash));
+            assert_eq!(
+                store
+                    .find_by_content_hash(&content_hash, Direction::Issued)
+                    .await
+                    .unwrap(),
+                Some(id)
+            );
+        });
+    }
+}
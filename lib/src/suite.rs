@@ -0,0 +1,358 @@
+//! Registration of custom linked data proof suites.
+//!
+//! A downstream crate can add a proof suite that DIDKit does not ship, e.g. one mandated by a
+//! national profile, by implementing [`ProofSuite`] and passing it to [`register_suite`] before
+//! issuing or verifying. A linked data proof whose `type` (or type IRI) is that of a registered
+//! suite is then signed and verified with the suite, in [`issue_credential`],
+//! [`issue_presentation`] and the verification functions, instead of with the built-in suites.
+//!
+//! The suite defines the signing input of a document and proof configuration (typically a hash of
+//! their canonical forms) and how the proof value is produced and checked. DIDKit builds the proof
+//! configuration from the issuance options, checks that the document uses the contexts the suite
+//! requires, and on verification checks the proof purpose, challenge and domain, and that the
+//! verification method belongs to the issuer or holder for the proof purpose.
+//!
+//! [`issue_credential`]: crate::issue_credential
+//! [`issue_presentation`]: crate::issue_presentation
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::issue::IssueOptions;
+use crate::key_cache;
+use crate::proof_purpose::{self, purpose_name, ProofPurposeError};
+use crate::signer::Signer;
+use crate::{DIDResolver, LinkedDataProofOptions, ProofPurpose, JWK};
+
+/// Proof properties holding the signature, excluded from the proof configuration.
+const PROOF_VALUE_PROPERTIES: &[&str] = &["proofValue", "jws", "signatureValue"];
+
+#[derive(Error, Debug)]
+pub enum SuiteError {
+    #[error("Proof suite {0} requires context {1}")]
+    MissingContext(String, String),
+    #[error("Proof suite {0} requires a verification method")]
+    MissingVerificationMethod(String),
+    #[error("Proof suite {0} does not support the key")]
+    UnsupportedKey(String),
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+    #[error("Proof purpose mismatch: expected {0}")]
+    ProofPurposeMismatch(String),
+    #[error("Challenge mismatch")]
+    ChallengeMismatch,
+    #[error("Domain mismatch")]
+    DomainMismatch,
+    #[error("Verification method {0} is not a {1} method of {2}")]
+    VerificationMethodMismatch(String, String, String),
+    #[error("Unable to resolve {0}: {1}")]
+    Resolution(String, String),
+    #[error("Unable to sign: {0}")]
+    Sign(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error(transparent)]
+    ProofPurpose(#[from] ProofPurposeError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// A linked data proof suite.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ProofSuite: Send + Sync {
+    /// Proof `type`, e.g. `ExampleSignature2024`.
+    fn proof_type(&self) -> &str;
+
+    /// IRI of the proof type, as defined by the suite's context, e.g.
+    /// `https://w3id.org/security#ExampleSignature2024`.
+    fn type_iri(&self) -> &str;
+
+    /// URLs of the contexts that a document secured with the suite must include in its
+    /// `@context`, e.g. the context defining the proof type.
+    fn required_contexts(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the suite can sign with, or verify with, a key.
+    fn supports_key(&self, key: &JWK) -> bool;
+
+    /// Signing input of a document, without its proof, and a proof configuration, i.e. the
+    /// proof without its proof value.
+    async fn signing_input(
+        &self,
+        document: &Value,
+        proof_configuration: &Map<String, Value>,
+    ) -> Result<Vec<u8>, SuiteError>;
+
+    /// Sign the signing input, returning the proof value properties, e.g. `proofValue`.
+    async fn sign(
+        &self,
+        signing_input: &[u8],
+        signer: &dyn Signer,
+    ) -> Result<Map<String, Value>, SuiteError>;
+
+    /// Check the proof value properties of a proof against the signing input and public key.
+    async fn verify(
+        &self,
+        signing_input: &[u8],
+        proof: &Map<String, Value>,
+        key: &JWK,
+    ) -> Result<(), SuiteError>;
+}
+
+lazy_static! {
+    static ref SUITES: RwLock<HashMap<String, Arc<dyn ProofSuite>>> = RwLock::new(HashMap::new());
+}
+
+/// Register a proof suite, by its proof type and type IRI, replacing any suite registered with
+/// the same type.
+pub fn register_suite(suite: Arc<dyn ProofSuite>) {
+    if let Ok(mut suites) = SUITES.write() {
+        suites.insert(suite.proof_type().to_string(), suite.clone());
+        suites.insert(suite.type_iri().to_string(), suite);
+    }
+}
+
+/// The registered proof suite of a proof type or type IRI.
+pub fn suite(proof_type: &str) -> Option<Arc<dyn ProofSuite>> {
+    SUITES.read().ok()?.get(proof_type).cloned()
+}
+
+/// The registered proof suite of the proof type of issuance options, if any.
+pub(crate) fn suite_for_options(options: &LinkedDataProofOptions) -> Option<Arc<dyn ProofSuite>> {
+    suite(options.type_.as_ref()?)
+}
+
+/// The registered proof suite of a proof, if any.
+pub(crate) fn suite_for_proof(proof: &Value) -> Option<Arc<dyn ProofSuite>> {
+    suite(proof.get("type")?.as_str()?)
+}
+
+fn check_contexts(suite: &dyn ProofSuite, document: &Value) -> Result<(), SuiteError> {
+    let contexts: Vec<&str> = match document.get("@context") {
+        Some(Value::String(context)) => vec![context],
+        Some(Value::Array(contexts)) => contexts.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    for required in suite.required_contexts() {
+        if !contexts.contains(&required.as_str()) {
+            return Err(SuiteError::MissingContext(
+                suite.proof_type().to_string(),
+                required,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Document without its proof, and proof without its proof value.
+fn unsecured(document: &Value, proof: &Map<String, Value>) -> (Value, Map<String, Value>) {
+    let mut document = document.clone();
+    if let Value::Object(ref mut object) = document {
+        object.remove("proof");
+    }
+    let mut configuration = proof.clone();
+    for name in PROOF_VALUE_PROPERTIES {
+        configuration.remove(*name);
+    }
+    (document, configuration)
+}
+
+/// Generate a proof of a document with a registered suite, using the issuance options.
+pub async fn generate_proof(
+    suite: &dyn ProofSuite,
+    document: &Value,
+    options: &IssueOptions,
+    signer: &dyn Signer,
+) -> Result<ssi::vc::Proof, SuiteError> {
+    let proof_type = suite.proof_type().to_string();
+    if !suite.supports_key(&signer.public_jwk()) {
+        return Err(SuiteError::UnsupportedKey(proof_type));
+    }
+    check_contexts(suite, document)?;
+    let ldp_options = &options.ldp_options;
+    let verification_method = ldp_options
+        .verification_method
+        .as_ref()
+        .ok_or_else(|| SuiteError::MissingVerificationMethod(proof_type.clone()))?;
+    let purpose = ldp_options
+        .proof_purpose
+        .clone()
+        .unwrap_or(ProofPurpose::AssertionMethod);
+    let created = ldp_options.created.unwrap_or_else(Utc::now);
+    let mut proof = Map::new();
+    proof.insert("type".to_string(), Value::String(proof_type));
+    proof.insert(
+        "proofPurpose".to_string(),
+        Value::String(purpose_name(&purpose)),
+    );
+    proof.insert(
+        "verificationMethod".to_string(),
+        Value::String(verification_method.to_string()),
+    );
+    proof.insert(
+        "created".to_string(),
+        Value::String(created.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    );
+    if let Some(ref challenge) = ldp_options.challenge {
+        proof.insert("challenge".to_string(), Value::String(challenge.clone()));
+    }
+    if let Some(ref domain) = ldp_options.domain {
+        proof.insert("domain".to_string(), Value::String(domain.clone()));
+    }
+    for (name, value) in options.extra_proof_properties.iter().flatten() {
+        proof.insert(name.clone(), value.clone());
+    }
+    let (document, configuration) = unsecured(document, &proof);
+    let signing_input = suite.signing_input(&document, &configuration).await?;
+    proof.extend(suite.sign(&signing_input, signer).await?);
+    Ok(serde_json::from_value(Value::Object(proof))?)
+}
+
+/// Verify a proof of a document with a registered suite. The verification method must belong to
+/// the controller, i.e. the issuer or holder, for the proof purpose.
+pub async fn verify_proof(
+    suite: &dyn ProofSuite,
+    document: &Value,
+    proof: &Value,
+    controller: &str,
+    options: &LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<(), SuiteError> {
+    let proof = proof
+        .as_object()
+        .ok_or_else(|| SuiteError::InvalidProof("expected object".to_string()))?;
+    let property = |name: &str| proof.get(name).and_then(Value::as_str);
+    check_contexts(suite, document)?;
+    let purpose = property("proofPurpose")
+        .ok_or_else(|| SuiteError::InvalidProof("missing proofPurpose".to_string()))?;
+    if let Some(ref expected) = options.proof_purpose {
+        let expected = purpose_name(expected);
+        if purpose != expected {
+            return Err(SuiteError::ProofPurposeMismatch(expected));
+        }
+    }
+    if let Some(ref challenge) = options.challenge {
+        if property("challenge") != Some(challenge.as_str()) {
+            return Err(SuiteError::ChallengeMismatch);
+        }
+    }
+    if let Some(ref domain) = options.domain {
+        if property("domain") != Some(domain.as_str()) {
+            return Err(SuiteError::DomainMismatch);
+        }
+    }
+    let verification_method = property("verificationMethod")
+        .ok_or_else(|| SuiteError::MissingVerificationMethod(suite.proof_type().to_string()))?;
+    if let Some(ref expected) = options.verification_method {
+        if expected.to_string() != verification_method {
+            return Err(SuiteError::VerificationMethodMismatch(
+                verification_method.to_string(),
+                purpose.to_string(),
+                controller.to_string(),
+            ));
+        }
+    }
+    proof_purpose::check_authorized(verification_method, controller, purpose, resolver).await?;
+    let key = key_cache::resolve_key(verification_method, resolver)
+        .await
+        .map_err(|e| SuiteError::Resolution(verification_method.to_string(), e.to_string()))?;
+    if !suite.supports_key(&key) {
+        return Err(SuiteError::UnsupportedKey(suite.proof_type().to_string()));
+    }
+    let (document, configuration) = unsecured(document, proof);
+    let signing_input = suite.signing_input(&document, &configuration).await?;
+    suite.verify(&signing_input, proof, &key).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct ExampleSuite;
+
+    #[async_trait]
+    impl ProofSuite for ExampleSuite {
+        fn proof_type(&self) -> &str {
+            "ExampleSignature2024"
+        }
+
+        fn type_iri(&self) -> &str {
+            "https://example.org/security#ExampleSignature2024"
+        }
+
+        fn required_contexts(&self) -> Vec<String> {
+            vec!["https://example.org/security/v1".to_string()]
+        }
+
+        fn supports_key(&self, _key: &JWK) -> bool {
+            true
+        }
+
+        async fn signing_input(
+            &self,
+            document: &Value,
+            proof_configuration: &Map<String, Value>,
+        ) -> Result<Vec<u8>, SuiteError> {
+            Ok(serde_json::to_vec(&(document, proof_configuration))?)
+        }
+
+        async fn sign(
+            &self,
+            signing_input: &[u8],
+            _signer: &dyn Signer,
+        ) -> Result<Map<String, Value>, SuiteError> {
+            let mut proof = Map::new();
+            proof.insert(
+                "proofValue".to_string(),
+                json!(signing_input.len().to_string()),
+            );
+            Ok(proof)
+        }
+
+        async fn verify(
+            &self,
+            signing_input: &[u8],
+            proof: &Map<String, Value>,
+            _key: &JWK,
+        ) -> Result<(), SuiteError> {
+            if proof["proofValue"] == json!(signing_input.len().to_string()) {
+                Ok(())
+            } else {
+                Err(SuiteError::InvalidSignature("length mismatch".to_string()))
+            }
+        }
+    }
+
+    #[test]
+    fn registered_suite() {
+        register_suite(Arc::new(ExampleSuite));
+        assert!(suite("ExampleSignature2024").is_some());
+        assert!(suite("https://example.org/security#ExampleSignature2024").is_some());
+        assert!(suite_for_proof(&json!({ "type": "Ed25519Signature2018" })).is_none());
+
+        let document = json!({ "@context": ["https://www.w3.org/2018/credentials/v1"] });
+        assert!(matches!(
+            check_contexts(&ExampleSuite, &document),
+            Err(SuiteError::MissingContext(_, _))
+        ));
+
+        let document = json!({ "id": "urn:example", "proof": { "type": "ExampleSignature2024" } });
+        let mut proof = Map::new();
+        proof.insert("type".to_string(), json!("ExampleSignature2024"));
+        proof.insert("proofValue".to_string(), json!(1));
+        let (document, configuration) = unsecured(&document, &proof);
+        assert_eq!(document, json!({ "id": "urn:example" }));
+        assert_eq!(
+            Value::Object(configuration),
+            json!({ "type": "ExampleSignature2024" })
+        );
+    }
+}
@@ -0,0 +1,2713 @@
+//! Structured verification results.
+//!
+//! [`VerificationReport`] is the library's verification outcome: errors and warnings carry
+//! machine-readable codes, each proof gets its own sub-result, and the report records who the
+//! issuer or holder was and how long verification took. It converts (lossily) into the
+//! vc-http-api [`VerificationResult`] used by the CLI, HTTP and FFI outputs.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ssi::one_or_many::OneOrMany;
+
+#[cfg(feature = "format-cacao")]
+use crate::cacao::{Cacao, CacaoError};
+use crate::credential_types;
+use crate::error::Error;
+use crate::integrity::{self, ResourceCheck, ResourceStatus};
+use crate::jwt_claims::{self, JWTClaimsOptions};
+use crate::key_cache;
+use crate::key_validity::{self, KeyValidityError};
+use crate::observe::{self, Event};
+use crate::proof_purpose::{self, ProofPurposeError};
+use crate::resolver::{RecordingResolver, ResolutionRecord, VersionTimeResolver};
+use crate::schema;
+use crate::securing::{self, DocumentKind, Secured};
+use crate::signer;
+use crate::suite::{self, ProofSuite};
+use crate::x509::{self, CertificateIdentity};
+use crate::{
+    CredentialOrJWT, DIDResolver, JWTOrLDPOptions, LinkedDataProofOptions, ProofFormat,
+    VerifiableCredential, VerifiablePresentation, VerificationResult, JWK,
+};
+pub use ssi::vc::Check;
+
+/// Machine-readable reason for a verification error.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The credential or presentation is not valid according to the data model.
+    InvalidDocument,
+    /// No proof was found that matches the proof options.
+    NoApplicableProof,
+    /// A signature did not verify.
+    InvalidSignature,
+    /// The proof purpose did not match the expected one.
+    ProofPurposeMismatch,
+    /// The verification method did not match the expected one, or was not usable.
+    VerificationMethodMismatch,
+    /// The proof challenge did not match the expected one.
+    ChallengeMismatch,
+    /// The proof domain did not match the expected one.
+    DomainMismatch,
+    /// Unable to resolve the DID or verification method.
+    ResolutionFailed,
+    /// The credential or proof is expired.
+    Expired,
+    /// The JWT could not be decoded or its claims are invalid.
+    InvalidJWT,
+    /// The issuer or holder uses a DID method that is not allowed by the verification options.
+    DisallowedDIDMethod,
+    /// The credential is not yet valid.
+    NotYetValid,
+    /// The credential has no expiration date, and the verification options require one.
+    MissingExpiration,
+    /// A credential in the presentation is not bound to the presentation's holder.
+    HolderBindingMismatch,
+    /// The issuer's X.509 certificate chain is missing, invalid or not trusted.
+    UntrustedCertificate,
+    /// The credential is revoked or suspended according to its status list.
+    Revoked,
+    /// The status list of the credential could not be fetched.
+    StatusUnavailable,
+    /// A context document does not match its digest in `relatedResource`, or could not be
+    /// obtained.
+    ContextIntegrity,
+    /// The proof format, proof type or signature algorithm is not accepted by the verification
+    /// profile.
+    DisallowedProof,
+    /// The credential does not match its `credentialSchema`, or the schema could not be
+    /// obtained.
+    CredentialSchema,
+    /// The credential has no subject with the expected ID.
+    SubjectMismatch,
+    /// The presentation has no proof challenge (or JWT `nonce`), and the verification options
+    /// expect one.
+    MissingChallenge,
+    /// The presentation has no proof domain (or JWT `aud`), and the verification options expect
+    /// one.
+    MissingDomain,
+    /// The verification method of a proof is not authorized for the proof's purpose by the
+    /// issuer or holder, i.e. not listed for that verification relationship of its DID document.
+    UnauthorizedVerificationMethod,
+    /// A `relatedResource` entry does not match its resource, or the resource could not be
+    /// obtained.
+    RelatedResourceIntegrity,
+    /// The credential is not valid according to a validator of one of its types in the
+    /// [credential type registry](crate::credential_types).
+    CredentialType,
+    /// The verification method of a proof had expired when the proof was made (see
+    /// [`key_validity`](crate::key_validity)).
+    VerificationMethodExpired,
+    /// The verification method of a proof had been revoked when the proof was made.
+    VerificationMethodRevoked,
+    /// The status list of a status entry is not issued by the credential's issuer, or is not
+    /// for the entry's status purpose.
+    StatusListMismatch,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorCode {
+    /// Stable numeric code, for FFI consumers.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::InvalidDocument => 100,
+            Self::NoApplicableProof => 101,
+            Self::InvalidSignature => 102,
+            Self::ProofPurposeMismatch => 103,
+            Self::VerificationMethodMismatch => 104,
+            Self::ChallengeMismatch => 105,
+            Self::DomainMismatch => 106,
+            Self::ResolutionFailed => 107,
+            Self::Expired => 108,
+            Self::InvalidJWT => 109,
+            Self::DisallowedDIDMethod => 110,
+            Self::NotYetValid => 111,
+            Self::MissingExpiration => 112,
+            Self::HolderBindingMismatch => 113,
+            Self::UntrustedCertificate => 114,
+            Self::Revoked => 115,
+            Self::StatusUnavailable => 116,
+            Self::ContextIntegrity => 117,
+            Self::DisallowedProof => 118,
+            Self::CredentialSchema => 119,
+            Self::SubjectMismatch => 120,
+            Self::MissingChallenge => 121,
+            Self::MissingDomain => 122,
+            Self::UnauthorizedVerificationMethod => 123,
+            Self::RelatedResourceIntegrity => 124,
+            Self::CredentialType => 125,
+            Self::VerificationMethodExpired => 126,
+            Self::VerificationMethodRevoked => 127,
+            Self::StatusListMismatch => 128,
+            Self::Other => 199,
+        }
+    }
+
+    /// Code of an error message in a verification result of `ssi`.
+    ///
+    /// `ssi` reports verification errors as strings, the messages of its errors. A message of a
+    /// known [`ssi::error::Error`] variant gets the code of the variant; other messages are
+    /// [classified](Self::classify).
+    pub fn from_message(message: &str) -> Self {
+        if message == NO_APPLICABLE_PROOF {
+            return Self::NoApplicableProof;
+        }
+        match SSI_ERROR_MESSAGES
+            .iter()
+            .find(|(known, _)| known == message)
+        {
+            Some((_, code)) => *code,
+            None => Self::classify(message),
+        }
+    }
+
+    /// Classify an error message by its wording.
+    ///
+    /// This is a fallback for messages that are not those of a known error, e.g. of errors of
+    /// `ssi` with parameters: matching on words is brittle, and may map a reworded message to
+    /// [`ErrorCode::Other`].
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if message.contains("no applicable proof")
+            || message.contains("missing proof")
+            || message.contains("no proof")
+        {
+            Self::NoApplicableProof
+        } else if message.contains("is not authorized for") {
+            Self::UnauthorizedVerificationMethod
+        } else if message.contains("proof purpose") {
+            Self::ProofPurposeMismatch
+        } else if message.contains("challenge") {
+            Self::ChallengeMismatch
+        } else if message.contains("domain") {
+            Self::DomainMismatch
+        } else if message.contains("expire") {
+            Self::Expired
+        } else if message.contains("signature") {
+            Self::InvalidSignature
+        } else if message.contains("verification method") || message.contains("key mismatch") {
+            Self::VerificationMethodMismatch
+        } else if message.contains("resol") || message.contains("not found") {
+            Self::ResolutionFailed
+        } else if message.contains("jwt") || message.contains("jws") {
+            Self::InvalidJWT
+        } else if message.contains("missing")
+            || message.contains("invalid")
+            || message.contains("expected")
+        {
+            Self::InvalidDocument
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl From<&ssi::error::Error> for ErrorCode {
+    fn from(err: &ssi::error::Error) -> Self {
+        use ssi::error::Error as SSIError;
+        match err {
+            SSIError::InvalidSignature => Self::InvalidSignature,
+            SSIError::MissingProof => Self::NoApplicableProof,
+            SSIError::KeyMismatch
+            | SSIError::VerificationMethodMismatch
+            | SSIError::AlgorithmMismatch => Self::VerificationMethodMismatch,
+            SSIError::InvalidJWS => Self::InvalidJWT,
+            SSIError::MissingKey | SSIError::ResourceNotFound(_) => Self::ResolutionFailed,
+            err => Self::classify(&err.to_string()),
+        }
+    }
+}
+
+/// Error of `ssi` verification results without an applicable proof, which is not the
+/// message of an [`ssi::error::Error`].
+const NO_APPLICABLE_PROOF: &str = "No applicable proof";
+
+lazy_static! {
+    /// Messages of the [`ssi::error::Error`] variants without parameters that have a code.
+    static ref SSI_ERROR_MESSAGES: Vec<(String, ErrorCode)> = {
+        use ssi::error::Error as SSIError;
+        [
+            SSIError::InvalidSignature,
+            SSIError::MissingProof,
+            SSIError::KeyMismatch,
+            SSIError::VerificationMethodMismatch,
+            SSIError::AlgorithmMismatch,
+            SSIError::InvalidJWS,
+            SSIError::MissingKey,
+        ]
+        .iter()
+        .map(|err| (err.to_string(), ErrorCode::from(err)))
+        .collect()
+    };
+}
+
+/// Machine-readable reason for a verification warning.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum WarningCode {
+    /// The credential has no expiration date.
+    MissingExpiration,
+    /// A type of the credential is not in the [credential type
+    /// registry](crate::credential_types).
+    UnknownCredentialType,
+    Other,
+}
+
+impl WarningCode {
+    /// Stable numeric code, for FFI consumers.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::MissingExpiration => 200,
+            Self::UnknownCredentialType => 201,
+            Self::Other => 299,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationWarning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+/// Result of verifying a single proof.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofReport {
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_format: Option<ProofFormat>,
+    /// Signature algorithm of a JWT, VC-JOSE or VC-COSE proof
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    pub checks: Vec<Check>,
+    pub warnings: Vec<VerificationWarning>,
+    pub errors: Vec<VerificationError>,
+}
+
+/// Wall-clock timing of a verification.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Timing {
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub duration_ms: i64,
+}
+
+impl Timing {
+    pub fn since(started: DateTime<Utc>) -> Self {
+        let finished = Utc::now();
+        Self {
+            started,
+            finished,
+            duration_ms: (finished - started).num_milliseconds(),
+        }
+    }
+}
+
+/// Verifier policy, applied in addition to the proof options.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationOptions {
+    /// DID methods that the issuer (of a credential) or holder (of a presentation) may use. If
+    /// unset, any method is allowed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_did_methods: Option<Vec<String>>,
+    /// Tolerance, in seconds, for clock differences when checking validity periods
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<i64>,
+    /// Check validity periods as of this time, instead of the current time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_as_of: Option<DateTime<Utc>>,
+    /// How to treat a credential without an expiration date. Defaults to allowing it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing_expiration: Option<MissingExpiration>,
+    /// Require each credential in a presentation to be bound to the presentation's holder, by
+    /// `credentialSubject.id` (or JWT `sub`), or by a JWT `cnf` confirmation method
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub require_holder_binding: bool,
+    /// Require the presentation's holder to be this DID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_holder: Option<String>,
+    /// CACAO (signed SIWE message) proving control of the holder's `did:pkh`, for a presentation
+    /// without a proof
+    #[cfg(feature = "format-cacao")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<serde_json::Value>"))]
+    pub cacao: Option<Cacao>,
+    /// Also verify the credentials embedded in a presentation, with the same verifier policy
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub verify_credentials: bool,
+    /// Maximum number of embedded credentials to verify concurrently. Defaults to
+    /// [`DEFAULT_CREDENTIAL_PARALLELISM`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_parallelism: Option<usize>,
+    /// Trusted X.509 certificates (PEM, or base64 DER). If set, a VC-JWT or VC-JOSE credential
+    /// must have an `x5c` or `x5u` certificate chain to one of them, and VC-COSE and JPT
+    /// credentials, which cannot carry one, fail verification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_certificates: Option<Vec<String>>,
+    /// Check the `@context` documents listed with a `digestSRI` in `relatedResource` against
+    /// their digests
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_context_integrity: bool,
+    /// Context documents, by URL, to check instead of fetching them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_documents: Option<HashMap<String, String>>,
+    /// Fetch every resource listed in `relatedResource`, and check it against its `digestSRI` or
+    /// `digestMultibase`. Documents in `context_documents` are checked instead of fetching them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_related_resources: bool,
+    /// Validate credentials against their `credentialSchema`, each of their subjects against the
+    /// schema of `credentialSubject`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_credential_schema: bool,
+    /// Schema documents, by ID, to validate with instead of fetching them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_documents: Option<HashMap<String, String>>,
+    /// Validate credentials with the validators of their types in the [credential type
+    /// registry](crate::credential_types), and warn of types that are not in it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_credential_types: bool,
+    /// Require credentials to have a subject with this ID (`credentialSubject.id`, or JWT `sub`),
+    /// among any others
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_subject: Option<String>,
+    /// Require the proof challenge of a presentation (or JWT `nonce`) to be this value. Unlike
+    /// the `challenge` proof option, a missing challenge is an error of its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_challenge: Option<String>,
+    /// Require the proof domain of a presentation (or JWT `aud`) to be this value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_domain: Option<String>,
+    /// Verify each proof against the DID document of its signer as of the proof's `created` date
+    /// (or JWT `iat`), for DID methods that resolve versions by `versionTime` (see
+    /// [`VERSIONED_METHODS`](crate::resolver::VERSIONED_METHODS)), so that a key since rotated
+    /// out still verifies the proofs it made.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub historical_resolution: bool,
+}
+
+/// Default number of embedded credentials of a presentation verified concurrently.
+pub const DEFAULT_CREDENTIAL_PARALLELISM: usize = 8;
+
+/// Treatment of credentials without an expiration date.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum MissingExpiration {
+    Allow,
+    Warn,
+    Error,
+}
+
+impl FromStr for MissingExpiration {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("Expected allow, warn or error: {}", s)),
+        }
+    }
+}
+
+impl VerificationOptions {
+    /// Check that a DID is of an allowed method.
+    pub fn check_did_method(&self, did: &str) -> Result<(), VerificationError> {
+        let allowed = match self.allowed_did_methods {
+            Some(ref allowed) => allowed,
+            None => return Ok(()),
+        };
+        let method = match did.strip_prefix("did:") {
+            Some(rest) => rest.split(':').next().unwrap_or_default(),
+            None => {
+                return Err(VerificationError {
+                    code: ErrorCode::DisallowedDIDMethod,
+                    message: format!("Expected DID: {}", did),
+                })
+            }
+        };
+        if allowed.iter().any(|m| m == method) {
+            Ok(())
+        } else {
+            Err(VerificationError {
+                code: ErrorCode::DisallowedDIDMethod,
+                message: format!("DID method not allowed: {}", method),
+            })
+        }
+    }
+}
+
+/// A check performed according to the verification options, in addition to proof checks.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum PolicyCheck {
+    HolderBinding,
+    CertificateChain,
+    ContextIntegrity,
+    RelatedResourceIntegrity,
+    Profile,
+    CredentialSchema,
+    CredentialType,
+    ExpectedSubject,
+    ExpectedChallenge,
+    ExpectedDomain,
+}
+
+/// Structured result of verifying a credential or presentation.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub checks: Vec<Check>,
+    pub warnings: Vec<VerificationWarning>,
+    pub errors: Vec<VerificationError>,
+    /// Per-proof results
+    pub proofs: Vec<ProofReport>,
+    /// Issuer of the verified credential
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Holder of the verified presentation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+    /// Checks performed according to the verification options
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub policy_checks: Vec<PolicyCheck>,
+    /// Results for the credentials embedded in the verified presentation, in order, if
+    /// [`VerificationOptions::verify_credentials`] is set
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub credentials: Vec<VerificationReport>,
+    /// Identity of the issuer from its X.509 certificate, if
+    /// [`VerificationOptions::trusted_certificates`] is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer_certificate: Option<CertificateIdentity>,
+    /// DID resolutions performed to verify the document, with the resolver and document
+    /// metadata of the DID document state used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolutions: Vec<ResolutionRecord>,
+    /// Results of checking the `relatedResource` entries, if
+    /// [`VerificationOptions::check_related_resources`] is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_resources: Vec<ResourceCheck>,
+}
+
+impl VerificationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(code: ErrorCode, message: &str) -> Self {
+        let mut report = Self::new();
+        report.push_error(code, message);
+        report
+    }
+
+    /// Verification succeeded if no errors were encountered.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn push_error(&mut self, code: ErrorCode, message: &str) {
+        self.errors.push(VerificationError {
+            code,
+            message: message.to_string(),
+        });
+    }
+
+    pub fn push_warning(&mut self, code: WarningCode, message: &str) {
+        self.warnings.push(VerificationWarning {
+            code,
+            message: message.to_string(),
+        });
+    }
+
+    pub fn has_error(&self, code: ErrorCode) -> bool {
+        self.errors.iter().any(|error| error.code == code)
+    }
+}
+
+impl From<VerificationResult> for VerificationReport {
+    fn from(result: VerificationResult) -> Self {
+        Self {
+            checks: result.checks,
+            warnings: result
+                .warnings
+                .into_iter()
+                .map(|message| VerificationWarning {
+                    code: WarningCode::Other,
+                    message,
+                })
+                .collect(),
+            errors: result
+                .errors
+                .into_iter()
+                .map(|message| VerificationError {
+                    code: ErrorCode::from_message(&message),
+                    message,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<VerificationReport> for VerificationResult {
+    /// Lossy conversion to the flat vc-http-api verification result. Codes, per-proof results,
+    /// issuer/holder, timing and policy checks are dropped.
+    fn from(report: VerificationReport) -> Self {
+        let mut result = VerificationResult::new();
+        result.checks = report.checks;
+        result.warnings = report
+            .warnings
+            .into_iter()
+            .map(|warning| warning.message)
+            .collect();
+        result.errors = report
+            .errors
+            .into_iter()
+            .map(|error| error.message)
+            .collect();
+        result
+    }
+}
+
+/// Get the `id` of a node that is either a string or an object with an `id` property.
+pub(crate) fn node_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.to_string()),
+        Value::Object(object) => object
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string()),
+        _ => None,
+    }
+}
+
+/// Decode the header and claims of a JWT without verifying it.
+pub(crate) fn decode_jwt_unverified(jwt: &str) -> Option<(Value, Value)> {
+    let mut parts = jwt.trim().split('.');
+    let header = parts.next()?;
+    let payload = parts.next()?;
+    let decode = |part: &str| -> Option<Value> {
+        let bytes = base64::decode_config(part, base64::URL_SAFE_NO_PAD).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    };
+    Some((decode(header)?, decode(payload)?))
+}
+
+fn proof_report(proof: &Value, result: VerificationResult) -> ProofReport {
+    let report = VerificationReport::from(result);
+    let string_prop = |name: &str| {
+        proof
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+    ProofReport {
+        type_: string_prop("type"),
+        verification_method: string_prop("verificationMethod"),
+        proof_purpose: string_prop("proofPurpose"),
+        created: string_prop("created"),
+        proof_format: Some(ProofFormat::LDP),
+        algorithm: None,
+        checks: report.checks,
+        warnings: report.warnings,
+        errors: report.errors,
+    }
+}
+
+fn jwt_proof_report(jwt: &str, result: VerificationResult) -> ProofReport {
+    let report = VerificationReport::from(result);
+    let (header, claims) = match decode_jwt_unverified(jwt) {
+        Some((header, claims)) => (Some(header), Some(claims)),
+        None => (None, None),
+    };
+    let created = claims
+        .as_ref()
+        .and_then(issued_at)
+        .map(|iat| iat.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let algorithm = header
+        .as_ref()
+        .and_then(|header| header.get("alg"))
+        .and_then(|alg| alg.as_str())
+        .map(|alg| alg.to_string());
+    ProofReport {
+        type_: algorithm.as_ref().map(|alg| format!("JWT ({})", alg)),
+        verification_method: header
+            .as_ref()
+            .and_then(|header| header.get("kid"))
+            .and_then(|kid| kid.as_str())
+            .map(|kid| kid.to_string()),
+        created,
+        proof_format: Some(ProofFormat::JWT),
+        algorithm,
+        checks: report.checks,
+        warnings: report.warnings,
+        errors: report.errors,
+        ..Default::default()
+    }
+}
+
+/// Issuance date of the claims of a JWT, its signing time.
+fn issued_at(claims: &Value) -> Option<DateTime<Utc>> {
+    let iat = claims.get("iat").and_then(Value::as_i64)?;
+    Utc.timestamp_opt(iat, 0).single()
+}
+
+/// Resolver of DID documents as of a proof's signing time, with historical resolution.
+fn historical_resolver(
+    resolver: &dyn DIDResolver,
+    historical: bool,
+    signed_at: Option<DateTime<Utc>>,
+) -> Option<VersionTimeResolver<'_>> {
+    match signed_at {
+        Some(signed_at) if historical => Some(VersionTimeResolver::new(resolver, signed_at)),
+        _ => None,
+    }
+}
+
+/// Combine per-proof results: verification succeeds if any proof verified.
+fn aggregate(proofs: Vec<ProofReport>) -> VerificationReport {
+    let mut report = VerificationReport::new();
+    match proofs.iter().find(|proof| proof.errors.is_empty()) {
+        Some(passed) => {
+            report.checks = passed.checks.clone();
+            report.warnings = passed.warnings.clone();
+        }
+        None => {
+            for proof in proofs.iter() {
+                for error in proof.errors.iter() {
+                    if !report.errors.contains(error) {
+                        report.errors.push(error.clone());
+                    }
+                }
+            }
+            if report.errors.is_empty() {
+                report.push_error(ErrorCode::NoApplicableProof, "No applicable proof");
+            }
+        }
+    }
+    report.proofs = proofs;
+    report
+}
+
+/// Verify a document with one proof using the registered proof suite of the proof.
+async fn verify_with_suite(
+    suite: &dyn ProofSuite,
+    document: &impl Serialize,
+    proof: &Value,
+    controller: Option<String>,
+    options: &LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+) -> VerificationResult {
+    let mut result = VerificationResult::new();
+    let verified = match (serde_json::to_value(document), controller) {
+        (Ok(document), Some(controller)) => {
+            suite::verify_proof(suite, &document, proof, &controller, options, resolver)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        (Ok(_), None) => Err("Missing issuer or holder".to_string()),
+        (Err(err), _) => Err(err.to_string()),
+    };
+    match verified {
+        Ok(()) => result.checks.push(Check::Proof),
+        Err(err) => result.errors.push(err),
+    }
+    result
+}
+
+/// Verify a credential with one proof, using its registered proof suite, if any (see
+/// [`suite`]), or `ssi`.
+async fn verify_credential_proof(
+    vc: &VerifiableCredential,
+    proof: Option<&ssi::vc::Proof>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationResult {
+    let versioned = historical_resolver(resolver, historical, proof.and_then(|p| p.created));
+    let resolver: &dyn DIDResolver = match versioned {
+        Some(ref versioned) => versioned,
+        None => resolver,
+    };
+    let proof = proof.and_then(|proof| serde_json::to_value(proof).ok());
+    let suite = proof.as_ref().and_then(suite::suite_for_proof);
+    match (suite, proof) {
+        (Some(suite), Some(proof)) => {
+            if let Err(err) = vc.validate() {
+                return VerificationResult::error(&err.to_string());
+            }
+            let issuer = credential_issuer(vc);
+            verify_with_suite(&*suite, vc, &proof, issuer, &options, resolver).await
+        }
+        (None, Some(proof)) => {
+            let mut result = vc.verify(Some(options), resolver).await;
+            let issuer = credential_issuer(vc);
+            check_proof_purpose(&mut result, &proof, issuer, "assertionMethod", resolver).await;
+            result
+        }
+        _ => vc.verify(Some(options), resolver).await,
+    }
+}
+
+/// Verify a presentation with one proof, using its registered proof suite, if any, or `ssi`.
+async fn verify_presentation_proof(
+    vp: &VerifiablePresentation,
+    proof: Option<&ssi::vc::Proof>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationResult {
+    let versioned = historical_resolver(resolver, historical, proof.and_then(|p| p.created));
+    let resolver: &dyn DIDResolver = match versioned {
+        Some(ref versioned) => versioned,
+        None => resolver,
+    };
+    let proof = proof.and_then(|proof| serde_json::to_value(proof).ok());
+    let suite = proof.as_ref().and_then(suite::suite_for_proof);
+    match (suite, proof) {
+        (Some(suite), Some(proof)) => {
+            if let Err(err) = vp.validate() {
+                return VerificationResult::error(&err.to_string());
+            }
+            let holder = vp.holder.as_ref().map(|holder| holder.to_string());
+            verify_with_suite(&*suite, vp, &proof, holder, &options, resolver).await
+        }
+        (None, Some(proof)) => {
+            let mut result = vp.verify(Some(options), resolver).await;
+            let holder = vp.holder.as_ref().map(|holder| holder.to_string());
+            check_proof_purpose(&mut result, &proof, holder, "authentication", resolver).await;
+            result
+        }
+        _ => vp.verify(Some(options), resolver).await,
+    }
+}
+
+/// If a proof failed to verify, check that its verification method is authorized for its proof
+/// purpose by the issuer or holder, so that an unauthorized verification method is reported as
+/// such, rather than as there being no applicable proof.
+async fn check_proof_purpose(
+    result: &mut VerificationResult,
+    proof: &Value,
+    controller: Option<String>,
+    default_purpose: &str,
+    resolver: &dyn DIDResolver,
+) {
+    if result.errors.is_empty() {
+        return;
+    }
+    let verification_method = match proof.get("verificationMethod").and_then(Value::as_str) {
+        Some(verification_method) if verification_method.starts_with("did:") => verification_method,
+        _ => return,
+    };
+    let controller = match controller {
+        Some(controller) if controller.starts_with("did:") => controller,
+        _ => return,
+    };
+    let purpose = proof
+        .get("proofPurpose")
+        .and_then(Value::as_str)
+        .unwrap_or(default_purpose);
+    if let Err(err @ ProofPurposeError::Unauthorized(..)) =
+        proof_purpose::check_authorized(verification_method, &controller, purpose, resolver).await
+    {
+        result
+            .errors
+            .retain(|error| ErrorCode::from_message(error) != ErrorCode::NoApplicableProof);
+        result.errors.insert(0, err.to_string());
+    }
+}
+
+async fn verify_credential_ldp(
+    vc: &VerifiableCredential,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationReport {
+    let proofs: Vec<_> = match vc.proof {
+        Some(ref proofs) => proofs.into_iter().cloned().collect(),
+        None => Vec::new(),
+    };
+    let mut report = if proofs.len() <= 1 {
+        let result =
+            verify_credential_proof(vc, proofs.first(), options, resolver, historical).await;
+        let proof_value = match proofs.first() {
+            Some(proof) => serde_json::to_value(proof).unwrap_or_default(),
+            None => Value::Null,
+        };
+        let mut report = VerificationReport::from(result.clone());
+        if !proofs.is_empty() {
+            report.proofs = vec![proof_report(&proof_value, result)];
+        }
+        report
+    } else {
+        let mut proof_reports = Vec::with_capacity(proofs.len());
+        let mut single = vc.clone();
+        for proof in proofs {
+            let proof_value = serde_json::to_value(&proof).unwrap_or_default();
+            single.proof = Some(OneOrMany::One(proof.clone()));
+            let result = verify_credential_proof(
+                &single,
+                Some(&proof),
+                options.clone(),
+                resolver,
+                historical,
+            )
+            .await;
+            proof_reports.push(proof_report(&proof_value, result));
+        }
+        aggregate(proof_reports)
+    };
+    report.issuer = credential_issuer(vc);
+    report
+}
+
+fn credential_issuer(vc: &VerifiableCredential) -> Option<String> {
+    // Serialize only the issuer, not the whole (possibly large) credential.
+    let issuer = serde_json::to_value(&vc.issuer).ok()?;
+    node_id(&issuer)
+}
+
+fn jwt_credential_issuer(jwt: &str) -> Option<String> {
+    if let Some(credential) = securing::payload_unverified(jwt) {
+        return credential.get("issuer").and_then(node_id);
+    }
+    #[cfg(feature = "format-jwp")]
+    if let Some(claims) = crate::jwp::claims_unverified(jwt) {
+        return claims.get("issuer").and_then(node_id);
+    }
+    let (_, claims) = decode_jwt_unverified(jwt)?;
+    claims
+        .get("iss")
+        .and_then(node_id)
+        .or_else(|| claims.pointer("/vc/issuer").and_then(node_id))
+}
+
+fn jwt_presentation_holder(jwt: &str) -> Option<String> {
+    if let Some(presentation) = securing::payload_unverified(jwt) {
+        return presentation.get("holder").and_then(node_id);
+    }
+    let (_, claims) = decode_jwt_unverified(jwt)?;
+    claims
+        .get("iss")
+        .and_then(node_id)
+        .or_else(|| claims.pointer("/vp/holder").and_then(node_id))
+}
+
+/// Verify a VC-JOSE or VC-COSE secured credential or presentation. The `kid` must be a
+/// verification method of the issuer (or holder), and for presentations, the `nonce` and `aud`
+/// properties must match the challenge and domain options.
+async fn verify_secured(
+    secured: &str,
+    kind: DocumentKind,
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<VerificationReport, Error> {
+    let decoded = match Secured::decode(secured) {
+        Ok(decoded) => decoded,
+        Err(err) => {
+            return Ok(VerificationReport::error(
+                ErrorCode::InvalidDocument,
+                &err.to_string(),
+            ))
+        }
+    };
+    if let Some(ref proof_format) = options.proof_format {
+        if proof_format != &decoded.proof_format {
+            return Err(Error::ProofFormatMismatch(proof_format.to_string()));
+        }
+    }
+    let mut report = VerificationReport::new();
+    let algorithm = signer::algorithm_name(decoded.algorithm);
+    let mut proof = ProofReport {
+        type_: Some(format!("{} ({})", decoded.proof_format, algorithm)),
+        verification_method: decoded.key_id.clone(),
+        proof_format: Some(decoded.proof_format.clone()),
+        algorithm: Some(algorithm),
+        ..Default::default()
+    };
+    let payload = &decoded.payload;
+    let (controller, expected_type, controller_property) = match kind {
+        DocumentKind::Credential => {
+            report.issuer = payload.get("issuer").and_then(node_id);
+            (report.issuer.clone(), "VerifiableCredential", "issuer")
+        }
+        DocumentKind::Presentation => {
+            report.holder = payload.get("holder").and_then(node_id);
+            (report.holder.clone(), "VerifiablePresentation", "holder")
+        }
+    };
+    if let Err(err) = decoded.check_media_type(kind) {
+        report.push_error(ErrorCode::InvalidDocument, &err.to_string());
+    }
+    if !one_or_many(payload.get("type"))
+        .iter()
+        .any(|type_| type_.as_str() == Some(expected_type))
+    {
+        report.push_error(
+            ErrorCode::InvalidDocument,
+            &format!("Missing type {}", expected_type),
+        );
+    }
+    match controller {
+        Some(ref controller) => match decoded.verify_signature(controller, resolver).await {
+            Ok(verification_method) => {
+                proof.verification_method = Some(verification_method);
+                proof.checks.push(Check::Proof);
+            }
+            Err(err) => {
+                let code = match err {
+                    securing::SecuringError::Resolution(_) => ErrorCode::ResolutionFailed,
+                    securing::SecuringError::InvalidSignature(_) => ErrorCode::InvalidSignature,
+                    _ => ErrorCode::VerificationMethodMismatch,
+                };
+                proof.errors.push(VerificationError {
+                    code,
+                    message: err.to_string(),
+                });
+            }
+        },
+        None => proof.errors.push(VerificationError {
+            code: ErrorCode::InvalidDocument,
+            message: format!("Missing {}", controller_property),
+        }),
+    }
+    report.errors.extend(proof.errors.iter().cloned());
+    report.checks.extend(proof.checks.iter().cloned());
+    report.proofs.push(proof);
+    if kind == DocumentKind::Presentation {
+        let ldp_options = &options.ldp_options;
+        let claim = |name: &str| payload.get(name).and_then(Value::as_str);
+        if let Some(ref challenge) = ldp_options.challenge {
+            if claim("nonce") != Some(challenge.as_str()) {
+                report.push_error(ErrorCode::ChallengeMismatch, "Challenge mismatch");
+            }
+        }
+        if let Some(ref domain) = ldp_options.domain {
+            if claim("aud") != Some(domain.as_str()) {
+                report.push_error(ErrorCode::DomainMismatch, "Domain mismatch");
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Verify a JSON Proof Token, issued or presented, of a credential. The `kid` must be a
+/// verification method of the issuer. As a presentation, the JPT must be presented, with the
+/// `nonce` and `aud` of its presentation header matching the challenge and domain options.
+#[cfg(feature = "format-jwp")]
+async fn verify_jpt(
+    jpt: &str,
+    kind: DocumentKind,
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> VerificationReport {
+    use crate::jwp::{self, JsonWebProof};
+    let jwp = match JsonWebProof::from_compact(jpt) {
+        Ok(jwp) => jwp,
+        Err(err) => return VerificationReport::error(ErrorCode::InvalidDocument, &err.to_string()),
+    };
+    let claims = match jwp.claims() {
+        Ok(claims) => Value::Object(claims),
+        Err(err) => return VerificationReport::error(ErrorCode::InvalidDocument, &err.to_string()),
+    };
+    let mut report = VerificationReport::new();
+    let algorithm = jwp.issuer_header.alg.clone();
+    let mut proof = ProofReport {
+        type_: Some(format!("{} ({})", ProofFormat::JPT, algorithm)),
+        verification_method: jwp.issuer_header.kid.clone(),
+        proof_format: Some(ProofFormat::JPT),
+        algorithm: Some(algorithm),
+        ..Default::default()
+    };
+    report.issuer = claims.get("issuer").and_then(node_id);
+    if !one_or_many(claims.get("type"))
+        .iter()
+        .any(|type_| type_.as_str() == Some("VerifiableCredential"))
+    {
+        report.push_error(
+            ErrorCode::InvalidDocument,
+            "Missing type VerifiableCredential",
+        );
+    }
+    let verification_method = jwp.issuer_header.kid.as_deref().unwrap_or_default();
+    let did = verification_method.split('#').next().unwrap_or_default();
+    let error = match report.issuer {
+        None => Some((ErrorCode::InvalidDocument, "Missing issuer".to_string())),
+        Some(ref issuer) if did != issuer => Some((
+            ErrorCode::VerificationMethodMismatch,
+            format!(
+                "Key ID {} is not a verification method of {}",
+                verification_method, issuer
+            ),
+        )),
+        Some(_) => match key_cache::resolve_key(verification_method, resolver).await {
+            Ok(key) => match jwp::verify(&jwp, &jwp::Bbs, &key) {
+                Ok(_) => None,
+                Err(err) => Some((ErrorCode::InvalidSignature, err.to_string())),
+            },
+            Err(err) => Some((ErrorCode::ResolutionFailed, err.to_string())),
+        },
+    };
+    match error {
+        Some((code, message)) => proof.errors.push(VerificationError { code, message }),
+        None => proof.checks.push(Check::Proof),
+    }
+    report.errors.extend(proof.errors.iter().cloned());
+    report.checks.extend(proof.checks.iter().cloned());
+    report.proofs.push(proof);
+    match kind {
+        DocumentKind::Credential => {
+            ValidityPeriod::of_value(&claims).check(&options.verification_options, &mut report);
+        }
+        DocumentKind::Presentation => {
+            let header = match jwp.presentation_header {
+                Some(ref header) => header,
+                None => {
+                    report.push_error(
+                        ErrorCode::InvalidDocument,
+                        "Expected a presented JSON Proof Token",
+                    );
+                    return report;
+                }
+            };
+            let ldp_options = &options.ldp_options;
+            if let Some(ref challenge) = ldp_options.challenge {
+                if header.nonce.as_deref() != Some(challenge.as_str()) {
+                    report.push_error(ErrorCode::ChallengeMismatch, "Challenge mismatch");
+                }
+            }
+            if let Some(ref domain) = ldp_options.domain {
+                if header.aud.as_deref() != Some(domain.as_str()) {
+                    report.push_error(ErrorCode::DomainMismatch, "Domain mismatch");
+                }
+            }
+        }
+    }
+    report
+}
+
+async fn verify_credential_jwt(
+    jwt: &str,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationReport {
+    let signed_at = decode_jwt_unverified(jwt).and_then(|(_, claims)| issued_at(&claims));
+    let versioned = historical_resolver(resolver, historical, signed_at);
+    let resolver: &dyn DIDResolver = match versioned {
+        Some(ref versioned) => versioned,
+        None => resolver,
+    };
+    let result = VerifiableCredential::verify_jwt(jwt, Some(options), resolver).await;
+    let mut report = VerificationReport::from(result.clone());
+    report.proofs = vec![jwt_proof_report(jwt, result)];
+    report.issuer = jwt_credential_issuer(jwt);
+    report
+}
+
+/// Check that the claims of a JWT credential map to its properties with the claim mapping
+/// options, e.g. that no claim differs from its property, with precedence `error`.
+fn check_jwt_claims(jwt: &str, options: &JWTClaimsOptions, report: &mut VerificationReport) {
+    let claims = match decode_jwt_unverified(jwt) {
+        Some((_header, claims)) => claims,
+        None => return,
+    };
+    if let Err(err) = jwt_claims::decode_credential(&claims, options) {
+        report.push_error(ErrorCode::InvalidJWT, &err.to_string());
+    }
+}
+
+/// Check the X.509 certificate chain of a JWT credential to the trusted certificates.
+async fn check_certificate_chain(
+    jwt: &str,
+    trusted_certificates: &[String],
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let as_of = options.verify_as_of.unwrap_or_else(Utc::now);
+    match x509::verify_jwt(jwt, trusted_certificates, as_of).await {
+        Ok(identity) => {
+            report.issuer_certificate = Some(identity);
+            report.policy_checks.push(PolicyCheck::CertificateChain);
+        }
+        Err(err) => report.push_error(ErrorCode::UntrustedCertificate, &err.to_string()),
+    }
+}
+
+/// Check the certificate chain of a credential not in a linked data proof format. Only compact JWS
+/// credentials, VC-JWT and VC-JOSE, can carry one: others fail the check, as their issuers
+/// cannot be anchored to the trusted certificates.
+async fn check_issuer_certificate(
+    credential: &str,
+    trusted_certificates: &[String],
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    #[cfg(feature = "format-jwp")]
+    let is_jpt = crate::jwp::is_jpt(credential);
+    #[cfg(not(feature = "format-jwp"))]
+    let is_jpt = false;
+    let unsupported = if is_jpt {
+        Some(ProofFormat::JPT)
+    } else {
+        match Secured::decode(credential) {
+            Ok(secured) if secured.proof_format == ProofFormat::VcCose => Some(ProofFormat::VcCose),
+            _ => None,
+        }
+    };
+    match unsupported {
+        Some(proof_format) => report.push_error(
+            ErrorCode::UntrustedCertificate,
+            &format!(
+                "Certificate chains are not supported with proof format {}",
+                proof_format
+            ),
+        ),
+        None => check_certificate_chain(credential, trusted_certificates, options, report).await,
+    }
+}
+
+/// The credential or presentation of a JWT, or the payload of a VC-JOSE or VC-COSE document,
+/// without verifying it.
+fn jwt_document_unverified(jwt: &str, claim: &str) -> Option<Value> {
+    if securing::is_secured(jwt) {
+        return securing::payload_unverified(jwt);
+    }
+    let (_header, mut payload) = decode_jwt_unverified(jwt)?;
+    payload.get_mut(claim).map(Value::take)
+}
+
+/// Check the contexts of a credential or presentation against their digests in
+/// `relatedResource`.
+async fn check_context_integrity(
+    document: Option<&Value>,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let document = match document {
+        Some(document) => document,
+        None => {
+            report.push_error(ErrorCode::InvalidDocument, "Unable to read contexts");
+            return;
+        }
+    };
+    match integrity::check_contexts(document, options.context_documents.as_ref()).await {
+        Ok(_) => report.policy_checks.push(PolicyCheck::ContextIntegrity),
+        Err(err) => report.push_error(ErrorCode::ContextIntegrity, &err.to_string()),
+    }
+}
+
+/// Check the `relatedResource` entries of a credential or presentation against their resources.
+async fn check_related_resources(
+    document: Option<&Value>,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let document = match document {
+        Some(document) => document,
+        None => {
+            report.push_error(ErrorCode::InvalidDocument, "Unable to read relatedResource");
+            return;
+        }
+    };
+    let checks = match integrity::check_related_resources(
+        document,
+        options.context_documents.as_ref(),
+    )
+    .await
+    {
+        Ok(checks) => checks,
+        Err(err) => {
+            report.push_error(ErrorCode::RelatedResourceIntegrity, &err.to_string());
+            return;
+        }
+    };
+    let mut verified = true;
+    for check in &checks {
+        if check.status != ResourceStatus::Verified {
+            verified = false;
+            let message = check.message.as_deref().unwrap_or(&check.id);
+            report.push_error(ErrorCode::RelatedResourceIntegrity, message);
+        }
+    }
+    if verified {
+        report
+            .policy_checks
+            .push(PolicyCheck::RelatedResourceIntegrity);
+    }
+    report.related_resources = checks;
+}
+
+/// Validate a credential against its `credentialSchema` entries.
+async fn check_credential_schema(
+    credential: Option<&Value>,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let credential = match credential {
+        Some(credential) => credential,
+        None => {
+            report.push_error(ErrorCode::InvalidDocument, "Unable to read credential");
+            return;
+        }
+    };
+    match schema::check_schemas(credential, options.schema_documents.as_ref()).await {
+        Ok(_) => report.policy_checks.push(PolicyCheck::CredentialSchema),
+        Err(err) => report.push_error(ErrorCode::CredentialSchema, &err.to_string()),
+    }
+}
+
+/// Validate a credential with the validators of its types.
+fn check_credential_types(credential: Option<&Value>, report: &mut VerificationReport) {
+    match credential {
+        Some(credential) => credential_types::registry().check(credential, report),
+        None => report.push_error(ErrorCode::InvalidDocument, "Unable to read credential"),
+    }
+}
+
+/// Check that a credential has a subject with the expected ID, or the expected JWT `sub`.
+fn check_expected_subject(
+    credential: Option<&Value>,
+    sub: Option<&str>,
+    expected_subject: &str,
+    report: &mut VerificationReport,
+) {
+    let found = sub == Some(expected_subject)
+        || credential.map_or(false, |credential| {
+            schema::subjects(credential)
+                .into_iter()
+                .filter_map(node_id)
+                .any(|id| id == expected_subject)
+        });
+    if found {
+        report.policy_checks.push(PolicyCheck::ExpectedSubject);
+    } else {
+        let message = format!("No credential subject {}", expected_subject);
+        report.push_error(ErrorCode::SubjectMismatch, &message);
+    }
+}
+
+async fn verify_presentation_ldp(
+    vp: &VerifiablePresentation,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationReport {
+    let proofs: Vec<_> = match vp.proof {
+        Some(ref proofs) => proofs.into_iter().cloned().collect(),
+        None => Vec::new(),
+    };
+    let mut report = if proofs.len() <= 1 {
+        let result =
+            verify_presentation_proof(vp, proofs.first(), options, resolver, historical).await;
+        let proof_value = match proofs.first() {
+            Some(proof) => serde_json::to_value(proof).unwrap_or_default(),
+            None => Value::Null,
+        };
+        let mut report = VerificationReport::from(result.clone());
+        if !proofs.is_empty() {
+            report.proofs = vec![proof_report(&proof_value, result)];
+        }
+        report
+    } else {
+        let mut proof_reports = Vec::with_capacity(proofs.len());
+        let mut single = vp.clone();
+        for proof in proofs {
+            let proof_value = serde_json::to_value(&proof).unwrap_or_default();
+            single.proof = Some(OneOrMany::One(proof.clone()));
+            let result = verify_presentation_proof(
+                &single,
+                Some(&proof),
+                options.clone(),
+                resolver,
+                historical,
+            )
+            .await;
+            proof_reports.push(proof_report(&proof_value, result));
+        }
+        aggregate(proof_reports)
+    };
+    report.holder = vp.holder.as_ref().map(|holder| holder.to_string());
+    report
+}
+
+/// Verify a presentation whose holder proves control of their `did:pkh` with a CACAO, instead of
+/// a proof. The CACAO's nonce and domain take the place of the proof challenge and domain, which
+/// are required, and its resources must list the [hash](crate::cacao::presentation_resource) of
+/// the presentation, as its signature covers only the SIWE message.
+#[cfg(feature = "format-cacao")]
+fn verify_presentation_cacao(
+    vp: &VerifiablePresentation,
+    cacao: &Cacao,
+    options: &LinkedDataProofOptions,
+    verification_options: &VerificationOptions,
+) -> VerificationReport {
+    let mut report = VerificationReport::new();
+    report.holder = vp.holder.as_ref().map(|holder| holder.to_string());
+    if let Err(err) = vp.validate_unsigned() {
+        report.push_error(ErrorCode::InvalidDocument, &err.to_string());
+        return report;
+    }
+    if report.holder.as_deref() != Some(cacao.p.iss.as_str()) {
+        report.push_error(
+            ErrorCode::HolderBindingMismatch,
+            &format!("Holder does not match CACAO issuer: {}", cacao.p.iss),
+        );
+        return report;
+    }
+    match options.challenge {
+        None => report.push_error(
+            ErrorCode::MissingChallenge,
+            "A challenge is required to verify a presentation with a CACAO",
+        ),
+        Some(ref challenge) if challenge != &cacao.p.nonce => report.push_error(
+            ErrorCode::ChallengeMismatch,
+            "CACAO nonce does not match challenge",
+        ),
+        Some(_) => {}
+    }
+    match options.domain {
+        None => report.push_error(
+            ErrorCode::MissingDomain,
+            "A domain is required to verify a presentation with a CACAO",
+        ),
+        Some(ref domain) if domain != &cacao.p.domain => {
+            report.push_error(ErrorCode::DomainMismatch, "CACAO domain does not match")
+        }
+        Some(_) => {}
+    }
+    let presentation = serde_json::to_value(vp).unwrap_or_default();
+    if !cacao.binds(&presentation) {
+        report.push_error(
+            ErrorCode::HolderBindingMismatch,
+            "CACAO resources do not list the hash of the presentation",
+        );
+    }
+    let as_of = verification_options.verify_as_of.unwrap_or_else(Utc::now);
+    match cacao.verify(as_of) {
+        Ok(()) => report.checks.push(Check::Proof),
+        Err(CacaoError::Expired) => report.push_error(ErrorCode::Expired, "CACAO expired"),
+        Err(CacaoError::NotYetValid) => {
+            report.push_error(ErrorCode::NotYetValid, "CACAO not yet valid")
+        }
+        Err(err) => report.push_error(ErrorCode::InvalidSignature, &err.to_string()),
+    }
+    report
+}
+
+async fn verify_presentation_jwt(
+    jwt: &str,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    historical: bool,
+) -> VerificationReport {
+    let signed_at = decode_jwt_unverified(jwt).and_then(|(_, claims)| issued_at(&claims));
+    let versioned = historical_resolver(resolver, historical, signed_at);
+    let resolver: &dyn DIDResolver = match versioned {
+        Some(ref versioned) => versioned,
+        None => resolver,
+    };
+    let result = VerifiablePresentation::verify_jwt(jwt, Some(options), resolver).await;
+    let mut report = VerificationReport::from(result.clone());
+    report.proofs = vec![jwt_proof_report(jwt, result)];
+    report.holder = jwt_presentation_holder(jwt);
+    report
+}
+
+/// Verifiable Presentation, or Verifiable Presentation as a JWT.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PresentationOrJWT {
+    VP(VerifiablePresentation),
+    JWT(String),
+}
+
+/// Validity period of a credential, from `issuanceDate`/`validFrom` and
+/// `expirationDate`/`validUntil`, or from JWT `nbf` and `exp` claims.
+#[derive(Debug, Clone, Default)]
+struct ValidityPeriod {
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl ValidityPeriod {
+    fn of_credential(vc: &VerifiableCredential) -> Self {
+        // Look at the date properties only, without serializing the whole credential.
+        Self::of_properties(|name: &str| -> Option<Value> {
+            match name {
+                "issuanceDate" => serde_json::to_value(&vc.issuance_date).ok(),
+                "expirationDate" => serde_json::to_value(&vc.expiration_date).ok(),
+                _ => vc.property_set.as_ref()?.get(name).cloned(),
+            }
+        })
+    }
+
+    fn of_value(credential: &Value) -> Self {
+        Self::of_properties(|name: &str| credential.get(name).cloned())
+    }
+
+    fn of_properties(property: impl Fn(&str) -> Option<Value>) -> Self {
+        let date = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| property(name))
+                .find_map(|date| DateTime::parse_from_rfc3339(date.as_str()?).ok())
+                .map(|date| date.with_timezone(&Utc))
+        };
+        Self {
+            from: date(&["validFrom", "issuanceDate"]),
+            until: date(&["validUntil", "expirationDate"]),
+        }
+    }
+
+    fn of_jwt(jwt: &str) -> Self {
+        let claims = match decode_jwt_unverified(jwt) {
+            Some((_, claims)) => claims,
+            None => return Self::default(),
+        };
+        let date = |name: &str| {
+            claims
+                .get(name)
+                .and_then(|date| date.as_i64())
+                .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+        };
+        Self {
+            from: date("nbf"),
+            until: date("exp"),
+        }
+    }
+
+    fn check(&self, options: &VerificationOptions, report: &mut VerificationReport) {
+        let now = options.verify_as_of.unwrap_or_else(Utc::now);
+        let skew = Duration::seconds(options.clock_skew.unwrap_or(0));
+        if let Some(from) = self.from {
+            if now + skew < from {
+                report.push_error(
+                    ErrorCode::NotYetValid,
+                    &format!("Credential is not valid until {}", from.to_rfc3339()),
+                );
+            }
+        }
+        match self.until {
+            Some(until) if now - skew > until => {
+                report.push_error(
+                    ErrorCode::Expired,
+                    &format!("Credential expired at {}", until.to_rfc3339()),
+                );
+            }
+            Some(_) => {}
+            None => match options.missing_expiration {
+                Some(MissingExpiration::Warn) => report.push_warning(
+                    WarningCode::MissingExpiration,
+                    "Credential has no expiration date",
+                ),
+                Some(MissingExpiration::Error) => report.push_error(
+                    ErrorCode::MissingExpiration,
+                    "Credential has no expiration date",
+                ),
+                Some(MissingExpiration::Allow) | None => {}
+            },
+        }
+    }
+}
+
+/// Credentials embedded in a presentation, as JSON-LD objects or JWT strings.
+pub(crate) fn embedded_credentials(presentation: &PresentationOrJWT) -> Vec<Value> {
+    let credentials = match presentation {
+        PresentationOrJWT::VP(vp) => serde_json::to_value(&vp.verifiable_credential).ok(),
+        PresentationOrJWT::JWT(jwt) => match securing::payload_unverified(jwt) {
+            Some(presentation) => presentation.get("verifiableCredential").cloned(),
+            None => decode_jwt_unverified(jwt)
+                .and_then(|(_, claims)| claims.pointer("/vp/verifiableCredential").cloned()),
+        },
+    };
+    match credentials {
+        Some(Value::Array(credentials)) => credentials,
+        Some(Value::Null) | None => Vec::new(),
+        Some(credential) => vec![credential],
+    }
+}
+
+fn one_or_many(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    }
+}
+
+/// Check that a credential is bound to the holder of a presentation, either by subject ID or by
+/// a JWT confirmation method matching the key that signed the presentation.
+async fn check_credential_binding(
+    credential: &Value,
+    holder: &str,
+    holder_verification_method: Option<&str>,
+    resolver: &dyn DIDResolver,
+) -> bool {
+    let (subjects, cnf) = match credential {
+        Value::String(jwt) => match decode_jwt_unverified(jwt) {
+            Some((_, claims)) => {
+                let mut subjects: Vec<String> =
+                    one_or_many(claims.pointer("/vc/credentialSubject"))
+                        .into_iter()
+                        .filter_map(node_id)
+                        .collect();
+                if let Some(sub) = claims.get("sub").and_then(|sub| sub.as_str()) {
+                    subjects.push(sub.to_string());
+                }
+                (subjects, claims.get("cnf").cloned())
+            }
+            None => return false,
+        },
+        credential => (
+            one_or_many(credential.get("credentialSubject"))
+                .into_iter()
+                .filter_map(node_id)
+                .collect(),
+            None,
+        ),
+    };
+    if subjects.iter().any(|subject| subject == holder) {
+        return true;
+    }
+    // https://datatracker.ietf.org/doc/html/rfc7800#section-3
+    let (cnf, vm) = match (cnf, holder_verification_method) {
+        (Some(cnf), Some(vm)) => (cnf, vm),
+        _ => return false,
+    };
+    if let Some(kid) = cnf.get("kid").and_then(|kid| kid.as_str()) {
+        return kid == vm || kid == holder;
+    }
+    if let Some(jwk) = cnf.get("jwk") {
+        let cnf_jwk: JWK = match serde_json::from_value(jwk.clone()) {
+            Ok(jwk) => jwk,
+            Err(_) => return false,
+        };
+        let holder_jwk = match key_cache::resolve_key(vm, resolver).await {
+            Ok(jwk) => jwk,
+            Err(_) => return false,
+        };
+        return match (cnf_jwk.thumbprint(), holder_jwk.thumbprint()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+    }
+    false
+}
+
+/// Nonce and domain of the CACAO of a presentation without a proof.
+#[cfg(feature = "format-cacao")]
+fn cacao_challenge_domain(
+    vp: &VerifiablePresentation,
+    options: &VerificationOptions,
+) -> Option<(Vec<String>, Vec<String>)> {
+    match (&vp.proof, &options.cacao) {
+        (None, Some(cacao)) => Some((vec![cacao.p.nonce.clone()], vec![cacao.p.domain.clone()])),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "format-cacao"))]
+fn cacao_challenge_domain(
+    _vp: &VerifiablePresentation,
+    _options: &VerificationOptions,
+) -> Option<(Vec<String>, Vec<String>)> {
+    None
+}
+
+/// Challenges and domains of a presentation: those of its linked data proofs (or of its CACAO),
+/// or the `nonce` and `aud` of a JWT or VC-JOSE presentation.
+fn presentation_challenges_domains(
+    presentation: &PresentationOrJWT,
+    options: &VerificationOptions,
+) -> (Vec<String>, Vec<String>) {
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        one_or_many(value)
+            .into_iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect()
+    };
+    match presentation {
+        PresentationOrJWT::VP(vp) => {
+            if let Some(found) = cacao_challenge_domain(vp, options) {
+                return found;
+            }
+            let proofs: Vec<_> = vp.proof.iter().flatten().collect();
+            let challenges = proofs.iter().filter_map(|proof| proof.challenge.clone());
+            let domains = proofs.iter().filter_map(|proof| proof.domain.clone());
+            (challenges.collect(), domains.collect())
+        }
+        PresentationOrJWT::JWT(jwt) => {
+            let claims = match securing::payload_unverified(jwt) {
+                Some(payload) => Some(payload),
+                None => decode_jwt_unverified(jwt).map(|(_header, claims)| claims),
+            };
+            let claims = claims.unwrap_or_default();
+            (strings(claims.get("nonce")), strings(claims.get("aud")))
+        }
+    }
+}
+
+/// Check the challenge and domain of a presentation against those the verification options
+/// expect: each must be present, with the expected value.
+fn check_expected_challenge_domain(
+    presentation: &PresentationOrJWT,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let (challenges, domains) = presentation_challenges_domains(presentation, options);
+    let expectations = [
+        (
+            &options.expected_challenge,
+            challenges,
+            "challenge",
+            ErrorCode::MissingChallenge,
+            ErrorCode::ChallengeMismatch,
+            PolicyCheck::ExpectedChallenge,
+        ),
+        (
+            &options.expected_domain,
+            domains,
+            "domain",
+            ErrorCode::MissingDomain,
+            ErrorCode::DomainMismatch,
+            PolicyCheck::ExpectedDomain,
+        ),
+    ];
+    for (expected, found, name, missing, mismatch, check) in expectations.iter() {
+        let expected = match expected {
+            Some(expected) => expected,
+            None => continue,
+        };
+        if found.is_empty() {
+            report.push_error(*missing, &format!("Missing {}", name));
+        } else if found.iter().any(|value| value == expected) {
+            report.policy_checks.push(*check);
+        } else {
+            let message = format!("Expected {} {}, found {}", name, expected, found.join(", "));
+            report.push_error(*mismatch, &message);
+        }
+    }
+}
+
+async fn check_holder_binding(
+    presentation: &PresentationOrJWT,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+    resolver: &dyn DIDResolver,
+) {
+    let holder = match report.holder.clone() {
+        Some(holder) => holder,
+        None => {
+            report.push_error(
+                ErrorCode::HolderBindingMismatch,
+                "Presentation has no holder",
+            );
+            return;
+        }
+    };
+    if let Some(ref expected) = options.expected_holder {
+        if expected != &holder {
+            report.push_error(
+                ErrorCode::HolderBindingMismatch,
+                &format!("Expected holder {}, found {}", expected, holder),
+            );
+            return;
+        }
+    }
+    if options.require_holder_binding {
+        let vm = report
+            .proofs
+            .iter()
+            .find(|proof| proof.errors.is_empty())
+            .and_then(|proof| proof.verification_method.clone());
+        for (i, credential) in embedded_credentials(presentation).iter().enumerate() {
+            if !check_credential_binding(credential, &holder, vm.as_deref(), resolver).await {
+                report.push_error(
+                    ErrorCode::HolderBindingMismatch,
+                    &format!("Credential {} is not bound to holder {}", i, holder),
+                );
+                return;
+            }
+        }
+    }
+    report.policy_checks.push(PolicyCheck::HolderBinding);
+}
+
+/// Verify the credentials embedded in a presentation, up to
+/// [`VerificationOptions::credential_parallelism`] at a time, adding their reports to the
+/// presentation's report. An error in any credential is an error of the presentation.
+async fn verify_embedded_credentials(
+    presentation: &PresentationOrJWT,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+    resolver: &dyn DIDResolver,
+) {
+    let credential_options = JWTOrLDPOptions {
+        verification_options: VerificationOptions {
+            verify_credentials: false,
+            ..options.clone()
+        },
+        ..Default::default()
+    };
+    let parallelism = options
+        .credential_parallelism
+        .unwrap_or(DEFAULT_CREDENTIAL_PARALLELISM)
+        .max(1);
+    let credential_options = &credential_options;
+    let credentials: Vec<Result<CredentialOrJWT, String>> = match presentation {
+        PresentationOrJWT::VP(vp) => match vp.verifiable_credential {
+            Some(ref credentials) => credentials.into_iter().cloned().map(Ok).collect(),
+            None => Vec::new(),
+        },
+        PresentationOrJWT::JWT(_) => embedded_credentials(presentation)
+            .into_iter()
+            .map(|credential| serde_json::from_value(credential).map_err(|e| e.to_string()))
+            .collect(),
+    };
+    let reports: Vec<VerificationReport> = stream::iter(credentials)
+        .map(|credential| async move {
+            let credential = match credential {
+                Ok(credential) => credential,
+                Err(err) => return VerificationReport::error(ErrorCode::InvalidDocument, &err),
+            };
+            verify_credential(&credential, credential_options, resolver)
+                .await
+                .unwrap_or_else(|err| {
+                    VerificationReport::error(ErrorCode::InvalidDocument, &err.to_string())
+                })
+        })
+        .buffered(parallelism)
+        .collect()
+        .await;
+    for (i, credential_report) in reports.iter().enumerate() {
+        for error in credential_report.errors.iter() {
+            report.push_error(error.code, &format!("Credential {}: {}", i, error.message));
+        }
+    }
+    report.credentials = reports;
+}
+
+/// Build a failed report without verifying proofs, if the issuer or holder is not allowed by the
+/// verification options. This avoids resolving DIDs of disallowed methods.
+fn check_policy(
+    id: Option<String>,
+    options: &VerificationOptions,
+    issuer: bool,
+    started: DateTime<Utc>,
+) -> Option<VerificationReport> {
+    let did = id.as_deref().unwrap_or_default();
+    let error = options.check_did_method(did).err()?;
+    let mut report = VerificationReport::new();
+    report.errors.push(error);
+    if issuer {
+        report.issuer = id;
+    } else {
+        report.holder = id;
+    }
+    report.timing = Some(Timing::since(started));
+    Some(report)
+}
+
+async fn verify_secured_credential(
+    secured: &str,
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<VerificationReport, Error> {
+    let mut report = verify_secured(secured, DocumentKind::Credential, options, resolver).await?;
+    if let Some(credential) = securing::payload_unverified(secured) {
+        ValidityPeriod::of_value(&credential).check(&options.verification_options, &mut report);
+    }
+    Ok(report)
+}
+
+/// Check the Data Integrity `expires` property of linked data proofs, at the verification time
+/// of the options.
+fn check_proof_expiration<'a>(
+    proofs: impl IntoIterator<Item = &'a ssi::vc::Proof>,
+    options: &VerificationOptions,
+    report: &mut VerificationReport,
+) {
+    let now = options.verify_as_of.unwrap_or_else(Utc::now);
+    let skew = Duration::seconds(options.clock_skew.unwrap_or(0));
+    for proof in proofs {
+        let proof = match serde_json::to_value(proof) {
+            Ok(proof) => proof,
+            Err(_) => continue,
+        };
+        let expires = match proof.get("expires").and_then(Value::as_str) {
+            Some(expires) => expires,
+            None => continue,
+        };
+        match DateTime::parse_from_rfc3339(expires) {
+            Ok(date) if now - skew > date => report.push_error(
+                ErrorCode::Expired,
+                &format!("Proof expired at {}", date.to_rfc3339()),
+            ),
+            Ok(_) => {}
+            Err(_) => report.push_error(
+                ErrorCode::Other,
+                &format!("Invalid proof expiration: {}", expires),
+            ),
+        }
+    }
+}
+
+/// Check that the verification methods of the verified proofs were not expired or revoked when
+/// the proofs were made (see [`key_validity`]). A proof made with an expired or revoked
+/// verification method fails; the verification fails if no other proof verified.
+async fn check_key_validity(
+    report: &mut VerificationReport,
+    options: &VerificationOptions,
+    resolver: &dyn DIDResolver,
+) {
+    let now = options.verify_as_of.unwrap_or_else(Utc::now);
+    let skew = Duration::seconds(options.clock_skew.unwrap_or(0));
+    let mut errors = Vec::new();
+    let mut verified = false;
+    for proof in report.proofs.iter_mut() {
+        if !proof.errors.is_empty() {
+            continue;
+        }
+        let verification_method = match proof.verification_method {
+            Some(ref verification_method) => verification_method,
+            None => {
+                verified = true;
+                continue;
+            }
+        };
+        let signed_at = proof
+            .created
+            .as_deref()
+            .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+            .map(|created| created.with_timezone(&Utc))
+            .unwrap_or(now);
+        match key_validity::check_verification_method(
+            verification_method,
+            signed_at - skew,
+            resolver,
+        )
+        .await
+        {
+            Ok(()) => verified = true,
+            Err(err) => {
+                let code = match err {
+                    KeyValidityError::Expired(..) => ErrorCode::VerificationMethodExpired,
+                    KeyValidityError::Revoked(..) => ErrorCode::VerificationMethodRevoked,
+                    KeyValidityError::InvalidDate(..) => ErrorCode::VerificationMethodMismatch,
+                };
+                let error = VerificationError {
+                    code,
+                    message: err.to_string(),
+                };
+                proof.errors.push(error.clone());
+                errors.push(error);
+            }
+        }
+    }
+    if !verified {
+        for error in errors {
+            report.push_error(error.code, &error.message);
+        }
+    }
+}
+
+/// Verify a credential, in any proof format.
+///
+/// If `options.proof_format` is set, it must match the form of the credential.
+pub async fn verify_credential(
+    credential: &CredentialOrJWT,
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<VerificationReport, Error> {
+    let started = Utc::now();
+    let recorder = RecordingResolver::new(resolver);
+    let resolver: &dyn DIDResolver = &recorder;
+    let issuer = match credential {
+        CredentialOrJWT::Credential(vc) => credential_issuer(vc),
+        CredentialOrJWT::JWT(jwt) => jwt_credential_issuer(jwt),
+    };
+    if let Some(report) = check_policy(issuer, &options.verification_options, true, started) {
+        return Ok(observed(report, false));
+    }
+    let ldp_options = options.ldp_options.clone();
+    let historical = options.verification_options.historical_resolution;
+    let mut report = match (&options.proof_format, credential) {
+        (Some(ProofFormat::LDP), CredentialOrJWT::Credential(vc))
+        | (None, CredentialOrJWT::Credential(vc)) => {
+            let mut report = verify_credential_ldp(vc, ldp_options, resolver, historical).await;
+            ValidityPeriod::of_credential(vc).check(&options.verification_options, &mut report);
+            check_proof_expiration(
+                vc.proof.iter().flatten(),
+                &options.verification_options,
+                &mut report,
+            );
+            report
+        }
+        (Some(ProofFormat::VcJose), CredentialOrJWT::JWT(secured))
+        | (Some(ProofFormat::VcCose), CredentialOrJWT::JWT(secured)) => {
+            verify_secured_credential(secured, options, resolver).await?
+        }
+        (None, CredentialOrJWT::JWT(secured)) if securing::is_secured(secured) => {
+            verify_secured_credential(secured, options, resolver).await?
+        }
+        #[cfg(feature = "format-jwp")]
+        (Some(ProofFormat::JPT), CredentialOrJWT::JWT(jpt)) => {
+            verify_jpt(jpt, DocumentKind::Credential, options, resolver).await
+        }
+        #[cfg(feature = "format-jwp")]
+        (None, CredentialOrJWT::JWT(jpt)) if crate::jwp::is_jpt(jpt) => {
+            verify_jpt(jpt, DocumentKind::Credential, options, resolver).await
+        }
+        (Some(ProofFormat::JWT), CredentialOrJWT::JWT(jwt)) | (None, CredentialOrJWT::JWT(jwt)) => {
+            let mut report = verify_credential_jwt(jwt, ldp_options, resolver, historical).await;
+            ValidityPeriod::of_jwt(jwt).check(&options.verification_options, &mut report);
+            if let Some(ref jwt_claims) = options.jwt_claims {
+                check_jwt_claims(jwt, jwt_claims, &mut report);
+            }
+            report
+        }
+        (Some(proof_format), _) => {
+            return Err(Error::ProofFormatMismatch(proof_format.to_string()));
+        }
+    };
+    let verification_options = &options.verification_options;
+    if let Some(ref trusted) = verification_options.trusted_certificates {
+        if let CredentialOrJWT::JWT(jwt) = credential {
+            check_issuer_certificate(jwt, trusted, verification_options, &mut report).await;
+        }
+    }
+    check_key_validity(&mut report, verification_options, resolver).await;
+    if verification_options.check_context_integrity
+        || verification_options.check_related_resources
+        || verification_options.check_credential_schema
+        || verification_options.check_credential_types
+        || verification_options.expected_subject.is_some()
+    {
+        let (document, sub) = match credential {
+            CredentialOrJWT::Credential(vc) => (serde_json::to_value(vc).ok(), None),
+            CredentialOrJWT::JWT(jwt) => (
+                jwt_document_unverified(jwt, "vc"),
+                jwt_document_unverified(jwt, "sub"),
+            ),
+        };
+        if verification_options.check_context_integrity {
+            check_context_integrity(document.as_ref(), verification_options, &mut report).await;
+        }
+        if verification_options.check_related_resources {
+            check_related_resources(document.as_ref(), verification_options, &mut report).await;
+        }
+        if verification_options.check_credential_schema {
+            check_credential_schema(document.as_ref(), verification_options, &mut report).await;
+        }
+        if verification_options.check_credential_types {
+            check_credential_types(document.as_ref(), &mut report);
+        }
+        if let Some(ref expected_subject) = verification_options.expected_subject {
+            let sub = sub.as_ref().and_then(Value::as_str);
+            check_expected_subject(document.as_ref(), sub, expected_subject, &mut report);
+        }
+    }
+    report.resolutions = recorder.records();
+    report.timing = Some(Timing::since(started));
+    Ok(observed(report, false))
+}
+
+/// Report a verification to the [observers](crate::observe).
+fn observed(report: VerificationReport, presentation: bool) -> VerificationReport {
+    observe::emit(|| Event::Verified {
+        presentation,
+        report: &report,
+    });
+    report
+}
+
+/// Verify a presentation, in any proof format.
+///
+/// If `options.proof_format` is set, it must match the form of the presentation.
+pub async fn verify_presentation(
+    presentation: &PresentationOrJWT,
+    options: &JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+) -> Result<VerificationReport, Error> {
+    let started = Utc::now();
+    let recorder = RecordingResolver::new(resolver);
+    let resolver: &dyn DIDResolver = &recorder;
+    let holder = match presentation {
+        PresentationOrJWT::VP(vp) => vp.holder.as_ref().map(|holder| holder.to_string()),
+        PresentationOrJWT::JWT(jwt) => jwt_presentation_holder(jwt),
+    };
+    if let Some(report) = check_policy(holder, &options.verification_options, false, started) {
+        return Ok(observed(report, true));
+    }
+    let ldp_options = options.ldp_options.clone();
+    let historical = options.verification_options.historical_resolution;
+    let mut report = match (&options.proof_format, presentation) {
+        (Some(ProofFormat::LDP), PresentationOrJWT::VP(vp)) | (None, PresentationOrJWT::VP(vp)) => {
+            #[cfg(feature = "format-cacao")]
+            let mut report = match options.verification_options.cacao {
+                Some(ref cacao) if vp.proof.is_none() => verify_presentation_cacao(
+                    vp,
+                    cacao,
+                    &ldp_options,
+                    &options.verification_options,
+                ),
+                _ => verify_presentation_ldp(vp, ldp_options, resolver, historical).await,
+            };
+            #[cfg(not(feature = "format-cacao"))]
+            let mut report = verify_presentation_ldp(vp, ldp_options, resolver, historical).await;
+            check_proof_expiration(
+                vp.proof.iter().flatten(),
+                &options.verification_options,
+                &mut report,
+            );
+            report
+        }
+        (Some(ProofFormat::VcJose), PresentationOrJWT::JWT(secured))
+        | (Some(ProofFormat::VcCose), PresentationOrJWT::JWT(secured)) => {
+            verify_secured(secured, DocumentKind::Presentation, options, resolver).await?
+        }
+        (None, PresentationOrJWT::JWT(secured)) if securing::is_secured(secured) => {
+            verify_secured(secured, DocumentKind::Presentation, options, resolver).await?
+        }
+        #[cfg(feature = "format-jwp")]
+        (Some(ProofFormat::JPT), PresentationOrJWT::JWT(jpt)) => {
+            verify_jpt(jpt, DocumentKind::Presentation, options, resolver).await
+        }
+        #[cfg(feature = "format-jwp")]
+        (None, PresentationOrJWT::JWT(jpt)) if crate::jwp::is_jpt(jpt) => {
+            verify_jpt(jpt, DocumentKind::Presentation, options, resolver).await
+        }
+        (Some(ProofFormat::JWT), PresentationOrJWT::JWT(jwt))
+        | (None, PresentationOrJWT::JWT(jwt)) => {
+            let mut report = verify_presentation_jwt(jwt, ldp_options, resolver, historical).await;
+            // Presentations commonly have no expiration, so only check the bounds that are set.
+            let options = VerificationOptions {
+                missing_expiration: None,
+                ..options.verification_options.clone()
+            };
+            ValidityPeriod::of_jwt(jwt).check(&options, &mut report);
+            report
+        }
+        (Some(proof_format), _) => {
+            return Err(Error::ProofFormatMismatch(proof_format.to_string()));
+        }
+    };
+    let verification_options = &options.verification_options;
+    check_key_validity(&mut report, verification_options, resolver).await;
+    if verification_options.check_context_integrity || verification_options.check_related_resources
+    {
+        let document = match presentation {
+            PresentationOrJWT::VP(vp) => serde_json::to_value(vp).ok(),
+            PresentationOrJWT::JWT(jwt) => jwt_document_unverified(jwt, "vp"),
+        };
+        if verification_options.check_context_integrity {
+            check_context_integrity(document.as_ref(), verification_options, &mut report).await;
+        }
+        if verification_options.check_related_resources {
+            check_related_resources(document.as_ref(), verification_options, &mut report).await;
+        }
+    }
+    if verification_options.expected_challenge.is_some()
+        || verification_options.expected_domain.is_some()
+    {
+        check_expected_challenge_domain(presentation, verification_options, &mut report);
+    }
+    if verification_options.require_holder_binding || verification_options.expected_holder.is_some()
+    {
+        check_holder_binding(presentation, verification_options, &mut report, resolver).await;
+    }
+    if verification_options.verify_credentials {
+        verify_embedded_credentials(presentation, verification_options, &mut report, resolver)
+            .await;
+    }
+    report.resolutions = recorder.records();
+    report.timing = Some(Timing::since(started));
+    Ok(observed(report, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "format-jwp")]
+    #[test]
+    fn verify_jpt_credential_and_presentation() {
+        use crate::jwp::{self, JsonWebProof, PresentationHeader};
+        use crate::{
+            Document, DocumentMetadata, IssueOptions, ResolutionInputMetadata, ResolutionMetadata,
+        };
+        use serde_json::json;
+
+        struct Issuer(Document);
+
+        #[async_trait::async_trait]
+        impl DIDResolver for Issuer {
+            async fn resolve(
+                &self,
+                did: &str,
+                _input_metadata: &ResolutionInputMetadata,
+            ) -> (
+                ResolutionMetadata,
+                Option<Document>,
+                Option<DocumentMetadata>,
+            ) {
+                if did != self.0.id {
+                    return (ResolutionMetadata::from_error("notFound"), None, None);
+                }
+                (
+                    ResolutionMetadata::default(),
+                    Some(self.0.clone()),
+                    Some(DocumentMetadata::default()),
+                )
+            }
+        }
+
+        let key = jwp::generate_bbs_key().unwrap();
+        let did = "did:example:bbs-issuer";
+        let verification_method = format!("{}#bbs", did);
+        let resolver = Issuer(
+            serde_json::from_value(json!({
+                "@context": "https://www.w3.org/ns/did/v1",
+                "id": did,
+                "verificationMethod": [{
+                    "id": verification_method,
+                    "type": "JsonWebKey2020",
+                    "controller": did,
+                    "publicKeyJwk": key.to_public(),
+                }],
+                "assertionMethod": [verification_method],
+            }))
+            .unwrap(),
+        );
+        let credential: VerifiableCredential = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": did,
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject"},
+        }))
+        .unwrap();
+        let options = IssueOptions {
+            proof_format: ProofFormat::JPT,
+            ldp_options: LinkedDataProofOptions {
+                verification_method: Some(ssi::vc::URI::String(verification_method)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rt = crate::runtime::get().unwrap();
+        let jpt = match rt
+            .block_on(crate::issue_credential(
+                credential, &options, &key, &resolver,
+            ))
+            .unwrap()
+        {
+            CredentialOrJWT::JWT(jpt) => jpt,
+            CredentialOrJWT::Credential(_) => panic!("Expected a JSON Proof Token"),
+        };
+        let report = rt
+            .block_on(verify_credential(
+                &CredentialOrJWT::JWT(jpt.clone()),
+                &JWTOrLDPOptions::default(),
+                &resolver,
+            ))
+            .unwrap();
+        assert!(report.is_success(), "{:?}", report.errors);
+        assert_eq!(report.issuer.as_deref(), Some(did));
+        assert_eq!(report.proofs[0].proof_format, Some(ProofFormat::JPT));
+        // The issuer's DID resolution is recorded once.
+        assert_eq!(report.resolutions.len(), 1);
+        assert_eq!(report.resolutions[0].did, did);
+
+        let issued = JsonWebProof::from_compact(&jpt).unwrap();
+        let presentation_header = PresentationHeader {
+            nonce: Some("n-0S6".to_string()),
+            ..Default::default()
+        };
+        let presented = jwp::present(
+            &issued,
+            &["issuer", "type"],
+            presentation_header,
+            &jwp::Bbs,
+            &key.to_public(),
+        )
+        .unwrap()
+        .to_compact()
+        .unwrap();
+        let options = |challenge: &str| JWTOrLDPOptions {
+            ldp_options: LinkedDataProofOptions {
+                challenge: Some(challenge.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let presentation = PresentationOrJWT::JWT(presented);
+        let report = rt
+            .block_on(verify_presentation(
+                &presentation,
+                &options("n-0S6"),
+                &resolver,
+            ))
+            .unwrap();
+        assert!(report.is_success(), "{:?}", report.errors);
+        let report = rt
+            .block_on(verify_presentation(
+                &presentation,
+                &options("other"),
+                &resolver,
+            ))
+            .unwrap();
+        assert!(report
+            .errors
+            .iter()
+            .any(|error| error.code == ErrorCode::ChallengeMismatch));
+        // An issued JPT is not a presentation.
+        let report = rt
+            .block_on(verify_presentation(
+                &PresentationOrJWT::JWT(jpt),
+                &options("n-0S6"),
+                &resolver,
+            ))
+            .unwrap();
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn classify_errors() {
+        assert_eq!(
+            ErrorCode::classify("No applicable proof"),
+            ErrorCode::NoApplicableProof
+        );
+        assert_eq!(
+            ErrorCode::classify("Invalid signature"),
+            ErrorCode::InvalidSignature
+        );
+        assert_eq!(ErrorCode::classify("Something else"), ErrorCode::Other);
+    }
+
+    #[test]
+    fn error_codes_of_ssi_errors() {
+        use ssi::error::Error as SSIError;
+        assert_eq!(
+            ErrorCode::from(&SSIError::InvalidSignature),
+            ErrorCode::InvalidSignature
+        );
+        assert_eq!(
+            ErrorCode::from(&SSIError::KeyMismatch),
+            ErrorCode::VerificationMethodMismatch
+        );
+        assert_eq!(
+            ErrorCode::from(&SSIError::ResourceNotFound("did:example:foo".to_string())),
+            ErrorCode::ResolutionFailed
+        );
+        // Messages of ssi errors map like the errors, whatever their wording.
+        for err in &[
+            SSIError::InvalidSignature,
+            SSIError::MissingProof,
+            SSIError::KeyMismatch,
+            SSIError::InvalidJWS,
+        ] {
+            assert_eq!(
+                ErrorCode::from_message(&err.to_string()),
+                ErrorCode::from(err)
+            );
+        }
+        assert_eq!(
+            ErrorCode::from_message(NO_APPLICABLE_PROOF),
+            ErrorCode::NoApplicableProof
+        );
+        assert_eq!(
+            ErrorCode::from_message("Invalid signature length"),
+            ErrorCode::classify("Invalid signature length")
+        );
+    }
+
+    #[test]
+    fn allowed_did_methods() {
+        let options = VerificationOptions {
+            allowed_did_methods: Some(vec!["key".to_string(), "web".to_string()]),
+            ..Default::default()
+        };
+        assert!(options.check_did_method("did:key:z6Mk").is_ok());
+        let err = options.check_did_method("did:example:foo").unwrap_err();
+        assert_eq!(err.code, ErrorCode::DisallowedDIDMethod);
+        assert!(options.check_did_method("https://example.org/").is_err());
+        assert!(VerificationOptions::default()
+            .check_did_method("did:example:foo")
+            .is_ok());
+    }
+
+    #[test]
+    fn proof_expiration() {
+        let proof: ssi::vc::Proof = serde_json::from_value(serde_json::json!({
+            "type": "Ed25519Signature2020",
+            "expires": "2020-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        let mut report = VerificationReport::new();
+        check_proof_expiration(vec![&proof], &VerificationOptions::default(), &mut report);
+        assert!(report.has_error(ErrorCode::Expired));
+
+        let options = VerificationOptions {
+            verify_as_of: Some(Utc.timestamp_opt(1_500_000_000, 0).unwrap()),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        check_proof_expiration(vec![&proof], &options, &mut report);
+        assert!(report.is_success());
+    }
+
+    #[test]
+    fn validity_period() {
+        let now = Utc::now();
+        let period = ValidityPeriod {
+            from: Some(now - Duration::days(2)),
+            until: Some(now - Duration::days(1)),
+        };
+        let mut report = VerificationReport::new();
+        period.check(&VerificationOptions::default(), &mut report);
+        assert!(report.has_error(ErrorCode::Expired));
+
+        let options = VerificationOptions {
+            verify_as_of: Some(now - Duration::hours(36)),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        period.check(&options, &mut report);
+        assert!(report.is_success());
+
+        let options = VerificationOptions {
+            clock_skew: Some(60),
+            missing_expiration: Some(MissingExpiration::Warn),
+            ..Default::default()
+        };
+        let period = ValidityPeriod {
+            from: Some(now + Duration::seconds(30)),
+            until: None,
+        };
+        let mut report = VerificationReport::new();
+        period.check(&options, &mut report);
+        assert!(report.is_success());
+        assert_eq!(report.warnings[0].code, WarningCode::MissingExpiration);
+    }
+
+    #[test]
+    fn credential_binding() {
+        let resolver = crate::DID_METHODS.to_resolver();
+        let credential = serde_json::json!({
+            "credentialSubject": [{"id": "did:example:bob"}, {"id": "did:example:alice"}]
+        });
+        let rt = crate::runtime::get().unwrap();
+        assert!(rt.block_on(check_credential_binding(
+            &credential,
+            "did:example:alice",
+            None,
+            resolver
+        )));
+        assert!(!rt.block_on(check_credential_binding(
+            &credential,
+            "did:example:eve",
+            None,
+            resolver
+        )));
+    }
+
+    #[test]
+    fn expected_subject() {
+        let credential = serde_json::json!({
+            "credentialSubject": [{"id": "did:example:bob"}, {"id": "did:example:alice"}]
+        });
+        let mut report = VerificationReport::new();
+        check_expected_subject(Some(&credential), None, "did:example:alice", &mut report);
+        assert_eq!(report.policy_checks, vec![PolicyCheck::ExpectedSubject]);
+        check_expected_subject(Some(&credential), None, "did:example:eve", &mut report);
+        assert!(report.has_error(ErrorCode::SubjectMismatch));
+    }
+
+    #[test]
+    fn lossy_conversion() {
+        let mut report = VerificationReport::error(ErrorCode::Expired, "Credential is expired");
+        report.push_warning(WarningCode::Other, "Some warning");
+        report.proofs.push(ProofReport::default());
+        let result = VerificationResult::from(report);
+        assert_eq!(result.errors, vec!["Credential is expired".to_string()]);
+        assert_eq!(result.warnings, vec!["Some warning".to_string()]);
+    }
+
+    #[test]
+    fn credential_validity_period() {
+        let vc: VerifiableCredential = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": {"id": "did:example:issuer", "name": "Example"},
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "validUntil": "2022-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject"}
+        }))
+        .unwrap();
+        let period = ValidityPeriod::of_credential(&vc);
+        assert_eq!(
+            period.from.unwrap().to_rfc3339(),
+            "2021-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            period.until.unwrap().to_rfc3339(),
+            "2022-01-01T00:00:00+00:00"
+        );
+        assert_eq!(credential_issuer(&vc).unwrap(), "did:example:issuer");
+    }
+
+    #[test]
+    fn embedded_credential_reports() {
+        let vp: VerifiablePresentation = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": ["a.b.c", "d.e.f", "g.h.i"]
+        }))
+        .unwrap();
+        let options = VerificationOptions {
+            verify_credentials: true,
+            credential_parallelism: Some(2),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(verify_embedded_credentials(
+            &PresentationOrJWT::VP(vp),
+            &options,
+            &mut report,
+            crate::DID_METHODS.to_resolver(),
+        ));
+        assert_eq!(report.credentials.len(), 3);
+        assert!(report
+            .credentials
+            .iter()
+            .all(|credential| !credential.is_success()));
+        assert!(report
+            .errors
+            .iter()
+            .any(|error| error.message.starts_with("Credential 2: ")));
+    }
+    #[test]
+    fn certificate_chain_required() {
+        // {"alg":"EdDSA"}, without x5c or x5u
+        let jwt = "eyJhbGciOiJFZERTQSJ9.e30.c2ln";
+        let options = VerificationOptions {
+            trusted_certificates: Some(Vec::new()),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        let rt = crate::runtime::get().unwrap();
+        rt.block_on(check_certificate_chain(jwt, &[], &options, &mut report));
+        assert!(report.has_error(ErrorCode::UntrustedCertificate));
+        assert!(report.issuer_certificate.is_none());
+        assert!(report.policy_checks.is_empty());
+    }
+
+    #[cfg(feature = "did-key")]
+    #[test]
+    fn secured_certificate_chain_required() {
+        use crate::{get_verification_method, Source, DID_METHODS};
+        use serde_json::json;
+
+        let key = JWK::generate_ed25519().unwrap();
+        let did = DID_METHODS
+            .generate(&Source::KeyAndPattern(&key, "key"))
+            .unwrap();
+        let resolver = DID_METHODS.to_resolver();
+        let rt = crate::runtime::get().unwrap();
+        let verification_method = rt
+            .block_on(get_verification_method(&did, resolver))
+            .unwrap();
+        let credential = json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": did,
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": { "id": "did:example:subject" }
+        });
+        let ldp_options = LinkedDataProofOptions {
+            verification_method: Some(ssi::vc::URI::String(verification_method)),
+            ..Default::default()
+        };
+        let trusted = JWTOrLDPOptions {
+            verification_options: VerificationOptions {
+                trusted_certificates: Some(Vec::new()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for proof_format in &[ProofFormat::VcJose, ProofFormat::VcCose] {
+            let secured = rt
+                .block_on(securing::secure(
+                    &credential,
+                    DocumentKind::Credential,
+                    proof_format,
+                    &ldp_options,
+                    &key,
+                ))
+                .unwrap();
+            let secured = CredentialOrJWT::JWT(secured);
+            let report = rt
+                .block_on(verify_credential(
+                    &secured,
+                    &JWTOrLDPOptions::default(),
+                    resolver,
+                ))
+                .unwrap();
+            assert!(report.is_success(), "{:?}", report.errors);
+            // Without a certificate chain, the issuer is not anchored to a trusted certificate.
+            let report = rt
+                .block_on(verify_credential(&secured, &trusted, resolver))
+                .unwrap();
+            assert!(report.has_error(ErrorCode::UntrustedCertificate));
+            assert!(report.issuer_certificate.is_none());
+        }
+    }
+
+    #[cfg(feature = "format-cacao")]
+    #[test]
+    fn cacao_bound_to_presentation() {
+        use crate::cacao::{key_address, presentation_resource, SiweMessage};
+        use serde_json::json;
+
+        let key: JWK = serde_json::from_value(json!({
+            "kty": "EC",
+            "crv": "secp256k1",
+            "x": "yclqMZ0MtyVkKm1eBh2AyaUtsqT0l5RJM3g4SzRT96A",
+            "y": "yQzUwKnftWCJPGs-faGaHiYi1sxA6fGJVw2Px_LCNe8",
+            "d": "meTmccmR_6ZsOa2YuTTkKkJ4ZPYsKdAH1Wx_RRf2j_E"
+        }))
+        .unwrap();
+        let address = key_address(&key).unwrap();
+        let vp: VerifiablePresentation = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiablePresentation"],
+            "holder": format!("did:pkh:eip155:1:{}", address),
+            "verifiableCredential": "a.b.c"
+        }))
+        .unwrap();
+        let message = SiweMessage {
+            domain: "verifier.example.org".to_string(),
+            address,
+            statement: None,
+            uri: "https://verifier.example.org/present".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            nonce: "32891756".to_string(),
+            issued_at: "2021-09-30T16:25:24Z".to_string(),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: vec![presentation_resource(&serde_json::to_value(&vp).unwrap())],
+        };
+        let cacao = Cacao::sign(&message, &key).unwrap();
+        let options = LinkedDataProofOptions {
+            challenge: Some("32891756".to_string()),
+            domain: Some("verifier.example.org".to_string()),
+            ..Default::default()
+        };
+        let verification_options = VerificationOptions::default();
+        let report = verify_presentation_cacao(&vp, &cacao, &options, &verification_options);
+        assert!(report.is_success(), "{:?}", report.errors);
+
+        // The CACAO does not vouch for other credentials under the same holder.
+        let mut swapped = vp.clone();
+        swapped.verifiable_credential = serde_json::from_value(json!("d.e.f")).ok();
+        let report = verify_presentation_cacao(&swapped, &cacao, &options, &verification_options);
+        assert!(report.has_error(ErrorCode::HolderBindingMismatch));
+
+        // Without a challenge and a domain, a captured CACAO could be replayed.
+        let report = verify_presentation_cacao(
+            &vp,
+            &cacao,
+            &LinkedDataProofOptions::default(),
+            &verification_options,
+        );
+        assert!(report.has_error(ErrorCode::MissingChallenge));
+        assert!(report.has_error(ErrorCode::MissingDomain));
+    }
+
+    #[test]
+    fn expected_challenge_domain() {
+        let vp: VerifiablePresentation = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiablePresentation"],
+            "proof": {
+                "type": "Ed25519Signature2018",
+                "proofPurpose": "authentication",
+                "verificationMethod": "did:example:holder#key",
+                "challenge": "e1b35ae0",
+                "jws": "..."
+            }
+        }))
+        .unwrap();
+        let presentation = PresentationOrJWT::VP(vp);
+        let options = VerificationOptions {
+            expected_challenge: Some("e1b35ae0".to_string()),
+            expected_domain: Some("example.org".to_string()),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        check_expected_challenge_domain(&presentation, &options, &mut report);
+        assert_eq!(report.policy_checks, vec![PolicyCheck::ExpectedChallenge]);
+        assert!(report.has_error(ErrorCode::MissingDomain));
+        assert!(!report.has_error(ErrorCode::DomainMismatch));
+
+        let options = VerificationOptions {
+            expected_challenge: Some("other".to_string()),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        check_expected_challenge_domain(&presentation, &options, &mut report);
+        assert!(report.has_error(ErrorCode::ChallengeMismatch));
+
+        // {"alg":"EdDSA"}.{"nonce":"e1b35ae0","aud":"example.org"}
+        let jwt = PresentationOrJWT::JWT(
+            "eyJhbGciOiJFZERTQSJ9.eyJub25jZSI6ImUxYjM1YWUwIiwiYXVkIjoiZXhhbXBsZS5vcmcifQ.c2ln"
+                .to_string(),
+        );
+        let options = VerificationOptions {
+            expected_challenge: Some("e1b35ae0".to_string()),
+            expected_domain: Some("example.org".to_string()),
+            ..Default::default()
+        };
+        let mut report = VerificationReport::new();
+        check_expected_challenge_domain(&jwt, &options, &mut report);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.policy_checks.len(), 2);
+    }
+}
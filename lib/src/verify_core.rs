@@ -0,0 +1,225 @@
+//! Verification profile for embedded verifiers, such as kiosks and access control readers.
+//!
+//! The `verify-core` feature adds synchronous functions verifying credentials and presentations
+//! signed with Ed25519 or P-256 keys, as JWTs or with Data Integrity (linked data) proofs. They do
+//! no I/O: DIDs are resolved from `did:key` or from DID documents provisioned in an
+//! [`OfflineResolver`], and JSON-LD contexts are only those built into `ssi`, which never loads
+//! contexts remotely. No async runtime is needed; the verification futures are polled on the
+//! calling thread, and fail with [`ErrorCode::ResolutionFailed`] if they would block.
+//!
+//! A minimal build of the library for this profile disables the default features, keeping P-256
+//! (Ed25519 also needs `ring` or `suite-ed25519`):
+//!
+//! ```toml
+//! didkit = { version = "0.2", default-features = false, features = ["verify-core", "p256"] }
+//! ```
+//!
+//! The functions of the profile neither start a runtime nor spawn threads, but the build is not
+//! free of them: `ssi` requires `std`, and the library still depends on Tokio, with its
+//! multi-threaded runtime, and on the JNI bindings, whatever its features.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use ssi::one_or_many::OneOrMany;
+use ssi::vc::Proof;
+
+use crate::error::Error;
+use crate::verification::{ErrorCode, VerificationReport};
+use crate::{
+    CredentialOrJWT, DIDMethod, DIDResolver, Document, DocumentMetadata, JWTOrLDPOptions,
+    PresentationOrJWT, ResolutionInputMetadata, ResolutionMetadata,
+};
+
+/// Linked data proof types that can be verified with Ed25519 or P-256 keys.
+pub const PROOF_TYPES: &[&str] = &[
+    "Ed25519Signature2018",
+    "Ed25519Signature2020",
+    "EcdsaSecp256r1Signature2019",
+    "JsonWebSignature2020",
+];
+
+/// JWS algorithms of the profile.
+pub const JWT_ALGORITHMS: &[&str] = &["EdDSA", "ES256"];
+
+/// Resolver of `did:key` DIDs and of provisioned DID documents, without I/O.
+#[derive(Debug, Clone, Default)]
+pub struct OfflineResolver {
+    documents: HashMap<String, Document>,
+}
+
+impl OfflineResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a DID document, e.g. of a `did:web` issuer, to resolve by its `id`.
+    pub fn insert(&mut self, document: Document) {
+        self.documents.insert(document.id.clone(), document);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for OfflineResolver {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        if let Some(document) = self.documents.get(did) {
+            return (
+                ResolutionMetadata::default(),
+                Some(document.clone()),
+                Some(DocumentMetadata::default()),
+            );
+        }
+        did_method_key::DIDKey
+            .to_resolver()
+            .resolve(did, input_metadata)
+            .await
+    }
+}
+
+struct Flag(AtomicBool);
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Run a future that does no I/O to completion, on the current thread.
+///
+/// Returns `None` if the future is pending without having woken itself, i.e. it is waiting for
+/// an event that will not happen without an async runtime.
+fn block_on_ready<F: Future>(future: F) -> Option<F::Output> {
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut context) {
+            return Some(output);
+        }
+        if !flag.0.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JWSHeader {
+    alg: String,
+}
+
+fn check_jwt(jwt: &str) -> Result<(), String> {
+    let header = jwt.split('.').next().unwrap_or_default();
+    let header = base64::decode_config(header, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| format!("Unable to decode JWT header: {}", e))?;
+    let header: JWSHeader = serde_json::from_slice(&header)
+        .map_err(|e| format!("Unable to parse JWT header: {}", e))?;
+    if JWT_ALGORITHMS.contains(&header.alg.as_str()) {
+        Ok(())
+    } else {
+        Err(format!("JWT algorithm not supported: {}", header.alg))
+    }
+}
+
+fn check_proofs(proofs: &Option<OneOrMany<Proof>>) -> Result<(), String> {
+    for proof in proofs.iter().flatten() {
+        if !PROOF_TYPES.contains(&proof.type_.as_str()) {
+            return Err(format!("Proof type not supported: {}", proof.type_));
+        }
+    }
+    Ok(())
+}
+
+fn run<F>(profile: Result<(), String>, verification: F) -> Result<VerificationReport, Error>
+where
+    F: Future<Output = Result<VerificationReport, Error>>,
+{
+    if let Err(message) = profile {
+        return Ok(VerificationReport::error(
+            ErrorCode::NoApplicableProof,
+            &message,
+        ));
+    }
+    block_on_ready(verification).unwrap_or_else(|| {
+        Ok(VerificationReport::error(
+            ErrorCode::ResolutionFailed,
+            "Verification would block",
+        ))
+    })
+}
+
+/// Verify a credential with the profile's proof types, without I/O.
+pub fn verify_credential(
+    credential: &CredentialOrJWT,
+    options: &JWTOrLDPOptions,
+    resolver: &OfflineResolver,
+) -> Result<VerificationReport, Error> {
+    let profile = match credential {
+        CredentialOrJWT::Credential(vc) => check_proofs(&vc.proof),
+        CredentialOrJWT::JWT(jwt) => check_jwt(jwt),
+    };
+    run(
+        profile,
+        crate::verify_credential(credential, options, resolver),
+    )
+}
+
+/// Verify a presentation with the profile's proof types, without I/O.
+pub fn verify_presentation(
+    presentation: &PresentationOrJWT,
+    options: &JWTOrLDPOptions,
+    resolver: &OfflineResolver,
+) -> Result<VerificationReport, Error> {
+    let profile = match presentation {
+        PresentationOrJWT::VP(vp) => check_proofs(&vp.proof),
+        PresentationOrJWT::JWT(jwt) => check_jwt(jwt),
+    };
+    run(
+        profile,
+        crate::verify_presentation(presentation, options, resolver),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwt_algorithms() {
+        // {"alg":"EdDSA"} and {"alg":"RS256"}
+        assert!(check_jwt("eyJhbGciOiJFZERTQSJ9.e30.c2ln").is_ok());
+        assert!(check_jwt("eyJhbGciOiJSUzI1NiJ9.e30.c2ln").is_err());
+        assert!(check_jwt("not a jwt").is_err());
+    }
+
+    #[test]
+    fn pending_future() {
+        assert_eq!(block_on_ready(async { 1 }), Some(1));
+        assert_eq!(block_on_ready(std::future::pending::<()>()), None);
+    }
+
+    #[test]
+    fn resolve_did_key() {
+        let resolver = OfflineResolver::new();
+        let did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+        let (res_meta, doc, _) =
+            block_on_ready(resolver.resolve(did, &ResolutionInputMetadata::default())).unwrap();
+        assert!(res_meta.error.is_none());
+        assert_eq!(doc.unwrap().id, did);
+    }
+}
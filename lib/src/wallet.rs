@@ -0,0 +1,487 @@
+//! Local encrypted store of credentials and connections, for agents acting as wallets.
+//!
+//! Each item is stored in its own file, as a JWE (see [`crate::jwe`]) encrypted to the wallet
+//! key, which must be an Ed25519 or X25519 key:
+//!
+//! ```text
+//! <dir>/credentials/<id>.jwe
+//! <dir>/connections/<id>.jwe
+//! ```
+//!
+//! A wallet can be exported as a [Universal Wallet 2020][universal-wallet] backup, an
+//! `EncryptedWallet` credential whose `encryptedWalletContents` is a JWE of the wallet contents:
+//! keys (`JsonWebKey2020`, with `privateKeyJwk`), credentials, JWT credentials as
+//! `EnvelopedVerifiableCredential`, and connections (`Connection`). The JWE is in the compact
+//! serialization, encrypted with ECDH-ES to a recipient's key.
+//!
+//! [universal-wallet]: https://w3c-ccg.github.io/universal-wallet-interop-spec/
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::hex;
+use crate::jwe::{self, JWEError};
+use crate::{DIDMethod, Source, JWK};
+
+const CREDENTIALS: &str = "credentials";
+const CONNECTIONS: &str = "connections";
+
+/// JSON-LD context of Universal Wallet 2020 contents.
+pub const WALLET_CONTEXT: &str = "https://w3id.org/wallet/v1";
+const CREDENTIALS_V1_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+const CREDENTIALS_V2_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+/// Prefix of the `id` of an `EnvelopedVerifiableCredential` containing a JWT.
+const JWT_DATA_URL: &str = "data:application/jwt,";
+
+#[derive(Error, Debug)]
+pub enum WalletError {
+    #[error("IO: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("JWE: {0}")]
+    JWE(#[from] JWEError),
+    #[error("Invalid item ID: {0}")]
+    InvalidId(String),
+    #[error("Invalid wallet backup: {0}")]
+    InvalidBackup(String),
+    #[error("Unable to generate a DID for the key")]
+    UnableToGenerateDID,
+}
+
+/// Credential in a wallet.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredential {
+    pub id: String,
+    /// Credential, or JWT string
+    pub credential: Value,
+    /// DID the credential was received from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_from: Option<String>,
+    pub received: DateTime<Utc>,
+}
+
+/// Party that the wallet has exchanged messages with, or whose out-of-band invitation it
+/// accepted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub did: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created: DateTime<Utc>,
+    pub last_message: DateTime<Utc>,
+    /// Our DID in the connection, e.g. a pairwise DID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub my_did: Option<String>,
+    /// DIDComm service endpoint URI of the party
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Keys of the mediators to route messages to the party through, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing_keys: Vec<String>,
+    /// ID of the out-of-band invitation that started the connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invitation_id: Option<String>,
+}
+
+impl Connection {
+    /// Connection with a DID, started now.
+    pub fn new(did: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            did: did.into(),
+            label: None,
+            created: now,
+            last_message: now,
+            my_did: None,
+            endpoint: None,
+            routing_keys: Vec::new(),
+            invitation_id: None,
+        }
+    }
+}
+
+/// Contents added to a wallet from a backup.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletImport {
+    pub credentials: usize,
+    pub connections: usize,
+    /// Keys of the backup, as Universal Wallet key objects, which the wallet does not store
+    pub keys: Vec<Value>,
+}
+
+/// Encrypted store in a directory.
+pub struct Wallet {
+    dir: PathBuf,
+    key: JWK,
+}
+
+/// Generate a random (version 4) UUID URN.
+fn urn_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(&bytes);
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// did:key DID of a key.
+fn key_did(key: &JWK) -> Result<String, WalletError> {
+    did_method_key::DIDKey
+        .generate(&Source::Key(key))
+        .ok_or(WalletError::UnableToGenerateDID)
+}
+
+/// Universal Wallet key object of a key, with its did:key verification method.
+fn key_content(key: &JWK) -> Result<Value, WalletError> {
+    let did = key_did(key)?;
+    let fragment = did.trim_start_matches("did:key:").to_string();
+    Ok(json!({
+        "@context": [WALLET_CONTEXT],
+        "id": format!("{}#{}", did, fragment),
+        "type": "JsonWebKey2020",
+        "controller": did,
+        "publicKeyJwk": key.to_public(),
+        "privateKeyJwk": key,
+    }))
+}
+
+fn has_type(item: &Value, name: &str) -> bool {
+    match item.get("type") {
+        Some(Value::String(type_)) => type_ == name,
+        Some(Value::Array(types)) => types.iter().any(|type_| type_ == name),
+        _ => false,
+    }
+}
+
+impl Wallet {
+    /// Open the wallet in a directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>, key: JWK) -> Result<Self, WalletError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(CREDENTIALS))?;
+        fs::create_dir_all(dir.join(CONNECTIONS))?;
+        Ok(Self { dir, key })
+    }
+
+    fn path(&self, collection: &str, id: &str) -> Result<PathBuf, WalletError> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(WalletError::InvalidId(id.to_string()));
+        }
+        Ok(self.dir.join(collection).join(format!("{}.jwe", id)))
+    }
+
+    fn put<T: Serialize>(&self, collection: &str, id: &str, item: &T) -> Result<(), WalletError> {
+        let plaintext = serde_json::to_vec(item)?;
+        let jwe = jwe::encrypt(
+            &plaintext,
+            &self.key.to_public(),
+            self.key.key_id.clone(),
+            Some("application/json".to_string()),
+        )?;
+        fs::write(self.path(collection, id)?, jwe)?;
+        Ok(())
+    }
+
+    fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        collection: &str,
+        id: &str,
+    ) -> Result<Option<T>, WalletError> {
+        let jwe = match fs::read_to_string(self.path(collection, id)?) {
+            Ok(jwe) => jwe,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let (_, plaintext) = jwe::decrypt(&jwe, &self.key)?;
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
+
+    fn list<T: for<'de> Deserialize<'de>>(&self, collection: &str) -> Result<Vec<T>, WalletError> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(self.dir.join(collection))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jwe") {
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        let mut items = Vec::new();
+        for id in ids {
+            if let Some(item) = self.get(collection, &id)? {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    fn remove(&self, collection: &str, id: &str) -> Result<bool, WalletError> {
+        match fs::remove_file(self.path(collection, id)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store a credential (or JWT string), and return its ID in the wallet.
+    pub fn insert_credential(
+        &self,
+        credential: Value,
+        received_from: Option<String>,
+    ) -> Result<String, WalletError> {
+        let stored = StoredCredential {
+            id: hex::random_id(),
+            credential,
+            received_from,
+            received: Utc::now(),
+        };
+        self.put(CREDENTIALS, &stored.id, &stored)?;
+        Ok(stored.id)
+    }
+
+    pub fn credential(&self, id: &str) -> Result<Option<StoredCredential>, WalletError> {
+        self.get(CREDENTIALS, id)
+    }
+
+    pub fn credentials(&self) -> Result<Vec<StoredCredential>, WalletError> {
+        self.list(CREDENTIALS)
+    }
+
+    /// Remove a credential. Returns whether it was in the wallet.
+    pub fn remove_credential(&self, id: &str) -> Result<bool, WalletError> {
+        self.remove(CREDENTIALS, id)
+    }
+
+    /// Add a connection with a DID, or record a new message on an existing one.
+    pub fn touch_connection(&self, did: &str) -> Result<Connection, WalletError> {
+        let id = hex::encode(&Sha256::digest(did.as_bytes()));
+        let now = Utc::now();
+        let connection = match self.get::<Connection>(CONNECTIONS, &id)? {
+            Some(connection) => Connection {
+                last_message: now,
+                ..connection
+            },
+            None => Connection::new(did),
+        };
+        self.put(CONNECTIONS, &id, &connection)?;
+        Ok(connection)
+    }
+
+    /// Connection with a DID, if any.
+    pub fn connection(&self, did: &str) -> Result<Option<Connection>, WalletError> {
+        self.get(CONNECTIONS, &hex::encode(&Sha256::digest(did.as_bytes())))
+    }
+
+    pub fn connections(&self) -> Result<Vec<Connection>, WalletError> {
+        self.list(CONNECTIONS)
+    }
+
+    /// Store a connection, replacing any connection with the same DID.
+    pub fn insert_connection(&self, connection: &Connection) -> Result<(), WalletError> {
+        let id = hex::encode(&Sha256::digest(connection.did.as_bytes()));
+        self.put(CONNECTIONS, &id, connection)
+    }
+
+    /// Contents of the wallet as Universal Wallet 2020 objects: the given keys, credentials and
+    /// connections.
+    pub fn contents(&self, keys: &[JWK]) -> Result<Vec<Value>, WalletError> {
+        let mut contents = Vec::new();
+        for key in keys {
+            contents.push(key_content(key)?);
+        }
+        for stored in self.credentials()? {
+            contents.push(match stored.credential {
+                Value::String(jwt) => json!({
+                    "@context": [CREDENTIALS_V2_CONTEXT],
+                    "id": format!("{}{}", JWT_DATA_URL, jwt),
+                    "type": "EnvelopedVerifiableCredential",
+                }),
+                credential => credential,
+            });
+        }
+        for connection in self.connections()? {
+            let mut content = json!({
+                "@context": [WALLET_CONTEXT],
+                "id": connection.did,
+                "type": "Connection",
+                "created": connection.created,
+                "lastMessage": connection.last_message,
+            });
+            if let Some(label) = connection.label {
+                content["name"] = Value::String(label);
+            }
+            contents.push(content);
+        }
+        Ok(contents)
+    }
+
+    /// Export the wallet as an `EncryptedWallet` credential, encrypted to `recipient`, including
+    /// the given keys.
+    pub fn export(&self, recipient: &JWK, keys: &[JWK]) -> Result<Value, WalletError> {
+        let contents = serde_json::to_vec(&self.contents(keys)?)?;
+        let encrypted = jwe::encrypt(
+            &contents,
+            &recipient.to_public(),
+            recipient.key_id.clone(),
+            Some("application/json".to_string()),
+        )?;
+        let did = key_did(&self.key)?;
+        Ok(json!({
+            "@context": [CREDENTIALS_V1_CONTEXT, WALLET_CONTEXT],
+            "id": urn_uuid(),
+            "type": ["VerifiableCredential", "EncryptedWallet"],
+            "issuer": did,
+            "issuanceDate": Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "credentialSubject": {
+                "id": did,
+                "encryptedWalletContents": encrypted,
+            },
+        }))
+    }
+
+    /// Add the credentials and connections of an `EncryptedWallet` backup, decrypted with `key`.
+    pub fn import(&self, backup: &Value, key: &JWK) -> Result<WalletImport, WalletError> {
+        let invalid = |message: &str| WalletError::InvalidBackup(message.to_string());
+        if !has_type(backup, "EncryptedWallet") {
+            return Err(invalid("expected an EncryptedWallet credential"));
+        }
+        let encrypted = backup["credentialSubject"]["encryptedWalletContents"]
+            .as_str()
+            .ok_or_else(|| invalid("missing encryptedWalletContents JWE"))?;
+        let (_, plaintext) = jwe::decrypt(encrypted, key)?;
+        let contents: Vec<Value> = serde_json::from_slice(&plaintext)?;
+        let mut import = WalletImport::default();
+        for item in contents {
+            if item.get("privateKeyJwk").is_some() {
+                import.keys.push(item);
+            } else if has_type(&item, "EnvelopedVerifiableCredential") {
+                let jwt = item["id"]
+                    .as_str()
+                    .and_then(|id| id.strip_prefix(JWT_DATA_URL))
+                    .ok_or_else(|| invalid("unsupported EnvelopedVerifiableCredential"))?;
+                self.insert_credential(Value::String(jwt.to_string()), None)?;
+                import.credentials += 1;
+            } else if has_type(&item, "VerifiableCredential") {
+                self.insert_credential(item, None)?;
+                import.credentials += 1;
+            } else if has_type(&item, "Connection") {
+                let did = item["id"]
+                    .as_str()
+                    .ok_or_else(|| invalid("connection without id"))?;
+                let date = |name: &str| {
+                    serde_json::from_value(item[name].clone()).unwrap_or_else(|_| Utc::now())
+                };
+                self.insert_connection(&Connection {
+                    label: item["name"].as_str().map(String::from),
+                    created: date("created"),
+                    last_message: date("lastMessage"),
+                    ..Connection::new(did)
+                })?;
+                import.connections += 1;
+            }
+        }
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn store_credentials() {
+        let dir = std::env::temp_dir().join(format!("didkit-wallet-{}", hex::random_id()));
+        let wallet = Wallet::open(&dir, JWK::generate_ed25519().unwrap()).unwrap();
+        let credential = json!({ "type": ["VerifiableCredential"] });
+        let id = wallet
+            .insert_credential(credential.clone(), Some("did:example:issuer".to_string()))
+            .unwrap();
+        let jwe = fs::read_to_string(dir.join(CREDENTIALS).join(format!("{}.jwe", id))).unwrap();
+        assert!(!jwe.contains("VerifiableCredential"));
+        assert_eq!(
+            wallet.credential(&id).unwrap().unwrap().credential,
+            credential
+        );
+        assert_eq!(wallet.credentials().unwrap().len(), 1);
+        assert!(wallet.credential("../connections").is_err());
+        assert!(wallet.remove_credential(&id).unwrap());
+        assert!(wallet.credential(&id).unwrap().is_none());
+
+        wallet
+            .insert_connection(&Connection {
+                my_did: Some("did:example:holder".to_string()),
+                routing_keys: vec!["did:example:mediator#key-1".to_string()],
+                ..Connection::new("did:example:issuer")
+            })
+            .unwrap();
+        wallet.touch_connection("did:example:issuer").unwrap();
+        assert_eq!(wallet.connections().unwrap().len(), 1);
+        let connection = wallet.connection("did:example:issuer").unwrap().unwrap();
+        assert_eq!(connection.my_did.as_deref(), Some("did:example:holder"));
+        assert_eq!(connection.routing_keys.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn export_import() {
+        let key = JWK::generate_ed25519().unwrap();
+        let dir = std::env::temp_dir().join(format!("didkit-wallet-{}", hex::random_id()));
+        let wallet = Wallet::open(&dir, key.clone()).unwrap();
+        let credential = json!({ "type": ["VerifiableCredential"] });
+        wallet.insert_credential(credential, None).unwrap();
+        wallet
+            .insert_credential(
+                Value::String("eyJhbGciOiJFZERTQSJ9.e30.c2ln".to_string()),
+                None,
+            )
+            .unwrap();
+        wallet.touch_connection("did:example:issuer").unwrap();
+
+        let recipient = JWK::generate_ed25519().unwrap();
+        let backup = wallet.export(&recipient, &[key.clone()]).unwrap();
+        assert!(has_type(&backup, "EncryptedWallet"));
+        let encrypted = backup["credentialSubject"]["encryptedWalletContents"].to_string();
+        assert!(!encrypted.contains("VerifiableCredential"));
+
+        let other_dir = std::env::temp_dir().join(format!("didkit-wallet-{}", hex::random_id()));
+        let other = Wallet::open(&other_dir, JWK::generate_ed25519().unwrap()).unwrap();
+        assert!(other.import(&backup, &key).is_err());
+        let import = other.import(&backup, &recipient).unwrap();
+        assert_eq!(import.credentials, 2);
+        assert_eq!(import.connections, 1);
+        assert_eq!(import.keys[0]["privateKeyJwk"], json!(key));
+        let mut credentials: Vec<Value> = other
+            .credentials()
+            .unwrap()
+            .into_iter()
+            .map(|stored| stored.credential)
+            .collect();
+        credentials.sort_by_key(Value::is_string);
+        assert!(credentials[1].is_string());
+        assert_eq!(other.connections().unwrap()[0].did, "did:example:issuer");
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(other_dir).unwrap();
+    }
+}
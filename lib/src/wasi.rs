@@ -0,0 +1,189 @@
+//! Support for WASI hosts, such as serverless and edge runtimes.
+//!
+//! WASI has no sockets, so HTTP requests are made by a function imported from the host, in the
+//! `didkit` import module:
+//!
+//! ```text
+//! didkit_http_get(url_ptr, url_len, accept_ptr, accept_len, body_ptr_out, body_len_out) -> i32
+//! ```
+//!
+//! The host fetches `url` with the given `Accept` header, allocates a buffer for the response
+//! body with the exported [`didkit_alloc`], writes the body to it, stores its address and length
+//! in `body_ptr_out` and `body_len_out`, and returns the HTTP status code, or a negative number if
+//! the request could not be made. The buffer is then owned by the module.
+//!
+//! `did:web` is resolved using this function; DID methods that need other network access are
+//! not available on WASI.
+
+use std::convert::TryFrom;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use ssi::did::{DIDMethod, Document};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata, ERROR_INVALID_DID,
+    ERROR_NOT_FOUND, TYPE_DID_LD_JSON,
+};
+
+#[link(wasm_import_module = "didkit")]
+extern "C" {
+    fn didkit_http_get(
+        url: *const u8,
+        url_len: usize,
+        accept: *const u8,
+        accept_len: usize,
+        body: *mut *mut u8,
+        body_len: *mut usize,
+    ) -> i32;
+}
+
+/// Allocate a buffer in the module's memory, for the host to write a response body to.
+#[no_mangle]
+pub extern "C" fn didkit_alloc(len: usize) -> *mut u8 {
+    let mut buf = Vec::<u8>::with_capacity(len);
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}
+
+/// Free a buffer allocated with [`didkit_alloc`], e.g. if the host fails to write to it.
+///
+/// # Safety
+///
+/// `ptr` and `len` must be those of a buffer allocated with [`didkit_alloc`], not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn didkit_dealloc(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("Host unable to make request: {0}")]
+    Host(i32),
+    #[error("HTTP status {0}")]
+    Status(u16),
+}
+
+/// Make an HTTP GET request, using the host function.
+pub fn http_get(url: &str, accept: &str) -> Result<Vec<u8>, FetchError> {
+    let mut body: *mut u8 = std::ptr::null_mut();
+    let mut body_len: usize = 0;
+    let status = unsafe {
+        didkit_http_get(
+            url.as_ptr(),
+            url.len(),
+            accept.as_ptr(),
+            accept.len(),
+            &mut body,
+            &mut body_len,
+        )
+    };
+    let body = if body.is_null() {
+        Vec::new()
+    } else {
+        // The host allocated the buffer with didkit_alloc(body_len), and wrote body_len bytes.
+        unsafe { Vec::from_raw_parts(body, body_len, body_len) }
+    };
+    match u16::try_from(status) {
+        Ok(200) => Ok(body),
+        Ok(status) => Err(FetchError::Status(status)),
+        Err(_) => Err(FetchError::Host(status)),
+    }
+}
+
+/// URL of the DID document of a `did:web` DID.
+fn did_web_url(did: &str) -> Option<String> {
+    let id = did.strip_prefix("did:web:")?;
+    let mut parts = id.split(':');
+    let host = parts
+        .next()
+        .filter(|host| !host.is_empty())?
+        .replace("%3A", ":");
+    let path: Vec<&str> = parts.collect();
+    Some(if path.is_empty() {
+        format!("https://{}/.well-known/did.json", host)
+    } else {
+        format!("https://{}/{}/did.json", host, path.join("/"))
+    })
+}
+
+/// `did:web` resolver using [`http_get`].
+pub struct DIDWebHost;
+
+fn resolution_error(
+    error: &str,
+) -> (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+) {
+    (
+        ResolutionMetadata {
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+}
+
+#[async_trait(?Send)]
+impl DIDResolver for DIDWebHost {
+    async fn resolve(
+        &self,
+        did: &str,
+        _input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let url = match did_web_url(did) {
+            Some(url) => url,
+            None => return resolution_error(ERROR_INVALID_DID),
+        };
+        let body = match http_get(&url, TYPE_DID_LD_JSON) {
+            Ok(body) => body,
+            Err(FetchError::Status(404)) => return resolution_error(ERROR_NOT_FOUND),
+            Err(err) => return resolution_error(&err.to_string()),
+        };
+        match serde_json::from_slice::<Document>(&body) {
+            Ok(doc) if doc.id == did => (
+                ResolutionMetadata::default(),
+                Some(doc),
+                Some(DocumentMetadata::default()),
+            ),
+            Ok(_) => resolution_error("DID document id does not match DID"),
+            Err(err) => resolution_error(&format!("Unable to parse DID document: {}", err)),
+        }
+    }
+}
+
+impl DIDMethod for DIDWebHost {
+    fn name(&self) -> &'static str {
+        "web"
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_url() {
+        assert_eq!(
+            did_web_url("did:web:example.com").unwrap(),
+            "https://example.com/.well-known/did.json"
+        );
+        assert_eq!(
+            did_web_url("did:web:localhost%3A8443:user:alice").unwrap(),
+            "https://localhost:8443/user/alice/did.json"
+        );
+        assert!(did_web_url("did:web:").is_none());
+    }
+}
@@ -0,0 +1,666 @@
+//! X.509 certificates of JWT issuers.
+//!
+//! A JWT may carry the certificate chain of its signing key in the `x5c` header (base64 DER
+//! certificates, leaf first), or refer to it with `x5u` (an HTTPS URL of PEM certificates), as in
+//! [RFC 7515][]. If [`VerificationOptions::trusted_certificates`] is set, a VC-JWT or VC-JOSE
+//! credential must have such a chain, and [`verify_jwt`] checks that:
+//!
+//! - each certificate is valid at the verification time, and signed by the next one;
+//! - the leaf is not a CA, and, if it has a key usage extension, may make digital signatures;
+//! - each certificate other than the leaf, and the trusted certificate signing the last one, is
+//!   a CA, may sign certificates if it has a key usage extension, and has no more CAs under it
+//!   than its path length constraint;
+//! - the last certificate is, or is signed by, a trusted certificate;
+//! - the JWT is signed by the leaf's key.
+//!
+//! The leaf's subject is reported as the [`CertificateIdentity`] of the issuer, alongside its DID,
+//! which is verified as usual. Ed25519, P-256 and secp256k1 leaf keys are supported. Validating
+//! chains needs the `x509` feature; without it, JWT credentials fail verification when trusted
+//! certificates are set. VC-COSE and JPT credentials cannot carry a chain, and always fail
+//! verification when trusted certificates are set.
+//!
+//! [RFC 7515]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1.5
+//! [`VerificationOptions::trusted_certificates`]: crate::VerificationOptions::trusted_certificates
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "x509")]
+use chrono::TimeZone;
+#[cfg(feature = "x509")]
+use serde_json::Value;
+#[cfg(feature = "x509")]
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+#[cfg(feature = "x509")]
+use x509_parser::certificate::X509Certificate;
+#[cfg(feature = "x509")]
+use x509_parser::extensions::GeneralName;
+#[cfg(feature = "x509")]
+use x509_parser::prelude::FromDer;
+#[cfg(feature = "x509")]
+use x509_parser::time::ASN1Time;
+
+#[cfg(feature = "x509")]
+const OID_ED25519: &str = "1.3.101.112";
+#[cfg(feature = "x509")]
+const OID_EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+#[cfg(all(feature = "x509", feature = "p256"))]
+const OID_P256: &str = "1.2.840.10045.3.1.7";
+#[cfg(all(feature = "x509", feature = "secp256k1"))]
+const OID_SECP256K1: &str = "1.3.132.0.10";
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+#[derive(Error, Debug)]
+pub enum X509Error {
+    #[error("X.509 support is not enabled")]
+    Disabled,
+    #[error("JWT has no x5c or x5u header")]
+    MissingChain,
+    #[error("Invalid x5c or x5u header")]
+    InvalidHeader,
+    #[error("Unable to decode certificate: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("Unable to parse certificate: {0}")]
+    Parse(String),
+    #[error("Certificate is not valid at {1}: {0}")]
+    OutsideValidity(String, DateTime<Utc>),
+    #[error("Certificate is not a CA: {0}")]
+    NotCA(String),
+    #[error("Leaf certificate is a CA: {0}")]
+    NotEndEntity(String),
+    #[error("Certificate key usage does not allow {1}: {0}")]
+    KeyUsage(String, &'static str),
+    #[error("Certificate chain exceeds the path length constraint of {0}")]
+    PathLength(String),
+    #[error("Invalid certificate signature: {0}")]
+    Signature(String),
+    #[error("Certificate chain does not lead to a trusted certificate")]
+    Untrusted,
+    #[error("Unsupported certificate key: {0}")]
+    UnsupportedKey(String),
+    #[error("Unable to fetch certificate chain: {0}")]
+    Fetch(String),
+    #[error("JWT is not signed by the certificate's key: {0}")]
+    JWS(#[from] ssi::error::Error),
+}
+
+/// Identity of an issuer, from the leaf of its certificate chain.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateIdentity {
+    /// Subject distinguished name, e.g. `C=US, O=Example, CN=Example Issuer`
+    pub subject: String,
+    /// Distinguished name of the certificate's issuer
+    pub issuer: String,
+    /// Serial number, as colon-separated hex
+    pub serial_number: String,
+    /// DNS names, URIs and email addresses of the subject alternative name extension
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject_alt_names: Vec<String>,
+    pub not_after: DateTime<Utc>,
+}
+
+/// Decode DER certificates from PEM (any number of `CERTIFICATE` blocks) or from a single base64
+/// DER certificate.
+pub fn parse_certificates(input: &str) -> Result<Vec<Vec<u8>>, X509Error> {
+    if !input.contains(PEM_BEGIN) {
+        return Ok(vec![base64::decode(input.trim())?]);
+    }
+    let mut certificates = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let block = &rest[start + PEM_BEGIN.len()..];
+        let end = block.find(PEM_END).ok_or(X509Error::InvalidHeader)?;
+        let base64: String = block[..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        certificates.push(base64::decode(base64)?);
+        rest = &block[end + PEM_END.len()..];
+    }
+    Ok(certificates)
+}
+
+#[cfg(feature = "x509")]
+fn parse(der: &[u8]) -> Result<X509Certificate<'_>, X509Error> {
+    X509Certificate::from_der(der)
+        .map(|(_, certificate)| certificate)
+        .map_err(|e| X509Error::Parse(e.to_string()))
+}
+
+#[cfg(feature = "x509")]
+fn is_ca(certificate: &X509Certificate) -> bool {
+    certificate
+        .tbs_certificate
+        .basic_constraints()
+        .map_or(false, |(_, constraints)| constraints.ca)
+}
+
+/// Check that the leaf of a chain is an end-entity certificate for signing.
+#[cfg(feature = "x509")]
+fn check_leaf(certificate: &X509Certificate) -> Result<(), X509Error> {
+    if is_ca(certificate) {
+        return Err(X509Error::NotEndEntity(certificate.subject().to_string()));
+    }
+    match certificate.tbs_certificate.key_usage() {
+        Some((_, key_usage)) if !key_usage.digital_signature() => Err(X509Error::KeyUsage(
+            certificate.subject().to_string(),
+            "digital signatures",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check that a certificate may issue certificates, with a number of intermediate CAs under it.
+#[cfg(feature = "x509")]
+fn check_ca(certificate: &X509Certificate, intermediates: usize) -> Result<(), X509Error> {
+    let subject = certificate.subject().to_string();
+    let constraints = match certificate.tbs_certificate.basic_constraints() {
+        Some((_, constraints)) if constraints.ca => constraints,
+        _ => return Err(X509Error::NotCA(subject)),
+    };
+    if let Some((_, key_usage)) = certificate.tbs_certificate.key_usage() {
+        if !key_usage.key_cert_sign() {
+            return Err(X509Error::KeyUsage(subject, "certificate signing"));
+        }
+    }
+    match constraints.path_len_constraint {
+        Some(path_length) if intermediates as u64 > path_length as u64 => {
+            Err(X509Error::PathLength(subject))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "x509")]
+fn check_signed_by(
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+) -> Result<(), X509Error> {
+    if certificate.issuer() != issuer.subject() {
+        return Err(X509Error::Signature(format!(
+            "{} is not issued by {}",
+            certificate.subject(),
+            issuer.subject()
+        )));
+    }
+    certificate
+        .verify_signature(Some(&issuer.tbs_certificate.subject_pki))
+        .map_err(|e| X509Error::Signature(format!("{}: {:?}", certificate.subject(), e)))
+}
+
+#[cfg(feature = "x509")]
+fn identity(certificate: &X509Certificate) -> CertificateIdentity {
+    let subject_alt_names = match certificate.tbs_certificate.subject_alternative_name() {
+        Some((_, san)) => san
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name)
+                | GeneralName::URI(name)
+                | GeneralName::RFC822Name(name) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    CertificateIdentity {
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        serial_number: certificate.tbs_certificate.raw_serial_as_string(),
+        subject_alt_names,
+        not_after: Utc.timestamp(certificate.validity().not_after.timestamp(), 0),
+    }
+}
+
+/// Validate a certificate chain (DER, leaf first) to trusted certificates (DER), and get the
+/// identity of the leaf.
+#[cfg(feature = "x509")]
+pub fn validate_chain(
+    chain: &[Vec<u8>],
+    trusted: &[Vec<u8>],
+    as_of: DateTime<Utc>,
+) -> Result<CertificateIdentity, X509Error> {
+    let certificates = chain
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+    let anchors = trusted
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (leaf, last) = match (certificates.first(), certificates.last()) {
+        (Some(leaf), Some(last)) => (leaf, last),
+        _ => return Err(X509Error::MissingChain),
+    };
+    let time = ASN1Time::from_timestamp(as_of.timestamp());
+    check_leaf(leaf)?;
+    for (i, certificate) in certificates.iter().enumerate() {
+        if !certificate.validity().is_valid_at(time) {
+            return Err(X509Error::OutsideValidity(
+                certificate.subject().to_string(),
+                as_of,
+            ));
+        }
+        if i > 0 {
+            check_ca(certificate, i - 1)?;
+        }
+        if let Some(issuer) = certificates.get(i + 1) {
+            check_signed_by(certificate, issuer)?;
+        }
+    }
+    let is_trusted = trusted.iter().any(|der| chain.last() == Some(der))
+        || anchors.iter().any(|anchor| {
+            check_ca(anchor, certificates.len() - 1).is_ok()
+                && anchor.validity().is_valid_at(time)
+                && check_signed_by(last, anchor).is_ok()
+        });
+    if !is_trusted {
+        return Err(X509Error::Untrusted);
+    }
+    Ok(identity(leaf))
+}
+
+/// Get the public key of a certificate as a JWK.
+#[cfg(feature = "x509")]
+fn public_jwk(certificate: &X509Certificate) -> Result<JWK, X509Error> {
+    let spki = &certificate.tbs_certificate.subject_pki;
+    let key = spki.subject_public_key.data;
+    let algorithm = spki.algorithm.algorithm.to_id_string();
+    match &algorithm[..] {
+        OID_ED25519 => Ok(JWK::from(Params::OKP(OctetParams {
+            curve: "Ed25519".to_string(),
+            public_key: Base64urlUInt(key.to_vec()),
+            private_key: None,
+        }))),
+        OID_EC_PUBLIC_KEY => {
+            let curve = spki
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|parameters| parameters.as_oid().ok())
+                .map(|oid| oid.to_id_string())
+                .unwrap_or_default();
+            let jwk = match &curve[..] {
+                #[cfg(feature = "p256")]
+                OID_P256 => ssi::jwk::p256_parse(key),
+                #[cfg(feature = "secp256k1")]
+                OID_SECP256K1 => ssi::jwk::secp256k1_parse(key),
+                _ => return Err(X509Error::UnsupportedKey(curve)),
+            };
+            jwk.map_err(|e| X509Error::Parse(e.to_string()))
+        }
+        _ => Err(X509Error::UnsupportedKey(algorithm)),
+    }
+}
+
+/// Get the public key of a certificate (DER) as a JWK.
+#[cfg(feature = "x509")]
+pub(crate) fn certificate_jwk(der: &[u8]) -> Result<JWK, X509Error> {
+    public_jwk(&parse(der)?)
+}
+
+/// Get the value of an extension of a certificate (DER), by OID.
+#[cfg(feature = "x509")]
+pub(crate) fn certificate_extension(der: &[u8], oid: &str) -> Result<Option<Vec<u8>>, X509Error> {
+    let certificate = parse(der)?;
+    let extension = certificate
+        .tbs_certificate
+        .extensions()
+        .iter()
+        .find(|extension| extension.oid.to_id_string() == oid);
+    Ok(extension.map(|extension| extension.value.to_vec()))
+}
+
+#[cfg(feature = "x509")]
+async fn fetch(url: &str) -> Result<String, reqwest::Error> {
+    reqwest::get(url).await?.error_for_status()?.text().await
+}
+
+/// Get the certificate chain (DER, leaf first) of a JWT header, from `x5c` or `x5u`.
+#[cfg(feature = "x509")]
+async fn certificate_chain(header: &Value) -> Result<Vec<Vec<u8>>, X509Error> {
+    if let Some(x5c) = header.get("x5c") {
+        let certificates = x5c.as_array().ok_or(X509Error::InvalidHeader)?;
+        return certificates
+            .iter()
+            .map(|certificate| {
+                let certificate = certificate.as_str().ok_or(X509Error::InvalidHeader)?;
+                Ok(base64::decode(certificate)?)
+            })
+            .collect();
+    }
+    if let Some(x5u) = header.get("x5u") {
+        let url = x5u.as_str().ok_or(X509Error::InvalidHeader)?;
+        if !url.starts_with("https://") {
+            return Err(X509Error::Fetch(format!("Expected HTTPS URL: {}", url)));
+        }
+        let pem = fetch(url)
+            .await
+            .map_err(|e| X509Error::Fetch(e.to_string()))?;
+        return parse_certificates(&pem);
+    }
+    Err(X509Error::MissingChain)
+}
+
+/// Verify a JWT with the certificate chain of its header, to trusted certificates (PEM or base64
+/// DER), and get the identity of its signer.
+#[cfg(feature = "x509")]
+pub async fn verify_jwt(
+    jwt: &str,
+    trusted_certificates: &[String],
+    as_of: DateTime<Utc>,
+) -> Result<CertificateIdentity, X509Error> {
+    let (header, _) =
+        crate::verification::decode_jwt_unverified(jwt).ok_or(X509Error::InvalidHeader)?;
+    let chain = certificate_chain(&header).await?;
+    let mut trusted = Vec::new();
+    for certificates in trusted_certificates {
+        trusted.extend(parse_certificates(certificates)?);
+    }
+    let identity = validate_chain(&chain, &trusted, as_of)?;
+    let leaf = parse(&chain[0])?;
+    ssi::jws::decode_verify(jwt, &public_jwk(&leaf)?)?;
+    Ok(identity)
+}
+
+/// Verify a JWT with the certificate chain of its header. Fails without the `x509` feature.
+#[cfg(not(feature = "x509"))]
+pub async fn verify_jwt(
+    _jwt: &str,
+    _trusted_certificates: &[String],
+    _as_of: DateTime<Utc>,
+) -> Result<CertificateIdentity, X509Error> {
+    Err(X509Error::Disabled)
+}
+
+/// Certificates generated for tests, with Ed25519 keys.
+#[cfg(all(test, feature = "x509"))]
+pub(crate) mod testing {
+    use super::*;
+    use ssi::jwk::Algorithm;
+
+    /// Key usage bits of the first byte of the extension.
+    pub const DIGITAL_SIGNATURE: u8 = 0x80;
+    pub const KEY_CERT_SIGN: u8 = 0x04;
+
+    /// Encode a DER element.
+    pub fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        let length = contents.len().to_be_bytes();
+        match length.iter().position(|byte| *byte != 0) {
+            Some(start) if contents.len() >= 0x80 => {
+                element.push(0x80 | (length.len() - start) as u8);
+                element.extend_from_slice(&length[start..]);
+            }
+            _ => element.push(contents.len() as u8),
+        }
+        element.extend_from_slice(contents);
+        element
+    }
+
+    pub fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+        der(0x30, &elements.concat())
+    }
+
+    /// Encode an OID, e.g. `2.5.29.19`.
+    pub fn oid(oid: &str) -> Vec<u8> {
+        let arcs: Vec<u64> = oid.split('.').map(|arc| arc.parse().unwrap()).collect();
+        let mut contents = vec![(arcs[0] * 40 + arcs[1]) as u8];
+        for arc in &arcs[2..] {
+            let mut bytes = vec![(arc & 0x7f) as u8];
+            let mut rest = arc >> 7;
+            while rest > 0 {
+                bytes.push(0x80 | (rest & 0x7f) as u8);
+                rest >>= 7;
+            }
+            contents.extend(bytes.iter().rev());
+        }
+        der(0x06, &contents)
+    }
+
+    fn name(common_name: &str) -> Vec<u8> {
+        let attribute = sequence(&[oid("2.5.4.3"), der(0x0c, common_name.as_bytes())]);
+        sequence(&[der(0x31, &attribute)])
+    }
+
+    fn time(time: DateTime<Utc>) -> Vec<u8> {
+        der(0x17, time.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+    }
+
+    fn ed25519_public_key(key: &JWK) -> Vec<u8> {
+        match key.params {
+            Params::OKP(ref params) => params.public_key.0.clone(),
+            _ => panic!("Expected an Ed25519 key"),
+        }
+    }
+
+    /// Contents of a certificate to issue.
+    #[derive(Debug, Clone)]
+    pub struct CertificateTemplate {
+        pub subject: String,
+        pub not_before: DateTime<Utc>,
+        pub not_after: DateTime<Utc>,
+        /// Basic constraints, if any: whether the subject is a CA, and its path length
+        /// constraint
+        pub basic_constraints: Option<(bool, Option<u8>)>,
+        /// First byte of the key usage extension, if any
+        pub key_usage: Option<u8>,
+        /// Other extensions, by OID
+        pub extensions: Vec<(String, Vec<u8>)>,
+    }
+
+    impl CertificateTemplate {
+        /// Template of a CA certificate, valid for a day around now.
+        pub fn ca(subject: &str) -> Self {
+            let now = Utc::now();
+            Self {
+                subject: subject.to_string(),
+                not_before: now - chrono::Duration::days(1),
+                not_after: now + chrono::Duration::days(1),
+                basic_constraints: Some((true, None)),
+                key_usage: Some(KEY_CERT_SIGN),
+                extensions: Vec::new(),
+            }
+        }
+
+        /// Template of an end-entity certificate, valid for a day around now.
+        pub fn leaf(subject: &str) -> Self {
+            Self {
+                basic_constraints: None,
+                key_usage: Some(DIGITAL_SIGNATURE),
+                ..Self::ca(subject)
+            }
+        }
+
+        /// Issue the certificate (DER) of a key, signed by an issuer's key.
+        pub fn issue(&self, key: &JWK, issuer: &str, issuer_key: &JWK) -> Vec<u8> {
+            let mut extensions = Vec::new();
+            if let Some((ca, path_length)) = self.basic_constraints {
+                let mut constraints = Vec::new();
+                if ca {
+                    constraints.push(der(0x01, &[0xff]));
+                }
+                if let Some(path_length) = path_length {
+                    constraints.push(der(0x02, &[path_length]));
+                }
+                extensions.push(("2.5.29.19".to_string(), sequence(&constraints)));
+            }
+            if let Some(key_usage) = self.key_usage {
+                let unused_bits = key_usage.trailing_zeros().min(7) as u8;
+                extensions.push((
+                    "2.5.29.15".to_string(),
+                    der(0x03, &[unused_bits, key_usage]),
+                ));
+            }
+            extensions.extend(self.extensions.iter().cloned());
+            let extensions: Vec<Vec<u8>> = extensions
+                .iter()
+                .map(|(id, value)| sequence(&[oid(id), der(0x04, value)]))
+                .collect();
+            let algorithm = sequence(&[oid(OID_ED25519)]);
+            let mut public_key = vec![0];
+            public_key.extend(ed25519_public_key(key));
+            let tbs = sequence(&[
+                der(0xa0, &der(0x02, &[2])),
+                der(0x02, &[1]),
+                algorithm.clone(),
+                name(issuer),
+                sequence(&[time(self.not_before), time(self.not_after)]),
+                name(&self.subject),
+                sequence(&[algorithm.clone(), der(0x03, &public_key)]),
+                der(0xa3, &sequence(&extensions)),
+            ]);
+            let mut signature = vec![0];
+            signature.extend(ssi::jws::sign_bytes(Algorithm::EdDSA, &tbs, issuer_key).unwrap());
+            sequence(&[tbs, algorithm, der(0x03, &signature)])
+        }
+
+        /// Issue a self-signed certificate of a key.
+        pub fn self_sign(&self, key: &JWK) -> Vec<u8> {
+            self.issue(key, &self.subject, key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pem_certificates() {
+        let pem = format!(
+            "{}\nAAEC\nAw==\n{}\n{}\nBAU=\n{}\n",
+            PEM_BEGIN, PEM_END, PEM_BEGIN, PEM_END
+        );
+        assert_eq!(
+            parse_certificates(&pem).unwrap(),
+            vec![vec![0, 1, 2, 3], vec![4, 5]]
+        );
+        assert_eq!(parse_certificates(" AAEC ").unwrap(), vec![vec![0, 1, 2]]);
+        assert!(parse_certificates(&format!("{}\nAAEC\n", PEM_BEGIN)).is_err());
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn validate_generated_chains() {
+        use super::testing::{CertificateTemplate, DIGITAL_SIGNATURE, KEY_CERT_SIGN};
+
+        let root_key = JWK::generate_ed25519().unwrap();
+        let intermediate_key = JWK::generate_ed25519().unwrap();
+        let leaf_key = JWK::generate_ed25519().unwrap();
+        let root = CertificateTemplate::ca("Root").self_sign(&root_key);
+        let intermediate =
+            CertificateTemplate::ca("Intermediate").issue(&intermediate_key, "Root", &root_key);
+        let leaf =
+            CertificateTemplate::leaf("Leaf").issue(&leaf_key, "Intermediate", &intermediate_key);
+        let now = Utc::now();
+        let trusted = vec![root.clone()];
+        let validate = |chain: &[Vec<u8>]| validate_chain(chain, &trusted, now);
+
+        let identity = validate(&[leaf.clone(), intermediate.clone()]).unwrap();
+        assert_eq!(identity.subject, "CN=Leaf");
+        assert_eq!(identity.issuer, "CN=Intermediate");
+        validate(&[leaf.clone(), intermediate.clone(), root.clone()]).unwrap();
+        assert!(matches!(validate(&[]), Err(X509Error::MissingChain)));
+
+        // Expired leaf
+        let mut expired = CertificateTemplate::leaf("Leaf");
+        expired.not_after = now - chrono::Duration::hours(1);
+        let expired = expired.issue(&leaf_key, "Intermediate", &intermediate_key);
+        assert!(matches!(
+            validate(&[expired, intermediate.clone()]),
+            Err(X509Error::OutsideValidity(..))
+        ));
+
+        // Intermediate which is not a CA
+        let mut not_ca = CertificateTemplate::ca("Intermediate");
+        not_ca.basic_constraints = None;
+        let not_ca = not_ca.issue(&intermediate_key, "Root", &root_key);
+        assert!(matches!(
+            validate(&[leaf.clone(), not_ca]),
+            Err(X509Error::NotCA(_))
+        ));
+
+        // Leaf which is a CA
+        let ca_leaf =
+            CertificateTemplate::ca("Leaf").issue(&leaf_key, "Intermediate", &intermediate_key);
+        assert!(matches!(
+            validate(&[ca_leaf, intermediate.clone()]),
+            Err(X509Error::NotEndEntity(_))
+        ));
+
+        // Key usages
+        let mut signing_ca = CertificateTemplate::ca("Intermediate");
+        signing_ca.key_usage = Some(DIGITAL_SIGNATURE);
+        let signing_ca = signing_ca.issue(&intermediate_key, "Root", &root_key);
+        assert!(matches!(
+            validate(&[leaf.clone(), signing_ca]),
+            Err(X509Error::KeyUsage(..))
+        ));
+        let mut issuing_leaf = CertificateTemplate::leaf("Leaf");
+        issuing_leaf.key_usage = Some(KEY_CERT_SIGN);
+        let issuing_leaf = issuing_leaf.issue(&leaf_key, "Intermediate", &intermediate_key);
+        assert!(matches!(
+            validate(&[issuing_leaf, intermediate.clone()]),
+            Err(X509Error::KeyUsage(..))
+        ));
+
+        // Path length constraints, of an intermediate and of the trust anchor
+        let sub_key = JWK::generate_ed25519().unwrap();
+        let mut constrained = CertificateTemplate::ca("Intermediate");
+        constrained.basic_constraints = Some((true, Some(0)));
+        let constrained = constrained.issue(&intermediate_key, "Root", &root_key);
+        let sub = CertificateTemplate::ca("Sub").issue(&sub_key, "Intermediate", &intermediate_key);
+        let sub_leaf = CertificateTemplate::leaf("Leaf").issue(&leaf_key, "Sub", &sub_key);
+        validate(&[leaf.clone(), constrained.clone()]).unwrap();
+        assert!(matches!(
+            validate(&[sub_leaf.clone(), sub.clone(), constrained]),
+            Err(X509Error::PathLength(_))
+        ));
+        let mut constrained_root = CertificateTemplate::ca("Root");
+        constrained_root.basic_constraints = Some((true, Some(1)));
+        let constrained_root = constrained_root.self_sign(&root_key);
+        let constrained_trust = vec![constrained_root];
+        validate_chain(
+            &[leaf.clone(), intermediate.clone()],
+            &constrained_trust,
+            now,
+        )
+        .unwrap();
+        assert!(matches!(
+            validate_chain(
+                &[sub_leaf, sub, intermediate.clone()],
+                &constrained_trust,
+                now
+            ),
+            Err(X509Error::Untrusted)
+        ));
+
+        // Issuer name not matching the next certificate
+        let misnamed =
+            CertificateTemplate::leaf("Leaf").issue(&leaf_key, "Other", &intermediate_key);
+        assert!(matches!(
+            validate(&[misnamed, intermediate.clone()]),
+            Err(X509Error::Signature(_))
+        ));
+
+        // Signature not made by the issuer's key
+        let other_key = JWK::generate_ed25519().unwrap();
+        let forged = CertificateTemplate::leaf("Leaf").issue(&leaf_key, "Intermediate", &other_key);
+        assert!(matches!(
+            validate(&[forged, intermediate.clone()]),
+            Err(X509Error::Signature(_))
+        ));
+
+        // Chain to another root
+        let other_root = CertificateTemplate::ca("Root").self_sign(&other_key);
+        assert!(matches!(
+            validate_chain(&[leaf, intermediate], &[other_root], now),
+            Err(X509Error::Untrusted)
+        ));
+    }
+}
@@ -0,0 +1,381 @@
+//! Kotlin and Swift bindings generated with UniFFI.
+//!
+//! The interface is defined once, by the `#[uniffi::export]` items in this file; `uniffi-bindgen`
+//! generates the Kotlin and Swift code from the compiled library. Errors are a typed enum, and
+//! functions that resolve DIDs or sign are `suspend` functions in Kotlin and `async` in Swift.
+//! Credentials, presentations, keys and options are passed as JSON strings, as in the other
+//! bindings.
+
+use std::convert::TryFrom;
+
+use serde::Serialize;
+
+use didkit::signer::{algorithm_name, FnSigner, Signer};
+use didkit::verification::{VerificationError, VerificationWarning};
+use didkit::{
+    dereference, get_verification_method, CredentialOrJWT, DIDResolver, DereferencingInputMetadata,
+    IssueOptions, JWTOrLDPOptions, PresentationOrJWT, ResolutionInputMetadata, Source,
+    VerifiableCredential, VerifiablePresentation, DID_METHODS, JWK, URI,
+};
+
+uniffi::setup_scaffolding!();
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum DIDKitError {
+    /// An argument could not be parsed.
+    #[error("Invalid input: {message}")]
+    InvalidInput { message: String },
+    #[error("Unable to generate DID")]
+    UnableToGenerateDID,
+    #[error("Unable to get verification method")]
+    UnableToGetVerificationMethod,
+    #[error("Resolution failed: {message}")]
+    ResolutionFailed { message: String },
+    #[error("Unable to sign: {message}")]
+    SigningFailed { message: String },
+    #[error("Unable to verify: {message}")]
+    VerificationFailed { message: String },
+}
+
+impl From<serde_json::Error> for DIDKitError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::InvalidInput {
+            message: err.to_string(),
+        }
+    }
+}
+
+// Required to return `DIDKitError` from callback interfaces, for errors in the foreign code not
+// declared by the interface.
+impl From<uniffi::UnexpectedUniFFICallbackError> for DIDKitError {
+    fn from(err: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        Self::SigningFailed {
+            message: err.reason,
+        }
+    }
+}
+
+/// An error or warning in a [`VerificationReport`].
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct VerificationMessage {
+    /// Stable numeric code, as in `didkit::verification::ErrorCode::code`
+    pub code: u32,
+    /// Name of the code, e.g. `invalidSignature`
+    pub kind: String,
+    pub message: String,
+}
+
+/// Result of verifying a credential or presentation.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct VerificationReport {
+    pub checks: Vec<String>,
+    pub warnings: Vec<VerificationMessage>,
+    pub errors: Vec<VerificationMessage>,
+    pub issuer: Option<String>,
+    pub holder: Option<String>,
+    /// The full report, as JSON
+    pub json: String,
+}
+
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+impl From<&VerificationError> for VerificationMessage {
+    fn from(error: &VerificationError) -> Self {
+        Self {
+            code: error.code.code(),
+            kind: name(&error.code),
+            message: error.message.clone(),
+        }
+    }
+}
+
+impl From<&VerificationWarning> for VerificationMessage {
+    fn from(warning: &VerificationWarning) -> Self {
+        Self {
+            code: warning.code.code(),
+            kind: name(&warning.code),
+            message: warning.message.clone(),
+        }
+    }
+}
+
+impl TryFrom<didkit::VerificationReport> for VerificationReport {
+    type Error = DIDKitError;
+    fn try_from(report: didkit::VerificationReport) -> Result<Self, Self::Error> {
+        Ok(Self {
+            checks: report.checks.iter().map(name).collect(),
+            warnings: report
+                .warnings
+                .iter()
+                .map(VerificationMessage::from)
+                .collect(),
+            errors: report
+                .errors
+                .iter()
+                .map(VerificationMessage::from)
+                .collect(),
+            json: serde_json::to_string(&report)?,
+            issuer: report.issuer,
+            holder: report.holder,
+        })
+    }
+}
+
+#[uniffi::export]
+pub fn get_version() -> String {
+    VERSION.to_string()
+}
+
+#[uniffi::export]
+pub fn generate_ed25519_key() -> Result<String, DIDKitError> {
+    let jwk = JWK::generate_ed25519().map_err(|e| DIDKitError::SigningFailed {
+        message: e.to_string(),
+    })?;
+    Ok(serde_json::to_string(&jwk)?)
+}
+
+#[uniffi::export]
+pub fn key_to_did(method_pattern: String, jwk: String) -> Result<String, DIDKitError> {
+    let key: JWK = serde_json::from_str(&jwk)?;
+    DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, &method_pattern))
+        .ok_or(DIDKitError::UnableToGenerateDID)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn key_to_verification_method(
+    method_pattern: String,
+    jwk: String,
+) -> Result<String, DIDKitError> {
+    let did = key_to_did(method_pattern, jwk)?;
+    get_verification_method(&did, DID_METHODS.to_resolver())
+        .await
+        .ok_or(DIDKitError::UnableToGetVerificationMethod)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn resolve_did(
+    did: String,
+    input_metadata: Option<String>,
+) -> Result<String, DIDKitError> {
+    let input_metadata: ResolutionInputMetadata =
+        serde_json::from_str(input_metadata.as_deref().unwrap_or("{}"))?;
+    let (res_meta, doc, _) = DID_METHODS
+        .to_resolver()
+        .resolve(&did, &input_metadata)
+        .await;
+    if let Some(message) = res_meta.error {
+        return Err(DIDKitError::ResolutionFailed { message });
+    }
+    let doc = doc.ok_or_else(|| DIDKitError::ResolutionFailed {
+        message: "No document resolved.".to_string(),
+    })?;
+    Ok(serde_json::to_string(&doc)?)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn dereference_did_url(
+    did_url: String,
+    input_metadata: Option<String>,
+) -> Result<String, DIDKitError> {
+    let input_metadata: DereferencingInputMetadata =
+        serde_json::from_str(input_metadata.as_deref().unwrap_or("{}"))?;
+    let deref_result = dereference(DID_METHODS.to_resolver(), &did_url, &input_metadata).await;
+    Ok(serde_json::to_string(&deref_result)?)
+}
+
+fn issue_options(options: &str) -> Result<IssueOptions, DIDKitError> {
+    let options: JWTOrLDPOptions = serde_json::from_str(options)?;
+    IssueOptions::try_from(options).map_err(|e| DIDKitError::InvalidInput {
+        message: e.to_string(),
+    })
+}
+
+/// Issue a credential. The result is the credential with a proof, or a JWT if the `proofFormat`
+/// option is `jwt`.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn issue_credential(
+    credential: String,
+    options: String,
+    jwk: String,
+) -> Result<String, DIDKitError> {
+    let credential: VerifiableCredential = serde_json::from_str(&credential)?;
+    let options = issue_options(&options)?;
+    let key: JWK = serde_json::from_str(&jwk)?;
+    let vc = didkit::issue_credential(credential, &options, &key, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::SigningFailed {
+            message: e.to_string(),
+        })?;
+    match vc {
+        CredentialOrJWT::JWT(jwt) => Ok(jwt),
+        vc => Ok(serde_json::to_string(&vc)?),
+    }
+}
+
+/// Signer implemented by the host application, e.g. using the Android Keystore or the iOS Secure
+/// Enclave, so that the private key does not need to be passed to DIDKit.
+#[uniffi::export(callback_interface)]
+pub trait ExternalSigner: Send + Sync {
+    /// Sign the signing input with the given JWS algorithm (e.g. `EdDSA` or `ES256`), returning
+    /// the raw signature.
+    fn sign(&self, signing_input: Vec<u8>, algorithm: String) -> Result<Vec<u8>, DIDKitError>;
+}
+
+fn external_signer(
+    public_jwk: &str,
+    signer: Box<dyn ExternalSigner>,
+) -> Result<impl Signer, DIDKitError> {
+    let public_jwk: JWK = serde_json::from_str(public_jwk)?;
+    Ok(FnSigner::new(
+        public_jwk,
+        move |signing_input, algorithm| {
+            signer
+                .sign(signing_input.to_vec(), algorithm_name(algorithm))
+                .map_err(|e| e.to_string())
+        },
+    ))
+}
+
+/// Issue a credential, signing with an [`ExternalSigner`] holding the key of the given public
+/// JWK.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn issue_credential_with_signer(
+    credential: String,
+    options: String,
+    public_jwk: String,
+    signer: Box<dyn ExternalSigner>,
+) -> Result<String, DIDKitError> {
+    let credential: VerifiableCredential = serde_json::from_str(&credential)?;
+    let options = issue_options(&options)?;
+    let signer = external_signer(&public_jwk, signer)?;
+    let vc = didkit::issue_credential(credential, &options, &signer, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::SigningFailed {
+            message: e.to_string(),
+        })?;
+    match vc {
+        CredentialOrJWT::JWT(jwt) => Ok(jwt),
+        vc => Ok(serde_json::to_string(&vc)?),
+    }
+}
+
+/// Verify a credential, given as JSON or as a JWT.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn verify_credential(
+    credential: String,
+    options: String,
+) -> Result<VerificationReport, DIDKitError> {
+    let credential = if credential.trim_start().starts_with('{') {
+        CredentialOrJWT::Credential(serde_json::from_str(&credential)?)
+    } else {
+        CredentialOrJWT::JWT(credential)
+    };
+    let options: JWTOrLDPOptions = serde_json::from_str(&options)?;
+    let report = didkit::verify_credential(&credential, &options, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::VerificationFailed {
+            message: e.to_string(),
+        })?;
+    VerificationReport::try_from(report)
+}
+
+async fn issue_presentation_with(
+    presentation: VerifiablePresentation,
+    options: &str,
+    jwk: &str,
+) -> Result<String, DIDKitError> {
+    let options = issue_options(options)?;
+    let key: JWK = serde_json::from_str(jwk)?;
+    let vp = didkit::issue_presentation(presentation, &options, &key, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::SigningFailed {
+            message: e.to_string(),
+        })?;
+    match vp {
+        PresentationOrJWT::JWT(jwt) => Ok(jwt),
+        vp => Ok(serde_json::to_string(&vp)?),
+    }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn issue_presentation(
+    presentation: String,
+    options: String,
+    jwk: String,
+) -> Result<String, DIDKitError> {
+    let presentation: VerifiablePresentation = serde_json::from_str(&presentation)?;
+    issue_presentation_with(presentation, &options, &jwk).await
+}
+
+/// Issue a presentation, signing with an [`ExternalSigner`] holding the key of the given public
+/// JWK.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn issue_presentation_with_signer(
+    presentation: String,
+    options: String,
+    public_jwk: String,
+    signer: Box<dyn ExternalSigner>,
+) -> Result<String, DIDKitError> {
+    let presentation: VerifiablePresentation = serde_json::from_str(&presentation)?;
+    let options = issue_options(&options)?;
+    let signer = external_signer(&public_jwk, signer)?;
+    let vp = didkit::issue_presentation(presentation, &options, &signer, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::SigningFailed {
+            message: e.to_string(),
+        })?;
+    match vp {
+        PresentationOrJWT::JWT(jwt) => Ok(jwt),
+        vp => Ok(serde_json::to_string(&vp)?),
+    }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn did_auth(holder: String, options: String, jwk: String) -> Result<String, DIDKitError> {
+    let presentation = VerifiablePresentation {
+        holder: Some(URI::String(holder)),
+        ..Default::default()
+    };
+    issue_presentation_with(presentation, &options, &jwk).await
+}
+
+/// Verify a presentation, given as JSON or as a JWT.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn verify_presentation(
+    presentation: String,
+    options: String,
+) -> Result<VerificationReport, DIDKitError> {
+    let presentation = if presentation.trim_start().starts_with('{') {
+        PresentationOrJWT::VP(serde_json::from_str(&presentation)?)
+    } else {
+        PresentationOrJWT::JWT(presentation)
+    };
+    let options: JWTOrLDPOptions = serde_json::from_str(&options)?;
+    let report = didkit::verify_presentation(&presentation, &options, DID_METHODS.to_resolver())
+        .await
+        .map_err(|e| DIDKitError::VerificationFailed {
+            message: e.to_string(),
+        })?;
+    VerificationReport::try_from(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_input() {
+        assert!(matches!(
+            key_to_did("key".to_string(), "{".to_string()),
+            Err(DIDKitError::InvalidInput { .. })
+        ));
+    }
+}
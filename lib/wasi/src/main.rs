@@ -0,0 +1,119 @@
+//! DIDKit commands for WASI runtimes.
+//!
+//! A thin command surface over the library, with no async runtime: futures are run to completion
+//! on the calling thread, and HTTP requests (for `did:web`) are made by the host, as described in
+//! [`didkit::wasi`]. The document to operate on is read from standard input, and the result is
+//! written to standard output as JSON.
+//!
+//! ```text
+//! didkit-wasi verify-credential [OPTIONS_JSON] < credential
+//! didkit-wasi verify-presentation [OPTIONS_JSON] < presentation
+//! didkit-wasi resolve DID [INPUT_METADATA_JSON]
+//! didkit-wasi dereference DID_URL [INPUT_METADATA_JSON]
+//! ```
+
+use std::io::{self, Read};
+use std::process::exit;
+
+use futures::executor::block_on;
+
+use didkit::{
+    dereference, CredentialOrJWT, DIDResolver, DereferencingInputMetadata, JWTOrLDPOptions,
+    PresentationOrJWT, ResolutionInputMetadata, VerificationReport, DID_METHODS,
+};
+
+const USAGE: &str =
+    "Usage: didkit-wasi (verify-credential | verify-presentation) [OPTIONS_JSON] < document
+       didkit-wasi (resolve | dereference) DID [INPUT_METADATA_JSON]";
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    exit(2);
+}
+
+fn read_stdin() -> String {
+    let mut input = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut input) {
+        fail(format!("Unable to read standard input: {}", err));
+    }
+    input
+}
+
+fn parse<T: serde::de::DeserializeOwned>(json: Option<&String>, default: T) -> T {
+    match json {
+        Some(json) => serde_json::from_str(json).unwrap_or_else(|err| fail(err)),
+        None => default,
+    }
+}
+
+fn print_report(report: VerificationReport) {
+    println!("{}", serde_json::to_string(&report).unwrap());
+    if !report.errors.is_empty() {
+        exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let resolver = DID_METHODS.to_resolver();
+    match args.first().map(String::as_str) {
+        Some("verify-credential") => {
+            let input = read_stdin();
+            let input = input.trim();
+            let credential = if input.starts_with('{') {
+                CredentialOrJWT::Credential(serde_json::from_str(input).unwrap_or_else(|e| fail(e)))
+            } else {
+                CredentialOrJWT::JWT(input.to_string())
+            };
+            let options = parse(args.get(1), JWTOrLDPOptions::default());
+            let report = block_on(didkit::verify_credential(&credential, &options, resolver))
+                .unwrap_or_else(|e| fail(e));
+            print_report(report);
+        }
+        Some("verify-presentation") => {
+            let input = read_stdin();
+            let input = input.trim();
+            let presentation = if input.starts_with('{') {
+                PresentationOrJWT::VP(serde_json::from_str(input).unwrap_or_else(|e| fail(e)))
+            } else {
+                PresentationOrJWT::JWT(input.to_string())
+            };
+            let options = parse(args.get(1), JWTOrLDPOptions::default_for_vp());
+            let report = block_on(didkit::verify_presentation(
+                &presentation,
+                &options,
+                resolver,
+            ))
+            .unwrap_or_else(|e| fail(e));
+            print_report(report);
+        }
+        Some("resolve") => {
+            let did = args.get(1).unwrap_or_else(|| fail(USAGE));
+            let input_metadata: ResolutionInputMetadata =
+                parse(args.get(2), ResolutionInputMetadata::default());
+            let (res_meta, doc, doc_meta) = block_on(resolver.resolve(did, &input_metadata));
+            let result = serde_json::json!({
+                "didResolutionMetadata": res_meta,
+                "didDocument": doc,
+                "didDocumentMetadata": doc_meta,
+            });
+            println!("{}", result);
+            if res_meta.error.is_some() {
+                exit(1);
+            }
+        }
+        Some("dereference") => {
+            let did_url = args.get(1).unwrap_or_else(|| fail(USAGE));
+            let input_metadata: DereferencingInputMetadata =
+                parse(args.get(2), DereferencingInputMetadata::default());
+            let (deref_meta, content, content_meta) =
+                block_on(dereference(resolver, did_url, &input_metadata));
+            let result = serde_json::json!([deref_meta, content, content_meta]);
+            println!("{}", result);
+            if deref_meta.error.is_some() {
+                exit(1);
+            }
+        }
+        _ => fail(USAGE),
+    }
+}
@@ -1,14 +1,31 @@
 use core::future::Future;
+#[cfg(any(feature = "issue", feature = "credential", feature = "presentation"))]
+use std::convert::TryFrom;
+
+#[cfg(feature = "issue")]
+use async_trait::async_trait;
 
 use js_sys::Promise;
 use serde_json::Value;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::future_to_promise;
+#[cfg(feature = "issue")]
+use wasm_bindgen_futures::JsFuture;
 
+#[cfg(feature = "cacao")]
+use didkit::cacao::{Cacao, CacaoError, SiweMessage};
 use didkit::error::Error;
 #[cfg(doc)]
 use didkit::error::{didkit_error_code, didkit_error_message};
 use didkit::get_verification_method;
+#[cfg(feature = "encrypt")]
+use didkit::jwe;
+use didkit::render::{self, CredentialManifest};
+use didkit::resolution_error;
+#[cfg(feature = "issue")]
+use didkit::signer::{algorithm_name, Signer, SignerError};
+#[cfg(any(feature = "issue", feature = "credential", feature = "presentation"))]
+use didkit::IssueOptions;
 use didkit::LinkedDataProofOptions;
 use didkit::ProofPreparation;
 use didkit::Source;
@@ -16,23 +33,186 @@ use didkit::VerifiableCredential;
 use didkit::VerifiablePresentation;
 use didkit::DID_METHODS;
 use didkit::JWK;
+use didkit::{dereference, DereferencingInputMetadata};
+use didkit::{CredentialOrJWT, PresentationOrJWT, VerificationReport, VerificationResult};
 use didkit::{Delegation, Invocation};
 use didkit::{JWTOrLDPOptions, ProofFormat, URI};
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn map_jsvalue(result: Result<String, Error>) -> Result<String, JsValue> {
-    match result {
-        Ok(string) => Ok(string),
-        Err(err) => Err(err.to_string().into()),
+// TypeScript definitions of the JSON arguments and results passed as strings. These mirror the
+// serde representations of `didkit::JWTOrLDPOptions` and `didkit::VerificationReport`, and
+// should be kept in sync with them.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+export type ProofFormat = "ldp" | "jwt";
+
+export type MissingExpiration = "allow" | "warn" | "error";
+
+export interface ProofOptions {
+  type?: string;
+  verificationMethod?: string;
+  proofPurpose?: string;
+  created?: string;
+  challenge?: string;
+  domain?: string;
+  checks?: string[];
+  proofFormat?: ProofFormat;
+  allowedDidMethods?: string[];
+  clockSkew?: number;
+  verifyAsOf?: string;
+  missingExpiration?: MissingExpiration;
+  requireHolderBinding?: boolean;
+  expectedHolder?: string;
+  cacao?: object;
+  verifyCredentials?: boolean;
+  credentialParallelism?: number;
+  checkCredentialSchema?: boolean;
+  schemaDocuments?: { [id: string]: string };
+  checkCredentialTypes?: boolean;
+  expectedSubject?: string;
+  expectedChallenge?: string;
+  expectedDomain?: string;
+  historicalResolution?: boolean;
+}
+
+export interface VerificationResult {
+  checks: string[];
+  warnings: string[];
+  errors: string[];
+}
+
+export type ErrorCode =
+  | "invalidDocument"
+  | "noApplicableProof"
+  | "invalidSignature"
+  | "proofPurposeMismatch"
+  | "verificationMethodMismatch"
+  | "challengeMismatch"
+  | "domainMismatch"
+  | "resolutionFailed"
+  | "expired"
+  | "invalidJWT"
+  | "disallowedDIDMethod"
+  | "notYetValid"
+  | "missingExpiration"
+  | "holderBindingMismatch"
+  | "untrustedCertificate"
+  | "revoked"
+  | "statusUnavailable"
+  | "contextIntegrity"
+  | "disallowedProof"
+  | "credentialSchema"
+  | "subjectMismatch"
+  | "missingChallenge"
+  | "missingDomain"
+  | "unauthorizedVerificationMethod"
+  | "relatedResourceIntegrity"
+  | "credentialType"
+  | "verificationMethodExpired"
+  | "verificationMethodRevoked"
+  | "statusListMismatch"
+  | "other";
+
+export interface VerificationError {
+  code: ErrorCode;
+  message: string;
+}
+
+export interface VerificationWarning {
+  code: "missingExpiration" | "unknownCredentialType" | "other";
+  message: string;
+}
+
+export interface ProofReport {
+  type?: string;
+  verificationMethod?: string;
+  proofPurpose?: string;
+  created?: string;
+  proofFormat?: "LDP" | "JWT" | "VcJose" | "VcCose";
+  algorithm?: string;
+  checks: string[];
+  warnings: VerificationWarning[];
+  errors: VerificationError[];
+}
+
+export interface VerificationReport {
+  checks: string[];
+  warnings: VerificationWarning[];
+  errors: VerificationError[];
+  proofs: ProofReport[];
+  issuer?: string;
+  holder?: string;
+  timing?: { started: string; finished: string; durationMs: number };
+  policyChecks?: (
+    | "holderBinding"
+    | "certificateChain"
+    | "contextIntegrity"
+    | "relatedResourceIntegrity"
+    | "profile"
+    | "credentialSchema"
+    | "credentialType"
+    | "expectedSubject"
+    | "expectedChallenge"
+    | "expectedDomain"
+  )[];
+  credentials?: VerificationReport[];
+  issuerCertificate?: {
+    subject: string;
+    issuer: string;
+    serialNumber: string;
+    subjectAltNames?: string[];
+    notAfter: string;
+  };
+  relatedResources?: {
+    id: string;
+    status: "verified" | "mismatch" | "unavailable" | "invalid";
+    message?: string;
+  }[];
+}
+
+/** Error thrown by DIDKit functions, or with which their promises are rejected. */
+export interface DIDKitError extends Error {
+  /** Stable numeric error code, as returned by `didkit_error_code` in the C API */
+  code: number;
+  kind: string;
+  details?: object;
+}
+
+export type SignFunction = (
+  signingInput: Uint8Array,
+  algorithm: string
+) => Uint8Array | Promise<Uint8Array>;
+"#;
+
+/// Convert an error to a JavaScript `Error`, with the `code`, `kind` and `details` of
+/// [`didkit::error::ErrorInfo`] as additional properties.
+fn to_js_error(err: Error) -> JsValue {
+    let info = err.info();
+    let js_error = js_sys::Error::new(&info.message);
+    let details = match info.details {
+        Some(details) => js_sys::JSON::parse(&details.to_string()).unwrap_or(JsValue::UNDEFINED),
+        None => JsValue::UNDEFINED,
+    };
+    for (name, value) in [
+        ("code", JsValue::from(info.code)),
+        ("kind", JsValue::from(info.kind)),
+        ("details", details),
+    ] {
+        js_sys::Reflect::set(&js_error, &JsValue::from(name), &value).ok();
     }
+    js_error.into()
+}
+
+fn map_jsvalue(result: Result<String, Error>) -> Result<String, JsValue> {
+    result.map_err(to_js_error)
 }
 
 fn map_async_jsvalue(future: impl Future<Output = Result<String, Error>> + 'static) -> Promise {
     future_to_promise(async {
         match future.await {
             Ok(string) => Ok(string.into()),
-            Err(err) => Err(err.to_string().into()),
+            Err(err) => Err(to_js_error(err)),
         }
     })
 }
@@ -44,7 +224,7 @@ pub fn getVersion() -> String {
 }
 
 async fn resolve_did(did: String, input_metadata: String) -> Result<String, String> {
-    let (res_meta, doc, _) = DID_METHODS
+    let (mut res_meta, doc, _) = DID_METHODS
         .to_resolver()
         .resolve(
             &did,
@@ -52,8 +232,9 @@ async fn resolve_did(did: String, input_metadata: String) -> Result<String, Stri
         )
         .await;
 
-    if let Some(error) = res_meta.error {
-        return Err(error);
+    resolution_error::normalize_resolution_metadata(&mut res_meta);
+    if let Some(ref error) = res_meta.error {
+        return Err(resolution_error::describe(error, &res_meta.property_set));
     }
 
     if let Some(d) = doc {
@@ -74,6 +255,20 @@ pub fn resolveDID(did: String, input_metadata: String) -> Promise {
     })
 }
 
+async fn dereference_did_url(did_url: String, input_metadata: String) -> Result<String, Error> {
+    let input_metadata: DereferencingInputMetadata = serde_json::from_str(&input_metadata)?;
+    let mut deref_result = dereference(DID_METHODS.to_resolver(), &did_url, &input_metadata).await;
+    resolution_error::normalize_dereferencing_metadata(&mut deref_result.0);
+    let result_json = serde_json::to_string(&deref_result)?;
+    Ok(result_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn dereferenceDIDURL(did_url: String, input_metadata: String) -> Promise {
+    map_async_jsvalue(dereference_did_url(did_url, input_metadata))
+}
+
 #[cfg(feature = "generate")]
 fn generate_ed25519_key() -> Result<String, Error> {
     let jwk = JWK::generate_ed25519()?;
@@ -134,27 +329,14 @@ async fn issue_credential(
     proof_options: String,
     key: String,
 ) -> Result<String, Error> {
-    let mut credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let credential = VerifiableCredential::from_json_unsigned(&credential)?;
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let resolver = DID_METHODS.to_resolver();
-    let vc_string = match proof_format {
-        ProofFormat::JWT => {
-            let vc_jwt = credential
-                .generate_jwt(Some(&key), &options.ldp_options, resolver)
-                .await?;
-            vc_jwt
-        }
-        ProofFormat::LDP => {
-            let proof = credential
-                .generate_proof(&key, &options.ldp_options, resolver)
-                .await?;
-            credential.add_proof(proof);
-            let vc_json = serde_json::to_string(&credential)?;
-            vc_json
-        }
-        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    let vc_string = match didkit::issue_credential(credential, &options, &key, resolver).await? {
+        CredentialOrJWT::JWT(jwt) => jwt,
+        vc => serde_json::to_string(&vc)?,
     };
     Ok(vc_string)
 }
@@ -233,6 +415,129 @@ pub fn completeIssueCredential(
     ))
 }
 
+/// Signer calling a JavaScript function, e.g. one using WebCrypto with a non-extractable key.
+/// The function is called with the signing input as a `Uint8Array` and the JWS algorithm name,
+/// and must return the raw signature as a `Uint8Array`, or a promise of one.
+#[cfg(feature = "issue")]
+struct JsSigner {
+    public_jwk: JWK,
+    sign: js_sys::Function,
+}
+
+// WASM is single-threaded, so the JavaScript function is never shared between threads.
+#[cfg(feature = "issue")]
+unsafe impl Send for JsSigner {}
+#[cfg(feature = "issue")]
+unsafe impl Sync for JsSigner {}
+
+#[cfg(feature = "issue")]
+fn js_sign_error(err: JsValue) -> SignerError {
+    SignerError::Sign(err.as_string().unwrap_or_else(|| format!("{:?}", err)))
+}
+
+#[cfg(feature = "issue")]
+#[async_trait(?Send)]
+impl Signer for JsSigner {
+    fn public_jwk(&self) -> JWK {
+        self.public_jwk.to_public()
+    }
+
+    async fn sign(
+        &self,
+        signing_input: &[u8],
+        algorithm: ssi::jwk::Algorithm,
+    ) -> Result<Vec<u8>, SignerError> {
+        let signing_input = js_sys::Uint8Array::from(signing_input);
+        let algorithm = JsValue::from(algorithm_name(algorithm));
+        let result = self
+            .sign
+            .call2(&JsValue::NULL, &signing_input, &algorithm)
+            .map_err(js_sign_error)?;
+        let signature = JsFuture::from(Promise::resolve(&result))
+            .await
+            .map_err(js_sign_error)?;
+        Ok(js_sys::Uint8Array::new(&signature).to_vec())
+    }
+}
+
+#[cfg(feature = "issue")]
+async fn issue_credential_with_signer(
+    credential: String,
+    proof_options: String,
+    public_key: String,
+    sign: js_sys::Function,
+) -> Result<String, Error> {
+    let credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let public_jwk: JWK = serde_json::from_str(&public_key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let options = IssueOptions::try_from(options)?;
+    let signer = JsSigner { public_jwk, sign };
+    let vc =
+        didkit::issue_credential(credential, &options, &signer, DID_METHODS.to_resolver()).await?;
+    let vc_string = match vc {
+        CredentialOrJWT::JWT(jwt) => jwt,
+        vc => serde_json::to_string(&vc)?,
+    };
+    Ok(vc_string)
+}
+
+/// Issue a credential, signing with a JavaScript function instead of a private key.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "issue")]
+pub fn issueCredentialWithSigner(
+    credential: String,
+    proof_options: String,
+    public_key: String,
+    sign: js_sys::Function,
+) -> Promise {
+    map_async_jsvalue(issue_credential_with_signer(
+        credential,
+        proof_options,
+        public_key,
+        sign,
+    ))
+}
+
+#[cfg(feature = "issue")]
+async fn issue_presentation_with_signer(
+    presentation: String,
+    proof_options: String,
+    public_key: String,
+    sign: js_sys::Function,
+) -> Result<String, Error> {
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
+    let public_jwk: JWK = serde_json::from_str(&public_key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let options = IssueOptions::try_from(options)?;
+    let signer = JsSigner { public_jwk, sign };
+    let vp = didkit::issue_presentation(presentation, &options, &signer, DID_METHODS.to_resolver())
+        .await?;
+    let vp_string = match vp {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
+    };
+    Ok(vp_string)
+}
+
+/// Issue a presentation, signing with a JavaScript function instead of a private key.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "issue")]
+pub fn issuePresentationWithSigner(
+    presentation: String,
+    proof_options: String,
+    public_key: String,
+    sign: js_sys::Function,
+) -> Promise {
+    map_async_jsvalue(issue_presentation_with_signer(
+        presentation,
+        proof_options,
+        public_key,
+        sign,
+    ))
+}
+
 #[cfg(any(
     all(feature = "verify", feature = "credential"),
     all(feature = "verify", not(feature = "presentation")),
@@ -242,24 +547,58 @@ pub fn completeIssueCredential(
         not(feature = "verify")
     )
 ))]
-async fn verify_credential(vc_string: String, proof_options: String) -> Result<String, Error> {
+async fn credential_report(
+    vc_string: String,
+    proof_options: String,
+) -> Result<VerificationReport, Error> {
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let proof_format = options.proof_format.clone().unwrap_or_default();
     let resolver = DID_METHODS.to_resolver();
-    let result = match proof_format {
-        ProofFormat::JWT => {
-            VerifiableCredential::verify_jwt(&vc_string, Some(options.ldp_options), resolver).await
-        }
+    let vc = match proof_format {
+        ProofFormat::JWT => CredentialOrJWT::JWT(vc_string),
         ProofFormat::LDP => {
-            let vc = VerifiableCredential::from_json_unsigned(&vc_string)?;
-            vc.verify(Some(options.ldp_options), resolver).await
+            CredentialOrJWT::Credential(VerifiableCredential::from_json_unsigned(&vc_string)?)
         }
         _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
     };
+    let report = didkit::verify_credential(&vc, &options, resolver).await?;
+    Ok(report)
+}
+
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_credential(vc_string: String, proof_options: String) -> Result<String, Error> {
+    let report = credential_report(vc_string, proof_options).await?;
+    let result = VerificationResult::from(report);
     let result_json = serde_json::to_string(&result)?;
     Ok(result_json)
 }
 
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_credential_report(
+    vc_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
+    let report = credential_report(vc_string, proof_options).await?;
+    let report_json = serde_json::to_string(&report)?;
+    Ok(report_json)
+}
+
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(
@@ -275,6 +614,23 @@ pub fn verifyCredential(vc: String, proof_options: String) -> Promise {
     map_async_jsvalue(verify_credential(vc, proof_options))
 }
 
+/// Verify a credential, resolving to a JSON `VerificationReport` with error codes and per-proof
+/// results, instead of the `VerificationResult` of [`verifyCredential`].
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyCredentialReport(vc: String, proof_options: String) -> Promise {
+    map_async_jsvalue(verify_credential_report(vc, proof_options))
+}
+
 #[cfg(any(
     all(feature = "issue", feature = "presentation"),
     all(feature = "issue", not(feature = "credential")),
@@ -289,25 +645,15 @@ async fn issue_presentation(
     proof_options: String,
     key: String,
 ) -> Result<String, Error> {
-    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let resolver = DID_METHODS.to_resolver();
-    let vp_string = match proof_format {
-        ProofFormat::JWT => {
-            presentation
-                .generate_jwt(Some(&key), &options.ldp_options, resolver)
-                .await?
-        }
-        ProofFormat::LDP => {
-            let proof = presentation
-                .generate_proof(&key, &options.ldp_options, resolver)
-                .await?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
-        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    let vp_string = match didkit::issue_presentation(presentation, &options, &key, resolver).await?
+    {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(vp_string)
 }
@@ -336,25 +682,58 @@ pub fn issuePresentation(presentation: String, proof_options: String, key: Strin
         not(feature = "verify")
     )
 ))]
-async fn verify_presentation(vp_string: String, proof_options: String) -> Result<String, Error> {
+async fn presentation_report(
+    vp_string: String,
+    proof_options: String,
+) -> Result<VerificationReport, Error> {
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let proof_format = options.proof_format.clone().unwrap_or_default();
     let resolver = DID_METHODS.to_resolver();
-    let result = match proof_format {
-        ProofFormat::JWT => {
-            VerifiablePresentation::verify_jwt(&vp_string, Some(options.ldp_options), resolver)
-                .await
-        }
+    let vp = match proof_format {
+        ProofFormat::JWT => PresentationOrJWT::JWT(vp_string),
         ProofFormat::LDP => {
-            let vp = VerifiablePresentation::from_json_unsigned(&vp_string)?;
-            vp.verify(Some(options.ldp_options), resolver).await
+            PresentationOrJWT::VP(VerifiablePresentation::from_json_unsigned(&vp_string)?)
         }
         _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
     };
+    let report = didkit::verify_presentation(&vp, &options, resolver).await?;
+    Ok(report)
+}
+
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_presentation(vp_string: String, proof_options: String) -> Result<String, Error> {
+    let report = presentation_report(vp_string, proof_options).await?;
+    let result = VerificationResult::from(report);
     let result_json = serde_json::to_string(&result)?;
     Ok(result_json)
 }
 
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_presentation_report(
+    vp_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
+    let report = presentation_report(vp_string, proof_options).await?;
+    let report_json = serde_json::to_string(&report)?;
+    Ok(report_json)
+}
+
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(
@@ -370,6 +749,22 @@ pub fn verifyPresentation(vp: String, proof_options: String) -> Promise {
     map_async_jsvalue(verify_presentation(vp, proof_options))
 }
 
+/// Verify a presentation, resolving to a JSON `VerificationReport`.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyPresentationReport(vp: String, proof_options: String) -> Promise {
+    map_async_jsvalue(verify_presentation_report(vp, proof_options))
+}
+
 #[cfg(any(
     all(feature = "issue", feature = "presentation"),
     all(feature = "issue", not(feature = "credential")),
@@ -384,22 +779,12 @@ async fn did_auth(holder: String, proof_options: String, key: String) -> Result<
     presentation.holder = Some(ssi::vc::URI::String(holder));
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
-    let proof_format = options.proof_format.unwrap_or_default();
+    let options = IssueOptions::try_from(options)?;
     let resolver = DID_METHODS.to_resolver();
-    let vp_string = match proof_format {
-        ProofFormat::JWT => {
-            presentation
-                .generate_jwt(Some(&key), &options.ldp_options, resolver)
-                .await?
-        }
-        ProofFormat::LDP => {
-            let proof = presentation
-                .generate_proof(&key, &options.ldp_options, resolver)
-                .await?;
-            presentation.add_proof(proof);
-            serde_json::to_string(&presentation)?
-        }
-        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    let vp_string = match didkit::issue_presentation(presentation, &options, &key, resolver).await?
+    {
+        PresentationOrJWT::JWT(jwt) => jwt,
+        vp => serde_json::to_string(&vp)?,
     };
     Ok(vp_string)
 }
@@ -690,3 +1075,143 @@ async fn verify_invocation(invocation: String, delegation: String) -> Result<Str
 pub fn verifyInvocation(invocation: String, delegation: String) -> Promise {
     map_async_jsvalue(verify_invocation(invocation, delegation))
 }
+
+#[cfg(feature = "encrypt")]
+async fn encrypt_credential(document: String, recipient: String) -> Result<String, String> {
+    let document = document.trim();
+    let cty = if document.starts_with('{') {
+        jwe::CTY_LD_JSON
+    } else {
+        jwe::CTY_JWT
+    };
+    jwe::encrypt_to_did(
+        document.as_bytes(),
+        &recipient,
+        Some(cty.to_string()),
+        DID_METHODS.to_resolver(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Encrypt a credential (JSON or JWT) to the key agreement key of a DID, as a compact JWE.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "encrypt")]
+pub fn encryptCredential(document: String, recipient: String) -> Promise {
+    future_to_promise(async {
+        match encrypt_credential(document, recipient).await {
+            Ok(string) => Ok(string.into()),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+#[cfg(feature = "encrypt")]
+fn decrypt_credential(jwe: String, key: String) -> Result<String, String> {
+    let key: JWK = serde_json::from_str(&key).map_err(|e| e.to_string())?;
+    let (_, plaintext) = jwe::decrypt(&jwe, &key).map_err(|e| e.to_string())?;
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "encrypt")]
+pub fn decryptCredential(jwe: String, key: String) -> Result<String, JsValue> {
+    decrypt_credential(jwe, key).map_err(JsValue::from)
+}
+
+#[cfg(feature = "cacao")]
+fn sign_cacao(message: String, key: String) -> Result<String, String> {
+    let message: SiweMessage = message
+        .parse()
+        .map_err(|e: didkit::cacao::CacaoError| e.to_string())?;
+    let key: JWK = serde_json::from_str(&key).map_err(|e| e.to_string())?;
+    let cacao = Cacao::sign(&message, &key).map_err(|e| e.to_string())?;
+    serde_json::to_string(&cacao).map_err(|e| e.to_string())
+}
+
+/// Sign a Sign-In with Ethereum message with a secp256k1 key, as a CACAO.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "cacao")]
+pub fn signCacao(message: String, key: String) -> Result<String, JsValue> {
+    sign_cacao(message, key).map_err(JsValue::from)
+}
+
+#[cfg(feature = "cacao")]
+fn verify_cacao(cacao: String) -> Result<String, String> {
+    let cacao: Cacao = serde_json::from_str(&cacao).map_err(|e| e.to_string())?;
+    cacao
+        .verify(chrono::Utc::now())
+        .map_err(|e| e.to_string())?;
+    Ok(cacao.p.iss)
+}
+
+/// Verify a CACAO, returning its issuer (a `did:pkh`).
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "cacao")]
+pub fn verifyCacao(cacao: String) -> Result<String, JsValue> {
+    verify_cacao(cacao).map_err(JsValue::from)
+}
+
+/// Resource to list in a Sign-In with Ethereum message to bind its CACAO to a presentation.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "cacao")]
+pub fn cacaoPresentationResource(presentation: String) -> Result<String, JsValue> {
+    let presentation = serde_json::from_str(&presentation).map_err(|e| e.to_string())?;
+    Ok(didkit::cacao::presentation_resource(&presentation))
+}
+
+fn credential_card(
+    credential: String,
+    manifest: String,
+    descriptor_id: Option<String>,
+) -> Result<String, Error> {
+    let credential = render::parse_credential(&credential)?;
+    let manifest: CredentialManifest = serde_json::from_str(&manifest)?;
+    let card = render::card(&manifest, &credential, descriptor_id.as_deref())?;
+    Ok(serde_json::to_string(&card)?)
+}
+
+/// Resolve the display of a credential, as JSON or a JWT, by an output descriptor of a Credential
+/// Manifest, selected by id or else by the credential's types and schemas, as a card with the
+/// entity styles of the descriptor and of the manifest's issuer.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn credentialCard(
+    credential: String,
+    manifest: String,
+    descriptor_id: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(credential_card(credential, manifest, descriptor_id))
+}
+
+fn validate_credential_display(
+    credential: String,
+    manifest: String,
+    descriptor_id: Option<String>,
+) -> Result<String, Error> {
+    let credential = render::parse_credential(&credential)?;
+    let manifest: CredentialManifest = serde_json::from_str(&manifest)?;
+    let problems = render::validate_display(&manifest, &credential, descriptor_id.as_deref())?;
+    Ok(serde_json::to_string(&problems)?)
+}
+
+/// Check the display mappings and styles of an output descriptor of a Credential Manifest against
+/// a credential, returning a JSON array of the problems found.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn validateCredentialDisplay(
+    credential: String,
+    manifest: String,
+    descriptor_id: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(validate_credential_display(
+        credential,
+        manifest,
+        descriptor_id,
+    ))
+}